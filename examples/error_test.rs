@@ -5,7 +5,7 @@
 //! cargo run --example error_test -- <login> <password> <server>
 //! ```
 
-use mt4_client::{LoginCredentials, Mt4Client, Mt4Event};
+use mt4_client::{LoginCredentials, Mt4Client, Mt4Event, Ticket};
 use std::env;
 use std::time::Duration;
 use tokio::time::timeout;
@@ -30,7 +30,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let credentials = LoginCredentials {
         login: args[1].clone(),
-        password: args[2].clone(),
+        password: args[2].as_str().into(),
         server: args[3].clone(),
     };
 
@@ -102,7 +102,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\n==================================================");
     println!("[TEST 5] 平仓无效订单: ticket=999999999");
     println!("==================================================");
-    client.close_order(999999999, "EURUSD", 0.01).await?;
+    client.close_order(Ticket(999999999), "EURUSD", 0.01).await?;
     wait_for_result(&mut client).await;
 
     tokio::time::sleep(Duration::from_secs(1)).await;
@@ -149,7 +149,7 @@ async fn wait_for_result(client: &mut Mt4Client) {
         }
         None
     }).await {
-        Ok(Some(_)) => println!(""),
+        Ok(Some(_)) => println!(),
         Ok(None) => println!("[TIMEOUT] 等待结果超时\n"),
         Err(_) => println!("[TIMEOUT] 等待超时\n"),
     }
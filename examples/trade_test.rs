@@ -35,7 +35,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let credentials = LoginCredentials {
         login: args[1].clone(),
-        password: args[2].clone(),
+        password: args[2].as_str().into(),
         server: args[3].clone(),
     };
 
@@ -108,9 +108,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // let now = std::time::SystemTime::now()
     //     .duration_since(std::time::UNIX_EPOCH)
     //     .unwrap()
-    //     .as_secs() as i32;
+    //     .as_secs() as i64;
     // let seven_days_ago = now - 7 * 24 * 3600;
-    // client.request_order_history_range(seven_days_ago, now).await?;
+    // let history = client.request_order_history_range(seven_days_ago, now).await?;
+    // println!("    {} 个历史订单", history.len());
 
     println!("\n[4] 跳过历史订单获取，只监听实时更新...");
     tokio::time::sleep(Duration::from_secs(1)).await;
@@ -123,16 +124,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\n[6] 持续监听事件...");
     println!("    按 Ctrl+C 退出\n");
 
-    let mut last_ping = std::time::Instant::now();
-
+    // 心跳由客户端自动发送 (默认空闲 30 秒)
     loop {
-        // 每30秒发送心跳
-        if last_ping.elapsed() >= Duration::from_secs(30) {
-            println!("[PING] 发送心跳...");
-            client.ping().await?;
-            last_ping = std::time::Instant::now();
-        }
-
         match timeout(Duration::from_secs(5), client.next_event()).await {
             Ok(Some(event)) => {
                 match event {
@@ -206,9 +199,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         // 显示关联订单 (Close By) - CSV格式
                         if let Some(ref related) = update.related_order {
                             println!(
-                                "{},{},{},{},{:?},{:.2},{:.5},{:.5},{:.5},{:.5},{:.2},{:.2},{:.2},{},{},{}",
+                                "{},对冲单,{},{},{:?},{:.2},{:.5},{:.5},{:.5},{:.5},{:.2},{:.2},{:.2},{},{},{}",
                                 chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
-                                "对冲单",
                                 related.ticket,
                                 related.symbol,
                                 related.order_type,
@@ -257,8 +249,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("[ACCOUNT] ----------------------------------------");
                         println!("[ACCOUNT] 余额: {:.2}", account.balance);
                         println!("[ACCOUNT] 净值: {:.2}", account.equity);
-                        println!("[ACCOUNT] 已用保证金: {:.2}", account.margin);
-                        println!("[ACCOUNT] 可用保证金: {:.2}", account.free_margin);
+                        if let Some(margin) = account.margin {
+                            println!("[ACCOUNT] 已用保证金: {:.2}", margin);
+                        }
+                        if let Some(free_margin) = account.free_margin {
+                            println!("[ACCOUNT] 可用保证金: {:.2}", free_margin);
+                        }
                         println!("[ACCOUNT] ----------------------------------------");
                         if !account.currency.is_empty() {
                             println!("[ACCOUNT] 货币: {}", account.currency);
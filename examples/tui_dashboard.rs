@@ -0,0 +1,237 @@
+//! MT4 终端监控面板
+//!
+//! 显示账户净值、当前持仓 (持仓缓存)、报价 (定时查询) 和事件日志，按 `q` 或 `Esc` 退出。
+//!
+//! 用法:
+//! ```bash
+//! cargo run --example tui_dashboard --features tui -- <login> <password> <server> [品种...]
+//!
+//! # 示例
+//! cargo run --example tui_dashboard --features tui -- 31313724 password ICMarketsSC-Demo03 EURUSD XAUUSD
+//! ```
+
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind};
+use futures_util::StreamExt;
+use mt4_client::{AccountInfo, LoginCredentials, Mt4Client, Mt4Event, Mt4Handle, Order};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table};
+use ratatui::Frame;
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 报价查询间隔
+const QUOTE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 持仓/账户刷新和重绘间隔
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 日志面板保留的行数
+const LOG_LINES: usize = 200;
+
+/// 未指定品种时查询的报价
+const DEFAULT_SYMBOLS: [&str; 3] = ["EURUSD", "GBPUSD", "XAUUSD"];
+
+/// 面板状态
+#[derive(Default)]
+struct Dashboard {
+    account: Option<AccountInfo>,
+    positions: Vec<Order>,
+    /// 品种 -> (bid, ask)
+    quotes: BTreeMap<String, (f64, f64)>,
+    log: VecDeque<String>,
+}
+
+impl Dashboard {
+    fn push_log(&mut self, message: String) {
+        if self.log.len() == LOG_LINES {
+            self.log.pop_front();
+        }
+        self.log.push_back(format!("{} {}", chrono::Local::now().format("%H:%M:%S"), message));
+    }
+
+    fn on_event(&mut self, event: &Mt4Event) {
+        let message = match event {
+            Mt4Event::AccountInfo(account) => {
+                self.account = Some(account.clone());
+                return;
+            }
+            Mt4Event::Pong => return,
+            Mt4Event::OrderUpdate(update) => describe_order("OrderUpdate", &update.order),
+            Mt4Event::OrderUpdates(updates) => {
+                for update in updates {
+                    self.push_log(describe_order("OrderUpdate", &update.order));
+                }
+                return;
+            }
+            Mt4Event::PositionsSnapshot(orders) => format!("PositionsSnapshot {} orders", orders.len()),
+            Mt4Event::TradeSuccess { request_id, status } => format!("TradeSuccess #{} status {}", request_id, status),
+            Mt4Event::TradeFailed { code, message } => format!("TradeFailed {} {}", code, message),
+            Mt4Event::Error(message) => format!("Error {}", message),
+            other => other.kind().to_string(),
+        };
+        self.push_log(message);
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, log] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(6), Constraint::Length(12)]).areas(frame.area());
+        let [positions, quotes] = Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)]).areas(body);
+        self.draw_account(frame, header);
+        self.draw_positions(frame, positions);
+        self.draw_quotes(frame, quotes);
+        self.draw_log(frame, log);
+    }
+
+    fn draw_account(&self, frame: &mut Frame, area: Rect) {
+        let text = match &self.account {
+            Some(a) => format!(
+                "#{} {}  balance {:.2}  equity {:.2}  {}  1:{}",
+                a.login, a.name, a.balance, a.equity, a.currency, a.leverage
+            ),
+            None => "等待账户信息...".to_string(),
+        };
+        frame.render_widget(Paragraph::new(text).block(Block::bordered().title(" 账户 ")), area);
+    }
+
+    fn draw_positions(&self, frame: &mut Frame, area: Rect) {
+        let header = Row::new(["ticket", "symbol", "type", "volume", "open", "sl", "tp", "profit"])
+            .style(Style::new().add_modifier(Modifier::BOLD));
+        let rows = self.positions.iter().map(|order| {
+            let profit = order.profit + order.commission + order.swap;
+            let color = if profit >= 0.0 { Color::Green } else { Color::Red };
+            Row::new([
+                order.ticket.to_string(),
+                order.symbol.to_string(),
+                format!("{:?}", order.order_type),
+                format!("{:.2}", order.volume),
+                order.open_price.to_string(),
+                order.sl.to_string(),
+                order.tp.to_string(),
+                format!("{:.2}", profit),
+            ])
+            .style(Style::new().fg(color))
+        });
+        let widths = [
+            Constraint::Length(11),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(7),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ];
+        let title = format!(" 持仓 ({}) ", self.positions.len());
+        frame.render_widget(Table::new(rows, widths).header(header).block(Block::bordered().title(title)), area);
+    }
+
+    fn draw_quotes(&self, frame: &mut Frame, area: Rect) {
+        let header = Row::new(["symbol", "bid", "ask"]).style(Style::new().add_modifier(Modifier::BOLD));
+        let rows = self
+            .quotes
+            .iter()
+            .map(|(symbol, (bid, ask))| Row::new([symbol.clone(), bid.to_string(), ask.to_string()]));
+        let widths = [Constraint::Length(10), Constraint::Length(10), Constraint::Length(10)];
+        frame.render_widget(Table::new(rows, widths).header(header).block(Block::bordered().title(" 报价 ")), area);
+    }
+
+    fn draw_log(&self, frame: &mut Frame, area: Rect) {
+        let visible = area.height.saturating_sub(2) as usize;
+        let items: Vec<ListItem> =
+            self.log.iter().skip(self.log.len().saturating_sub(visible)).map(|line| ListItem::new(Line::raw(line))).collect();
+        frame.render_widget(List::new(items).block(Block::bordered().title(" 事件 (q 退出) ")), area);
+    }
+}
+
+fn describe_order(kind: &str, order: &Order) -> String {
+    format!("{} #{} {} {:?} {:.2} @ {}", kind, order.ticket, order.symbol, order.order_type, order.volume, order.open_price)
+}
+
+/// 定时查询报价，面板退出后结束
+fn spawn_quotes(handle: Mt4Handle, symbols: Vec<String>, tx: mpsc::Sender<(String, mt4_client::Result<(f64, f64)>)>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(QUOTE_INTERVAL);
+        loop {
+            interval.tick().await;
+            for symbol in &symbols {
+                let query = symbol.clone();
+                let price = handle
+                    .call(move |client| Box::pin(async move { client.request_price(&query).await }))
+                    .await
+                    .and_then(|result| result);
+                if tx.send((symbol.clone(), price)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 4 {
+        eprintln!("用法: {} <login> <password> <server> [品种...]", args[0]);
+        std::process::exit(1);
+    }
+    let credentials = LoginCredentials { login: args[1].clone(), password: args[2].as_str().into(), server: args[3].clone() };
+    let symbols: Vec<String> = if args.len() > 4 {
+        args[4..].to_vec()
+    } else {
+        DEFAULT_SYMBOLS.iter().map(|s| s.to_string()).collect()
+    };
+
+    // 进入面板前完成连接，认证失败直接在终端输出
+    let handle = Mt4Client::builder().auth_timeout(Duration::from_secs(15)).build().spawn();
+    let mut events = handle.subscribe();
+    handle.connect(&credentials).await?;
+
+    let (quote_tx, mut quote_rx) = mpsc::channel(64);
+    spawn_quotes(handle.clone(), symbols, quote_tx);
+
+    let mut dashboard = Dashboard { account: handle.account_info().await?, ..Default::default() };
+    dashboard.push_log(format!("已连接 {} @ {}", credentials.login, credentials.server));
+
+    let mut terminal = ratatui::init();
+    let mut keys = EventStream::new();
+    let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+    let result: mt4_client::Result<()> = loop {
+        tokio::select! {
+            key = keys.next() => match key {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press
+                    && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) => break Ok(()),
+                Some(Ok(_)) => {}
+                _ => break Ok(()),
+            },
+            event = events.recv() => match event {
+                Some(event) => dashboard.on_event(&event),
+                None => break Ok(()),
+            },
+            Some((symbol, price)) = quote_rx.recv() => match price {
+                Ok(price) => {
+                    dashboard.quotes.insert(symbol, price);
+                }
+                Err(e) => dashboard.push_log(format!("{} 报价失败: {}", symbol, e)),
+            },
+            _ = refresh.tick() => {
+                match handle.positions().await {
+                    Ok(positions) => dashboard.positions = positions,
+                    Err(e) => break Err(e),
+                }
+                if let Err(e) = terminal.draw(|frame| dashboard.draw(frame)) {
+                    ratatui::restore();
+                    return Err(e.into());
+                }
+            }
+        }
+    };
+    ratatui::restore();
+
+    let _ = handle.disconnect().await;
+    result?;
+    Ok(())
+}
@@ -1,12 +1,29 @@
-//! HTTP API 模块 - 获取认证 token
+//! HTTP API 模块 - 获取认证 token，探测网关
 
 use crate::error::{Mt4Error, Result};
-use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::proxy::ProxyConfig;
+use secrecy::SecretString;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::ops::RangeInclusive;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::net::TcpStream;
 
-/// MT4 Web API 基础 URL
-const BASE_URL: &str = "https://metatraderweb.app";
+/// MT4 Web API 默认基础 URL
+pub const DEFAULT_BASE_URL: &str = "https://metatraderweb.app";
+
+/// 网关编号范围
+pub const GATEWAYS: RangeInclusive<i32> = 1..=8;
+
+/// 单个网关探测的超时 (token 请求 + TCP 连接 + WebSocket 握手)
+pub const GATEWAY_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Token 响应
+///
+/// 会话密钥和 token 以 `SecretString` 保存，释放时清零，`Debug` 输出中显示为 `[REDACTED]`
 #[derive(Debug, Clone, Deserialize)]
 pub struct TokenResponse {
     /// 信号服务器地址
@@ -20,9 +37,11 @@ pub struct TokenResponse {
     /// Ping 值
     pub ping: Option<i32>,
     /// 会话密钥 (64位十六进制)
-    pub key: String,
+    #[serde(deserialize_with = "deserialize_secret")]
+    pub key: SecretString,
     /// 认证 token
-    pub token: String,
+    #[serde(deserialize_with = "deserialize_secret")]
+    pub token: SecretString,
     /// 协议版本
     pub version: Option<i32>,
     /// 是否启用
@@ -35,6 +54,62 @@ pub struct TokenResponse {
     pub error: Option<String>,
 }
 
+impl TokenResponse {
+    /// 信号服务器的 WebSocket 地址 (默认 wss，省略 443 端口)
+    pub fn ws_url(&self) -> String {
+        let protocol = if self.ssl.unwrap_or(true) { "wss" } else { "ws" };
+        let signal_server = self.signal_server.strip_suffix(":443").unwrap_or(&self.signal_server);
+        format!("{}://{}/", protocol, signal_server)
+    }
+}
+
+fn deserialize_secret<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<SecretString, D::Error> {
+    String::deserialize(deserializer).map(SecretString::from)
+}
+
+/// 网关探测结果
+///
+/// 各阶段耗时依次测量，某一阶段失败时后续阶段为 None，失败原因见 `error`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GatewayProbe {
+    /// 网关编号
+    pub gateway: i32,
+    /// token 响应中的信号服务器
+    pub signal_server: Option<String>,
+    /// token 请求耗时
+    pub token_latency: Option<Duration>,
+    /// 到信号服务器的 TCP 连接耗时 (经代理时为隧道建立耗时)
+    pub tcp_latency: Option<Duration>,
+    /// TLS + WebSocket 握手耗时
+    pub handshake_latency: Option<Duration>,
+    /// 失败原因
+    pub error: Option<String>,
+}
+
+impl GatewayProbe {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn new(gateway: i32) -> Self {
+        Self {
+            gateway,
+            signal_server: None,
+            token_latency: None,
+            tcp_latency: None,
+            handshake_latency: None,
+            error: None,
+        }
+    }
+
+    /// 所有阶段均成功
+    pub fn is_reachable(&self) -> bool {
+        self.error.is_none() && self.handshake_latency.is_some()
+    }
+
+    /// 各阶段总耗时 (不可达时为 None)
+    pub fn total_latency(&self) -> Option<Duration> {
+        Some(self.token_latency? + self.tcp_latency? + self.handshake_latency?)
+    }
+}
+
 /// Token 请求参数
 #[derive(Debug, Serialize)]
 struct TokenRequest {
@@ -47,15 +122,15 @@ struct TokenRequest {
 pub struct Mt4Api {
     client: reqwest::Client,
     base_url: String,
+    /// 探测网关时建立 TCP 连接使用的代理
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy: Option<ProxyConfig>,
 }
 
 impl Mt4Api {
     /// 创建新的 API 客户端
     pub fn new() -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            base_url: BASE_URL.to_string(),
-        }
+        Self::with_base_url(DEFAULT_BASE_URL)
     }
 
     /// 使用自定义基础 URL 创建 API 客户端
@@ -63,6 +138,8 @@ impl Mt4Api {
         Self {
             client: reqwest::Client::new(),
             base_url: base_url.to_string(),
+            #[cfg(not(target_arch = "wasm32"))]
+            proxy: None,
         }
     }
 
@@ -81,11 +158,11 @@ impl Mt4Api {
     pub async fn get_token(&self, login: &str, server: &str, gwt: i32) -> Result<TokenResponse> {
         let url = format!("{}/trade/json", self.base_url);
 
-        let params = [
-            ("login", login),
-            ("trade_server", server),
-            ("gwt", &gwt.to_string()),
-        ];
+        let params = TokenRequest {
+            login: login.to_string(),
+            trade_server: server.to_string(),
+            gwt,
+        };
 
         tracing::debug!("Requesting token for login: {}, server: {}", login, server);
 
@@ -113,7 +190,7 @@ impl Mt4Api {
         }
 
         if !token_response.enabled {
-            return Err(Mt4Error::Server("Web Terminal not supported".to_string()));
+            return Err(Mt4Error::WebTerminalDisabled);
         }
 
         tracing::info!(
@@ -143,6 +220,77 @@ impl Mt4Api {
     }
 }
 
+/// 代理和网关探测依赖 tokio 的 TCP 连接，浏览器中不可用
+#[cfg(not(target_arch = "wasm32"))]
+impl Mt4Api {
+    /// 使用自定义基础 URL 和代理创建 API 客户端
+    ///
+    /// 未指定代理时 reqwest 本身会读取 `HTTPS_PROXY` / `ALL_PROXY` 等环境变量
+    pub fn with_proxy(base_url: &str, proxy: &ProxyConfig) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().proxy(proxy.to_reqwest()?).build()?,
+            base_url: base_url.to_string(),
+            proxy: Some(proxy.clone()),
+        })
+    }
+
+    /// 并发探测所有网关 (1-8) 的可达性和延迟，不进行登录
+    ///
+    /// 每个网关依次测量 token 请求、到信号服务器的 TCP 连接和 TLS + WebSocket 握手耗时，
+    /// 握手完成后立即关闭连接。可脱离交易会话单独用于经纪商监控。
+    pub async fn probe_gateways(&self, login: &str, server: &str) -> Vec<GatewayProbe> {
+        futures_util::future::join_all(GATEWAYS.map(|gateway| self.probe_gateway(login, server, gateway))).await
+    }
+
+    /// 探测单个网关
+    pub async fn probe_gateway(&self, login: &str, server: &str, gateway: i32) -> GatewayProbe {
+        let mut probe = GatewayProbe::new(gateway);
+        let result = tokio::time::timeout(GATEWAY_PROBE_TIMEOUT, self.run_probe(login, server, &mut probe)).await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => probe.error = Some(e.to_string()),
+            Err(_) => probe.error = Some(Mt4Error::Timeout.to_string()),
+        }
+        tracing::debug!("Gateway {} probe: {:?}", gateway, probe);
+        probe
+    }
+
+    async fn run_probe(&self, login: &str, server: &str, probe: &mut GatewayProbe) -> Result<()> {
+        let started = Instant::now();
+        let token = self.get_token(login, server, probe.gateway).await?;
+        probe.token_latency = Some(started.elapsed());
+        probe.signal_server = Some(token.signal_server.clone());
+
+        let ws_url = token.ws_url();
+        let (host, port) = ws_host_port(&ws_url)?;
+        let started = Instant::now();
+        let stream = match &self.proxy {
+            Some(proxy) => proxy.connect(&host, port).await?,
+            None => TcpStream::connect((host.as_str(), port))
+                .await
+                .map_err(|e| Mt4Error::Connection(format!("连接 {}:{} 失败: {}", host, port, e)))?,
+        };
+        probe.tcp_latency = Some(started.elapsed());
+
+        let started = Instant::now();
+        let mut ws = crate::tls::TlsConfig::default().connect(&ws_url, Some(stream)).await?;
+        probe.handshake_latency = Some(started.elapsed());
+        let _ = ws.close(None).await;
+        Ok(())
+    }
+}
+
+/// 从 WebSocket 地址中取出主机和端口
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn ws_host_port(ws_url: &str) -> Result<(String, u16)> {
+    let url = url::Url::parse(ws_url).map_err(|e| Mt4Error::Connection(format!("WebSocket 地址 {} 无效: {}", ws_url, e)))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| Mt4Error::Connection(format!("WebSocket 地址 {} 缺少主机", ws_url)))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    Ok((host.to_string(), port))
+}
+
 impl Default for Mt4Api {
     fn default() -> Self {
         Self::new()
@@ -152,6 +300,7 @@ impl Default for Mt4Api {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use secrecy::ExposeSecret;
 
     #[tokio::test]
     #[ignore] // 需要网络连接
@@ -160,7 +309,80 @@ mod tests {
         let result = api.get_token("31313724", "ICMarketsSC-Demo03", 4).await;
         assert!(result.is_ok());
         let token = result.unwrap();
-        assert!(!token.token.is_empty());
-        assert!(!token.key.is_empty());
+        assert!(!token.token.expose_secret().is_empty());
+        assert!(!token.key.expose_secret().is_empty());
+    }
+
+    #[test]
+    fn test_token_response_redacts_secrets() {
+        let json = r#"{"signal_server":"s:443","trade_server":"Demo","login":"1",
+            "key":"a1b2c3d4","token":"tok-secret","enabled":true}"#;
+        let token: TokenResponse = serde_json::from_str(json).unwrap();
+        assert_eq!((token.key.expose_secret(), token.token.expose_secret()), ("a1b2c3d4", "tok-secret"));
+        let debug = format!("{:?}", token);
+        assert!(!debug.contains("a1b2c3d4") && !debug.contains("tok-secret"), "{}", debug);
+        assert_eq!(token.ws_url(), "wss://s/");
+    }
+
+    #[tokio::test]
+    async fn test_probe_gateways() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // 信号服务器: 接受 WebSocket 握手
+        let ws_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_port = ws_listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = ws_listener.accept().await {
+                tokio::spawn(async move {
+                    if let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await {
+                        while let Some(Ok(_)) = futures_util::StreamExt::next(&mut ws).await {}
+                    }
+                });
+            }
+        });
+
+        // Web API: 只有网关 2 可用
+        let http_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", http_listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = http_listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !String::from_utf8_lossy(&request).contains("gwt=") {
+                        match stream.read(&mut buf).await {
+                            Ok(n) if n > 0 => request.extend_from_slice(&buf[..n]),
+                            _ => return,
+                        }
+                    }
+                    let body = if String::from_utf8_lossy(&request).contains("gwt=2") {
+                        format!(
+                            r#"{{"signal_server":"127.0.0.1:{}","trade_server":"Demo","login":"1","key":"00","token":"t","enabled":true,"ssl":false}}"#,
+                            ws_port
+                        )
+                    } else {
+                        r#"{"signal_server":"","trade_server":"Demo","login":"1","key":"","token":"","enabled":true,"error":"gateway unavailable"}"#.to_string()
+                    };
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        let probes = Mt4Api::with_base_url(&base_url).probe_gateways("1", "Demo").await;
+        assert_eq!(probes.iter().map(|p| p.gateway).collect::<Vec<_>>(), GATEWAYS.collect::<Vec<_>>());
+        let reachable: Vec<&GatewayProbe> = probes.iter().filter(|p| p.is_reachable()).collect();
+        assert_eq!(reachable.len(), 1);
+        assert_eq!(reachable[0].gateway, 2);
+        assert_eq!(reachable[0].signal_server.as_deref(), Some(format!("127.0.0.1:{}", ws_port).as_str()));
+        assert!(reachable[0].total_latency().is_some());
+
+        let failed = &probes[0];
+        assert!(failed.error.as_deref().unwrap().contains("gateway unavailable"));
+        assert!(failed.token_latency.is_none() && failed.total_latency().is_none());
     }
 }
@@ -0,0 +1,197 @@
+//! 大区间历史数据回补
+//!
+//! 批量回补K线或逐笔报价时连续请求会触发服务器限流。`Backfill` 描述一次回补，由
+//! `Mt4Client::backfill_ticks()` (需要 `experimental-ticks` 特性) / `Mt4Client::backfill_candles()`
+//! (需要 `experimental-chart` 特性) 执行:
+//!
+//! - 区间按 `chunk_secs` 切分 (K线默认每块 `DEFAULT_PAGE_BARS` 根，报价默认每块 1 小时)
+//! - 相邻两次请求至少间隔 `request_interval`
+//! - 失败的块按 `retry_delay` 指数退避重试，最多 `max_retries` 次 (参数错误不重试)
+//! - 每完成一块、每次重试都发出 `Mt4Event::BackfillProgress`
+//!
+//! ```
+//! use mt4_client::Backfill;
+//!
+//! // 一天的数据，每分钟最多 30 次请求，每块 1 小时
+//! let backfill = Backfill::new("EURUSD", 1_600_000_000, 1_600_086_400).with_requests_per_minute(30);
+//! assert_eq!(backfill.chunks(3600).len(), 24);
+//! ```
+//!
+//! 逐笔报价使用 QuoteHistory (Command 27)，数据布局为推断 (因此需要开启 `experimental-ticks` 特性): 请求与 ChartRequest 相同但没有周期字段
+//! (0-11 symbol，12-15 from，16-19 to)，响应为 32 字节报价结构数组 (与 Command 8 相同)。
+
+#[cfg(feature = "experimental-chart")]
+use crate::protocol::Timeframe;
+use serde::Serialize;
+use std::time::Duration;
+
+/// 逐笔报价默认每块时间跨度 (秒，1 小时)
+#[cfg(feature = "experimental-ticks")]
+pub const DEFAULT_TICK_CHUNK_SECS: i64 = 3600;
+
+/// 单次逐笔报价请求的超时时间 (秒)
+#[cfg(feature = "experimental-ticks")]
+pub const TICK_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// 默认请求间隔
+pub const DEFAULT_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 默认每块最多重试次数
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// 默认首次重试等待时间 (之后每次翻倍)
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// 历史数据回补参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backfill {
+    /// 品种
+    pub symbol: String,
+    /// 开始时间 (服务器时间，秒)
+    pub from: i64,
+    /// 结束时间 (服务器时间，秒)
+    pub to: i64,
+    /// 每块时间跨度 (秒，None 表示按数据类型的默认值)
+    pub chunk_secs: Option<i64>,
+    /// 相邻两次请求的最小间隔
+    pub request_interval: Duration,
+    /// 每块最多重试次数
+    pub max_retries: u32,
+    /// 首次重试等待时间
+    pub retry_delay: Duration,
+}
+
+impl Backfill {
+    /// 创建回补参数
+    pub fn new(symbol: &str, from: i64, to: i64) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            from,
+            to,
+            chunk_secs: None,
+            request_interval: DEFAULT_REQUEST_INTERVAL,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+
+    /// 设置每块时间跨度 (秒)
+    pub fn with_chunk_secs(mut self, chunk_secs: i64) -> Self {
+        self.chunk_secs = Some(chunk_secs.max(1));
+        self
+    }
+
+    /// 设置相邻两次请求的最小间隔
+    pub fn with_request_interval(mut self, interval: Duration) -> Self {
+        self.request_interval = interval;
+        self
+    }
+
+    /// 每分钟最多 `requests` 次请求
+    pub fn with_requests_per_minute(self, requests: u32) -> Self {
+        self.with_request_interval(Duration::from_secs(60) / requests.max(1))
+    }
+
+    /// 设置每块最多重试次数
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// 设置首次重试等待时间
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// 下载该周期K线时的每块时间跨度
+    #[cfg(feature = "experimental-chart")]
+    pub fn candle_chunk_secs(&self, timeframe: Timeframe) -> i64 {
+        self.chunk_secs.unwrap_or(timeframe.seconds() * crate::chart::DEFAULT_PAGE_BARS as i64)
+    }
+
+    /// 下载逐笔报价时的每块时间跨度
+    #[cfg(feature = "experimental-ticks")]
+    pub fn tick_chunk_secs(&self) -> i64 {
+        self.chunk_secs.unwrap_or(DEFAULT_TICK_CHUNK_SECS)
+    }
+
+    /// 按时间切分的块 (首尾相接，不重叠)
+    pub fn chunks(&self, chunk_secs: i64) -> Vec<(i64, i64)> {
+        let mut chunks = Vec::new();
+        let mut from = self.from;
+        while from < self.to {
+            let to = (from + chunk_secs.max(1)).min(self.to);
+            chunks.push((from, to));
+            from = to;
+        }
+        chunks
+    }
+
+    /// 第 `attempt` 次重试前的等待时间 (从 1 开始，每次翻倍)
+    pub fn retry_backoff(&self, attempt: u32) -> Duration {
+        self.retry_delay.saturating_mul(1 << attempt.saturating_sub(1).min(16))
+    }
+}
+
+/// 回补进度
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackfillProgress {
+    /// 品种
+    pub symbol: String,
+    /// 已完成块数
+    pub chunks_done: usize,
+    /// 总块数
+    pub chunks_total: usize,
+    /// 已获取的K线或报价数量
+    pub fetched: usize,
+    /// 已覆盖到的时间 (服务器时间，秒)
+    pub covered_to: i64,
+    /// 累计重试次数
+    pub retries: u32,
+    /// 本次请求失败的原因 (将重试；成功完成一块时为 None)
+    pub error: Option<String>,
+}
+
+/// 编码逐笔报价请求 (Command 27，20 字节)
+#[cfg(feature = "experimental-ticks")]
+pub(crate) fn tick_request_bytes(symbol: &str, from: i64, to: i64) -> Vec<u8> {
+    let mut data = vec![0u8; 20];
+    let len = symbol.len().min(12);
+    data[..len].copy_from_slice(&symbol.as_bytes()[..len]);
+    data[12..16].copy_from_slice(&(from as i32).to_le_bytes());
+    data[16..20].copy_from_slice(&(to as i32).to_le_bytes());
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_and_backoff() {
+        let backfill = Backfill::new("EURUSD", 0, 25 * 60).with_retry_delay(Duration::from_millis(100));
+        #[cfg(feature = "experimental-chart")]
+        assert_eq!(backfill.chunks(backfill.candle_chunk_secs(Timeframe::M1)), vec![(0, 25 * 60)]);
+        let chunked = backfill.clone().with_chunk_secs(600);
+        assert_eq!(chunked.chunks(600), vec![(0, 600), (600, 1200), (1200, 1500)]);
+
+        assert_eq!(backfill.retry_backoff(1), Duration::from_millis(100));
+        assert_eq!(backfill.retry_backoff(3), Duration::from_millis(400));
+        assert_eq!(
+            Backfill::new("EURUSD", 0, 1).with_requests_per_minute(30).request_interval,
+            Duration::from_secs(2)
+        );
+    }
+
+    #[cfg(feature = "experimental-ticks")]
+    #[test]
+    fn test_tick_request() {
+        let backfill = Backfill::new("EURUSD", 0, 7200);
+        assert_eq!(backfill.chunks(backfill.tick_chunk_secs()), vec![(0, 3600), (3600, 7200)]);
+
+        let data = tick_request_bytes("EURUSD", 100, 200);
+        assert_eq!(&data[..6], b"EURUSD");
+        assert_eq!(i32::from_le_bytes(data[16..20].try_into().unwrap()), 200);
+    }
+}
@@ -0,0 +1,249 @@
+//! mt4 命令行工具 (需要开启 `cli` feature)
+//!
+//! 在写代码之前检查经纪商账户、报价和下单流程:
+//!
+//! ```bash
+//! export MT4_LOGIN=31313724 MT4_PASSWORD=password MT4_SERVER=ICMarketsSC-Demo03
+//! mt4 quote EURUSD
+//! mt4 buy EURUSD 0.01 --sl 1.0800 --tp 1.0900
+//! mt4 positions
+//! mt4 history --from 2024-01-01 --to 2024-01-31
+//! mt4 watch > events.ndjson
+//! ```
+//!
+//! 日志输出到 stderr，级别由 `RUST_LOG` 控制 (默认 `mt4_client=warn`)
+
+use chrono::{NaiveDate, Utc};
+use clap::{Args, Parser, Subcommand};
+use mt4_client::{LoginCredentials, Mt4Client, Mt4Event, NdjsonSink, Order, Symbol, TradeRequest, TradeResponse};
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+/// 等待持仓/历史响应的时间
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Parser)]
+#[command(name = "mt4", version, about = "MT4 Web Terminal 命令行工具")]
+struct Cli {
+    #[command(flatten)]
+    account: AccountArgs,
+
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Debug, Args)]
+struct AccountArgs {
+    /// 账号
+    #[arg(long, env = "MT4_LOGIN", global = true, hide_env_values = true)]
+    login: Option<String>,
+
+    /// 密码
+    #[arg(long, env = "MT4_PASSWORD", global = true, hide_env_values = true)]
+    password: Option<String>,
+
+    /// 交易服务器名称
+    #[arg(long, env = "MT4_SERVER", global = true)]
+    server: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliCommand {
+    /// 查询当前报价
+    Quote {
+        /// 品种
+        symbol: String,
+    },
+    /// 市价买入
+    Buy(OrderArgs),
+    /// 市价卖出
+    Sell(OrderArgs),
+    /// 列出当前持仓和挂单
+    Positions,
+    /// 列出已平仓订单 (日期为经纪商时间)
+    History {
+        /// 开始日期 (YYYY-MM-DD)
+        #[arg(long)]
+        from: NaiveDate,
+        /// 结束日期 (YYYY-MM-DD，包含当天，默认今天)
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// 以 NDJSON 格式持续输出事件，Ctrl-C 退出
+    Watch,
+}
+
+#[derive(Debug, Args)]
+struct OrderArgs {
+    /// 品种
+    symbol: String,
+    /// 手数
+    volume: f64,
+    /// 止损价
+    #[arg(long)]
+    sl: Option<f64>,
+    /// 止盈价
+    #[arg(long)]
+    tp: Option<f64>,
+    /// 订单注释
+    #[arg(long)]
+    comment: Option<String>,
+}
+
+impl OrderArgs {
+    fn to_request(&self, buy: bool) -> mt4_client::Result<TradeRequest> {
+        let symbol = Symbol::new(&self.symbol)?;
+        let (sl, tp) = (self.sl.unwrap_or(0.0), self.tp.unwrap_or(0.0));
+        let mut request = if buy {
+            TradeRequest::buy(&symbol, self.volume, sl, tp)
+        } else {
+            TradeRequest::sell(&symbol, self.volume, sl, tp)
+        };
+        if let Some(comment) = &self.comment {
+            request.comment = comment.clone();
+        }
+        Ok(request)
+    }
+}
+
+impl AccountArgs {
+    fn credentials(&self) -> Result<LoginCredentials, String> {
+        let missing = |name: &str, env: &str| format!("缺少 --{} (或环境变量 {})", name, env);
+        Ok(LoginCredentials {
+            login: self.login.clone().ok_or_else(|| missing("login", "MT4_LOGIN"))?,
+            password: self.password.as_deref().ok_or_else(|| missing("password", "MT4_PASSWORD"))?.into(),
+            server: self.server.clone().ok_or_else(|| missing("server", "MT4_SERVER"))?,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("mt4_client=warn")))
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("错误: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let credentials = cli.account.credentials()?;
+    let mut client = Mt4Client::builder().auth_timeout(Duration::from_secs(15)).build();
+    // 连接前订阅，不错过认证后的持仓快照
+    let mut events = client.subscribe();
+    client.connect(&credentials).await?;
+
+    match cli.command {
+        CliCommand::Quote { symbol } => {
+            let (bid, ask) = client.request_price(&symbol).await?;
+            println!("{} bid {} ask {}", symbol, bid, ask);
+        }
+        CliCommand::Buy(args) => print_response(&client.send_trade_and_wait(args.to_request(true)?).await?),
+        CliCommand::Sell(args) => print_response(&client.send_trade_and_wait(args.to_request(false)?).await?),
+        CliCommand::Positions => {
+            let orders = wait_for(&mut events, |event| match event {
+                Mt4Event::PositionsSnapshot(orders) => Some(orders),
+                _ => None,
+            })
+            .await?;
+            print_orders(&orders);
+        }
+        CliCommand::History { from, to } => {
+            let (start, end) = history_range(from, to.unwrap_or_else(|| Utc::now().date_naive()));
+            let orders = client.request_order_history_range(start, end).await?;
+            print_orders(&orders);
+        }
+        CliCommand::Watch => {
+            let sink = NdjsonSink::from_writer(tokio::io::stdout()).with_flush_interval(Duration::ZERO);
+            tokio::select! {
+                result = sink.run(events) => result?,
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        }
+    }
+
+    client.disconnect().await;
+    Ok(())
+}
+
+/// 等待第一个匹配的事件
+async fn wait_for<T>(
+    events: &mut mt4_client::EventSubscription,
+    mut matcher: impl FnMut(Mt4Event) -> Option<T>,
+) -> Result<T, String> {
+    let wait = async {
+        while let Some(event) = events.recv().await {
+            if let Some(value) = matcher(event) {
+                return Some(value);
+            }
+        }
+        None
+    };
+    match tokio::time::timeout(RESPONSE_TIMEOUT, wait).await {
+        Ok(Some(value)) => Ok(value),
+        Ok(None) => Err("连接已关闭".to_string()),
+        Err(_) => Err(format!("{} 秒内未收到服务器响应", RESPONSE_TIMEOUT.as_secs())),
+    }
+}
+
+/// 日期范围换算为服务器时间 (秒)，结束日期包含当天
+fn history_range(from: NaiveDate, to: NaiveDate) -> (i64, i64) {
+    let start = from.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+    let end = to.and_hms_opt(23, 59, 59).unwrap().and_utc().timestamp();
+    (start, end)
+}
+
+fn print_response(response: &TradeResponse) {
+    println!("request #{} status {} price {} / {}", response.request_id, response.status, response.price1, response.price2);
+    print_orders(&response.orders);
+}
+
+fn print_orders(orders: &[Order]) {
+    if orders.is_empty() {
+        println!("(无订单)");
+        return;
+    }
+    println!(
+        "{:>10} {:<10} {:<10} {:>8} {:>12} {:>12} {:>12} {:>12} {:>10}",
+        "ticket", "symbol", "type", "volume", "open", "sl", "tp", "close", "profit"
+    );
+    for order in orders {
+        println!(
+            "{:>10} {:<10} {:<10} {:>8.2} {:>12} {:>12} {:>12} {:>12} {:>10.2}",
+            order.ticket,
+            order.symbol,
+            format!("{:?}", order.order_type),
+            order.volume,
+            order.open_price,
+            order.sl,
+            order.tp,
+            order.close_price,
+            order.profit + order.commission + order.swap,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        let cli = Cli::try_parse_from(["mt4", "--login", "1", "buy", "EURUSD", "0.01", "--sl", "1.08"]).unwrap();
+        let CliCommand::Buy(args) = cli.command else { panic!("expected buy") };
+        let request = args.to_request(true).unwrap();
+        assert_eq!((request.volume, request.sl, request.tp), (0.01, 1.08, 0.0));
+        assert_eq!(cli.account.login.as_deref(), Some("1"));
+
+        let cli = Cli::try_parse_from(["mt4", "history", "--from", "2024-01-01", "--to", "2024-01-31"]).unwrap();
+        let CliCommand::History { from, to } = cli.command else { panic!("expected history") };
+        assert_eq!(history_range(from, to.unwrap()), (1_704_067_200, 1_706_745_599));
+
+        assert!(Cli::try_parse_from(["mt4", "buy", "EURUSD"]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! 挂单簿: 按品种、按价格排序的本地挂单
+//!
+//! 由 `Mt4Client::pending_book()` 从当前挂单生成快照，支持按价格距离查询，
+//! 例如"当前 bid 下方最近的挂单"、"10 点 (pip) 以内的所有挂单"，供网格、OCO 等策略逻辑使用。
+
+use crate::types::{Order, Symbol};
+use std::collections::HashMap;
+
+/// 挂单簿快照
+#[derive(Debug, Clone, Default)]
+pub struct PendingBook {
+    /// 品种 -> 按开仓价升序排列的挂单
+    symbols: HashMap<Symbol, Vec<Order>>,
+}
+
+impl PendingBook {
+    /// 从订单列表创建 (忽略市价单)
+    pub fn from_orders<'a>(orders: impl IntoIterator<Item = &'a Order>) -> Self {
+        let mut symbols: HashMap<Symbol, Vec<Order>> = HashMap::new();
+        for order in orders.into_iter().filter(|o| o.is_pending()) {
+            symbols.entry(order.symbol.clone()).or_default().push(order.clone());
+        }
+        for orders in symbols.values_mut() {
+            orders.sort_by(|a, b| a.open_price.total_cmp(&b.open_price).then(a.ticket.cmp(&b.ticket)));
+        }
+        Self { symbols }
+    }
+
+    /// 指定品种的挂单，按价格升序
+    pub fn orders(&self, symbol: &str) -> &[Order] {
+        self.symbols.get(symbol).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// 有挂单的品种
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.symbols.keys().map(Symbol::as_str)
+    }
+
+    /// 挂单总数
+    pub fn len(&self) -> usize {
+        self.symbols.values().map(Vec::len).sum()
+    }
+
+    /// 是否没有挂单
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// 价格严格低于 `price` 的最近挂单
+    pub fn nearest_below(&self, symbol: &str, price: f64) -> Option<&Order> {
+        let orders = self.orders(symbol);
+        let idx = orders.partition_point(|o| o.open_price < price);
+        idx.checked_sub(1).map(|i| &orders[i])
+    }
+
+    /// 价格严格高于 `price` 的最近挂单
+    pub fn nearest_above(&self, symbol: &str, price: f64) -> Option<&Order> {
+        let orders = self.orders(symbol);
+        let idx = orders.partition_point(|o| o.open_price <= price);
+        orders.get(idx)
+    }
+
+    /// 价格在 [low, high] 区间内的挂单
+    pub fn in_range(&self, symbol: &str, low: f64, high: f64) -> &[Order] {
+        let orders = self.orders(symbol);
+        let start = orders.partition_point(|o| o.open_price < low);
+        let end = orders.partition_point(|o| o.open_price <= high);
+        &orders[start..end.max(start)]
+    }
+
+    /// 距离 `price` 不超过 `distance` (价格单位) 的挂单
+    pub fn within(&self, symbol: &str, price: f64, distance: f64) -> &[Order] {
+        self.in_range(symbol, price - distance, price + distance)
+    }
+
+    /// 距离 `price` 不超过 `pips` 点的挂单 (点值按订单的小数位数计算，见 `pip_size`)
+    pub fn within_pips(&self, symbol: &str, price: f64, pips: f64) -> &[Order] {
+        match self.orders(symbol).first() {
+            Some(order) => self.within(symbol, price, pips * pip_size(order.digits)),
+            None => &[],
+        }
+    }
+}
+
+/// 点 (pip) 的价格大小: 5/3 位报价为 10 个最小变动单位，其余为 1 个
+pub fn pip_size(digits: i32) -> f64 {
+    let point = 10f64.powi(-digits);
+    if digits == 3 || digits == 5 {
+        point * 10.0
+    } else {
+        point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+    use crate::types::Ticket;
+
+    fn order(ticket: i32, order_type: OrderType, open_price: f64) -> Order {
+        Order::for_test(ticket, "EURUSD", order_type, 0.1, open_price)
+    }
+
+    #[test]
+    fn test_price_distance_queries() {
+        let orders = [
+            order(1, OrderType::BuyLimit, 1.0800),
+            order(2, OrderType::SellStop, 1.0790),
+            order(3, OrderType::BuyStop, 1.0850),
+            order(4, OrderType::Buy, 1.0810),
+            order(5, OrderType::SellLimit, 1.0900),
+        ];
+        let book = PendingBook::from_orders(&orders);
+        assert_eq!(book.len(), 4);
+
+        let bid = 1.0815;
+        assert_eq!(book.nearest_below("EURUSD", bid).map(|o| o.ticket), Some(Ticket(1)));
+        assert_eq!(book.nearest_above("EURUSD", bid).map(|o| o.ticket), Some(Ticket(3)));
+        assert!(book.nearest_below("EURUSD", 1.0790).is_none());
+        assert!(book.nearest_above("GBPUSD", bid).is_none());
+
+        let near: Vec<i32> = book.within_pips("EURUSD", bid, 20.0).iter().map(|o| o.ticket.get()).collect();
+        assert_eq!(near, vec![1]);
+        let near: Vec<i32> = book.within_pips("EURUSD", bid, 40.0).iter().map(|o| o.ticket.get()).collect();
+        assert_eq!(near, vec![2, 1, 3]);
+        assert!((pip_size(3) - 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_empty_book() {
+        // 只有市价单时挂单簿为空，所有查询都返回空结果
+        let book = PendingBook::from_orders(&[order(1, OrderType::Buy, 1.0810), order(2, OrderType::Sell, 1.0820)]);
+        assert!(book.is_empty() && book.symbols().next().is_none());
+        assert!(book.orders("EURUSD").is_empty());
+        assert!(book.nearest_below("EURUSD", 1.08).is_none() && book.nearest_above("EURUSD", 1.08).is_none());
+        assert!(book.within_pips("EURUSD", 1.08, 100.0).is_empty());
+
+        // 区间上下限颠倒时为空
+        let book = PendingBook::from_orders(&[order(3, OrderType::BuyLimit, 1.0800)]);
+        assert!(book.in_range("EURUSD", 1.09, 1.07).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! 保本价计算
+//!
+//! 保本价为计入点差、佣金和隔夜利息后盈亏为零的市场价格，统一以 bid 表示:
+//!
+//! - 买单按 bid 平仓: `bid = 开仓价 - (佣金 + 利息) / (手数 × 合约数量)`
+//! - 卖单按 ask 平仓: `ask = 开仓价 + (佣金 + 利息) / (手数 × 合约数量)`，换算为 bid 再减去点差
+//!
+//! 净持仓 (同一品种的所有持仓) 的保本价按各持仓盈亏之和为零求解，多空手数相等时不存在。
+//! 佣金和利息随订单更新变化，点差随报价变化，每次收到新报价时重新计算即可得到实时保本价。
+
+use crate::types::{Order, SymbolInfo, Ticket};
+use serde::Serialize;
+
+/// 保本价
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Breakeven {
+    /// 品种
+    pub symbol: String,
+    /// 订单号 (净持仓为 None)
+    pub ticket: Option<Ticket>,
+    /// 净手数 (多为正，空为负)
+    pub net_volume: f64,
+    /// 保本 bid 价格
+    pub price: f64,
+    /// 佣金与利息合计 (账户货币)
+    pub costs: f64,
+}
+
+/// 计算单个持仓的保本价 (挂单或手数为 0 时为 None)
+///
+/// `spread` 为当前 ask - bid，未知时传 0
+pub fn position_breakeven(order: &Order, spec: &SymbolInfo, spread: f64) -> Option<Breakeven> {
+    net_breakeven(std::slice::from_ref(order), spec, spread).map(|b| Breakeven { ticket: Some(order.ticket), ..b })
+}
+
+/// 计算净持仓的保本价 (只计入与 `spec` 同品种的持仓，净手数为 0 时为 None)
+pub fn net_breakeven(orders: &[Order], spec: &SymbolInfo, spread: f64) -> Option<Breakeven> {
+    if spec.contract_size <= 0.0 {
+        return None;
+    }
+    let mut net_volume = 0.0;
+    // Σ 买单 开仓价×手数 - Σ 卖单 (开仓价-点差)×手数
+    let mut weighted = 0.0;
+    let mut costs = 0.0;
+    for order in orders.iter().filter(|o| o.symbol == spec.symbol && !o.is_pending()) {
+        costs += order.commission + order.swap;
+        if order.order_type.is_buy() {
+            net_volume += order.volume;
+            weighted += order.open_price * order.volume;
+        } else {
+            net_volume -= order.volume;
+            weighted -= (order.open_price - spread) * order.volume;
+        }
+    }
+    if net_volume.abs() < 1e-9 {
+        return None;
+    }
+    let price = (weighted - costs / spec.contract_size) / net_volume;
+    let factor = 10f64.powi(spec.digits.max(0) + 2);
+    Some(Breakeven {
+        symbol: spec.symbol.clone(),
+        ticket: None,
+        net_volume,
+        price: (price * factor).round() / factor,
+        costs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+
+    fn order(ticket: i32, order_type: OrderType, volume: f64, open_price: f64, commission: f64, swap: f64) -> Order {
+        Order { commission, swap, ..Order::for_test(ticket, "EURUSD", order_type, volume, open_price) }
+    }
+
+    #[test]
+    fn test_breakeven() {
+        let spec = SymbolInfo::new("EURUSD", 5);
+        // 1 手买单，佣金 -7，利息 -3: 需要上涨 10 美元 = 1 点
+        let buy = order(1, OrderType::Buy, 1.0, 1.08000, -7.0, -3.0);
+        let b = position_breakeven(&buy, &spec, 0.0002).unwrap();
+        assert_eq!((b.ticket, b.price), (Some(Ticket(1)), 1.08010));
+
+        // 0.5 手卖单，佣金 -5: ask 需下跌 1 点，bid 再减去 2 点点差
+        let sell = order(2, OrderType::Sell, 0.5, 1.08100, -5.0, 0.0);
+        assert_eq!(position_breakeven(&sell, &spec, 0.0002).unwrap().price, 1.08070);
+
+        // 净多 0.5 手
+        let net = net_breakeven(&[buy.clone(), sell], &spec, 0.0002).unwrap();
+        assert_eq!((net.ticket, net.net_volume, net.costs), (None, 0.5, -15.0));
+        assert_eq!(net.price, 1.07950);
+
+        // 多空对冲
+        let hedge = order(3, OrderType::Sell, 1.0, 1.08000, 0.0, 0.0);
+        assert!(net_breakeven(&[buy, hedge], &spec, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_breakeven_edge_cases() {
+        let spec = SymbolInfo::new("EURUSD", 5);
+        // 卖单与买单对称: 相同成本下保本价在开仓价另一侧 1 点
+        let sell = order(1, OrderType::Sell, 1.0, 1.08000, -7.0, -3.0);
+        assert_eq!(position_breakeven(&sell, &spec, 0.0).unwrap().price, 1.07990);
+
+        // 手数为 0、挂单、其他品种和合约数量无效时没有保本价
+        assert!(position_breakeven(&order(2, OrderType::Buy, 0.0, 1.08, -7.0, 0.0), &spec, 0.0).is_none());
+        assert!(position_breakeven(&order(3, OrderType::BuyLimit, 1.0, 1.08, 0.0, 0.0), &spec, 0.0).is_none());
+        assert!(position_breakeven(&sell, &SymbolInfo::new("GBPUSD", 5), 0.0).is_none());
+        assert!(position_breakeven(&sell, &SymbolInfo { contract_size: 0.0, ..spec.clone() }, 0.0).is_none());
+        assert!(net_breakeven(&[], &spec, 0.0).is_none());
+    }
+}
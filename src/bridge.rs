@@ -0,0 +1,424 @@
+//! 本地 MT4 终端桥接传输
+//!
+//! 经纪商关闭 Web Terminal (Token 响应 `enabled: false`) 时，可以改为连接本地运行的
+//! MT4 终端 (包括 Wine 下运行的终端)。终端中加载的桥接 EA (`ea/Mt4Bridge.mq4`) 监听一个 TCP 端口，
+//! 双方以 JSON Lines 交换带 `type` 标签的消息:
+//!
+//! ```text
+//! EA -> 客户端: {"type":"hello","version":1}
+//! 客户端 -> EA: {"type":"login","password":"..."}
+//! EA -> 客户端: {"type":"auth","error_code":0}
+//! 客户端 -> EA: {"type":"account_info"}
+//! EA -> 客户端: {"type":"account","login":12345,"balance":1000.0,"equity":1000.0,"leverage":100,"currency":"USD"}
+//! 客户端 -> EA: {"type":"trade","request":{"trade_type":66,"order_type":"Buy","symbol":"EURUSD",...}}
+//! EA -> 客户端: {"type":"trade_result","request_id":1000,"status":0,"orders":[{"ticket":1001,...}]}
+//! ```
+//!
+//! 每种消息对应一个 Web 协议命令 (`BridgeMessage` / `BridgeCommand`)。入站消息编码为该命令的帧数据，
+//! 交给与 WebSocket 连接相同的解析流程；客户端发出的明文命令帧直接转换为出站消息，不经过加密。
+//! 因此 `Mt4Client` 的交易、持仓、事件等接口无需区分传输方式。没有对应消息的命令以 `frame`
+//! 消息传递十六进制编码的帧数据。
+//!
+//! 密码以明文经过该连接，EA 只应监听本机地址 (127.0.0.1)。
+
+use crate::error::{Mt4Error, Result};
+use crate::packet::OutboundFrame;
+use crate::protocol::{Command, ORDER_DATA_SIZE, ORDER_UPDATE_SIZE, QUOTE_SIZE};
+use crate::types::{Order, Quote, Symbol, TradeRequest, ACCOUNT_INFO_SIZE, SYMBOL_MAX_LEN};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::sync::{mpsc, oneshot};
+use zeroize::Zeroizing;
+
+/// 桥接 EA 的默认监听地址
+pub const DEFAULT_BRIDGE_ADDR: &str = "127.0.0.1:8222";
+
+/// 桥接协议版本 (EA 在 `hello` 中报告)
+pub const BRIDGE_PROTOCOL_VERSION: u32 = 1;
+
+/// EA 发来的消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BridgeMessage {
+    /// 连接建立 (Command 0)，客户端随后发送 `login`
+    Hello {
+        /// 协议版本
+        #[serde(default)]
+        version: u32,
+    },
+    /// 认证结果 (Command 1)，0 表示成功
+    Auth {
+        /// 错误码
+        #[serde(default)]
+        error_code: u8,
+    },
+    /// 账户信息 (Command 3)
+    Account {
+        /// 账号
+        login: i32,
+        /// 余额
+        balance: f64,
+        /// 净值
+        equity: f64,
+        /// 杠杆
+        leverage: i32,
+        /// 账户货币
+        currency: String,
+        /// 账户名称
+        #[serde(default)]
+        name: String,
+        /// 交易服务器
+        #[serde(default)]
+        server: String,
+    },
+    /// 当前持仓和挂单 (Command 4)
+    Positions {
+        /// 订单列表
+        orders: Vec<Order>,
+    },
+    /// 报价 (Command 8)
+    Quote(Quote),
+    /// 订单变化 (Command 10)
+    OrderUpdate {
+        /// 通知 ID
+        #[serde(default)]
+        notify_id: i32,
+        /// 通知类型 (0 新订单 / 1 已平仓 / 2 已修改)
+        notify_type: i32,
+        /// 变化后的余额 (0 表示未提供)
+        #[serde(default)]
+        balance: f64,
+        /// 变化后的信用额 (0 表示未提供)
+        #[serde(default)]
+        credit: f64,
+        /// 订单
+        order: Order,
+    },
+    /// 交易结果 (Command 12)，回应 `trade` 请求
+    TradeResult {
+        /// 请求 ID (与请求中的 request_id 相同)
+        request_id: i32,
+        /// 状态 (小于 2 表示成功，否则为交易服务器返回码)
+        status: i32,
+        /// 价格 1 (报价请求为 bid)
+        #[serde(default)]
+        price1: f64,
+        /// 价格 2 (报价请求为 ask)
+        #[serde(default)]
+        price2: f64,
+        /// 成交、修改或剩余的订单
+        #[serde(default)]
+        orders: Vec<Order>,
+    },
+    /// 其他命令的原始帧
+    Frame {
+        /// 命令
+        command: u16,
+        /// 错误码
+        #[serde(default)]
+        error_code: u8,
+        /// 帧数据
+        #[serde(default, with = "crate::session::hex_bytes")]
+        data: Vec<u8>,
+    },
+}
+
+/// 发给 EA 的消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BridgeCommand {
+    /// 登录 (Command 1)，EA 核对桥接密码后回复 `auth`
+    Login {
+        /// 密码
+        #[serde(with = "secret_string")]
+        password: SecretString,
+    },
+    /// 登出 (Command 2)，客户端随后关闭连接
+    Logout,
+    /// 请求账户信息 (Command 3)，EA 回复 `account`
+    AccountInfo,
+    /// 请求当前持仓 (Command 4)，EA 回复 `positions`
+    Positions,
+    /// 交易请求 (Command 12)，EA 回复 `trade_result`
+    Trade {
+        /// 请求 (trade_type: 0 报价 / 66 市价 / 67 挂单 / 70 平仓 / 71 修改 / 72 删除)
+        request: TradeRequest,
+    },
+    /// 显示或隐藏报价品种 (Command 26)
+    MarketWatch {
+        /// true 为显示
+        show: bool,
+        /// 品种
+        symbols: Vec<Symbol>,
+    },
+    /// 心跳 (Command 51)
+    Ping,
+    /// 其他命令的原始帧
+    Frame {
+        /// 命令
+        command: u16,
+        /// 命令数据
+        #[serde(with = "crate::session::hex_bytes")]
+        data: Vec<u8>,
+    },
+}
+
+impl BridgeMessage {
+    /// 解析一行 JSON
+    pub fn parse(line: &str) -> Result<Self> {
+        serde_json::from_str(line).map_err(|e| Mt4Error::Protocol(format!("桥接消息格式错误: {}", e)))
+    }
+
+    /// 编码为对应命令的帧 (命令, 错误码, 数据)，数据布局与 Web 协议相同
+    pub fn into_frame(self) -> (u16, u8, Vec<u8>) {
+        match self {
+            Self::Hello { .. } => (Command::AuthToken as u16, 0, Vec::new()),
+            Self::Auth { error_code } => (Command::AuthPassword as u16, error_code, Vec::new()),
+            Self::Account { login, balance, equity, leverage, currency, name, server } => {
+                let mut data = vec![0u8; ACCOUNT_INFO_SIZE];
+                data[1..9].copy_from_slice(&balance.to_le_bytes());
+                data[9..17].copy_from_slice(&equity.to_le_bytes());
+                write_utf16(&mut data[17..49], &currency);
+                data[49..53].copy_from_slice(&leverage.to_le_bytes());
+                data[53..57].copy_from_slice(&login.to_le_bytes());
+                write_utf16(&mut data[58..186], &server);
+                write_bytes(&mut data[190..254], name.as_bytes());
+                (Command::AccountInfo as u16, 0, data)
+            }
+            Self::Positions { orders } => {
+                let mut data = Vec::with_capacity(orders.len() * ORDER_DATA_SIZE);
+                orders.iter().for_each(|order| data.extend_from_slice(&encode_order(order)));
+                (Command::CurrentPositions as u16, 0, data)
+            }
+            Self::Quote(quote) => {
+                let mut data = vec![0u8; QUOTE_SIZE];
+                write_bytes(&mut data[..SYMBOL_MAX_LEN], quote.symbol.as_bytes());
+                data[12..20].copy_from_slice(&quote.bid.to_le_bytes());
+                data[20..28].copy_from_slice(&quote.ask.to_le_bytes());
+                data[28..32].copy_from_slice(&(quote.time as i32).to_le_bytes());
+                (Command::QuotesRequest as u16, 0, data)
+            }
+            Self::OrderUpdate { notify_id, notify_type, balance, credit, order } => {
+                let mut data = Vec::with_capacity(ORDER_UPDATE_SIZE);
+                data.extend_from_slice(&notify_id.to_le_bytes());
+                data.extend_from_slice(&notify_type.to_le_bytes());
+                data.extend_from_slice(&balance.to_le_bytes());
+                data.extend_from_slice(&credit.to_le_bytes());
+                data.extend_from_slice(&encode_order(&order));
+                (Command::OrderUpdate as u16, 0, data)
+            }
+            Self::TradeResult { request_id, status, price1, price2, orders } => {
+                let mut data = Vec::with_capacity(24 + orders.len() * ORDER_DATA_SIZE);
+                data.extend_from_slice(&request_id.to_le_bytes());
+                data.extend_from_slice(&status.to_le_bytes());
+                data.extend_from_slice(&price1.to_le_bytes());
+                data.extend_from_slice(&price2.to_le_bytes());
+                orders.iter().for_each(|order| data.extend_from_slice(&encode_order(order)));
+                (Command::TradeRequest as u16, 0, data)
+            }
+            Self::Frame { command, error_code, data } => (command, error_code, data),
+        }
+    }
+}
+
+impl BridgeCommand {
+    /// 由客户端发出的明文命令帧构建
+    pub(crate) fn from_frame(frame: &OutboundFrame) -> Self {
+        let data = &frame.data[..];
+        match frame.command {
+            c if c == Command::AuthPassword as u16 => {
+                let units: Vec<u16> = data
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .take_while(|&unit| unit != 0)
+                    .collect();
+                Self::Login { password: String::from_utf16_lossy(&units).into() }
+            }
+            c if c == Command::Logout as u16 => Self::Logout,
+            c if c == Command::AccountInfo as u16 => Self::AccountInfo,
+            c if c == Command::CurrentPositions as u16 => Self::Positions,
+            c if c == Command::TradeRequest as u16 => match TradeRequest::from_bytes(data) {
+                Some(request) => Self::Trade { request },
+                None => Self::Frame { command: frame.command, data: data.to_vec() },
+            },
+            c if c == Command::QuoteSubscribe as u16 && !data.is_empty() => Self::MarketWatch {
+                show: data[0] != 0,
+                symbols: data[1..].chunks_exact(SYMBOL_MAX_LEN).map(Symbol::from_wire).collect(),
+            },
+            c if c == Command::Ping as u16 => Self::Ping,
+            command => Self::Frame { command, data: data.to_vec() },
+        }
+    }
+
+    /// 序列化为一行 JSON (含换行符)，包含密码时释放后清零
+    pub fn to_line(&self) -> Result<Zeroizing<String>> {
+        let mut line = Zeroizing::new(
+            serde_json::to_string(self).map_err(|e| Mt4Error::Serialization(format!("序列化桥接消息失败: {}", e)))?,
+        );
+        line.push('\n');
+        Ok(line)
+    }
+}
+
+/// 订单记录 (161 字节，布局见 `Order::from_bytes`)
+fn encode_order(order: &Order) -> [u8; ORDER_DATA_SIZE] {
+    let mut record = [0u8; ORDER_DATA_SIZE];
+    record[0..4].copy_from_slice(&order.ticket.0.to_le_bytes());
+    write_bytes(&mut record[4..16], order.symbol.as_bytes());
+    record[16..20].copy_from_slice(&order.digits.to_le_bytes());
+    record[20..24].copy_from_slice(&(order.order_type as i32).to_le_bytes());
+    record[24..28].copy_from_slice(&((order.volume * 100.0).round() as i32).to_le_bytes());
+    record[28..32].copy_from_slice(&(order.open_time as i32).to_le_bytes());
+    record[36..44].copy_from_slice(&order.open_price.to_le_bytes());
+    record[44..52].copy_from_slice(&order.sl.to_le_bytes());
+    record[52..60].copy_from_slice(&order.tp.to_le_bytes());
+    record[60..64].copy_from_slice(&(order.close_time as i32).to_le_bytes());
+    record[93..101].copy_from_slice(&order.close_price.to_le_bytes());
+    record[101..109].copy_from_slice(&order.profit.to_le_bytes());
+    record[109..117].copy_from_slice(&order.swap.to_le_bytes());
+    write_bytes(&mut record[121..153], order.comment.as_bytes());
+    record[153..161].copy_from_slice(&order.commission.to_le_bytes());
+    record
+}
+
+/// 写入定长字段 (超长部分截断，其余为 0)
+fn write_bytes(field: &mut [u8], bytes: &[u8]) {
+    let len = bytes.len().min(field.len());
+    field[..len].copy_from_slice(&bytes[..len]);
+}
+
+/// 写入定长 UTF-16 LE 字段
+fn write_utf16(field: &mut [u8], text: &str) {
+    for (slot, unit) in field.chunks_exact_mut(2).zip(text.encode_utf16()) {
+        slot.copy_from_slice(&unit.to_le_bytes());
+    }
+}
+
+/// `SecretString` 的序列化 (只用于写入桥接连接)
+mod secret_string {
+    use secrecy::{ExposeSecret, SecretString};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(secret: &SecretString, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(secret.expose_secret())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<SecretString, D::Error> {
+        String::deserialize(deserializer).map(SecretString::from)
+    }
+}
+
+/// 桥接写入任务: 把客户端发出的明文命令帧转换为消息逐行写给 EA，连接断开或发送端关闭时结束
+///
+/// 收到关闭通知时先转发已排队的命令，再关闭 TCP 写入端
+pub(crate) async fn forward_requests(
+    mut write: OwnedWriteHalf,
+    mut frames: mpsc::Receiver<OutboundFrame>,
+    mut shutdown: oneshot::Receiver<()>,
+) {
+    loop {
+        let frame = tokio::select! {
+            biased;
+            frame = frames.recv() => match frame {
+                Some(frame) => frame,
+                None => break,
+            },
+            _ = &mut shutdown => break,
+        };
+        let line = match BridgeCommand::from_frame(&frame).to_line() {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::error!("Bridge encode error: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = write.write_all(line.as_bytes()).await {
+            tracing::error!("Bridge write error: {}", e);
+            return;
+        }
+    }
+    if let Err(e) = write.shutdown().await {
+        tracing::debug!("Bridge shutdown error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet;
+    use crate::protocol::OrderType;
+    use crate::types::{AccountInfo, OrderUpdate, Ticket, TradeResponse};
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn test_bridge_messages() {
+        let message = BridgeMessage::parse(r#"{"type":"auth","error_code":3}"#).unwrap();
+        assert_eq!(message.into_frame(), (1, 3, Vec::new()));
+        let frame = BridgeMessage::parse(r#"{"type":"frame","command":15,"data":"dead"}"#).unwrap();
+        assert_eq!(frame.into_frame(), (15, 0, vec![0xde, 0xad]));
+        assert!(BridgeMessage::parse(r#"{"command":10}"#).is_err());
+        assert!(BridgeMessage::parse("not json").is_err());
+
+        // 入站消息编码后由 Web 协议的解析器读回相同的值
+        let account = r#"{"type":"account","login":12345,"balance":1000.5,"equity":990.25,"leverage":100,
+            "currency":"USD","name":"Demo","server":"Broker-Demo"}"#;
+        let (command, _, data) = BridgeMessage::parse(account).unwrap().into_frame();
+        let account = AccountInfo::from_bytes(&data).unwrap();
+        assert_eq!(command, 3);
+        assert_eq!((account.login, account.balance, account.equity, account.leverage), (12345, 1000.5, 990.25, 100));
+        assert_eq!((&account.currency[..], &account.name[..], &account.server[..]), ("USD", "Demo", "Broker-Demo"));
+
+        let mut order = Order::for_test(1001, "EURUSD", OrderType::Sell, 0.05, 1.0852);
+        (order.sl, order.profit, order.commission, order.comment) = (1.09, -1.5, -0.35, "grid#3".to_string());
+        let (notify_id, notify_type, balance, credit) = (7, 2, 1000.5, 0.0);
+        let update = BridgeMessage::OrderUpdate { notify_id, notify_type, balance, credit, order: order.clone() };
+        let (command, _, data) = update.into_frame();
+        let update = OrderUpdate::from_bytes(&data, 0).unwrap();
+        assert_eq!((command, data.len(), update.notify_id, update.notify_type, update.df), (10, 185, 7, 2, 1000.5));
+        assert_eq!(serde_json::to_value(&update.order).unwrap(), serde_json::to_value(&order).unwrap());
+
+        let (request_id, status, price1, price2) = (1000, 0, 1.0852, 0.0);
+        let result = BridgeMessage::TradeResult { request_id, status, price1, price2, orders: vec![order] };
+        let (command, _, data) = result.into_frame();
+        let response = TradeResponse::from_bytes(&data).unwrap();
+        assert_eq!((command, response.request_id, response.orders.len()), (12, 1000, 1));
+        assert_eq!(response.orders[0].ticket, Ticket(1001));
+
+        let quote = Quote { symbol: "XAUUSD".to_string(), bid: 2050.1, ask: 2050.4, time: 1_704_153_600 };
+        let (command, _, data) = BridgeMessage::Quote(quote.clone()).into_frame();
+        assert_eq!((command, Quote::parse_all(&data)), (8, vec![quote]));
+    }
+
+    #[test]
+    fn test_bridge_commands() {
+        let line = |command: u16, data: Vec<u8>| {
+            BridgeCommand::from_frame(&OutboundFrame::new(1, command, data)).to_line().unwrap().to_string()
+        };
+        assert_eq!(line(1, packet::encode_password("secret")), "{\"type\":\"login\",\"password\":\"secret\"}\n");
+        assert_eq!(line(3, Vec::new()), "{\"type\":\"account_info\"}\n");
+        assert_eq!(line(51, Vec::new()), "{\"type\":\"ping\"}\n");
+        assert_eq!(line(99, vec![1, 2, 3]), "{\"type\":\"frame\",\"command\":99,\"data\":\"010203\"}\n");
+        let symbols = [Symbol::new("EURUSD").unwrap(), Symbol::new("XAUUSD").unwrap()];
+        assert_eq!(
+            line(26, packet::encode_market_watch(true, &symbols)),
+            "{\"type\":\"market_watch\",\"show\":true,\"symbols\":[\"EURUSD\",\"XAUUSD\"]}\n"
+        );
+
+        // 交易请求按字段传递，EA 无需解析二进制布局
+        let mut request = TradeRequest::buy(&symbols[0], 0.1, 1.08, 0.0);
+        request.request_id = 1000;
+        let command = BridgeCommand::from_frame(&OutboundFrame::new(1, 12, request.to_bytes()));
+        let BridgeCommand::Trade { request: parsed } = &command else { panic!("{:?}", command) };
+        assert_eq!(parsed, &request);
+        let json: serde_json::Value = serde_json::from_str(&command.to_line().unwrap()).unwrap();
+        assert_eq!((json["type"].as_str(), json["request"]["trade_type"].as_i64()), (Some("trade"), Some(66)));
+
+        // Debug 输出不包含密码
+        let login = BridgeCommand::from_frame(&OutboundFrame::new(1, 1, packet::encode_password("secret")));
+        assert!(!format!("{:?}", login).contains("secret"));
+        let BridgeCommand::Login { password } = login else { unreachable!() };
+        assert_eq!(password.expose_secret(), "secret");
+    }
+}
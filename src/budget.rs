@@ -0,0 +1,92 @@
+//! 协作式工作预算
+//!
+//! 几十个客户端共享一个 Tokio 运行时时，一个连接持续收到大批数据 (如登录后的订单历史、
+//! 大量报价) 会在读取/解析循环中长时间占用工作线程，其他客户端的读取和心跳随之延迟。
+//!
+//! 每个读取任务持有一个 `WorkBudget`: 处理的帧数或占用时间达到预算后主动让出 (`yield_now`)，
+//! 把单个客户端对其他任务造成的延迟限制在大约一个时间片内。预算通过
+//! `Mt4ClientBuilder::read_budget()` 设置。
+
+use std::time::{Duration, Instant};
+
+/// 默认时间片
+pub const DEFAULT_TIME_SLICE: Duration = Duration::from_millis(2);
+
+/// 默认每个时间片最多处理的帧数
+pub const DEFAULT_FRAMES_PER_SLICE: usize = 32;
+
+/// 读取任务的工作预算
+#[derive(Debug, Clone)]
+pub struct WorkBudget {
+    time_slice: Duration,
+    max_units: usize,
+    units: usize,
+    started: Instant,
+}
+
+impl Default for WorkBudget {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIME_SLICE, DEFAULT_FRAMES_PER_SLICE)
+    }
+}
+
+impl WorkBudget {
+    /// 创建预算: 每个时间片最多占用 `time_slice`、处理 `max_units` 个单位 (至少为 1)
+    pub fn new(time_slice: Duration, max_units: usize) -> Self {
+        Self { time_slice, max_units: max_units.max(1), units: 0, started: Instant::now() }
+    }
+
+    /// 预算是否已用完
+    pub fn is_exhausted(&self) -> bool {
+        self.units >= self.max_units || self.started.elapsed() >= self.time_slice
+    }
+
+    /// 在处理一个单位之前调用: 预算用完时先让出执行权，再开始新的时间片
+    pub async fn consume(&mut self) {
+        if self.is_exhausted() {
+            tokio::task::yield_now().await;
+            self.units = 0;
+            self.started = Instant::now();
+        }
+        self.units += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模拟解析耗时的忙等
+    fn busy(duration: Duration) {
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            std::hint::spin_loop();
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_bounded_cross_client_latency() {
+        // 一个客户端连续解析 100 帧 (每帧 1ms)，同一线程上的另一个客户端测量自己两次运行的最大间隔
+        let heavy = tokio::spawn(async {
+            let mut budget = WorkBudget::new(Duration::from_millis(2), 32);
+            for _ in 0..100 {
+                budget.consume().await;
+                busy(Duration::from_millis(1));
+            }
+        });
+        let light = tokio::spawn(async move {
+            let mut last = Instant::now();
+            let mut max_gap = Duration::ZERO;
+            while !heavy.is_finished() {
+                tokio::task::yield_now().await;
+                max_gap = max_gap.max(last.elapsed());
+                last = Instant::now();
+            }
+            max_gap
+        });
+
+        // 没有预算时间隔约为 100ms；有预算时约为一个时间片加一帧
+        let max_gap = light.await.unwrap();
+        assert!(max_gap < Duration::from_millis(25), "max gap {:?}", max_gap);
+    }
+}
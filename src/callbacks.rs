@@ -0,0 +1,152 @@
+//! 回调式事件处理
+//!
+//! 部分集成 (GUI 框架、FFI) 难以驱动异步的拉取循环。`Mt4Client::on_quote()` / `on_order_update()` /
+//! `on_account()` / `on_disconnect()` 注册的回调在事件发出时直接调用:
+//!
+//! ```no_run
+//! # fn example(client: &mt4_client::Mt4Client) {
+//! let id = client.on_quote(|quote| println!("{} {}/{}", quote.symbol, quote.bid, quote.ask));
+//! client.on_disconnect(|| eprintln!("disconnected"));
+//! // ...
+//! client.remove_callback(id);
+//! # }
+//! ```
+//!
+//! - 回调在发出事件的任务中同步执行 (读取任务收到的事件在读取任务中执行)，耗时操作应交给其他线程，
+//!   否则会延迟后续帧的处理
+//! - 回调 panic 时记录错误并继续，不影响读取任务
+//! - 回调与 `next_event()` / `subscribe()` 互不影响，同一事件都会收到
+
+use crate::client::Mt4Event;
+use crate::types::{AccountInfo, OrderUpdate, Quote};
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// 回调注册 ID (用于 `Mt4Client::remove_callback()`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallbackId(u64);
+
+type Handler<T> = Arc<dyn Fn(&T) + Send + Sync>;
+
+/// 已注册的回调
+#[derive(Default)]
+pub(crate) struct Callbacks {
+    next_id: AtomicU64,
+    quote: RwLock<Vec<(CallbackId, Handler<Quote>)>>,
+    order_update: RwLock<Vec<(CallbackId, Handler<OrderUpdate>)>>,
+    account: RwLock<Vec<(CallbackId, Handler<AccountInfo>)>>,
+    disconnect: RwLock<Vec<(CallbackId, Handler<()>)>>,
+}
+
+pub(crate) type SharedCallbacks = Arc<Callbacks>;
+
+impl fmt::Debug for Callbacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Callbacks")
+            .field("quote", &count(&self.quote))
+            .field("order_update", &count(&self.order_update))
+            .field("account", &count(&self.account))
+            .field("disconnect", &count(&self.disconnect))
+            .finish()
+    }
+}
+
+impl Callbacks {
+    pub(crate) fn on_quote(&self, handler: impl Fn(&Quote) + Send + Sync + 'static) -> CallbackId {
+        self.register(&self.quote, Arc::new(handler))
+    }
+
+    pub(crate) fn on_order_update(&self, handler: impl Fn(&OrderUpdate) + Send + Sync + 'static) -> CallbackId {
+        self.register(&self.order_update, Arc::new(handler))
+    }
+
+    pub(crate) fn on_account(&self, handler: impl Fn(&AccountInfo) + Send + Sync + 'static) -> CallbackId {
+        self.register(&self.account, Arc::new(handler))
+    }
+
+    pub(crate) fn on_disconnect(&self, handler: impl Fn() + Send + Sync + 'static) -> CallbackId {
+        self.register(&self.disconnect, Arc::new(move |_: &()| handler()))
+    }
+
+    /// 移除回调，返回是否存在
+    pub(crate) fn remove(&self, id: CallbackId) -> bool {
+        remove_from(&self.quote, id)
+            || remove_from(&self.order_update, id)
+            || remove_from(&self.account, id)
+            || remove_from(&self.disconnect, id)
+    }
+
+    /// 调用与事件对应的回调
+    pub(crate) fn dispatch(&self, event: &Mt4Event) {
+        match event {
+            Mt4Event::Quote(quote) => invoke(&self.quote, quote),
+            Mt4Event::OrderUpdates(updates) => {
+                for update in updates {
+                    invoke(&self.order_update, update);
+                }
+            }
+            Mt4Event::AccountInfo(account) => invoke(&self.account, account),
+            Mt4Event::Disconnected => invoke(&self.disconnect, &()),
+            _ => {}
+        }
+    }
+
+    fn register<T>(&self, list: &RwLock<Vec<(CallbackId, Handler<T>)>>, handler: Handler<T>) -> CallbackId {
+        let id = CallbackId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        if let Ok(mut list) = list.write() {
+            list.push((id, handler));
+        }
+        id
+    }
+}
+
+fn count<T>(list: &RwLock<Vec<(CallbackId, Handler<T>)>>) -> usize {
+    list.read().map(|list| list.len()).unwrap_or(0)
+}
+
+fn remove_from<T>(list: &RwLock<Vec<(CallbackId, Handler<T>)>>, id: CallbackId) -> bool {
+    let Ok(mut list) = list.write() else { return false };
+    let before = list.len();
+    list.retain(|(existing, _)| *existing != id);
+    list.len() != before
+}
+
+/// 依次调用回调 (先复制列表，回调中可以注册或移除回调)
+fn invoke<T>(list: &RwLock<Vec<(CallbackId, Handler<T>)>>, value: &T) {
+    let handlers: Vec<Handler<T>> = match list.read() {
+        Ok(list) if !list.is_empty() => list.iter().map(|(_, h)| h.clone()).collect(),
+        _ => return,
+    };
+    for handler in handlers {
+        if catch_unwind(AssertUnwindSafe(|| handler(value))).is_err() {
+            tracing::error!("Event callback panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_dispatch_and_remove() {
+        let callbacks = Callbacks::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let id = callbacks.on_quote(move |q| log.lock().unwrap().push(q.symbol.clone()));
+        let log = seen.clone();
+        callbacks.on_disconnect(move || log.lock().unwrap().push("disconnected".to_string()));
+        callbacks.on_account(|_| panic!("callback bug"));
+
+        let quote = Quote { symbol: "EURUSD".to_string(), bid: 1.1, ask: 1.1, time: 0 };
+        callbacks.dispatch(&Mt4Event::Quote(quote.clone()));
+        callbacks.dispatch(&Mt4Event::AccountInfo(AccountInfo::default()));
+        callbacks.dispatch(&Mt4Event::Disconnected);
+        assert!(callbacks.remove(id) && !callbacks.remove(id));
+        callbacks.dispatch(&Mt4Event::Quote(quote));
+        assert_eq!(*seen.lock().unwrap(), ["EURUSD", "disconnected"]);
+    }
+}
@@ -0,0 +1,224 @@
+//! K线本地缓存
+//!
+//! 经纪商会限制重复的整段K线下载。开启缓存 (`Mt4Client::enable_candle_cache()`) 后，
+//! `Mt4Client::request_candles()` 先从本地读取已下载的区间，只向服务器请求缺失的部分，
+//! 新下载的K线写回缓存。需要开启 `experimental-chart` 特性 (Command 11 布局尚未确认，见 `chart` 模块)。
+//!
+//! - 每个品种 / 周期一个文件: `<目录>/<品种>_<周期分钟数>.mt4c`
+//! - 文件记录已下载的时间区间，区间内没有K线 (休市) 也不会重复请求
+//! - 未收盘的K线不写入缓存: 已知服务器时间时，当前周期开盘之后的部分视为未下载；
+//!   否则最后一根K线及之后的部分视为未下载
+//!
+//! 文件格式 (小端):
+//!
+//! | 偏移 | 内容 |
+//! |------|------|
+//! | 0-3  | `MT4C` |
+//! | 4-7  | 版本 (u32，目前为 1) |
+//! | 8-11 | 区间数 N (u32) |
+//! | 12-  | N 个区间 (from: i64, to: i64) |
+//! | 之后 | K线 (44 字节 RateInfo 结构，与 Command 11 响应相同)，按时间升序 |
+
+use crate::error::{Mt4Error, Result};
+use crate::protocol::{Timeframe, CANDLE_SIZE};
+use crate::types::Candle;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 文件头标识
+const MAGIC: &[u8; 4] = b"MT4C";
+
+/// 文件格式版本
+const VERSION: u32 = 1;
+
+/// 一个品种 / 周期的缓存内容
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CachedCandles {
+    /// 已下载的时间区间 [from, to) (升序，互不相接)
+    pub covered: Vec<(i64, i64)>,
+    /// K线 (时间升序，不重复)
+    pub candles: Vec<Candle>,
+}
+
+impl CachedCandles {
+    /// [from, to) 中尚未下载的区间
+    pub fn missing(&self, from: i64, to: i64) -> Vec<(i64, i64)> {
+        let mut gaps = Vec::new();
+        let mut cursor = from;
+        for &(start, end) in &self.covered {
+            if start >= to {
+                break;
+            }
+            if end <= cursor {
+                continue;
+            }
+            if start > cursor {
+                gaps.push((cursor, start));
+            }
+            cursor = end;
+        }
+        if cursor < to {
+            gaps.push((cursor, to));
+        }
+        gaps
+    }
+
+    /// 记录区间 [from, to) 的下载结果 (替换该区间内已缓存的K线，区间外的K线忽略)
+    pub fn insert(&mut self, from: i64, to: i64, candles: impl IntoIterator<Item = Candle>) {
+        if from >= to {
+            return;
+        }
+        self.candles.retain(|c| c.time < from || c.time >= to);
+        self.candles.extend(candles.into_iter().filter(|c| c.time >= from && c.time < to));
+        self.candles.sort_by_key(|c| c.time);
+        self.candles.dedup_by_key(|c| c.time);
+
+        self.covered.push((from, to));
+        self.covered.sort_unstable();
+        let mut merged: Vec<(i64, i64)> = Vec::with_capacity(self.covered.len());
+        for &(start, end) in &self.covered {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        self.covered = merged;
+    }
+
+    /// [from, to) 内已缓存的K线
+    pub fn range(&self, from: i64, to: i64) -> &[Candle] {
+        let start = self.candles.partition_point(|c| c.time < from);
+        let end = self.candles.partition_point(|c| c.time < to).max(start);
+        &self.candles[start..end]
+    }
+
+    /// 序列化为文件内容
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(12 + self.covered.len() * 16 + self.candles.len() * CANDLE_SIZE);
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&(self.covered.len() as u32).to_le_bytes());
+        for (from, to) in &self.covered {
+            data.extend_from_slice(&from.to_le_bytes());
+            data.extend_from_slice(&to.to_le_bytes());
+        }
+        for candle in &self.candles {
+            data.extend_from_slice(&candle.to_bytes());
+        }
+        data
+    }
+
+    /// 从文件内容解析 (格式不符时返回 None)
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let read_u32 = |offset: usize| Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?));
+        let read_i64 = |offset: usize| Some(i64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?));
+        if data.get(..4)? != MAGIC || read_u32(4)? != VERSION {
+            return None;
+        }
+        let count = read_u32(8)? as usize;
+        let candles_start = 12usize.checked_add(count.checked_mul(16)?)?;
+        if data.len() < candles_start || !(data.len() - candles_start).is_multiple_of(CANDLE_SIZE) {
+            return None;
+        }
+        let covered = (0..count)
+            .map(|i| Some((read_i64(12 + i * 16)?, read_i64(20 + i * 16)?)))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { covered, candles: Candle::parse_all(&data[candles_start..]) })
+    }
+}
+
+/// K线缓存目录
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandleCache {
+    dir: PathBuf,
+}
+
+impl CandleCache {
+    /// 打开缓存目录 (不存在时创建)
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .map_err(|e| Mt4Error::Io(format!("创建K线缓存目录 {} 失败: {}", dir.display(), e)))?;
+        Ok(Self { dir })
+    }
+
+    /// 缓存目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 品种 / 周期的缓存文件路径 (品种中的特殊字符替换为 `_`)
+    pub fn path(&self, symbol: &str, timeframe: Timeframe) -> PathBuf {
+        let symbol: String = symbol
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}_{}.mt4c", symbol, timeframe.minutes()))
+    }
+
+    /// 读取缓存 (文件不存在或已损坏时返回空缓存)
+    pub fn load(&self, symbol: &str, timeframe: Timeframe) -> Result<CachedCandles> {
+        let path = self.path(symbol, timeframe);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(CachedCandles::default()),
+            Err(e) => return Err(Mt4Error::Io(format!("读取K线缓存 {} 失败: {}", path.display(), e))),
+        };
+        Ok(CachedCandles::from_bytes(&data).unwrap_or_else(|| {
+            tracing::warn!("Ignoring corrupt candle cache {}", path.display());
+            CachedCandles::default()
+        }))
+    }
+
+    /// 写入缓存 (先写临时文件再替换，写入中途崩溃不会损坏原文件)
+    pub fn store(&self, symbol: &str, timeframe: Timeframe, cached: &CachedCandles) -> Result<()> {
+        let path = self.path(symbol, timeframe);
+        let tmp = path.with_extension("mt4c.tmp");
+        fs::write(&tmp, cached.to_bytes())
+            .and_then(|_| fs::rename(&tmp, &path))
+            .map_err(|e| Mt4Error::Io(format!("写入K线缓存 {} 失败: {}", path.display(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(time: i64) -> Candle {
+        Candle { time, open: 1.0, high: 1.5, low: 0.5, close: 1.2, volume: 10.0 }
+    }
+
+    #[test]
+    fn test_missing_and_insert() {
+        let mut cached = CachedCandles::default();
+        assert_eq!(cached.missing(0, 600), vec![(0, 600)]);
+
+        cached.insert(120, 300, [candle(60), candle(120), candle(180), candle(240)]);
+        cached.insert(400, 500, []);
+        assert_eq!(cached.missing(0, 600), vec![(0, 120), (300, 400), (500, 600)]);
+        assert_eq!(cached.range(0, 600).iter().map(|c| c.time).collect::<Vec<_>>(), vec![120, 180, 240]);
+
+        // 相接的区间合并
+        cached.insert(300, 400, [candle(300)]);
+        assert_eq!(cached.covered, vec![(120, 500)]);
+        assert!(cached.missing(150, 450).is_empty());
+    }
+
+    #[test]
+    fn test_store_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("mt4_candle_cache_{}", std::process::id()));
+        let cache = CandleCache::open(&dir).unwrap();
+        assert_eq!(cache.load("EURUSD", Timeframe::M1).unwrap(), CachedCandles::default());
+
+        let mut cached = CachedCandles::default();
+        cached.insert(0, 180, [candle(0), candle(60), candle(120)]);
+        cache.store("EURUSD", Timeframe::M1, &cached).unwrap();
+        assert_eq!(cache.load("EURUSD", Timeframe::M1).unwrap(), cached);
+        assert!(cache.path("#US30", Timeframe::H1).ends_with("_US30_60.mt4c"));
+
+        // 损坏的文件按空缓存处理
+        fs::write(cache.path("EURUSD", Timeframe::M1), b"MT4C").unwrap();
+        assert_eq!(cache.load("EURUSD", Timeframe::M1).unwrap(), CachedCandles::default());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
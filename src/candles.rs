@@ -0,0 +1,176 @@
+//! 报价合成K线
+//!
+//! `CandleAggregator` 按任意周期 (包括 M2、H6 等非标准周期) 把报价合成为 OHLC K线，
+//! 策略可以直接按K线运行，不必反复请求K线历史:
+//!
+//! - K线按 bid 价格合成，成交量为报价笔数 (tick volume)
+//! - 开盘时间按周期对齐到 Unix 纪元 (与服务器时间相同的时区)
+//! - 一根K线在该品种下一个周期的第一条报价到达时收盘；没有新报价时可调用 `on_time()` 按时间收盘
+//!
+//! 通过 `Mt4Client::aggregate_candles()` 开启后，客户端收到报价时自动合成，
+//! 收盘的K线作为 `Mt4Event::CandleClosed` 发出 (`Strategy::on_candle` 回调):
+//!
+//! ```no_run
+//! # async fn example(client: &mut mt4_client::Mt4Client) {
+//! use mt4_client::Mt4Event;
+//! use std::time::Duration;
+//!
+//! client.aggregate_candles(Duration::from_secs(120));
+//! while let Some(event) = client.next_event().await {
+//!     if let Mt4Event::CandleClosed(bar) = event {
+//!         println!("{} M2 close {}", bar.symbol, bar.candle.close);
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::types::{Candle, Quote};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// 收盘的K线
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CandleClosed {
+    /// 品种
+    pub symbol: String,
+    /// 周期 (秒)
+    pub period_secs: i64,
+    /// K线
+    pub candle: Candle,
+}
+
+/// 报价K线合成器
+#[derive(Debug, Clone, Default)]
+pub struct CandleAggregator {
+    /// 周期 (秒，去重)
+    periods: Vec<i64>,
+    /// (品种, 周期) -> 未收盘的K线
+    bars: HashMap<(String, i64), Candle>,
+}
+
+impl CandleAggregator {
+    /// 创建合成器 (周期不足 1 秒时按 1 秒处理)
+    pub fn new(periods: impl IntoIterator<Item = Duration>) -> Self {
+        let mut aggregator = Self::default();
+        for period in periods {
+            aggregator.add_period(period);
+        }
+        aggregator
+    }
+
+    /// 添加周期
+    pub fn add_period(&mut self, period: Duration) {
+        let secs = (period.as_secs() as i64).max(1);
+        if !self.periods.contains(&secs) {
+            self.periods.push(secs);
+        }
+    }
+
+    /// 移除周期 (丢弃未收盘的K线)，返回是否存在
+    pub fn remove_period(&mut self, period: Duration) -> bool {
+        let secs = (period.as_secs() as i64).max(1);
+        let before = self.periods.len();
+        self.periods.retain(|p| *p != secs);
+        self.bars.retain(|(_, p), _| *p != secs);
+        self.periods.len() != before
+    }
+
+    /// 正在合成的周期 (秒，按添加顺序)
+    pub fn periods(&self) -> &[i64] {
+        &self.periods
+    }
+
+    /// 是否没有任何周期
+    pub fn is_empty(&self) -> bool {
+        self.periods.is_empty()
+    }
+
+    /// 品种在该周期的未收盘K线
+    pub fn current(&self, symbol: &str, period: Duration) -> Option<Candle> {
+        let secs = (period.as_secs() as i64).max(1);
+        self.bars.get(&(symbol.to_string(), secs)).copied()
+    }
+
+    /// 处理一条报价，返回因此收盘的K线
+    ///
+    /// 早于当前K线开盘时间的报价忽略
+    pub fn on_quote(&mut self, quote: &Quote) -> Vec<CandleClosed> {
+        let mut closed = Vec::new();
+        for &period in &self.periods {
+            let open_time = quote.time - quote.time.rem_euclid(period);
+            let key = (quote.symbol.clone(), period);
+            match self.bars.get_mut(&key) {
+                Some(bar) if bar.time == open_time => {
+                    bar.high = bar.high.max(quote.bid);
+                    bar.low = bar.low.min(quote.bid);
+                    bar.close = quote.bid;
+                    bar.volume += 1.0;
+                }
+                Some(bar) if bar.time > open_time => {}
+                current => {
+                    let price = quote.bid;
+                    let bar = Candle { time: open_time, open: price, high: price, low: price, close: price, volume: 1.0 };
+                    if let Some(previous) = current.map(|current| std::mem::replace(current, bar)) {
+                        closed.push(CandleClosed { symbol: quote.symbol.clone(), period_secs: period, candle: previous });
+                    } else {
+                        self.bars.insert(key, bar);
+                    }
+                }
+            }
+        }
+        closed
+    }
+
+    /// 收盘所有在 `now` (服务器时间，秒) 之前结束的K线，按收盘时间排序
+    pub fn on_time(&mut self, now: i64) -> Vec<CandleClosed> {
+        let due: Vec<(String, i64)> =
+            self.bars.iter().filter(|((_, period), bar)| bar.time + period <= now).map(|(key, _)| key.clone()).collect();
+        let mut closed: Vec<CandleClosed> = due
+            .into_iter()
+            .filter_map(|key| {
+                let candle = self.bars.remove(&key)?;
+                Some(CandleClosed { symbol: key.0, period_secs: key.1, candle })
+            })
+            .collect();
+        // 按收盘时间排序
+        closed.sort_by(|a, b| {
+            (a.candle.time + a.period_secs, &a.symbol).cmp(&(b.candle.time + b.period_secs, &b.symbol))
+        });
+        closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(bid: f64, time: i64) -> Quote {
+        Quote { symbol: "EURUSD".to_string(), bid, ask: bid + 0.0001, time }
+    }
+
+    #[test]
+    fn test_aggregates_custom_periods() {
+        // M2 和 H6
+        let mut aggregator = CandleAggregator::new([Duration::from_secs(120), Duration::from_secs(6 * 3600)]);
+        for (bid, time) in [(1.10, 0), (1.12, 30), (1.09, 60), (1.11, 119)] {
+            assert!(aggregator.on_quote(&quote(bid, time)).is_empty());
+        }
+        let closed = aggregator.on_quote(&quote(1.13, 125));
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].period_secs, 120);
+        assert_eq!(closed[0].candle, Candle { time: 0, open: 1.10, high: 1.12, low: 1.09, close: 1.11, volume: 4.0 });
+
+        // 迟到的报价不影响已开始的 M2 K线，仍计入 H6 K线
+        assert!(aggregator.on_quote(&quote(1.05, 100)).is_empty());
+        let m2 = aggregator.current("EURUSD", Duration::from_secs(120)).unwrap();
+        assert_eq!((m2.time, m2.low, m2.volume), (120, 1.13, 1.0));
+        let h6 = aggregator.current("EURUSD", Duration::from_secs(6 * 3600)).unwrap();
+        assert_eq!((h6.high, h6.low, h6.volume), (1.13, 1.05, 6.0));
+
+        // 按时间收盘
+        let closed = aggregator.on_time(6 * 3600);
+        assert_eq!(closed.iter().map(|c| c.period_secs).collect::<Vec<_>>(), vec![120, 6 * 3600]);
+        assert!(aggregator.current("EURUSD", Duration::from_secs(120)).is_none());
+    }
+}
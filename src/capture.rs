@@ -0,0 +1,202 @@
+//! 原始数据包抓取
+//!
+//! 把连接上收发的每个数据包同时以加密和解密两种形式写入 JSON Lines 文件，便于向经纪商
+//! 报告协议问题或离线分析:
+//!
+//! ```text
+//! {"time":1704153600.125,"elapsed_ms":0,"direction":"out","command":0,"encrypted":"50000000...","decrypted":null}
+//! {"time":1704153600.342,"elapsed_ms":217,"direction":"in","command":0,"error_code":0,"encrypted":"10000000...","decrypted":""}
+//! ```
+//!
+//! - `encrypted` 为完整数据包 (8 字节头 + 密文) 的十六进制字符串
+//! - `decrypted` 为解密后的帧数据 (去掉随机字节、命令和错误码)，无法解密时为 null
+//! - 认证命令 (token 和密码) 只记录密文
+//!
+//! 抓取通过 `Mt4Client::start_capture()` 开启，与会话录制 (`session` 模块) 相互独立。
+
+use crate::error::{Mt4Error, Result};
+use crate::protocol::Command;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// 客户端读写任务共用的抓取器
+pub(crate) type SharedCapture = Arc<std::sync::Mutex<Option<PacketCapture>>>;
+
+/// 数据包方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureDirection {
+    /// 服务器 -> 客户端
+    #[serde(rename = "in")]
+    Inbound,
+    /// 客户端 -> 服务器
+    #[serde(rename = "out")]
+    Outbound,
+}
+
+/// 抓取的数据包
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedPacket {
+    /// 本地时间 (Unix 时间戳，秒)
+    pub time: f64,
+    /// 距抓取开始的时间 (毫秒)
+    pub elapsed_ms: u64,
+    /// 方向
+    pub direction: CaptureDirection,
+    /// 命令 (无法解密时为 None)
+    pub command: Option<u16>,
+    /// 错误码 (仅入站)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<u8>,
+    /// 完整数据包
+    #[serde(with = "crate::session::hex_bytes")]
+    pub encrypted: Vec<u8>,
+    /// 解密后的帧数据
+    #[serde(with = "hex_bytes_opt")]
+    pub decrypted: Option<Vec<u8>>,
+}
+
+/// 数据包抓取器
+#[derive(Debug)]
+pub struct PacketCapture {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    started: Instant,
+    packets: usize,
+}
+
+impl PacketCapture {
+    /// 创建抓取文件 (已存在时覆盖)
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::create(&path)
+            .map_err(|e| Mt4Error::Io(format!("创建抓取文件 {} 失败: {}", path.display(), e)))?;
+        Ok(Self { path, writer: BufWriter::new(file), started: Instant::now(), packets: 0 })
+    }
+
+    /// 记录一个入站数据包，`frame` 为解密结果 (命令, 错误码, 数据)
+    pub fn record_inbound(&mut self, packet: &[u8], frame: Option<(u16, u8, &[u8])>) -> Result<()> {
+        self.write(CapturedPacket {
+            time: unix_time(),
+            elapsed_ms: self.elapsed_ms(),
+            direction: CaptureDirection::Inbound,
+            command: frame.map(|(command, _, _)| command),
+            error_code: frame.map(|(_, error_code, _)| error_code),
+            encrypted: packet.to_vec(),
+            decrypted: frame.map(|(_, _, data)| data.to_vec()),
+        })
+    }
+
+    /// 记录一个出站数据包，`frame` 为解密结果 (命令, 数据)
+    pub fn record_outbound(&mut self, packet: &[u8], frame: Option<(u16, &[u8])>) -> Result<()> {
+        let command = frame.map(|(command, _)| command);
+        let secret = matches!(command, Some(c) if c == Command::AuthToken as u16 || c == Command::AuthPassword as u16);
+        self.write(CapturedPacket {
+            time: unix_time(),
+            elapsed_ms: self.elapsed_ms(),
+            direction: CaptureDirection::Outbound,
+            command,
+            error_code: None,
+            encrypted: packet.to_vec(),
+            decrypted: frame.filter(|_| !secret).map(|(_, data)| data.to_vec()),
+        })
+    }
+
+    /// 已抓取的数据包数
+    pub fn packets(&self) -> usize {
+        self.packets
+    }
+
+    /// 抓取文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// 写入一行 (立即刷新，进程崩溃时不丢失已抓取的数据包)
+    fn write(&mut self, packet: CapturedPacket) -> Result<()> {
+        let line = serde_json::to_string(&packet)
+            .map_err(|e| Mt4Error::Serialization(format!("序列化抓取数据包失败: {}", e)))?;
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| Mt4Error::Io(format!("写入抓取文件 {} 失败: {}", self.path.display(), e)))?;
+        self.packets += 1;
+        Ok(())
+    }
+}
+
+/// 读取抓取文件中的所有数据包 (忽略空行)
+pub fn read_capture(path: impl AsRef<Path>) -> Result<Vec<CapturedPacket>> {
+    let path = path.as_ref();
+    let file = File::open(path)
+        .map_err(|e| Mt4Error::Io(format!("打开抓取文件 {} 失败: {}", path.display(), e)))?;
+    let mut packets = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| Mt4Error::Io(format!("读取抓取文件失败: {}", e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let packet = serde_json::from_str(&line)
+            .map_err(|e| Mt4Error::Serialization(format!("抓取文件第 {} 行格式错误: {}", i + 1, e)))?;
+        packets.push(packet);
+    }
+    Ok(packets)
+}
+
+fn unix_time() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or(0.0)
+}
+
+/// 以十六进制字符串序列化可选字节 (None 为 null)
+mod hex_bytes_opt {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &Option<Vec<u8>>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match data {
+            Some(data) => serializer.serialize_str(&hex::encode(data)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| hex::decode(text).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_and_read() {
+        let path = std::env::temp_dir().join(format!("mt4_capture_{}.jsonl", std::process::id()));
+        let mut capture = PacketCapture::create(&path).unwrap();
+        capture.record_outbound(&[1, 2], Some((Command::AuthPassword as u16, b"secret"))).unwrap();
+        capture.record_outbound(&[3, 4], Some((Command::Ping as u16, &[]))).unwrap();
+        capture.record_inbound(&[5, 6], Some((10, 2, &[0xde, 0xad]))).unwrap();
+        capture.record_inbound(&[7], None).unwrap();
+        assert_eq!(capture.packets(), 4);
+        drop(capture);
+
+        let packets = read_capture(&path).unwrap();
+        assert_eq!(packets.len(), 4);
+        // 密码只记录密文
+        assert_eq!((packets[0].command, &packets[0].decrypted), (Some(1), &None));
+        assert_eq!(packets[1].decrypted, Some(Vec::new()));
+        assert_eq!(packets[2].direction, CaptureDirection::Inbound);
+        assert_eq!((packets[2].error_code, packets[2].decrypted.as_deref()), (Some(2), Some(&[0xde, 0xad][..])));
+        assert_eq!((packets[3].command, &packets[3].encrypted), (None, &vec![7]));
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains(&hex::encode(b"secret")) && text.contains("\"direction\":\"in\""));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,188 @@
+//! K线历史分页下载
+//!
+//! 多年的 M1 数据无法在一个 ChartRequest (Command 11) 中取回。`ChartDownload` 将时间区间
+//! 按每页最多 `page_bars` 根K线切分，由 `Mt4Client::download_candles()` 逐页请求，
+//! 每页完成后通过回调报告进度，回调返回 `ControlFlow::Break` 即可中途取消。
+//!
+//! 24 字节请求和 44 字节 RateInfo 响应的布局来自 MT4 `.hst` 历史文件格式的推断，没有网页端脚本
+//! 或抓包依据，因此只在开启 `experimental-chart` 特性时编译。抓到真实的 Command 11 响应后应加入
+//! `fixtures/captures/` 并按终端显示的K线核对。
+
+use crate::protocol::Timeframe;
+use crate::types::{Candle, ChartRequest};
+
+/// 默认每页K线数量
+pub const DEFAULT_PAGE_BARS: usize = 1000;
+
+/// 单页请求的超时时间 (秒)
+pub const CHART_PAGE_TIMEOUT_SECS: u64 = 30;
+
+/// K线分页下载参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartDownload {
+    /// 品种
+    pub symbol: String,
+    /// 周期
+    pub timeframe: Timeframe,
+    /// 开始时间 (服务器时间，秒)
+    pub from: i64,
+    /// 结束时间 (服务器时间，秒)
+    pub to: i64,
+    /// 每页最多K线数量
+    pub page_bars: usize,
+}
+
+impl ChartDownload {
+    /// 创建下载参数 (每页 `DEFAULT_PAGE_BARS` 根)
+    pub fn new(symbol: &str, timeframe: Timeframe, from: i64, to: i64) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            timeframe,
+            from,
+            to,
+            page_bars: DEFAULT_PAGE_BARS,
+        }
+    }
+
+    /// 设置每页K线数量
+    pub fn with_page_bars(mut self, page_bars: usize) -> Self {
+        self.page_bars = page_bars.max(1);
+        self
+    }
+
+    /// 按时间切分的分页请求 (首尾相接，不重叠)
+    pub fn pages(&self) -> Vec<ChartRequest> {
+        let span = self.timeframe.seconds() * self.page_bars.max(1) as i64;
+        let mut pages = Vec::new();
+        let mut from = self.from;
+        while from < self.to {
+            let to = (from + span).min(self.to);
+            pages.push(ChartRequest {
+                symbol: self.symbol.clone(),
+                timeframe: self.timeframe,
+                from,
+                to,
+            });
+            from = to;
+        }
+        pages
+    }
+}
+
+/// 下载进度
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChartProgress {
+    /// 已完成页数
+    pub pages_done: usize,
+    /// 总页数
+    pub pages_total: usize,
+    /// 已获取K线数量 (去重后)
+    pub bars_fetched: usize,
+    /// 预计剩余K线数量
+    ///
+    /// 按已下载区间的实际K线密度推算 (休市时段没有K线)，尚无数据时按周期上限估算
+    pub estimated_remaining_bars: usize,
+    /// 已覆盖到的时间 (服务器时间，秒)
+    pub covered_to: i64,
+}
+
+impl ChartProgress {
+    /// 根据已完成的区间计算进度
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn new(
+        download: &ChartDownload,
+        pages_done: usize,
+        pages_total: usize,
+        bars_fetched: usize,
+        covered_to: i64,
+    ) -> Self {
+        let covered = (covered_to - download.from).max(0) as f64;
+        let remaining = (download.to - covered_to).max(0) as f64;
+        let estimated_remaining_bars = if bars_fetched > 0 && covered > 0.0 {
+            (bars_fetched as f64 * remaining / covered).round() as usize
+        } else {
+            (remaining / download.timeframe.seconds() as f64).ceil() as usize
+        };
+        Self {
+            pages_done,
+            pages_total,
+            bars_fetched,
+            estimated_remaining_bars,
+            covered_to,
+        }
+    }
+
+    /// 完成比例 (0.0 - 1.0，按页数)
+    pub fn fraction(&self) -> f64 {
+        if self.pages_total == 0 {
+            1.0
+        } else {
+            self.pages_done as f64 / self.pages_total as f64
+        }
+    }
+}
+
+/// 分页下载结果
+#[derive(Debug, Clone)]
+pub struct CandleDownload {
+    /// 按时间升序排列的K线 (取消时为已下载部分)
+    pub candles: Vec<Candle>,
+    /// 是否被回调中途取消
+    pub cancelled: bool,
+}
+
+/// 合并一页K线: 丢弃区间外及重复时间的K线，保持时间升序
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn merge_page(candles: &mut Vec<Candle>, page: Vec<Candle>, request: &ChartRequest) {
+    for candle in page {
+        if candle.time < request.from || candle.time >= request.to {
+            continue;
+        }
+        if candles.last().is_some_and(|last| last.time >= candle.time) {
+            if let Err(pos) = candles.binary_search_by_key(&candle.time, |c| c.time) {
+                candles.insert(pos, candle);
+            }
+        } else {
+            candles.push(candle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(time: i64) -> Candle {
+        Candle { time, open: 1.0, high: 1.0, low: 1.0, close: 1.0, volume: 1.0 }
+    }
+
+    #[test]
+    fn test_pages_cover_range() {
+        let download = ChartDownload::new("EURUSD", Timeframe::M1, 0, 25 * 60).with_page_bars(10);
+        let pages = download.pages();
+        assert_eq!(pages.len(), 3);
+        assert_eq!((pages[0].from, pages[0].to), (0, 600));
+        assert_eq!((pages[2].from, pages[2].to), (1200, 1500));
+        assert!(ChartDownload::new("EURUSD", Timeframe::M1, 100, 100).pages().is_empty());
+    }
+
+    #[test]
+    fn test_progress_estimate() {
+        let download = ChartDownload::new("EURUSD", Timeframe::M1, 0, 4000 * 60);
+        // 尚无数据: 按周期上限估算
+        assert_eq!(ChartProgress::new(&download, 0, 4, 0, 0).estimated_remaining_bars, 4000);
+        // 前一半区间只有 1000 根K线 (含休市)，剩余按相同密度估算
+        let progress = ChartProgress::new(&download, 2, 4, 1000, 2000 * 60);
+        assert_eq!(progress.estimated_remaining_bars, 1000);
+        assert_eq!(progress.fraction(), 0.5);
+    }
+
+    #[test]
+    fn test_merge_page_dedup() {
+        let request = ChartRequest { symbol: "EURUSD".to_string(), timeframe: Timeframe::M1, from: 60, to: 300 };
+        let mut candles = vec![candle(60), candle(180)];
+        merge_page(&mut candles, vec![candle(0), candle(120), candle(180), candle(240), candle(300)], &request);
+        let times: Vec<i64> = candles.iter().map(|c| c.time).collect();
+        assert_eq!(times, vec![60, 120, 180, 240]);
+    }
+}
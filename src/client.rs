@@ -1,20 +1,93 @@
 //! MT4 WebSocket 客户端
 
-use crate::api::{Mt4Api, TokenResponse};
-use crate::crypto::Mt4Crypto;
-use crate::error::{Mt4Error, Result};
-use crate::protocol::{Command, AUTH_DATA_SIZE};
-use crate::types::{AccountInfo, Order, OrderUpdate, TradeRequest};
+use crate::api::{ws_host_port, Mt4Api};
+use crate::backfill::BackfillProgress;
+#[cfg(any(feature = "experimental-chart", feature = "experimental-ticks"))]
+use crate::backfill::Backfill;
+#[cfg(feature = "experimental-ticks")]
+use crate::backfill::{tick_request_bytes, TICK_REQUEST_TIMEOUT_SECS};
+use crate::book::{pip_size, PendingBook};
+use crate::breakeven::Breakeven;
+use crate::bridge::{forward_requests, BridgeMessage, BRIDGE_PROTOCOL_VERSION};
+use crate::callbacks::{CallbackId, SharedCallbacks};
+#[cfg(feature = "experimental-chart")]
+use crate::candle_cache::CandleCache;
+use crate::candles::{CandleAggregator, CandleClosed};
+use crate::capture::{PacketCapture, SharedCapture};
+use crate::budget::WorkBudget;
+#[cfg(feature = "experimental-chart")]
+use crate::chart::{merge_page, CandleDownload, ChartDownload, ChartProgress, CHART_PAGE_TIMEOUT_SECS};
+use crate::clock::DriftEstimator;
+use crate::config::{ClientConfig, Mt4ClientBuilder, DEFAULT_SLIPPAGE, DEFAULT_TRADE_TIMEOUT};
+use crate::crypto::{CryptoProvider, Mt4Crypto, SharedCrypto};
+use crate::decoders::{CustomEvent, CustomValue, DecoderRegistry};
+use crate::decrypt::{DecodedFrame, DecryptPipeline};
+use crate::error::{ErrorKind, Mt4Error, Result};
+use crate::events::{
+    event_channel, EventReceiver, EventSender, EventStream, EventSubscription, FilteredSubscribers, QuoteChannels,
+    QuoteReceiver, TimedEvent, EVENT_BROADCAST_CAPACITY,
+};
+use crate::filter::EventFilter;
+use crate::forensics::{install_panic_hook, CrashForensics, SharedForensics};
+use crate::funding::{split_order_updates, BalanceOperation, FundingDetector, FundingOperation};
+use crate::handle::Mt4Handle;
+use crate::history::{merge_orders, range_bytes, HistoryDownload, HISTORY_PAGE_TIMEOUT_SECS};
+use crate::mirror::Mt4Mirror;
+use crate::intents::{unix_now, IntentOutcome, IntentQueue, TradeIntent};
+use crate::lifecycle::{OrderLifecycle, OrderState, OrderTransition};
+use crate::margin::{required_margin, used_margin, MarginAlert, MarginCheck, MarginMonitor, MarginZone};
+use crate::positions::PositionManager;
+use crate::presets::{BrokerPreset, PresetRegistry};
+use crate::packet::{self, OutboundFrame};
+use crate::packet_stats::PacketStats;
+use crate::pips::{currency_pair, point_value_in_account_currency, CrossRates};
+use crate::protocol::{Command, OrderType};
+#[cfg(feature = "experimental-chart")]
+use crate::protocol::Timeframe;
+use crate::proxy::ProxyConfig;
+use crate::quirks::{AccountCalibration, AccountLayout, QuirkRegistry};
+use crate::requote::{is_requote_code, RequotePolicy};
+use crate::risk::{RiskLimits, RiskManager, RiskRule};
+use crate::session::{read_session, SessionRecorder};
+use crate::selftest::SelfTestReport;
+use crate::sizing::position_size;
+use crate::reconcile::StateDivergence;
+use crate::sequence::{MissedUpdates, NotifySequence};
+use crate::snapshot::ClientSnapshot;
+use crate::spread::{SpreadAlert, SpreadMonitor};
+use crate::stats::{TradeStatistics, TradeStatsReport};
+use crate::tap::{RawMessageReceiver, RawTap};
+use crate::telemetry;
+use crate::throttle::{RateBudget, TradeThrottle};
+use crate::trailing::{TrailUpdate, TrailingEngine, TrailingStop};
+use crate::types::{
+    AccountInfo, Candle, ACCOUNT_INFO_SIZE, Order, OrderUpdate, PartialClose, Quote, Symbol,
+    SymbolInfo, Ticket, TimeInForce, TradeRequest, TradeResponse,
+};
+#[cfg(feature = "experimental-chart")]
+use crate::types::ChartRequest;
+#[cfg(feature = "experimental-status")]
+use crate::types::ConnectionStatus;
+use crate::validation::{
+    is_position_modify, validate_expiration, validate_position_stops, validate_trade_request,
+    validate_trade_request_without_spec,
+};
 use crate::LoginCredentials;
-use byteorder::{LittleEndian, WriteBytesExt};
-use futures_util::{SinkExt, StreamExt};
-use std::collections::HashMap;
-use std::io::Cursor;
-use std::sync::atomic::{AtomicI32, Ordering};
+use bytes::Bytes;
+use secrecy::{ExposeSecret, SecretString};
+use futures_util::stream::FuturesUnordered;
+use futures_util::{stream, SinkExt, Stream, StreamExt};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+#[cfg(feature = "experimental-chart")]
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::{mpsc, Mutex, RwLock};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex, RwLock};
+use tokio_tungstenite::tungstenite::Message;
 
 /// 待确认的交易请求
 /// 根据 JS mt4.en.js 第1183行: N[b.kj] = b (待确认请求映射)
@@ -26,8 +99,10 @@ pub struct PendingRequest {
     pub request: TradeRequest,
     /// 创建时间
     pub created_at: Instant,
+    /// 超时时间 (到达时仍未收到响应则视为超时)
+    pub deadline: Instant,
     /// 目标ticket (平仓/取消/修改操作时有值)
-    pub target_ticket: Option<i32>,
+    pub target_ticket: Option<Ticket>,
 }
 
 /// 请求追踪器
@@ -46,7 +121,11 @@ pub struct RequestTracker {
     /// ticket 防重复: ticket -> request_id
     /// 对应 JS 的 E[]
     /// 防止同一个ticket同时有多个操作
-    ticket_locks: RwLock<HashMap<i32, i32>>,
+    ticket_locks: RwLock<HashMap<Ticket, i32>>,
+    /// 等待响应的调用方: request_id -> 结果通知
+    waiters: Mutex<HashMap<i32, oneshot::Sender<Result<TradeResponse>>>>,
+    /// 请求超时 (对应 JS 的 W[] 定时器时长)
+    timeout: Duration,
 }
 
 impl Default for RequestTracker {
@@ -56,25 +135,44 @@ impl Default for RequestTracker {
 }
 
 impl RequestTracker {
-    /// 创建新的请求追踪器
+    /// 创建新的请求追踪器 (超时 180 秒)
     pub fn new() -> Self {
+        Self::with_timeout(DEFAULT_TRADE_TIMEOUT)
+    }
+
+    /// 创建指定请求超时的追踪器
+    pub fn with_timeout(timeout: Duration) -> Self {
         Self {
             // 根据 JS: B.GH = 1000
             next_request_id: AtomicI32::new(1000),
             pending_requests: RwLock::new(HashMap::new()),
             ticket_locks: RwLock::new(HashMap::new()),
+            waiters: Mutex::new(HashMap::new()),
+            timeout,
         }
     }
 
+    /// 请求超时
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
     /// 生成下一个 request_id
     /// 对应 JS: b.kj = B.GH++
     pub fn next_id(&self) -> i32 {
         self.next_request_id.fetch_add(1, Ordering::SeqCst)
     }
 
+    /// 为非交易数据包分配 ID (与 request_id 共用计数器，取低 16 位)
+    ///
+    /// 交易请求的数据包直接使用其 request_id 的低 16 位，不再另外分配
+    pub fn next_packet_id(&self) -> u16 {
+        self.next_id() as u16
+    }
+
     /// 检查ticket是否已被锁定(防止重复操作)
     /// 对应 JS: if (E && E[b.R]) return;
-    pub async fn is_ticket_locked(&self, ticket: i32) -> bool {
+    pub async fn is_ticket_locked(&self, ticket: Ticket) -> bool {
         let locks = self.ticket_locks.read().await;
         locks.contains_key(&ticket)
     }
@@ -83,11 +181,7 @@ impl RequestTracker {
     /// 对应 JS: E[b.R] = b.kj; N[b.kj] = b;
     pub async fn add_pending(&self, request: TradeRequest) -> i32 {
         let request_id = request.request_id;
-        let target_ticket = if request.ticket != 0 {
-            Some(request.ticket)
-        } else {
-            None
-        };
+        let target_ticket = request.ticket.is_set().then_some(request.ticket);
 
         // 如果是针对特定ticket的操作，锁定该ticket
         if let Some(ticket) = target_ticket {
@@ -96,10 +190,12 @@ impl RequestTracker {
         }
 
         // 添加到待确认队列
+        let created_at = Instant::now();
         let pending = PendingRequest {
             request_id,
             request,
-            created_at: Instant::now(),
+            created_at,
+            deadline: created_at + self.timeout,
             target_ticket,
         };
 
@@ -131,6 +227,25 @@ impl RequestTracker {
         }
     }
 
+    /// 注册一个等待者，在收到该 request_id 的交易响应 (或超时) 时得到通知
+    pub async fn register_waiter(&self, request_id: i32) -> oneshot::Receiver<Result<TradeResponse>> {
+        let (tx, rx) = oneshot::channel();
+        self.waiters.lock().await.insert(request_id, tx);
+        rx
+    }
+
+    /// 通知等待者请求结果 (没有等待者时忽略)
+    pub async fn resolve(&self, request_id: i32, result: Result<TradeResponse>) {
+        if let Some(tx) = self.waiters.lock().await.remove(&request_id) {
+            let _ = tx.send(result);
+        }
+    }
+
+    /// 取消等待者 (请求未发出时调用)
+    pub async fn cancel_waiter(&self, request_id: i32) {
+        self.waiters.lock().await.remove(&request_id);
+    }
+
     /// 获取超时的请求 (超过指定时间未确认)
     /// 对应 JS 第1183行的超时处理: setTimeout(..., 180000)
     pub async fn get_timed_out(&self, timeout_secs: u64) -> Vec<PendingRequest> {
@@ -145,15 +260,26 @@ impl RequestTracker {
 
     /// 移除超时的请求并返回
     pub async fn remove_timed_out(&self, timeout_secs: u64) -> Vec<PendingRequest> {
+        let now = Instant::now();
+        self.remove_where(|p| now.duration_since(p.created_at).as_secs() >= timeout_secs).await
+    }
+
+    /// 移除已到超时时间的请求并返回，等待者收到 `Mt4Error::Timeout`
+    ///
+    /// 同时清理调用方已放弃等待 (接收端已丢弃) 的等待者
+    pub async fn remove_expired(&self) -> Vec<PendingRequest> {
+        let now = Instant::now();
+        let expired = self.remove_where(|p| now >= p.deadline).await;
+        self.waiters.lock().await.retain(|_, tx| !tx.is_closed());
+        expired
+    }
+
+    /// 移除符合条件的请求 (清除 ticket 锁并以超时通知等待者)
+    async fn remove_where(&self, expired: impl Fn(&PendingRequest) -> bool) -> Vec<PendingRequest> {
         let mut pending_requests = self.pending_requests.write().await;
         let mut locks = self.ticket_locks.write().await;
-        let now = Instant::now();
 
-        let timed_out: Vec<i32> = pending_requests
-            .iter()
-            .filter(|(_, p)| now.duration_since(p.created_at).as_secs() >= timeout_secs)
-            .map(|(id, _)| *id)
-            .collect();
+        let timed_out: Vec<i32> = pending_requests.iter().filter(|(_, p)| expired(p)).map(|(id, _)| *id).collect();
 
         let mut result = Vec::new();
         for request_id in timed_out {
@@ -167,6 +293,12 @@ impl RequestTracker {
                 result.push(pending);
             }
         }
+        drop(locks);
+        drop(pending_requests);
+
+        for pending in &result {
+            self.resolve(pending.request_id, Err(Mt4Error::Timeout)).await;
+        }
         result
     }
 
@@ -180,6 +312,7 @@ impl RequestTracker {
     pub async fn clear(&self) {
         self.pending_requests.write().await.clear();
         self.ticket_locks.write().await.clear();
+        self.waiters.lock().await.clear();
     }
 }
 
@@ -205,6 +338,8 @@ pub enum Mt4Event {
     /// 历史订单（Command 5 响应，包含已平仓订单）
     /// 这些订单不应触发跟单逻辑，仅用于显示和导出
     HistoryOrders(Vec<Order>),
+    /// 报价 (Command 8 报价快照中的每个品种各一条)
+    Quote(Quote),
     /// 交易成功
     TradeSuccess { request_id: i32, status: i32 },
     /// 交易失败
@@ -216,14 +351,233 @@ pub enum Mt4Event {
         request: TradeRequest,
         elapsed_secs: f64,
     },
+    /// 市价单被重新报价 (135 / 138)，即将按 `RequotePolicy` 以新的价格和滑点重新提交
+    Requote { symbol: Symbol, code: u8, attempt: u32, slippage: i32, price: f64 },
+    /// 开仓请求违反风险限制，未发送
+    RiskRejected { rule: RiskRule, reason: String, request: TradeRequest },
+    /// 离线交易意图已执行
+    IntentExecuted { intent_id: u64, request_id: i32 },
+    /// 离线交易意图的有效条件不再满足，已丢弃
+    IntentExpired { intent_id: u64, reason: String },
+    /// 离线交易意图执行失败
+    IntentFailed { intent_id: u64, message: String },
+    /// 订单生命周期状态变化 (由订单更新推导)
+    OrderStateChanged(OrderTransition),
+    /// 推断的入金/出金或信用增减 (由余额变化推导，见 `funding` 模块)
+    Funding(FundingOperation),
+    /// 服务器推送的余额/信用操作 (订单更新中 cmd 为 6 / 7 的记录，见 `funding` 模块)
+    BalanceOperation(BalanceOperation),
+    /// 订单更新通知序号跳号 (漏收通知，本地持仓状态可能已过期，见 `sequence` 模块)
+    MissedUpdates(MissedUpdates),
+    /// 对账时发现的本地持仓缓存与服务器的不一致 (已按服务器数据修正，见 `reconcile` 模块)
+    StateDivergence(StateDivergence),
+    /// 交易服务器连接状态 (Command 15，`experimental-status` 特性)，断开或维护时应停止交易
+    #[cfg(feature = "experimental-status")]
+    ConnectionStatus(ConnectionStatus),
+    /// 点差超过上限或回落 (见 `spread` 模块)
+    SpreadAlert(SpreadAlert),
+    /// 由报价合成的K线收盘 (见 `candles` 模块)
+    CandleClosed(CandleClosed),
+    /// 历史数据回补进度 (见 `backfill` 模块)
+    BackfillProgress(BackfillProgress),
+    /// 保证金比例降到警告阈值 (见 `margin` 模块)
+    MarginWarning(MarginAlert),
+    /// 保证金比例降到危险阈值，应在强平前减仓
+    MarginCritical(MarginAlert),
+    /// 已平仓交易统计快照 (见 `stats` 模块，按 `set_trade_stats_report_every` 的间隔发出)
+    TradeStats(TradeStatsReport),
     /// 连接断开
     Disconnected,
     /// 错误
     Error(String),
     /// Pong 响应
     Pong,
-    /// 原始消息 (未识别的命令)
-    RawMessage { command: u16, error_code: u8, data: Vec<u8> },
+    /// 原始消息 (未识别的命令；所有命令的原始帧可通过 `raw_messages()` 订阅)
+    /// 数据与解密后的帧共享缓冲区，不复制
+    RawMessage { command: u16, error_code: u8, data: Bytes },
+    /// 自定义解码器解析的命令 (见 `decoders` 模块)
+    Custom(CustomEvent),
+}
+
+impl Mt4Event {
+    /// 事件类型名称 (用于日志、状态页等)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Mt4Event::Connected => "Connected",
+            Mt4Event::Authenticated => "Authenticated",
+            Mt4Event::AuthFailed(_) => "AuthFailed",
+            Mt4Event::AccountInfo(_) => "AccountInfo",
+            Mt4Event::OrderUpdate(_) => "OrderUpdate",
+            Mt4Event::OrderUpdates(_) => "OrderUpdates",
+            Mt4Event::PositionsSnapshot(_) => "PositionsSnapshot",
+            Mt4Event::HistoryOrders(_) => "HistoryOrders",
+            Mt4Event::Quote(_) => "Quote",
+            Mt4Event::TradeSuccess { .. } => "TradeSuccess",
+            Mt4Event::TradeFailed { .. } => "TradeFailed",
+            Mt4Event::TradeTimeout { .. } => "TradeTimeout",
+            Mt4Event::Requote { .. } => "Requote",
+            Mt4Event::RiskRejected { .. } => "RiskRejected",
+            Mt4Event::IntentExecuted { .. } => "IntentExecuted",
+            Mt4Event::IntentExpired { .. } => "IntentExpired",
+            Mt4Event::IntentFailed { .. } => "IntentFailed",
+            Mt4Event::OrderStateChanged(_) => "OrderStateChanged",
+            Mt4Event::Funding(_) => "Funding",
+            Mt4Event::BalanceOperation(_) => "BalanceOperation",
+            Mt4Event::MissedUpdates(_) => "MissedUpdates",
+            Mt4Event::StateDivergence(_) => "StateDivergence",
+            #[cfg(feature = "experimental-status")]
+            Mt4Event::ConnectionStatus(_) => "ConnectionStatus",
+            Mt4Event::SpreadAlert(_) => "SpreadAlert",
+            Mt4Event::CandleClosed(_) => "CandleClosed",
+            Mt4Event::BackfillProgress(_) => "BackfillProgress",
+            Mt4Event::MarginWarning(_) => "MarginWarning",
+            Mt4Event::MarginCritical(_) => "MarginCritical",
+            Mt4Event::TradeStats(_) => "TradeStats",
+            Mt4Event::Disconnected => "Disconnected",
+            Mt4Event::Error(_) => "Error",
+            Mt4Event::Pong => "Pong",
+            Mt4Event::RawMessage { .. } => "RawMessage",
+            Mt4Event::Custom(_) => "Custom",
+        }
+    }
+
+    /// 事件携带的服务器时间 (经纪商时间，秒)
+    ///
+    /// 目前只有订单新建/平仓通知携带可靠的服务器时间
+    pub fn server_time(&self) -> Option<i64> {
+        match self {
+            Mt4Event::OrderUpdate(update) => update.server_time(),
+            Mt4Event::OrderUpdates(updates) => updates.iter().filter_map(|u| u.server_time()).max(),
+            _ => None,
+        }
+    }
+}
+
+/// 部分平仓后等待剩余订单推送的时间 (秒)
+const REMAINDER_WAIT_SECS: u64 = 5;
+
+/// 批量平仓时单个订单遇到重新报价等临时错误的最大尝试次数
+const CLOSE_MAX_ATTEMPTS: u32 = 3;
+
+/// 批量平仓重试间隔 (毫秒)
+const CLOSE_RETRY_DELAY_MS: u64 = 500;
+
+/// 未配置 `auth_timeout` 时，为执行离线意图等待认证的最长时间
+const INTENT_FLUSH_AUTH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 批量平仓失败的订单
+#[derive(Debug)]
+pub struct CloseFailure {
+    /// 订单号
+    pub ticket: Ticket,
+    /// 品种
+    pub symbol: Symbol,
+    /// 最后一次尝试的错误
+    pub error: Mt4Error,
+}
+
+/// 批量平仓结果汇总
+#[derive(Debug, Default)]
+pub struct CloseAllSummary {
+    /// 成功平仓的订单号
+    pub closed: Vec<Ticket>,
+    /// 平仓失败的订单
+    pub failed: Vec<CloseFailure>,
+}
+
+impl CloseAllSummary {
+    /// 是否全部平仓成功
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// 批量修改操作
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModifyAction {
+    /// 设置止损价 (0 表示取消止损)
+    SetStopLoss(f64),
+    /// 设置止盈价 (0 表示取消止盈)
+    SetTakeProfit(f64),
+    /// 同时设置止损和止盈
+    SetStops { sl: f64, tp: f64 },
+    /// 将现有止损移动指定点数 (pip)，正数向盈利方向 (收紧)，负数向亏损方向 (放宽)；没有止损的订单不修改
+    OffsetPips(f64),
+}
+
+impl ModifyAction {
+    /// 计算订单修改后的 (止损, 止盈)
+    fn apply(&self, order: &Order) -> (f64, f64) {
+        let (sl, tp) = match *self {
+            ModifyAction::SetStopLoss(sl) => (sl, order.tp),
+            ModifyAction::SetTakeProfit(tp) => (order.sl, tp),
+            ModifyAction::SetStops { sl, tp } => (sl, tp),
+            ModifyAction::OffsetPips(pips) if order.sl > 0.0 => {
+                let offset = pips * pip_size(order.digits);
+                let sl = if order.order_type.is_buy() { order.sl + offset } else { order.sl - offset };
+                (sl, order.tp)
+            }
+            ModifyAction::OffsetPips(_) => (order.sl, order.tp),
+        };
+        let factor = 10f64.powi(order.digits.max(0));
+        ((sl * factor).round() / factor, (tp * factor).round() / factor)
+    }
+}
+
+/// 批量修改失败的订单
+#[derive(Debug)]
+pub struct ModifyFailure {
+    /// 订单号
+    pub ticket: Ticket,
+    /// 品种
+    pub symbol: Symbol,
+    /// 错误
+    pub error: Mt4Error,
+}
+
+/// 批量修改结果汇总
+#[derive(Debug, Default)]
+pub struct ModifySummary {
+    /// 修改成功的订单号
+    pub modified: Vec<Ticket>,
+    /// 止损/止盈已是目标值、无需修改的订单号
+    pub unchanged: Vec<Ticket>,
+    /// 修改失败的订单
+    pub failed: Vec<ModifyFailure>,
+}
+
+impl ModifySummary {
+    /// 是否全部成功 (包括无需修改的订单)
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// 批量撤单失败的挂单
+#[derive(Debug)]
+pub struct CancelFailure {
+    /// 订单号
+    pub ticket: Ticket,
+    /// 品种
+    pub symbol: Symbol,
+    /// 错误
+    pub error: Mt4Error,
+}
+
+/// 批量撤单结果汇总
+#[derive(Debug, Default)]
+pub struct CancelSummary {
+    /// 撤单成功的订单号
+    pub cancelled: Vec<Ticket>,
+    /// 撤单失败的挂单
+    pub failed: Vec<CancelFailure>,
+}
+
+impl CancelSummary {
+    /// 是否全部撤单成功
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
 }
 
 /// MT4 WebSocket 客户端
@@ -231,31 +585,231 @@ pub struct Mt4Client {
     /// API 客户端
     api: Mt4Api,
     /// 加密器
-    crypto: Arc<Mutex<Mt4Crypto>>,
-    /// WebSocket 写端
-    writer: Option<mpsc::Sender<Vec<u8>>>,
+    crypto: SharedCrypto,
+    /// 写入任务的发送通道 (WebSocket 或终端桥接)
+    writer: Option<mpsc::Sender<OutboundFrame>>,
     /// 事件接收器
-    event_rx: Option<mpsc::Receiver<Mt4Event>>,
-    /// 是否已认证
-    authenticated: bool,
-    /// Token 信息
-    token_info: Option<TokenResponse>,
+    event_rx: Option<EventReceiver>,
+    /// 事件发送端 (用于客户端自身产生的事件)
+    event_tx: Option<EventSender>,
+    /// 服务器时钟偏移估计
+    clock: Arc<std::sync::RwLock<DriftEstimator>>,
+    /// 最近发出的事件 (环形缓冲)
+    recent_events: Arc<std::sync::Mutex<VecDeque<TimedEvent>>>,
+    /// 事件广播 (subscribe 订阅)
+    broadcast: broadcast::Sender<TimedEvent>,
+    /// 最近一次收到的账户信息 (Command 3)
+    account: Arc<RwLock<Option<AccountInfo>>>,
+    /// 最近一次收到的交易服务器连接状态
+    #[cfg(feature = "experimental-status")]
+    connection_status: Arc<RwLock<Option<ConnectionStatus>>>,
+    /// 市场报价中的品种 (认证成功后重新发送)
+    market_watch: Arc<RwLock<BTreeSet<Symbol>>>,
+    /// 最近一次收到的原始账户信息块 (254 字节，用于校准)
+    account_raw: Arc<RwLock<Option<Vec<u8>>>>,
+    /// 当前连接的交易服务器名称
+    server: Option<String>,
+    /// 经纪商差异登记表
+    quirks: Arc<RwLock<QuirkRegistry>>,
+    /// 是否已认证 (由读取任务维护)
+    authenticated: Arc<AtomicBool>,
+    /// 当前连接的登录账号 (token 和会话密钥不在客户端中保留)
+    login: Option<String>,
     /// 请求追踪器 (用于管理待确认请求、防重复、超时)
     /// 根据 JS mt4.en.js 第1216行: N={}, W={}, E={}, B.GH=1000
     request_tracker: Arc<RequestTracker>,
+    /// 当前持仓/挂单 (由 Command 4/10/12 维护)
+    positions: Arc<PositionManager>,
+    /// 订单生命周期状态 (由 Command 4/10 维护)
+    lifecycle: Arc<Mutex<OrderLifecycle>>,
+    /// 品种交易规格缓存: symbol -> SymbolInfo
+    symbols: Arc<RwLock<HashMap<String, SymbolInfo>>>,
+    /// 移动止损 (通过 trail_stop 开启，见 `trailing` 模块)
+    trailing: Arc<Mutex<TrailingEngine>>,
+    /// 等待部分平仓剩余订单: 原 ticket -> 剩余订单通知
+    remainder_waiters: Arc<Mutex<HashMap<Ticket, oneshot::Sender<Order>>>>,
+    /// 当前连接使用的经纪商预设 (每次连接重新选择)
+    broker_preset: Option<BrokerPreset>,
+    /// 离线交易意图队列 (通过 enable_intent_queue 开启)
+    intent_queue: Option<Arc<Mutex<IntentQueue>>>,
+    /// 等待非交易命令响应: command -> 按发送顺序排列的等待者 (error_code, data)
+    command_waiters: CommandWaiters,
+    /// 自定义命令解码器
+    decoders: DecoderRegistry,
+    /// 会话录制器 (通过 record_session 开启)
+    recorder: Arc<std::sync::Mutex<Option<SessionRecorder>>>,
+    /// 原始数据包抓取 (通过 start_capture 开启)
+    capture: SharedCapture,
+    /// 交易日志 (通过 enable_journal 开启)
+    #[cfg(feature = "sqlite")]
+    journal: crate::journal::SharedJournal,
+    /// 按策略的交易频率限制
+    throttle: Arc<std::sync::Mutex<TradeThrottle>>,
+    /// 点差监控 (收到报价时更新)
+    spread_monitor: Arc<std::sync::Mutex<SpreadMonitor>>,
+    /// 实时汇率 (收到报价时更新)
+    cross_rates: Arc<std::sync::Mutex<CrossRates>>,
+    /// 保证金比例监控 (收到账户信息、持仓变化、持仓重新估值时更新)
+    margin_monitor: Arc<std::sync::Mutex<MarginMonitor>>,
+    /// 单品种报价通道 (通过 quotes 订阅)
+    quote_channels: Arc<QuoteChannels>,
+    /// 原始帧监听 (通过 raw_messages 订阅)
+    raw_tap: Arc<RawTap>,
+    /// 按命令统计的入站流量 (通过 stats 读取)
+    packet_stats: Arc<std::sync::Mutex<PacketStats>>,
+    /// 事件回调 (通过 on_quote 等注册)
+    callbacks: SharedCallbacks,
+    /// 带过滤条件的订阅 (通过 subscribe_filtered 创建)
+    filtered_subscribers: Arc<FilteredSubscribers>,
+    /// 已平仓交易统计 (收到平仓通知时更新)
+    trade_stats: Arc<std::sync::Mutex<TradeStatistics>>,
+    /// 报价K线合成 (收到报价时更新)
+    candles: Arc<std::sync::Mutex<CandleAggregator>>,
+    /// K线本地缓存 (通过 enable_candle_cache 开启)
+    #[cfg(feature = "experimental-chart")]
+    candle_cache: std::sync::Mutex<Option<CandleCache>>,
+    /// 风险控制 (通过 set_risk_limits 设置)
+    risk: Arc<Mutex<RiskManager>>,
+    /// 客户端配置
+    config: ClientConfig,
+    /// 最近一次收发数据的时间 (心跳任务据此判断连接是否空闲)
+    last_activity: Arc<std::sync::Mutex<Instant>>,
+    /// 心跳任务
+    heartbeat: Option<tokio::task::JoinHandle<()>>,
+    /// 定期对账任务
+    reconcile_task: Option<tokio::task::JoinHandle<()>>,
+    /// 交易请求超时检测任务
+    timeout_task: Option<tokio::task::JoinHandle<()>>,
+    /// 连接的读取和写入任务 (disconnect 时等待结束)
+    io_tasks: Vec<tokio::task::JoinHandle<()>>,
+    /// 通知写入任务关闭连接
+    shutdown: Option<oneshot::Sender<()>>,
+    /// 崩溃现场记录 (通过 enable_crash_forensics 开启)
+    forensics: SharedForensics,
+    /// 是否已安装崩溃记录的 panic hook
+    forensics_hook: AtomicBool,
+}
+
+/// 非交易命令响应等待表 (服务器按请求顺序响应同一命令)
+type CommandWaiters = Arc<Mutex<HashMap<u16, VecDeque<CommandWaiter>>>>;
+
+/// 超时的命令请求在队列中保留的时间: 此期间到达的下一个响应视为它的迟到响应，不交给之后的请求
+const LATE_RESPONSE_WINDOW: Duration = Duration::from_secs(30);
+
+/// 等待命令响应的请求
+#[derive(Debug)]
+struct CommandWaiter {
+    /// 请求的数据包 ID
+    packet_id: u16,
+    /// 响应接收端 (超时后移除)
+    tx: Option<oneshot::Sender<(u8, Bytes)>>,
+    /// 超过该时间仍未收到响应时视为响应丢失，不再占用队列位置
+    expires: Instant,
 }
 
 impl Mt4Client {
-    /// 创建新的客户端
+    /// 使用默认配置创建客户端
     pub fn new() -> Self {
+        Self::with_config(ClientConfig::default())
+    }
+
+    /// 创建客户端构建器
+    pub fn builder() -> Mt4ClientBuilder {
+        Mt4ClientBuilder::new()
+    }
+
+    /// 离线自检加密和协议假设 (见 `selftest` 模块)，部署时运行以发现损坏的构建
+    pub fn self_test() -> SelfTestReport {
+        crate::selftest::run()
+    }
+
+    /// 使用指定配置创建客户端
+    pub fn with_config(config: ClientConfig) -> Self {
         Self {
-            api: Mt4Api::new(),
-            crypto: Arc::new(Mutex::new(Mt4Crypto::default())),
+            api: Mt4Api::with_base_url(&config.base_url),
+            crypto: Arc::new(RwLock::new(Box::new(Mt4Crypto::default()))),
             writer: None,
             event_rx: None,
-            authenticated: false,
-            token_info: None,
-            request_tracker: Arc::new(RequestTracker::new()),
+            event_tx: None,
+            clock: Arc::new(std::sync::RwLock::new(DriftEstimator::default())),
+            recent_events: Arc::new(std::sync::Mutex::new(VecDeque::new())),
+            broadcast: broadcast::channel(EVENT_BROADCAST_CAPACITY).0,
+            account: Arc::new(RwLock::new(None)),
+            account_raw: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "experimental-status")]
+            connection_status: Arc::new(RwLock::new(None)),
+            market_watch: Arc::new(RwLock::new(BTreeSet::new())),
+            server: None,
+            quirks: Arc::new(RwLock::new(QuirkRegistry::new())),
+            authenticated: Arc::new(AtomicBool::new(false)),
+            login: None,
+            request_tracker: Arc::new(RequestTracker::with_timeout(config.trade_timeout)),
+            positions: Arc::new(PositionManager::new()),
+            lifecycle: Arc::new(Mutex::new(OrderLifecycle::new())),
+            symbols: Arc::new(RwLock::new(HashMap::new())),
+            trailing: Arc::new(Mutex::new(TrailingEngine::new())),
+            remainder_waiters: Arc::new(Mutex::new(HashMap::new())),
+            broker_preset: None,
+            intent_queue: None,
+            command_waiters: Arc::new(Mutex::new(HashMap::new())),
+            decoders: DecoderRegistry::new(),
+            recorder: Arc::new(std::sync::Mutex::new(None)),
+            capture: Arc::new(std::sync::Mutex::new(None)),
+            #[cfg(feature = "sqlite")]
+            journal: Arc::new(std::sync::Mutex::new(None)),
+            throttle: Arc::new(std::sync::Mutex::new(TradeThrottle::new())),
+            spread_monitor: Arc::new(std::sync::Mutex::new(SpreadMonitor::new())),
+            cross_rates: Arc::new(std::sync::Mutex::new(CrossRates::new())),
+            margin_monitor: Arc::new(std::sync::Mutex::new(MarginMonitor::new())),
+            quote_channels: Arc::default(),
+            raw_tap: Arc::default(),
+            packet_stats: Arc::default(),
+            callbacks: SharedCallbacks::default(),
+            filtered_subscribers: Arc::default(),
+            trade_stats: Arc::new(std::sync::Mutex::new(TradeStatistics::new())),
+            candles: Arc::new(std::sync::Mutex::new(CandleAggregator::default())),
+            #[cfg(feature = "experimental-chart")]
+            candle_cache: std::sync::Mutex::new(None),
+            risk: Arc::new(Mutex::new(RiskManager::default())),
+            config,
+            last_activity: Arc::new(std::sync::Mutex::new(Instant::now())),
+            heartbeat: None,
+            reconcile_task: None,
+            timeout_task: None,
+            io_tasks: Vec::new(),
+            shutdown: None,
+            forensics: Arc::new(std::sync::Mutex::new(None)),
+            forensics_hook: AtomicBool::new(false),
+        }
+    }
+
+    /// 客户端配置
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// 当前连接使用的经纪商预设
+    pub fn broker_preset(&self) -> Option<&BrokerPreset> {
+        self.broker_preset.as_ref()
+    }
+
+    /// 便捷方法使用的滑点: 显式设置的滑点优先，其次为经纪商预设的滑点
+    pub fn default_slippage(&self) -> i32 {
+        self.config
+            .default_slippage
+            .or(self.broker_preset.as_ref().map(|p| p.default_slippage))
+            .unwrap_or(DEFAULT_SLIPPAGE)
+    }
+
+    /// 为本次连接选择经纪商预设 (显式指定的预设优先，开启 `auto_broker_preset` 时按服务器名称选择)
+    fn select_broker_preset(&mut self, server: &str) {
+        self.broker_preset = match &self.config.broker_preset {
+            Some(preset) => Some(preset.clone()),
+            None if self.config.auto_broker_preset => PresetRegistry::builtin().find(server).cloned(),
+            None => None,
+        };
+        if let Some(preset) = &self.broker_preset {
+            tracing::info!("Using broker preset {} for {}", preset.name, server);
         }
     }
 
@@ -265,16 +819,32 @@ impl Mt4Client {
     }
 
     /// 连接到 MT4 服务器
+    #[tracing::instrument(name = "mt4.connect", skip_all, fields(login = %credentials.login, server = %credentials.server), err(level = "warn"))]
     pub async fn connect(&mut self, credentials: &LoginCredentials) -> Result<()> {
         tracing::info!(
             "Connecting to MT4: login={}, server={}",
             credentials.login,
             credentials.server
         );
+        self.select_broker_preset(&credentials.server);
 
-        // 1. 获取 token
-        let token_info = self.api.get_token(&credentials.login, &credentials.server, 4).await?;
-        tracing::info!("Token received: {}", &token_info.token[..20.min(token_info.token.len())]);
+        // 1. 获取 token (显式配置的代理同样用于 HTTP 请求)
+        let proxy = match &self.config.proxy {
+            Some(url) => {
+                let proxy = ProxyConfig::parse(url)?;
+                self.api = Mt4Api::with_proxy(&self.config.base_url, &proxy)?;
+                Some(proxy)
+            }
+            None => ProxyConfig::from_env()?,
+        };
+        let connect_deadline = tokio::time::Instant::now() + self.config.connect_timeout;
+        let token_info = tokio::time::timeout_at(
+            connect_deadline,
+            self.api.get_token(&credentials.login, &credentials.server, self.config.gateway),
+        )
+        .await
+        .map_err(|_| Mt4Error::Timeout)??;
+        tracing::info!("Token received from {}", token_info.signal_server);
 
         // 验证服务器是否匹配（API 可能返回不同的服务器）
         if token_info.trade_server != credentials.server {
@@ -293,86 +863,92 @@ impl Mt4Client {
 
         // 2. 设置会话密钥
         {
-            let mut crypto = self.crypto.lock().await;
-            crypto.set_session_key(&token_info.key)?;
-            tracing::debug!("Session key set: {}", &token_info.key[..20.min(token_info.key.len())]);
+            let mut crypto = self.crypto.write().await;
+            crypto.set_session_key(token_info.key.expose_secret())?;
         }
 
         // 3. 构建 WebSocket URL
-        let use_ssl = token_info.ssl.unwrap_or(true);
-        let protocol = if use_ssl { "wss" } else { "ws" };
-        let mut signal_server = token_info.signal_server.clone();
-        if signal_server.ends_with(":443") {
-            signal_server = signal_server.replace(":443", "");
-        }
-        let ws_url = format!("{}://{}/", protocol, signal_server);
+        let ws_url = token_info.ws_url();
         tracing::info!("Connecting to WebSocket: {}", ws_url);
 
-        // 4. 连接 WebSocket
-        let (ws_stream, _) = connect_async(&ws_url).await?;
+        // 4. 连接 WebSocket (自定义 TLS 配置时使用对应的连接器)
+        let ws_connect = async {
+            let tunnel = match &proxy {
+                Some(proxy) => {
+                    let (host, port) = ws_host_port(&ws_url)?;
+                    Some(proxy.connect(&host, port).await?)
+                }
+                None => None,
+            };
+            self.config.tls.connect(&ws_url, tunnel).await
+        };
+        let ws_stream = tokio::time::timeout_at(connect_deadline, ws_connect)
+            .await
+            .map_err(|_| Mt4Error::Timeout)??;
         let (write, read) = ws_stream.split();
 
         // 5. 创建通道
-        let (write_tx, mut write_rx) = mpsc::channel::<Vec<u8>>(32);
-        let (event_tx, event_rx) = mpsc::channel::<Mt4Event>(64);
+        let (write_tx, write_rx) = mpsc::channel::<OutboundFrame>(self.config.write_channel_size);
+        let event_tx = self.open_event_channel();
 
         self.writer = Some(write_tx.clone());
-        self.event_rx = Some(event_rx);
-        self.token_info = Some(token_info.clone());
 
-        // 6. 启动写入任务
-        let write = Arc::new(Mutex::new(write));
-        let write_clone = write.clone();
-        tokio::spawn(async move {
-            while let Some(data) = write_rx.recv().await {
-                let mut w = write_clone.lock().await;
-                if let Err(e) = w.send(Message::Binary(data)).await {
-                    tracing::error!("WebSocket write error: {}", e);
-                    break;
-                }
-            }
-        });
+        // 6. 启动写入任务 (独占写端，其他地方只持有发送通道)
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        self.shutdown = Some(shutdown_tx);
+        self.io_tasks.push(tokio::spawn(write_frames(write, write_rx, shutdown_rx, self.capture.clone(), self.crypto.clone())));
 
         // 7. 启动读取任务
         let crypto = self.crypto.clone();
-        let password = credentials.password.clone();
-        let login_id: i32 = credentials.login.parse().unwrap_or(0);
+        let (decrypt_workers, decrypt_offload_size) = (self.config.decrypt_workers, self.config.decrypt_offload_size);
         let token = token_info.token.clone();
-        let write_tx_clone = write_tx.clone();
-        let request_tracker = self.request_tracker.clone();
-        let timeout_event_tx = event_tx.clone(); // 用于超时任务
+        let recorder = self.recorder.clone();
+        let forensics = self.forensics.clone();
+        let capture = self.capture.clone();
+        self.server = Some(credentials.server.clone());
+        self.login = Some(credentials.login.clone());
+        let mut handler = self.frame_handler(write_tx.clone(), event_tx.clone(), credentials.server.clone());
+        handler.password = credentials.password.clone();
+        handler.login_id = credentials.login.parse().ok();
+        handler.company = token_info.company.clone().unwrap_or_default();
 
-        tokio::spawn(async move {
+        self.io_tasks.push(tokio::spawn(async move {
             let mut read = read;
-            let mut pending_auth = true;
-            let mut password_sent = false;
-
-            while let Some(msg) = read.next().await {
-                match msg {
-                    Ok(Message::Binary(data)) => {
-                        // 解密消息
-                        let crypto_guard = crypto.lock().await;
-                        if data.len() < 8 {
-                            continue;
+            // 解密可以并行，帧处理按接收顺序进行
+            let mut pipeline = DecryptPipeline::new(crypto, decrypt_workers, decrypt_offload_size);
+            // 一个帧可能包含多个数据包，一个数据包也可能跨越多个帧
+            let mut framer = packet::PacketFramer::new();
+            let mut closed = None;
+
+            loop {
+                // 按流水线容量逐个取出已缓存的数据包，其余留在分帧器中
+                while pipeline.has_capacity() {
+                    match framer.next_packet() {
+                        Ok(Some(packet)) => pipeline.push(packet),
+                        Ok(None) => break,
+                        Err(e) => {
+                            tracing::error!("Framing error: {}", e);
+                            telemetry::decrypt_error();
+                            handler.decrypt_failed();
+                            break;
                         }
+                    }
+                }
 
-                        let payload = &data[8..];
-                        let decrypted = match crypto_guard.decrypt(payload) {
-                            Ok(d) => d,
+                tokio::select! {
+                    biased;
+                    Some((raw, decoded)) = pipeline.next(), if !pipeline.is_empty() => {
+                        capture_inbound(&capture, &raw, &decoded);
+                        let (command, error_code, msg_data) = match decoded {
+                            Ok(Some(frame)) => frame,
+                            Ok(None) => continue,
                             Err(e) => {
                                 tracing::error!("Decrypt error: {}", e);
+                                telemetry::decrypt_error();
+                                handler.decrypt_failed();
                                 continue;
                             }
                         };
-                        drop(crypto_guard);
-
-                        if decrypted.len() < 5 {
-                            continue;
-                        }
-
-                        let command = u16::from_le_bytes([decrypted[2], decrypted[3]]);
-                        let error_code = decrypted[4];
-                        let msg_data = decrypted[5..].to_vec();
 
                         tracing::info!(
                             "Received: command={}, error={}, data_len={}",
@@ -381,394 +957,310 @@ impl Mt4Client {
                             msg_data.len()
                         );
 
-                        // 处理消息
-                        match command {
-                            0 if pending_auth && !password_sent => {
-                                // Token 确认，发送密码
-                                tracing::info!("Token accepted, sending password...");
-                                let pwd_data = Self::encode_password(&password);
-                                let crypto_guard = crypto.lock().await;
-                                if let Ok(packet) = Self::build_packet(
-                                    Command::AuthPassword as u16,
-                                    &pwd_data,
-                                    &crypto_guard,
-                                    false,
-                                ) {
-                                    drop(crypto_guard);
-                                    let _ = write_tx_clone.send(packet).await;
-                                    password_sent = true;
-                                }
-                            }
-                            1 => {
-                                // 认证响应
-                                if error_code == 0 {
-                                    pending_auth = false;
-                                    tracing::info!("Authentication successful!");
-                                    let _ = event_tx.send(Mt4Event::Authenticated).await;
-                                    // 不发送 command=5，因为那是获取订单历史，不是当前持仓
-                                    // 当前持仓通过 command=10 (OrderUpdate) 推送事件获取
-                                } else {
-                                    tracing::error!("Authentication failed: {}", error_code);
-                                    let _ = event_tx.send(Mt4Event::AuthFailed(error_code)).await;
-                                }
-                            }
-                            3 => {
-                                // 账户信息响应
-                                // 数据结构 (根据 JS 源码 line 1180):
-                                // - 0-253: 账户信息 (254 字节, q.Vp=254)
-                                // - 254-1161: 品种信息 (28字节*32个, parsed by Ur())
-                                // - 1162+: 报价信息 (parsed by Qr() at offset q.Dk=1162)
-                                // 注意: Command 3 不包含订单数据!
-                                // 当前持仓需要通过 Command 4 请求, 历史订单通过 Command 5 获取
-
-                                if let Some(mut account) = Self::parse_account_info(&msg_data) {
-                                    // 使用认证时的 login (响应中可能没有正确的 login)
-                                    account.login = login_id;
-                                    tracing::info!(
-                                        "Account: login={}, balance={:.2}, equity={:.2}, leverage={}",
-                                        account.login,
-                                        account.balance,
-                                        account.equity,
-                                        account.leverage
-                                    );
-                                    let _ = event_tx.send(Mt4Event::AccountInfo(account)).await;
-
-                                    // 根据 mt4.en.js line 1181: 收到 Command 3 后调用 C.F.$().lf()
-                                    // lf() 函数 (line 1216) 会发送 Command 4 请求获取当前持仓
-                                    tracing::info!("Account info received, requesting current positions (Command 4)...");
-                                    let crypto_guard = crypto.lock().await;
-                                    if let Ok(packet) = Self::build_packet(
-                                        Command::CurrentPositions as u16,
-                                        &[],
-                                        &crypto_guard,
-                                        false,
-                                    ) {
-                                        drop(crypto_guard);
-                                        if let Err(e) = write_tx_clone.send(packet).await {
-                                            tracing::error!("Failed to send Command 4 request: {}", e);
-                                        }
-                                    }
-
-                                } else {
-                                    tracing::warn!(
-                                        "Failed to parse AccountInfo: data_len={}",
-                                        msg_data.len()
-                                    );
-                                    let _ = event_tx.send(Mt4Event::RawMessage {
-                                        command,
-                                        error_code,
-                                        data: msg_data,
-                                    }).await;
-                                }
-                            }
-                            4 => {
-                                // 当前持仓订单列表 (Command 4, mb.Mm)
-                                // 根据 mt4.en.js line 1204 函数 D 和 line 1296 的 Oo() 函数：
-                                // - 这是初始化 ef[] 数组（当前持仓）的命令
-                                // - 数据格式: 161 字节 Order 结构数组（无头部）
-                                // - 使用 Sr() 函数解析 (Math.floor(byteLength/161))
-                                // - 每个订单调用 Oo() 添加到 ef[] 数组
-
-                                let mut orders = Vec::new();
-
-                                // 记录原始数据长度和 error_code，便于诊断
-                                tracing::info!(
-                                    "Command 4 响应: error_code={}, data_len={} 字节",
-                                    error_code,
-                                    msg_data.len()
-                                );
-
-                                if msg_data.is_empty() {
-                                    tracing::warn!("Command 4 (当前持仓): 空数据 (无持仓订单或服务器未返回)");
-                                } else {
-                                    let order_count = msg_data.len() / 161;
-                                    tracing::info!(
-                                        "Command 4 (当前持仓): {} 个订单 ({} 字节)",
-                                        order_count,
-                                        msg_data.len()
-                                    );
+                        // 录制解密后的入站帧
+                        record_frame(&recorder, &forensics, command, error_code, &msg_data);
 
-                                    for i in 0..order_count {
-                                        let offset = i * 161;
-                                        if let Some(order) = Order::from_bytes(&msg_data, offset) {
-                                            // tracing::info!(
-                                            //     "持仓 #{}: ticket={}, symbol={}, type={:?}, volume={:.2}, open={:.5}, profit={:.2}",
-                                            //     i,
-                                            //     order.ticket,
-                                            //     order.symbol,
-                                            //     order.order_type,
-                                            //     order.volume,
-                                            //     order.open_price,
-                                            //     order.profit
-                                            // );
-                                            orders.push(order);
-                                        }
-                                    }
-                                }
+                        handler.handle(command, error_code, msg_data).await;
+                    }
+                    msg = read.next(), if closed.is_none() && pipeline.has_capacity() => match msg {
+                        Some(Ok(Message::Binary(data))) => framer.extend(&data),
+                        Some(Ok(Message::Close(_))) => closed = Some(None),
+                        Some(Err(e)) => closed = Some(Some(e)),
+                        None => break,
+                        Some(Ok(_)) => {}
+                    },
+                    // 连接已关闭且在途的帧都已处理
+                    else => break,
+                }
+            }
 
-                                // 发送持仓快照事件（包含所有当前持仓，用于同步本地缓存）
-                                let _ = event_tx.send(Mt4Event::PositionsSnapshot(orders)).await;
-                            }
-                            5 => {
-                                // 订单历史响应或当前持仓响应
-                                tracing::info!(
-                                    "Command 5 response: data_len={} bytes",
-                                    msg_data.len()
-                                );
-
-                                // 输出 hex 数据以便分析
-                                if !msg_data.is_empty() {
-                                    // // 输出前 200 字节
-                                    // let hex_preview = msg_data.iter()
-                                    //     .take(200)
-                                    //     .map(|b| format!("{:02x}", b))
-                                    //     .collect::<Vec<_>>()
-                                    //     .join(" ");
-                                    // tracing::info!("Command 5 data (first 200 bytes): {}", hex_preview);
-
-                                    // // 输出前 3 个 161 字节记录的完整 hex
-                                    // for i in 0..3 {
-                                    //     let offset = i * 161;
-                                    //     if msg_data.len() >= offset + 161 {
-                                    //         let order_hex = msg_data[offset..offset+161].iter()
-                                    //             .map(|b| format!("{:02x}", b))
-                                    //             .collect::<Vec<_>>()
-                                    //             .join(" ");
-                                    //         tracing::info!("Record #{} (161 bytes): {}", i, order_hex);
-                                    //     }
-                                    // }
-
-                                    // 解析订单（命令 5 = 历史订单）
-                                    // 根据 mt4.en.js line 1103 的 Sr() 函数:
-                                    // 数据格式: 161 字节 Order 结构数组（无头部）
-                                    let order_count = msg_data.len() / 161;
-                                    tracing::info!("Command 5: parsing {} orders from {} bytes", order_count, msg_data.len());
-
-                                    let mut history_orders = Vec::with_capacity(order_count);
-                                    for i in 0..order_count {
-                                        let offset = i * 161;
-                                        if let Some(order) = Order::from_bytes(&msg_data, offset) {
-                                            // tracing::info!(
-                                            //     "历史订单 #{}: ticket={}, symbol={}, type={:?}, volume={:.2}, open={:.5}, close={:.5}, profit={:.2}, open_time={}, close_time={}",
-                                            //     i, order.ticket, order.symbol, order.order_type, order.volume,
-                                            //     order.open_price, order.close_price, order.profit,
-                                            //     order.open_time, order.close_time
-                                            // );
-
-
-                                            history_orders.push(order);
-                                        }
-                                    }
-
-                                    // 一次性发送所有历史订单（使用新的 HistoryOrders 事件）
-                                    if !history_orders.is_empty() {
-                                        tracing::info!("Command 5: 发送 {} 个历史订单到引擎", history_orders.len());
-                                        let _ = event_tx.send(Mt4Event::HistoryOrders(history_orders)).await;
-                                    }
-                                }
-                            }
-                            10 => {
-                                // 订单更新 (实时推送) - 可能包含多个订单更新
-                                // tracing::debug!(
-                                //     "Order update raw: data_len={}, data_hex={:02x?}",
-                                //     msg_data.len(),
-                                //     &msg_data[..msg_data.len().min(32)]
-                                // );
-
-                                // 解析所有订单更新（一条消息可能包含多个）
-                                let updates = OrderUpdate::parse_all(&msg_data);
-                                if updates.is_empty() {
+            match closed {
+                Some(None) => {
+                    tracing::info!("WebSocket closed");
+                    telemetry::connection_state(false);
+                    handler.authenticated.store(false, Ordering::SeqCst);
+                    let _ = handler.event_tx.send(Mt4Event::Disconnected).await;
+                }
+                Some(Some(e)) => {
+                    tracing::error!("WebSocket error: {}", e);
+                    telemetry::connection_state(false);
+                    handler.authenticated.store(false, Ordering::SeqCst);
+                    let _ = handler.event_tx.send(Mt4Event::Error(e.to_string())).await;
+                }
+                None => {}
+            }
+        }));
+
+        // 8. 发送 token
+        let packet_id = self.request_tracker.next_packet_id();
+        let token_data = packet::encode_token(token.expose_secret());
+        let mut frame = OutboundFrame::new(packet_id, Command::AuthToken as u16, token_data);
+        frame.use_auth_key = true;
+
+        if let Some(writer) = &self.writer {
+            writer.send(frame).await.map_err(|_| Mt4Error::Connection("Send failed".to_string()))?;
+        }
+
+        telemetry::connection_state(true);
+
+        // 9. 启动超时检测任务和心跳任务
+        self.spawn_timeout_task(event_tx);
+        self.spawn_heartbeat();
+        self.spawn_reconcile();
+
+        // 10. 按配置等待认证完成，执行离线队列中的意图
+        self.finish_login().await
+    }
+
+    /// 连接 Web Terminal，经纪商未启用 Web Terminal 时改用本地终端桥接
+    pub async fn connect_with_bridge_fallback(&mut self, credentials: &LoginCredentials, bridge_addr: &str) -> Result<()> {
+        match self.connect(credentials).await {
+            Err(Mt4Error::WebTerminalDisabled) => {
+                tracing::warn!(
+                    "Web Terminal disabled for {}, falling back to terminal bridge at {}",
+                    credentials.server,
+                    bridge_addr
+                );
+                self.connect_bridge(bridge_addr, credentials).await
+            }
+            result => result,
+        }
+    }
+
+    /// 通过本地 MT4 终端的桥接 EA 连接 (见 `bridge` 模块)
+    ///
+    /// 连接后的认证流程、事件和交易接口与 `connect()` 相同
+    pub async fn connect_bridge(&mut self, addr: &str, credentials: &LoginCredentials) -> Result<()> {
+        if self.is_connected() {
+            return Err(Mt4Error::InvalidParams("客户端已连接".to_string()));
+        }
+        tracing::info!("Connecting to MT4 terminal bridge: {}", addr);
+        self.select_broker_preset(&credentials.server);
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| Mt4Error::Connection(format!("连接桥接 EA {} 失败: {}", addr, e)))?;
+        let (read, write) = stream.into_split();
+
+        let (write_tx, write_rx) = mpsc::channel::<OutboundFrame>(self.config.write_channel_size);
+        let event_tx = self.open_event_channel();
+
+        self.writer = Some(write_tx.clone());
+        self.server = Some(credentials.server.clone());
+        self.login = Some(credentials.login.clone());
+
+        // 写入任务: 把客户端发出的明文命令帧转换为 JSON 消息转发给 EA
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        self.shutdown = Some(shutdown_tx);
+        self.io_tasks.push(tokio::spawn(forward_requests(write, write_rx, shutdown_rx)));
+
+        // 读取任务: EA 发来的帧交给与 WebSocket 相同的处理流程
+        let recorder = self.recorder.clone();
+        let forensics = self.forensics.clone();
+        let mut handler = self.frame_handler(write_tx, event_tx.clone(), credentials.server.clone());
+        handler.password = credentials.password.clone();
+        handler.login_id = credentials.login.parse().ok();
+        self.io_tasks.push(tokio::spawn(async move {
+            let mut lines = BufReader::new(read).lines();
+            loop {
+                match lines.next_line().await {
+                    Ok(Some(line)) if line.trim().is_empty() => {}
+                    Ok(Some(line)) => match BridgeMessage::parse(&line) {
+                        Ok(message) => {
+                            if let BridgeMessage::Hello { version } = message {
+                                if version != BRIDGE_PROTOCOL_VERSION {
                                     tracing::warn!(
-                                        "Failed to parse OrderUpdate: data_len={} (expected >= 185)",
-                                        msg_data.len()
-                                    );
-                                } else {
-                                    tracing::debug!("Parsed {} order update(s) from {} bytes", updates.len(), msg_data.len());
-                                    for update in &updates {
-                                        // tracing::info!(
-                                        //     "Order update: ticket={}, symbol={}, type={:?}, notify_type={}, close_time={}, comment={}",
-                                        //     update.order.ticket,
-                                        //     update.order.symbol,
-                                        //     update.order.order_type,
-                                        //     update.notify_type,
-                                        //     update.order.close_time,
-                                        //     update.order.comment
-                                        // );
-                                        tracing::info!("update.order 详情: {:?}", update.order);
-
-                                    }
-                                    // 批量发送订单更新事件，让接收方可以一次性处理所有更新后再做决策 
-                                    let _ = event_tx.send(Mt4Event::OrderUpdates(updates)).await;
-                                }
-                            }
-                            12 => {
-                                // 交易响应 - 解析完整的响应数据
-                                // 根据 JS mt4.en.js 第1211行的 d 函数处理响应
-                                if let Some(response) = crate::types::TradeResponse::from_bytes(&msg_data) {
-                                    let request_id = response.request_id;
-
-                                    // 详细日志：显示 error_code 和 response.status 的值
-                                    tracing::debug!(
-                                        "Trade response: request_id={}, error_code={}, response.status={}, price1={:.5}, price2={:.5}",
-                                        request_id, error_code, response.status, response.price1, response.price2
+                                        "Bridge EA protocol version {} (expected {})",
+                                        version,
+                                        BRIDGE_PROTOCOL_VERSION
                                     );
-
-                                    // 确认请求完成 (对应 JS: clearTimeout(W[c.Xg]); N[c.Xg]=null; E[e.R]=null;)
-                                    if let Some(pending) = request_tracker.confirm(request_id).await {
-                                        tracing::info!(
-                                            "📥 [响应确认] request_id={}, 耗时={:.2}秒, target_ticket={:?}",
-                                            request_id,
-                                            pending.created_at.elapsed().as_secs_f64(),
-                                            pending.target_ticket
-                                        );
-                                    } else {
-                                        tracing::warn!(
-                                            "⚠️ [响应未匹配] request_id={} 未在待确认队列中找到",
-                                            request_id
-                                        );
-                                    }
-
-                                    // 根据JS原始逻辑:
-                                    // - error_code > 0 只是通讯层警告,仍需检查response.status
-                                    // - response.status >= 2 才是真正的交易错误
-                                    // - response.status 0=Success, 1=Request sent (都表示成功/待确认)
-
-                                    // 先记录通讯层警告(如果有)
-                                    if error_code != 0 {
-                                        let err = Mt4Error::from_trade_code(error_code);
-                                        if let Mt4Error::Trade { code: _, message } = err {
-                                            tracing::warn!(
-                                                "Trade response with header error_code (warning only): request_id={}, error_code={}, response.status={}, msg={}",
-                                                request_id, error_code, response.status, message
-                                            );
-                                        }
-                                    }
-
-                                    // 根据response.status判断交易结果
-                                    if response.status >= 2 {
-                                        // status >= 2 才是真正的错误
-                                        let err = Mt4Error::from_trade_code(response.status as u8);
-                                        if let Mt4Error::Trade { code, message } = err {
-                                            tracing::warn!(
-                                                "Trade failed (status>=2): request_id={}, error_code={}, response.status={}, code={}, msg={}",
-                                                request_id, error_code, response.status, code, message
-                                            );
-                                            let _ = event_tx.send(Mt4Event::TradeFailed { code, message }).await;
-                                        }
-                                    } else {
-                                        // status=0 (Success) 或 status=1 (Request sent) 都是成功/待确认
-                                        tracing::info!(
-                                            "Trade success (status=0 or 1): request_id={}, error_code={}, response.status={}, price1={:.5}, price2={:.5}, orders_count={}",
-                                            request_id, error_code, response.status, response.price1, response.price2, response.orders.len()
-                                        );
-                                        let _ = event_tx.send(Mt4Event::TradeSuccess {
-                                            request_id,
-                                            status: response.status
-                                        }).await;
-                                    }
-                                } else {
-                                    tracing::error!("Failed to parse trade response, data_len={}", msg_data.len());
-                                    // 如果解析失败，使用旧的简单解析方式作为后备
-                                    let request_id = if msg_data.len() >= 4 {
-                                        i32::from_le_bytes([msg_data[0], msg_data[1], msg_data[2], msg_data[3]])
-                                    } else {
-                                        0
-                                    };
-                                    let status = if msg_data.len() >= 8 {
-                                        i32::from_le_bytes([msg_data[4], msg_data[5], msg_data[6], msg_data[7]])
-                                    } else {
-                                        0
-                                    };
-
-                                    // 确认请求完成
-                                    if request_id != 0 {
-                                        request_tracker.confirm(request_id).await;
-                                    }
-
-                                    // 根据JS原始逻辑: error_code只是警告,status>=2才是错误
-                                    if error_code != 0 {
-                                        let err = Mt4Error::from_trade_code(error_code);
-                                        if let Mt4Error::Trade { code: _, message } = err {
-                                            tracing::warn!("Trade response with header error_code (warning only): error_code={}, msg={}", error_code, message);
-                                        }
-                                    }
-
-                                    if status >= 2 {
-                                        let err = Mt4Error::from_trade_code(status as u8);
-                                        if let Mt4Error::Trade { code, message } = err {
-                                            tracing::warn!("Trade failed (status>=2): code={}, msg={}", code, message);
-                                            let _ = event_tx.send(Mt4Event::TradeFailed { code, message }).await;
-                                        }
-                                    } else {
-                                        tracing::info!("Trade success: request_id={}, status={}", request_id, status);
-                                        let _ = event_tx.send(Mt4Event::TradeSuccess { request_id, status }).await;
-                                    }
                                 }
                             }
-                            51 => {
-                                // Pong
-                                tracing::trace!("Pong received");
-                                let _ = event_tx.send(Mt4Event::Pong).await;
-                            }
-                            _ => {
-                                let _ = event_tx.send(Mt4Event::RawMessage {
-                                    command,
-                                    error_code,
-                                    data: msg_data,
-                                }).await;
-                            }
+                            let (command, error_code, data) = message.into_frame();
+                            tracing::debug!(
+                                "Bridge received: command={}, error={}, data_len={}",
+                                command,
+                                error_code,
+                                data.len()
+                            );
+                            record_frame(&recorder, &forensics, command, error_code, &data);
+                            handler.handle(command, error_code, data.into()).await;
                         }
-                    }
-                    Ok(Message::Close(_)) => {
-                        tracing::info!("WebSocket closed");
-                        let _ = event_tx.send(Mt4Event::Disconnected).await;
+                        Err(e) => tracing::warn!("{}", e),
+                    },
+                    Ok(None) => {
+                        tracing::info!("Terminal bridge closed");
+                        telemetry::connection_state(false);
+                        handler.authenticated.store(false, Ordering::SeqCst);
+                        let _ = handler.event_tx.send(Mt4Event::Disconnected).await;
                         break;
                     }
                     Err(e) => {
-                        tracing::error!("WebSocket error: {}", e);
-                        let _ = event_tx.send(Mt4Event::Error(e.to_string())).await;
+                        tracing::error!("Bridge read error: {}", e);
+                        telemetry::connection_state(false);
+                        handler.authenticated.store(false, Ordering::SeqCst);
+                        let _ = handler.event_tx.send(Mt4Event::Error(e.to_string())).await;
                         break;
                     }
-                    _ => {}
                 }
             }
-        });
+        }));
 
-        // 8. 发送 token
-        let token_data = Self::encode_token(&token);
-        let crypto_guard = self.crypto.lock().await;
-        let packet = Self::build_packet(Command::AuthToken as u16, &token_data, &crypto_guard, true)?;
-        drop(crypto_guard);
+        telemetry::connection_state(true);
+        self.spawn_timeout_task(event_tx);
+        self.spawn_heartbeat();
+        self.spawn_reconcile();
 
-        if let Some(writer) = &self.writer {
-            writer.send(packet).await.map_err(|_| Mt4Error::Connection("Send failed".to_string()))?;
+        self.finish_login().await
+    }
+
+    /// 按配置等待认证完成；离线队列中有意图时等待认证并立即执行
+    ///
+    /// 未配置 `auth_timeout` 时最多等待 `INTENT_FLUSH_AUTH_TIMEOUT`，认证未完成则保留队列，不影响连接结果
+    async fn finish_login(&self) -> Result<()> {
+        let queued = match &self.intent_queue {
+            Some(queue) => !queue.lock().await.is_empty(),
+            None => false,
+        };
+        match self.config.auth_timeout {
+            Some(timeout) => self.wait_authenticated(timeout).await?,
+            None if queued => {
+                if let Err(e) = self.wait_authenticated(INTENT_FLUSH_AUTH_TIMEOUT).await {
+                    tracing::warn!("Queued trade intents not flushed: {}", e);
+                    return Ok(());
+                }
+            }
+            None => return Ok(()),
+        }
+        if queued {
+            match self.flush_intents().await {
+                Ok(outcomes) => tracing::info!("Flushed {} queued trade intent(s)", outcomes.len()),
+                Err(e) => tracing::error!("Failed to flush queued trade intents: {}", e),
+            }
         }
+        Ok(())
+    }
 
-        // 9. 启动超时检测任务
-        // 根据 JS mt4.en.js 第1183行: setTimeout(..., 180000) - 180秒超时
-        let timeout_tracker = self.request_tracker.clone();
-        tokio::spawn(async move {
-            const TIMEOUT_SECS: u64 = 180; // 与 JS 一致
-            const CHECK_INTERVAL_SECS: u64 = 5; // 每5秒检查一次
+    /// 等待认证完成
+    ///
+    /// 认证失败时返回 `Mt4Error::AuthFailed`，超时返回 `Mt4Error::Timeout`
+    #[tracing::instrument(name = "mt4.auth", skip_all, err(level = "warn"))]
+    async fn wait_authenticated(&self, timeout: Duration) -> Result<()> {
+        let started = Instant::now();
+        let wait = async {
+            loop {
+                if self.authenticated.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+                // 认证结果也可能已随连接断开被覆盖，从最近事件中查找
+                let outcome = self.recent_events.lock().ok().and_then(|recent| {
+                    recent.iter().rev().take_while(|e| e.time.monotonic >= started).find_map(|e| match e.event {
+                        Mt4Event::Authenticated => Some(Ok(())),
+                        Mt4Event::AuthFailed(code) => Some(Err(Mt4Error::AuthFailed(code))),
+                        _ => None,
+                    })
+                });
+                if let Some(outcome) = outcome {
+                    return outcome;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.map_err(|_| Mt4Error::Timeout)?
+    }
 
-            let mut interval = tokio::time::interval(
-                tokio::time::Duration::from_secs(CHECK_INTERVAL_SECS)
-            );
+    /// 按配置启动心跳任务
+    ///
+    /// 连接空闲 (未收发任何数据) 达到心跳间隔时发送 Ping，有其他数据往来时不发送；
+    /// `disconnect()` 或发送通道关闭时停止
+    fn spawn_heartbeat(&mut self) {
+        if let Some(task) = self.heartbeat.take() {
+            task.abort();
+        }
+        let (Some(interval), Some(writer)) = (self.config.heartbeat_interval, &self.writer) else {
+            return;
+        };
+        let writer = writer.downgrade();
+        let tracker = self.request_tracker.clone();
+        let last_activity = self.last_activity.clone();
+        touch(&last_activity);
+        self.heartbeat = Some(tokio::spawn(async move {
+            loop {
+                let idle = last_activity.lock().map(|t| t.elapsed()).unwrap_or(interval);
+                if idle < interval {
+                    tokio::time::sleep(interval - idle).await;
+                    continue;
+                }
+                let Some(writer) = writer.upgrade() else {
+                    break;
+                };
+                let frame = OutboundFrame::new(tracker.next_packet_id(), Command::Ping as u16, Vec::new());
+                if writer.send(frame).await.is_err() {
+                    break;
+                }
+                tracing::debug!("Heartbeat sent after {:.1}s idle", idle.as_secs_f64());
+                touch(&last_activity);
+            }
+        }));
+    }
+
+    /// 按配置启动定期对账任务
+    ///
+    /// 每隔 `reconcile_interval` 请求账户信息 (Command 3)，读取任务收到后会接着请求订单列表 (Command 4)
+    /// 并与本地持仓缓存对账；登录后服务器首次发送的订单列表同样会对账，因此第一次请求在一个间隔之后
+    fn spawn_reconcile(&mut self) {
+        if let Some(task) = self.reconcile_task.take() {
+            task.abort();
+        }
+        let (Some(period), Some(writer)) = (self.config.reconcile_interval, &self.writer) else {
+            return;
+        };
+        let writer = writer.downgrade();
+        let tracker = self.request_tracker.clone();
+        self.reconcile_task = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let Some(writer) = writer.upgrade() else {
+                    break;
+                };
+                let frame = OutboundFrame::new(tracker.next_packet_id(), Command::AccountInfo as u16, Vec::new());
+                if writer.send(frame).await.is_err() {
+                    break;
+                }
+                tracing::debug!("Reconciliation requested");
+            }
+        }));
+    }
+
+    /// 启动交易请求超时检测任务
+    /// 根据 JS mt4.en.js 第1183行: setTimeout(..., 180000) - 超时由 `trade_timeout` 配置 (默认 180 秒)
+    fn spawn_timeout_task(&mut self, event_tx: EventSender) {
+        if let Some(task) = self.timeout_task.take() {
+            task.abort();
+        }
+        let timeout_tracker = self.request_tracker.clone();
+        let timeout_event_tx = event_tx;
+        self.timeout_task = Some(tokio::spawn(async move {
+            // 每秒检查一次，超时误差不超过 1 秒
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            let timeout_secs = timeout_tracker.timeout().as_secs_f64();
 
             loop {
                 interval.tick().await;
 
-                // 获取超时的请求
-                let timed_out = timeout_tracker.remove_timed_out(TIMEOUT_SECS).await;
+                // 移除超时的请求 (等待者收到 Mt4Error::Timeout)
+                let timed_out = timeout_tracker.remove_expired().await;
 
                 for pending in timed_out {
+                    telemetry::trade_timeout();
                     tracing::warn!(
                         "⏰ [请求超时] request_id={}, 等待时间={:.1}秒, symbol={}, ticket={}, 超过{}秒未响应",
                         pending.request_id,
                         pending.created_at.elapsed().as_secs_f64(),
                         pending.request.symbol,
                         pending.request.ticket,
-                        TIMEOUT_SECS
+                        timeout_secs
                     );
 
                     // 发送超时事件
@@ -786,70 +1278,37 @@ impl Mt4Client {
                     }).await;
                 }
             }
-        });
-
-        Ok(())
+        }));
     }
 
-    /// 编码 token (64字节 ASCII)
-    fn encode_token(token: &str) -> Vec<u8> {
-        let mut buffer = vec![0u8; AUTH_DATA_SIZE];
-        let bytes = token.as_bytes();
-        let len = bytes.len().min(AUTH_DATA_SIZE);
-        buffer[..len].copy_from_slice(&bytes[..len]);
-        buffer
+    /// 发送命令
+    pub async fn send_command(&self, command: Command, data: &[u8]) -> Result<()> {
+        self.send_packet(command as u16, data).await
     }
 
-    /// 编码密码 (64字节 UTF-16 LE)
-    fn encode_password(password: &str) -> Vec<u8> {
-        let mut buffer = vec![0u8; AUTH_DATA_SIZE];
-        for (i, c) in password.chars().take(32).enumerate() {
-            let code = c as u16;
-            buffer[i * 2] = (code & 0xFF) as u8;
-            buffer[i * 2 + 1] = (code >> 8) as u8;
-        }
-        buffer
+    /// 发送任意命令号的数据包 (用于试探未收录在 `Command` 中的命令，响应作为 `RawMessage` 事件发出)
+    ///
+    /// 需要等待响应时使用 `request()`
+    pub async fn send_raw(&self, command: u16, data: &[u8]) -> Result<()> {
+        self.send_packet(command, data).await
     }
 
-    /// 构建数据包
-    fn build_packet(
-        command: u16,
-        data: &[u8],
-        crypto: &Mt4Crypto,
-        use_auth_key: bool,
-    ) -> Result<Vec<u8>> {
-        // 4字节头 + 数据
-        let mut payload = vec![0u8; 4 + data.len()];
-        payload[0] = rand::random();
-        payload[1] = rand::random();
-        payload[2] = (command & 0xFF) as u8;
-        payload[3] = (command >> 8) as u8;
-        payload[4..].copy_from_slice(data);
-
-        // 加密
-        let encrypted = crypto.encrypt(&payload, use_auth_key)?;
-
-        // 8字节头 + 加密数据
-        let mut packet = vec![0u8; 8 + encrypted.len()];
-        let mut cursor = Cursor::new(&mut packet[..]);
-        cursor.write_u32::<LittleEndian>(encrypted.len() as u32).unwrap();
-        cursor.write_u32::<LittleEndian>(1).unwrap();
-        packet[8..].copy_from_slice(&encrypted);
-
-        Ok(packet)
+    /// 分配数据包 ID 并发送一个命令帧
+    async fn send_packet(&self, command: u16, data: &[u8]) -> Result<()> {
+        self.send_packet_with_id(self.request_tracker.next_packet_id(), command, data).await
     }
 
-    /// 发送命令
-    pub async fn send_command(&self, command: Command, data: &[u8]) -> Result<()> {
-        let crypto = self.crypto.lock().await;
-        let packet = Self::build_packet(command as u16, data, &crypto, false)?;
-        drop(crypto);
+    /// 以指定的数据包 ID 发送一个命令帧 (由写入任务按传输方式编码)
+    async fn send_packet_with_id(&self, packet_id: u16, command: u16, data: &[u8]) -> Result<()> {
+        let frame = OutboundFrame::new(packet_id, command, data);
+        tracing::debug!("Sending: command={}, packet_id={}, data_len={}", command, packet_id, data.len());
 
         if let Some(writer) = &self.writer {
             writer
-                .send(packet)
+                .send(frame)
                 .await
                 .map_err(|_| Mt4Error::Connection("Send failed".to_string()))?;
+            touch(&self.last_activity);
         } else {
             return Err(Mt4Error::NotConnected);
         }
@@ -857,10 +1316,62 @@ impl Mt4Client {
         Ok(())
     }
 
+    /// 发送命令并等待该命令的下一个响应，返回 (error_code, 数据)
+    ///
+    /// 同一命令的多个请求按发送顺序依次匹配响应；被请求认领的响应不再作为 `RawMessage` 事件发出
+    /// (已知命令仍照常更新账户、持仓等状态)。`command` 可以是 `Command` 或未收录的命令编号。
+    /// 交易请求按 request_id 匹配，请使用 `send_trade_and_wait`。
+    ///
+    /// ```no_run
+    /// # async fn example(client: &mt4_client::Mt4Client) -> mt4_client::Result<()> {
+    /// let (error_code, data) = client.request(15u16, &[]).await?;
+    /// println!("error={} len={}", error_code, data.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn request(&self, command: impl Into<u16>, payload: &[u8]) -> Result<(u8, Bytes)> {
+        self.request_with_timeout(command, payload, self.config.request_timeout).await
+    }
+
+    /// 发送命令并在 `timeout` 内等待该命令的下一个响应
+    pub async fn request_with_timeout(
+        &self,
+        command: impl Into<u16>,
+        payload: &[u8],
+        timeout: Duration,
+    ) -> Result<(u8, Bytes)> {
+        let command = command.into();
+        let packet_id = self.request_tracker.next_packet_id();
+        let (tx, rx) = oneshot::channel();
+        let waiter = CommandWaiter { packet_id, tx: Some(tx), expires: Instant::now() + timeout + LATE_RESPONSE_WINDOW };
+        self.command_waiters.lock().await.entry(command).or_default().push_back(waiter);
+
+        if let Err(e) = self.send_packet_with_id(packet_id, command, payload).await {
+            // 未发出的请求不会有响应，移出队列
+            if let Some(queue) = self.command_waiters.lock().await.get_mut(&command) {
+                queue.retain(|w| w.packet_id != packet_id);
+            }
+            return Err(e);
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(response) => response.map_err(|_| Mt4Error::NotConnected),
+            Err(_) => {
+                // 保留队列位置但移除接收端，迟到的响应不会交给之后的请求
+                if let Some(queue) = self.command_waiters.lock().await.get_mut(&command) {
+                    if let Some(waiter) = queue.iter_mut().find(|w| w.packet_id == packet_id) {
+                        waiter.tx = None;
+                    }
+                }
+                Err(Mt4Error::Timeout)
+            }
+        }
+    }
+
     /// 发送交易请求 (内部方法，不使用追踪)
     async fn send_trade_internal(&self, request: &TradeRequest) -> Result<()> {
         let data = request.to_bytes();
-        self.send_command(Command::TradeRequest, &data).await
+        self.send_packet_with_id(request.request_id as u16, Command::TradeRequest as u16, &data).await
     }
 
     /// 发送交易请求 (带追踪)
@@ -875,24 +1386,68 @@ impl Mt4Client {
     /// - is_duplicate: 如果是重复操作则返回true (不发送)
     pub async fn send_trade(&self, mut request: TradeRequest) -> Result<(i32, bool)> {
         // 1. 生成 request_id (对应 JS: b.kj = B.GH++)
-        let request_id = self.request_tracker.next_id();
-        request.request_id = request_id;
+        request.request_id = self.request_tracker.next_id();
+        self.dispatch_trade(request).await
+    }
+
+    /// 发送已分配 request_id 的交易请求 (防重复 + 追踪 + 发送)
+    #[tracing::instrument(
+        name = "mt4.trade.dispatch",
+        skip_all,
+        fields(request_id = request.request_id, trade_type = request.trade_type, symbol = %request.symbol),
+        err(level = "warn")
+    )]
+    pub(crate) async fn dispatch_trade(&self, mut request: TradeRequest) -> Result<(i32, bool)> {
+        let request_id = request.request_id;
+
+        if self.config.auto_normalize_volume && request.is_open_request() {
+            let spec = self.symbol_info_or_default(&request.symbol, 5).await;
+            let volume = spec.normalize_volume(request.volume);
+            if volume != request.volume {
+                tracing::info!("📐 [手数取整] request_id={}: {} -> {} lots", request_id, request.volume, volume);
+                request.volume = volume;
+            }
+        }
 
         // 2. 检查 ticket 防重复 (对应 JS: if (E && E[b.R]) return;)
-        if request.ticket != 0 {
-            if self.request_tracker.is_ticket_locked(request.ticket).await {
-                tracing::warn!(
-                    "⚠️ [请求跳过] ticket #{} 已有待确认操作，跳过重复请求 (request_id={})",
-                    request.ticket,
-                    request_id
-                );
-                return Ok((request_id, true)); // 重复操作
+        if request.ticket.is_set() && self.request_tracker.is_ticket_locked(request.ticket).await {
+            tracing::warn!(
+                "⚠️ [请求跳过] ticket #{} 已有待确认操作，跳过重复请求 (request_id={})",
+                request.ticket,
+                request_id
+            );
+            return Ok((request_id, true)); // 重复操作
+        }
+
+        // 风险限制 (可能缩小手数或滑点)
+        if let Err(e) = self.check_risk(&mut request).await {
+            tracing::warn!("⛔ [风险控制] request_id={}: {}", request_id, e);
+            if let Mt4Error::RiskRejected { rule, reason } = &e {
+                self.emit(Mt4Event::RiskRejected { rule: *rule, reason: reason.clone(), request: request.clone() })
+                    .await;
             }
+            return Err(e);
         }
 
-        tracing::info!(
-            "📤 [发送请求] request_id={}, type={}, {:?} {} {} lots @ {}, ticket={}",
-            request_id,
+        // 本地校验，避免服务器以笼统的错误码 3 拒绝
+        if self.config.validate_trades {
+            if let Err(e) = self.validate_trade(&request).await {
+                tracing::warn!("⛔ [参数校验] request_id={}: {}", request_id, e);
+                return Err(e);
+            }
+        }
+
+        // 按策略标签检查交易频率
+        if let Ok(mut throttle) = self.throttle.lock() {
+            if let Err(e) = throttle.check(&request, Instant::now()) {
+                tracing::warn!("⛔ [频率限制] request_id={}: {}", request_id, e);
+                return Err(e);
+            }
+        }
+
+        tracing::info!(
+            "📤 [发送请求] request_id={}, type={}, {:?} {} {} lots @ {}, ticket={}",
+            request_id,
             request.trade_type,
             request.order_type,
             request.symbol,
@@ -903,6 +1458,7 @@ impl Mt4Client {
 
         // 3. 添加到待确认队列 (对应 JS: N[b.kj] = b; E[b.R] = b.kj;)
         self.request_tracker.add_pending(request.clone()).await;
+        telemetry::trade_request(request.trade_type);
 
         // 4. 发送请求
         let result = self.send_trade_internal(&request).await;
@@ -916,6 +1472,195 @@ impl Mt4Client {
         result.map(|_| (request_id, false))
     }
 
+    /// 按风险限制检查请求
+    async fn check_risk(&self, request: &mut TradeRequest) -> Result<()> {
+        if !request.is_open_request() {
+            return Ok(());
+        }
+        let spec = self.symbol_info_or_default(&request.symbol, 5).await;
+        let orders = self.positions.all().await;
+        let account = self.account.read().await.clone();
+        self.risk.lock().await.check(request, &spec, &orders, account.as_ref(), unix_now())
+    }
+
+    /// 按品种规格和经纪商预设校验交易请求
+    ///
+    /// 未设置品种规格时不检查手数规格；最小止损距离以点为单位，只有设置了品种规格 (已知小数位数) 时才检查。
+    /// 持仓修改请求的止损/止盈按最近收到的报价检查，尚未收到报价时交由服务器检查
+    async fn validate_trade(&self, request: &TradeRequest) -> Result<()> {
+        let spec = self.symbol_info(&request.symbol).await;
+        let stops_level = self.broker_preset().map_or(0, |p| p.stops_level);
+        match &spec {
+            Some(spec) => validate_trade_request(request, spec, stops_level)?,
+            None => validate_trade_request_without_spec(request)?,
+        }
+        if is_position_modify(request) {
+            let price = self.cross_rates.lock().ok().and_then(|rates| rates.price(&request.symbol));
+            if let Some((bid, ask)) = price {
+                validate_position_stops(request, spec.as_ref(), stops_level, bid, ask)?;
+            }
+        }
+        // 尚无服务器时间样本时无法判断过期时间，交由服务器检查
+        if let Some(server_now) = self.drift_estimator().utc_to_server(SystemTime::now()) {
+            validate_expiration(request, server_now)?;
+        }
+        Ok(())
+    }
+
+    /// 按估计的经纪商时区设置挂单有效期
+    ///
+    /// 尚未收到带服务器时间的推送 (无法估计时区) 时，GTD 返回 `Mt4Error::InvalidParams`
+    pub fn with_time_in_force(&self, request: TradeRequest, time_in_force: TimeInForce) -> Result<TradeRequest> {
+        let offset = match time_in_force {
+            TimeInForce::GoodTillCancelled => 0,
+            TimeInForce::GoodTillDate(_) => self.drift_estimator().timezone_offset_secs().ok_or_else(|| {
+                Mt4Error::InvalidParams("尚未估计出经纪商时区，无法换算过期时间".to_string())
+            })?,
+        };
+        Ok(request.with_time_in_force(time_in_force, offset))
+    }
+
+    /// 发送交易请求并等待服务器响应
+    ///
+    /// 与 `send_trade` 相同，但会等待 Command 12 交易响应:
+    /// - status 0/1 返回 `TradeResponse`
+    /// - status >= 2 返回 `Mt4Error::Trade`
+    /// - 超过 `trade_timeout` (默认 180 秒) 未响应返回 `Mt4Error::Timeout`
+    ///
+    /// 配置了 `RequotePolicy` 时，市价开仓请求遇到重新报价会刷新价格、放宽滑点后重新提交
+    #[tracing::instrument(
+        name = "mt4.trade",
+        skip_all,
+        fields(trade_type = request.trade_type, symbol = %request.symbol, ticket = %request.ticket, volume = request.volume),
+        err(level = "warn")
+    )]
+    pub async fn send_trade_and_wait(&self, request: TradeRequest) -> Result<TradeResponse> {
+        match &self.config.requote_policy {
+            Some(policy) if request.is_market_request() => self.send_with_requote_retry(request, policy).await,
+            _ => self.send_trade_and_wait_once(request).await,
+        }
+    }
+
+    /// 按重试策略提交市价单，每次重新报价后发出 `Mt4Event::Requote`
+    async fn send_with_requote_retry(&self, mut request: TradeRequest, policy: &RequotePolicy) -> Result<TradeResponse> {
+        let base_slippage = request.slippage;
+        let mut attempt = 0;
+        loop {
+            match self.send_trade_and_wait_once(request.clone()).await {
+                Err(Mt4Error::Trade { code, message }) if is_requote_code(code) && attempt < policy.max_retries => {
+                    attempt += 1;
+                    request.slippage = policy.slippage_for(base_slippage, attempt);
+                    // 指定了价格的请求 (立即执行) 改用最新报价
+                    if request.price > 0.0 {
+                        let (bid, ask) = self.quote_price(&request.symbol).await?;
+                        request.price = if request.order_type.is_buy() { ask } else { bid };
+                    }
+                    tracing::info!(
+                        "🔁 [重新报价] {} {} ({}: {})，第 {} 次重试，滑点 {}",
+                        request.order_type.name(),
+                        request.symbol,
+                        code,
+                        message,
+                        attempt,
+                        request.slippage
+                    );
+                    self.emit(Mt4Event::Requote {
+                        symbol: request.symbol.clone(),
+                        code,
+                        attempt,
+                        slippage: request.slippage,
+                        price: request.price,
+                    })
+                    .await;
+                    tokio::time::sleep(policy.retry_delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 发送交易请求并等待服务器响应 (不重试)
+    async fn send_trade_and_wait_once(&self, mut request: TradeRequest) -> Result<TradeResponse> {
+        let request_id = self.request_tracker.next_id();
+        request.request_id = request_id;
+        let ticket = request.ticket;
+
+        // 先注册等待者，避免响应先于注册到达
+        let rx = self.request_tracker.register_waiter(request_id).await;
+        let dispatched = self.dispatch_trade(request).await;
+        Self::await_trade_response(&self.request_tracker, request_id, ticket, dispatched, rx).await
+    }
+
+    /// 批量发送交易请求，按关联到的顺序返回每个请求的结果 `(索引, 结果)`
+    ///
+    /// 请求按顺序分配 request_id 并依次发送，相邻请求间隔 `ClientConfig::batch_order_delay`；
+    /// 全部发出后返回的流在各请求的响应到达时产生结果，索引对应 `requests` 中的位置。
+    ///
+    /// ```no_run
+    /// # async fn example(client: &mt4_client::Mt4Client) -> mt4_client::Result<()> {
+    /// use futures_util::StreamExt;
+    /// use mt4_client::{Symbol, TradeRequest};
+    ///
+    /// let symbol = Symbol::new("EURUSD")?;
+    /// let requests = vec![TradeRequest::buy(&symbol, 0.01, 0.0, 0.0), TradeRequest::sell(&symbol, 0.01, 0.0, 0.0)];
+    /// let mut results = client.send_trades(requests).await;
+    /// while let Some((index, result)) = results.next().await {
+    ///     println!("#{}: {:?}", index, result.map(|r| r.status));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_trades(
+        &self,
+        requests: Vec<TradeRequest>,
+    ) -> impl Stream<Item = (usize, Result<TradeResponse>)> + Send + 'static {
+        let pending = FuturesUnordered::new();
+        let count = requests.len();
+        for (index, mut request) in requests.into_iter().enumerate() {
+            let request_id = self.request_tracker.next_id();
+            request.request_id = request_id;
+            let ticket = request.ticket;
+
+            let rx = self.request_tracker.register_waiter(request_id).await;
+            let dispatched = self.dispatch_trade(request).await;
+            let tracker = self.request_tracker.clone();
+            pending.push(async move {
+                (index, Self::await_trade_response(&tracker, request_id, ticket, dispatched, rx).await)
+            });
+
+            if index + 1 < count && !self.config.batch_order_delay.is_zero() {
+                tokio::time::sleep(self.config.batch_order_delay).await;
+            }
+        }
+        pending
+    }
+
+    /// 根据发送结果等待已注册的交易响应 (发送失败或重复时取消等待)
+    pub(crate) async fn await_trade_response(
+        tracker: &RequestTracker,
+        request_id: i32,
+        ticket: Ticket,
+        dispatched: Result<(i32, bool)>,
+        rx: oneshot::Receiver<Result<TradeResponse>>,
+    ) -> Result<TradeResponse> {
+        let (_, is_duplicate) = match dispatched {
+            Ok(r) => r,
+            Err(e) => {
+                tracker.cancel_waiter(request_id).await;
+                return Err(e);
+            }
+        };
+        if is_duplicate {
+            tracker.cancel_waiter(request_id).await;
+            return Err(Mt4Error::InvalidParams(format!(
+                "ticket #{} 已有待确认操作",
+                ticket
+            )));
+        }
+
+        rx.await.unwrap_or(Err(Mt4Error::Timeout))
+    }
+
     /// 发送交易请求 (简化版，兼容旧接口)
     /// 返回 Result<()>，隐藏 request_id 和重复检测
     pub async fn send_trade_simple(&self, request: TradeRequest) -> Result<()> {
@@ -928,18 +1673,67 @@ impl Mt4Client {
         }
     }
 
+    /// 应用配置的默认滑点 (便捷方法构建的请求)
+    fn with_default_slippage(&self, mut request: TradeRequest) -> TradeRequest {
+        request.slippage = self.default_slippage();
+        request
+    }
+
     /// 市价买入
     pub async fn buy(&self, symbol: &str, volume: f64, sl: Option<f64>, tp: Option<f64>) -> Result<()> {
-        let request = TradeRequest::buy(symbol, volume, sl.unwrap_or(0.0), tp.unwrap_or(0.0));
+        let request = self.with_default_slippage(TradeRequest::buy(&Symbol::new(symbol)?, volume, sl.unwrap_or(0.0), tp.unwrap_or(0.0)));
         self.send_trade_simple(request).await
     }
 
     /// 市价卖出
     pub async fn sell(&self, symbol: &str, volume: f64, sl: Option<f64>, tp: Option<f64>) -> Result<()> {
-        let request = TradeRequest::sell(symbol, volume, sl.unwrap_or(0.0), tp.unwrap_or(0.0));
+        let request = self.with_default_slippage(TradeRequest::sell(&Symbol::new(symbol)?, volume, sl.unwrap_or(0.0), tp.unwrap_or(0.0)));
         self.send_trade_simple(request).await
     }
 
+    /// 按风险比例市价买入，返回发送的手数
+    ///
+    /// 以最新账户净值的 `risk_percent`% 作为止损时的最大亏损，
+    /// 按当前 ask 到 `sl` 的距离和品种规格计算手数 (见 `position_size`)
+    pub async fn buy_risk(&self, symbol: &str, risk_percent: f64, sl: f64, tp: Option<f64>) -> Result<f64> {
+        self.open_with_risk(symbol, true, risk_percent, sl, tp).await
+    }
+
+    /// 按风险比例市价卖出，返回发送的手数
+    pub async fn sell_risk(&self, symbol: &str, risk_percent: f64, sl: f64, tp: Option<f64>) -> Result<f64> {
+        self.open_with_risk(symbol, false, risk_percent, sl, tp).await
+    }
+
+    async fn open_with_risk(&self, symbol: &str, buy: bool, risk_percent: f64, sl: f64, tp: Option<f64>) -> Result<f64> {
+        let symbol = Symbol::new(symbol)?;
+        let equity = self
+            .account_info()
+            .await
+            .map(|a| a.equity)
+            .ok_or_else(|| Mt4Error::InvalidParams("尚未收到账户信息，无法按风险计算手数".to_string()))?;
+        let (bid, ask) = self.quote_price(&symbol).await?;
+        let entry = if buy { ask } else { bid };
+        if sl <= 0.0 || (if buy { sl >= entry } else { sl <= entry }) {
+            return Err(Mt4Error::InvalidParams(format!(
+                "止损 {} 应在{}价格 {} {}方",
+                sl,
+                if buy { "买入" } else { "卖出" },
+                entry,
+                if buy { "下" } else { "上" }
+            )));
+        }
+        let spec = self.symbol_info_or_default(&symbol, 5).await;
+        let volume = position_size(equity, risk_percent, entry - sl, &spec)?;
+        let request = if buy {
+            TradeRequest::buy(&symbol, volume, sl, tp.unwrap_or(0.0))
+        } else {
+            TradeRequest::sell(&symbol, volume, sl, tp.unwrap_or(0.0))
+        };
+        tracing::info!("📐 [按风险开仓] {} {} 手 (净值 {:.2} 的 {}%，止损 {})", symbol, volume, equity, risk_percent, sl);
+        self.send_trade_simple(self.with_default_slippage(request)).await?;
+        Ok(volume)
+    }
+
     /// 限价买入
     pub async fn buy_limit(
         &self,
@@ -949,7 +1743,7 @@ impl Mt4Client {
         sl: Option<f64>,
         tp: Option<f64>,
     ) -> Result<()> {
-        let request = TradeRequest::buy_limit(symbol, volume, price, sl.unwrap_or(0.0), tp.unwrap_or(0.0));
+        let request = TradeRequest::buy_limit(&Symbol::new(symbol)?, volume, price, sl.unwrap_or(0.0), tp.unwrap_or(0.0));
         self.send_trade_simple(request).await
     }
 
@@ -962,13 +1756,13 @@ impl Mt4Client {
         sl: Option<f64>,
         tp: Option<f64>,
     ) -> Result<()> {
-        let request = TradeRequest::sell_limit(symbol, volume, price, sl.unwrap_or(0.0), tp.unwrap_or(0.0));
+        let request = TradeRequest::sell_limit(&Symbol::new(symbol)?, volume, price, sl.unwrap_or(0.0), tp.unwrap_or(0.0));
         self.send_trade_simple(request).await
     }
 
     /// 平仓 (需要传入原订单方向，以便发送反向平仓)
-    pub async fn close_order(&self, ticket: i32, symbol: &str, volume: f64) -> Result<()> {
-        let request = TradeRequest::close(ticket, symbol, volume);
+    pub async fn close_order(&self, ticket: Ticket, symbol: &str, volume: f64) -> Result<()> {
+        let request = self.with_default_slippage(TradeRequest::close(ticket, &Symbol::new(symbol)?, volume));
         tracing::info!(
             "Sending close: ticket={}, symbol={}, volume={}",
             ticket, symbol, volume
@@ -976,9 +1770,512 @@ impl Mt4Client {
         self.send_trade_simple(request).await
     }
 
+    /// 请求品种当前报价，返回 (bid, ask)
+    ///
+    /// 发送 type=0 的报价请求，服务器在交易响应中返回两个价格
+    pub async fn request_price(&self, symbol: &str) -> Result<(f64, f64)> {
+        self.quote_price(&Symbol::new(symbol)?).await
+    }
+
+    async fn quote_price(&self, symbol: &Symbol) -> Result<(f64, f64)> {
+        let response = self.send_trade_and_wait_once(TradeRequest::quote(symbol, 0.01)).await?;
+        let bid = response.price1.min(response.price2);
+        let ask = response.price1.max(response.price2);
+        if bid <= 0.0 {
+            return Err(Mt4Error::Protocol(format!("{} 报价响应无有效价格", symbol)));
+        }
+        Ok((bid, ask))
+    }
+
+    /// 请求报价快照 (Command 8)，返回市场报价中所有品种的当前报价
+    ///
+    /// 用于在报价推送到达前取得初始价格；每条报价同时作为 `Mt4Event::Quote` 事件发出
+    pub async fn request_quotes(&self) -> Result<Vec<Quote>> {
+        let (error_code, data) = self.request(Command::QuotesRequest, &[]).await?;
+        if error_code != 0 {
+            return Err(Mt4Error::Server(format!("报价快照请求失败: error_code={}", error_code)));
+        }
+        Ok(Quote::parse_all(&data))
+    }
+
+    /// 把品种加入市场报价 (Command 26)
+    ///
+    /// 部分经纪商只推送市场报价中品种的报价。品种记录在本地，已认证时立即发送，
+    /// 之后每次认证成功 (包括重连) 都会重新发送完整列表
+    pub async fn add_to_market_watch<I, T>(&self, symbols: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let symbols = symbols.into_iter().map(|s| Symbol::new(s.as_ref())).collect::<Result<Vec<_>>>()?;
+        self.market_watch.write().await.extend(symbols.iter().cloned());
+        self.send_market_watch(true, &symbols).await
+    }
+
+    /// 把品种移出市场报价 (Command 26)
+    pub async fn remove_from_market_watch<I, T>(&self, symbols: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let symbols = symbols.into_iter().map(|s| Symbol::new(s.as_ref())).collect::<Result<Vec<_>>>()?;
+        let mut watch = self.market_watch.write().await;
+        for symbol in &symbols {
+            watch.remove(symbol);
+        }
+        drop(watch);
+        self.send_market_watch(false, &symbols).await
+    }
+
+    /// 市场报价中的品种 (按名称排序)
+    pub async fn market_watch(&self) -> Vec<Symbol> {
+        self.market_watch.read().await.iter().cloned().collect()
+    }
+
+    /// 已认证时发送市场报价变更，未认证时只更新本地列表
+    async fn send_market_watch(&self, show: bool, symbols: &[Symbol]) -> Result<()> {
+        if symbols.is_empty() || !self.is_authenticated() {
+            return Ok(());
+        }
+        self.send_command(Command::QuoteSubscribe, &packet::encode_market_watch(show, symbols)).await
+    }
+
+    /// 开启离线交易意图队列 (持久化到指定文件)
+    ///
+    /// 文件中已有的意图会被加载，在下次调用 `flush_intents()` 时执行
+    pub fn enable_intent_queue(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let queue = IntentQueue::open(path)?;
+        if !queue.is_empty() {
+            tracing::info!("Loaded {} queued trade intent(s)", queue.len());
+        }
+        self.intent_queue = Some(Arc::new(Mutex::new(queue)));
+        Ok(())
+    }
+
+    /// 获取离线队列中等待执行的意图
+    pub async fn queued_intents(&self) -> Vec<TradeIntent> {
+        match &self.intent_queue {
+            Some(queue) => queue.lock().await.intents().to_vec(),
+            None => Vec::new(),
+        }
+    }
+
+    /// 提交交易意图
+    ///
+    /// - 已认证时立即检查有效条件并执行
+    /// - 未连接且开启了离线队列时写入队列，返回 `IntentOutcome::Queued`
+    /// - 未连接且未开启离线队列时返回 `Mt4Error::NotConnected`
+    pub async fn submit_intent(&self, intent: TradeIntent) -> Result<IntentOutcome> {
+        if self.is_authenticated() {
+            let outcome = self.execute_intent(intent).await;
+            self.report_intent_failure(&outcome).await;
+            return Ok(outcome);
+        }
+        match &self.intent_queue {
+            Some(queue) => {
+                let id = queue.lock().await.push(intent)?;
+                tracing::info!("Trade intent #{} queued while offline", id);
+                Ok(IntentOutcome::Queued(id))
+            }
+            None => Err(Mt4Error::NotConnected),
+        }
+    }
+
+    /// 执行离线队列中的交易意图
+    ///
+    /// `connect()` / `connect_bridge()` 认证成功后会自动调用，通常无需手动调用。
+    /// 按提交顺序逐个执行，每个意图都会发出 `IntentExecuted` / `IntentExpired` / `IntentFailed` 事件。
+    /// 执行中途连接断开时，当前意图放回队首 (不发出 `IntentFailed`)，剩余意图保留到下次认证。
+    pub async fn flush_intents(&self) -> Result<Vec<IntentOutcome>> {
+        let Some(queue) = &self.intent_queue else {
+            return Ok(Vec::new());
+        };
+
+        let mut outcomes = Vec::new();
+        while self.is_authenticated() {
+            let Some(intent) = queue.lock().await.pop_front()? else {
+                break;
+            };
+
+            let outcome = self.execute_intent(intent.clone()).await;
+            if matches!(&outcome, IntentOutcome::Failed { error, .. } if error.kind() == ErrorKind::Connection) {
+                tracing::info!("Trade intent #{} requeued after connection loss", intent.id);
+                queue.lock().await.push_front(intent)?;
+                break;
+            }
+            self.report_intent_failure(&outcome).await;
+            outcomes.push(outcome);
+        }
+        Ok(outcomes)
+    }
+
+    /// 检查有效条件并执行单个交易意图 (失败时由调用方决定丢弃还是放回队列)
+    async fn execute_intent(&self, intent: TradeIntent) -> IntentOutcome {
+        let intent_id = intent.id;
+
+        let expired = match intent.check_age(unix_now()) {
+            Some(reason) => Some(reason),
+            None if intent.has_price_bounds() => match self.request_price(&intent.request.symbol).await {
+                Ok((bid, ask)) => intent.check_price(bid, ask),
+                Err(error) => return IntentOutcome::Failed { intent_id, error },
+            },
+            None => None,
+        };
+        if let Some(reason) = expired {
+            tracing::warn!("Trade intent #{} expired: {}", intent_id, reason);
+            self.emit(Mt4Event::IntentExpired { intent_id, reason: reason.clone() }).await;
+            return IntentOutcome::Expired { intent_id, reason };
+        }
+
+        match self.send_trade_and_wait(intent.request).await {
+            Ok(response) => {
+                let request_id = response.request_id;
+                self.emit(Mt4Event::IntentExecuted { intent_id, request_id }).await;
+                IntentOutcome::Executed { intent_id, request_id }
+            }
+            Err(error) => IntentOutcome::Failed { intent_id, error },
+        }
+    }
+
+    /// 为被丢弃的失败意图发出 `IntentFailed` 事件
+    async fn report_intent_failure(&self, outcome: &IntentOutcome) {
+        if let IntentOutcome::Failed { intent_id, error } = outcome {
+            tracing::warn!("Trade intent #{} failed: {}", intent_id, error);
+            self.emit(Mt4Event::IntentFailed { intent_id: *intent_id, message: error.to_string() }).await;
+        }
+    }
+
+    /// 发送客户端自身产生的事件
+    async fn emit(&self, event: Mt4Event) {
+        if let Some(tx) = &self.event_tx {
+            let _ = tx.send(event).await;
+        }
+    }
+
+    /// 部分平仓
+    ///
+    /// 根据缓存的订单和品种手数规格校验平仓手数，发送部分平仓请求，
+    /// 并等待服务器为剩余手数开出的新订单 (注释为 "from #<ticket>")
+    ///
+    /// # 参数
+    /// - `ticket`: 要部分平仓的持仓订单号
+    /// - `volume`: 平仓手数 (必须小于持仓手数，且剩余手数不低于最小手数)
+    pub async fn close_partial(&self, ticket: Ticket, volume: f64) -> Result<PartialClose> {
+        let order = self.cached_order(ticket).await.ok_or_else(|| {
+            Mt4Error::InvalidParams(format!("订单 #{} 不在本地持仓缓存中", ticket))
+        })?;
+        let spec = self.symbol_info_or_default(&order.symbol, order.digits).await;
+        let remaining_volume = Self::validate_partial_volume(&order, &spec, volume)?;
+
+        // 在发送前注册剩余订单等待者；同一订单已有进行中的部分平仓时拒绝，避免剩余订单交给错误的调用方
+        let (tx, mut rx) = oneshot::channel();
+        {
+            let mut waiters = self.remainder_waiters.lock().await;
+            if waiters.get(&ticket).is_some_and(|waiter| !waiter.is_closed()) {
+                return Err(Mt4Error::InvalidParams(format!("订单 #{} 已有进行中的部分平仓", ticket)));
+            }
+            waiters.insert(ticket, tx);
+        }
+
+        tracing::info!(
+            "Sending partial close: ticket={}, symbol={}, volume={} of {}",
+            ticket, order.symbol, volume, order.volume
+        );
+        let request = self.with_default_slippage(TradeRequest::close(ticket, &order.symbol, volume));
+        let response = match self.send_trade_and_wait(request).await {
+            Ok(r) => r,
+            Err(e) => {
+                self.remainder_waiters.lock().await.remove(&ticket);
+                return Err(e);
+            }
+        };
+
+        let remaining_order = match tokio::time::timeout(
+            std::time::Duration::from_secs(REMAINDER_WAIT_SECS),
+            &mut rx,
+        )
+        .await
+        {
+            Ok(Ok(order)) => Some(order),
+            _ => {
+                tracing::warn!("部分平仓 #{} 未在 {} 秒内收到剩余订单", ticket, REMAINDER_WAIT_SECS);
+                None
+            }
+        };
+        self.remainder_waiters.lock().await.remove(&ticket);
+
+        Ok(PartialClose {
+            ticket,
+            closed_volume: volume,
+            remaining_volume,
+            remaining_ticket: remaining_order.as_ref().map(|o| o.ticket),
+            remaining_order,
+            response,
+        })
+    }
+
+    /// 校验部分平仓手数，返回剩余手数
+    fn validate_partial_volume(order: &Order, spec: &SymbolInfo, volume: f64) -> Result<f64> {
+        if order.is_pending() {
+            return Err(Mt4Error::InvalidParams(format!(
+                "订单 #{} 是挂单，不能部分平仓",
+                order.ticket
+            )));
+        }
+        if !volume.is_finite() || volume <= 0.0 {
+            return Err(Mt4Error::InvalidParams(format!("无效的平仓手数: {}", volume)));
+        }
+        if volume >= order.volume {
+            return Err(Mt4Error::InvalidParams(format!(
+                "平仓手数 {} 不小于持仓手数 {}，请使用 close_order 全部平仓",
+                volume, order.volume
+            )));
+        }
+        if volume < spec.lot_min || !spec.is_volume_on_step(volume) {
+            return Err(Mt4Error::InvalidParams(format!(
+                "平仓手数 {} 不符合 {} 的手数规格 (最小 {}, 步长 {})",
+                volume, spec.symbol, spec.lot_min, spec.lot_step
+            )));
+        }
+        let remaining = ((order.volume - volume) / spec.lot_step).round() * spec.lot_step;
+        if remaining < spec.lot_min {
+            return Err(Mt4Error::InvalidParams(format!(
+                "剩余手数 {} 低于 {} 的最小手数 {}",
+                remaining, spec.symbol, spec.lot_min
+            )));
+        }
+        Ok(remaining)
+    }
+
+    /// 平掉本地缓存中的所有持仓 (不包括挂单)
+    ///
+    /// # 参数
+    /// - `max_concurrency`: 同时进行中的平仓请求数量上限
+    pub async fn close_all(&self, max_concurrency: usize) -> CloseAllSummary {
+        self.close_positions(None, max_concurrency).await
+    }
+
+    /// 平掉本地缓存中指定品种的所有持仓 (不包括挂单)
+    pub async fn close_all_for_symbol(&self, symbol: &str, max_concurrency: usize) -> CloseAllSummary {
+        self.close_positions(Some(symbol), max_concurrency).await
+    }
+
+    /// 按品种筛选持仓并以有限并发平仓
+    async fn close_positions(&self, symbol: Option<&str>, max_concurrency: usize) -> CloseAllSummary {
+        let positions: Vec<Order> = self
+            .open_orders()
+            .await
+            .into_iter()
+            .filter(|o| !o.is_pending())
+            .filter(|o| symbol.is_none_or(|s| o.symbol == s))
+            .collect();
+
+        tracing::info!(
+            "Closing {} position(s){} with concurrency {}",
+            positions.len(),
+            symbol.map(|s| format!(" for {}", s)).unwrap_or_default(),
+            max_concurrency
+        );
+
+        let results: Vec<(Order, Result<()>)> = stream::iter(positions)
+            .map(|order| async move {
+                let result = self.close_with_retry(&order).await;
+                (order, result)
+            })
+            .buffer_unordered(max_concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut summary = CloseAllSummary::default();
+        for (order, result) in results {
+            match result {
+                Ok(()) => summary.closed.push(order.ticket),
+                Err(error) => {
+                    tracing::warn!("Failed to close #{} {}: {}", order.ticket, order.symbol, error);
+                    summary.failed.push(CloseFailure {
+                        ticket: order.ticket,
+                        symbol: order.symbol,
+                        error,
+                    });
+                }
+            }
+        }
+        summary
+    }
+
+    /// 平仓单个订单，遇到重新报价/价格变化/服务器繁忙时重试
+    async fn close_with_retry(&self, order: &Order) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            let request = self.with_default_slippage(TradeRequest::close(order.ticket, &order.symbol, order.volume));
+            match self.send_trade_and_wait(request).await {
+                Ok(_) => return Ok(()),
+                Err(e)
+                    if matches!(e.kind(), ErrorKind::Busy | ErrorKind::Requote | ErrorKind::OffQuotes)
+                        && attempt < CLOSE_MAX_ATTEMPTS =>
+                {
+                    tracing::info!("Close #{} attempt {} failed ({}), retrying", order.ticket, attempt, e);
+                    attempt += 1;
+                    tokio::time::sleep(std::time::Duration::from_millis(CLOSE_RETRY_DELAY_MS)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// 为持仓开启 (或更新) 移动止损
+    ///
+    /// 之后读取任务每收到该品种的报价 (Command 8) 就按报价移动止损，重连后沿用同一跟踪列表；
+    /// 持仓平仓后自动停止跟踪
+    pub async fn trail_stop(&self, ticket: Ticket, stop: TrailingStop) -> Result<()> {
+        match self.cached_order(ticket).await {
+            Some(order) if !order.is_pending() => {
+                self.trailing.lock().await.track(ticket, stop);
+                Ok(())
+            }
+            Some(_) => Err(Mt4Error::InvalidParams(format!("订单 #{} 是挂单，不能移动止损", ticket))),
+            None => Err(Mt4Error::InvalidParams(format!("订单 #{} 不在本地缓存中", ticket))),
+        }
+    }
+
+    /// 停止持仓的移动止损，返回是否曾在跟踪
+    pub async fn cancel_trailing(&self, ticket: Ticket) -> bool {
+        self.trailing.lock().await.untrack(ticket)
+    }
+
+    /// 正在移动止损的持仓
+    pub async fn trailing_tickets(&self) -> Vec<Ticket> {
+        self.trailing.lock().await.tracked()
+    }
+
+    /// 根据报价立即移动已跟踪持仓的止损并等待结果，返回每个修改请求的结果
+    ///
+    /// 收到的实时报价已由读取任务自动处理；用于按其他来源的价格 (如外部行情) 移动止损。
+    /// 已平仓的持仓停止跟踪，部分平仓的持仓转到剩余手数的新 ticket；
+    /// 重连后尚未收到持仓快照的持仓暂不处理，快照到达后自动恢复
+    pub async fn apply_trailing(&self, quote: &Quote) -> Vec<(Ticket, Result<TradeResponse>)> {
+        let updates = trailing_updates(&self.trailing, &self.positions, &self.lifecycle, quote).await;
+        let mut results = Vec::with_capacity(updates.len());
+        for update in updates {
+            tracing::info!("📈 [移动止损] #{} SL -> {}", update.ticket, update.sl);
+            results.push((update.ticket, self.modify_order(update.ticket, update.sl, update.tp).await));
+        }
+        results
+    }
+
+    /// 修改订单的止损/止盈 (挂单保持原价格)
+    pub async fn modify_order(&self, ticket: Ticket, sl: f64, tp: f64) -> Result<TradeResponse> {
+        let order = self
+            .cached_order(ticket)
+            .await
+            .ok_or_else(|| Mt4Error::InvalidParams(format!("订单 #{} 不在本地缓存中", ticket)))?;
+        let request = TradeRequest::modify(ticket, &order.symbol, order.order_type, order.open_price, sl, tp);
+        self.send_trade_and_wait(request).await
+    }
+
+    /// 对本地缓存中所有符合条件的持仓和挂单执行修改，以有限并发发送
+    ///
+    /// 修改后与当前止损/止盈相同的订单不发送请求，计入 `unchanged`
+    ///
+    /// ```no_run
+    /// # async fn example(client: &mt4_client::Mt4Client) {
+    /// use mt4_client::ModifyAction;
+    /// // 新闻发布前把所有 EURUSD 止损放宽 10 点
+    /// let summary = client.modify_all(|o| o.symbol == "EURUSD", ModifyAction::OffsetPips(-10.0), 4).await;
+    /// assert!(summary.is_complete());
+    /// # }
+    /// ```
+    pub async fn modify_all<F>(&self, filter: F, action: ModifyAction, max_concurrency: usize) -> ModifySummary
+    where
+        F: Fn(&Order) -> bool,
+    {
+        let mut summary = ModifySummary::default();
+        let mut requests = Vec::new();
+        for order in self.open_orders().await.into_iter().filter(|o| filter(o)) {
+            let (sl, tp) = action.apply(&order);
+            if sl == order.sl && tp == order.tp {
+                summary.unchanged.push(order.ticket);
+            } else {
+                requests.push(TradeRequest::modify(order.ticket, &order.symbol, order.order_type, order.open_price, sl, tp));
+            }
+        }
+
+        tracing::info!(
+            "Modifying {} order(s) ({:?}) with concurrency {}, {} unchanged",
+            requests.len(),
+            action,
+            max_concurrency,
+            summary.unchanged.len()
+        );
+
+        let results: Vec<(TradeRequest, Result<TradeResponse>)> = stream::iter(requests)
+            .map(|request| async move {
+                let result = self.send_trade_and_wait(request.clone()).await;
+                (request, result)
+            })
+            .buffer_unordered(max_concurrency.max(1))
+            .collect()
+            .await;
+
+        for (request, result) in results {
+            match result {
+                Ok(_) => summary.modified.push(request.ticket),
+                Err(error) => {
+                    tracing::warn!("Failed to modify #{} {}: {}", request.ticket, request.symbol, error);
+                    summary.failed.push(ModifyFailure {
+                        ticket: request.ticket,
+                        symbol: request.symbol,
+                        error,
+                    });
+                }
+            }
+        }
+        summary.modified.sort_unstable();
+        summary
+    }
+
+    /// 删除本地缓存中所有符合条件的挂单，以有限并发发送 (type=72)
+    ///
+    /// ```no_run
+    /// # async fn example(client: &mt4_client::Mt4Client) {
+    /// // 撤销网格策略在 EURUSD 上的所有挂单
+    /// let summary = client.cancel_all_pending(|o| o.symbol == "EURUSD" && o.comment.starts_with("grid"), 4).await;
+    /// println!("cancelled {:?}", summary.cancelled);
+    /// # }
+    /// ```
+    pub async fn cancel_all_pending<F>(&self, filter: F, max_concurrency: usize) -> CancelSummary
+    where
+        F: Fn(&Order) -> bool,
+    {
+        let pending: Vec<Order> = self.positions.pending_orders().await.into_iter().filter(|o| filter(o)).collect();
+        tracing::info!("Cancelling {} pending order(s) with concurrency {}", pending.len(), max_concurrency);
+
+        let results: Vec<(Order, Result<TradeResponse>)> = stream::iter(pending)
+            .map(|order| async move {
+                let result = self.send_trade_and_wait(TradeRequest::cancel(order.ticket, &order.symbol)).await;
+                (order, result)
+            })
+            .buffer_unordered(max_concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut summary = CancelSummary::default();
+        for (order, result) in results {
+            match result {
+                Ok(_) => summary.cancelled.push(order.ticket),
+                Err(error) => {
+                    tracing::warn!("Failed to cancel #{} {}: {}", order.ticket, order.symbol, error);
+                    summary.failed.push(CancelFailure { ticket: order.ticket, symbol: order.symbol, error });
+                }
+            }
+        }
+        summary.cancelled.sort_unstable();
+        summary
+    }
+
     /// 取消挂单
-    pub async fn cancel_order(&self, ticket: i32, symbol: &str) -> Result<()> {
-        let request = TradeRequest::cancel(ticket, symbol);
+    pub async fn cancel_order(&self, ticket: Ticket, symbol: &str) -> Result<()> {
+        let request = TradeRequest::cancel(ticket, &Symbol::new(symbol)?);
         tracing::info!("Sending cancel: ticket={}, symbol={}", ticket, symbol);
         self.send_trade_simple(request).await
     }
@@ -1016,81 +2313,1930 @@ impl Mt4Client {
         self.send_command(Command::OrdersRequest, &[]).await
     }
 
-    /// 请求指定时间范围的订单历史
+    /// 请求指定时间范围的完整订单历史 (Command 6)
+    ///
+    /// 按 `HistoryDownload` 的默认参数分页请求，返回按 ticket 去重、按平仓时间升序的订单。
+    /// 需要调整分页时使用 `download_order_history`。
     ///
     /// # 参数
     /// - `start_time`: 开始时间（Unix时间戳，秒）
     /// - `end_time`: 结束时间（Unix时间戳，秒）
     ///
     /// # 示例
-    /// ```rust
+    /// ```no_run
+    /// # async fn example(client: &mt4_client::Mt4Client) -> mt4_client::Result<()> {
     /// // 获取最近7天的订单
     /// let now = std::time::SystemTime::now()
     ///     .duration_since(std::time::UNIX_EPOCH)
     ///     .unwrap()
-    ///     .as_secs() as i32;
+    ///     .as_secs() as i64;
     /// let seven_days_ago = now - 7 * 24 * 3600;
-    /// client.request_order_history_range(seven_days_ago, now).await?;
+    /// let orders = client.request_order_history_range(seven_days_ago, now).await?;
+    /// println!("{} orders", orders.len());
+    /// # Ok(())
+    /// # }
     /// ```
-    pub async fn request_order_history_range(&self, start_time: i32, end_time: i32) -> Result<()> {
-        // 构造8字节的数据包
-        // 前4字节: 开始时间（Unix时间戳，秒）
-        // 后4字节: 结束时间（Unix时间戳，秒）
-        let mut data = Vec::with_capacity(8);
-        data.extend_from_slice(&start_time.to_le_bytes());
-        data.extend_from_slice(&end_time.to_le_bytes());
-
-        self.send_command(Command::OrdersRequest, &data).await
+    pub async fn request_order_history_range(&self, start_time: i64, end_time: i64) -> Result<Vec<Order>> {
+        self.download_order_history(HistoryDownload::new(start_time, end_time)).await
     }
 
-    /// 接收下一个事件
-    pub async fn next_event(&mut self) -> Option<Mt4Event> {
-        if let Some(rx) = &mut self.event_rx {
-            rx.recv().await
-        } else {
-            None
+    /// 分页下载订单历史 (见 `history` 模块)
+    ///
+    /// 返回的订单数达到 `page_limit` 的页视为被截断，对半拆分区间后重新请求。任一页失败时返回错误。
+    pub async fn download_order_history(&self, download: HistoryDownload) -> Result<Vec<Order>> {
+        let mut windows: VecDeque<(i64, i64)> = download.pages().into();
+        let mut orders = Vec::new();
+
+        while let Some((from, to)) = windows.pop_front() {
+            let page = self.request_history_page(from, to).await?;
+            tracing::debug!("History page [{}, {}): {} orders", from, to, page.len());
+            let truncated = page.len() >= download.page_limit && to - from > 1;
+            merge_orders(&mut orders, page);
+            if truncated {
+                let mid = from + (to - from) / 2;
+                windows.push_front((mid, to));
+                windows.push_front((from, mid));
+            }
         }
-    }
 
-    /// 是否已连接
-    pub fn is_connected(&self) -> bool {
-        self.writer.is_some()
+        tracing::info!("Order history [{}, {}): {} orders", download.from, download.to, orders.len());
+        Ok(orders)
     }
 
-    /// 断开连接
-    pub async fn disconnect(&mut self) {
-        self.writer = None;
-        self.event_rx = None;
-        self.authenticated = false;
+    /// 请求一页订单历史 (Command 6)，等待服务器响应
+    async fn request_history_page(&self, from: i64, to: i64) -> Result<Vec<Order>> {
+        let (error_code, data) = self
+            .request_with_timeout(
+                Command::HistoryRequest,
+                &range_bytes(from, to),
+                Duration::from_secs(HISTORY_PAGE_TIMEOUT_SECS),
+            )
+            .await?;
+        if error_code != 0 {
+            return Err(Mt4Error::Server(format!("订单历史请求失败: error_code={}", error_code)));
+        }
+        Ok(Order::parse_list(&data))
     }
 
-    /// 解析账户信息响应 (command=3)
+    /// 请求一段K线历史 (Command 11)，等待服务器响应
     ///
-    /// 数据包结构 (根据 JS 源码):
-    /// - 账户信息头部 (约 254 字节，q.Vp=254)
-    /// - 品种信息 (254-1161)
-    /// - 报价信息 (1162+, q.Dk=1162)
-    fn parse_account_info(data: &[u8]) -> Option<AccountInfo> {
-        AccountInfo::from_bytes(data)
+    /// 单次请求的K线数量受服务器限制，长区间请使用 `download_candles` 分页下载
+    #[cfg(feature = "experimental-chart")]
+    pub async fn request_chart(&self, request: &ChartRequest) -> Result<Vec<Candle>> {
+        let (error_code, data) = self
+            .request_with_timeout(Command::ChartRequest, &request.to_bytes(), Duration::from_secs(CHART_PAGE_TIMEOUT_SECS))
+            .await?;
+        if error_code != 0 {
+            return Err(Mt4Error::Server(format!(
+                "{} K线请求失败: error_code={}",
+                request.symbol, error_code
+            )));
+        }
+        Ok(Candle::parse_all(&data))
     }
 
-    /// 判断订单是否已平仓
+    /// 分页下载K线历史
     ///
-    /// 判断逻辑:
-    /// 1. close_time > 0 表示已平仓 (最可靠)
-    /// 2. close_price > 0 且 != open_price 表示已平仓 (备用)
-    fn is_order_closed(order: &Order) -> bool {
-        // 方法1: 有明确的平仓时间
-        if order.close_time > 0 {
-            return true;
-        }
+    /// 每完成一页调用一次 `on_progress`；回调返回 `ControlFlow::Break(())` 时停止下载，
+    /// 返回已下载部分并将 `cancelled` 置为 true。任一页失败时返回错误。
+    ///
+    /// ```no_run
+    /// # use mt4_client::{ChartDownload, Mt4Client, Timeframe};
+    /// # use std::ops::ControlFlow;
+    /// # async fn example(client: &Mt4Client) -> mt4_client::Result<()> {
+    /// let download = ChartDownload::new("EURUSD", Timeframe::M1, 1_600_000_000, 1_700_000_000);
+    /// let result = client
+    ///     .download_candles(download, |p| {
+    ///         println!("{} bars, ~{} remaining", p.bars_fetched, p.estimated_remaining_bars);
+    ///         ControlFlow::Continue(())
+    ///     })
+    ///     .await?;
+    /// println!("{} candles", result.candles.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "experimental-chart")]
+    pub async fn download_candles<F>(&self, download: ChartDownload, mut on_progress: F) -> Result<CandleDownload>
+    where
+        F: FnMut(&ChartProgress) -> ControlFlow<()>,
+    {
+        let pages = download.pages();
+        let mut candles: Vec<Candle> = Vec::new();
 
-        // 方法2: close_price 有意义且不等于开仓价 (允许浮点误差)
-        if order.close_price > 0.0 && (order.close_price - order.open_price).abs() > 0.00001 {
-            return true;
+        for (i, page) in pages.iter().enumerate() {
+            let bars = self.request_chart(page).await?;
+            tracing::debug!(
+                "Chart page {}/{}: {} {} bars [{}, {})",
+                i + 1,
+                pages.len(),
+                page.symbol,
+                bars.len(),
+                page.from,
+                page.to
+            );
+            merge_page(&mut candles, bars, page);
+
+            let progress = ChartProgress::new(&download, i + 1, pages.len(), candles.len(), page.to);
+            if on_progress(&progress).is_break() {
+                tracing::info!("Chart download cancelled after {} bars", candles.len());
+                return Ok(CandleDownload { candles, cancelled: true });
+            }
+        }
+
+        Ok(CandleDownload { candles, cancelled: false })
+    }
+
+    /// 开启K线本地缓存 (见 `candle_cache` 模块)，之后 `request_candles` 只向服务器请求缓存中缺失的区间
+    #[cfg(feature = "experimental-chart")]
+    pub fn enable_candle_cache(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let cache = CandleCache::open(dir)?;
+        tracing::info!("Caching candles in {}", cache.dir().display());
+        if let Ok(mut current) = self.candle_cache.lock() {
+            *current = Some(cache);
+        }
+        Ok(())
+    }
+
+    /// 关闭K线本地缓存 (不删除已写入的文件)
+    #[cfg(feature = "experimental-chart")]
+    pub fn disable_candle_cache(&self) {
+        if let Ok(mut current) = self.candle_cache.lock() {
+            *current = None;
+        }
+    }
+
+    /// 获取一段K线历史，优先使用本地缓存
+    ///
+    /// 未开启缓存时等同于分页下载整个区间。开启后只分页下载缓存中缺失的区间，
+    /// 已收盘的K线写回缓存；未收盘的K线照常返回但不缓存。
+    #[cfg(feature = "experimental-chart")]
+    pub async fn request_candles(&self, request: &ChartRequest) -> Result<Vec<Candle>> {
+        let cache = self.candle_cache.lock().ok().and_then(|cache| cache.clone());
+        let Some(cache) = cache else {
+            let download = ChartDownload::new(&request.symbol, request.timeframe, request.from, request.to);
+            return Ok(self.download_candles(download, |_| ControlFlow::Continue(())).await?.candles);
+        };
+
+        let mut cached = cache.load(&request.symbol, request.timeframe)?;
+        let gaps = cached.missing(request.from, request.to);
+        let period = request.timeframe.seconds();
+        let server_now = self.drift_estimator().utc_to_server(SystemTime::now());
+        let mut forming = Vec::new();
+        for &(from, to) in &gaps {
+            let download = ChartDownload::new(&request.symbol, request.timeframe, from, to);
+            let candles = self.download_candles(download, |_| ControlFlow::Continue(())).await?.candles;
+            // 当前周期 (或最后一根) 的K线可能未收盘，不计入已下载区间
+            let settled_to = match server_now {
+                Some(now) => to.min(now - now.rem_euclid(period)),
+                None => candles.last().map_or(from, |last| to.min(last.time)),
+            };
+            let (settled, open): (Vec<Candle>, Vec<Candle>) = candles.into_iter().partition(|c| c.time < settled_to);
+            cached.insert(from, settled_to, settled);
+            forming.extend(open);
+        }
+        if !gaps.is_empty() {
+            cache.store(&request.symbol, request.timeframe, &cached)?;
+        }
+        tracing::debug!(
+            "{} candles [{}, {}): {} gaps fetched",
+            request.symbol,
+            request.from,
+            request.to,
+            gaps.len()
+        );
+
+        let mut candles = cached.range(request.from, request.to).to_vec();
+        candles.extend(forming);
+        candles.sort_by_key(|c| c.time);
+        candles.dedup_by_key(|c| c.time);
+        Ok(candles)
+    }
+
+    /// 请求一段逐笔报价 (Command 27)，等待服务器响应
+    ///
+    /// 数据布局为推断 (见 `backfill` 模块，需要 `experimental-ticks` 特性)，长区间请使用 `backfill_ticks` 分块下载
+    #[cfg(feature = "experimental-ticks")]
+    pub async fn request_ticks(&self, symbol: &str, from: i64, to: i64) -> Result<Vec<Quote>> {
+        let (error_code, data) = self
+            .request_with_timeout(
+                Command::QuoteHistory,
+                &tick_request_bytes(symbol, from, to),
+                Duration::from_secs(TICK_REQUEST_TIMEOUT_SECS),
+            )
+            .await?;
+        if error_code != 0 {
+            return Err(Mt4Error::Server(format!("{} 报价历史请求失败: error_code={}", symbol, error_code)));
+        }
+        Ok(Quote::parse_all(&data))
+    }
+
+    /// 按块回补K线历史 (控制请求频率、失败重试，见 `backfill` 模块)，按时间升序返回
+    #[cfg(feature = "experimental-chart")]
+    pub async fn backfill_candles(&self, timeframe: Timeframe, backfill: &Backfill) -> Result<Vec<Candle>> {
+        let chunks = backfill.chunks(backfill.candle_chunk_secs(timeframe));
+        let mut candles = self
+            .run_backfill(backfill, chunks, |from, to| {
+                let request = ChartRequest { symbol: backfill.symbol.clone(), timeframe, from, to };
+                async move {
+                    let bars = self.request_chart(&request).await?;
+                    Ok(bars.into_iter().filter(|c| c.time >= from && c.time < to).collect())
+                }
+            })
+            .await?;
+        candles.sort_by_key(|c| c.time);
+        candles.dedup_by_key(|c| c.time);
+        Ok(candles)
+    }
+
+    /// 按块回补逐笔报价 (控制请求频率、失败重试，见 `backfill` 模块)，按时间升序返回
+    #[cfg(feature = "experimental-ticks")]
+    pub async fn backfill_ticks(&self, backfill: &Backfill) -> Result<Vec<Quote>> {
+        let chunks = backfill.chunks(backfill.tick_chunk_secs());
+        let mut ticks = self
+            .run_backfill(backfill, chunks, |from, to| async move {
+                let ticks = self.request_ticks(&backfill.symbol, from, to).await?;
+                Ok(ticks.into_iter().filter(|q| q.time >= from && q.time < to).collect())
+            })
+            .await?;
+        ticks.sort_by_key(|q| q.time);
+        Ok(ticks)
+    }
+
+    /// 逐块执行回补: 保持请求间隔，失败的块退避后重试，发出进度事件
+    #[cfg(any(feature = "experimental-chart", feature = "experimental-ticks"))]
+    async fn run_backfill<T, F, Fut>(&self, backfill: &Backfill, chunks: Vec<(i64, i64)>, mut fetch: F) -> Result<Vec<T>>
+    where
+        F: FnMut(i64, i64) -> Fut,
+        Fut: std::future::Future<Output = Result<Vec<T>>>,
+    {
+        let mut items = Vec::new();
+        let mut retries = 0;
+        let mut next_request = tokio::time::Instant::now();
+        let progress = |chunks_done: usize, fetched: usize, covered_to: i64, retries: u32, error: Option<String>| {
+            Mt4Event::BackfillProgress(BackfillProgress {
+                symbol: backfill.symbol.clone(),
+                chunks_done,
+                chunks_total: chunks.len(),
+                fetched,
+                covered_to,
+                retries,
+                error,
+            })
+        };
+
+        for (i, &(from, to)) in chunks.iter().enumerate() {
+            let mut attempt = 0;
+            loop {
+                tokio::time::sleep_until(next_request).await;
+                next_request = tokio::time::Instant::now() + backfill.request_interval;
+                let error = match fetch(from, to).await {
+                    Ok(page) => {
+                        items.extend(page);
+                        break;
+                    }
+                    // 历史请求不改变账户状态，除参数错误外都可以重试
+                    Err(e) if attempt < backfill.max_retries && e.kind() != ErrorKind::InvalidRequest => e,
+                    Err(e) => {
+                        let symbol = &backfill.symbol;
+                        tracing::warn!("Backfill {} [{}, {}) failed after {} attempts: {}", symbol, from, to, attempt + 1, e);
+                        return Err(e);
+                    }
+                };
+                attempt += 1;
+                retries += 1;
+                tracing::debug!("Backfill {} [{}, {}) attempt {} failed: {}", backfill.symbol, from, to, attempt, error);
+                self.emit(progress(i, items.len(), from, retries, Some(error.to_string()))).await;
+                next_request = next_request.max(tokio::time::Instant::now() + backfill.retry_backoff(attempt));
+            }
+            self.emit(progress(i + 1, items.len(), to, retries, None)).await;
+        }
+
+        tracing::info!(
+            "Backfill {} [{}, {}): {} items, {} retries",
+            backfill.symbol,
+            backfill.from,
+            backfill.to,
+            items.len(),
+            retries
+        );
+        Ok(items)
+    }
+
+    /// 设置开仓请求的风险限制
+    ///
+    /// ```no_run
+    /// # async fn example(client: &mt4_client::Mt4Client) {
+    /// use mt4_client::RiskLimits;
+    /// let limits = RiskLimits::new().with_max_open_lots(5.0).with_max_daily_loss(500.0).with_downsize(true);
+    /// client.set_risk_limits(limits).await;
+    /// # }
+    /// ```
+    pub async fn set_risk_limits(&self, limits: RiskLimits) {
+        self.risk.lock().await.set_limits(limits);
+    }
+
+    /// 当前风险限制
+    pub async fn risk_limits(&self) -> RiskLimits {
+        self.risk.lock().await.limits().clone()
+    }
+
+    /// 设置策略的交易预算 (策略标签为注释中第一个 ':' 之前的部分，None 表示不限)
+    pub fn set_strategy_budget(&self, strategy: &str, budget: Option<RateBudget>) {
+        if let Ok(mut throttle) = self.throttle.lock() {
+            throttle.set_budget(strategy, budget);
+        }
+    }
+
+    /// 设置未单独配置的策略使用的默认交易预算 (None 表示不限)
+    pub fn set_default_trade_budget(&self, budget: Option<RateBudget>) {
+        if let Ok(mut throttle) = self.throttle.lock() {
+            throttle.set_default_budget(budget);
+        }
+    }
+
+    /// 策略当前窗口内剩余的交易次数 (不限时为 None)
+    pub fn strategy_budget_remaining(&self, strategy: &str) -> Option<u32> {
+        self.throttle.lock().ok()?.remaining(strategy, Instant::now())
+    }
+
+    /// 设置品种的点差上限 (价格单位，None 表示使用默认上限)，超过时发出 `Mt4Event::SpreadAlert`
+    pub fn set_spread_threshold(&self, symbol: &str, threshold: Option<f64>) {
+        if let Ok(mut monitor) = self.spread_monitor.lock() {
+            monitor.set_threshold(symbol, threshold);
+        }
+    }
+
+    /// 设置未单独配置的品种使用的默认点差上限 (None 表示不告警)
+    pub fn set_default_spread_threshold(&self, threshold: Option<f64>) {
+        if let Ok(mut monitor) = self.spread_monitor.lock() {
+            monitor.set_default_threshold(threshold);
+        }
+    }
+
+    /// 品种最近一次报价的点差 (尚无报价时为 None)
+    pub fn spread(&self, symbol: &str) -> Option<f64> {
+        self.spread_monitor.lock().ok()?.spread(symbol)
+    }
+
+    /// 品种点差是否超过上限
+    pub fn spread_too_wide(&self, symbol: &str) -> bool {
+        self.spread_monitor.lock().map(|monitor| monitor.is_too_wide(symbol)).unwrap_or(false)
+    }
+
+    /// 按周期由报价合成K线 (可多次调用添加多个周期)，收盘时发出 `Mt4Event::CandleClosed`
+    pub fn aggregate_candles(&self, period: Duration) {
+        if let Ok(mut candles) = self.candles.lock() {
+            candles.add_period(period);
+        }
+    }
+
+    /// 停止合成该周期的K线，返回之前是否在合成
+    pub fn stop_aggregating_candles(&self, period: Duration) -> bool {
+        self.candles.lock().map(|mut candles| candles.remove_period(period)).unwrap_or(false)
+    }
+
+    /// 品种在该周期正在合成 (未收盘) 的K线
+    pub fn current_candle(&self, symbol: &str, period: Duration) -> Option<Candle> {
+        self.candles.lock().ok()?.current(symbol, period)
+    }
+
+    /// 开始录制会话: 之后收到的每个解密入站帧都写入文件 (已在录制时切换到新文件)
+    pub fn record_session(&self, path: impl AsRef<Path>) -> Result<()> {
+        let recorder = SessionRecorder::create(path)?;
+        tracing::info!("Recording session to {}", recorder.path().display());
+        if let Ok(mut current) = self.recorder.lock() {
+            *current = Some(recorder);
+        }
+        Ok(())
+    }
+
+    /// 停止录制，返回已录制的帧数
+    pub fn stop_recording(&self) -> Option<usize> {
+        let recorder = self.recorder.lock().ok()?.take()?;
+        Some(recorder.frames())
+    }
+
+    /// 开始抓取原始数据包: 之后收发的每个数据包以加密和解密形式写入文件 (见 `capture` 模块，已在抓取时切换到新文件)
+    pub fn start_capture(&self, path: impl AsRef<Path>) -> Result<()> {
+        let capture = PacketCapture::create(path)?;
+        tracing::info!("Capturing packets to {}", capture.path().display());
+        if let Ok(mut current) = self.capture.lock() {
+            *current = Some(capture);
+        }
+        Ok(())
+    }
+
+    /// 停止抓取，返回已抓取的数据包数
+    pub fn stop_capture(&self) -> Option<usize> {
+        let capture = self.capture.lock().ok()?.take()?;
+        Some(capture.packets())
+    }
+
+    /// 开启交易日志: 之后收到的订单更新、交易响应和账户信息写入 SQLite 数据库 (见 `journal` 模块)
+    ///
+    /// 重复调用时切换到新的数据库
+    #[cfg(feature = "sqlite")]
+    pub fn enable_journal(&self, path: impl AsRef<Path>) -> Result<()> {
+        let journal = crate::journal::TradeJournal::open(&path)?;
+        tracing::info!("Trade journal: {}", path.as_ref().display());
+        if let Ok(mut current) = self.journal.lock() {
+            *current = Some(journal);
+        }
+        Ok(())
+    }
+
+    /// 关闭交易日志，返回之前是否已开启
+    #[cfg(feature = "sqlite")]
+    pub fn disable_journal(&self) -> bool {
+        self.journal.lock().ok().and_then(|mut j| j.take()).is_some()
+    }
+
+    /// 开启崩溃现场记录: 保留最近 `max_events` 个事件和 `max_frames` 个入站帧，
+    /// 进程 panic 时写入 `dir` (见 `forensics` 模块)
+    ///
+    /// 重复调用时替换为新的记录器
+    pub fn enable_crash_forensics(&self, dir: impl AsRef<Path>, max_events: usize, max_frames: usize) {
+        if let Ok(mut forensics) = self.forensics.lock() {
+            *forensics = Some(CrashForensics::new(dir, max_events, max_frames));
+        }
+        if !self.forensics_hook.swap(true, Ordering::SeqCst) {
+            install_panic_hook(self.forensics.clone());
+        }
+    }
+
+    /// 关闭崩溃现场记录
+    pub fn disable_crash_forensics(&self) {
+        if let Ok(mut forensics) = self.forensics.lock() {
+            *forensics = None;
+        }
+    }
+
+    /// 立即写出崩溃现场记录 (未开启时返回 InvalidParams)
+    pub fn dump_forensics(&self) -> Result<PathBuf> {
+        let forensics = self.forensics.lock().map_err(|_| Mt4Error::InvalidParams("崩溃记录不可用".to_string()))?;
+        let forensics = forensics
+            .as_ref()
+            .ok_or_else(|| Mt4Error::InvalidParams("未开启崩溃现场记录".to_string()))?;
+        forensics.dump(None)?;
+        Ok(forensics.dir().to_path_buf())
+    }
+
+    /// 回放录制的会话
+    ///
+    /// 在未连接的客户端上调用: 文件中的帧按顺序经过与实时连接相同的解析流程，
+    /// 更新本地状态并通过 `next_event()` 发出事件，回放结束后发出 `Mt4Event::Disconnected`。
+    /// 回放期间不会向服务器发送任何数据。返回帧数
+    pub async fn replay_session(&mut self, path: impl AsRef<Path>) -> Result<usize> {
+        if self.is_connected() {
+            return Err(Mt4Error::InvalidParams("回放需要在未连接的客户端上进行".to_string()));
+        }
+        let frames = read_session(path)?;
+        let count = frames.len();
+
+        let event_tx = self.open_event_channel();
+
+        // 写入端无人接收，处理器发出的请求被丢弃
+        let (writer, _) = mpsc::channel::<OutboundFrame>(1);
+        let server = self.server.clone().unwrap_or_default();
+        let mut handler = self.frame_handler(writer, event_tx, server);
+        handler.pending_auth = false;
+        handler.password_sent = true;
+
+        tokio::spawn(async move {
+            for frame in frames {
+                tracing::debug!(
+                    "Replay: +{}ms command={}, error={}, data_len={}",
+                    frame.elapsed_ms,
+                    frame.command,
+                    frame.error_code,
+                    frame.data.len()
+                );
+                handler.handle(frame.command, frame.error_code, frame.data.into()).await;
+            }
+            handler.authenticated.store(false, Ordering::SeqCst);
+            let _ = handler.event_tx.send(Mt4Event::Disconnected).await;
+        });
+
+        Ok(count)
+    }
+
+    /// 获取本地缓存的所有当前持仓和挂单
+    pub async fn open_orders(&self) -> Vec<Order> {
+        self.positions.all().await
+    }
+
+    /// 获取本地缓存中的指定订单
+    pub async fn cached_order(&self, ticket: Ticket) -> Option<Order> {
+        self.positions.get(ticket).await
+    }
+
+    /// 当前持仓 (市价单)，按 ticket 排序
+    pub async fn positions(&self) -> Vec<Order> {
+        self.positions.positions().await
+    }
+
+    /// 当前挂单，按 ticket 排序
+    pub async fn pending_orders(&self) -> Vec<Order> {
+        self.positions.pending_orders().await
+    }
+
+    /// 当前挂单按品种和价格排序的快照，用于价格距离查询
+    pub async fn pending_book(&self) -> PendingBook {
+        self.positions.pending_book().await
+    }
+
+    /// 持仓的保本价 (bid)，计入点差、佣金和利息
+    ///
+    /// `spread` 为当前 ask - bid；合约规格取自 `set_symbol_info()`，未设置时使用默认值
+    pub async fn breakeven(&self, ticket: Ticket, spread: f64) -> Option<Breakeven> {
+        self.mirror().breakeven(ticket, spread).await
+    }
+
+    /// 品种净持仓的保本价 (bid)，多空手数相等时为 None
+    pub async fn net_breakeven(&self, symbol: &str, spread: f64) -> Option<Breakeven> {
+        self.mirror().net_breakeven(symbol, spread).await
+    }
+
+    /// 订单生命周期状态 (未跟踪的订单为 None)
+    pub async fn order_state(&self, ticket: Ticket) -> Option<OrderState> {
+        self.lifecycle.lock().await.state(ticket)
+    }
+
+    /// 持仓管理器 (可在其他任务中共享)
+    pub fn position_manager(&self) -> &Arc<PositionManager> {
+        &self.positions
+    }
+
+    /// 设置品种交易规格 (手数最小值/最大值/步长)
+    pub async fn set_symbol_info(&self, info: SymbolInfo) {
+        self.symbols.write().await.insert(info.symbol.clone(), info);
+    }
+
+    /// 获取已设置的品种交易规格
+    pub async fn symbol_info(&self, symbol: &str) -> Option<SymbolInfo> {
+        self.symbols.read().await.get(symbol).cloned()
+    }
+
+    /// 价格变动 1 点时 1 手的盈亏 (账户货币，见 `pips` 模块)
+    ///
+    /// 需要已设置品种规格 (`set_symbol_info`)、已收到账户信息，且报价货币不是账户货币时
+    /// 需要已收到换算货币对的报价，否则返回 None
+    pub async fn point_value(&self, symbol: &str) -> Option<f64> {
+        let spec = self.symbol_info(symbol).await?;
+        let currency = self.account.read().await.as_ref()?.currency.clone();
+        let rates = self.cross_rates.lock().ok()?;
+        point_value_in_account_currency(&spec, &currency, &rates)
+    }
+
+    /// 估算开仓所需保证金并与可用保证金比较 (见 `margin` 模块)
+    ///
+    /// 按最近收到的报价计算 (买入用 ask，卖出用 bid，挂单同样按当前价格估算)，尚未收到该品种报价时向服务器请求报价。
+    /// 可用保证金 = 净值 - 按持仓估算的已用保证金 (`used_margin`)。
+    /// 尚未收到账户信息、该品种或任一持仓的品种没有设置规格 (`set_symbol_info`)、缺少换算汇率时
+    /// 返回 `Mt4Error::InvalidParams`，不使用默认规格估算
+    pub async fn check_margin(&self, symbol: &str, volume: f64, order_type: OrderType) -> Result<MarginCheck> {
+        let account = self
+            .account_info()
+            .await
+            .ok_or_else(|| Mt4Error::InvalidParams("尚未收到账户信息，无法估算保证金".to_string()))?;
+        let spec = self
+            .symbol_info(symbol)
+            .await
+            .ok_or_else(|| Mt4Error::InvalidParams(format!("未设置 {} 的品种规格，无法估算保证金", symbol)))?;
+        let used = estimate_used_margin(&account, &self.positions, &self.symbols, &self.cross_rates)
+            .await
+            .ok_or_else(|| Mt4Error::InvalidParams("无法估算持仓占用的保证金 (缺少品种规格或换算汇率)".to_string()))?;
+        let free_margin = live_equity(&account, &self.positions).await - used;
+        let cached = self.cross_rates.lock().ok().and_then(|rates| rates.price(symbol));
+        let (bid, ask) = match cached {
+            Some(price) => price,
+            None => self.quote_price(&Symbol::new(symbol)?).await?,
+        };
+        let price = if order_type.is_buy() { ask } else { bid };
+        let required = {
+            let rates = self.cross_rates.lock().map_err(|_| Mt4Error::InvalidParams("汇率表不可用".to_string()))?;
+            required_margin(&spec, volume, price, account.leverage, &account.currency, &rates)
+        }
+        .ok_or_else(|| {
+            Mt4Error::InvalidParams(format!(
+                "无法估算 {} 的保证金 (杠杆 {}，账户货币 {})",
+                symbol, account.leverage, account.currency
+            ))
+        })?;
+        let check = MarginCheck::new(symbol, volume, required, free_margin);
+        if !check.sufficient {
+            tracing::warn!(
+                "Margin check failed: {} {} lots needs {:.2}, free margin {:.2}",
+                symbol,
+                volume,
+                required,
+                free_margin
+            );
+        }
+        Ok(check)
+    }
+
+    /// 设置保证金比例的警告和危险阈值 (%，None 表示不告警)，降到阈值时发出
+    /// `Mt4Event::MarginWarning` / `Mt4Event::MarginCritical`
+    pub fn set_margin_thresholds(&self, warning: Option<f64>, critical: Option<f64>) {
+        if let Ok(mut monitor) = self.margin_monitor.lock() {
+            monitor.set_thresholds(warning, critical);
+        }
+    }
+
+    /// 最近一次计算的保证金比例 (%，没有占用保证金时为 None)
+    pub fn margin_level(&self) -> Option<f64> {
+        self.margin_monitor.lock().ok()?.level()
+    }
+
+    /// 按持仓估算的已用保证金 (账户货币，见 `margin::used_margin`)
+    ///
+    /// 尚未收到账户信息，或任一持仓的品种规格 (`set_symbol_info`) / 换算汇率缺失时返回 None
+    pub async fn used_margin(&self) -> Option<f64> {
+        let account = self.account_info().await?;
+        estimate_used_margin(&account, &self.positions, &self.symbols, &self.cross_rates).await
+    }
+
+    /// 按命令统计的入站流量快照 (见 `packet_stats` 模块)
+    pub fn stats(&self) -> PacketStats {
+        self.packet_stats.lock().map(|stats| stats.clone()).unwrap_or_default()
+    }
+
+    /// 清空入站流量统计
+    pub fn reset_stats(&self) {
+        if let Ok(mut stats) = self.packet_stats.lock() {
+            *stats = PacketStats::new();
+        }
+    }
+
+    /// 已平仓交易统计快照 (本次运行期间收到的平仓通知，见 `stats` 模块)
+    pub fn trade_stats(&self) -> TradeStatsReport {
+        self.trade_stats.lock().map(|stats| stats.report().clone()).unwrap_or_default()
+    }
+
+    /// 每平仓 `trades` 笔发出一次 `Mt4Event::TradeStats` (None 表示不发出)
+    pub fn set_trade_stats_report_every(&self, trades: Option<u32>) {
+        if let Ok(mut stats) = self.trade_stats.lock() {
+            stats.set_report_every(trades);
+        }
+    }
+
+    /// 清空已平仓交易统计
+    pub fn reset_trade_stats(&self) {
+        if let Ok(mut stats) = self.trade_stats.lock() {
+            stats.reset();
+        }
+    }
+
+    /// 由最近报价得到的汇率: 1 单位 `base` 以 `quote` 计的价格
+    pub fn cross_rate(&self, base: &str, quote: &str) -> Option<f64> {
+        self.cross_rates.lock().ok()?.rate(base, quote)
+    }
+
+    /// 获取品种交易规格，未设置时使用默认规格
+    async fn symbol_info_or_default(&self, symbol: &str, digits: i32) -> SymbolInfo {
+        self.symbol_info(symbol)
+            .await
+            .unwrap_or_else(|| SymbolInfo::new(symbol, digits))
+    }
+
+    /// 订阅事件广播
+    ///
+    /// 每个订阅独立接收订阅之后的所有事件 (包括之后重连产生的事件)，
+    /// 互不影响，也不影响 `next_event()` / `events()`。
+    /// 订阅落后超过 `EVENT_BROADCAST_CAPACITY` 条时丢弃最旧的事件
+    pub fn subscribe(&self) -> EventSubscription {
+        EventSubscription::new(self.broadcast.subscribe())
+    }
+
+    /// 订阅符合过滤条件的事件 (见 `filter` 模块)
+    ///
+    /// 过滤在分发时执行，不匹配的事件不会复制给该订阅；订阅落后超过 `EVENT_BROADCAST_CAPACITY` 条时
+    /// 丢弃新事件并累计到 `lagged()`
+    pub fn subscribe_filtered(&self, filter: EventFilter) -> EventSubscription {
+        self.filtered_subscribers.subscribe(filter)
+    }
+
+    /// 订阅单个品种的报价 (只包含该品种，不影响其他事件流)
+    ///
+    /// 只在本地按品种分发，品种需要已在市场报价列表中 (见 `add_to_market_watch`)
+    pub fn quotes(&self, symbol: &str) -> QuoteReceiver {
+        self.quote_channels.subscribe(symbol)
+    }
+
+    /// 订阅所有解密后的入站帧，包括已解析的命令 (见 `tap` 模块)
+    pub fn raw_messages(&self) -> RawMessageReceiver {
+        self.raw_tap.subscribe()
+    }
+
+    /// 注册报价回调 (见 `callbacks` 模块)
+    pub fn on_quote(&self, handler: impl Fn(&Quote) + Send + Sync + 'static) -> CallbackId {
+        self.callbacks.on_quote(handler)
+    }
+
+    /// 注册订单更新回调 (批量更新逐条调用)
+    pub fn on_order_update(&self, handler: impl Fn(&OrderUpdate) + Send + Sync + 'static) -> CallbackId {
+        self.callbacks.on_order_update(handler)
+    }
+
+    /// 注册账户信息回调
+    pub fn on_account(&self, handler: impl Fn(&AccountInfo) + Send + Sync + 'static) -> CallbackId {
+        self.callbacks.on_account(handler)
+    }
+
+    /// 注册连接断开回调
+    pub fn on_disconnect(&self, handler: impl Fn() + Send + Sync + 'static) -> CallbackId {
+        self.callbacks.on_disconnect(handler)
+    }
+
+    /// 移除回调，返回是否存在
+    pub fn remove_callback(&self, id: CallbackId) -> bool {
+        self.callbacks.remove(id)
+    }
+
+    /// 订阅事件广播，并先回放最近的 `count` 个事件
+    ///
+    /// 回放与实时事件之间不重复也不遗漏；可回放的数量受 `recent_events_capacity` 限制
+    pub fn subscribe_with_replay(&self, count: usize) -> EventSubscription {
+        self.mirror().subscribe_with_replay(count)
+    }
+
+    /// 订阅事件广播，并先回放序号 (`TimedEvent::seq`) 大于 `seq` 的事件
+    ///
+    /// 适合断开后重新订阅的组件从上次收到的事件继续。可回放的范围受 `recent_events_capacity` 限制，
+    /// 已被覆盖的事件数计入 `EventSubscription::lagged()`
+    pub fn subscribe_from(&self, seq: u64) -> EventSubscription {
+        self.mirror().subscribe_from(seq)
+    }
+
+    /// 订阅事件广播，并先收到当前状态快照: `AccountInfo` (已知时) 和 `PositionsSnapshot`
+    ///
+    /// 适合连接后才启动的界面组件。快照之后的实时订单更新可能与快照重叠，按 ticket 覆盖即可
+    pub async fn subscribe_with_snapshot(&self) -> EventSubscription {
+        self.mirror().subscribe_with_snapshot().await
+    }
+
+    /// 共享本会话数据的只读视图 (见 `mirror` 模块)，可交给只观察、不交易的组件
+    pub fn mirror(&self) -> Mt4Mirror {
+        Mt4Mirror {
+            account: self.account.clone(),
+            positions: self.positions.clone(),
+            lifecycle: self.lifecycle.clone(),
+            symbols: self.symbols.clone(),
+            authenticated: self.authenticated.clone(),
+            broadcast: self.broadcast.clone(),
+            recent_events: self.recent_events.clone(),
+        }
+    }
+
+    /// 把客户端移入后台任务，返回可克隆的句柄 (见 `handle` 模块)
+    pub fn spawn(self) -> Mt4Handle {
+        Mt4Handle::spawn(self)
+    }
+
+    /// 事件广播发送端 (句柄据此订阅)
+    pub(crate) fn broadcast_sender(&self) -> broadcast::Sender<TimedEvent> {
+        self.broadcast.clone()
+    }
+
+    /// 丢弃 `next_event()` 使用的事件接收端，之后的事件只进入广播
+    pub(crate) fn discard_event_receiver(&mut self) {
+        self.event_rx = None;
+    }
+
+    /// 取出事件流，与交易句柄分开消费事件
+    ///
+    /// 事件流只能取出一次，取出后 `next_event()` 返回 None。
+    /// 未连接 (也未回放) 时返回 `Mt4Error::NotConnected`
+    pub fn events(&mut self) -> Result<EventStream> {
+        match self.event_rx.take() {
+            Some(rx) => Ok(EventStream::new(rx)),
+            None if self.is_connected() => Err(Mt4Error::InvalidParams("事件流已被取出".to_string())),
+            None => Err(Mt4Error::NotConnected),
+        }
+    }
+
+    /// 接收下一个事件
+    pub async fn next_event(&mut self) -> Option<Mt4Event> {
+        self.next_timed_event().await.map(|e| e.event)
+    }
+
+    /// 接收下一个事件 (带接收时间和服务器时间)
+    pub async fn next_timed_event(&mut self) -> Option<TimedEvent> {
+        if let Some(rx) = &mut self.event_rx {
+            rx.recv().await
+        } else {
+            None
+        }
+    }
+
+    /// 最近发出的事件 (最多 `RECENT_EVENTS_CAPACITY` 个，按时间顺序)
+    pub fn recent_events(&self) -> Vec<TimedEvent> {
+        self.recent_events
+            .lock()
+            .map(|r| r.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 最近一次收到的账户信息
+    pub async fn account_info(&self) -> Option<AccountInfo> {
+        self.account.read().await.clone()
+    }
+
+    /// 当前状态快照: 账户信息、持仓、挂单和订阅状态 (见 `snapshot` 模块)
+    pub async fn snapshot(&self) -> ClientSnapshot {
+        let now = SystemTime::now();
+        let candle_periods = match self.candles.lock() {
+            Ok(candles) => candles.periods().iter().map(|p| *p as u64).collect(),
+            Err(_) => Vec::new(),
+        };
+        ClientSnapshot {
+            taken_at: now.duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or(0.0),
+            server_time: self.drift_estimator().utc_to_server(now),
+            server: self.server.clone(),
+            connected: self.is_connected(),
+            authenticated: self.is_authenticated(),
+            #[cfg(feature = "experimental-status")]
+            connection_status: self.connection_status().await,
+            account: self.account_info().await,
+            margin_level: self.margin_level(),
+            unrealized_pnl: self.positions.total_unrealized_pnl().await,
+            positions: self.positions.positions().await,
+            pending_orders: self.positions.pending_orders().await,
+            market_watch: self.market_watch().await,
+            candle_periods,
+        }
+    }
+
+    /// 最近一次收到的交易服务器连接状态 (Command 15，尚未收到时为 None)
+    #[cfg(feature = "experimental-status")]
+    pub async fn connection_status(&self) -> Option<ConnectionStatus> {
+        *self.connection_status.read().await
+    }
+
+    /// 校准账户信息字段偏移
+    ///
+    /// 在最近一次收到的账户信息块中搜索已知的 login/balance (及可选的 equity)，
+    /// 将找到的偏移登记到当前服务器的差异记录中，之后该服务器的 Command 3 都按此偏移解析。
+    /// 需在收到账户信息 (`Mt4Event::AccountInfo`) 之后调用
+    pub async fn calibrate_account_info(&self, known: &AccountCalibration) -> Result<AccountLayout> {
+        let server = self.server.clone().ok_or(Mt4Error::NotConnected)?;
+        let raw = self
+            .account_raw
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| Mt4Error::Protocol("尚未收到账户信息".to_string()))?;
+
+        let layout = AccountLayout::discover(&raw, known)?;
+        tracing::info!("Account layout calibrated for {}: {:?}", server, layout);
+        self.quirks.write().await.set_account_layout(&server, layout);
+
+        // 按新偏移重新解析缓存的账户信息
+        if let Some(mut account) = AccountInfo::from_bytes_with_layout(&raw, &layout) {
+            let mut cache = self.account.write().await;
+            if let Some(previous) = cache.as_ref() {
+                account.company = previous.company.clone();
+            }
+            *cache = Some(account);
+        }
+        Ok(layout)
+    }
+
+    /// 替换加密提供者 (默认为 `Mt4Crypto`)
+    ///
+    /// 在 `connect` 之前调用，连接时会把服务器返回的会话密钥交给新的提供者
+    pub async fn set_crypto_provider(&self, provider: impl CryptoProvider) {
+        *self.crypto.write().await = Box::new(provider);
+    }
+
+    /// 为命令注册解码器: 未被内置流程解析的该命令帧交给解码器，结果作为 `Mt4Event::Custom` 发出 (见 `decoders` 模块)
+    ///
+    /// 解码器参数为 (错误码, 数据)，返回 None 时仍作为 `RawMessage` 发出
+    pub fn register_decoder<T, F>(&self, command: u16, name: &'static str, decoder: F)
+    where
+        T: CustomValue,
+        F: Fn(u8, &[u8]) -> Option<T> + Send + Sync + 'static,
+    {
+        self.decoders.register(command, name, decoder);
+    }
+
+    /// 移除命令的解码器，返回是否存在
+    pub fn unregister_decoder(&self, command: u16) -> bool {
+        self.decoders.unregister(command)
+    }
+
+    /// 设置经纪商差异登记表 (例如从文件加载的历史校准结果)
+    pub async fn set_quirk_registry(&self, registry: QuirkRegistry) {
+        *self.quirks.write().await = registry;
+    }
+
+    /// 获取经纪商差异登记表副本 (可保存到文件供下次使用)
+    pub async fn quirk_registry(&self) -> QuirkRegistry {
+        self.quirks.read().await.clone()
+    }
+
+    /// 状态页数据来源 (共享客户端内部状态，可在 `connect()` 之前或之后获取)
+    #[cfg(feature = "status-page")]
+    pub fn status_provider(&self, login: &str, server: &str) -> crate::status::ClientStatus {
+        crate::status::ClientStatus {
+            login: login.to_string(),
+            server: server.to_string(),
+            authenticated: self.authenticated.clone(),
+            request_tracker: self.request_tracker.clone(),
+            positions: self.positions.clone(),
+            account: self.account.clone(),
+            recent_events: self.recent_events.clone(),
+        }
+    }
+
+    /// 在指定地址提供该客户端的状态页 (HTML: `/`, JSON: `/status.json`)
+    #[cfg(feature = "status-page")]
+    pub async fn serve_status(
+        &self,
+        addr: std::net::SocketAddr,
+    ) -> Result<crate::status::StatusServer> {
+        let login = self.login.clone().unwrap_or_default();
+        let server = self.server.clone().unwrap_or_default();
+        let provider = Arc::new(self.status_provider(&login, &server));
+        crate::status::StatusServer::bind(addr, provider).await
+    }
+
+    /// 获取当前的服务器时钟偏移估计 (用于服务器时间与 UTC 互相换算)
+    pub fn drift_estimator(&self) -> DriftEstimator {
+        self.clock.read().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    /// 是否已连接
+    pub fn is_connected(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// 是否已认证 (连接断开后变为 false)
+    pub fn is_authenticated(&self) -> bool {
+        self.writer.is_some() && self.authenticated.load(Ordering::SeqCst)
+    }
+
+    /// 断开连接
+    ///
+    /// 已认证时先发送 Logout 注销服务器会话，然后进行关闭握手 (WebSocket Close 帧 / 桥接 TCP 关闭)，
+    /// 并等待读写任务结束；超过 `disconnect_timeout` 仍未结束的任务会被强制终止
+    pub async fn disconnect(&mut self) {
+        let tasks = [self.heartbeat.take(), self.reconcile_task.take(), self.timeout_task.take()];
+        for task in tasks.into_iter().flatten() {
+            task.abort();
+        }
+        if self.is_authenticated() {
+            if let Err(e) = self.send_command(Command::Logout, &[]).await {
+                tracing::debug!("Logout failed: {}", e);
+            }
+        }
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        self.writer = None;
+        telemetry::connection_state(false);
+
+        let deadline = tokio::time::Instant::now() + self.config.disconnect_timeout;
+        for mut task in self.io_tasks.drain(..) {
+            if tokio::time::timeout_at(deadline, &mut task).await.is_err() {
+                tracing::warn!("Connection task did not finish within disconnect timeout, aborting");
+                task.abort();
+            }
+        }
+        self.event_rx = None;
+        self.event_tx = None;
+        self.authenticated.store(false, Ordering::SeqCst);
+        // 丢弃等待者，未完成的命令请求返回 NotConnected
+        self.command_waiters.lock().await.clear();
+    }
+
+    /// 为新连接创建事件通道，返回发送端
+    ///
+    /// `next_event()` / `events()` 使用的接收端随每次连接重建，广播订阅跨连接保留
+    pub(crate) fn open_event_channel(&mut self) -> EventSender {
+        let (raw_event_tx, event_rx, conflation) = event_channel(self.config.event_channel_size, self.config.conflate_quotes);
+        let event_tx = EventSender::new(
+            raw_event_tx,
+            self.broadcast.clone(),
+            self.clock.clone(),
+            self.recent_events.clone(),
+            self.config.recent_events_capacity,
+            self.forensics.clone(),
+            conflation,
+        )
+        .with_callbacks(self.callbacks.clone())
+        .with_filtered_subscribers(self.filtered_subscribers.clone());
+        self.event_rx = Some(event_rx);
+        self.event_tx = Some(event_tx.clone());
+        event_tx
+    }
+
+    /// 创建共享客户端状态的入站帧处理器
+    fn frame_handler(
+        &self,
+        writer: mpsc::Sender<OutboundFrame>,
+        event_tx: EventSender,
+        server: String,
+    ) -> FrameHandler {
+        FrameHandler {
+            writer,
+            event_tx,
+            password: SecretString::default(),
+            login_id: None,
+            server,
+            company: String::new(),
+            pending_auth: true,
+            password_sent: false,
+            authenticated: self.authenticated.clone(),
+            request_tracker: self.request_tracker.clone(),
+            account: self.account.clone(),
+            account_raw: self.account_raw.clone(),
+            #[cfg(feature = "experimental-status")]
+            connection_status: self.connection_status.clone(),
+            market_watch: self.market_watch.clone(),
+            spread_monitor: self.spread_monitor.clone(),
+            cross_rates: self.cross_rates.clone(),
+            margin_monitor: self.margin_monitor.clone(),
+            risk: self.risk.clone(),
+            quote_channels: self.quote_channels.clone(),
+            raw_tap: self.raw_tap.clone(),
+            packet_stats: self.packet_stats.clone(),
+            symbols: self.symbols.clone(),
+            trade_stats: self.trade_stats.clone(),
+            candles: self.candles.clone(),
+            quirks: self.quirks.clone(),
+            positions: self.positions.clone(),
+            lifecycle: self.lifecycle.clone(),
+            trailing: self.trailing.clone(),
+            remainder_waiters: self.remainder_waiters.clone(),
+            command_waiters: self.command_waiters.clone(),
+            decoders: self.decoders.clone(),
+            last_activity: self.last_activity.clone(),
+            funding: FundingDetector::default(),
+            notify_sequence: NotifySequence::new(),
+            reconcile: self.config.reconcile_interval.is_some(),
+            budget: WorkBudget::new(self.config.read_time_slice, self.config.read_frames_per_slice),
+            #[cfg(feature = "sqlite")]
+            journal: self.journal.clone(),
+        }
+    }
+
+    /// 解析账户信息响应 (command=3)
+    ///
+    /// 数据包结构 (根据 JS 源码):
+    /// - 账户信息头部 (约 254 字节，q.Vp=254)
+    /// - 品种信息 (254-1161)
+    /// - 报价信息 (1162+, q.Dk=1162)
+    ///
+    /// 已校准的服务器按登记的字段偏移解析
+    fn parse_account_info(data: &[u8], layout: Option<&AccountLayout>) -> Option<AccountInfo> {
+        match layout {
+            Some(layout) => AccountInfo::from_bytes_with_layout(data, layout),
+            None => AccountInfo::from_bytes(data),
+        }
+    }
+
+    /// 根据订单更新维护本地持仓缓存
+    async fn apply_order_updates(
+        positions: &PositionManager,
+        remainder_waiters: &Mutex<HashMap<Ticket, oneshot::Sender<Order>>>,
+        updates: &[OrderUpdate],
+    ) {
+        positions.apply_updates(updates).await;
+
+        let opened: Vec<Order> = updates
+            .iter()
+            .filter(|u| u.notify_type == 0)
+            .map(|u| u.order.clone())
+            .collect();
+        Self::notify_remainders(remainder_waiters, &opened).await;
+    }
+
+    /// 将命令响应交给最早的等待者，没有等待者时返回原数据
+    ///
+    /// 最早的请求已超时或被放弃时，响应是它的迟到响应，同样返回原数据而不交给之后的请求；
+    /// 放弃超过 `LATE_RESPONSE_WINDOW` 的请求视为响应丢失并跳过
+    async fn deliver_command_response(
+        command_waiters: &CommandWaiters,
+        command: u16,
+        error_code: u8,
+        data: Bytes,
+    ) -> Option<Bytes> {
+        let mut waiters = command_waiters.lock().await;
+        let queue = match waiters.get_mut(&command) {
+            Some(queue) => queue,
+            None => return Some(data),
+        };
+        let now = Instant::now();
+        while let Some(waiter) = queue.pop_front() {
+            match waiter.tx.filter(|tx| !tx.is_closed()) {
+                Some(tx) => return tx.send((error_code, data)).err().map(|(_, data)| data),
+                None if waiter.expires > now => {
+                    tracing::debug!("Late response for command {} (packet_id={}) not delivered", command, waiter.packet_id);
+                    return Some(data);
+                }
+                None => {}
+            }
+        }
+        Some(data)
+    }
+
+    /// 将部分平仓产生的剩余订单通知给等待者
+    async fn notify_remainders(
+        remainder_waiters: &Mutex<HashMap<Ticket, oneshot::Sender<Order>>>,
+        orders: &[Order],
+    ) {
+        let mut waiters = remainder_waiters.lock().await;
+        if waiters.is_empty() {
+            return;
+        }
+        for order in orders {
+            if let Some(parent) = order.parent_ticket() {
+                if let Some(tx) = waiters.remove(&parent) {
+                    let _ = tx.send(order.clone());
+                }
+            }
         }
+    }
+}
+
+/// WebSocket 写入任务: 独占写端，把通道中的命令帧加密为数据包逐个发送
+///
+/// 收到关闭通知时先发完已排队的数据包，再发送 Close 帧发起关闭握手
+async fn write_frames<S>(
+    mut sink: S,
+    mut frames: mpsc::Receiver<OutboundFrame>,
+    mut shutdown: oneshot::Receiver<()>,
+    capture: SharedCapture,
+    crypto: SharedCrypto,
+) where
+    S: futures_util::Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    loop {
+        tokio::select! {
+            biased;
+            frame = frames.recv() => {
+                let Some(frame) = frame else { break };
+                let packet = match frame.encode(&**crypto.read().await) {
+                    Ok(packet) => packet,
+                    Err(e) => {
+                        tracing::error!("Failed to encrypt command {}: {}", frame.command, e);
+                        continue;
+                    }
+                };
+                capture_outbound(&capture, &packet, &frame);
+                if let Err(e) = sink.send(Message::Binary(packet)).await {
+                    tracing::error!("WebSocket write error: {}", e);
+                    return;
+                }
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+    // 服务器回应 Close 后读取任务结束
+    if let Err(e) = sink.close().await {
+        tracing::debug!("WebSocket close error: {}", e);
+    }
+}
+
+/// 整理跟踪列表 (已平仓的停止跟踪，部分平仓的转到剩余订单) 后按报价计算需要移动的止损
+async fn trailing_updates(
+    trailing: &Mutex<TrailingEngine>,
+    positions: &PositionManager,
+    lifecycle: &Mutex<OrderLifecycle>,
+    quote: &Quote,
+) -> Vec<TrailUpdate> {
+    let mut trailing = trailing.lock().await;
+    if trailing.is_empty() {
+        return Vec::new();
+    }
+    for ticket in trailing.tracked() {
+        let state = lifecycle.lock().await.state(ticket);
+        match state {
+            Some(OrderState::PartiallyClosed) => {
+                let remainder = positions.all().await.into_iter().find(|o| o.parent_ticket() == Some(ticket));
+                match remainder {
+                    Some(order) => trailing.transfer(ticket, order.ticket),
+                    None => {
+                        trailing.untrack(ticket);
+                    }
+                }
+            }
+            Some(state) if state.is_terminal() => {
+                trailing.untrack(ticket);
+            }
+            _ => {}
+        }
+    }
+    trailing.on_quote(quote, &positions.positions_for_symbol(&quote.symbol).await)
+}
+
+/// 记录收发数据的时间
+fn touch(last_activity: &std::sync::Mutex<Instant>) {
+    if let Ok(mut t) = last_activity.lock() {
+        *t = Instant::now();
+    }
+}
+
+/// 将入站数据包写入当前的抓取文件 (如已开启)
+fn capture_inbound(capture: &std::sync::Mutex<Option<PacketCapture>>, packet: &[u8], decoded: &DecodedFrame) {
+    if let Ok(mut capture) = capture.lock() {
+        if let Some(c) = capture.as_mut() {
+            let frame = match decoded {
+                Ok(Some((command, error_code, data))) => Some((*command, *error_code, &data[..])),
+                _ => None,
+            };
+            if let Err(e) = c.record_inbound(packet, frame) {
+                tracing::warn!("Packet capture failed: {}", e);
+            }
+        }
+    }
+}
+
+/// 将出站数据包连同明文命令和数据写入当前的抓取文件 (如已开启)
+fn capture_outbound(capture: &std::sync::Mutex<Option<PacketCapture>>, packet: &[u8], frame: &OutboundFrame) {
+    if let Ok(mut capture) = capture.lock() {
+        if let Some(c) = capture.as_mut() {
+            if let Err(e) = c.record_outbound(packet, Some((frame.command, &frame.data))) {
+                tracing::warn!("Packet capture failed: {}", e);
+            }
+        }
+    }
+}
+
+/// 将入站帧写入当前的会话录制和崩溃现场记录 (如已开启)
+fn record_frame(
+    recorder: &std::sync::Mutex<Option<SessionRecorder>>,
+    forensics: &SharedForensics,
+    command: u16,
+    error_code: u8,
+    data: &[u8],
+) {
+    telemetry::frame_received(command);
+    if let Ok(mut recorder) = recorder.lock() {
+        if let Some(r) = recorder.as_mut() {
+            if let Err(e) = r.record(command, error_code, data) {
+                tracing::warn!("Session recording failed: {}", e);
+            }
+        }
+    }
+    if let Ok(mut forensics) = forensics.lock() {
+        if let Some(f) = forensics.as_mut() {
+            f.record_frame(command, error_code, data);
+        }
+    }
+}
+
+/// 按缓存的余额和持仓浮动盈亏计算净值 (账户信息中的净值只在收到 Command 3 时更新；信用额度未知时不计入)
+async fn live_equity(account: &AccountInfo, positions: &PositionManager) -> f64 {
+    account.balance + account.credit.unwrap_or(0.0) + positions.total_unrealized_pnl().await
+}
+
+/// 按持仓缓存和已设置的品种规格估算已用保证金 (见 `margin::used_margin`)
+async fn estimate_used_margin(
+    account: &AccountInfo,
+    positions: &PositionManager,
+    symbols: &RwLock<HashMap<String, SymbolInfo>>,
+    rates: &std::sync::Mutex<CrossRates>,
+) -> Option<f64> {
+    let orders = positions.positions().await;
+    let specs = symbols.read().await;
+    let rates = rates.lock().ok()?;
+    used_margin(&orders, &specs, account.leverage, &account.currency, &rates)
+}
+
+/// 入站帧处理器
+///
+/// 持有读取任务所需的共享状态，按命令解析解密后的帧并更新本地状态、发出事件。
+/// 实时连接和会话回放使用同一个处理器
+struct FrameHandler {
+    writer: mpsc::Sender<OutboundFrame>,
+    event_tx: EventSender,
+    password: SecretString,
+    /// 认证时的账号 (回放时为 None)
+    login_id: Option<i32>,
+    server: String,
+    company: String,
+    pending_auth: bool,
+    password_sent: bool,
+    authenticated: Arc<AtomicBool>,
+    request_tracker: Arc<RequestTracker>,
+    account: Arc<RwLock<Option<AccountInfo>>>,
+    #[cfg(feature = "experimental-status")]
+    connection_status: Arc<RwLock<Option<ConnectionStatus>>>,
+    market_watch: Arc<RwLock<BTreeSet<Symbol>>>,
+    spread_monitor: Arc<std::sync::Mutex<SpreadMonitor>>,
+    /// 实时汇率 (收到报价时更新)
+    cross_rates: Arc<std::sync::Mutex<CrossRates>>,
+    /// 保证金比例监控 (收到账户信息、持仓变化、持仓重新估值时更新)
+    margin_monitor: Arc<std::sync::Mutex<MarginMonitor>>,
+    /// 风险控制 (收到账户信息时更新当日起始净值)
+    risk: Arc<Mutex<RiskManager>>,
+    quote_channels: Arc<QuoteChannels>,
+    raw_tap: Arc<RawTap>,
+    packet_stats: Arc<std::sync::Mutex<PacketStats>>,
+    symbols: Arc<RwLock<HashMap<String, SymbolInfo>>>,
+    /// 已平仓交易统计
+    trade_stats: Arc<std::sync::Mutex<TradeStatistics>>,
+    candles: Arc<std::sync::Mutex<CandleAggregator>>,
+    account_raw: Arc<RwLock<Option<Vec<u8>>>>,
+    quirks: Arc<RwLock<QuirkRegistry>>,
+    positions: Arc<PositionManager>,
+    lifecycle: Arc<Mutex<OrderLifecycle>>,
+    /// 移动止损 (收到报价时按跟踪列表发送修改请求)
+    trailing: Arc<Mutex<TrailingEngine>>,
+    remainder_waiters: Arc<Mutex<HashMap<Ticket, oneshot::Sender<Order>>>>,
+    command_waiters: CommandWaiters,
+    decoders: DecoderRegistry,
+    last_activity: Arc<std::sync::Mutex<Instant>>,
+    funding: FundingDetector,
+    /// 订单更新通知序号
+    notify_sequence: NotifySequence,
+    /// 收到订单列表时是否与本地持仓缓存对账
+    reconcile: bool,
+    /// 读取任务的工作预算
+    budget: WorkBudget,
+    #[cfg(feature = "sqlite")]
+    journal: crate::journal::SharedJournal,
+}
+
+impl FrameHandler {
+    /// 按报价移动已跟踪持仓的止损 (见 `trailing` 模块)
+    ///
+    /// 读取任务不能等待自己处理的交易响应，修改请求登记后直接发送，响应照常更新持仓缓存；
+    /// 同一 ticket 已有待确认操作时跳过，由之后的报价重新计算
+    async fn trail_stops(&self, quote: &Quote) {
+        let updates = trailing_updates(&self.trailing, &self.positions, &self.lifecycle, quote).await;
+        for TrailUpdate { ticket, sl, tp } in updates {
+            let Some(order) = self.positions.get(ticket).await else { continue };
+            if self.request_tracker.is_ticket_locked(ticket).await {
+                continue;
+            }
+            let mut request = TradeRequest::modify(ticket, &order.symbol, order.order_type, order.open_price, sl, tp);
+            request.request_id = self.request_tracker.next_id();
+            tracing::info!("📈 [移动止损] #{} SL -> {} (request_id={})", ticket, sl, request.request_id);
+            self.request_tracker.add_pending(request.clone()).await;
+            let frame = OutboundFrame::new(request.request_id as u16, Command::TradeRequest as u16, request.to_bytes());
+            if self.writer.send(frame).await.is_err() {
+                tracing::warn!("Trailing stop for #{} not sent: writer closed", ticket);
+                self.request_tracker.confirm(request.request_id).await;
+            } else {
+                touch(&self.last_activity);
+            }
+        }
+    }
+
+    /// 按报价重新估值该品种的持仓 (盈亏按实时汇率换算为账户货币)，有持仓被估值时重新计算保证金比例
+    ///
+    /// 只估值已设置品种规格的品种；未设置时合约数量未知，保留服务器推送的盈亏
+    async fn revalue_positions(&self, quote: &Quote) {
+        let Some(contract_size) = self.symbols.read().await.get(&quote.symbol).map(|spec| spec.contract_size) else {
+            return;
+        };
+        let Some(currency) = self.account.read().await.as_ref().map(|account| account.currency.clone()) else { return };
+        let to_account = match currency_pair(&quote.symbol) {
+            Some((_, profit_currency)) => {
+                match self.cross_rates.lock().ok().and_then(|rates| rates.convert(1.0, profit_currency, &currency)) {
+                    Some(rate) => rate,
+                    None => return,
+                }
+            }
+            None => 1.0,
+        };
+        if self.positions.revalue(quote, contract_size, to_account).await > 0 {
+            self.update_margin_level().await;
+        }
+    }
+
+    /// 按缓存的账户余额和持仓浮动盈亏重新计算保证金比例，进入警告/危险区间时发出事件
+    ///
+    /// 浮动盈亏按最新报价估值 (见 `PositionManager::total_unrealized_pnl`)，已用保证金按持仓估算
+    /// (账户信息中没有该字段)，有持仓缺少品种规格时不更新
+    async fn update_margin_level(&self) {
+        let Some(account) = self.account.read().await.clone() else { return };
+        let margin = estimate_used_margin(&account, &self.positions, &self.symbols, &self.cross_rates).await;
+        let Some(margin) = margin else { return };
+        let equity = live_equity(&account, &self.positions).await;
+        let alert = self.margin_monitor.lock().ok().and_then(|mut monitor| monitor.on_update(equity, margin));
+        if let Some((zone, alert)) = alert {
+            tracing::warn!("Margin level {:.1}% <= {:.1}% ({:?})", alert.margin_level, alert.threshold, zone);
+            let event = match zone {
+                MarginZone::Critical => Mt4Event::MarginCritical(alert),
+                _ => Mt4Event::MarginWarning(alert),
+            };
+            let _ = self.event_tx.send(event).await;
+        }
+    }
+
+    /// 记录一次入站帧解密失败
+    fn decrypt_failed(&self) {
+        if let Ok(mut stats) = self.packet_stats.lock() {
+            stats.record_decrypt_failure();
+        }
+    }
+
+    /// 记录一次消息数据解析失败
+    fn parse_failed(&self, command: u16) {
+        if let Ok(mut stats) = self.packet_stats.lock() {
+            stats.record_parse_failure(command);
+        }
+    }
+
+    /// 处理一个解密后的入站帧
+    async fn handle(&mut self, command: u16, error_code: u8, msg_data: Bytes) {
+        // 连续处理的帧达到预算时先让出执行权，避免饿死共享运行时的其他客户端
+        self.budget.consume().await;
+        touch(&self.last_activity);
+        self.raw_tap.publish(command, error_code, &msg_data);
+        if let Ok(mut stats) = self.packet_stats.lock() {
+            stats.record_message(command, msg_data.len());
+        }
+        // 等待该命令响应的 request() 调用方优先收到数据
+        let claimed =
+            Mt4Client::deliver_command_response(&self.command_waiters, command, error_code, msg_data.clone()).await.is_none();
+        // 处理消息
+        match command {
+            0 if self.pending_auth && !self.password_sent => {
+                // Token 确认，发送密码
+                tracing::info!("Token accepted, sending password...");
+                let pwd_data = packet::encode_password(self.password.expose_secret());
+                let packet_id = self.request_tracker.next_packet_id();
+                let frame = OutboundFrame::new(packet_id, Command::AuthPassword as u16, pwd_data);
+                let _ = self.writer.send(frame).await;
+                self.password_sent = true;
+            }
+            1 => {
+                // 认证响应
+                if error_code == 0 {
+                    self.pending_auth = false;
+                    self.authenticated.store(true, Ordering::SeqCst);
+                    tracing::info!("Authentication successful!");
+                    let _ = self.event_tx.send(Mt4Event::Authenticated).await;
+                    // 恢复市场报价 (重连后服务器不保留上次连接的列表)
+                    let watch: Vec<Symbol> = self.market_watch.read().await.iter().cloned().collect();
+                    if !watch.is_empty() {
+                        let data = packet::encode_market_watch(true, &watch);
+                        let packet_id = self.request_tracker.next_packet_id();
+                        let frame = OutboundFrame::new(packet_id, Command::QuoteSubscribe as u16, data);
+                        tracing::info!("Restoring market watch: {} symbol(s)", watch.len());
+                        let _ = self.writer.send(frame).await;
+                    }
+                    // 不发送 command=5，因为那是获取订单历史，不是当前持仓
+                    // 当前持仓通过 command=10 (OrderUpdate) 推送事件获取
+                } else {
+                    tracing::error!("Authentication failed: {}", error_code);
+                    let _ = self.event_tx.send(Mt4Event::AuthFailed(error_code)).await;
+                }
+            }
+            3 => {
+                // 账户信息响应
+                // 数据结构 (根据 JS 源码 line 1180):
+                // - 0-253: 账户信息 (254 字节, q.Vp=254)
+                // - 254-1161: 品种信息 (28字节*32个, parsed by Ur())
+                // - 1162+: 报价信息 (parsed by Qr() at offset q.Dk=1162)
+                // 注意: Command 3 不包含订单数据!
+                // 当前持仓需要通过 Command 4 请求, 历史订单通过 Command 5 获取
+
+                let layout = self.quirks.read().await.account_layout(&self.server);
+                if let Some(mut account) = Mt4Client::parse_account_info(&msg_data, layout.as_ref()) {
+                    // 未校准 login 偏移时使用认证时的 login (响应中可能没有正确的 login)
+                    if let Some(login_id) = self.login_id {
+                        if layout.and_then(|l| l.login).is_none() && account.login != login_id {
+                            tracing::debug!("Account block login {} != {}, using auth login", account.login, login_id);
+                            account.login = login_id;
+                        }
+                    }
+                    // 账户块中没有公司名称，使用 Token 响应中的值
+                    account.company = self.company.clone();
+                    *self.account_raw.write().await = Some(msg_data[..ACCOUNT_INFO_SIZE].to_vec());
+                    tracing::info!(
+                        "Account: login={}, balance={:.2}, equity={:.2}, leverage={}",
+                        account.login,
+                        account.balance,
+                        account.equity,
+                        account.leverage
+                    );
+                    *self.account.write().await = Some(account.clone());
+                    self.risk.lock().await.on_account(&account, unix_now());
+                    #[cfg(feature = "sqlite")]
+                    crate::journal::record(&self.journal, |j| j.record_account(&account));
+                    let funding = self.funding.on_account(&account);
+                    let _ = self.event_tx.send(Mt4Event::AccountInfo(account)).await;
+                    if let Some(operation) = funding {
+                        let _ = self.event_tx.send(Mt4Event::Funding(operation)).await;
+                    }
+                    self.update_margin_level().await;
+
+                    // 根据 mt4.en.js line 1181: 收到 Command 3 后调用 C.F.$().lf()
+                    // lf() 函数 (line 1216) 会发送 Command 4 请求获取当前持仓
+                    tracing::info!("Account info received, requesting current positions (Command 4)...");
+                    let packet_id = self.request_tracker.next_packet_id();
+                    let frame = OutboundFrame::new(packet_id, Command::CurrentPositions as u16, Vec::new());
+                    if let Err(e) = self.writer.send(frame).await {
+                        tracing::error!("Failed to send Command 4 request: {}", e);
+                    }
+
+                } else {
+                    tracing::warn!(
+                        "Failed to parse AccountInfo: data_len={}",
+                        msg_data.len()
+                    );
+                    self.parse_failed(command);
+                    let _ = self.event_tx.send(Mt4Event::RawMessage {
+                        command,
+                        error_code,
+                        data: msg_data,
+                    }).await;
+                }
+            }
+            4 => {
+                // 当前持仓订单列表 (Command 4, mb.Mm)
+                // 根据 mt4.en.js line 1204 函数 D 和 line 1296 的 Oo() 函数：
+                // - 这是初始化 ef[] 数组（当前持仓）的命令
+                // - 数据格式: 161 字节 Order 结构数组（无头部）
+                // - 使用 Sr() 函数解析 (Math.floor(byteLength/161))
+                // - 每个订单调用 Oo() 添加到 ef[] 数组
+
+                let mut orders = Vec::new();
+
+                // 记录原始数据长度和 error_code，便于诊断
+                tracing::info!(
+                    "Command 4 响应: error_code={}, data_len={} 字节",
+                    error_code,
+                    msg_data.len()
+                );
 
-        false
+                if msg_data.is_empty() {
+                    tracing::warn!("Command 4 (当前持仓): 空数据 (无持仓订单或服务器未返回)");
+                } else {
+                    let order_count = msg_data.len() / 161;
+                    tracing::info!(
+                        "Command 4 (当前持仓): {} 个订单 ({} 字节)",
+                        order_count,
+                        msg_data.len()
+                    );
+
+                    for i in 0..order_count {
+                        let offset = i * 161;
+                        if let Some(order) = Order::from_bytes(&msg_data, offset) {
+                            // tracing::info!(
+                            //     "持仓 #{}: ticket={}, symbol={}, type={:?}, volume={:.2}, open={:.5}, profit={:.2}",
+                            //     i,
+                            //     order.ticket,
+                            //     order.symbol,
+                            //     order.order_type,
+                            //     order.volume,
+                            //     order.open_price,
+                            //     order.profit
+                            // );
+                            orders.push(order);
+                        }
+                    }
+                }
+
+                // 同步本地持仓缓存：快照中没有的订单视为已不存在 (开启对账时同时找出不一致之处)
+                let divergences = if self.reconcile {
+                    self.positions.reconcile(&orders).await
+                } else {
+                    self.positions.apply_snapshot(&orders).await;
+                    Vec::new()
+                };
+                self.lifecycle.lock().await.seed(&orders);
+
+                // 发送持仓快照事件（包含所有当前持仓，用于同步本地缓存）
+                let _ = self.event_tx.send(Mt4Event::PositionsSnapshot(orders)).await;
+                for divergence in divergences {
+                    tracing::warn!("Position cache diverged from server: {:?}", divergence);
+                    let _ = self.event_tx.send(Mt4Event::StateDivergence(divergence)).await;
+                }
+                self.update_margin_level().await;
+            }
+            5 => {
+                // 订单历史响应或当前持仓响应
+                tracing::info!(
+                    "Command 5 response: data_len={} bytes",
+                    msg_data.len()
+                );
+
+                // 输出 hex 数据以便分析
+                if !msg_data.is_empty() {
+                    // // 输出前 200 字节
+                    // let hex_preview = msg_data.iter()
+                    //     .take(200)
+                    //     .map(|b| format!("{:02x}", b))
+                    //     .collect::<Vec<_>>()
+                    //     .join(" ");
+                    // tracing::info!("Command 5 data (first 200 bytes): {}", hex_preview);
+
+                    // // 输出前 3 个 161 字节记录的完整 hex
+                    // for i in 0..3 {
+                    //     let offset = i * 161;
+                    //     if msg_data.len() >= offset + 161 {
+                    //         let order_hex = msg_data[offset..offset+161].iter()
+                    //             .map(|b| format!("{:02x}", b))
+                    //             .collect::<Vec<_>>()
+                    //             .join(" ");
+                    //         tracing::info!("Record #{} (161 bytes): {}", i, order_hex);
+                    //     }
+                    // }
+
+                    // 解析订单（命令 5 = 历史订单）
+                    // 根据 mt4.en.js line 1103 的 Sr() 函数:
+                    // 数据格式: 161 字节 Order 结构数组（无头部）
+                    let order_count = msg_data.len() / 161;
+                    tracing::info!("Command 5: parsing {} orders from {} bytes", order_count, msg_data.len());
+
+                    let mut history_orders = Vec::with_capacity(order_count);
+                    for i in 0..order_count {
+                        let offset = i * 161;
+                        if let Some(order) = Order::from_bytes(&msg_data, offset) {
+                            // tracing::info!(
+                            //     "历史订单 #{}: ticket={}, symbol={}, type={:?}, volume={:.2}, open={:.5}, close={:.5}, profit={:.2}, open_time={}, close_time={}",
+                            //     i, order.ticket, order.symbol, order.order_type, order.volume,
+                            //     order.open_price, order.close_price, order.profit,
+                            //     order.open_time, order.close_time
+                            // );
+
+
+                            history_orders.push(order);
+                        }
+                    }
+
+                    // 一次性发送所有历史订单（使用新的 HistoryOrders 事件）
+                    if !history_orders.is_empty() {
+                        tracing::info!("Command 5: 发送 {} 个历史订单到引擎", history_orders.len());
+                        let _ = self.event_tx.send(Mt4Event::HistoryOrders(history_orders)).await;
+                    }
+                }
+            }
+            8 => {
+                // 报价快照 (32 字节报价记录数组)
+                let quotes = Quote::parse_all(&msg_data);
+                tracing::debug!("Command 8: {} quotes from {} bytes", quotes.len(), msg_data.len());
+                for quote in quotes {
+                    if let Ok(mut rates) = self.cross_rates.lock() {
+                        rates.update(&quote);
+                    }
+                    self.revalue_positions(&quote).await;
+                    self.trail_stops(&quote).await;
+                    let alert = self.spread_monitor.lock().ok().and_then(|mut monitor| monitor.on_quote(&quote));
+                    let closed = self.candles.lock().map(|mut candles| candles.on_quote(&quote)).unwrap_or_default();
+                    self.quote_channels.publish(&quote);
+                    let _ = self.event_tx.send(Mt4Event::Quote(quote)).await;
+                    if let Some(alert) = alert {
+                        tracing::warn!(
+                            "Spread alert: {} spread={} threshold={:?} active={}",
+                            alert.symbol,
+                            alert.spread,
+                            alert.threshold,
+                            alert.active
+                        );
+                        let _ = self.event_tx.send(Mt4Event::SpreadAlert(alert)).await;
+                    }
+                    for bar in closed {
+                        let _ = self.event_tx.send(Mt4Event::CandleClosed(bar)).await;
+                    }
+                }
+            }
+            9 => {
+                // 历史订单 (与 Command 5 相同的 161 字节 Order 结构数组)
+                let orders = Order::parse_list(&msg_data);
+                if !msg_data.len().is_multiple_of(161) {
+                    tracing::warn!("Command 9: {} trailing bytes ignored", msg_data.len() % 161);
+                }
+                tracing::info!("Command 9: parsed {} history orders from {} bytes", orders.len(), msg_data.len());
+                if !orders.is_empty() {
+                    let _ = self.event_tx.send(Mt4Event::HistoryOrders(orders)).await;
+                }
+            }
+            10 => {
+                // 订单更新 (实时推送) - 可能包含多个订单更新
+                // tracing::debug!(
+                //     "Order update raw: data_len={}, data_hex={:02x?}",
+                //     msg_data.len(),
+                //     &msg_data[..msg_data.len().min(32)]
+                // );
+
+                // 解析所有订单更新（一条消息可能包含多个）
+                let (updates, balance_operations) = split_order_updates(&msg_data);
+                let notify_ids = updates.iter().map(|u| u.notify_id);
+                let notify_ids = notify_ids.chain(balance_operations.iter().map(|o| o.notify_id));
+                for missed in self.notify_sequence.observe_all(notify_ids) {
+                    tracing::warn!(
+                        "Missed {} order notification(s): notify_id {}..={}",
+                        missed.count(),
+                        missed.from,
+                        missed.to
+                    );
+                    let _ = self.event_tx.send(Mt4Event::MissedUpdates(missed)).await;
+                }
+                for operation in balance_operations {
+                    tracing::info!("Balance operation: {:?} {:.2} ({})", operation.kind, operation.amount, operation.comment);
+                    self.funding.on_balance_operation(&operation);
+                    let _ = self.event_tx.send(Mt4Event::BalanceOperation(operation)).await;
+                }
+                if updates.is_empty() && msg_data.len() < 185 {
+                    tracing::warn!(
+                        "Failed to parse OrderUpdate: data_len={} (expected >= 185)",
+                        msg_data.len()
+                    );
+                    self.parse_failed(command);
+                } else if !updates.is_empty() {
+                    tracing::debug!("Parsed {} order update(s) from {} bytes", updates.len(), msg_data.len());
+                    for update in &updates {
+                        // tracing::info!(
+                        //     "Order update: ticket={}, symbol={}, type={:?}, notify_type={}, close_time={}, comment={}",
+                        //     update.order.ticket,
+                        //     update.order.symbol,
+                        //     update.order.order_type,
+                        //     update.notify_type,
+                        //     update.order.close_time,
+                        //     update.order.comment
+                        // );
+                        tracing::info!("update.order 详情: {:?}", update.order);
+
+                    }
+                    Mt4Client::apply_order_updates(&self.positions, &self.remainder_waiters, &updates).await;
+                    #[cfg(feature = "sqlite")]
+                    crate::journal::record(&self.journal, |j| j.record_updates(&updates));
+                    let transitions = self.lifecycle.lock().await.apply(&updates);
+                    let funding = self.funding.on_updates(&updates);
+                    let stats = self.trade_stats.lock().ok().and_then(|mut stats| stats.on_updates(&updates));
+                    // 批量发送订单更新事件，让接收方可以一次性处理所有更新后再做决策 
+                    let _ = self.event_tx.send(Mt4Event::OrderUpdates(updates)).await;
+                    for transition in transitions {
+                        let _ = self.event_tx.send(Mt4Event::OrderStateChanged(transition)).await;
+                    }
+                    for operation in funding {
+                        let _ = self.event_tx.send(Mt4Event::Funding(operation)).await;
+                    }
+                    if let Some(report) = stats {
+                        let _ = self.event_tx.send(Mt4Event::TradeStats(report)).await;
+                    }
+                    self.update_margin_level().await;
+                }
+            }
+            12 => {
+                // 交易响应 - 解析完整的响应数据
+                // 根据 JS mt4.en.js 第1211行的 d 函数处理响应
+                if let Some(response) = crate::types::TradeResponse::from_bytes(&msg_data) {
+                    let request_id = response.request_id;
+
+                    // 详细日志：显示 error_code 和 response.status 的值
+                    tracing::debug!(
+                        "Trade response: request_id={}, error_code={}, response.status={}, price1={:.5}, price2={:.5}",
+                        request_id, error_code, response.status, response.price1, response.price2
+                    );
+
+                    // 确认请求完成 (对应 JS: clearTimeout(W[c.Xg]); N[c.Xg]=null; E[e.R]=null;)
+                    let pending = self.request_tracker.confirm(request_id).await;
+                    #[cfg(feature = "sqlite")]
+                    crate::journal::record(&self.journal, |j| j.record_response(&response, pending.as_ref().map(|p| &p.request)));
+                    telemetry::trade_response(response.status < 2, pending.as_ref().map(|p| p.created_at.elapsed()));
+                    if let Some(pending) = pending {
+                        tracing::info!(
+                            "📥 [响应确认] request_id={}, 耗时={:.2}秒, target_ticket={:?}",
+                            request_id,
+                            pending.created_at.elapsed().as_secs_f64(),
+                            pending.target_ticket
+                        );
+                    } else {
+                        tracing::warn!(
+                            "⚠️ [响应未匹配] request_id={} 未在待确认队列中找到",
+                            request_id
+                        );
+                    }
+
+                    // 部分平仓的剩余订单可能直接随交易响应返回
+                    Mt4Client::notify_remainders(&self.remainder_waiters, &response.orders).await;
+                    self.positions.apply_trade_response(&response).await;
+
+                    // 通知等待该请求结果的调用方
+                    let result = if response.status >= 2 {
+                        Err(Mt4Error::from_trade_code(response.status as u8))
+                    } else {
+                        Ok(response.clone())
+                    };
+                    self.request_tracker.resolve(request_id, result).await;
+
+                    // 根据JS原始逻辑:
+                    // - error_code > 0 只是通讯层警告,仍需检查response.status
+                    // - response.status >= 2 才是真正的交易错误
+                    // - response.status 0=Success, 1=Request sent (都表示成功/待确认)
+
+                    // 先记录通讯层警告(如果有)
+                    if error_code != 0 {
+                        let err = Mt4Error::from_trade_code(error_code);
+                        if let Mt4Error::Trade { code: _, message } = err {
+                            tracing::warn!(
+                                "Trade response with header error_code (warning only): request_id={}, error_code={}, response.status={}, msg={}",
+                                request_id, error_code, response.status, message
+                            );
+                        }
+                    }
+
+                    // 根据response.status判断交易结果
+                    if response.status >= 2 {
+                        // status >= 2 才是真正的错误
+                        let err = Mt4Error::from_trade_code(response.status as u8);
+                        if let Mt4Error::Trade { code, message } = err {
+                            tracing::warn!(
+                                "Trade failed (status>=2): request_id={}, error_code={}, response.status={}, code={}, msg={}",
+                                request_id, error_code, response.status, code, message
+                            );
+                            let _ = self.event_tx.send(Mt4Event::TradeFailed { code, message }).await;
+                        }
+                    } else {
+                        // status=0 (Success) 或 status=1 (Request sent) 都是成功/待确认
+                        tracing::info!(
+                            "Trade success (status=0 or 1): request_id={}, error_code={}, response.status={}, price1={:.5}, price2={:.5}, orders_count={}",
+                            request_id, error_code, response.status, response.price1, response.price2, response.orders.len()
+                        );
+                        let _ = self.event_tx.send(Mt4Event::TradeSuccess {
+                            request_id,
+                            status: response.status
+                        }).await;
+                    }
+                } else {
+                    tracing::error!("Failed to parse trade response, data_len={}", msg_data.len());
+                    self.parse_failed(command);
+                    // 如果解析失败，使用旧的简单解析方式作为后备
+                    let request_id = if msg_data.len() >= 4 {
+                        i32::from_le_bytes([msg_data[0], msg_data[1], msg_data[2], msg_data[3]])
+                    } else {
+                        0
+                    };
+                    let status = if msg_data.len() >= 8 {
+                        i32::from_le_bytes([msg_data[4], msg_data[5], msg_data[6], msg_data[7]])
+                    } else {
+                        0
+                    };
+
+                    // 确认请求完成
+                    if request_id != 0 {
+                        self.request_tracker.confirm(request_id).await;
+                        let result = if status >= 2 {
+                            Err(Mt4Error::from_trade_code(status as u8))
+                        } else {
+                            Ok(TradeResponse {
+                                request_id,
+                                status,
+                                price1: 0.0,
+                                price2: 0.0,
+                                orders: Vec::new(),
+                            })
+                        };
+                        self.request_tracker.resolve(request_id, result).await;
+                    }
+
+                    // 根据JS原始逻辑: error_code只是警告,status>=2才是错误
+                    if error_code != 0 {
+                        let err = Mt4Error::from_trade_code(error_code);
+                        if let Mt4Error::Trade { code: _, message } = err {
+                            tracing::warn!("Trade response with header error_code (warning only): error_code={}, msg={}", error_code, message);
+                        }
+                    }
+
+                    if status >= 2 {
+                        let err = Mt4Error::from_trade_code(status as u8);
+                        if let Mt4Error::Trade { code, message } = err {
+                            tracing::warn!("Trade failed (status>=2): code={}, msg={}", code, message);
+                            let _ = self.event_tx.send(Mt4Event::TradeFailed { code, message }).await;
+                        }
+                    } else {
+                        tracing::info!("Trade success: request_id={}, status={}", request_id, status);
+                        let _ = self.event_tx.send(Mt4Event::TradeSuccess { request_id, status }).await;
+                    }
+                }
+            }
+            #[cfg(feature = "experimental-status")]
+            15 => {
+                // 交易服务器连接状态 (布局未确认，关闭特性时作为原始消息发出)
+                let status = ConnectionStatus::from_bytes(error_code, &msg_data);
+                if status.can_trade() {
+                    tracing::info!("Trade server status: code={}", status.code);
+                } else {
+                    tracing::warn!("Trade server unavailable: code={}, error={}", status.code, error_code);
+                }
+                *self.connection_status.write().await = Some(status);
+                let _ = self.event_tx.send(Mt4Event::ConnectionStatus(status)).await;
+            }
+            51 => {
+                // Pong
+                tracing::trace!("Pong received");
+                let _ = self.event_tx.send(Mt4Event::Pong).await;
+            }
+            // 未被 request() 认领的其他命令 (包括没有等待者的K线响应) 交给自定义解码器，没有解码器时作为原始消息发出
+            _ if !claimed => {
+                let event = match self.decoders.decode(command, error_code, &msg_data) {
+                    Some(custom) => Mt4Event::Custom(custom),
+                    None => Mt4Event::RawMessage { command, error_code, data: msg_data },
+                };
+                let _ = self.event_tx.send(event).await;
+            }
+            _ => {}
+        }
     }
 }
 
@@ -1099,3 +4245,888 @@ impl Default for Mt4Client {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+
+    fn position(volume: f64) -> Order {
+        Order::for_test(1001, "EURUSD", OrderType::Buy, volume, 1.1)
+    }
+
+    #[test]
+    fn test_validate_partial_volume() {
+        let spec = SymbolInfo::new("EURUSD", 5);
+        let order = position(0.10);
+
+        let remaining = Mt4Client::validate_partial_volume(&order, &spec, 0.03).unwrap();
+        assert!((remaining - 0.07).abs() < 1e-9);
+
+        // 全部平仓、越界和不符合步长的手数都应被拒绝
+        assert!(Mt4Client::validate_partial_volume(&order, &spec, 0.10).is_err());
+        assert!(Mt4Client::validate_partial_volume(&order, &spec, 0.0).is_err());
+        assert!(Mt4Client::validate_partial_volume(&order, &spec, 0.015).is_err());
+
+        // 剩余手数低于最小手数
+        let spec = SymbolInfo { lot_min: 0.05, ..SymbolInfo::new("EURUSD", 5) };
+        assert!(Mt4Client::validate_partial_volume(&order, &spec, 0.06).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_close_partial_rejects_concurrent_call() {
+        let client = Mt4Client::new();
+        client.set_symbol_info(SymbolInfo::new("EURUSD", 5)).await;
+        client.positions.apply_snapshot(&[Order::for_test(1001, "EURUSD", OrderType::Buy, 0.1, 1.1)]).await;
+
+        // 进行中的部分平仓保留自己的等待者
+        let (tx, mut rx) = oneshot::channel();
+        client.remainder_waiters.lock().await.insert(Ticket(1001), tx);
+        let result = client.close_partial(Ticket(1001), 0.03).await;
+        assert!(matches!(result, Err(Mt4Error::InvalidParams(_))), "{:?}", result);
+        let tx = client.remainder_waiters.lock().await.remove(&Ticket(1001)).unwrap();
+        tx.send(Order::for_test(1002, "EURUSD", OrderType::Buy, 0.07, 1.1)).unwrap();
+        assert_eq!(rx.try_recv().unwrap().ticket, Ticket(1002));
+
+        // 已放弃的等待者被替换 (未连接时发送失败并移除)
+        let (tx, rx) = oneshot::channel::<Order>();
+        drop(rx);
+        client.remainder_waiters.lock().await.insert(Ticket(1001), tx);
+        assert!(matches!(client.close_partial(Ticket(1001), 0.03).await, Err(Mt4Error::NotConnected)));
+        assert!(client.remainder_waiters.lock().await.is_empty());
+    }
+
+    #[test]
+    fn test_parent_ticket() {
+        let mut order = position(0.07);
+        order.comment = "from #1001".to_string();
+        assert_eq!(order.parent_ticket(), Some(Ticket(1001)));
+
+        order.comment = "manual".to_string();
+        assert_eq!(order.parent_ticket(), None);
+    }
+
+    #[test]
+    fn test_modify_action() {
+        let mut buy = Order { sl: 1.09, tp: 1.12, ..position(0.1) };
+        assert_eq!(ModifyAction::OffsetPips(-10.0).apply(&buy), (1.089, 1.12));
+        assert_eq!(ModifyAction::SetStopLoss(1.095).apply(&buy), (1.095, 1.12));
+
+        let sell = Order { order_type: OrderType::Sell, ..buy.clone() };
+        assert_eq!(ModifyAction::OffsetPips(5.0).apply(&sell), (1.0895, 1.12));
+
+        // 没有止损时不移动
+        buy.sl = 0.0;
+        assert_eq!(ModifyAction::OffsetPips(10.0).apply(&buy), (0.0, 1.12));
+    }
+
+    #[tokio::test]
+    async fn test_replay_session() {
+        // Command 10: 新开仓 #1001 (notify_type=0)
+        let mut update = vec![0u8; 185];
+        update[0..4].copy_from_slice(&1i32.to_le_bytes());
+        update[24..28].copy_from_slice(&1001i32.to_le_bytes());
+        update[28..34].copy_from_slice(b"EURUSD");
+        update[48..52].copy_from_slice(&10i32.to_le_bytes());
+
+        let path = std::env::temp_dir().join(format!("mt4_replay_{}.jsonl", std::process::id()));
+        let mut recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(10, 0, &update).unwrap();
+        recorder.record(51, 0, &[]).unwrap();
+        drop(recorder);
+
+        let mut client = Mt4Client::new();
+        assert!(matches!(client.events(), Err(Mt4Error::NotConnected)));
+        let mut subscribers = [client.subscribe(), client.subscribe()];
+        let mut frames = client.raw_messages();
+        assert_eq!(client.replay_session(&path).await.unwrap(), 2);
+        // 已解析的命令同样经过原始帧监听
+        assert_eq!(frames.recv().await.map(|f| (f.command, f.data.len())), Some((10, update.len())));
+        let stats = client.stats();
+        assert_eq!((stats.command(10).messages, stats.command(10).bytes), (1, update.len() as u64));
+        assert_eq!((stats.command(51).messages, stats.total_messages()), (1, 2));
+
+        let mut events = client.events().unwrap();
+        let mut kinds = Vec::new();
+        while let Some(event) = events.next().await {
+            kinds.push(event.kind());
+            if matches!(event, Mt4Event::Disconnected) {
+                break;
+            }
+        }
+        assert_eq!(kinds, vec!["OrderUpdates", "OrderStateChanged", "Pong", "Disconnected"]);
+        assert!(client.next_event().await.is_none());
+        for subscriber in &mut subscribers {
+            assert_eq!(subscriber.recv().await.map(|e| e.kind()), Some("OrderUpdates"));
+        }
+
+        // 晚到的订阅者: 回放最近事件或当前快照
+        let mut late = client.subscribe_with_replay(2);
+        assert_eq!(late.backlog_len(), 2);
+        assert_eq!(late.recv().await.map(|e| e.kind()), Some("Pong"));
+        assert_eq!(late.recv().await.map(|e| e.kind()), Some("Disconnected"));
+        let mut resumed = client.subscribe_from(2);
+        assert_eq!(resumed.recv_timed().await.map(|e| (e.event.kind(), e.seq)), Some(("Pong", 3)));
+        assert_eq!(client.subscribe_from(4).backlog_len(), 0);
+        match client.subscribe_with_snapshot().await.recv().await {
+            Some(Mt4Event::PositionsSnapshot(orders)) => assert_eq!(orders[0].ticket, Ticket(1001)),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(client.positions().await[0].volume, 0.1);
+        assert_eq!(client.order_state(Ticket(1001)).await, Some(OrderState::Open));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Command 4 中的一条买单记录 (161 字节)
+    fn position_record(ticket: i32, symbol: &str, volume: f64, open_price: f64, profit: f64) -> Vec<u8> {
+        let mut data = vec![0u8; 161];
+        data[0..4].copy_from_slice(&ticket.to_le_bytes());
+        data[4..4 + symbol.len()].copy_from_slice(symbol.as_bytes());
+        data[16..20].copy_from_slice(&5i32.to_le_bytes());
+        data[24..28].copy_from_slice(&((volume * 100.0).round() as i32).to_le_bytes());
+        data[36..44].copy_from_slice(&open_price.to_le_bytes());
+        data[101..109].copy_from_slice(&profit.to_le_bytes());
+        data
+    }
+
+    #[tokio::test]
+    async fn test_margin_level_from_account_updates() {
+        // Command 4: 1 手 EURUSD 买单 @1.1，浮亏 200
+        let position = position_record(1001, "EURUSD", 1.0, 1.1, -200.0);
+        // Command 3: 杠杆 1:100，账户货币 USD
+        let account = |balance: f64| {
+            let mut data = vec![0u8; ACCOUNT_INFO_SIZE];
+            data[1..9].copy_from_slice(&balance.to_le_bytes());
+            data[9..17].copy_from_slice(&balance.to_le_bytes());
+            for (i, c) in "USD".encode_utf16().enumerate() {
+                data[17 + i * 2..19 + i * 2].copy_from_slice(&c.to_le_bytes());
+            }
+            data[49..53].copy_from_slice(&100i32.to_le_bytes());
+            data
+        };
+
+        let path = std::env::temp_dir().join(format!("mt4_margin_{}.jsonl", std::process::id()));
+        let mut recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(4, 0, &position).unwrap();
+        for balance in [1_000.0, 2_000.0, 500.0] {
+            recorder.record(3, 0, &account(balance)).unwrap();
+        }
+        drop(recorder);
+
+        // 已用保证金 = 100000 × 1.1 ÷ 100 = 1100: 净值 800 (72.7%) 警告，2000 恢复，500 (27.3%) 危险
+        let mut client = Mt4Client::new();
+        client.set_symbol_info(SymbolInfo::new("EURUSD", 5)).await;
+        client.set_margin_thresholds(Some(100.0), Some(50.0));
+        client.replay_session(&path).await.unwrap();
+        let mut alerts = Vec::new();
+        while let Some(event) = client.next_event().await {
+            match event {
+                Mt4Event::MarginWarning(alert) | Mt4Event::MarginCritical(alert) => {
+                    alerts.push((alert.margin.round(), (alert.margin_level * 10.0).round() / 10.0))
+                }
+                Mt4Event::Disconnected => break,
+                _ => {}
+            }
+        }
+        assert_eq!(alerts, vec![(1100.0, 72.7), (1100.0, 27.3)]);
+        assert!(client.used_margin().await.is_some_and(|m| (m - 1100.0).abs() < 1e-6));
+
+        // 开仓预检: 可用保证金 = 净值 300 - 已用 1100；未设置规格的品种不估算
+        let quote = Quote { symbol: "EURUSD".to_string(), bid: 1.1, ask: 1.1, time: 0 };
+        client.cross_rates.lock().unwrap().update(&quote);
+        let check = client.check_margin("EURUSD", 0.1, OrderType::Buy).await.unwrap();
+        assert!((check.free_margin + 800.0).abs() < 1e-6 && (check.required_margin - 110.0).abs() < 1e-6);
+        assert!(!check.sufficient);
+        assert!(matches!(client.check_margin("GBPUSD", 0.1, OrderType::Buy).await, Err(Mt4Error::InvalidParams(_))));
+
+        // 没有品种规格时不估算保证金，也不告警
+        let mut client = Mt4Client::new();
+        client.set_margin_thresholds(Some(100.0), Some(50.0));
+        client.replay_session(&path).await.unwrap();
+        while let Some(event) = client.next_event().await {
+            assert!(!matches!(event, Mt4Event::MarginWarning(_) | Mt4Event::MarginCritical(_)));
+            if matches!(event, Mt4Event::Disconnected) {
+                break;
+            }
+        }
+        assert_eq!((client.used_margin().await, client.margin_level()), (None, None));
+        assert!(client.check_margin("EURUSD", 0.1, OrderType::Buy).await.is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_revalue_requires_symbol_spec() {
+        let mut account = vec![0u8; ACCOUNT_INFO_SIZE];
+        for (i, c) in "USD".encode_utf16().enumerate() {
+            account[17 + i * 2..19 + i * 2].copy_from_slice(&c.to_le_bytes());
+        }
+        let positions =
+            [position_record(1, "EURUSD", 0.1, 1.1, -5.0), position_record(2, "US30", 1.0, 39_000.0, 50.0)].concat();
+        let quote = |symbol: &str, price: f64| {
+            let mut data = vec![0u8; crate::protocol::QUOTE_SIZE];
+            data[..symbol.len()].copy_from_slice(symbol.as_bytes());
+            data[12..20].copy_from_slice(&price.to_le_bytes());
+            data[20..28].copy_from_slice(&price.to_le_bytes());
+            data
+        };
+
+        let path = std::env::temp_dir().join(format!("mt4_revalue_{}.jsonl", std::process::id()));
+        let mut recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(3, 0, &account).unwrap();
+        recorder.record(4, 0, &positions).unwrap();
+        recorder.record(8, 0, &[quote("EURUSD", 1.101), quote("US30", 39_100.0)].concat()).unwrap();
+        drop(recorder);
+
+        // 只有 EURUSD 设置了规格: 0.1 手 × 100000 × 0.001 = 10；US30 合约数量未知，保留服务器盈亏
+        let mut client = Mt4Client::new();
+        client.set_symbol_info(SymbolInfo::new("EURUSD", 5)).await;
+        client.replay_session(&path).await.unwrap();
+        while let Some(event) = client.next_event().await {
+            if matches!(event, Mt4Event::Disconnected) {
+                break;
+            }
+        }
+        assert_eq!(client.positions.unrealized_pnl(Ticket(1)).await, Some(10.0));
+        assert_eq!(client.positions.unrealized_pnl(Ticket(2)).await, Some(50.0));
+        assert_eq!(client.positions.total_unrealized_pnl().await, 60.0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_connect_bridge() {
+        use crate::bridge::BridgeCommand;
+        use tokio::io::AsyncWriteExt;
+
+        // 模拟桥接 EA: 发送 hello，收到密码后确认认证，再推送 Pong
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let ea = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\",\"version\":1}\n").await.unwrap();
+            let request: BridgeCommand = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            write.write_all(b"{\"type\":\"auth\",\"error_code\":0}\n").await.unwrap();
+            write.write_all(b"{\"type\":\"frame\",\"command\":51}\n").await.unwrap();
+            request
+        });
+
+        let mut client = Mt4Client::builder().auth_timeout(Duration::from_secs(5)).build();
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        client.connect_bridge(&addr, &credentials).await.unwrap();
+
+        let mut kinds = Vec::new();
+        while let Some(event) = client.next_event().await {
+            kinds.push(event.kind());
+            if matches!(event, Mt4Event::Disconnected) {
+                break;
+            }
+        }
+        assert_eq!(kinds, vec!["Authenticated", "Pong", "Disconnected"]);
+
+        let BridgeCommand::Login { password } = ea.await.unwrap() else { panic!("expected login") };
+        assert_eq!(password.expose_secret(), "secret");
+    }
+
+    #[tokio::test]
+    async fn test_request_response() {
+        use crate::bridge::{BridgeCommand, BridgeMessage};
+        use tokio::io::AsyncWriteExt;
+
+        // 模拟桥接 EA: 认证后把 Command 77 的数据反转作为响应，其他命令不响应
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\"}\n").await.unwrap();
+            lines.next_line().await.unwrap();
+            write.write_all(b"{\"type\":\"auth\"}\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if let BridgeCommand::Frame { command: 77, data } = serde_json::from_str(&line).unwrap() {
+                    let data: Vec<u8> = data.into_iter().rev().collect();
+                    let response = BridgeMessage::Frame { command: 77, error_code: 3, data };
+                    let line = format!("{}\n", serde_json::to_string(&response).unwrap());
+                    write.write_all(line.as_bytes()).await.unwrap();
+                }
+            }
+        });
+
+        let mut client = Mt4Client::builder().auth_timeout(Duration::from_secs(5)).disable_heartbeat().build();
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        let mut events = client.subscribe();
+        client.connect_bridge(&addr, &credentials).await.unwrap();
+
+        let (error_code, data) = client.request(77u16, &[1, 2, 3]).await.unwrap();
+        assert_eq!((error_code, &data[..]), (3, &[3, 2, 1][..]));
+        let result = client.request_with_timeout(Command::ConnectionStatus, &[], Duration::from_millis(100)).await;
+        assert!(matches!(result, Err(Mt4Error::Timeout)));
+        client.send_raw(78, &[9]).await.unwrap();
+
+        // 被认领的响应不再作为原始消息发出
+        client.disconnect().await;
+        assert!(matches!(client.send_raw(78, &[]).await, Err(Mt4Error::NotConnected)));
+        let mut kinds = Vec::new();
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_secs(1), events.recv()).await {
+            kinds.push(event.kind());
+        }
+        assert!(kinds.contains(&"Authenticated") && !kinds.contains(&"RawMessage"), "{:?}", kinds);
+    }
+
+    #[tokio::test]
+    async fn test_late_response_not_delivered_to_next_request() {
+        use crate::bridge::{BridgeCommand, BridgeMessage};
+        use tokio::io::AsyncWriteExt;
+
+        // 模拟桥接 EA: 按顺序原样返回 Command 79 的数据，第一个响应延迟 300ms
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\"}\n").await.unwrap();
+            lines.next_line().await.unwrap();
+            write.write_all(b"{\"type\":\"auth\"}\n").await.unwrap();
+            let mut delay = Duration::from_millis(300);
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if let BridgeCommand::Frame { command: 79, data } = serde_json::from_str(&line).unwrap() {
+                    tokio::time::sleep(std::mem::take(&mut delay)).await;
+                    let response = BridgeMessage::Frame { command: 79, error_code: 0, data };
+                    let line = format!("{}\n", serde_json::to_string(&response).unwrap());
+                    write.write_all(line.as_bytes()).await.unwrap();
+                }
+            }
+        });
+
+        let mut client = Mt4Client::builder().auth_timeout(Duration::from_secs(5)).disable_heartbeat().build();
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        client.connect_bridge(&addr, &credentials).await.unwrap();
+
+        let result = client.request_with_timeout(79u16, &[1], Duration::from_millis(100)).await;
+        assert!(matches!(result, Err(Mt4Error::Timeout)), "{:?}", result);
+        // 第一个请求的迟到响应先到达，不能交给第二个请求
+        let (_, data) = client.request_with_timeout(79u16, &[2], Duration::from_secs(2)).await.unwrap();
+        assert_eq!(&data[..], &[2]);
+        assert!(client.command_waiters.lock().await.get(&79).is_none_or(|queue| queue.is_empty()));
+        client.disconnect().await;
+
+        // 发送失败的请求移出队列
+        assert!(matches!(client.request(79u16, &[3]).await, Err(Mt4Error::NotConnected)));
+        assert!(client.command_waiters.lock().await.get(&79).is_none_or(|queue| queue.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_trailing_past_open_price() {
+        use crate::bridge::{BridgeCommand, BridgeMessage};
+        use tokio::io::AsyncWriteExt;
+
+        // 模拟桥接 EA: 认证后推送 1 手 EURUSD 买单 (@1.1000) 和报价 1.1050/1.1052，
+        // 对交易请求回复成功并带回修改后的订单
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let ea = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\"}\n").await.unwrap();
+            lines.next_line().await.unwrap();
+            let position = Order::for_test(1001, "EURUSD", OrderType::Buy, 1.0, 1.1);
+            let quote = Quote { symbol: "EURUSD".to_string(), bid: 1.1050, ask: 1.1052, time: 0 };
+            let messages = [
+                BridgeMessage::Auth { error_code: 0 },
+                BridgeMessage::Positions { orders: vec![position.clone()] },
+                BridgeMessage::Quote(quote),
+            ];
+            for message in messages {
+                write.write_all(format!("{}\n", serde_json::to_string(&message).unwrap()).as_bytes()).await.unwrap();
+            }
+
+            let mut modifies = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let BridgeCommand::Trade { request } = serde_json::from_str(&line).unwrap() else {
+                    continue;
+                };
+                let mut order = position.clone();
+                order.sl = request.sl;
+                let result = BridgeMessage::TradeResult {
+                    request_id: request.request_id,
+                    status: 0,
+                    price1: 0.0,
+                    price2: 0.0,
+                    orders: vec![order],
+                };
+                write.write_all(format!("{}\n", serde_json::to_string(&result).unwrap()).as_bytes()).await.unwrap();
+                modifies.push(request);
+            }
+            modifies
+        });
+
+        let mut client = Mt4Client::builder().auth_timeout(Duration::from_secs(5)).disable_heartbeat().build();
+        client.set_symbol_info(SymbolInfo::new("EURUSD", 5)).await;
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        let mut events = client.subscribe();
+        client.connect_bridge(&addr, &credentials).await.unwrap();
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_secs(5), events.recv()).await {
+            if matches!(event, Mt4Event::Quote(_)) {
+                break;
+            }
+        }
+
+        // 距离 20 点: 止损移到 1.1030，高于开仓价，按当前 bid 校验后发送
+        client.trail_stop(Ticket(1001), TrailingStop::new(200)).await.unwrap();
+        let quote = Quote { symbol: "EURUSD".to_string(), bid: 1.1050, ask: 1.1052, time: 0 };
+        let results = client.apply_trailing(&quote).await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_ok(), "{:?}", results[0].1);
+        assert!((client.cached_order(Ticket(1001)).await.unwrap().sl - 1.1030).abs() < 1e-9);
+
+        // 止损高于当前 bid 的修改在本地被拒绝，不会发送
+        let result = client.modify_order(Ticket(1001), 1.1060, 0.0).await;
+        assert!(matches!(result, Err(Mt4Error::InvalidParams(_))), "{:?}", result);
+
+        client.disconnect().await;
+        let modifies = ea.await.unwrap();
+        assert_eq!(modifies.len(), 1);
+        let modify = &modifies[0];
+        assert_eq!((modify.trade_type, modify.ticket, modify.order_type), (71, Ticket(1001), OrderType::Buy));
+        assert!((modify.sl - 1.1030).abs() < 1e-9 && modify.price == 1.1);
+    }
+
+    #[tokio::test]
+    async fn test_trailing_driven_by_quotes_across_reconnect() {
+        use crate::bridge::{BridgeCommand, BridgeMessage};
+        use tokio::io::AsyncWriteExt;
+
+        // 模拟桥接 EA: 认证后推送持仓 (止损 sl)，收到信号后推送报价，回复一个修改请求并返回其止损
+        async fn serve(listener: tokio::net::TcpListener, sl: f64, bid: f64, go: oneshot::Receiver<()>) -> f64 {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\"}\n").await.unwrap();
+            lines.next_line().await.unwrap();
+            let position = Order { sl, ..Order::for_test(1001, "EURUSD", OrderType::Buy, 1.0, 1.1) };
+            let send = |message: BridgeMessage| format!("{}\n", serde_json::to_string(&message).unwrap());
+            write.write_all(send(BridgeMessage::Auth { error_code: 0 }).as_bytes()).await.unwrap();
+            let positions = BridgeMessage::Positions { orders: vec![position.clone()] };
+            write.write_all(send(positions).as_bytes()).await.unwrap();
+            go.await.unwrap();
+            let quote = Quote { symbol: "EURUSD".to_string(), bid, ask: bid + 0.0002, time: 0 };
+            write.write_all(send(BridgeMessage::Quote(quote)).as_bytes()).await.unwrap();
+            loop {
+                let line = lines.next_line().await.unwrap().unwrap();
+                let BridgeCommand::Trade { request } = serde_json::from_str(&line).unwrap() else {
+                    continue;
+                };
+                let order = Order { sl: request.sl, ..position };
+                let result = BridgeMessage::TradeResult {
+                    request_id: request.request_id,
+                    status: 0,
+                    price1: 0.0,
+                    price2: 0.0,
+                    orders: vec![order],
+                };
+                write.write_all(send(result).as_bytes()).await.unwrap();
+                return request.sl;
+            }
+        }
+
+        let mut client = Mt4Client::builder().auth_timeout(Duration::from_secs(5)).disable_heartbeat().build();
+        client.set_symbol_info(SymbolInfo::new("EURUSD", 5)).await;
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        let mut tracked = false;
+        // 第二次连接模拟重连: 跟踪列表保留，新连接的报价继续移动止损
+        for (sl, bid, expected) in [(0.0, 1.1050, 1.1030), (1.1030, 1.1100, 1.1080)] {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            let (go_tx, go_rx) = oneshot::channel();
+            let ea = tokio::spawn(serve(listener, sl, bid, go_rx));
+            client.connect_bridge(&addr, &credentials).await.unwrap();
+            while client.cached_order(Ticket(1001)).await.is_none_or(|o| o.sl != sl) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            if !tracked {
+                client.trail_stop(Ticket(1001), TrailingStop::new(200)).await.unwrap();
+                tracked = true;
+            }
+            go_tx.send(()).unwrap();
+
+            let sent = tokio::time::timeout(Duration::from_secs(5), ea).await.unwrap().unwrap();
+            assert!((sent - expected).abs() < 1e-9, "{} != {}", sent, expected);
+            client.disconnect().await;
+        }
+        assert_eq!(client.trailing_tickets().await, vec![Ticket(1001)]);
+    }
+
+    #[test]
+    fn test_broker_preset_selection() {
+        use crate::presets::AccountMode;
+
+        // 默认不选择预设
+        let mut client = Mt4Client::new();
+        client.select_broker_preset("FOREX.com-US-Live 5");
+        assert!(client.broker_preset().is_none());
+        assert_eq!(client.default_slippage(), DEFAULT_SLIPPAGE);
+
+        // 开启后每次连接按服务器名称重新选择
+        let mut client = Mt4Client::builder().auto_broker_preset().build();
+        client.select_broker_preset("FOREX.com-US-Live 5");
+        assert_eq!(client.broker_preset().map(|p| p.account_mode), Some(AccountMode::Fifo));
+        client.select_broker_preset("Unknown-Demo");
+        assert!(client.broker_preset().is_none());
+
+        // 显式设置的滑点优先，即使与默认值相同
+        let custom = BrokerPreset::new("Custom", &["Custom*"]).with_slippage(10);
+        let mut client = Mt4Client::builder().broker_preset(custom.clone()).build();
+        client.select_broker_preset("Other-Server");
+        assert_eq!((client.broker_preset().unwrap().name.as_str(), client.default_slippage()), ("Custom", 10));
+        let mut client = Mt4Client::builder().broker_preset(custom).default_slippage(DEFAULT_SLIPPAGE).build();
+        client.select_broker_preset("Other-Server");
+        assert_eq!(client.default_slippage(), DEFAULT_SLIPPAGE);
+    }
+
+    #[tokio::test]
+    async fn test_intents_flushed_after_auth() {
+        use crate::bridge::{BridgeCommand, BridgeMessage};
+        use tokio::io::AsyncWriteExt;
+
+        let path = std::env::temp_dir().join(format!("mt4_flush_intents_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let symbol = Symbol::new("EURUSD").unwrap();
+        let mut queue = IntentQueue::open(&path).unwrap();
+        for volume in [0.01, 0.02, 0.03] {
+            queue.push(TradeIntent::new(TradeRequest::buy(&symbol, volume, 0.0, 0.0))).unwrap();
+        }
+        drop(queue);
+
+        // 模拟桥接 EA: 第一笔成交，第二笔资金不足 (134)，第三笔回复服务器无连接 (6)
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let ea = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\"}\n{\"type\":\"auth\"}\n").await.unwrap();
+            let mut volumes = Vec::new();
+            let mut statuses = [0, 134, 6].into_iter();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let BridgeCommand::Trade { request } = serde_json::from_str(&line).unwrap() else {
+                    continue;
+                };
+                volumes.push(request.volume);
+                let result = BridgeMessage::TradeResult {
+                    request_id: request.request_id,
+                    status: statuses.next().unwrap(),
+                    price1: 0.0,
+                    price2: 0.0,
+                    orders: Vec::new(),
+                };
+                write.write_all(format!("{}\n", serde_json::to_string(&result).unwrap()).as_bytes()).await.unwrap();
+            }
+            volumes
+        });
+
+        let mut client = Mt4Client::builder().disable_heartbeat().build();
+        client.set_symbol_info(SymbolInfo::new("EURUSD", 5)).await;
+        client.enable_intent_queue(&path).unwrap();
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        let mut events = client.subscribe();
+        // 未配置 auth_timeout 时同样等待认证并执行队列
+        client.connect_bridge(&addr, &credentials).await.unwrap();
+
+        // 被服务器拒绝的意图丢弃并发出 IntentFailed，无连接的意图放回队列且不发出事件
+        let queued = client.queued_intents().await;
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].request.volume, 0.03);
+        let mut intent_events = Vec::new();
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_millis(200), events.recv()).await {
+            match event {
+                Mt4Event::IntentExecuted { intent_id, .. } => intent_events.push(("executed", intent_id)),
+                Mt4Event::IntentFailed { intent_id, .. } => intent_events.push(("failed", intent_id)),
+                _ => {}
+            }
+        }
+        assert_eq!(intent_events, vec![("executed", 1), ("failed", 2)]);
+
+        client.disconnect().await;
+        assert_eq!(ea.await.unwrap(), vec![0.01, 0.02, 0.03]);
+        assert_eq!(IntentQueue::open(&path).unwrap().intents()[0].id, 3);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_packet_ids_share_request_counter() {
+        let tracker = RequestTracker::new();
+        assert_eq!(tracker.next_id(), 1000);
+        assert_eq!(tracker.next_packet_id(), 1001);
+        assert_eq!(tracker.next_id(), 1002);
+    }
+
+    #[tokio::test]
+    async fn test_expired_requests_are_purged() {
+        let tracker = RequestTracker::with_timeout(Duration::ZERO);
+        let mut request = TradeRequest::buy(&Symbol::new("EURUSD").unwrap(), 0.01, 0.0, 0.0);
+        request.request_id = tracker.next_id();
+        let rx = tracker.register_waiter(request.request_id).await;
+        tracker.add_pending(request).await;
+        // 调用方已放弃等待的等待者同样清理
+        drop(tracker.register_waiter(2000).await);
+
+        let expired = tracker.remove_expired().await;
+        assert_eq!(expired.len(), 1);
+        assert!(matches!(rx.await, Ok(Err(Mt4Error::Timeout))));
+        assert_eq!((tracker.pending_count().await, tracker.waiters.lock().await.len()), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_send_trades_reports_per_order() {
+        let client = Mt4Client::builder().batch_order_delay(Duration::from_millis(30)).build();
+        client.set_symbol_info(SymbolInfo::new("EURUSD", 5)).await;
+        let symbol = Symbol::new("EURUSD").unwrap();
+        let requests = vec![
+            TradeRequest::buy(&symbol, 0.01, 0.0, 0.0),
+            TradeRequest::buy(&symbol, 0.015, 0.0, 0.0),
+            TradeRequest::sell(&symbol, 0.01, 0.0, 0.0),
+        ];
+
+        let started = Instant::now();
+        let mut results: Vec<(usize, Result<TradeResponse>)> = client.send_trades(requests).await.collect().await;
+        assert!(started.elapsed() >= Duration::from_millis(60));
+        results.sort_by_key(|(index, _)| *index);
+
+        // 未连接的请求发送失败，手数不符合步长的请求在本地被拒绝
+        assert!(matches!(results[0], (0, Err(Mt4Error::NotConnected))));
+        assert!(matches!(results[1], (1, Err(Mt4Error::InvalidParams(_)))));
+        assert!(matches!(results[2], (2, Err(Mt4Error::NotConnected))));
+        assert_eq!(client.request_tracker().pending_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_pending_filters() {
+        let client = Mt4Client::new();
+        let pending = |ticket: i32, symbol: &str, order_type: OrderType, comment: &str| Order {
+            comment: comment.to_string(),
+            ..Order::for_test(ticket, symbol, order_type, 0.1, 1.2)
+        };
+        client
+            .positions
+            .apply_snapshot(&[
+                pending(1, "EURUSD", OrderType::BuyLimit, "grid"),
+                pending(2, "EURUSD", OrderType::SellStop, "manual"),
+                pending(3, "GBPUSD", OrderType::BuyLimit, "grid"),
+                pending(4, "EURUSD", OrderType::Buy, "grid"),
+            ])
+            .await;
+
+        // 只处理符合条件的挂单，持仓不受影响；未连接时全部报告失败
+        let summary = client.cancel_all_pending(|o| o.symbol == "EURUSD" && o.comment == "grid", 2).await;
+        assert!(summary.cancelled.is_empty() && !summary.is_complete());
+        let failed: Vec<Ticket> = summary.failed.iter().map(|f| f.ticket).collect();
+        assert_eq!(failed, vec![Ticket(1)]);
+        assert!(matches!(summary.failed[0].error, Mt4Error::NotConnected));
+        assert_eq!(client.cancel_all_pending(|_| true, 2).await.failed.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_close_all_partial_failure_and_retry() {
+        use crate::bridge::{BridgeCommand, BridgeMessage};
+        use tokio::io::AsyncWriteExt;
+
+        // 模拟桥接 EA: #1 成交；#2 资金不足 (134，不重试)；#3 先重新报价 (138) 后成交；#5 一直繁忙 (4)
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let ea = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\"}\n{\"type\":\"auth\"}\n").await.unwrap();
+            let mut attempts: HashMap<i32, u32> = HashMap::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let BridgeCommand::Trade { request } = serde_json::from_str(&line).unwrap() else {
+                    continue;
+                };
+                let ticket = request.ticket.get();
+                let attempt = attempts.entry(ticket).or_default();
+                *attempt += 1;
+                let status = match (ticket, *attempt) {
+                    (2, _) => 134,
+                    (3, 1) => 138,
+                    (5, _) => 4,
+                    _ => 0,
+                };
+                let result = BridgeMessage::TradeResult {
+                    request_id: request.request_id,
+                    status,
+                    price1: 0.0,
+                    price2: 0.0,
+                    orders: Vec::new(),
+                };
+                write.write_all(format!("{}\n", serde_json::to_string(&result).unwrap()).as_bytes()).await.unwrap();
+            }
+            attempts
+        });
+
+        let mut client = Mt4Client::builder().disable_heartbeat().build();
+        client.set_symbol_info(SymbolInfo::new("EURUSD", 5)).await;
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        client.connect_bridge(&addr, &credentials).await.unwrap();
+        client
+            .positions
+            .apply_snapshot(&[
+                Order::for_test(1, "EURUSD", OrderType::Buy, 0.1, 1.1),
+                Order::for_test(2, "EURUSD", OrderType::Sell, 0.1, 1.1),
+                Order::for_test(3, "EURUSD", OrderType::Buy, 0.2, 1.1),
+                Order::for_test(4, "EURUSD", OrderType::BuyLimit, 0.1, 1.0),
+                Order::for_test(5, "EURUSD", OrderType::Sell, 0.3, 1.1),
+            ])
+            .await;
+
+        // 一个订单失败不影响其他订单，挂单不处理
+        let summary = client.close_all(2).await;
+        let mut closed = summary.closed.clone();
+        closed.sort_by_key(|t| t.get());
+        assert_eq!(closed, vec![Ticket(1), Ticket(3)]);
+        let mut failed: Vec<(Ticket, ErrorKind)> = summary.failed.iter().map(|f| (f.ticket, f.error.kind())).collect();
+        failed.sort_by_key(|(t, _)| t.get());
+        assert_eq!(failed, vec![(Ticket(2), ErrorKind::InsufficientFunds), (Ticket(5), ErrorKind::Busy)]);
+        assert!(!summary.is_complete());
+
+        client.disconnect().await;
+        let attempts = ea.await.unwrap();
+        assert_eq!((attempts[&1], attempts[&2], attempts[&3], attempts[&5]), (1, 1, 2, CLOSE_MAX_ATTEMPTS));
+        assert!(!attempts.contains_key(&4));
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_when_idle() {
+        use crate::bridge::BridgeCommand;
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let ea = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\"}\n").await.unwrap();
+            lines.next_line().await.unwrap();
+            write.write_all(b"{\"type\":\"auth\"}\n").await.unwrap();
+            let started = Instant::now();
+            let ping: BridgeCommand = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            (ping, started.elapsed())
+        });
+
+        let mut client = Mt4Client::builder().heartbeat_interval(Duration::from_millis(200)).build();
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        client.connect_bridge(&addr, &credentials).await.unwrap();
+
+        let (command, idle) = tokio::time::timeout(Duration::from_secs(5), ea).await.unwrap().unwrap();
+        assert!(matches!(command, BridgeCommand::Ping), "{:?}", command);
+        // 认证响应之后至少空闲一个心跳间隔才发送
+        assert!(idle >= Duration::from_millis(150));
+        client.disconnect().await;
+    }
+
+    #[tokio::test]
+    async fn test_write_frames_owns_sink() {
+        use tokio_tungstenite::tungstenite::protocol::Role;
+        use tokio_tungstenite::WebSocketStream;
+
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let client_ws = WebSocketStream::from_raw_socket(client_io, Role::Client, None).await;
+        let mut server_ws = WebSocketStream::from_raw_socket(server_io, Role::Server, None).await;
+        let (sink, _read) = client_ws.split();
+
+        let (tx, rx) = mpsc::channel(4);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tx.send(OutboundFrame::new(1, Command::Ping as u16, Vec::new())).await.unwrap();
+        tx.send(OutboundFrame::new(2, 77, vec![2, 3])).await.unwrap();
+        shutdown_tx.send(()).unwrap();
+        let crypto = Mt4Crypto::default();
+        let expected = [
+            OutboundFrame::new(1, Command::Ping as u16, Vec::new()).encode(&crypto).unwrap(),
+            OutboundFrame::new(2, 77, vec![2, 3]).encode(&crypto).unwrap(),
+        ];
+        let crypto: SharedCrypto = Arc::new(RwLock::new(Box::new(crypto)));
+        tokio::spawn(write_frames(sink, rx, shutdown_rx, Arc::new(std::sync::Mutex::new(None)), crypto));
+
+        // 命令帧在写入任务中加密，已排队的数据包先于 Close 帧发出
+        for packet in expected {
+            assert_eq!(server_ws.next().await.unwrap().unwrap(), Message::Binary(packet));
+        }
+        assert!(matches!(server_ws.next().await.unwrap().unwrap(), Message::Close(_)));
+    }
+
+    #[tokio::test]
+    async fn test_graceful_disconnect() {
+        use tokio::io::AsyncWriteExt;
+
+        // 模拟桥接 EA: 认证后记录收到的请求，直到客户端关闭连接
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let ea = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\"}\n").await.unwrap();
+            lines.next_line().await.unwrap();
+            write.write_all(b"{\"type\":\"auth\"}\n").await.unwrap();
+            let mut commands = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                commands.push(serde_json::from_str::<serde_json::Value>(&line).unwrap()["type"].to_string());
+            }
+            commands
+        });
+
+        let mut client = Mt4Client::builder().auth_timeout(Duration::from_secs(5)).disable_heartbeat().build();
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        client.connect_bridge(&addr, &credentials).await.unwrap();
+        assert!(client.is_authenticated());
+
+        tokio::time::timeout(Duration::from_secs(2), client.disconnect()).await.unwrap();
+        assert!(!client.is_connected() && client.io_tasks.is_empty());
+        // EA 收到 Logout 后读到连接关闭
+        let commands = tokio::time::timeout(Duration::from_secs(2), ea).await.unwrap().unwrap();
+        assert_eq!(commands, vec!["\"logout\""]);
+    }
+}
@@ -0,0 +1,187 @@
+//! 事件时间戳与服务器时钟偏差估计
+//!
+//! MT4 订单中的时间 (open_time/close_time) 为经纪商服务器时间 (通常为 GMT+2/+3)，
+//! 不是 UTC。`DriftEstimator` 通过比较带服务器时间的事件与本地接收时间，
+//! 估计 "服务器时间 - UTC" 的偏移量，用于在两种时间之间换算。
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 默认保留的样本数量
+const DEFAULT_SAMPLE_CAPACITY: usize = 32;
+
+/// 时区偏移的取整粒度 (秒)，经纪商时区均为半小时的整数倍
+const TIMEZONE_GRANULARITY_SECS: i64 = 1800;
+
+/// 事件时间信息
+#[derive(Debug, Clone, Copy)]
+pub struct EventTime {
+    /// 本地单调时钟接收时间 (用于计算间隔，不受系统时间调整影响)
+    pub monotonic: Instant,
+    /// 本地 UTC 接收时间
+    pub utc: SystemTime,
+    /// 服务器时间戳 (经纪商时间，秒)，仅部分事件携带
+    pub server_time: Option<i64>,
+}
+
+impl EventTime {
+    /// 以当前时间创建
+    pub fn now(server_time: Option<i64>) -> Self {
+        Self {
+            monotonic: Instant::now(),
+            utc: SystemTime::now(),
+            server_time,
+        }
+    }
+
+    /// 本地 UTC 接收时间 (Unix 时间戳，秒，带小数)
+    pub fn utc_secs(&self) -> f64 {
+        unix_secs_f64(self.utc)
+    }
+}
+
+/// 服务器时钟偏移估计器
+///
+/// 每个样本为 "服务器时间 - 本地 UTC 接收时间"。网络延迟只会让样本偏小，
+/// 因此取窗口内最大值作为偏移估计 (即延迟最小的样本)。
+#[derive(Debug, Clone)]
+pub struct DriftEstimator {
+    samples: VecDeque<f64>,
+    capacity: usize,
+}
+
+impl Default for DriftEstimator {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_CAPACITY)
+    }
+}
+
+impl DriftEstimator {
+    /// 创建估计器，保留最近 `capacity` 个样本
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// 添加样本: 服务器时间戳与对应的本地接收时间
+    pub fn add_sample(&mut self, server_time: i64, received: SystemTime) {
+        if server_time <= 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(server_time as f64 - unix_secs_f64(received));
+    }
+
+    /// 样本数量
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// 估计的 "服务器时间 - UTC" 偏移 (秒)，无样本时为 None
+    pub fn offset_secs(&self) -> Option<f64> {
+        self.samples.iter().copied().reduce(f64::max)
+    }
+
+    /// 经纪商时区偏移 (秒，按半小时取整)
+    pub fn timezone_offset_secs(&self) -> Option<i64> {
+        let offset = self.offset_secs()?;
+        let granularity = TIMEZONE_GRANULARITY_SECS as f64;
+        Some(((offset / granularity).round() * granularity) as i64)
+    }
+
+    /// 服务器时钟相对于本地时钟的漂移 (秒，已扣除时区)
+    pub fn drift_secs(&self) -> Option<f64> {
+        Some(self.offset_secs()? - self.timezone_offset_secs()? as f64)
+    }
+
+    /// 服务器时间戳换算为 UTC
+    pub fn server_to_utc(&self, server_time: i64) -> Option<SystemTime> {
+        let utc = server_time as f64 - self.offset_secs()?;
+        from_unix_secs_f64(utc)
+    }
+
+    /// UTC 换算为服务器时间戳
+    pub fn utc_to_server(&self, utc: SystemTime) -> Option<i64> {
+        Some((unix_secs_f64(utc) + self.offset_secs()?).round() as i64)
+    }
+
+    /// 事件延迟: 本地接收时间 - 服务器时间换算后的 UTC
+    ///
+    /// 偏移取自延迟最小的样本，因此结果是相对于最快事件的延迟
+    pub fn latency(&self, time: &EventTime) -> Option<Duration> {
+        let sent = self.server_to_utc(time.server_time?)?;
+        Some(time.utc.duration_since(sent).unwrap_or_default())
+    }
+}
+
+/// SystemTime 转换为 Unix 时间戳 (秒，带小数)
+fn unix_secs_f64(time: SystemTime) -> f64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    }
+}
+
+/// 自 1970-01-01 起的天数换算为 (年, 月, 日)
+pub(crate) fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Unix 时间戳 (秒，带小数) 转换为 SystemTime
+fn from_unix_secs_f64(secs: f64) -> Option<SystemTime> {
+    if secs.is_finite() && secs >= 0.0 {
+        Some(UNIX_EPOCH + Duration::from_secs_f64(secs))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift_estimation() {
+        let mut estimator = DriftEstimator::default();
+        let received = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        // 服务器为 GMT+2，时钟快 3 秒；第二个样本有 1 秒网络延迟
+        estimator.add_sample(1_700_000_000 + 7200 + 3, received);
+        estimator.add_sample(1_700_000_000 + 7200 + 2, received);
+
+        assert_eq!(estimator.offset_secs(), Some(7203.0));
+        assert_eq!(estimator.timezone_offset_secs(), Some(7200));
+        assert_eq!(estimator.drift_secs(), Some(3.0));
+
+        let server = estimator.utc_to_server(received).unwrap();
+        assert_eq!(server, 1_700_000_000 + 7203);
+        assert_eq!(estimator.server_to_utc(server), Some(received));
+
+        let time = EventTime {
+            monotonic: Instant::now(),
+            utc: received,
+            server_time: Some(1_700_000_000 + 7202),
+        };
+        assert_eq!(estimator.latency(&time), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_empty_estimator() {
+        let estimator = DriftEstimator::default();
+        assert!(estimator.offset_secs().is_none());
+        assert!(estimator.server_to_utc(1_700_000_000).is_none());
+    }
+}
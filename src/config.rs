@@ -0,0 +1,319 @@
+//! 客户端配置
+//!
+//! `Mt4ClientBuilder` 在 `connect()` 之前设置连接参数:
+//!
+//! ```no_run
+//! use mt4_client::Mt4Client;
+//! use std::time::Duration;
+//!
+//! let client = Mt4Client::builder()
+//!     .gateway(2)
+//!     .connect_timeout(Duration::from_secs(10))
+//!     .auth_timeout(Duration::from_secs(15))
+//!     .heartbeat_interval(Duration::from_secs(30))
+//!     .default_slippage(20)
+//!     .build();
+//! ```
+
+use crate::budget::{DEFAULT_FRAMES_PER_SLICE, DEFAULT_TIME_SLICE};
+use crate::client::Mt4Client;
+use crate::decrypt::{DEFAULT_DECRYPT_OFFLOAD_SIZE, DEFAULT_DECRYPT_WORKERS};
+use crate::events::RECENT_EVENTS_CAPACITY;
+use crate::presets::BrokerPreset;
+use crate::requote::RequotePolicy;
+use crate::tls::TlsConfig;
+use std::time::Duration;
+
+pub use crate::api::DEFAULT_BASE_URL;
+
+/// 默认网关编号
+pub const DEFAULT_GATEWAY: i32 = 4;
+
+/// 默认心跳间隔
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 默认交易请求超时 (与网页端一致)
+pub const DEFAULT_TRADE_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// 默认滑点 (点)，与 `TradeRequest` 构造函数一致
+pub const DEFAULT_SLIPPAGE: i32 = 50;
+
+/// 客户端配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientConfig {
+    /// Web API 基础 URL
+    pub base_url: String,
+    /// 网关编号 (1-8)
+    pub gateway: i32,
+    /// 代理地址 (http:// 或 socks5://，None 时读取 `HTTPS_PROXY` / `ALL_PROXY` 环境变量，见 `proxy` 模块)
+    pub proxy: Option<String>,
+    /// 信号服务器 (WebSocket) 连接的 TLS 配置 (自定义根证书、证书固定，见 `tls` 模块)
+    pub tls: TlsConfig,
+    /// 获取 token 并建立 WebSocket 连接的超时
+    pub connect_timeout: Duration,
+    /// 等待认证完成的超时 (None 表示 `connect()` 发送 token 后立即返回，不等待认证)
+    pub auth_timeout: Option<Duration>,
+    /// `disconnect()` 等待关闭握手和读写任务结束的超时
+    pub disconnect_timeout: Duration,
+    /// `request()` 等待命令响应的超时
+    pub request_timeout: Duration,
+    /// 交易请求等待服务器响应的超时: 超时后从待确认队列移除，等待者收到 `Mt4Error::Timeout`，并发出 `TradeTimeout`
+    pub trade_timeout: Duration,
+    /// 心跳 (Ping) 间隔，连接空闲达到该时长时自动发送 (None 表示不自动发送)
+    pub heartbeat_interval: Option<Duration>,
+    /// 与服务器对账的间隔: 定期重新请求账户信息和订单列表并修正本地持仓缓存 (None 表示不对账，见 `reconcile` 模块)
+    pub reconcile_interval: Option<Duration>,
+    /// 发送通道容量
+    pub write_channel_size: usize,
+    /// 事件通道容量
+    pub event_channel_size: usize,
+    /// 保留的最近事件数 (`recent_events()` 以及 `subscribe_with_replay()` 可回放的上限)
+    pub recent_events_capacity: usize,
+    /// 事件通道已满时合并报价 (每个品种只保留最新一条，见 `events` 模块)
+    pub conflate_quotes: bool,
+    /// 读取任务每个时间片的最长占用时间，用完后让出执行权 (见 `budget` 模块)
+    pub read_time_slice: Duration,
+    /// 读取任务每个时间片最多处理的帧数
+    pub read_frames_per_slice: usize,
+    /// 同时在途的入站帧解密数 (见 `decrypt` 模块)
+    pub decrypt_workers: usize,
+    /// 交给阻塞线程池解密的最小帧长度 (字节)，更小的帧在读取任务中解密
+    pub decrypt_offload_size: usize,
+    /// 客户端便捷方法 (buy/sell/close_order 等) 使用的滑点
+    /// (None 时使用经纪商预设的滑点，没有预设时为 `DEFAULT_SLIPPAGE`)
+    pub default_slippage: Option<i32>,
+    /// 显式指定的经纪商预设 (每次连接都使用，见 `presets` 模块)
+    pub broker_preset: Option<BrokerPreset>,
+    /// 未指定预设时，连接时是否按服务器名称从内置预设中选择 (默认关闭)
+    pub auto_broker_preset: bool,
+    /// 发送交易请求前是否在本地校验手数、止损止盈和注释 (见 `validation` 模块)
+    pub validate_trades: bool,
+    /// 开仓前是否按品种规格自动取整手数 (见 `SymbolInfo::normalize_volume`)
+    pub auto_normalize_volume: bool,
+    /// 市价单遇到重新报价时的自动重试策略 (None 表示不重试，见 `requote` 模块)
+    pub requote_policy: Option<RequotePolicy>,
+    /// `send_trades()` 批量发送时相邻两个请求的间隔 (避免服务器返回 141 "Too many requests")
+    pub batch_order_delay: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            gateway: DEFAULT_GATEWAY,
+            proxy: None,
+            tls: TlsConfig::default(),
+            connect_timeout: Duration::from_secs(30),
+            auth_timeout: None,
+            disconnect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+            trade_timeout: DEFAULT_TRADE_TIMEOUT,
+            heartbeat_interval: Some(DEFAULT_HEARTBEAT_INTERVAL),
+            reconcile_interval: None,
+            write_channel_size: 32,
+            event_channel_size: 64,
+            recent_events_capacity: RECENT_EVENTS_CAPACITY,
+            conflate_quotes: false,
+            read_time_slice: DEFAULT_TIME_SLICE,
+            read_frames_per_slice: DEFAULT_FRAMES_PER_SLICE,
+            decrypt_workers: DEFAULT_DECRYPT_WORKERS,
+            decrypt_offload_size: DEFAULT_DECRYPT_OFFLOAD_SIZE,
+            default_slippage: None,
+            broker_preset: None,
+            auto_broker_preset: false,
+            validate_trades: true,
+            auto_normalize_volume: false,
+            requote_policy: None,
+            batch_order_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+/// 客户端构建器
+#[derive(Debug, Clone, Default)]
+pub struct Mt4ClientBuilder {
+    config: ClientConfig,
+}
+
+impl Mt4ClientBuilder {
+    /// 使用默认配置创建构建器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置 Web API 基础 URL
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.config.base_url = base_url.to_string();
+        self
+    }
+
+    /// 设置网关编号
+    pub fn gateway(mut self, gateway: i32) -> Self {
+        self.config.gateway = gateway;
+        self
+    }
+
+    /// 设置代理地址 (如 "socks5://127.0.0.1:1080")
+    pub fn proxy(mut self, url: &str) -> Self {
+        self.config.proxy = Some(url.to_string());
+        self
+    }
+
+    /// 设置信号服务器连接的 TLS 配置
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.config.tls = tls;
+        self
+    }
+
+    /// 追加信任的根证书 (DER)
+    pub fn add_root_certificate(mut self, der: impl Into<Vec<u8>>) -> Self {
+        self.config.tls.root_certs.push(der.into());
+        self
+    }
+
+    /// 固定信号服务器证书的 SHA-256 指纹
+    pub fn pin_certificate_sha256(mut self, fingerprint: [u8; 32]) -> Self {
+        self.config.tls.pinned_sha256.push(fingerprint);
+        self
+    }
+
+    /// 设置连接超时
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    /// 设置认证超时 (`connect()` 等待认证完成后才返回)
+    pub fn auth_timeout(mut self, timeout: Duration) -> Self {
+        self.config.auth_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置断开连接的超时
+    pub fn disconnect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.disconnect_timeout = timeout;
+        self
+    }
+
+    /// 设置 `request()` 等待命令响应的超时
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.config.request_timeout = timeout;
+        self
+    }
+
+    /// 设置交易请求等待服务器响应的超时 (默认 180 秒)
+    pub fn trade_timeout(mut self, timeout: Duration) -> Self {
+        self.config.trade_timeout = timeout;
+        self
+    }
+
+    /// 设置心跳间隔
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.config.heartbeat_interval = Some(interval);
+        self
+    }
+
+    /// 关闭自动心跳 (由调用方自行调用 `ping()`)
+    pub fn disable_heartbeat(mut self) -> Self {
+        self.config.heartbeat_interval = None;
+        self
+    }
+
+    /// 每隔 `interval` 与服务器对账，发现不一致时发出 `Mt4Event::StateDivergence`
+    pub fn reconcile_interval(mut self, interval: Duration) -> Self {
+        self.config.reconcile_interval = Some(interval);
+        self
+    }
+
+    /// 设置发送通道容量 (至少为 1)
+    pub fn write_channel_size(mut self, size: usize) -> Self {
+        self.config.write_channel_size = size.max(1);
+        self
+    }
+
+    /// 设置事件通道容量 (至少为 1)
+    pub fn event_channel_size(mut self, size: usize) -> Self {
+        self.config.event_channel_size = size.max(1);
+        self
+    }
+
+    /// 事件通道已满时合并报价: 每个品种只保留最新一条，订单和交易事件不受影响
+    pub fn conflate_quotes(mut self, enabled: bool) -> Self {
+        self.config.conflate_quotes = enabled;
+        self
+    }
+
+    /// 设置保留的最近事件数 (至少为 1)
+    pub fn recent_events_capacity(mut self, capacity: usize) -> Self {
+        self.config.recent_events_capacity = capacity.max(1);
+        self
+    }
+
+    /// 设置读取任务的工作预算 (多个客户端共享运行时时限制单个客户端连续占用的时间)
+    pub fn read_budget(mut self, time_slice: Duration, frames_per_slice: usize) -> Self {
+        self.config.read_time_slice = time_slice;
+        self.config.read_frames_per_slice = frames_per_slice.max(1);
+        self
+    }
+
+    /// 设置入站帧解密流水线: 最多 `workers` 个帧同时解密，不小于 `offload_size` 字节的帧在阻塞线程池中解密
+    ///
+    /// `offload_size` 为 `usize::MAX` 时全部在读取任务中解密
+    pub fn decrypt_pipeline(mut self, workers: usize, offload_size: usize) -> Self {
+        self.config.decrypt_workers = workers.max(1);
+        self.config.decrypt_offload_size = offload_size;
+        self
+    }
+
+    /// 设置便捷方法使用的默认滑点
+    pub fn default_slippage(mut self, slippage: i32) -> Self {
+        self.config.default_slippage = Some(slippage);
+        self
+    }
+
+    /// 指定经纪商预设 (未显式设置滑点时使用预设的滑点)
+    pub fn broker_preset(mut self, preset: BrokerPreset) -> Self {
+        self.config.broker_preset = Some(preset);
+        self
+    }
+
+    /// 未指定预设时，每次连接按服务器名称从内置预设中选择
+    pub fn auto_broker_preset(mut self) -> Self {
+        self.config.auto_broker_preset = true;
+        self
+    }
+
+    /// 不在本地校验交易请求，全部交由服务器判断
+    pub fn disable_trade_validation(mut self) -> Self {
+        self.config.validate_trades = false;
+        self
+    }
+
+    /// 开仓前按品种规格自动取整手数 (如步长 0.01 时 0.013 取整为 0.01)，而不是被服务器以错误码 131 拒绝
+    pub fn auto_normalize_volume(mut self, enabled: bool) -> Self {
+        self.config.auto_normalize_volume = enabled;
+        self
+    }
+
+    /// 设置市价单遇到重新报价 (135 / 138) 时的自动重试策略
+    pub fn requote_policy(mut self, policy: RequotePolicy) -> Self {
+        self.config.requote_policy = Some(policy);
+        self
+    }
+
+    /// 设置批量发送交易请求的间隔
+    pub fn batch_order_delay(mut self, delay: Duration) -> Self {
+        self.config.batch_order_delay = delay;
+        self
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// 创建客户端
+    pub fn build(self) -> Mt4Client {
+        Mt4Client::with_config(self.config)
+    }
+}
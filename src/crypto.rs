@@ -1,12 +1,38 @@
 //! AES-256-CBC 加密/解密模块
+//!
+//! 客户端通过 `CryptoProvider` 使用加密器，默认为 `Mt4Crypto`。可以用
+//! `Mt4Client::set_crypto_provider` 替换为硬件保管的密钥、协议变体的其他加密参数，
+//! 或包装默认实现以记录收发的数据
 
 use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
 use crate::error::{Mt4Error, Result};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
 type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
 
+/// 客户端各任务共用的加密提供者
+pub(crate) type SharedCrypto = Arc<RwLock<Box<dyn CryptoProvider>>>;
+
+/// 加密提供者
+///
+/// 数据包负载的加密和解密都经过该接口，数据包头和随机前缀由 `packet` 模块处理
+pub trait CryptoProvider: Send + Sync + 'static {
+    /// 加密数据，`use_auth_key` 为 true 时使用预设的认证密钥 (仅用于发送 token)
+    fn encrypt(&self, data: &[u8], use_auth_key: bool) -> Result<Vec<u8>>;
+
+    /// 解密数据 (设置会话密钥后使用会话密钥)
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>>;
+
+    /// 设置会话密钥 (获取 token 时服务器返回的十六进制 key 字段)
+    fn set_session_key(&mut self, key_hex: &str) -> Result<()>;
+}
+
 /// AES-256-CBC 加密器
+///
+/// 释放时清零认证密钥和会话密钥
 #[derive(Clone)]
 pub struct Mt4Crypto {
     /// 预设的认证密钥 (用于 token)
@@ -28,8 +54,9 @@ impl Mt4Crypto {
     /// 解码预设的认证密钥
     fn decode_auth_key() -> Result<[u8; 32]> {
         let hex_str = crate::protocol::AUTH_KEY_HEX;
-        let bytes = hex::decode(hex_str)
-            .map_err(|e| Mt4Error::Encryption(format!("Failed to decode auth key: {}", e)))?;
+        let bytes = Zeroizing::new(
+            hex::decode(hex_str).map_err(|e| Mt4Error::Encryption(format!("Failed to decode auth key: {}", e)))?,
+        );
 
         if bytes.len() != 32 {
             return Err(Mt4Error::Encryption(format!(
@@ -45,8 +72,9 @@ impl Mt4Crypto {
 
     /// 设置会话密钥 (从服务器返回的 key 字段)
     pub fn set_session_key(&mut self, key_hex: &str) -> Result<()> {
-        let bytes = hex::decode(key_hex)
-            .map_err(|e| Mt4Error::Encryption(format!("Failed to decode session key: {}", e)))?;
+        let bytes = Zeroizing::new(
+            hex::decode(key_hex).map_err(|e| Mt4Error::Encryption(format!("Failed to decode session key: {}", e)))?,
+        );
 
         if bytes.len() != 32 {
             return Err(Mt4Error::Encryption(format!(
@@ -55,9 +83,8 @@ impl Mt4Crypto {
             )));
         }
 
-        let mut key = [0u8; 32];
+        let key = self.session_key.insert([0u8; 32]);
         key.copy_from_slice(&bytes);
-        self.session_key = Some(key);
         Ok(())
     }
 
@@ -106,15 +133,38 @@ impl Mt4Crypto {
 
     /// 获取认证密钥的十六进制表示
     pub fn auth_key_hex(&self) -> String {
-        hex::encode(&self.auth_key)
+        hex::encode(self.auth_key)
     }
 
     /// 获取会话密钥的十六进制表示
     pub fn session_key_hex(&self) -> Option<String> {
-        self.session_key.map(|k| hex::encode(&k))
+        self.session_key.map(hex::encode)
     }
 }
 
+impl CryptoProvider for Mt4Crypto {
+    fn encrypt(&self, data: &[u8], use_auth_key: bool) -> Result<Vec<u8>> {
+        Mt4Crypto::encrypt(self, data, use_auth_key)
+    }
+
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Mt4Crypto::decrypt(self, data)
+    }
+
+    fn set_session_key(&mut self, key_hex: &str) -> Result<()> {
+        Mt4Crypto::set_session_key(self, key_hex)
+    }
+}
+
+impl Drop for Mt4Crypto {
+    fn drop(&mut self) {
+        self.auth_key.zeroize();
+        self.session_key.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Mt4Crypto {}
+
 impl Default for Mt4Crypto {
     fn default() -> Self {
         Self::new().expect("Failed to initialize crypto")
@@ -157,4 +207,46 @@ mod tests {
         assert!(crypto.session_key.is_some());
         assert_eq!(crypto.session_key_hex().unwrap(), session_key);
     }
+
+    #[test]
+    fn test_instrumented_provider() {
+        use crate::packet::{build_packet, decode_packet};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// 统计调用次数的包装
+        struct Counting(Mt4Crypto, Arc<AtomicUsize>);
+
+        impl CryptoProvider for Counting {
+            fn encrypt(&self, data: &[u8], use_auth_key: bool) -> Result<Vec<u8>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.encrypt(data, use_auth_key)
+            }
+
+            fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.decrypt(data)
+            }
+
+            fn set_session_key(&mut self, key_hex: &str) -> Result<()> {
+                self.0.set_session_key(key_hex)
+            }
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut provider: Box<dyn CryptoProvider> = Box::new(Counting(Mt4Crypto::new().unwrap(), calls.clone()));
+        provider.set_session_key("1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef").unwrap();
+        // 入站帧的第一个数据字节为错误码
+        let packet = build_packet(1, 3, b"\x00data", &*provider, false).unwrap();
+        let (command, error_code, data) = decode_packet(&packet, &*provider).unwrap().unwrap();
+        assert_eq!((command, error_code, data.as_ref()), (3, 0, b"data".as_ref()));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_password_redacted() {
+        use secrecy::ExposeSecret;
+        let credentials = crate::LoginCredentials::new("31313724", "hunter2", "Demo");
+        assert!(!format!("{:?}", credentials).contains("hunter2"));
+        assert_eq!(credentials.clone().password.expose_secret(), "hunter2");
+    }
 }
@@ -0,0 +1,114 @@
+//! chrono 时间访问器 (`chrono` 特性)
+//!
+//! 订单的 `open_time` / `close_time`、`Quote::time` 和 `Candle::time` 都是经纪商服务器时间
+//! (通常为 GMT+2/+3) 的秒数，不是 UTC。这里提供两类访问器:
+//!
+//! - `*_broker()`: 经纪商本地时间 (`NaiveDateTime`，与 MT4 终端显示一致)
+//! - `*_utc(offset_secs)`: 扣除 "服务器时间 - UTC" 偏移后的 `DateTime<Utc>`
+//!
+//! 偏移可以由 `DriftEstimator::timezone_offset_secs()` 估计，或直接使用 `DriftEstimator::server_to_datetime()`:
+//!
+//! ```
+//! use mt4_client::datetime::server_time_to_utc;
+//!
+//! // GMT+2 的经纪商时间 2023-11-14 22:13:20 对应 UTC 20:13:20
+//! let utc = server_time_to_utc(1_700_000_000, 7200).unwrap();
+//! assert_eq!(utc.to_rfc3339(), "2023-11-14T20:13:20+00:00");
+//! ```
+
+use crate::clock::DriftEstimator;
+use crate::types::{Candle, Order, Quote, TimeInForce};
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// 经纪商服务器时间 (秒) 作为经纪商本地时间，0 或超出范围时为 None
+pub fn server_time_to_broker(server_time: i64) -> Option<NaiveDateTime> {
+    (server_time > 0).then(|| DateTime::from_timestamp(server_time, 0)).flatten().map(|t| t.naive_utc())
+}
+
+/// 经纪商服务器时间 (秒) 换算为 UTC，`offset_secs` 为 "服务器时间 - UTC" 偏移
+pub fn server_time_to_utc(server_time: i64, offset_secs: i64) -> Option<DateTime<Utc>> {
+    (server_time > 0).then(|| DateTime::from_timestamp(server_time - offset_secs, 0)).flatten()
+}
+
+impl Order {
+    /// 开仓时间 (经纪商本地时间)
+    pub fn open_time_broker(&self) -> Option<NaiveDateTime> {
+        server_time_to_broker(self.open_time)
+    }
+
+    /// 开仓时间 (UTC)
+    pub fn open_time_utc(&self, offset_secs: i64) -> Option<DateTime<Utc>> {
+        server_time_to_utc(self.open_time, offset_secs)
+    }
+
+    /// 平仓时间 (经纪商本地时间，未平仓时为 None)
+    pub fn close_time_broker(&self) -> Option<NaiveDateTime> {
+        server_time_to_broker(self.close_time)
+    }
+
+    /// 平仓时间 (UTC，未平仓时为 None)
+    pub fn close_time_utc(&self, offset_secs: i64) -> Option<DateTime<Utc>> {
+        server_time_to_utc(self.close_time, offset_secs)
+    }
+}
+
+impl Quote {
+    /// 报价时间 (经纪商本地时间)
+    pub fn time_broker(&self) -> Option<NaiveDateTime> {
+        server_time_to_broker(self.time)
+    }
+
+    /// 报价时间 (UTC)
+    pub fn time_utc(&self, offset_secs: i64) -> Option<DateTime<Utc>> {
+        server_time_to_utc(self.time, offset_secs)
+    }
+}
+
+impl Candle {
+    /// 开盘时间 (经纪商本地时间)
+    pub fn time_broker(&self) -> Option<NaiveDateTime> {
+        server_time_to_broker(self.time)
+    }
+
+    /// 开盘时间 (UTC)
+    pub fn time_utc(&self, offset_secs: i64) -> Option<DateTime<Utc>> {
+        server_time_to_utc(self.time, offset_secs)
+    }
+}
+
+impl From<DateTime<Utc>> for TimeInForce {
+    /// 在指定 UTC 时间前有效 (GTD)
+    fn from(expiration: DateTime<Utc>) -> Self {
+        TimeInForce::GoodTillDate(expiration.timestamp())
+    }
+}
+
+impl DriftEstimator {
+    /// 服务器时间戳按估计的经纪商时区换算为 UTC (无样本时为 None)
+    ///
+    /// 订单和K线时间是整秒的经纪商时间，按半小时取整的时区偏移换算，不包含时钟漂移
+    pub fn server_to_datetime(&self, server_time: i64) -> Option<DateTime<Utc>> {
+        server_time_to_utc(server_time, self.timezone_offset_secs()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_datetime_accessors() {
+        let quote = Quote { symbol: "EURUSD".to_string(), bid: 1.08, ask: 1.0801, time: 1_700_000_000 };
+        assert_eq!(quote.time_broker().unwrap().to_string(), "2023-11-14 22:13:20");
+        assert_eq!(quote.time_utc(3 * 3600).unwrap().to_rfc3339(), "2023-11-14T19:13:20+00:00");
+        assert!(server_time_to_utc(0, 7200).is_none());
+
+        let expiration = server_time_to_utc(1_700_007_200, 7200).unwrap();
+        assert_eq!(TimeInForce::from(expiration).to_server_time(7200), 1_700_007_200);
+
+        let mut estimator = DriftEstimator::default();
+        estimator.add_sample(1_700_000_000 + 7203, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        assert_eq!(estimator.server_to_datetime(1_700_007_200).unwrap().timestamp(), 1_700_000_000);
+    }
+}
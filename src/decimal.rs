@@ -0,0 +1,155 @@
+//! rust_decimal 访问器 (`decimal` 特性)
+//!
+//! 协议中的价格、手数和金额都是 f64，直接比较止损/止盈等价格时会遇到 `1.1 + 0.2 != 1.3`
+//! 之类的舍入问题。这里为 `Order`、`TradeRequest` 和 `Quote` 提供并行的 `Decimal` 访问器:
+//! 价格按品种小数位数、手数按 2 位、金额按 2 位四舍五入 (与 MT4 `NormalizeDouble` 一致)，
+//! 字段本身仍为 f64，不影响协议编码。
+//!
+//! ```
+//! use mt4_client::decimal::{price_to_decimal, to_f64};
+//! use rust_decimal::Decimal;
+//!
+//! let sl = price_to_decimal(1.1 + 0.2, 5);
+//! assert_eq!(sl, Decimal::new(130_000, 5));
+//! assert_eq!(to_f64(sl), 1.3);
+//! ```
+
+use crate::types::{Order, Quote, TradeRequest};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// 手数的小数位数
+pub const VOLUME_DECIMALS: u32 = 2;
+
+/// 金额 (盈亏、佣金、利息) 的小数位数
+pub const MONEY_DECIMALS: u32 = 2;
+
+/// f64 按小数位数四舍五入为 Decimal (NaN / 无穷大为 0)
+///
+/// 先按 f64 的最短十进制表示转换，`1.40005` 不会因二进制误差变成 `1.4000499...`
+pub fn price_to_decimal(value: f64, digits: u32) -> Decimal {
+    Decimal::from_f64(value)
+        .unwrap_or_default()
+        .round_dp_with_strategy(digits, RoundingStrategy::MidpointAwayFromZero)
+}
+
+/// Decimal 转换为 f64 (用于写回协议字段)
+pub fn to_f64(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or_default()
+}
+
+fn digits_of(digits: i32) -> u32 {
+    digits.max(0) as u32
+}
+
+impl Order {
+    /// 手数
+    pub fn volume_decimal(&self) -> Decimal {
+        price_to_decimal(self.volume, VOLUME_DECIMALS)
+    }
+
+    /// 开仓价
+    pub fn open_price_decimal(&self) -> Decimal {
+        price_to_decimal(self.open_price, digits_of(self.digits))
+    }
+
+    /// 止损 (0 表示未设置)
+    pub fn sl_decimal(&self) -> Decimal {
+        price_to_decimal(self.sl, digits_of(self.digits))
+    }
+
+    /// 止盈 (0 表示未设置)
+    pub fn tp_decimal(&self) -> Decimal {
+        price_to_decimal(self.tp, digits_of(self.digits))
+    }
+
+    /// 平仓价 (持仓为当前价)
+    pub fn close_price_decimal(&self) -> Decimal {
+        price_to_decimal(self.close_price, digits_of(self.digits))
+    }
+
+    /// 盈亏
+    pub fn profit_decimal(&self) -> Decimal {
+        price_to_decimal(self.profit, MONEY_DECIMALS)
+    }
+
+    /// 佣金
+    pub fn commission_decimal(&self) -> Decimal {
+        price_to_decimal(self.commission, MONEY_DECIMALS)
+    }
+
+    /// 隔夜利息
+    pub fn swap_decimal(&self) -> Decimal {
+        price_to_decimal(self.swap, MONEY_DECIMALS)
+    }
+}
+
+impl TradeRequest {
+    /// 手数
+    pub fn volume_decimal(&self) -> Decimal {
+        price_to_decimal(self.volume, VOLUME_DECIMALS)
+    }
+
+    /// 价格，按品种小数位数 `digits` 取整
+    pub fn price_decimal(&self, digits: i32) -> Decimal {
+        price_to_decimal(self.price, digits_of(digits))
+    }
+
+    /// 止损，按品种小数位数 `digits` 取整
+    pub fn sl_decimal(&self, digits: i32) -> Decimal {
+        price_to_decimal(self.sl, digits_of(digits))
+    }
+
+    /// 止盈，按品种小数位数 `digits` 取整
+    pub fn tp_decimal(&self, digits: i32) -> Decimal {
+        price_to_decimal(self.tp, digits_of(digits))
+    }
+
+    /// 以 Decimal 设置价格、止损和止盈
+    pub fn with_prices_decimal(mut self, price: Decimal, sl: Decimal, tp: Decimal) -> Self {
+        self.price = to_f64(price);
+        self.sl = to_f64(sl);
+        self.tp = to_f64(tp);
+        self
+    }
+}
+
+impl Quote {
+    /// 买价，按品种小数位数 `digits` 取整
+    pub fn bid_decimal(&self, digits: i32) -> Decimal {
+        price_to_decimal(self.bid, digits_of(digits))
+    }
+
+    /// 卖价，按品种小数位数 `digits` 取整
+    pub fn ask_decimal(&self, digits: i32) -> Decimal {
+        price_to_decimal(self.ask, digits_of(digits))
+    }
+
+    /// 点差 (ask - bid)
+    pub fn spread_decimal(&self, digits: i32) -> Decimal {
+        self.ask_decimal(digits) - self.bid_decimal(digits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Symbol;
+
+    #[test]
+    fn test_decimal_accessors() {
+        let request = TradeRequest::buy(&Symbol::new("EURUSD").unwrap(), 0.1 + 0.2, 1.1 + 0.2, 1.4).with_prices_decimal(
+            Decimal::ZERO,
+            Decimal::new(130_000, 5),
+            Decimal::new(140_005, 5),
+        );
+        assert_eq!(request.volume_decimal(), Decimal::new(30, 2));
+        assert_eq!(request.sl_decimal(5), Decimal::new(130_000, 5));
+        // 中间值远离零取整
+        assert_eq!(request.tp_decimal(4), Decimal::new(14_001, 4));
+
+        let quote = Quote { symbol: "USDJPY".to_string(), bid: 151.234, ask: 151.249, time: 0 };
+        assert_eq!(quote.spread_decimal(3), Decimal::new(15, 3));
+        assert_eq!(price_to_decimal(f64::NAN, 5), Decimal::ZERO);
+    }
+}
@@ -0,0 +1,150 @@
+//! 自定义命令解码
+//!
+//! 客户端没有解析的命令 (经纪商特有或尚未实现的命令) 默认作为 `Mt4Event::RawMessage` 发出。
+//! 为命令注册解码器后，这些帧交给解码器解析，结果作为 `Mt4Event::Custom` 发出:
+//!
+//! ```no_run
+//! use mt4_client::{Mt4Client, Mt4Event};
+//!
+//! #[derive(Debug)]
+//! struct ServerNews {
+//!     id: i32,
+//! }
+//!
+//! # async fn example() {
+//! let mut client = Mt4Client::new();
+//! client.register_decoder(31, "ServerNews", |_error_code, data| {
+//!     Some(ServerNews { id: i32::from_le_bytes(data.get(..4)?.try_into().ok()?) })
+//! });
+//! while let Some(event) = client.next_event().await {
+//!     if let Mt4Event::Custom(custom) = &event {
+//!         if let Some(news) = custom.downcast_ref::<ServerNews>() {
+//!             println!("news #{}", news.id);
+//!         }
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! - 只处理客户端内置流程不解析、也没有被 `request()` 认领的帧，不能覆盖内置命令
+//! - 解码器返回 None 时仍作为 `RawMessage` 发出
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+
+/// 解码后的值 (可 `Debug` 输出，可向下转型)
+pub trait CustomValue: Any + Debug + Send + Sync {
+    /// 转换为 `Any` 以便向下转型
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any + Debug + Send + Sync> CustomValue for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// 自定义解码器产生的事件
+#[derive(Debug, Clone)]
+pub struct CustomEvent {
+    /// 命令
+    pub command: u16,
+    /// 注册时给出的名称
+    pub name: &'static str,
+    /// 错误码
+    pub error_code: u8,
+    /// 解码结果
+    pub value: Arc<dyn CustomValue>,
+}
+
+impl CustomEvent {
+    /// 按类型取出解码结果，类型不符时返回 None
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        // 注意不能直接对 Arc 调用 as_any (Arc 本身也实现了 CustomValue)
+        (*self.value).as_any().downcast_ref()
+    }
+}
+
+type Decoder = Arc<dyn Fn(u8, &[u8]) -> Option<Arc<dyn CustomValue>> + Send + Sync>;
+
+/// 命令解码器登记表 (克隆后共享同一张表)
+#[derive(Clone, Default)]
+pub struct DecoderRegistry {
+    decoders: Arc<RwLock<HashMap<u16, (&'static str, Decoder)>>>,
+}
+
+impl Debug for DecoderRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut commands: Vec<u16> =
+            self.decoders.read().map(|decoders| decoders.keys().copied().collect()).unwrap_or_default();
+        commands.sort_unstable();
+        f.debug_struct("DecoderRegistry").field("commands", &commands).finish()
+    }
+}
+
+impl DecoderRegistry {
+    /// 创建空的登记表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为命令注册解码器 (替换已有的解码器)
+    pub fn register<T, F>(&self, command: u16, name: &'static str, decoder: F)
+    where
+        T: CustomValue,
+        F: Fn(u8, &[u8]) -> Option<T> + Send + Sync + 'static,
+    {
+        let decoder: Decoder =
+            Arc::new(move |error_code, data| decoder(error_code, data).map(|value| Arc::new(value) as Arc<dyn CustomValue>));
+        if let Ok(mut decoders) = self.decoders.write() {
+            decoders.insert(command, (name, decoder));
+        }
+    }
+
+    /// 移除命令的解码器，返回是否存在
+    pub fn unregister(&self, command: u16) -> bool {
+        self.decoders.write().map(|mut decoders| decoders.remove(&command).is_some()).unwrap_or(false)
+    }
+
+    /// 是否为命令注册了解码器
+    pub fn contains(&self, command: u16) -> bool {
+        self.decoders.read().map(|decoders| decoders.contains_key(&command)).unwrap_or(false)
+    }
+
+    /// 解码一个帧，没有解码器或解码器返回 None 时返回 None
+    pub fn decode(&self, command: u16, error_code: u8, data: &[u8]) -> Option<CustomEvent> {
+        // 解码在锁外进行，解码器内部可以注册其他命令
+        let (name, decoder) = self.decoders.read().ok()?.get(&command).cloned()?;
+        let value = decoder(error_code, data)?;
+        Some(CustomEvent { command, name, error_code, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Ticker(u32);
+
+    #[test]
+    fn test_decode() {
+        let registry = DecoderRegistry::new();
+        registry.register(200, "Ticker", |_, data| Some(Ticker(u32::from_le_bytes(data.get(..4)?.try_into().ok()?))));
+        assert!(registry.contains(200) && !registry.contains(201));
+
+        let event = registry.clone().decode(200, 0, &[7, 0, 0, 0]).unwrap();
+        assert_eq!((event.command, event.name), (200, "Ticker"));
+        assert_eq!(event.downcast_ref::<Ticker>(), Some(&Ticker(7)));
+        assert!(event.downcast_ref::<u32>().is_none());
+        assert!(format!("{:?}", event).contains("Ticker(7)"));
+
+        // 解码失败或没有解码器时返回 None
+        assert!(registry.decode(200, 0, &[1]).is_none());
+        assert!(registry.decode(201, 0, &[7, 0, 0, 0]).is_none());
+        assert!(registry.unregister(200));
+        assert!(registry.decode(200, 0, &[7, 0, 0, 0]).is_none());
+    }
+}
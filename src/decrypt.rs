@@ -0,0 +1,113 @@
+//! 入站帧解密流水线
+//!
+//! 订阅大量报价时，读取任务逐帧做 AES-CBC 解密会成为瓶颈。`DecryptPipeline` 把超过
+//! `offload_size` 的帧交给阻塞线程池解密，同时最多 `workers` 个帧在途，读取任务在等待期间
+//! 继续接收和处理已解密的帧。小帧仍在读取任务中解密 (线程切换比解密本身更慢)。
+//!
+//! 解密结果按接收顺序交给帧处理器，订单事件的先后顺序与连接上一致。参数通过
+//! `Mt4ClientBuilder::decrypt_pipeline()` 设置。
+
+use crate::crypto::SharedCrypto;
+use crate::error::{Mt4Error, Result};
+use crate::packet;
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use futures_util::stream::{FuturesOrdered, StreamExt};
+
+/// 默认同时在途的解密帧数
+pub const DEFAULT_DECRYPT_WORKERS: usize = 4;
+
+/// 默认交给线程池解密的最小帧长度 (字节)
+pub const DEFAULT_DECRYPT_OFFLOAD_SIZE: usize = 4096;
+
+/// 解密后的帧: (命令, 错误码, 数据)，负载过短时为 None
+pub(crate) type DecodedFrame = Result<Option<(u16, u8, Bytes)>>;
+
+/// 原始数据包及其解密结果
+pub(crate) type DecodedPacket = (Bytes, DecodedFrame);
+
+/// 保持顺序的解密流水线
+pub(crate) struct DecryptPipeline {
+    crypto: SharedCrypto,
+    workers: usize,
+    offload_size: usize,
+    pending: FuturesOrdered<BoxFuture<'static, DecodedPacket>>,
+}
+
+impl DecryptPipeline {
+    pub(crate) fn new(crypto: SharedCrypto, workers: usize, offload_size: usize) -> Self {
+        Self { crypto, workers: workers.max(1), offload_size, pending: FuturesOrdered::new() }
+    }
+
+    /// 是否还能接收新的帧
+    pub(crate) fn has_capacity(&self) -> bool {
+        self.pending.len() < self.workers
+    }
+
+    /// 是否有在途的帧
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// 加入一个收到的数据包
+    pub(crate) fn push(&mut self, data: Bytes) {
+        let crypto = self.crypto.clone();
+        let decode: BoxFuture<'static, DecodedPacket> = if data.len() >= self.offload_size {
+            Box::pin(async move {
+                tokio::task::spawn_blocking(move || {
+                    let decoded = packet::decode_packet(&data, &**crypto.blocking_read());
+                    (data, decoded)
+                })
+                .await
+                .unwrap_or_else(|e| (Bytes::new(), Err(Mt4Error::Decryption(format!("解密任务失败: {}", e)))))
+            })
+        } else {
+            Box::pin(async move {
+                let decoded = packet::decode_packet(&data, &**crypto.read().await);
+                (data, decoded)
+            })
+        };
+        self.pending.push_back(decode);
+    }
+
+    /// 按接收顺序取出下一个数据包及其解密结果，没有在途的帧时返回 None
+    pub(crate) async fn next(&mut self) -> Option<DecodedPacket> {
+        self.pending.next().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Mt4Crypto;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    #[tokio::test]
+    async fn test_preserves_order() {
+        let crypto: SharedCrypto = Arc::new(RwLock::new(Box::new(Mt4Crypto::new().unwrap())));
+        // 大帧和小帧交替，大帧在线程池中解密
+        let mut pipeline = DecryptPipeline::new(crypto.clone(), 3, 64);
+        let mut decoded = Vec::new();
+        for command in 0..10u16 {
+            let mut data = vec![0u8; if command % 2 == 0 { 256 } else { 4 }];
+            data[1] = command as u8;
+            let packet = packet::build_packet(command, command, &data, &**crypto.read().await, false).unwrap();
+            while !pipeline.has_capacity() {
+                decoded.push(pipeline.next().await.unwrap().1.unwrap().unwrap());
+            }
+            pipeline.push(packet.into());
+        }
+        while let Some((_, frame)) = pipeline.next().await {
+            decoded.push(frame.unwrap().unwrap());
+        }
+        assert!(pipeline.is_empty());
+        let commands: Vec<u16> = decoded.iter().map(|(command, _, _)| *command).collect();
+        assert_eq!(commands, (0..10).collect::<Vec<_>>());
+        assert!(decoded.iter().all(|(command, _, data)| data[0] == *command as u8));
+
+        pipeline.push(Bytes::from(vec![0u8; 8 + 15]));
+        let (packet, decoded) = pipeline.next().await.unwrap();
+        assert!(packet.len() == 23 && decoded.is_err());
+    }
+}
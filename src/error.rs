@@ -1,5 +1,6 @@
 //! 错误类型定义
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// MT4 客户端错误类型
@@ -9,9 +10,10 @@ pub enum Mt4Error {
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
 
-    /// WebSocket 错误
+    /// WebSocket 错误 (装箱以减小 Result 体积)
+    #[cfg(not(target_arch = "wasm32"))]
     #[error("WebSocket error: {0}")]
-    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    WebSocket(#[from] Box<tokio_tungstenite::tungstenite::Error>),
 
     /// 加密错误
     #[error("Encryption error: {0}")]
@@ -45,6 +47,10 @@ pub enum Mt4Error {
     #[error("Operation timeout")]
     Timeout,
 
+    /// 经纪商未启用 Web Terminal (可改用本地终端桥接，见 `Mt4Client::connect_bridge`)
+    #[error("Web Terminal not supported")]
+    WebTerminalDisabled,
+
     /// 服务器错误
     #[error("Server error: {0}")]
     Server(String),
@@ -52,6 +58,29 @@ pub enum Mt4Error {
     /// 无效参数
     #[error("Invalid parameters: {0}")]
     InvalidParams(String),
+
+    /// 超出交易频率限制
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    /// 本地文件或存储读写错误 (录制文件、日志、意图队列等)
+    #[error("IO error: {0}")]
+    Io(String),
+
+    /// 序列化或反序列化失败
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    /// 违反风险限制 (见 `risk` 模块)
+    #[error("Risk rejected ({rule}): {reason}")]
+    RiskRejected { rule: RiskRule, reason: String },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<tokio_tungstenite::tungstenite::Error> for Mt4Error {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        Mt4Error::WebSocket(Box::new(e))
+    }
 }
 
 /// 交易错误码映射
@@ -103,5 +132,159 @@ impl Mt4Error {
     }
 }
 
+/// 错误分类
+///
+/// 自动交易系统据此决定是否重试，无需直接匹配原始错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// 服务器或交易通道繁忙 (4, 137, 139, 146)
+    Busy,
+    /// 价格已变化或重新报价 (135, 138)
+    Requote,
+    /// 无报价 (136)
+    OffQuotes,
+    /// 请求过于频繁 (8, 141，以及本地频率限制)
+    RateLimited,
+    /// 连接中断或未连接
+    Connection,
+    /// 交易超时 (128)，请求可能已被执行
+    Timeout,
+    /// 请求参数无效 (3, 129, 130, 131, 145, 147，以及本地参数校验)
+    InvalidRequest,
+    /// 资金不足 (134)
+    InsufficientFunds,
+    /// 交易被禁止或市场关闭 (7, 64, 65, 132, 133, 140, 149, 150)
+    TradeDisabled,
+    /// 订单数量达到上限 (148)
+    TooManyOrders,
+    /// 违反本地风险限制
+    RiskRejected,
+    /// 认证失败或经纪商未启用 Web Terminal
+    Auth,
+    /// 加解密或协议错误
+    Protocol,
+    /// 本地文件或存储读写失败，可能是暂时的 (磁盘已满、文件被占用)
+    Io,
+    /// 本地数据序列化或反序列化失败 (文件内容损坏或格式不兼容)
+    Serialization,
+    /// 其他错误
+    Other,
+}
+
+impl ErrorKind {
+    /// 交易错误码的分类
+    pub fn from_trade_code(code: u8) -> Self {
+        match code {
+            4 | 137 | 139 | 146 => ErrorKind::Busy,
+            135 | 138 => ErrorKind::Requote,
+            136 => ErrorKind::OffQuotes,
+            8 | 141 => ErrorKind::RateLimited,
+            6 => ErrorKind::Connection,
+            128 => ErrorKind::Timeout,
+            3 | 129 | 130 | 131 | 145 | 147 => ErrorKind::InvalidRequest,
+            134 => ErrorKind::InsufficientFunds,
+            7 | 64 | 65 | 132 | 133 | 140 | 149 | 150 => ErrorKind::TradeDisabled,
+            148 => ErrorKind::TooManyOrders,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// 稍后以相同参数重试是否可能成功
+    ///
+    /// 超时不可重试: 请求可能已被执行，应先核对持仓
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Busy
+                | ErrorKind::Requote
+                | ErrorKind::OffQuotes
+                | ErrorKind::RateLimited
+                | ErrorKind::Connection
+                | ErrorKind::Io
+        )
+    }
+}
+
+impl Mt4Error {
+    /// 错误分类
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Mt4Error::Trade { code, .. } => ErrorKind::from_trade_code(*code),
+            Mt4Error::Http(_) | Mt4Error::Connection(_) | Mt4Error::NotConnected => ErrorKind::Connection,
+            #[cfg(not(target_arch = "wasm32"))]
+            Mt4Error::WebSocket(_) => ErrorKind::Connection,
+            Mt4Error::Timeout => ErrorKind::Timeout,
+            Mt4Error::InvalidParams(_) => ErrorKind::InvalidRequest,
+            Mt4Error::RateLimited(_) => ErrorKind::RateLimited,
+            Mt4Error::RiskRejected { .. } => ErrorKind::RiskRejected,
+            Mt4Error::AuthFailed(_) | Mt4Error::WebTerminalDisabled => ErrorKind::Auth,
+            Mt4Error::Encryption(_) | Mt4Error::Decryption(_) | Mt4Error::Protocol(_) => ErrorKind::Protocol,
+            Mt4Error::Io(_) => ErrorKind::Io,
+            Mt4Error::Serialization(_) => ErrorKind::Serialization,
+            Mt4Error::Server(_) => ErrorKind::Other,
+        }
+    }
+
+    /// 稍后重试是否可能成功 (见 `ErrorKind::is_retryable`)
+    ///
+    /// ```
+    /// use mt4_client::Mt4Error;
+    ///
+    /// assert!(Mt4Error::from_trade_code(146).is_retryable());
+    /// assert!(!Mt4Error::from_trade_code(131).is_retryable());
+    /// ```
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+}
+
+/// 风险规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RiskRule {
+    /// 总手数上限
+    MaxOpenLots,
+    /// 单个品种的订单数量上限
+    MaxPositionsPerSymbol,
+    /// 当日最大亏损
+    MaxDailyLoss,
+    /// 最大滑点
+    MaxSlippage,
+}
+
+impl std::fmt::Display for RiskRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RiskRule::MaxOpenLots => "max_open_lots",
+            RiskRule::MaxPositionsPerSymbol => "max_positions_per_symbol",
+            RiskRule::MaxDailyLoss => "max_daily_loss",
+            RiskRule::MaxSlippage => "max_slippage",
+        };
+        f.write_str(name)
+    }
+}
+
 /// 结果类型别名
 pub type Result<T> = std::result::Result<T, Mt4Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(Mt4Error::from_trade_code(138).kind(), ErrorKind::Requote);
+        assert_eq!(Mt4Error::from_trade_code(136).kind(), ErrorKind::OffQuotes);
+        assert_eq!(Mt4Error::from_trade_code(133).kind(), ErrorKind::TradeDisabled);
+        assert_eq!(Mt4Error::InvalidParams("volume".into()).kind(), ErrorKind::InvalidRequest);
+        assert_eq!(Mt4Error::Io("disk full".into()).kind(), ErrorKind::Io);
+        assert_eq!(Mt4Error::Serialization("eof".into()).kind(), ErrorKind::Serialization);
+
+        assert!(Mt4Error::from_trade_code(4).is_retryable());
+        assert!(Mt4Error::NotConnected.is_retryable());
+        assert!(Mt4Error::Io("disk full".into()).is_retryable());
+        assert!(!Mt4Error::Serialization("eof".into()).is_retryable());
+        for code in [3, 128, 131, 132, 134, 148, 150] {
+            assert!(!Mt4Error::from_trade_code(code).is_retryable(), "code {}", code);
+        }
+    }
+}
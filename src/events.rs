@@ -0,0 +1,540 @@
+//! 事件分发
+//!
+//! 读取任务和客户端产生的所有事件都通过 `EventSender` 发出，
+//! 在这里统一打上接收时间戳并更新服务器时钟偏移估计。
+//!
+//! 开启报价合并 (`Mt4ClientBuilder::conflate_quotes()`) 后，事件通道已满时报价不再阻塞读取任务，
+//! 而是暂存在通道外，每个品种只保留最新一条；接收端读空通道后再取出暂存的报价。
+//! 其他事件发送前先把暂存的报价送入通道，事件顺序不变且不会被丢弃。广播订阅不受影响。
+//!
+//! `Mt4Client::quotes()` 返回只包含单个品种报价的 `QuoteReceiver`，多品种策略不必在热路径中
+//! 从全局事件流里按品种分拣。每个品种一个广播通道，接收端全部丢弃后通道随下一条报价移除。
+//! `Mt4Client::subscribe_filtered()` 的订阅在分发时按 `EventFilter` 过滤，只复制匹配的事件。
+
+use crate::callbacks::SharedCallbacks;
+use crate::client::Mt4Event;
+use crate::clock::{DriftEstimator, EventTime};
+use crate::filter::EventFilter;
+use crate::forensics::SharedForensics;
+use crate::telemetry;
+use crate::types::Quote;
+use futures_util::Stream;
+use std::collections::{HashMap, VecDeque};
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use tokio::sync::{broadcast, mpsc};
+
+/// 最近事件缓冲区默认容量
+pub const RECENT_EVENTS_CAPACITY: usize = 100;
+
+/// 事件广播缓冲区容量 (每个订阅最多落后的事件数)
+pub const EVENT_BROADCAST_CAPACITY: usize = 1024;
+
+/// 单品种报价通道容量 (每个接收端最多落后的报价数)
+pub const QUOTE_CHANNEL_CAPACITY: usize = 256;
+
+/// 带时间戳的事件
+#[derive(Debug, Clone)]
+pub struct TimedEvent {
+    /// 事件
+    pub event: Mt4Event,
+    /// 接收时间 (本地单调时钟 + UTC) 及服务器时间
+    pub time: EventTime,
+    /// 事件序号 (同一客户端内从 1 开始连续递增，可用于 `subscribe_from()` 续订；
+    /// 未经客户端分发的事件，如订阅快照，为 0)
+    pub seq: u64,
+}
+
+/// 通道已满时暂存的报价 (每个品种一条，按首次暂存的顺序)
+#[derive(Debug, Default)]
+pub(crate) struct ConflatedQuotes {
+    quotes: VecDeque<TimedEvent>,
+}
+
+impl ConflatedQuotes {
+    /// 暂存报价，同一品种已有暂存时替换为最新报价
+    fn push(&mut self, event: TimedEvent) {
+        let Mt4Event::Quote(quote) = &event.event else { return };
+        let existing =
+            self.quotes.iter_mut().find(|e| matches!(&e.event, Mt4Event::Quote(q) if q.symbol == quote.symbol));
+        match existing {
+            Some(existing) => *existing = event,
+            None => self.quotes.push_back(event),
+        }
+    }
+}
+
+type SharedConflation = Arc<Mutex<ConflatedQuotes>>;
+
+/// 创建事件通道，`conflate_quotes` 为 true 时开启报价合并
+pub(crate) fn event_channel(
+    capacity: usize,
+    conflate_quotes: bool,
+) -> (mpsc::Sender<TimedEvent>, EventReceiver, Option<SharedConflation>) {
+    let (tx, rx) = mpsc::channel(capacity);
+    let conflation = conflate_quotes.then(SharedConflation::default);
+    (tx, EventReceiver { rx, conflation: conflation.clone() }, conflation)
+}
+
+/// 事件发送端
+#[derive(Debug, Clone)]
+pub(crate) struct EventSender {
+    tx: mpsc::Sender<TimedEvent>,
+    broadcast: broadcast::Sender<TimedEvent>,
+    clock: Arc<RwLock<DriftEstimator>>,
+    recent: Arc<Mutex<VecDeque<TimedEvent>>>,
+    recent_capacity: usize,
+    forensics: SharedForensics,
+    conflation: Option<SharedConflation>,
+    callbacks: Option<SharedCallbacks>,
+    filtered: Option<Arc<FilteredSubscribers>>,
+}
+
+impl EventSender {
+    pub(crate) fn new(
+        tx: mpsc::Sender<TimedEvent>,
+        broadcast: broadcast::Sender<TimedEvent>,
+        clock: Arc<RwLock<DriftEstimator>>,
+        recent: Arc<Mutex<VecDeque<TimedEvent>>>,
+        recent_capacity: usize,
+        forensics: SharedForensics,
+        conflation: Option<SharedConflation>,
+    ) -> Self {
+        Self { tx, broadcast, clock, recent, recent_capacity, forensics, conflation, callbacks: None, filtered: None }
+    }
+
+    /// 发送前调用已注册的回调 (见 `callbacks` 模块)
+    pub(crate) fn with_callbacks(mut self, callbacks: SharedCallbacks) -> Self {
+        self.callbacks = Some(callbacks);
+        self
+    }
+
+    /// 同时分发给带过滤条件的订阅
+    pub(crate) fn with_filtered_subscribers(mut self, filtered: Arc<FilteredSubscribers>) -> Self {
+        self.filtered = Some(filtered);
+        self
+    }
+
+    /// 打上时间戳后发送事件
+    pub(crate) async fn send(
+        &self,
+        event: Mt4Event,
+    ) -> std::result::Result<(), mpsc::error::SendError<TimedEvent>> {
+        let time = EventTime::now(event.server_time());
+        if let Some(server_time) = time.server_time {
+            if let Ok(mut clock) = self.clock.write() {
+                clock.add_sample(server_time, time.utc);
+            }
+        }
+        telemetry::event(event.kind());
+        if let Some(callbacks) = &self.callbacks {
+            callbacks.dispatch(&event);
+        }
+        let mut timed = TimedEvent { event, time, seq: 0 };
+        // 在最近事件锁内分配序号并广播，订阅时取最近事件与订阅广播不会重复或遗漏
+        if let Ok(mut recent) = self.recent.lock() {
+            timed.seq = recent.back().map_or(0, |e| e.seq) + 1;
+            while recent.len() >= self.recent_capacity.max(1) {
+                recent.pop_front();
+            }
+            recent.push_back(timed.clone());
+            // 没有订阅者时发送失败，忽略
+            let _ = self.broadcast.send(timed.clone());
+            if let Some(filtered) = &self.filtered {
+                filtered.publish(&timed);
+            }
+        }
+        if let Ok(mut forensics) = self.forensics.lock() {
+            if let Some(f) = forensics.as_mut() {
+                f.record_event(&timed);
+            }
+        }
+        if let Some(conflation) = &self.conflation {
+            if matches!(timed.event, Mt4Event::Quote(_)) {
+                let Ok(mut pending) = conflation.lock() else { return self.tx.send(timed).await };
+                // 已有暂存的报价时也暂存，保持报价之间的顺序
+                if pending.quotes.is_empty() {
+                    match self.tx.try_send(timed) {
+                        Ok(()) => return Ok(()),
+                        Err(mpsc::error::TrySendError::Full(timed)) => pending.push(timed),
+                        Err(mpsc::error::TrySendError::Closed(timed)) => return Err(mpsc::error::SendError(timed)),
+                    }
+                } else {
+                    pending.push(timed);
+                }
+                return Ok(());
+            }
+            let pending = conflation.lock().map(|mut p| std::mem::take(&mut p.quotes)).unwrap_or_default();
+            for quote in pending {
+                self.tx.send(quote).await?;
+            }
+        }
+        self.tx.send(timed).await
+    }
+}
+
+/// 事件通道接收端 (通道读空后取出暂存的报价)
+#[derive(Debug)]
+pub(crate) struct EventReceiver {
+    rx: mpsc::Receiver<TimedEvent>,
+    conflation: Option<SharedConflation>,
+}
+
+impl EventReceiver {
+    pub(crate) async fn recv(&mut self) -> Option<TimedEvent> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<TimedEvent>> {
+        match self.rx.poll_recv(cx) {
+            Poll::Ready(Some(event)) => Poll::Ready(Some(event)),
+            // 通道为空 (或已关闭) 时取暂存的报价；发送端只在通道已满时暂存，之后必有唤醒
+            other => match self.conflation.as_ref().and_then(|c| c.lock().ok()?.quotes.pop_front()) {
+                Some(event) => Poll::Ready(Some(event)),
+                None => other,
+            },
+        }
+    }
+}
+
+/// 事件流 (由 `Mt4Client::events()` 创建)
+///
+/// 实现 `Stream<Item = Mt4Event>`，可配合 `StreamExt` 组合子和 `select!` 使用。
+/// 连接断开时先收到 `Mt4Event::Disconnected`；客户端断开或被丢弃后流结束
+#[derive(Debug)]
+pub struct EventStream {
+    rx: EventReceiver,
+}
+
+impl EventStream {
+    pub(crate) fn new(rx: EventReceiver) -> Self {
+        Self { rx }
+    }
+
+    /// 转换为带时间戳的事件流
+    pub fn timed(self) -> TimedEventStream {
+        TimedEventStream { rx: self.rx }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Mt4Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx).map(|e| e.map(|e| e.event))
+    }
+}
+
+/// 带时间戳的事件流
+#[derive(Debug)]
+pub struct TimedEventStream {
+    rx: EventReceiver,
+}
+
+impl Stream for TimedEventStream {
+    type Item = TimedEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// 事件广播订阅 (由 `Mt4Client::subscribe()` / `Mt4Client::subscribe_filtered()` 创建)
+#[derive(Debug)]
+pub struct EventSubscription {
+    source: SubscriptionSource,
+    /// 订阅前的事件 (回放或快照)，先于实时事件返回
+    backlog: VecDeque<TimedEvent>,
+    lagged: u64,
+}
+
+#[derive(Debug)]
+enum SubscriptionSource {
+    Broadcast(broadcast::Receiver<TimedEvent>),
+    /// 带过滤条件的订阅 (通道已满时丢弃的事件数由发送端累计)
+    Filtered { rx: mpsc::Receiver<TimedEvent>, dropped: Arc<AtomicU64> },
+}
+
+impl EventSubscription {
+    pub(crate) fn new(rx: broadcast::Receiver<TimedEvent>) -> Self {
+        Self::with_backlog(rx, VecDeque::new())
+    }
+
+    pub(crate) fn with_backlog(rx: broadcast::Receiver<TimedEvent>, backlog: VecDeque<TimedEvent>) -> Self {
+        Self { source: SubscriptionSource::Broadcast(rx), backlog, lagged: 0 }
+    }
+
+    /// 回放缓冲区中序号大于 `seq` 的事件
+    ///
+    /// 中间已被缓冲区覆盖的事件计入 `lagged()`；`seq` 大于最新序号 (来自客户端重启之前) 时回放整个缓冲区
+    pub(crate) fn replay_from(rx: broadcast::Receiver<TimedEvent>, recent: &VecDeque<TimedEvent>, seq: u64) -> Self {
+        let latest = recent.back().map_or(0, |e| e.seq);
+        let seq = if seq > latest { 0 } else { seq };
+        let backlog: VecDeque<TimedEvent> = recent.iter().filter(|e| e.seq > seq).cloned().collect();
+        let lagged = backlog.front().map_or(0, |e| e.seq - seq - 1);
+        Self { source: SubscriptionSource::Broadcast(rx), backlog, lagged }
+    }
+
+    /// 尚未取出的订阅前事件数
+    pub fn backlog_len(&self) -> usize {
+        self.backlog.len()
+    }
+
+    /// 接收下一个事件 (客户端被丢弃后返回 None)
+    pub async fn recv(&mut self) -> Option<Mt4Event> {
+        self.recv_timed().await.map(|e| e.event)
+    }
+
+    /// 接收下一个带时间戳的事件
+    ///
+    /// 订阅落后过多时跳过被覆盖的事件并累计到 `lagged()`
+    pub async fn recv_timed(&mut self) -> Option<TimedEvent> {
+        if let Some(event) = self.backlog.pop_front() {
+            return Some(event);
+        }
+        let rx = match &mut self.source {
+            SubscriptionSource::Broadcast(rx) => rx,
+            SubscriptionSource::Filtered { rx, .. } => return rx.recv().await,
+        };
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("Event subscriber lagged, {} events dropped", n);
+                    self.lagged += n;
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// 因落后而丢弃的事件总数
+    pub fn lagged(&self) -> u64 {
+        match &self.source {
+            SubscriptionSource::Broadcast(_) => self.lagged,
+            SubscriptionSource::Filtered { dropped, .. } => dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 带过滤条件的订阅者
+#[derive(Debug)]
+struct FilteredSubscriber {
+    filter: EventFilter,
+    tx: mpsc::Sender<TimedEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+/// 带过滤条件的订阅 (在发送端过滤后分别投递)
+#[derive(Debug, Default)]
+pub(crate) struct FilteredSubscribers {
+    subscribers: Mutex<Vec<FilteredSubscriber>>,
+}
+
+impl FilteredSubscribers {
+    /// 创建订阅 (每个订阅最多缓存 `EVENT_BROADCAST_CAPACITY` 个事件，超出时丢弃新事件)
+    pub(crate) fn subscribe(&self, filter: EventFilter) -> EventSubscription {
+        let (tx, rx) = mpsc::channel(EVENT_BROADCAST_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(FilteredSubscriber { filter, tx, dropped: dropped.clone() });
+        }
+        EventSubscription {
+            source: SubscriptionSource::Filtered { rx, dropped },
+            backlog: VecDeque::new(),
+            lagged: 0,
+        }
+    }
+
+    /// 将事件投递给条件匹配的订阅 (移除已丢弃的订阅)
+    pub(crate) fn publish(&self, timed: &TimedEvent) {
+        let Ok(mut subscribers) = self.subscribers.lock() else { return };
+        subscribers.retain(|subscriber| {
+            let Some(event) = subscriber.filter.apply(&timed.event) else {
+                return !subscriber.tx.is_closed();
+            };
+            match subscriber.tx.try_send(TimedEvent { event, time: timed.time, seq: timed.seq }) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    subscriber.dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            }
+        });
+    }
+}
+
+/// 按品种分发报价的广播通道
+#[derive(Debug, Default)]
+pub(crate) struct QuoteChannels {
+    senders: Mutex<HashMap<String, broadcast::Sender<Quote>>>,
+}
+
+impl QuoteChannels {
+    /// 订阅品种的报价
+    pub(crate) fn subscribe(&self, symbol: &str) -> QuoteReceiver {
+        let rx = match self.senders.lock() {
+            Ok(mut senders) => senders
+                .entry(symbol.to_string())
+                .or_insert_with(|| broadcast::channel(QUOTE_CHANNEL_CAPACITY).0)
+                .subscribe(),
+            // 锁已中毒时返回立即结束的接收端
+            Err(_) => broadcast::channel(1).0.subscribe(),
+        };
+        QuoteReceiver { symbol: symbol.to_string(), rx, lagged: 0 }
+    }
+
+    /// 将报价发给该品种的接收端 (没有接收端时移除通道)
+    pub(crate) fn publish(&self, quote: &Quote) {
+        let Ok(mut senders) = self.senders.lock() else { return };
+        if let Some(tx) = senders.get(&quote.symbol) {
+            if tx.send(quote.clone()).is_err() {
+                senders.remove(&quote.symbol);
+            }
+        }
+    }
+}
+
+/// 单品种报价接收端 (由 `Mt4Client::quotes()` 创建)
+#[derive(Debug)]
+pub struct QuoteReceiver {
+    symbol: String,
+    rx: broadcast::Receiver<Quote>,
+    lagged: u64,
+}
+
+impl QuoteReceiver {
+    /// 订阅的品种
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// 接收下一条报价 (客户端被丢弃后返回 None)
+    ///
+    /// 落后超过 `QUOTE_CHANNEL_CAPACITY` 条时跳过被覆盖的报价并累计到 `lagged()`
+    pub async fn recv(&mut self) -> Option<Quote> {
+        loop {
+            match self.rx.recv().await {
+                Ok(quote) => return Some(quote),
+                Err(broadcast::error::RecvError::Lagged(n)) => self.lagged += n,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// 因落后而丢弃的报价总数
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+
+    /// 转换为报价流
+    pub fn into_stream(self) -> impl Stream<Item = Quote> + Send + 'static {
+        futures_util::stream::unfold(self, |mut rx| async move { rx.recv().await.map(|quote| (quote, rx)) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn quote(symbol: &str, bid: f64) -> Mt4Event {
+        Mt4Event::Quote(Quote { symbol: symbol.to_string(), bid, ask: bid + 0.0002, time: 0 })
+    }
+
+    #[tokio::test]
+    async fn test_conflates_quotes_when_full() {
+        let (tx, mut rx, conflation) = event_channel(3, true);
+        let sender = EventSender::new(
+            tx,
+            broadcast::channel(16).0,
+            Arc::default(),
+            Arc::default(),
+            16,
+            Arc::default(),
+            conflation,
+        );
+
+        // 通道已满后报价不阻塞，同一品种只保留最新一条
+        let quotes = [("EURUSD", 1.0), ("EURUSD", 2.0), ("EURUSD", 3.0), ("GBPUSD", 1.0), ("EURUSD", 4.0), ("EURUSD", 5.0)];
+        for (symbol, bid) in quotes {
+            tokio::time::timeout(Duration::from_millis(100), sender.send(quote(symbol, bid))).await.unwrap().unwrap();
+        }
+        // 交易事件不丢弃，在暂存的报价之后送达
+        let trade = tokio::spawn(async move {
+            sender.send(Mt4Event::TradeFailed { code: 134, message: "no money".to_string() }).await.unwrap();
+        });
+
+        let mut received = Vec::new();
+        for _ in 0..6 {
+            received.push(match rx.recv().await.unwrap().event {
+                Mt4Event::Quote(q) => format!("{} {}", q.symbol, q.bid),
+                other => other.kind().to_string(),
+            });
+        }
+        trade.await.unwrap();
+        assert_eq!(received, ["EURUSD 1", "EURUSD 2", "EURUSD 3", "GBPUSD 1", "EURUSD 5", "TradeFailed"]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_from_sequence() {
+        let (tx, _rx, _) = event_channel(16, false);
+        let (broadcast, _) = broadcast::channel(16);
+        let recent: Arc<Mutex<VecDeque<TimedEvent>>> = Arc::default();
+        let sender = EventSender::new(tx, broadcast.clone(), Arc::default(), recent.clone(), 3, Arc::default(), None);
+        for bid in 1..=5 {
+            sender.send(quote("EURUSD", bid as f64)).await.unwrap();
+        }
+        let recent = recent.lock().unwrap().clone();
+        assert_eq!(recent.iter().map(|e| e.seq).collect::<Vec<_>>(), [3, 4, 5]);
+
+        // 序号 2 已被覆盖，计入 lagged
+        let mut late = EventSubscription::replay_from(broadcast.subscribe(), &recent, 1);
+        assert_eq!((late.backlog_len(), late.lagged()), (3, 1));
+        assert_eq!(late.recv_timed().await.map(|e| e.seq), Some(3));
+        let current = EventSubscription::replay_from(broadcast.subscribe(), &recent, 5);
+        assert_eq!((current.backlog_len(), current.lagged()), (0, 0));
+        // 序号来自重启之前: 回放整个缓冲区
+        assert_eq!(EventSubscription::replay_from(broadcast.subscribe(), &recent, 99).backlog_len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_filtered_subscription() {
+        let filtered = FilteredSubscribers::default();
+        let mut trades = filtered.subscribe(EventFilter::new().with_kinds(["TradeFailed"]));
+        let quotes = filtered.subscribe(EventFilter::new().with_symbols(["EURUSD"]));
+        for event in [quote("EURUSD", 1.1), Mt4Event::TradeFailed { code: 134, message: String::new() }] {
+            filtered.publish(&TimedEvent { event, time: EventTime::now(None), seq: 0 });
+        }
+        assert_eq!(trades.recv().await.map(|e| e.kind()), Some("TradeFailed"));
+        assert_eq!(trades.lagged(), 0);
+
+        // 丢弃的订阅在下一次分发时移除
+        drop(quotes);
+        filtered.publish(&TimedEvent { event: quote("EURUSD", 1.2), time: EventTime::now(None), seq: 0 });
+        assert_eq!(filtered.subscribers.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_quote_channels_per_symbol() {
+        let channels = QuoteChannels::default();
+        let mut eurusd = channels.subscribe("EURUSD");
+        let gbpusd = channels.subscribe("GBPUSD");
+        for (symbol, bid) in [("GBPUSD", 1.25), ("EURUSD", 1.1), ("USDJPY", 150.0), ("EURUSD", 1.2)] {
+            channels.publish(&Quote { symbol: symbol.to_string(), bid, ask: bid, time: 0 });
+        }
+        assert_eq!(eurusd.recv().await.map(|q| q.bid), Some(1.1));
+        assert_eq!(eurusd.recv().await.map(|q| q.bid), Some(1.2));
+
+        // 接收端全部丢弃后，通道随下一条报价移除
+        drop(gbpusd);
+        channels.publish(&Quote { symbol: "GBPUSD".to_string(), bid: 1.26, ask: 1.26, time: 0 });
+        assert_eq!(channels.senders.lock().unwrap().len(), 1);
+        drop(channels);
+        assert!(eurusd.recv().await.is_none());
+    }
+}
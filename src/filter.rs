@@ -0,0 +1,174 @@
+//! 事件订阅过滤
+//!
+//! `Mt4Client::subscribe_filtered()` 创建带过滤条件的订阅。过滤在客户端分发事件时执行，
+//! 不匹配的事件 (例如高频报价) 不会复制给只关心交易的订阅者:
+//!
+//! ```no_run
+//! # fn example(client: &mt4_client::Mt4Client) {
+//! use mt4_client::{EventFilter, Ticket};
+//!
+//! // 只接收 EURUSD 的订单更新和状态变化
+//! let filter = EventFilter::new().with_kinds(["OrderUpdates", "OrderStateChanged"]).with_symbols(["EURUSD"]);
+//! let mut trades = client.subscribe_filtered(filter);
+//!
+//! // 只接收两个订单相关的事件
+//! let mut watched = client.subscribe_filtered(EventFilter::new().with_tickets([Ticket(1001), Ticket(1002)]));
+//! # }
+//! ```
+//!
+//! - 各项条件同时满足才匹配；未设置的条件不限制
+//! - 设置品种 / 订单号条件后，不携带品种 / 订单号的事件 (如 `AccountInfo`) 不匹配
+//! - 批量事件 (`OrderUpdates`、`HistoryOrders`) 只保留匹配的订单，全部不匹配时不发送；
+//!   `PositionsSnapshot` 只保留匹配的订单，为空时仍发送 (表示没有匹配的持仓)
+
+use crate::client::Mt4Event;
+use crate::types::{Order, OrderUpdate, Ticket};
+use std::collections::HashSet;
+
+/// 事件过滤条件
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventFilter {
+    kinds: Option<HashSet<String>>,
+    symbols: Option<HashSet<String>>,
+    tickets: Option<HashSet<Ticket>>,
+}
+
+impl EventFilter {
+    /// 不限制任何条件的过滤器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 只接收这些类型的事件 (`Mt4Event::kind()` 的返回值)
+    pub fn with_kinds<I, S>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.kinds.get_or_insert_with(HashSet::new).extend(kinds.into_iter().map(Into::into));
+        self
+    }
+
+    /// 只接收这些品种的事件
+    pub fn with_symbols<I, S>(mut self, symbols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.symbols.get_or_insert_with(HashSet::new).extend(symbols.into_iter().map(Into::into));
+        self
+    }
+
+    /// 只接收这些订单的事件
+    pub fn with_tickets(mut self, tickets: impl IntoIterator<Item = Ticket>) -> Self {
+        self.tickets.get_or_insert_with(HashSet::new).extend(tickets);
+        self
+    }
+
+    /// 按条件过滤事件: 不匹配时返回 None，批量事件只保留匹配的订单
+    pub fn apply(&self, event: &Mt4Event) -> Option<Mt4Event> {
+        if self.kinds.as_ref().is_some_and(|kinds| !kinds.contains(event.kind())) {
+            return None;
+        }
+        if self.symbols.is_none() && self.tickets.is_none() {
+            return Some(event.clone());
+        }
+        match event {
+            Mt4Event::OrderUpdates(updates) => {
+                let updates: Vec<OrderUpdate> =
+                    updates.iter().filter(|u| self.order_matches(&u.order)).cloned().collect();
+                (!updates.is_empty()).then_some(Mt4Event::OrderUpdates(updates))
+            }
+            Mt4Event::HistoryOrders(orders) => {
+                let orders = self.matching_orders(orders);
+                (!orders.is_empty()).then_some(Mt4Event::HistoryOrders(orders))
+            }
+            Mt4Event::PositionsSnapshot(orders) => Some(Mt4Event::PositionsSnapshot(self.matching_orders(orders))),
+            other => {
+                let symbol = event_symbol(other);
+                let ticket = event_ticket(other);
+                let symbol_ok = self.symbols.as_ref().is_none_or(|s| symbol.is_some_and(|symbol| s.contains(symbol)));
+                let ticket_ok = self.tickets.as_ref().is_none_or(|t| ticket.is_some_and(|ticket| t.contains(&ticket)));
+                (symbol_ok && ticket_ok).then(|| other.clone())
+            }
+        }
+    }
+
+    fn order_matches(&self, order: &Order) -> bool {
+        self.symbols.as_ref().is_none_or(|s| s.contains(order.symbol.as_str()))
+            && self.tickets.as_ref().is_none_or(|t| t.contains(&order.ticket))
+    }
+
+    fn matching_orders(&self, orders: &[Order]) -> Vec<Order> {
+        orders.iter().filter(|o| self.order_matches(o)).cloned().collect()
+    }
+}
+
+/// 事件涉及的品种
+fn event_symbol(event: &Mt4Event) -> Option<&str> {
+    match event {
+        Mt4Event::OrderUpdate(update) => Some(update.order.symbol.as_str()),
+        Mt4Event::Quote(quote) => Some(&quote.symbol),
+        Mt4Event::TradeTimeout { request, .. } | Mt4Event::RiskRejected { request, .. } => {
+            Some(request.symbol.as_str())
+        }
+        Mt4Event::Requote { symbol, .. } => Some(symbol.as_str()),
+        Mt4Event::OrderStateChanged(transition) => Some(transition.order.symbol.as_str()),
+        Mt4Event::StateDivergence(divergence) => Some(divergence.order().symbol.as_str()),
+        Mt4Event::SpreadAlert(alert) => Some(&alert.symbol),
+        Mt4Event::CandleClosed(bar) => Some(&bar.symbol),
+        Mt4Event::BackfillProgress(progress) => Some(&progress.symbol),
+        _ => None,
+    }
+}
+
+/// 事件涉及的订单号
+fn event_ticket(event: &Mt4Event) -> Option<Ticket> {
+    match event {
+        Mt4Event::OrderUpdate(update) => Some(update.order.ticket),
+        Mt4Event::TradeTimeout { request, .. } => Some(request.ticket).filter(|t| t.0 > 0),
+        Mt4Event::OrderStateChanged(transition) => Some(transition.ticket),
+        Mt4Event::StateDivergence(divergence) => Some(divergence.ticket()),
+        Mt4Event::BalanceOperation(operation) => Some(operation.ticket),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+    use crate::types::{AccountInfo, Quote};
+
+    fn update(ticket: i32, symbol: &str) -> OrderUpdate {
+        let order = Order::for_test(ticket, symbol, OrderType::Buy, 0.1, 1.08);
+        OrderUpdate { notify_id: ticket, notify_type: 0, df: 0.0, xh: 0.0, raw_size: 185, order, related_order: None }
+    }
+
+    #[test]
+    fn test_filter_events() {
+        let quote = Mt4Event::Quote(Quote { symbol: "EURUSD".to_string(), bid: 1.1, ask: 1.1, time: 0 });
+        let updates = Mt4Event::OrderUpdates(vec![update(1, "EURUSD"), update(2, "GBPUSD"), update(3, "EURUSD")]);
+        assert!(EventFilter::new().apply(&quote).is_some());
+
+        let trades = EventFilter::new().with_kinds(["OrderUpdates"]);
+        assert!(trades.apply(&quote).is_none());
+        assert!(trades.apply(&updates).is_some());
+
+        // 品种条件: 批量更新只保留匹配的订单，不携带品种的事件不匹配
+        let eurusd = EventFilter::new().with_symbols(["EURUSD"]);
+        match eurusd.apply(&updates) {
+            Some(Mt4Event::OrderUpdates(kept)) => {
+                assert_eq!(kept.iter().map(|u| u.order.ticket.0).collect::<Vec<_>>(), [1, 3])
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(eurusd.apply(&quote).is_some());
+        assert!(eurusd.apply(&Mt4Event::AccountInfo(AccountInfo::default())).is_none());
+
+        let ticket = EventFilter::new().with_tickets([Ticket(2)]).with_symbols(["EURUSD"]);
+        assert!(ticket.apply(&updates).is_none());
+        let snapshot = ticket.apply(&Mt4Event::PositionsSnapshot(vec![update(1, "EURUSD").order]));
+        assert!(matches!(snapshot, Some(Mt4Event::PositionsSnapshot(orders)) if orders.is_empty()));
+    }
+}
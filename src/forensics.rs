@@ -0,0 +1,170 @@
+//! 崩溃现场记录
+//!
+//! 在内存中保留最近 N 个事件和最近 M 个解密入站帧 (环形缓冲区)，进程 panic 时由
+//! panic hook 写入目录:
+//!
+//! - `events.jsonl`: 事件，`mt4.event.v1` JSON 结构 (见 `schema` 模块)
+//! - `frames.jsonl`: 入站帧，与会话录制格式相同，可用 `Mt4Client::replay_session()` 回放
+//! - `panic.txt`: panic 信息
+//!
+//! 下游策略代码崩溃时，可以据此还原崩溃前收到的准确事件序列。
+//! 通过 `Mt4Client::enable_crash_forensics()` 开启，也可以随时调用 `Mt4Client::dump_forensics()` 手动写出。
+
+use crate::error::{Mt4Error, Result};
+use crate::events::TimedEvent;
+use crate::schema::JsonSchemaAdapter;
+use crate::session::RecordedFrame;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::Instant;
+
+/// 客户端与 panic hook 共享的记录器
+pub(crate) type SharedForensics = Arc<Mutex<Option<CrashForensics>>>;
+
+/// 崩溃现场记录器
+#[derive(Debug)]
+pub struct CrashForensics {
+    dir: PathBuf,
+    max_events: usize,
+    max_frames: usize,
+    events: VecDeque<TimedEvent>,
+    frames: VecDeque<RecordedFrame>,
+    started: Instant,
+}
+
+impl CrashForensics {
+    /// 创建记录器，保留最近 `max_events` 个事件和 `max_frames` 个入站帧
+    pub fn new(dir: impl AsRef<Path>, max_events: usize, max_frames: usize) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            max_events,
+            max_frames,
+            events: VecDeque::with_capacity(max_events),
+            frames: VecDeque::with_capacity(max_frames),
+            started: Instant::now(),
+        }
+    }
+
+    /// 输出目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 记录事件
+    pub fn record_event(&mut self, event: &TimedEvent) {
+        push_bounded(&mut self.events, event.clone(), self.max_events);
+    }
+
+    /// 记录解密后的入站帧
+    pub fn record_frame(&mut self, command: u16, error_code: u8, data: &[u8]) {
+        let frame = RecordedFrame {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            command,
+            error_code,
+            data: data.to_vec(),
+        };
+        push_bounded(&mut self.frames, frame, self.max_frames);
+    }
+
+    /// 当前保留的 (事件数, 帧数)
+    pub fn len(&self) -> (usize, usize) {
+        (self.events.len(), self.frames.len())
+    }
+
+    /// 是否尚未记录任何内容
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty() && self.frames.is_empty()
+    }
+
+    /// 写出到输出目录 (覆盖上一次写出的文件)
+    pub fn dump(&self, panic_message: Option<&str>) -> Result<()> {
+        let io_err = |e: std::io::Error| Mt4Error::Io(format!("写入崩溃记录 {} 失败: {}", self.dir.display(), e));
+        fs::create_dir_all(&self.dir).map_err(io_err)?;
+
+        let mut events = String::new();
+        for event in &self.events {
+            events.push_str(&JsonSchemaAdapter.to_value(event).to_string());
+            events.push('\n');
+        }
+        fs::write(self.dir.join("events.jsonl"), events).map_err(io_err)?;
+
+        let mut frames = String::new();
+        for frame in &self.frames {
+            let line = serde_json::to_string(frame)
+                .map_err(|e| Mt4Error::Serialization(format!("序列化录制帧失败: {}", e)))?;
+            frames.push_str(&line);
+            frames.push('\n');
+        }
+        fs::write(self.dir.join("frames.jsonl"), frames).map_err(io_err)?;
+
+        if let Some(message) = panic_message {
+            let mut file = fs::File::create(self.dir.join("panic.txt")).map_err(io_err)?;
+            writeln!(file, "{}", message).map_err(io_err)?;
+        }
+        Ok(())
+    }
+}
+
+fn push_bounded<T>(buffer: &mut VecDeque<T>, item: T, capacity: usize) {
+    if capacity == 0 {
+        return;
+    }
+    if buffer.len() == capacity {
+        buffer.pop_front();
+    }
+    buffer.push_back(item);
+}
+
+/// 安装 panic hook: panic 时写出记录，然后交给之前的 hook
+pub(crate) fn install_panic_hook(shared: SharedForensics) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // 发生 panic 的线程可能正持有锁，不能阻塞等待
+        let guard = match shared.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        if let Some(forensics) = guard.as_ref().and_then(|g| g.as_ref()) {
+            match forensics.dump(Some(&info.to_string())) {
+                Ok(()) => eprintln!("MT4 crash forensics written to {}", forensics.dir().display()),
+                Err(e) => eprintln!("MT4 crash forensics failed: {}", e),
+            }
+        }
+        drop(guard);
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Mt4Event;
+    use crate::clock::EventTime;
+    use crate::session::read_session;
+
+    #[test]
+    fn test_bounded_dump() {
+        let dir = std::env::temp_dir().join(format!("mt4_forensics_{}", std::process::id()));
+        let mut forensics = CrashForensics::new(&dir, 2, 1);
+        for event in [Mt4Event::Connected, Mt4Event::Authenticated, Mt4Event::Pong] {
+            forensics.record_event(&TimedEvent { event, time: EventTime::now(None), seq: 0 });
+        }
+        forensics.record_frame(1, 0, &[]);
+        forensics.record_frame(51, 0, &[0xab]);
+        assert_eq!(forensics.len(), (2, 1));
+
+        forensics.dump(Some("boom")).unwrap();
+        let events = fs::read_to_string(dir.join("events.jsonl")).unwrap();
+        assert_eq!(events.lines().count(), 2);
+        assert!(events.lines().next().unwrap().contains("\"Authenticated\""));
+        let frames = read_session(dir.join("frames.jsonl")).unwrap();
+        assert_eq!((frames.len(), frames[0].command), (1, 51));
+        assert_eq!(fs::read_to_string(dir.join("panic.txt")).unwrap(), "boom\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,278 @@
+//! 出入金检测
+//!
+//! 订单更新 (Command 10) 携带变化后的账户余额 (`df`) 和信用额度 (`xh`)，账户信息 (Command 3)
+//! 携带余额。余额变化中不能由平仓 (盈亏 + 佣金 + 利息) 解释的部分判定为可能的入金或出金，
+//! 信用额度变化判定为信用增减，通过 `Mt4Event::Funding` 发出，供资金对账工具自动核对。
+//!
+//! 检测结果是推断: 同一批次中多笔平仓与出入金同时发生时只能得到净额。
+//! 每个连接使用新的检测器，断线期间的余额变化不会被误判为出入金。
+//!
+//! 服务器直接推送的余额/信用操作 (订单更新中 cmd 为 6 / 7 的记录) 不是订单，由 `split_order_updates`
+//! 拆分为 `BalanceOperation`，通过 `Mt4Event::BalanceOperation` 发出，不再作为 `OrderUpdate` 出现。
+
+use crate::types::{AccountInfo, OrderUpdate, Ticket};
+use serde::Serialize;
+
+/// 默认容差 (账户货币)，小于该值的差额视为舍入误差
+pub const DEFAULT_FUNDING_TOLERANCE: f64 = 0.01;
+
+/// 资金操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FundingKind {
+    /// 入金
+    Deposit,
+    /// 出金
+    Withdrawal,
+    /// 信用增加
+    CreditIn,
+    /// 信用减少
+    CreditOut,
+}
+
+/// 推断的资金操作
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FundingOperation {
+    /// 类型
+    pub kind: FundingKind,
+    /// 金额 (正数，账户货币)
+    pub amount: f64,
+    /// 变化后的余额
+    pub balance: f64,
+    /// 变化后的信用额度 (未知时为 None)
+    pub credit: Option<f64>,
+    /// 触发检测的订单更新通知 ID (来自账户信息时为 None)
+    pub notify_id: Option<i32>,
+}
+
+/// 余额操作的订单类型 (cmd)
+pub const CMD_BALANCE: i32 = 6;
+
+/// 信用操作的订单类型 (cmd)
+pub const CMD_CREDIT: i32 = 7;
+
+/// 服务器推送的余额/信用操作
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceOperation {
+    /// 操作单号
+    pub ticket: Ticket,
+    /// 类型
+    pub kind: FundingKind,
+    /// 金额 (正数，账户货币)
+    pub amount: f64,
+    /// 注释 (如 "Deposit")
+    pub comment: String,
+    /// 操作时间 (服务器时间，秒)
+    pub time: i64,
+    /// 操作后的余额 (通知未携带账户数据时为 None)
+    pub balance: Option<f64>,
+    /// 操作后的信用额度 (通知未携带账户数据时为 None)
+    pub credit: Option<f64>,
+    /// 通知 ID
+    pub notify_id: i32,
+}
+
+impl BalanceOperation {
+    /// 从订单更新记录 (185 字节) 解析，不是余额/信用操作时返回 None
+    pub fn from_bytes(data: &[u8], offset: usize) -> Option<Self> {
+        // 24-184 为订单数据，订单的 20-23 为 cmd
+        let cmd = i32::from_le_bytes(data.get(offset + 44..offset + 48)?.try_into().ok()?);
+        if cmd != CMD_BALANCE && cmd != CMD_CREDIT {
+            return None;
+        }
+        let update = OrderUpdate::from_bytes(data, offset)?;
+        let order = update.order;
+        let kind = match (cmd == CMD_CREDIT, order.profit >= 0.0) {
+            (false, true) => FundingKind::Deposit,
+            (false, false) => FundingKind::Withdrawal,
+            (true, true) => FundingKind::CreditIn,
+            (true, false) => FundingKind::CreditOut,
+        };
+        let carries_account = update.df != 0.0 || update.xh != 0.0;
+        Some(Self {
+            ticket: order.ticket,
+            kind,
+            amount: order.profit.abs(),
+            comment: order.comment,
+            time: if order.close_time > 0 { order.close_time } else { order.open_time },
+            balance: carries_account.then_some(update.df),
+            credit: carries_account.then_some(update.xh),
+            notify_id: update.notify_id,
+        })
+    }
+}
+
+/// 将订单更新数据 (Command 10) 拆分为订单更新和余额/信用操作
+pub fn split_order_updates(data: &[u8]) -> (Vec<OrderUpdate>, Vec<BalanceOperation>) {
+    let mut updates = Vec::new();
+    let mut operations = Vec::new();
+    for offset in (0..data.len() / 185).map(|i| i * 185) {
+        if let Some(operation) = BalanceOperation::from_bytes(data, offset) {
+            operations.push(operation);
+        } else if let Some(update) = OrderUpdate::from_bytes(data, offset) {
+            updates.push(update);
+        }
+    }
+    (updates, operations)
+}
+
+/// 出入金检测器
+#[derive(Debug, Clone)]
+pub struct FundingDetector {
+    balance: Option<f64>,
+    credit: Option<f64>,
+    tolerance: f64,
+}
+
+impl Default for FundingDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_FUNDING_TOLERANCE)
+    }
+}
+
+impl FundingDetector {
+    /// 创建检测器
+    pub fn new(tolerance: f64) -> Self {
+        Self { balance: None, credit: None, tolerance }
+    }
+
+    /// 当前已知余额
+    pub fn balance(&self) -> Option<f64> {
+        self.balance
+    }
+
+    /// 处理账户信息 (第一次只记录基准余额)
+    pub fn on_account(&mut self, account: &AccountInfo) -> Option<FundingOperation> {
+        let previous = self.balance.replace(account.balance)?;
+        self.classify_balance(account.balance - previous, account.balance, None)
+    }
+
+    /// 处理一批订单更新
+    pub fn on_updates(&mut self, updates: &[OrderUpdate]) -> Vec<FundingOperation> {
+        let mut operations = Vec::new();
+        for update in updates {
+            // 未填充余额和信用的通知不携带账户数据
+            if update.df == 0.0 && update.xh == 0.0 {
+                continue;
+            }
+            let realized = if update.is_close_notification() {
+                update.order.profit + update.order.commission + update.order.swap
+            } else {
+                0.0
+            };
+            if let Some(previous) = self.balance.replace(update.df) {
+                operations.extend(self.classify_balance(update.df - previous - realized, update.df, Some(update.notify_id)));
+            }
+            if let Some(previous) = self.credit.replace(update.xh) {
+                let delta = update.xh - previous;
+                if delta.abs() >= self.tolerance {
+                    operations.push(FundingOperation {
+                        kind: if delta > 0.0 { FundingKind::CreditIn } else { FundingKind::CreditOut },
+                        amount: round_cents(delta.abs()),
+                        balance: update.df,
+                        credit: Some(update.xh),
+                        notify_id: Some(update.notify_id),
+                    });
+                }
+            }
+        }
+        operations
+    }
+
+    /// 处理服务器推送的余额/信用操作: 更新基准余额和信用，之后的余额变化不再重复判定
+    pub fn on_balance_operation(&mut self, operation: &BalanceOperation) {
+        let (balance_delta, credit_delta) = match operation.kind {
+            FundingKind::Deposit => (operation.amount, 0.0),
+            FundingKind::Withdrawal => (-operation.amount, 0.0),
+            FundingKind::CreditIn => (0.0, operation.amount),
+            FundingKind::CreditOut => (0.0, -operation.amount),
+        };
+        self.balance = operation.balance.or(self.balance.map(|b| b + balance_delta));
+        self.credit = operation.credit.or(self.credit.map(|c| c + credit_delta));
+    }
+
+    fn classify_balance(&self, unexplained: f64, balance: f64, notify_id: Option<i32>) -> Option<FundingOperation> {
+        (unexplained.abs() >= self.tolerance).then(|| FundingOperation {
+            kind: if unexplained > 0.0 { FundingKind::Deposit } else { FundingKind::Withdrawal },
+            amount: round_cents(unexplained.abs()),
+            balance,
+            credit: self.credit,
+            notify_id,
+        })
+    }
+}
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+    use crate::types::{Order, Ticket};
+
+    fn update(notify_id: i32, notify_type: i32, profit: f64, df: f64, xh: f64) -> OrderUpdate {
+        let order =
+            Order { commission: -0.7, swap: -0.3, profit, ..Order::for_test(1001, "EURUSD", OrderType::Buy, 0.1, 1.08) };
+        OrderUpdate { notify_id, notify_type, df, xh, raw_size: 185, order, related_order: None }
+    }
+
+    #[test]
+    fn test_detect_funding() {
+        let mut detector = FundingDetector::default();
+        let mut account = AccountInfo { balance: 1000.0, ..Default::default() };
+        assert!(detector.on_account(&account).is_none());
+
+        // 平仓盈利 21 (扣除佣金和利息后 20)，余额变化可以解释
+        assert!(detector.on_updates(&[update(1, 0, 0.0, 1000.0, 0.0), update(2, 1, 21.0, 1020.0, 0.0)]).is_empty());
+
+        // 余额增加 500，没有平仓: 入金；同时信用增加 100
+        let ops = detector.on_updates(&[update(3, 3, 0.0, 1520.0, 100.0)]);
+        assert_eq!(ops.len(), 2);
+        assert_eq!((ops[0].kind, ops[0].amount, ops[0].notify_id), (FundingKind::Deposit, 500.0, Some(3)));
+        assert_eq!((ops[1].kind, ops[1].amount, ops[1].credit), (FundingKind::CreditIn, 100.0, Some(100.0)));
+
+        // 平仓亏损 10 的同时出金 200
+        let ops = detector.on_updates(&[update(4, 1, -9.0, 1310.0, 100.0)]);
+        assert_eq!((ops[0].kind, ops[0].amount), (FundingKind::Withdrawal, 200.0));
+
+        // 账户信息与已知余额不符
+        account.balance = 1300.0;
+        let op = detector.on_account(&account).unwrap();
+        assert_eq!((op.kind, op.amount, op.notify_id), (FundingKind::Withdrawal, 10.0, None));
+    }
+
+    #[test]
+    fn test_split_balance_operations() {
+        let record = |notify_id: i32, cmd: i32, profit: f64, (df, xh): (f64, f64), comment: &[u8]| {
+            let mut data = vec![0u8; 185];
+            data[..4].copy_from_slice(&notify_id.to_le_bytes());
+            data[4..8].copy_from_slice(&3i32.to_le_bytes());
+            data[8..16].copy_from_slice(&df.to_le_bytes());
+            data[16..24].copy_from_slice(&xh.to_le_bytes());
+            data[24..28].copy_from_slice(&(500 + notify_id).to_le_bytes());
+            data[44..48].copy_from_slice(&cmd.to_le_bytes());
+            data[52..56].copy_from_slice(&1_700_000_000i32.to_le_bytes());
+            data[125..133].copy_from_slice(&profit.to_le_bytes());
+            data[145..145 + comment.len()].copy_from_slice(comment);
+            data
+        };
+        let deposit = record(2, CMD_BALANCE, 500.0, (1500.0, 100.0), b"Deposit");
+        let data = [record(1, 1, 0.0, (1000.0, 100.0), b""), deposit].concat();
+        let (updates, operations) = split_order_updates(&data);
+        assert_eq!((updates.len(), updates[0].notify_id), (1, 1));
+        let op = &operations[0];
+        assert_eq!((op.ticket, op.kind, op.amount), (Ticket(502), FundingKind::Deposit, 500.0));
+        assert_eq!((op.comment.as_str(), op.time, op.balance), ("Deposit", 1_700_000_000, Some(1500.0)));
+
+        // 未携带账户数据的信用操作按金额调整基准，之后的余额变化不再判定为出入金
+        let credit = record(3, CMD_CREDIT, -50.0, (0.0, 0.0), b"Credit out");
+        let (_, credit) = split_order_updates(&credit);
+        assert_eq!((credit[0].kind, credit[0].balance), (FundingKind::CreditOut, None));
+        let mut detector = FundingDetector::default();
+        assert!(detector.on_updates(&[update(1, 0, 0.0, 1000.0, 100.0)]).is_empty());
+        detector.on_balance_operation(op);
+        detector.on_balance_operation(&credit[0]);
+        assert!(detector.on_updates(&[update(4, 2, 0.0, 1500.0, 50.0)]).is_empty());
+    }
+}
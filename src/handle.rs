@@ -0,0 +1,265 @@
+//! 可克隆的客户端句柄
+//!
+//! `Mt4Client::spawn()` 把客户端移入后台任务 (actor)，返回可廉价克隆的 `Mt4Handle`。
+//! 句柄把调用作为消息发给后台任务依次执行，一个任务下单的同时另一个任务可以消费事件，
+//! 无需自行包装 `Arc<Mutex<Mt4Client>>`:
+//!
+//! ```no_run
+//! use mt4_client::{LoginCredentials, Mt4Client};
+//!
+//! # async fn example(credentials: LoginCredentials) -> mt4_client::Result<()> {
+//! let handle = Mt4Client::new().spawn();
+//! handle.connect(&credentials).await?;
+//!
+//! let mut events = handle.subscribe();
+//! tokio::spawn(async move {
+//!     while let Some(event) = events.recv().await {
+//!         println!("{}", event.kind());
+//!     }
+//! });
+//!
+//! let trader = handle.clone();
+//! trader.buy("EURUSD", 0.01, None, None).await?;
+//! // 没有对应句柄方法的操作通过 call() 在后台任务中执行
+//! let book = handle.call(|client| Box::pin(async move { client.pending_book().await })).await?;
+//! # let _ = book;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! - 事件只能通过 `subscribe()` 等广播订阅获取，`next_event()` / `events()` 的事件通道在句柄模式下不使用
+//! - 等待交易响应的方法只在后台任务中发送请求，等待在调用方任务中进行，不阻塞其他调用
+//! - 所有句柄被丢弃后后台任务断开连接并退出
+
+use crate::client::{Mt4Client, RequestTracker};
+use crate::error::{Mt4Error, Result};
+use crate::events::{EventSubscription, TimedEvent};
+use crate::types::{AccountInfo, Order, Ticket, TradeRequest, TradeResponse};
+#[cfg(feature = "experimental-status")]
+use crate::types::ConnectionStatus;
+use crate::LoginCredentials;
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+/// 句柄到后台任务的消息通道容量
+const HANDLE_CHANNEL_SIZE: usize = 64;
+
+/// 在后台任务中执行的调用
+type Call = Box<dyn for<'a> FnOnce(&'a mut Mt4Client) -> BoxFuture<'a, ()> + Send>;
+
+/// 可克隆的客户端句柄
+#[derive(Clone)]
+pub struct Mt4Handle {
+    calls: mpsc::Sender<Call>,
+    broadcast: broadcast::Sender<TimedEvent>,
+    tracker: Arc<RequestTracker>,
+}
+
+impl std::fmt::Debug for Mt4Handle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mt4Handle").field("closed", &self.calls.is_closed()).finish()
+    }
+}
+
+impl Mt4Handle {
+    /// 启动后台任务
+    pub(crate) fn spawn(mut client: Mt4Client) -> Self {
+        let (calls, mut rx) = mpsc::channel::<Call>(HANDLE_CHANNEL_SIZE);
+        let handle = Self {
+            calls,
+            broadcast: client.broadcast_sender(),
+            tracker: client.request_tracker().clone(),
+        };
+        tokio::spawn(async move {
+            while let Some(call) = rx.recv().await {
+                call(&mut client).await;
+                // 没有人消费事件通道，丢弃接收端以免通道写满后阻塞读取任务
+                client.discard_event_receiver();
+            }
+            client.disconnect().await;
+        });
+        handle
+    }
+
+    /// 在后台任务中以 `&mut Mt4Client` 执行任意操作
+    ///
+    /// 后台任务已退出时返回 `Mt4Error::NotConnected`
+    pub async fn call<R, F>(&self, f: F) -> Result<R>
+    where
+        F: for<'a> FnOnce(&'a mut Mt4Client) -> BoxFuture<'a, R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let call: Call = Box::new(move |client| {
+            Box::pin(async move {
+                let _ = tx.send(f(client).await);
+            })
+        });
+        self.calls.send(call).await.map_err(|_| Mt4Error::NotConnected)?;
+        rx.await.map_err(|_| Mt4Error::NotConnected)
+    }
+
+    /// 连接到 MT4 服务器 (见 `Mt4Client::connect`)
+    pub async fn connect(&self, credentials: &LoginCredentials) -> Result<()> {
+        let credentials = credentials.clone();
+        self.call(move |client| Box::pin(async move { client.connect(&credentials).await })).await?
+    }
+
+    /// 断开连接 (后台任务继续运行，可再次连接)
+    pub async fn disconnect(&self) -> Result<()> {
+        self.call(|client| Box::pin(client.disconnect())).await
+    }
+
+    /// 是否已连接
+    pub async fn is_connected(&self) -> bool {
+        self.call(|client| Box::pin(async move { client.is_connected() })).await.unwrap_or(false)
+    }
+
+    /// 是否已认证
+    pub async fn is_authenticated(&self) -> bool {
+        self.call(|client| Box::pin(async move { client.is_authenticated() })).await.unwrap_or(false)
+    }
+
+    /// 订阅事件广播 (不经过后台任务)
+    pub fn subscribe(&self) -> EventSubscription {
+        EventSubscription::new(self.broadcast.subscribe())
+    }
+
+    /// 订阅事件广播，并先收到当前状态快照 (见 `Mt4Client::subscribe_with_snapshot`)
+    pub async fn subscribe_with_snapshot(&self) -> Result<EventSubscription> {
+        self.call(|client| Box::pin(client.subscribe_with_snapshot())).await
+    }
+
+    /// 订阅事件广播，并先回放序号大于 `seq` 的事件 (见 `Mt4Client::subscribe_from`)
+    pub async fn subscribe_from(&self, seq: u64) -> Result<EventSubscription> {
+        self.call(move |client| Box::pin(async move { client.subscribe_from(seq) })).await
+    }
+
+    /// 本地缓存的持仓
+    pub async fn positions(&self) -> Result<Vec<Order>> {
+        self.call(|client| Box::pin(client.positions())).await
+    }
+
+    /// 账户信息
+    pub async fn account_info(&self) -> Result<Option<AccountInfo>> {
+        self.call(|client| Box::pin(client.account_info())).await
+    }
+
+    /// 交易服务器连接状态
+    #[cfg(feature = "experimental-status")]
+    pub async fn connection_status(&self) -> Result<Option<ConnectionStatus>> {
+        self.call(|client| Box::pin(client.connection_status())).await
+    }
+
+    /// 发送交易请求，返回 (request_id, is_duplicate)
+    pub async fn send_trade(&self, request: TradeRequest) -> Result<(i32, bool)> {
+        self.call(move |client| Box::pin(async move { client.send_trade(request).await })).await?
+    }
+
+    /// 发送交易请求并等待服务器响应 (见 `Mt4Client::send_trade_and_wait`)
+    pub async fn send_trade_and_wait(&self, mut request: TradeRequest) -> Result<TradeResponse> {
+        let request_id = self.tracker.next_id();
+        request.request_id = request_id;
+        let ticket = request.ticket;
+
+        let rx = self.tracker.register_waiter(request_id).await;
+        let dispatched = self
+            .call(move |client| Box::pin(async move { client.dispatch_trade(request).await }))
+            .await
+            .and_then(|r| r);
+        Mt4Client::await_trade_response(&self.tracker, request_id, ticket, dispatched, rx).await
+    }
+
+    /// 市价买入
+    pub async fn buy(&self, symbol: &str, volume: f64, sl: Option<f64>, tp: Option<f64>) -> Result<()> {
+        let symbol = symbol.to_string();
+        self.call(move |client| Box::pin(async move { client.buy(&symbol, volume, sl, tp).await })).await?
+    }
+
+    /// 市价卖出
+    pub async fn sell(&self, symbol: &str, volume: f64, sl: Option<f64>, tp: Option<f64>) -> Result<()> {
+        let symbol = symbol.to_string();
+        self.call(move |client| Box::pin(async move { client.sell(&symbol, volume, sl, tp).await })).await?
+    }
+
+    /// 平仓
+    pub async fn close_order(&self, ticket: Ticket, symbol: &str, volume: f64) -> Result<()> {
+        let symbol = symbol.to_string();
+        self.call(move |client| Box::pin(async move { client.close_order(ticket, &symbol, volume).await }))
+            .await?
+    }
+
+    /// 修改订单的止损/止盈并等待响应
+    pub async fn modify_order(&self, ticket: Ticket, sl: f64, tp: f64) -> Result<TradeResponse> {
+        let order = self
+            .call(move |client| Box::pin(client.cached_order(ticket)))
+            .await?
+            .ok_or_else(|| Mt4Error::InvalidParams(format!("订单 #{} 不在本地缓存中", ticket)))?;
+        let request = TradeRequest::modify(ticket, &order.symbol, order.order_type, order.open_price, sl, tp);
+        self.send_trade_and_wait(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Mt4Event;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    #[tokio::test]
+    async fn test_handle_across_tasks() {
+        // 模拟桥接 EA: 认证后推送 100 个 Pong (超过事件通道容量)，记录收到的请求直到连接关闭
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let ea = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"{\"type\":\"hello\"}\n").await.unwrap();
+            lines.next_line().await.unwrap();
+            write.write_all(b"{\"type\":\"auth\"}\n").await.unwrap();
+            write.write_all("{\"type\":\"frame\",\"command\":51}\n".repeat(100).as_bytes()).await.unwrap();
+            let mut commands = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                commands.push(serde_json::from_str::<serde_json::Value>(&line).unwrap()["type"].to_string());
+            }
+            commands
+        });
+
+        let handle = Mt4Client::builder().auth_timeout(Duration::from_secs(5)).disable_heartbeat().event_channel_size(4).build().spawn();
+        let mut events = handle.subscribe();
+        let credentials = LoginCredentials {
+            login: "12345".to_string(),
+            password: "secret".into(),
+            server: "Broker-Demo".to_string(),
+        };
+        let addr_clone = addr.clone();
+        handle
+            .call(move |client| Box::pin(async move { client.connect_bridge(&addr_clone, &credentials).await }))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // 另一个任务持有句柄克隆并消费事件
+        let consumer = tokio::spawn(async move {
+            let mut pongs = 0;
+            while pongs < 100 {
+                if let Mt4Event::Pong = events.recv().await.unwrap() {
+                    pongs += 1;
+                }
+            }
+            pongs
+        });
+        let trader = handle.clone();
+        assert!(trader.is_authenticated().await);
+        assert!(trader.positions().await.unwrap().is_empty());
+        assert_eq!(tokio::time::timeout(Duration::from_secs(5), consumer).await.unwrap().unwrap(), 100);
+
+        // 丢弃所有句柄后后台任务注销并断开
+        drop((handle, trader));
+        let commands = tokio::time::timeout(Duration::from_secs(5), ea).await.unwrap().unwrap();
+        assert_eq!(commands, vec!["\"logout\""]);
+    }
+}
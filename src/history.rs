@@ -0,0 +1,118 @@
+//! 订单历史分页下载
+//!
+//! 一次 HistoryRequest (Command 6) 能返回的订单数受服务器限制，多年的账户历史无法一次取回。
+//! `HistoryDownload` 将时间区间按 `page_secs` 切分，由 `Mt4Client::download_order_history()`
+//! 逐页请求；某页返回的订单数达到 `page_limit` 时视为被截断，将该页区间对半拆分后重新请求。
+//! 各页结果按 ticket 去重 (后收到的覆盖先收到的)，按平仓时间升序返回。
+//!
+//! 请求数据与 Command 5 的时间范围相同 (8 字节: 开始时间、结束时间，i32 秒)，
+//! 响应为 161 字节 Order 结构数组 (无头部)。
+
+use crate::types::{Order, Ticket};
+use std::collections::HashMap;
+
+/// 默认每页时间跨度 (秒，30 天)
+pub const DEFAULT_HISTORY_PAGE_SECS: i64 = 30 * 24 * 3600;
+
+/// 默认单页订单上限 (达到时视为被截断)
+pub const DEFAULT_HISTORY_PAGE_LIMIT: usize = 1000;
+
+/// 单页请求的超时时间 (秒)
+pub const HISTORY_PAGE_TIMEOUT_SECS: u64 = 30;
+
+/// 订单历史分页下载参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryDownload {
+    /// 开始时间 (服务器时间，秒)
+    pub from: i64,
+    /// 结束时间 (服务器时间，秒)
+    pub to: i64,
+    /// 每页时间跨度 (秒)
+    pub page_secs: i64,
+    /// 单页订单上限
+    pub page_limit: usize,
+}
+
+impl HistoryDownload {
+    /// 创建下载参数 (每页 `DEFAULT_HISTORY_PAGE_SECS`，上限 `DEFAULT_HISTORY_PAGE_LIMIT`)
+    pub fn new(from: i64, to: i64) -> Self {
+        Self { from, to, page_secs: DEFAULT_HISTORY_PAGE_SECS, page_limit: DEFAULT_HISTORY_PAGE_LIMIT }
+    }
+
+    /// 设置每页时间跨度
+    pub fn with_page_secs(mut self, page_secs: i64) -> Self {
+        self.page_secs = page_secs.max(1);
+        self
+    }
+
+    /// 设置单页订单上限
+    pub fn with_page_limit(mut self, page_limit: usize) -> Self {
+        self.page_limit = page_limit.max(1);
+        self
+    }
+
+    /// 按时间切分的分页区间 (首尾相接，不重叠)
+    pub fn pages(&self) -> Vec<(i64, i64)> {
+        let mut pages = Vec::new();
+        let mut from = self.from;
+        while from < self.to {
+            let to = (from + self.page_secs.max(1)).min(self.to);
+            pages.push((from, to));
+            from = to;
+        }
+        pages
+    }
+}
+
+/// 编码时间范围请求 (开始时间、结束时间，超出 i32 的部分截断到边界)
+pub(crate) fn range_bytes(from: i64, to: i64) -> [u8; 8] {
+    let clamp = |time: i64| time.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+    let mut data = [0u8; 8];
+    data[..4].copy_from_slice(&clamp(from).to_le_bytes());
+    data[4..].copy_from_slice(&clamp(to).to_le_bytes());
+    data
+}
+
+/// 合并一页订单: 按 ticket 去重，保持平仓时间升序
+pub(crate) fn merge_orders(orders: &mut Vec<Order>, page: Vec<Order>) {
+    let mut index: HashMap<Ticket, usize> = orders.iter().enumerate().map(|(i, order)| (order.ticket, i)).collect();
+    for order in page {
+        match index.get(&order.ticket) {
+            Some(&i) => orders[i] = order,
+            None => {
+                index.insert(order.ticket, orders.len());
+                orders.push(order);
+            }
+        }
+    }
+    orders.sort_by_key(|order| (order.close_time, order.ticket));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+
+    fn order(ticket: i32, close_time: i64, profit: f64) -> Order {
+        Order { close_time, profit, ..Order::for_test(ticket, "EURUSD", OrderType::Buy, 0.1, 1.08) }
+    }
+
+    #[test]
+    fn test_pages_and_range() {
+        let pages = HistoryDownload::new(0, 250).with_page_secs(100).pages();
+        assert_eq!(pages, vec![(0, 100), (100, 200), (200, 250)]);
+        assert!(HistoryDownload::new(100, 100).pages().is_empty());
+
+        let data = range_bytes(1_704_067_200, i64::MAX);
+        assert_eq!(i32::from_le_bytes(data[..4].try_into().unwrap()), 1_704_067_200);
+        assert_eq!(i32::from_le_bytes(data[4..].try_into().unwrap()), i32::MAX);
+    }
+
+    #[test]
+    fn test_merge_orders_dedup() {
+        let mut orders = vec![order(1, 100, 1.0), order(2, 300, 2.0)];
+        merge_orders(&mut orders, vec![order(3, 200, 3.0), order(2, 300, 5.0)]);
+        let merged: Vec<(i32, f64)> = orders.iter().map(|o| (o.ticket.get(), o.profit)).collect();
+        assert_eq!(merged, vec![(1, 1.0), (3, 3.0), (2, 5.0)]);
+    }
+}
@@ -0,0 +1,294 @@
+//! 技术指标
+//!
+//! SMA / EMA / ATR / RSI 按K线增量计算，创建后不再分配内存:
+//!
+//! - `update()` 用收盘的K线推进状态 (`Mt4Event::CandleClosed` 或历史K线)
+//! - `peek()` 用未收盘的K线 (如 `Mt4Client::current_candle()`) 计算临时值，不改变状态，可以每个报价调用一次
+//! - `feed()` 依次处理一段历史K线，用于预热
+//!
+//! ```no_run
+//! use mt4_client::{Indicator, Rsi, Sma};
+//! # fn example(history: &[mt4_client::Candle], forming: &mt4_client::Candle) {
+//! let mut sma = Sma::new(20);
+//! let mut rsi = Rsi::new(14);
+//! sma.feed(history);
+//! rsi.feed(history);
+//! if let (Some(sma), Some(rsi)) = (sma.peek(forming), rsi.peek(forming)) {
+//!     println!("sma {:.5} rsi {:.1}", sma, rsi);
+//! }
+//! # }
+//! ```
+//!
+//! 数据不足一个周期时值为 None。EMA 以前 `period` 根K线的 SMA 为初值，ATR 和 RSI 使用 Wilder 平滑。
+
+use crate::types::Candle;
+use std::collections::VecDeque;
+
+/// 按K线增量计算的指标
+pub trait Indicator {
+    /// 用收盘的K线更新，返回新值
+    fn update(&mut self, candle: &Candle) -> Option<f64>;
+
+    /// 假设 `candle` 此刻收盘时的值 (不改变状态)
+    fn peek(&self, candle: &Candle) -> Option<f64>;
+
+    /// 当前值
+    fn value(&self) -> Option<f64>;
+
+    /// 依次用一段K线更新，返回最后的值
+    fn feed<'a>(&mut self, candles: impl IntoIterator<Item = &'a Candle>) -> Option<f64>
+    where
+        Self: Sized,
+    {
+        for candle in candles {
+            self.update(candle);
+        }
+        self.value()
+    }
+}
+
+/// 简单移动平均 (收盘价)
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Sma {
+    /// 创建指标 (周期至少为 1)
+    pub fn new(period: usize) -> Self {
+        let period = period.max(1);
+        Self { period, window: VecDeque::with_capacity(period), sum: 0.0 }
+    }
+}
+
+impl Indicator for Sma {
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        if self.window.len() == self.period {
+            self.sum -= self.window.pop_front().unwrap_or(0.0);
+        }
+        self.window.push_back(candle.close);
+        self.sum += candle.close;
+        self.value()
+    }
+
+    fn peek(&self, candle: &Candle) -> Option<f64> {
+        let len = self.window.len();
+        if len + 1 < self.period {
+            return None;
+        }
+        let dropped = if len == self.period { self.window[0] } else { 0.0 };
+        Some((self.sum - dropped + candle.close) / self.period as f64)
+    }
+
+    fn value(&self) -> Option<f64> {
+        (self.window.len() == self.period).then(|| self.sum / self.period as f64)
+    }
+}
+
+/// 指数移动平均 (收盘价)
+#[derive(Debug, Clone, Copy)]
+pub struct Ema {
+    period: usize,
+    alpha: f64,
+    seed_sum: f64,
+    seed_count: usize,
+    value: Option<f64>,
+}
+
+impl Ema {
+    /// 创建指标 (周期至少为 1，平滑系数 2 / (period + 1))
+    pub fn new(period: usize) -> Self {
+        let period = period.max(1);
+        Self { period, alpha: 2.0 / (period as f64 + 1.0), seed_sum: 0.0, seed_count: 0, value: None }
+    }
+
+    fn next(&self, close: f64) -> Self {
+        let mut next = *self;
+        match self.value {
+            Some(value) => next.value = Some(value + self.alpha * (close - value)),
+            None => {
+                next.seed_sum += close;
+                next.seed_count += 1;
+                if next.seed_count == self.period {
+                    next.value = Some(next.seed_sum / self.period as f64);
+                }
+            }
+        }
+        next
+    }
+}
+
+impl Indicator for Ema {
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        *self = self.next(candle.close);
+        self.value
+    }
+
+    fn peek(&self, candle: &Candle) -> Option<f64> {
+        self.next(candle.close).value
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+/// 平均真实波幅 (Wilder 平滑)
+#[derive(Debug, Clone, Copy)]
+pub struct Atr {
+    period: usize,
+    prev_close: Option<f64>,
+    seed_sum: f64,
+    seed_count: usize,
+    value: Option<f64>,
+}
+
+impl Atr {
+    /// 创建指标 (周期至少为 1)
+    pub fn new(period: usize) -> Self {
+        Self { period: period.max(1), prev_close: None, seed_sum: 0.0, seed_count: 0, value: None }
+    }
+
+    fn next(&self, candle: &Candle) -> Self {
+        let range = candle.high - candle.low;
+        let true_range = match self.prev_close {
+            Some(prev) => range.max((candle.high - prev).abs()).max((candle.low - prev).abs()),
+            None => range,
+        };
+        let n = self.period as f64;
+        let mut next = *self;
+        next.prev_close = Some(candle.close);
+        match self.value {
+            Some(value) => next.value = Some((value * (n - 1.0) + true_range) / n),
+            None => {
+                next.seed_sum += true_range;
+                next.seed_count += 1;
+                if next.seed_count == self.period {
+                    next.value = Some(next.seed_sum / n);
+                }
+            }
+        }
+        next
+    }
+}
+
+impl Indicator for Atr {
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        *self = self.next(candle);
+        self.value
+    }
+
+    fn peek(&self, candle: &Candle) -> Option<f64> {
+        self.next(candle).value
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+/// 相对强弱指数 (0-100，Wilder 平滑)
+#[derive(Debug, Clone, Copy)]
+pub struct Rsi {
+    period: usize,
+    prev_close: Option<f64>,
+    gain_sum: f64,
+    loss_sum: f64,
+    seed_count: usize,
+    /// (平均涨幅, 平均跌幅)
+    average: Option<(f64, f64)>,
+}
+
+impl Rsi {
+    /// 创建指标 (周期至少为 1，需要 period + 1 根K线)
+    pub fn new(period: usize) -> Self {
+        Self { period: period.max(1), prev_close: None, gain_sum: 0.0, loss_sum: 0.0, seed_count: 0, average: None }
+    }
+
+    fn next(&self, close: f64) -> Self {
+        let mut next = *self;
+        next.prev_close = Some(close);
+        let Some(prev) = self.prev_close else { return next };
+        let (gain, loss) = ((close - prev).max(0.0), (prev - close).max(0.0));
+        let n = self.period as f64;
+        match self.average {
+            Some((avg_gain, avg_loss)) => {
+                next.average = Some(((avg_gain * (n - 1.0) + gain) / n, (avg_loss * (n - 1.0) + loss) / n));
+            }
+            None => {
+                next.gain_sum += gain;
+                next.loss_sum += loss;
+                next.seed_count += 1;
+                if next.seed_count == self.period {
+                    next.average = Some((next.gain_sum / n, next.loss_sum / n));
+                }
+            }
+        }
+        next
+    }
+}
+
+impl Indicator for Rsi {
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        *self = self.next(candle.close);
+        self.value()
+    }
+
+    fn peek(&self, candle: &Candle) -> Option<f64> {
+        self.next(candle.close).value()
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.average.map(|(gain, loss)| {
+            if loss > 0.0 {
+                100.0 - 100.0 / (1.0 + gain / loss)
+            } else if gain > 0.0 {
+                100.0
+            } else {
+                50.0
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(close: f64, range: f64) -> Candle {
+        Candle { time: 0, open: close, high: close + range / 2.0, low: close - range / 2.0, close, volume: 1.0 }
+    }
+
+    #[test]
+    fn test_indicators() {
+        let candles: Vec<Candle> = [1.0, 2.0, 3.0, 4.0, 5.0].iter().map(|c| candle(*c, 0.5)).collect();
+
+        let mut sma = Sma::new(3);
+        assert_eq!(sma.update(&candles[0]), None);
+        assert_eq!(sma.peek(&candles[1]), None);
+        assert_eq!(sma.feed(&candles[1..]), Some(4.0));
+        // peek 与 update 结果一致且不改变状态
+        assert_eq!(sma.peek(&candle(9.0, 0.5)), Some(6.0));
+        assert_eq!(sma.value(), Some(4.0));
+
+        let mut ema = Ema::new(3);
+        assert_eq!(ema.feed(&candles[..3]), Some(2.0));
+        assert_eq!(ema.peek(&candles[3]), Some(3.0));
+        assert_eq!(ema.update(&candles[3]), Some(3.0));
+
+        // 连续上涨时 RSI 为 100，涨跌相同时为 50
+        let mut rsi = Rsi::new(4);
+        assert_eq!(rsi.feed(&candles[..4]), None);
+        assert_eq!(rsi.update(&candles[4]), Some(100.0));
+        let mut flat = Rsi::new(2);
+        assert_eq!(flat.feed(&[candle(1.0, 0.0), candle(2.0, 0.0), candle(1.0, 0.0)]), Some(50.0));
+
+        // 每根K线波幅 0.5、收盘价相差 1.0 时真实波幅为 1.25
+        let mut atr = Atr::new(2);
+        assert_eq!(atr.update(&candles[0]), None);
+        assert_eq!(atr.update(&candles[1]), Some((0.5 + 1.25) / 2.0));
+        assert_eq!(atr.peek(&candles[2]), Some(((0.5 + 1.25) / 2.0 + 1.25) / 2.0));
+    }
+}
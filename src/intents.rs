@@ -0,0 +1,239 @@
+//! 离线交易意图队列
+//!
+//! 断线期间提交的交易意图会持久化到磁盘，重新连接并认证后由 `Mt4Client::flush_intents()`
+//! 自动执行。执行前检查有效条件 (最大存活时间、价格区间)，条件不再满足的意图会被丢弃并发出
+//! `Mt4Event::IntentExpired` 事件；执行中连接再次断开的意图放回队列，等待下次认证。
+
+use crate::error::{Mt4Error, Result};
+use crate::types::TradeRequest;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 交易意图
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeIntent {
+    /// 意图ID (队列内唯一)
+    pub id: u64,
+    /// 待执行的交易请求
+    pub request: TradeRequest,
+    /// 创建时间 (Unix 时间戳，秒)
+    pub created_at: i64,
+    /// 最大存活时间 (秒)，超过后不再执行
+    pub max_age_secs: Option<u64>,
+    /// 执行价下限 (买单比较 ask，卖单比较 bid)
+    pub min_price: Option<f64>,
+    /// 执行价上限 (买单比较 ask，卖单比较 bid)
+    pub max_price: Option<f64>,
+}
+
+impl TradeIntent {
+    /// 创建交易意图 (无有效条件)
+    pub fn new(request: TradeRequest) -> Self {
+        Self {
+            id: 0,
+            request,
+            created_at: unix_now(),
+            max_age_secs: None,
+            min_price: None,
+            max_price: None,
+        }
+    }
+
+    /// 设置最大存活时间
+    pub fn with_max_age(mut self, secs: u64) -> Self {
+        self.max_age_secs = Some(secs);
+        self
+    }
+
+    /// 设置执行价区间
+    pub fn with_price_bounds(mut self, min_price: Option<f64>, max_price: Option<f64>) -> Self {
+        self.min_price = min_price;
+        self.max_price = max_price;
+        self
+    }
+
+    /// 是否设置了价格条件
+    pub fn has_price_bounds(&self) -> bool {
+        self.min_price.is_some() || self.max_price.is_some()
+    }
+
+    /// 检查存活时间，已过期时返回原因
+    pub fn check_age(&self, now: i64) -> Option<String> {
+        let max_age = self.max_age_secs? as i64;
+        let age = now - self.created_at;
+        if age > max_age {
+            Some(format!("已存活 {} 秒，超过最大 {} 秒", age, max_age))
+        } else {
+            None
+        }
+    }
+
+    /// 检查执行价是否在区间内，不满足时返回原因
+    ///
+    /// 买方向使用 ask，卖方向使用 bid
+    pub fn check_price(&self, bid: f64, ask: f64) -> Option<String> {
+        let price = if self.request.order_type.is_buy() { ask } else { bid };
+        if let Some(min) = self.min_price {
+            if price < min {
+                return Some(format!("当前价 {} 低于下限 {}", price, min));
+            }
+        }
+        if let Some(max) = self.max_price {
+            if price > max {
+                return Some(format!("当前价 {} 高于上限 {}", price, max));
+            }
+        }
+        None
+    }
+}
+
+/// 交易意图执行结果
+#[derive(Debug)]
+pub enum IntentOutcome {
+    /// 已写入离线队列，等待重连后执行
+    Queued(u64),
+    /// 已执行 (request_id)
+    Executed { intent_id: u64, request_id: i32 },
+    /// 有效条件不再满足，已丢弃
+    Expired { intent_id: u64, reason: String },
+    /// 执行失败
+    Failed { intent_id: u64, error: Mt4Error },
+}
+
+/// 持久化的交易意图队列
+#[derive(Debug)]
+pub struct IntentQueue {
+    path: PathBuf,
+    intents: Vec<TradeIntent>,
+    next_id: u64,
+}
+
+impl IntentQueue {
+    /// 打开队列文件 (不存在时创建空队列)
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let intents: Vec<TradeIntent> = if path.exists() {
+            let text = std::fs::read_to_string(&path).map_err(|e| {
+                Mt4Error::Io(format!("读取意图队列 {} 失败: {}", path.display(), e))
+            })?;
+            if text.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(&text).map_err(|e| {
+                    Mt4Error::Serialization(format!("解析意图队列 {} 失败: {}", path.display(), e))
+                })?
+            }
+        } else {
+            Vec::new()
+        };
+        let next_id = intents.iter().map(|i| i.id).max().unwrap_or(0) + 1;
+        Ok(Self { path, intents, next_id })
+    }
+
+    /// 加入队列并持久化，返回分配的意图ID
+    pub fn push(&mut self, mut intent: TradeIntent) -> Result<u64> {
+        intent.id = self.next_id;
+        self.next_id += 1;
+        let id = intent.id;
+        self.intents.push(intent);
+        self.save()?;
+        Ok(id)
+    }
+
+    /// 放回队首 (保留原意图ID，用于执行中断时回滚)
+    pub fn push_front(&mut self, intent: TradeIntent) -> Result<()> {
+        self.intents.insert(0, intent);
+        self.save()
+    }
+
+    /// 取出并移除队首意图
+    pub fn pop_front(&mut self) -> Result<Option<TradeIntent>> {
+        if self.intents.is_empty() {
+            return Ok(None);
+        }
+        let intent = self.intents.remove(0);
+        self.save()?;
+        Ok(Some(intent))
+    }
+
+    /// 队列中的意图
+    pub fn intents(&self) -> &[TradeIntent] {
+        &self.intents
+    }
+
+    /// 队列长度
+    pub fn len(&self) -> usize {
+        self.intents.len()
+    }
+
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.intents.is_empty()
+    }
+
+    /// 写入磁盘 (先写临时文件再重命名，避免写入中断导致文件损坏)
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.intents)
+            .map_err(|e| Mt4Error::Serialization(format!("序列化意图队列失败: {}", e)))?;
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, json)
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| {
+                Mt4Error::Io(format!("写入意图队列 {} 失败: {}", self.path.display(), e))
+            })
+    }
+}
+
+/// 当前 Unix 时间戳 (秒)
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Symbol;
+
+    #[test]
+    fn test_intent_conditions() {
+        let intent = TradeIntent::new(TradeRequest::buy(&Symbol::new("EURUSD").unwrap(), 0.01, 0.0, 0.0))
+            .with_max_age(60)
+            .with_price_bounds(Some(1.0800), Some(1.0900));
+
+        assert!(intent.check_age(intent.created_at + 30).is_none());
+        assert!(intent.check_age(intent.created_at + 61).is_some());
+
+        // 买单比较 ask
+        assert!(intent.check_price(1.0700, 1.0850).is_none());
+        assert!(intent.check_price(1.0850, 1.0950).is_some());
+
+        // 卖单比较 bid
+        let sell = TradeIntent::new(TradeRequest::sell(&Symbol::new("EURUSD").unwrap(), 0.01, 0.0, 0.0))
+            .with_price_bounds(Some(1.0800), None);
+        assert!(sell.check_price(1.0790, 1.0810).is_some());
+    }
+
+    #[test]
+    fn test_queue_persistence() {
+        let path = std::env::temp_dir().join(format!("mt4_intents_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut queue = IntentQueue::open(&path).unwrap();
+        let id = queue
+            .push(TradeIntent::new(TradeRequest::buy(&Symbol::new("EURUSD").unwrap(), 0.01, 0.0, 0.0)))
+            .unwrap();
+        drop(queue);
+
+        let mut queue = IntentQueue::open(&path).unwrap();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.intents()[0].id, id);
+        assert_eq!(queue.pop_front().unwrap().unwrap().request.symbol, "EURUSD");
+        assert!(IntentQueue::open(&path).unwrap().is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
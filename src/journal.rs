@@ -0,0 +1,390 @@
+//! SQLite 交易日志
+//!
+//! 开启 `sqlite` 特性后，`Mt4Client::enable_journal()` 把收到的每个订单更新 (Command 10)、
+//! 交易响应 (Command 12) 和账户信息 (Command 3) 写入内嵌 SQLite 数据库，之后可以按品种、时间和注释查询:
+//!
+//! ```no_run
+//! use mt4_client::{JournalQuery, Mt4Client, TradeJournal};
+//!
+//! # fn example(client: &Mt4Client) -> mt4_client::Result<()> {
+//! client.enable_journal("journal.db")?;
+//!
+//! // 另一个连接只读查询
+//! let journal = TradeJournal::open("journal.db")?;
+//! let trades = journal.trades(&JournalQuery::new().symbol("EURUSD").between(1_704_067_200, 1_706_745_600))?;
+//! println!("{} 笔已平仓交易", trades.len());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! 数据库包含三张表 (`recorded_at` 为写入时的 UTC 时间戳):
+//!
+//! | 表 | 内容 |
+//! |----|------|
+//! | `order_updates` | 订单更新，`order_json` 为完整订单 |
+//! | `trade_responses` | 交易响应及对应请求的类型、品种、订单号 |
+//! | `account_snapshots` | 账户信息 |
+//!
+//! MT4 Web 协议的订单不含 magic number，EA 通常把标识写在注释中，因此按注释前缀 (`JournalQuery::comment_prefix`) 筛选。
+
+use crate::error::{Mt4Error, Result};
+use crate::intents::unix_now;
+use crate::types::{AccountInfo, Order, OrderUpdate, Ticket, TradeRequest, TradeResponse};
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// 客户端与读取任务共享的交易日志
+pub(crate) type SharedJournal = Arc<Mutex<Option<TradeJournal>>>;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS order_updates (
+        id INTEGER PRIMARY KEY,
+        recorded_at INTEGER NOT NULL,
+        notify_id INTEGER NOT NULL,
+        notify_type INTEGER NOT NULL,
+        ticket INTEGER NOT NULL,
+        symbol TEXT NOT NULL,
+        open_time INTEGER NOT NULL,
+        close_time INTEGER NOT NULL,
+        comment TEXT NOT NULL,
+        df REAL NOT NULL,
+        xh REAL NOT NULL,
+        order_json TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS order_updates_ticket ON order_updates (ticket);
+    CREATE INDEX IF NOT EXISTS order_updates_symbol ON order_updates (symbol, close_time);
+    CREATE TABLE IF NOT EXISTS trade_responses (
+        id INTEGER PRIMARY KEY,
+        recorded_at INTEGER NOT NULL,
+        request_id INTEGER NOT NULL,
+        status INTEGER NOT NULL,
+        price1 REAL NOT NULL,
+        price2 REAL NOT NULL,
+        trade_type INTEGER,
+        symbol TEXT,
+        ticket INTEGER,
+        orders_json TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS account_snapshots (
+        id INTEGER PRIMARY KEY,
+        recorded_at INTEGER NOT NULL,
+        login INTEGER NOT NULL,
+        balance REAL NOT NULL,
+        equity REAL NOT NULL,
+        margin REAL,
+        free_margin REAL,
+        account_json TEXT NOT NULL
+    );
+";
+
+/// 交易查询条件 (未设置的条件不筛选)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JournalQuery {
+    /// 品种
+    pub symbol: Option<String>,
+    /// 平仓时间下限 (含，服务器时间)
+    pub from: Option<i64>,
+    /// 平仓时间上限 (不含，服务器时间)
+    pub to: Option<i64>,
+    /// 注释前缀
+    pub comment_prefix: Option<String>,
+}
+
+impl JournalQuery {
+    /// 不筛选
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按品种筛选
+    pub fn symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// 按平仓时间 `[from, to)` 筛选
+    pub fn between(mut self, from: i64, to: i64) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    /// 按注释前缀筛选
+    pub fn comment_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.comment_prefix = Some(prefix.into());
+        self
+    }
+}
+
+/// 交易日志
+#[derive(Debug)]
+pub struct TradeJournal {
+    conn: Connection,
+}
+
+impl TradeJournal {
+    /// 打开 (或创建) 数据库文件
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .map_err(|e| Mt4Error::Io(format!("打开交易日志 {} 失败: {}", path.display(), e)))?;
+        Self::init(conn)
+    }
+
+    /// 内存数据库 (测试或临时使用)
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory().map_err(db_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self { conn })
+    }
+
+    /// 记录订单更新
+    pub fn record_updates(&mut self, updates: &[OrderUpdate]) -> Result<()> {
+        let now = unix_now();
+        let tx = self.conn.transaction().map_err(db_error)?;
+        {
+            let mut insert = tx
+                .prepare_cached(
+                    "INSERT INTO order_updates (recorded_at, notify_id, notify_type, ticket, symbol, open_time, close_time, comment, df, xh, order_json)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                )
+                .map_err(db_error)?;
+            for update in updates {
+                let order = &update.order;
+                insert
+                    .execute(params![
+                        now,
+                        update.notify_id,
+                        update.notify_type,
+                        order.ticket.0,
+                        order.symbol.as_str(),
+                        order.open_time,
+                        order.close_time,
+                        order.comment,
+                        update.df,
+                        update.xh,
+                        to_json(order)?,
+                    ])
+                    .map_err(db_error)?;
+            }
+        }
+        tx.commit().map_err(db_error)
+    }
+
+    /// 记录交易响应 (`request` 为对应的请求，未匹配时为 None)
+    pub fn record_response(&mut self, response: &TradeResponse, request: Option<&TradeRequest>) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO trade_responses (recorded_at, request_id, status, price1, price2, trade_type, symbol, ticket, orders_json)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    unix_now(),
+                    response.request_id,
+                    response.status,
+                    response.price1,
+                    response.price2,
+                    request.map(|r| r.trade_type),
+                    request.map(|r| r.symbol.as_str()),
+                    request.filter(|r| r.ticket.is_set()).map(|r| r.ticket.0),
+                    to_json(&response.orders)?,
+                ],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// 记录账户信息
+    pub fn record_account(&mut self, account: &AccountInfo) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO account_snapshots (recorded_at, login, balance, equity, margin, free_margin, account_json)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    unix_now(),
+                    account.login,
+                    account.balance,
+                    account.equity,
+                    account.margin,
+                    account.free_margin,
+                    to_json(account)?,
+                ],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// 已平仓交易 (每个订单号取最后一次平仓更新，按平仓时间排序)
+    pub fn trades(&self, query: &JournalQuery) -> Result<Vec<Order>> {
+        let like = query.comment_prefix.as_ref().map(|p| format!("{}%", p.replace('%', "\\%").replace('_', "\\_")));
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT order_json FROM order_updates WHERE id IN (
+                     SELECT MAX(id) FROM order_updates WHERE notify_type = 1 GROUP BY ticket
+                 )
+                 AND (?1 IS NULL OR symbol = ?1)
+                 AND (?2 IS NULL OR close_time >= ?2)
+                 AND (?3 IS NULL OR close_time < ?3)
+                 AND (?4 IS NULL OR comment LIKE ?4 ESCAPE '\\')
+                 ORDER BY close_time, ticket",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![query.symbol, query.from, query.to, like], |row| row.get::<_, String>(0))
+            .map_err(db_error)?;
+        rows.map(|json| from_json(&json.map_err(db_error)?)).collect()
+    }
+
+    /// 订单的全部更新 (按记录顺序)
+    pub fn order_history(&self, ticket: Ticket) -> Result<Vec<OrderUpdate>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT notify_id, notify_type, df, xh, order_json FROM order_updates WHERE ticket = ?1 ORDER BY id")
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![ticket.0], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get::<_, String>(4)?))
+            })
+            .map_err(db_error)?;
+        rows.map(|row| {
+            let (notify_id, notify_type, df, xh, json) = row.map_err(db_error)?;
+            Ok(OrderUpdate { notify_id, notify_type, df, xh, raw_size: 185, order: from_json(&json)?, related_order: None })
+        })
+        .collect()
+    }
+
+    /// 写入时间在 `[from, to)` 内的账户信息 `(recorded_at, 账户)`
+    pub fn account_snapshots(&self, from: i64, to: i64) -> Result<Vec<(i64, AccountInfo)>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT recorded_at, account_json FROM account_snapshots WHERE recorded_at >= ?1 AND recorded_at < ?2 ORDER BY id")
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![from, to], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))
+            .map_err(db_error)?;
+        rows.map(|row| {
+            let (at, json) = row.map_err(db_error)?;
+            Ok((at, from_json(&json)?))
+        })
+        .collect()
+    }
+
+    /// 写入时间在 `[from, to)` 内的交易响应 `(recorded_at, 响应)`
+    pub fn trade_responses(&self, from: i64, to: i64) -> Result<Vec<(i64, TradeResponse)>> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT recorded_at, request_id, status, price1, price2, orders_json FROM trade_responses
+                 WHERE recorded_at >= ?1 AND recorded_at < ?2 ORDER BY id",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![from, to], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get::<_, String>(5)?))
+            })
+            .map_err(db_error)?;
+        rows.map(|row| {
+            let (at, request_id, status, price1, price2, json) = row.map_err(db_error)?;
+            Ok((at, TradeResponse { request_id, status, price1, price2, orders: from_json(&json)? }))
+        })
+        .collect()
+    }
+}
+
+/// 在共享日志上执行写入 (未开启时忽略，失败只记录日志)
+pub(crate) fn record(journal: &SharedJournal, write: impl FnOnce(&mut TradeJournal) -> Result<()>) {
+    if let Ok(mut journal) = journal.lock() {
+        if let Some(j) = journal.as_mut() {
+            if let Err(e) = write(j) {
+                tracing::warn!("Trade journal write failed: {}", e);
+            }
+        }
+    }
+}
+
+fn db_error(e: rusqlite::Error) -> Mt4Error {
+    Mt4Error::Io(format!("交易日志数据库错误: {}", e))
+}
+
+fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| Mt4Error::Serialization(format!("序列化交易日志记录失败: {}", e)))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(json: &str) -> Result<T> {
+    serde_json::from_str(json).map_err(|e| Mt4Error::Serialization(format!("交易日志记录格式错误: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+    use crate::types::Symbol;
+
+    fn update(ticket: i32, symbol: &str, notify_type: i32, close_time: i64, comment: &str) -> OrderUpdate {
+        OrderUpdate {
+            notify_id: ticket * 10 + notify_type,
+            notify_type,
+            df: 0.0,
+            xh: 0.0,
+            raw_size: 185,
+            order: Order {
+                close_time,
+                close_price: if close_time > 0 { 1.09 } else { 0.0 },
+                profit: if close_time > 0 { 100.0 } else { 0.0 },
+                comment: comment.to_string(),
+                ..Order::for_test(ticket, symbol, OrderType::Buy, 0.1, 1.08)
+            },
+            related_order: None,
+        }
+    }
+
+    #[test]
+    fn test_journal_queries() {
+        let mut journal = TradeJournal::in_memory().unwrap();
+        journal
+            .record_updates(&[
+                update(1, "EURUSD", 0, 0, "grid_1"),
+                update(2, "GBPUSD", 0, 0, "manual"),
+                update(3, "EURUSD", 0, 0, "manual"),
+            ])
+            .unwrap();
+        journal
+            .record_updates(&[
+                update(1, "EURUSD", 1, 1_700_003_600, "grid_1"),
+                update(2, "GBPUSD", 1, 1_700_007_200, "manual"),
+                update(3, "EURUSD", 1, 1_700_090_000, "manual"),
+            ])
+            .unwrap();
+
+        let tickets = |query: JournalQuery| -> Vec<i32> {
+            journal.trades(&query).unwrap().iter().map(|o| o.ticket.0).collect()
+        };
+        assert_eq!(tickets(JournalQuery::new()), [1, 2, 3]);
+        assert_eq!(tickets(JournalQuery::new().symbol("EURUSD")), [1, 3]);
+        assert_eq!(tickets(JournalQuery::new().between(1_700_000_000, 1_700_086_400)), [1, 2]);
+        assert_eq!(tickets(JournalQuery::new().comment_prefix("grid")), [1]);
+        // "_" 按字面匹配
+        assert!(tickets(JournalQuery::new().comment_prefix("grid_2")).is_empty());
+
+        let history = journal.order_history(Ticket(1)).unwrap();
+        assert_eq!(history.iter().map(|u| u.notify_type).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(history[1].order.profit, 100.0);
+
+        let account = AccountInfo { login: 7, balance: 1_000.0, equity: 990.0, ..AccountInfo::default() };
+        journal.record_account(&account).unwrap();
+        let symbol = Symbol::new("EURUSD").unwrap();
+        let request = TradeRequest::buy(&symbol, 0.1, 0.0, 0.0);
+        let response = TradeResponse { request_id: 1001, status: 0, price1: 1.08, price2: 1.0802, orders: vec![] };
+        journal.record_response(&response, Some(&request)).unwrap();
+
+        let now = unix_now();
+        let snapshots = journal.account_snapshots(now - 60, now + 60).unwrap();
+        assert_eq!(snapshots[0].1.equity, 990.0);
+        let responses = journal.trade_responses(now - 60, now + 60).unwrap();
+        assert_eq!(responses[0].1.request_id, 1001);
+    }
+}
@@ -0,0 +1,386 @@
+//! Kafka 事件输出 (需要开启 `kafka` feature)
+//!
+//! `KafkaSink` 订阅客户端句柄的事件广播，把账户、订单、报价和交易事件序列化为 `mt4.event.v1` JSON
+//! (见 `schema` 模块) 写入 Kafka 主题，供数据湖等下游消费:
+//!
+//! - 消息键可选品种 (同一品种的事件进入同一分区) 或账号 (取最近一次 `AccountInfo` 中的 login)，
+//!   没有对应字段的事件不带键
+//! - `OrderUpdates` 按订单拆分为多条 `OrderUpdate` 消息，保证按品种分区时每条消息只有一个品种
+//! - 批量发送由 librdkafka 完成，`with_linger` / `with_batch_size` 对应 `linger.ms` / `batch.num.messages`
+//! - 投递结果在后台确认，失败的消息计入 `stats()` 并通过 `take_failures()` 的接收端上报
+//!
+//! ```no_run
+//! use mt4_client::kafka::{KafkaKey, KafkaSink, KafkaSinkConfig};
+//! use mt4_client::Mt4Client;
+//! use std::time::Duration;
+//!
+//! # async fn example() -> mt4_client::Result<()> {
+//! let handle = Mt4Client::new().spawn();
+//! let config = KafkaSinkConfig::new("kafka-1:9092,kafka-2:9092", "mt4.flow")
+//!     .with_key(KafkaKey::Symbol)
+//!     .with_linger(Duration::from_millis(20))
+//!     .with_property("compression.type", "lz4");
+//! let mut sink = KafkaSink::start(&config, handle)?;
+//! let mut failures = sink.take_failures().unwrap();
+//! while let Some(failure) = failures.recv().await {
+//!     eprintln!("{} ({:?}) not delivered: {}", failure.kind, failure.key, failure.error);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::Mt4Event;
+use crate::error::{Mt4Error, Result};
+use crate::events::{EventSubscription, TimedEvent};
+use crate::handle::Mt4Handle;
+use crate::schema::JsonSchemaAdapter;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{DeliveryFuture, FutureProducer, FutureRecord, Producer};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// 投递失败上报通道的容量 (接收端未取走或处理不及时时丢弃)
+const FAILURE_CHANNEL_SIZE: usize = 1024;
+
+/// 消息键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KafkaKey {
+    /// 不带键 (按 librdkafka 默认策略分区)
+    #[default]
+    None,
+    /// 品种
+    Symbol,
+    /// 账号
+    Account,
+}
+
+/// Kafka 输出配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct KafkaSinkConfig {
+    /// `bootstrap.servers`
+    pub brokers: String,
+    /// 主题
+    pub topic: String,
+    /// 消息键
+    pub key: KafkaKey,
+    /// 批量等待时间 (`linger.ms`)
+    pub linger: Duration,
+    /// 每批最多消息数 (`batch.num.messages`)
+    pub batch_size: usize,
+    /// 其他 librdkafka 配置 (如 `compression.type`、`security.protocol`)
+    pub properties: Vec<(String, String)>,
+}
+
+impl KafkaSinkConfig {
+    /// 以 broker 列表和主题创建 (不带键，等待 5ms 凑批，每批最多 10000 条)
+    pub fn new(brokers: impl Into<String>, topic: impl Into<String>) -> Self {
+        Self {
+            brokers: brokers.into(),
+            topic: topic.into(),
+            key: KafkaKey::None,
+            linger: Duration::from_millis(5),
+            batch_size: 10_000,
+            properties: Vec::new(),
+        }
+    }
+
+    /// 设置消息键
+    pub fn with_key(mut self, key: KafkaKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// 设置批量等待时间
+    pub fn with_linger(mut self, linger: Duration) -> Self {
+        self.linger = linger;
+        self
+    }
+
+    /// 设置每批最多消息数
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// 设置其他 librdkafka 配置
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.properties.push((key.into(), value.into()));
+        self
+    }
+
+    fn client_config(&self) -> ClientConfig {
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", &self.brokers)
+            .set("linger.ms", self.linger.as_millis().to_string())
+            .set("batch.num.messages", self.batch_size.to_string());
+        for (key, value) in &self.properties {
+            config.set(key, value);
+        }
+        config
+    }
+}
+
+/// 投递统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KafkaSinkStats {
+    /// 已交给 producer 的消息数
+    pub produced: u64,
+    /// 已确认投递的消息数
+    pub delivered: u64,
+    /// 投递失败的消息数 (包括本地队列已满)
+    pub failed: u64,
+}
+
+/// 投递失败的消息
+#[derive(Debug, Clone)]
+pub struct KafkaDeliveryFailure {
+    /// 事件类型
+    pub kind: &'static str,
+    /// 消息键
+    pub key: Option<String>,
+    /// 错误信息
+    pub error: String,
+}
+
+#[derive(Default)]
+struct Counters {
+    produced: AtomicU64,
+    delivered: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// 失败计数并上报
+#[derive(Clone)]
+struct FailureReporter {
+    counters: Arc<Counters>,
+    tx: mpsc::Sender<KafkaDeliveryFailure>,
+}
+
+impl FailureReporter {
+    fn report(&self, failure: KafkaDeliveryFailure) {
+        self.counters.failed.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!("Kafka delivery of {} failed: {}", failure.kind, failure.error);
+        let _ = self.tx.try_send(failure);
+    }
+}
+
+/// 等待确认的消息
+struct Pending {
+    kind: &'static str,
+    key: Option<String>,
+    delivery: DeliveryFuture,
+}
+
+/// Kafka 事件输出，丢弃时停止 (未确认的消息可先调用 `flush`)
+pub struct KafkaSink {
+    producer: FutureProducer,
+    counters: Arc<Counters>,
+    failures: Option<mpsc::Receiver<KafkaDeliveryFailure>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl KafkaSink {
+    /// 创建 producer 并启动事件输出任务
+    ///
+    /// librdkafka 在后台连接 broker，broker 不可用时消息在本地排队，超过 `message.timeout.ms` 后投递失败
+    pub fn start(config: &KafkaSinkConfig, handle: Mt4Handle) -> Result<Self> {
+        let producer: FutureProducer = config
+            .client_config()
+            .create()
+            .map_err(|e| Mt4Error::InvalidParams(format!("创建 Kafka producer 失败: {}", e)))?;
+        let counters = Arc::new(Counters::default());
+        let (failure_tx, failures) = mpsc::channel(FAILURE_CHANNEL_SIZE);
+        let reporter = FailureReporter { counters: counters.clone(), tx: failure_tx };
+        let (pending_tx, pending_rx) = mpsc::unbounded_channel();
+
+        let tasks = vec![
+            tokio::spawn(produce_events(
+                producer.clone(),
+                handle.subscribe(),
+                config.clone(),
+                pending_tx,
+                reporter.clone(),
+            )),
+            tokio::spawn(confirm_deliveries(pending_rx, reporter)),
+        ];
+        tracing::info!("Kafka sink started: {} -> {}", config.brokers, config.topic);
+
+        Ok(Self { producer, counters, failures: Some(failures), tasks })
+    }
+
+    /// 投递统计
+    pub fn stats(&self) -> KafkaSinkStats {
+        KafkaSinkStats {
+            produced: self.counters.produced.load(Ordering::Relaxed),
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 取走投递失败的接收端 (只能取一次)
+    pub fn take_failures(&mut self) -> Option<mpsc::Receiver<KafkaDeliveryFailure>> {
+        self.failures.take()
+    }
+
+    /// 等待已交给 producer 的消息投递完成
+    pub async fn flush(&self, timeout: Duration) -> Result<()> {
+        let producer = self.producer.clone();
+        tokio::task::spawn_blocking(move || producer.flush(timeout))
+            .await
+            .map_err(|e| Mt4Error::InvalidParams(format!("Kafka flush 失败: {}", e)))?
+            .map_err(|e| {
+                tracing::warn!("Kafka flush failed: {}", e);
+                Mt4Error::Timeout
+            })
+    }
+
+    /// 停止输出
+    pub fn shutdown(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for KafkaSink {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// 把事件交给 producer，直到客户端被丢弃
+async fn produce_events(
+    producer: FutureProducer,
+    mut events: EventSubscription,
+    config: KafkaSinkConfig,
+    pending: mpsc::UnboundedSender<Pending>,
+    reporter: FailureReporter,
+) {
+    let adapter = JsonSchemaAdapter;
+    let mut account = None;
+    while let Some(event) = events.recv_timed().await {
+        if let Mt4Event::AccountInfo(info) = &event.event {
+            account = Some(info.login);
+        }
+        for message in messages(event) {
+            let kind = message.event.kind();
+            let key = message_key(&message.event, config.key, account);
+            let payload = adapter.to_value(&message).to_string();
+            let mut record = FutureRecord::to(&config.topic).payload(&payload);
+            if let Some(key) = &key {
+                record = record.key(key);
+            }
+            match producer.send_result(record) {
+                Ok(delivery) => {
+                    reporter.counters.produced.fetch_add(1, Ordering::Relaxed);
+                    let _ = pending.send(Pending { kind, key, delivery });
+                }
+                Err((e, _)) => reporter.report(KafkaDeliveryFailure { kind, key, error: e.to_string() }),
+            }
+        }
+    }
+}
+
+/// 依次等待投递结果
+async fn confirm_deliveries(mut pending: mpsc::UnboundedReceiver<Pending>, reporter: FailureReporter) {
+    while let Some(Pending { kind, key, delivery }) = pending.recv().await {
+        let error = match delivery.await {
+            Ok(Ok(_)) => {
+                reporter.counters.delivered.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            Ok(Err((e, _))) => e.to_string(),
+            Err(_) => "producer 已关闭".to_string(),
+        };
+        reporter.report(KafkaDeliveryFailure { kind, key, error });
+    }
+}
+
+/// 需要输出的事件 (`OrderUpdates` 拆分为单条 `OrderUpdate`)
+fn messages(event: TimedEvent) -> Vec<TimedEvent> {
+    match event.event {
+        Mt4Event::OrderUpdates(updates) => updates
+            .into_iter()
+            .map(|update| TimedEvent { event: Mt4Event::OrderUpdate(update), time: event.time, seq: event.seq })
+            .collect(),
+        Mt4Event::AccountInfo(_)
+        | Mt4Event::OrderUpdate(_)
+        | Mt4Event::PositionsSnapshot(_)
+        | Mt4Event::Quote(_)
+        | Mt4Event::TradeSuccess { .. }
+        | Mt4Event::TradeFailed { .. }
+        | Mt4Event::TradeTimeout { .. }
+        | Mt4Event::Requote { .. }
+        | Mt4Event::RiskRejected { .. }
+        | Mt4Event::OrderStateChanged(_)
+        | Mt4Event::Funding(_) => vec![event],
+        _ => Vec::new(),
+    }
+}
+
+/// 消息键
+fn message_key(event: &Mt4Event, key: KafkaKey, account: Option<i32>) -> Option<String> {
+    match key {
+        KafkaKey::None => None,
+        KafkaKey::Account => account.map(|login| login.to_string()),
+        KafkaKey::Symbol => match event {
+            Mt4Event::OrderUpdate(update) => Some(update.order.symbol.to_string()),
+            Mt4Event::Quote(quote) => Some(quote.symbol.clone()),
+            Mt4Event::TradeTimeout { request, .. } | Mt4Event::RiskRejected { request, .. } => {
+                Some(request.symbol.to_string())
+            }
+            Mt4Event::Requote { symbol, .. } => Some(symbol.to_string()),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::EventTime;
+    use crate::protocol::OrderType;
+    use crate::types::{Order, OrderUpdate, Quote};
+
+    fn update(ticket: i32, symbol: &str) -> OrderUpdate {
+        let order = Order::for_test(ticket, symbol, OrderType::Buy, 0.1, 1.08);
+        OrderUpdate { notify_id: ticket, notify_type: 0, df: 0.0, xh: 0.0, raw_size: 0, order, related_order: None }
+    }
+
+    #[test]
+    fn test_messages_and_keys() {
+        let batch = TimedEvent {
+            event: Mt4Event::OrderUpdates(vec![update(1, "EURUSD"), update(2, "XAUUSD")]),
+            time: EventTime::now(None),
+            seq: 0,
+        };
+        let split = messages(batch);
+        assert_eq!(split.len(), 2);
+        assert!(split.iter().all(|m| m.event.kind() == "OrderUpdate"));
+
+        let keys: Vec<_> = split.iter().map(|m| message_key(&m.event, KafkaKey::Symbol, Some(7))).collect();
+        assert_eq!(keys, vec![Some("EURUSD".to_string()), Some("XAUUSD".to_string())]);
+        assert_eq!(message_key(&split[0].event, KafkaKey::Account, Some(7)), Some("7".to_string()));
+        assert_eq!(message_key(&split[0].event, KafkaKey::Account, None), None);
+        assert_eq!(message_key(&split[0].event, KafkaKey::None, Some(7)), None);
+
+        let quote = Mt4Event::Quote(Quote { symbol: "GBPUSD".to_string(), bid: 1.27, ask: 1.2702, time: 0 });
+        assert_eq!(message_key(&quote, KafkaKey::Symbol, None), Some("GBPUSD".to_string()));
+
+        let pong = TimedEvent { event: Mt4Event::Pong, time: EventTime::now(None), seq: 0 };
+        assert!(messages(pong).is_empty());
+
+        let config = KafkaSinkConfig::new("localhost:9092", "mt4.flow")
+            .with_linger(Duration::from_millis(20))
+            .with_batch_size(500)
+            .with_property("compression.type", "none");
+        let client_config = config.client_config();
+        assert_eq!(client_config.get("linger.ms"), Some("20"));
+        assert_eq!(client_config.get("batch.num.messages"), Some("500"));
+        assert_eq!(client_config.get("compression.type"), Some("none"));
+    }
+}
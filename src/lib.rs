@@ -8,6 +8,7 @@
 //! - AES-256-CBC 加密/解密
 //! - 交易操作（下单、平仓、修改订单）
 //! - 实时报价和订单更新
+//! - 浏览器 (wasm32) 中直接连接 Web Terminal (`wasm` 模块)
 //!
 //! # 示例
 //! ```no_run
@@ -17,7 +18,7 @@
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     let credentials = LoginCredentials {
 //!         login: "31313724".to_string(),
-//!         password: "password".to_string(),
+//!         password: "password".into(),
 //!         server: "ICMarketsSC-Demo03".to_string(),
 //!     };
 //!
@@ -32,22 +33,222 @@
 //! ```
 
 pub mod api;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod backfill;
+pub mod book;
+pub mod breakeven;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bridge;
+pub mod budget;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod capture;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod callbacks;
+#[cfg(all(feature = "experimental-chart", not(target_arch = "wasm32")))]
+pub mod candle_cache;
+pub mod candles;
+#[cfg(feature = "experimental-chart")]
+pub mod chart;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod client;
+pub mod clock;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
 pub mod crypto;
+#[cfg(feature = "chrono")]
+pub mod datetime;
+#[cfg(feature = "decimal")]
+pub mod decimal;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod decoders;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod decrypt;
 pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod events;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod filter;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod forensics;
+pub mod funding;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod handle;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod history;
+pub mod indicators;
+pub mod intents;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub mod journal;
+#[cfg(all(feature = "kafka", not(target_arch = "wasm32")))]
+pub mod kafka;
+pub mod lifecycle;
+pub mod margin;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mirror;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ndjson;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod packet;
+pub mod packet_stats;
+pub mod pips;
+pub mod positions;
+pub mod presets;
 pub mod protocol;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod proxy;
+pub mod quirks;
+pub mod reconcile;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod recorder;
+#[cfg(all(feature = "redis", not(target_arch = "wasm32")))]
+pub mod redis_bridge;
+pub mod requote;
+pub mod risk;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod schema;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod selftest;
+pub mod sequence;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod server;
+pub mod session;
+pub mod sizing;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod snapshot;
+pub mod spread;
+pub mod stats;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod strategy;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod synthetic;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tap;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod telemetry;
+pub mod throttle;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tls;
+pub mod trailing;
+#[cfg(all(feature = "status-page", not(target_arch = "wasm32")))]
+pub mod status;
 pub mod types;
+pub mod validation;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
 
-pub use api::Mt4Api;
-pub use client::{Mt4Client, Mt4Event, PendingRequest, RequestTracker};
-pub use error::{Mt4Error, Result};
-pub use protocol::{Command, OrderType, TradeType};
+pub use api::{GatewayProbe, Mt4Api};
+#[cfg(not(target_arch = "wasm32"))]
+pub use backfill::{Backfill, BackfillProgress};
+pub use book::{pip_size, PendingBook};
+pub use breakeven::{net_breakeven, position_breakeven, Breakeven};
+#[cfg(not(target_arch = "wasm32"))]
+pub use bridge::{BridgeCommand, BridgeMessage, BRIDGE_PROTOCOL_VERSION, DEFAULT_BRIDGE_ADDR};
+pub use budget::WorkBudget;
+#[cfg(not(target_arch = "wasm32"))]
+pub use capture::{read_capture, CaptureDirection, CapturedPacket, PacketCapture};
+#[cfg(not(target_arch = "wasm32"))]
+pub use callbacks::CallbackId;
+#[cfg(all(feature = "experimental-chart", not(target_arch = "wasm32")))]
+pub use candle_cache::{CachedCandles, CandleCache};
+pub use candles::{CandleAggregator, CandleClosed};
+#[cfg(feature = "experimental-chart")]
+pub use chart::{CandleDownload, ChartDownload, ChartProgress};
+#[cfg(not(target_arch = "wasm32"))]
+pub use client::{
+    CancelFailure, CancelSummary, CloseAllSummary, CloseFailure, ModifyAction, ModifyFailure, ModifySummary,
+    Mt4Client, Mt4Event, PendingRequest, RequestTracker,
+};
+pub use clock::{DriftEstimator, EventTime};
+#[cfg(not(target_arch = "wasm32"))]
+pub use config::{ClientConfig, Mt4ClientBuilder};
+pub use crypto::{CryptoProvider, Mt4Crypto};
+#[cfg(not(target_arch = "wasm32"))]
+pub use decoders::{CustomEvent, CustomValue, DecoderRegistry};
+pub use error::{ErrorKind, Mt4Error, Result};
+#[cfg(not(target_arch = "wasm32"))]
+pub use events::{EventStream, EventSubscription, QuoteReceiver, TimedEvent, TimedEventStream};
+#[cfg(not(target_arch = "wasm32"))]
+pub use filter::EventFilter;
+#[cfg(not(target_arch = "wasm32"))]
+pub use forensics::CrashForensics;
+pub use funding::{split_order_updates, BalanceOperation, FundingDetector, FundingKind, FundingOperation};
+#[cfg(not(target_arch = "wasm32"))]
+pub use handle::Mt4Handle;
+#[cfg(not(target_arch = "wasm32"))]
+pub use history::HistoryDownload;
+pub use indicators::{Atr, Ema, Indicator, Rsi, Sma};
+pub use intents::{IntentOutcome, IntentQueue, TradeIntent};
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub use journal::{JournalQuery, TradeJournal};
+#[cfg(all(feature = "kafka", not(target_arch = "wasm32")))]
+pub use kafka::{KafkaDeliveryFailure, KafkaKey, KafkaSink, KafkaSinkConfig, KafkaSinkStats};
+pub use lifecycle::{OrderLifecycle, OrderState, OrderTransition};
+pub use margin::{margin_level, required_margin, used_margin, MarginAlert, MarginCheck, MarginMonitor, MarginZone};
+#[cfg(not(target_arch = "wasm32"))]
+pub use mirror::Mt4Mirror;
+#[cfg(not(target_arch = "wasm32"))]
+pub use ndjson::NdjsonSink;
+pub use packet_stats::{CommandStats, PacketStats};
+pub use pips::{currency_pair, pips_to_price, point_value_in_account_currency, price_to_pips, CrossRates};
+pub use positions::PositionManager;
+pub use presets::{AccountMode, BrokerPreset, PresetRegistry, WeeklySession};
+pub use protocol::{Command, OrderType, Timeframe, TradeType};
+#[cfg(not(target_arch = "wasm32"))]
+pub use proxy::{ProxyConfig, ProxyScheme};
+pub use quirks::{AccountCalibration, AccountLayout, BrokerQuirks, QuirkRegistry};
+pub use reconcile::{diff_orders, StateDivergence};
+#[cfg(not(target_arch = "wasm32"))]
+pub use recorder::{TickFormat, TickRecorder, TickRotation};
+#[cfg(all(feature = "redis", not(target_arch = "wasm32")))]
+pub use redis_bridge::{RedisBridge, RedisBridgeConfig};
+pub use requote::RequotePolicy;
+pub use risk::{RiskLimits, RiskManager, RiskRule};
+#[cfg(not(target_arch = "wasm32"))]
+pub use schema::{EventAdapter, ExecutionReport, FixAdapter, JsonSchemaAdapter};
+#[cfg(not(target_arch = "wasm32"))]
+pub use selftest::{SelfTestCheck, SelfTestReport};
+pub use sequence::{MissedUpdates, NotifySequence};
+pub use session::{read_session, RecordedFrame, SessionRecorder};
+pub use sizing::position_size;
+#[cfg(not(target_arch = "wasm32"))]
+pub use snapshot::ClientSnapshot;
+pub use spread::{SpreadAlert, SpreadMonitor};
+pub use stats::{TradeStatistics, TradeStats, TradeStatsReport};
+#[cfg(not(target_arch = "wasm32"))]
+pub use strategy::{Strategy, StrategyContext, StrategyRunner};
+#[cfg(not(target_arch = "wasm32"))]
+pub use synthetic::QuoteGenerator;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tap::{RawFrame, RawMessageReceiver};
+pub use throttle::{RateBudget, TradeThrottle};
+#[cfg(not(target_arch = "wasm32"))]
+pub use tls::TlsConfig;
+pub use trailing::{TrailUpdate, TrailingEngine, TrailingStop};
 pub use types::*;
+pub use validation::{
+    validate_expiration, validate_position_stops, validate_trade_request, validate_trade_request_without_spec,
+    COMMENT_MAX_LEN, MIN_EXPIRATION_SECS,
+};
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use wasm::{WasmFrame, WasmSession};
+
+pub use bytes::Bytes;
+pub use secrecy::{ExposeSecret, SecretString};
 
 /// 登录凭证
+///
+/// 密码释放时清零，`Debug` 输出中显示为 `[REDACTED]`
 #[derive(Debug, Clone)]
 pub struct LoginCredentials {
     pub login: String,
-    pub password: String,
+    pub password: SecretString,
     pub server: String,
 }
+
+impl LoginCredentials {
+    /// 创建登录凭证
+    pub fn new(login: impl Into<String>, password: impl Into<String>, server: impl Into<String>) -> Self {
+        Self { login: login.into(), password: SecretString::from(password.into()), server: server.into() }
+    }
+}
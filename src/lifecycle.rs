@@ -0,0 +1,219 @@
+//! 订单生命周期状态机
+//!
+//! 每个 ticket 的状态根据 Command 10 订单更新的 notify_type 推导:
+//!
+//! ```text
+//!            ┌──────────────► Cancelled
+//!            │
+//! PendingPlaced ──► Open ──► Closed
+//!            │        │
+//!            │        └────► PartiallyClosed (剩余手数转到新 ticket)
+//!            └──────────────► Expired
+//! ```
+//!
+//! 状态变化以 `Mt4Event::OrderStateChanged` 发出；不符合上图的变化记录警告日志，
+//! 并在 `OrderTransition::valid` 中标记为 false。
+
+use crate::protocol::OrderType;
+use crate::types::{Order, OrderUpdate, Ticket};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 订单状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum OrderState {
+    /// 挂单已下达，等待触发
+    PendingPlaced,
+    /// 持仓中
+    Open,
+    /// 部分平仓 (该 ticket 已关闭，剩余手数转到新 ticket)
+    PartiallyClosed,
+    /// 已平仓
+    Closed,
+    /// 挂单已删除
+    Cancelled,
+    /// 挂单已过期
+    Expired,
+}
+
+impl OrderState {
+    /// 是否为终止状态
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderState::PartiallyClosed | OrderState::Closed | OrderState::Cancelled | OrderState::Expired
+        )
+    }
+
+    /// 是否允许从当前状态变为 `to`
+    pub fn can_transition_to(&self, to: OrderState) -> bool {
+        matches!(
+            (self, to),
+            (OrderState::PendingPlaced, OrderState::Open)
+                | (OrderState::PendingPlaced, OrderState::Cancelled)
+                | (OrderState::PendingPlaced, OrderState::Expired)
+                | (OrderState::Open, OrderState::PartiallyClosed)
+                | (OrderState::Open, OrderState::Closed)
+        )
+    }
+}
+
+/// 订单状态变化
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderTransition {
+    /// 订单号
+    pub ticket: Ticket,
+    /// 原状态 (首次出现的订单为 None)
+    pub from: Option<OrderState>,
+    /// 新状态
+    pub to: OrderState,
+    /// 是否为合法的状态变化
+    pub valid: bool,
+    /// 部分平仓后剩余手数所在的新 ticket
+    pub remaining_ticket: Option<Ticket>,
+    /// 变化时的订单数据
+    pub order: Order,
+}
+
+/// 订单生命周期跟踪器
+#[derive(Debug, Default)]
+pub struct OrderLifecycle {
+    states: HashMap<Ticket, OrderState>,
+}
+
+impl OrderLifecycle {
+    /// 创建空跟踪器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 用持仓快照 (Command 4) 重置状态，不产生状态变化
+    ///
+    /// 快照之前的终止状态会被丢弃
+    pub fn seed(&mut self, orders: &[Order]) {
+        self.states = orders
+            .iter()
+            .filter(|o| o.close_time == 0)
+            .map(|o| (o.ticket, Self::live_state(o.order_type)))
+            .collect();
+    }
+
+    /// 获取订单当前状态
+    pub fn state(&self, ticket: Ticket) -> Option<OrderState> {
+        self.states.get(&ticket).copied()
+    }
+
+    /// 应用订单更新，返回产生的状态变化 (状态未变时为空)
+    pub fn apply(&mut self, updates: &[OrderUpdate]) -> Vec<OrderTransition> {
+        updates.iter().filter_map(|u| self.apply_one(u)).collect()
+    }
+
+    fn apply_one(&mut self, update: &OrderUpdate) -> Option<OrderTransition> {
+        let order = &update.order;
+        let to = match update.notify_type {
+            // 新建与修改: 挂单成交时订单类型变为市价单
+            0 | 2 => Self::live_state(order.order_type),
+            1 => Self::closed_state(order),
+            _ => return None,
+        };
+
+        let from = self.state(order.ticket);
+        if from == Some(to) {
+            return None;
+        }
+        let valid = from.is_none_or(|from| from.can_transition_to(to));
+        if !valid {
+            tracing::warn!(
+                "Invalid order transition: ticket={}, {:?} -> {:?} (notify_type={})",
+                order.ticket,
+                from,
+                to,
+                update.notify_type
+            );
+        }
+        self.states.insert(order.ticket, to);
+
+        Some(OrderTransition {
+            ticket: order.ticket,
+            from,
+            to,
+            valid,
+            remaining_ticket: if to == OrderState::PartiallyClosed { order.child_ticket() } else { None },
+            order: order.clone(),
+        })
+    }
+
+    fn live_state(order_type: OrderType) -> OrderState {
+        match order_type {
+            OrderType::Buy | OrderType::Sell => OrderState::Open,
+            _ => OrderState::PendingPlaced,
+        }
+    }
+
+    /// 关闭通知对应的终止状态
+    ///
+    /// MT4 在注释中标记原因: 过期挂单为 "[expiration]"，部分平仓的原订单为 "to #<新ticket>"
+    fn closed_state(order: &Order) -> OrderState {
+        if order.is_pending() {
+            if order.comment.to_ascii_lowercase().contains("expiration") {
+                OrderState::Expired
+            } else {
+                OrderState::Cancelled
+            }
+        } else if order.child_ticket().is_some() {
+            OrderState::PartiallyClosed
+        } else {
+            OrderState::Closed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(notify_type: i32, ticket: i32, order_type: OrderType, comment: &str) -> OrderUpdate {
+        let order = Order {
+            close_time: if notify_type == 1 { 1_700_000_100 } else { 0 },
+            comment: comment.to_string(),
+            ..Order::for_test(ticket, "EURUSD", order_type, 0.1, 1.08)
+        };
+        OrderUpdate { notify_id: 1, notify_type, df: 0.0, xh: 0.0, raw_size: 185, order, related_order: None }
+    }
+
+    #[test]
+    fn test_pending_fill_and_partial_close() {
+        let mut lifecycle = OrderLifecycle::new();
+        let t = lifecycle.apply(&[update(0, 1, OrderType::BuyLimit, "")]);
+        assert_eq!((t[0].from, t[0].to), (None, OrderState::PendingPlaced));
+
+        // 挂单成交
+        let t = lifecycle.apply(&[update(2, 1, OrderType::Buy, "")]);
+        assert_eq!((t[0].from, t[0].to), (Some(OrderState::PendingPlaced), OrderState::Open));
+
+        // 修改 SL/TP 不产生状态变化
+        assert!(lifecycle.apply(&[update(2, 1, OrderType::Buy, "")]).is_empty());
+
+        // 部分平仓: 原单关闭，剩余手数开新单
+        let t = lifecycle.apply(&[update(1, 1, OrderType::Buy, "to #2"), update(0, 2, OrderType::Buy, "from #1")]);
+        assert_eq!(t[0].to, OrderState::PartiallyClosed);
+        assert_eq!(t[0].remaining_ticket, Some(Ticket(2)));
+        assert_eq!(lifecycle.state(Ticket(2)), Some(OrderState::Open));
+        assert!(t.iter().all(|t| t.valid));
+    }
+
+    #[test]
+    fn test_cancel_expire_and_invalid() {
+        let mut lifecycle = OrderLifecycle::new();
+        lifecycle.seed(&[update(0, 1, OrderType::SellStop, "").order, update(0, 2, OrderType::BuyLimit, "").order]);
+
+        let t = lifecycle.apply(&[update(1, 1, OrderType::SellStop, "[expiration]")]);
+        assert_eq!(t[0].to, OrderState::Expired);
+        let t = lifecycle.apply(&[update(1, 2, OrderType::BuyLimit, "cancelled")]);
+        assert_eq!(t[0].to, OrderState::Cancelled);
+
+        // 已删除的挂单再次出现: 非法变化
+        let t = lifecycle.apply(&[update(0, 2, OrderType::Buy, "")]);
+        assert!(!t[0].valid);
+    }
+}
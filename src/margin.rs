@@ -0,0 +1,252 @@
+//! 保证金估算
+//!
+//! 按 MT4 外汇的计算方式估算开仓所需保证金，在发送请求前排除保证金不足 (错误码 134) 的交易:
+//!
+//! ```text
+//! 保证金 = 手数 × 合约数量 × 开仓价 ÷ 杠杆   (报价货币，再按实时汇率换算为账户货币)
+//! ```
+//!
+//! 品种名不是货币对时 (指数、差价合约) 视为以账户货币计价 (见 `pips` 模块)。
+//! 估算不考虑对冲仓位的保证金减免和经纪商为单个品种设置的保证金比例，只作为发送前的预检。
+//!
+//! 账户信息 (Command 3) 中没有已用保证金，`used_margin` 按持仓的开仓价和品种规格估算，
+//! 任一持仓缺少品种规格或换算汇率时不给出结果。
+//!
+//! `MarginMonitor` 跟踪保证金比例 (净值 ÷ 已用保证金 × 100%)，比例降到警告 / 危险阈值时返回告警，
+//! 客户端据此发出 `Mt4Event::MarginWarning` / `Mt4Event::MarginCritical`，监管代码可以在强平前减仓。
+//! 每个区间只在进入时告警一次，比例回升后再次下降时重新告警。
+//!
+//! ```no_run
+//! # async fn example(client: &mt4_client::Mt4Client) -> mt4_client::Result<()> {
+//! use mt4_client::OrderType;
+//!
+//! let check = client.check_margin("EURUSD", 1.0, OrderType::Buy).await?;
+//! if !check.sufficient {
+//!     println!("需要保证金 {:.2}，可用 {:.2}", check.required_margin, check.free_margin);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::pips::{currency_pair, CrossRates};
+use crate::types::{Order, SymbolInfo};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 保证金比例区间
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum MarginZone {
+    /// 高于警告阈值 (或没有持仓)
+    #[default]
+    Normal,
+    /// 不高于警告阈值
+    Warning,
+    /// 不高于危险阈值
+    Critical,
+}
+
+/// 保证金比例告警
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarginAlert {
+    /// 保证金比例 (%)
+    pub margin_level: f64,
+    /// 净值
+    pub equity: f64,
+    /// 已用保证金
+    pub margin: f64,
+    /// 触发的阈值 (%)
+    pub threshold: f64,
+}
+
+/// 保证金比例 (%)，没有占用保证金时为 None
+pub fn margin_level(equity: f64, margin: f64) -> Option<f64> {
+    (margin > 0.0).then(|| equity / margin * 100.0)
+}
+
+/// 保证金比例监控器
+#[derive(Debug, Clone, Default)]
+pub struct MarginMonitor {
+    warning_level: Option<f64>,
+    critical_level: Option<f64>,
+    level: Option<f64>,
+    zone: MarginZone,
+}
+
+impl MarginMonitor {
+    /// 创建监控器 (未设置阈值时只记录比例)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置警告和危险阈值 (保证金比例 %，None 表示不告警)
+    pub fn set_thresholds(&mut self, warning: Option<f64>, critical: Option<f64>) {
+        self.warning_level = warning;
+        self.critical_level = critical;
+    }
+
+    /// 最近一次计算的保证金比例 (%)
+    pub fn level(&self) -> Option<f64> {
+        self.level
+    }
+
+    /// 当前所在区间
+    pub fn zone(&self) -> MarginZone {
+        self.zone
+    }
+
+    /// 用最新的净值和已用保证金更新，进入更危险的区间时返回 (区间, 告警)
+    pub fn on_update(&mut self, equity: f64, margin: f64) -> Option<(MarginZone, MarginAlert)> {
+        self.level = margin_level(equity, margin);
+        let below = |threshold: Option<f64>| threshold.filter(|t| self.level.is_some_and(|level| level <= *t));
+        let (zone, threshold) = match (below(self.critical_level), below(self.warning_level)) {
+            (Some(threshold), _) => (MarginZone::Critical, threshold),
+            (None, Some(threshold)) => (MarginZone::Warning, threshold),
+            (None, None) => (MarginZone::Normal, 0.0),
+        };
+        let previous = std::mem::replace(&mut self.zone, zone);
+        if zone <= previous {
+            return None;
+        }
+        let margin_level = self.level?;
+        Some((zone, MarginAlert { margin_level, equity, margin, threshold }))
+    }
+}
+
+/// 保证金预检结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarginCheck {
+    /// 品种
+    pub symbol: String,
+    /// 手数
+    pub volume: f64,
+    /// 估算的所需保证金 (账户货币)
+    pub required_margin: f64,
+    /// 当前可用保证金 (账户货币)
+    pub free_margin: f64,
+    /// 开仓后剩余的可用保证金
+    pub free_margin_after: f64,
+    /// 可用保证金是否足够
+    pub sufficient: bool,
+}
+
+impl MarginCheck {
+    /// 比较所需保证金与可用保证金
+    pub fn new(symbol: &str, volume: f64, required_margin: f64, free_margin: f64) -> Self {
+        let free_margin_after = free_margin - required_margin;
+        Self {
+            symbol: symbol.to_string(),
+            volume,
+            required_margin,
+            free_margin,
+            free_margin_after,
+            sufficient: free_margin_after >= 0.0,
+        }
+    }
+}
+
+/// 以 `price` 开仓 `volume` 手所需的保证金 (账户货币)，杠杆无效或缺少换算汇率时返回 None
+pub fn required_margin(
+    spec: &SymbolInfo,
+    volume: f64,
+    price: f64,
+    leverage: i32,
+    account_currency: &str,
+    rates: &CrossRates,
+) -> Option<f64> {
+    if leverage <= 0 {
+        return None;
+    }
+    let margin = volume.abs() * spec.contract_size * price / leverage as f64;
+    match currency_pair(&spec.symbol) {
+        Some((_, quote)) => rates.convert(margin, quote, account_currency),
+        None => Some(margin),
+    }
+}
+
+/// 持仓占用的保证金合计 (账户货币，按开仓价估算，挂单不占用保证金)
+///
+/// `specs` 为品种 -> 交易规格，任一持仓缺少规格或换算汇率时返回 None，不使用默认规格
+pub fn used_margin<'a>(
+    positions: impl IntoIterator<Item = &'a Order>,
+    specs: &HashMap<String, SymbolInfo>,
+    leverage: i32,
+    account_currency: &str,
+    rates: &CrossRates,
+) -> Option<f64> {
+    positions.into_iter().filter(|o| !o.is_pending()).try_fold(0.0, |total, order| {
+        let spec = specs.get(order.symbol.as_str())?;
+        Some(total + required_margin(spec, order.volume, order.open_price, leverage, account_currency, rates)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+    use crate::types::Quote;
+
+    #[test]
+    fn test_required_margin() {
+        let mut rates = CrossRates::new();
+        rates.update(&Quote { symbol: "USDJPY".to_string(), bid: 150.0, ask: 150.0, time: 0 });
+
+        // 1 手 EURUSD，1:100 杠杆: 100000 × 1.1 ÷ 100 = 1100 USD
+        let eurusd = SymbolInfo::new("EURUSD", 5);
+        let margin = required_margin(&eurusd, 1.0, 1.1, 100, "USD", &rates).unwrap();
+        assert!((margin - 1100.0).abs() < 1e-6);
+        // 报价货币 JPY: 100000 × 150 ÷ 100 = 150000 JPY = 1000 USD
+        let usdjpy = SymbolInfo::new("USDJPY", 3);
+        let margin = required_margin(&usdjpy, 1.0, 150.0, 100, "USD", &rates).unwrap();
+        assert!((margin - 1000.0).abs() < 1e-6);
+        assert!(required_margin(&usdjpy, 1.0, 150.0, 0, "USD", &rates).is_none());
+        assert!(required_margin(&usdjpy, 1.0, 150.0, 100, "GBP", &rates).is_none());
+
+        let check = MarginCheck::new("USDJPY", 1.0, 1000.0, 800.0);
+        assert!(!check.sufficient && check.free_margin_after == -200.0);
+    }
+
+    #[test]
+    fn test_used_margin() {
+        let mut rates = CrossRates::new();
+        rates.update(&Quote { symbol: "USDJPY".to_string(), bid: 150.0, ask: 150.0, time: 0 });
+        let specs = HashMap::from([
+            ("EURUSD".to_string(), SymbolInfo::new("EURUSD", 5)),
+            ("USDJPY".to_string(), SymbolInfo::new("USDJPY", 3)),
+        ]);
+
+        // 1100 + 0.5 手 USDJPY (75000 JPY = 500 USD)，挂单不计入
+        let orders = [
+            Order::for_test(1, "EURUSD", OrderType::Buy, 1.0, 1.1),
+            Order::for_test(2, "USDJPY", OrderType::Sell, 0.5, 150.0),
+            Order::for_test(3, "EURUSD", OrderType::BuyLimit, 5.0, 1.0),
+        ];
+        let margin = used_margin(&orders, &specs, 100, "USD", &rates).unwrap();
+        assert!((margin - 1600.0).abs() < 1e-6);
+        assert_eq!(used_margin(&[], &specs, 100, "USD", &rates), Some(0.0));
+
+        // 缺少品种规格时不估算
+        let unknown = [Order::for_test(4, "XAUUSD", OrderType::Buy, 0.1, 2000.0)];
+        assert!(used_margin(&unknown, &specs, 100, "USD", &rates).is_none());
+    }
+
+    #[test]
+    fn test_margin_monitor_zones() {
+        let mut monitor = MarginMonitor::new();
+        assert!(monitor.on_update(500.0, 1000.0).is_none());
+        monitor.set_thresholds(Some(100.0), Some(50.0));
+
+        // 150% -> 90%: 警告；持续在警告区间不重复告警
+        assert!(monitor.on_update(1500.0, 1000.0).is_none());
+        let (zone, alert) = monitor.on_update(900.0, 1000.0).unwrap();
+        assert_eq!((zone, alert.margin_level, alert.threshold), (MarginZone::Warning, 90.0, 100.0));
+        assert!(monitor.on_update(800.0, 1000.0).is_none());
+
+        // 跌入危险区间；回升后再次下降重新告警
+        assert_eq!(monitor.on_update(400.0, 1000.0).unwrap().0, MarginZone::Critical);
+        assert!(monitor.on_update(700.0, 1000.0).is_none());
+        assert_eq!(monitor.zone(), MarginZone::Warning);
+        assert!(monitor.on_update(2000.0, 0.0).is_none());
+        assert_eq!((monitor.zone(), monitor.level()), (MarginZone::Normal, None));
+        assert_eq!(monitor.on_update(450.0, 1000.0).unwrap().0, MarginZone::Critical);
+    }
+}
@@ -0,0 +1,166 @@
+//! 只读镜像
+//!
+//! `Mt4Client::mirror()` 返回共享同一会话数据的只读视图，可以交给插件、界面等不受信任的组件:
+//! 账户信息、持仓/挂单、品种规格、订单状态和事件订阅都可以读取，但类型上没有任何交易或发送命令的方法。
+//! 镜像可以克隆，持有镜像不会使连接保持打开；报价随事件推送，通过订阅获取。
+
+use crate::book::PendingBook;
+use crate::breakeven::{net_breakeven, position_breakeven, Breakeven};
+use crate::client::Mt4Event;
+use crate::clock::EventTime;
+use crate::events::{EventSubscription, TimedEvent};
+use crate::lifecycle::{OrderLifecycle, OrderState};
+use crate::positions::PositionManager;
+use crate::types::{AccountInfo, Order, SymbolInfo, Ticket};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex, RwLock};
+
+/// 客户端会话的只读视图
+#[derive(Clone)]
+pub struct Mt4Mirror {
+    pub(crate) account: Arc<RwLock<Option<AccountInfo>>>,
+    pub(crate) positions: Arc<PositionManager>,
+    pub(crate) lifecycle: Arc<Mutex<OrderLifecycle>>,
+    pub(crate) symbols: Arc<RwLock<HashMap<String, SymbolInfo>>>,
+    pub(crate) authenticated: Arc<AtomicBool>,
+    pub(crate) broadcast: broadcast::Sender<TimedEvent>,
+    pub(crate) recent_events: Arc<std::sync::Mutex<VecDeque<TimedEvent>>>,
+}
+
+impl std::fmt::Debug for Mt4Mirror {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mt4Mirror").field("authenticated", &self.is_authenticated()).finish()
+    }
+}
+
+impl Mt4Mirror {
+    /// 会话是否已认证
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated.load(Ordering::SeqCst)
+    }
+
+    /// 最近一次收到的账户信息
+    pub async fn account_info(&self) -> Option<AccountInfo> {
+        self.account.read().await.clone()
+    }
+
+    /// 当前持仓 (市价单)，按 ticket 排序
+    pub async fn positions(&self) -> Vec<Order> {
+        self.positions.positions().await
+    }
+
+    /// 当前挂单，按 ticket 排序
+    pub async fn pending_orders(&self) -> Vec<Order> {
+        self.positions.pending_orders().await
+    }
+
+    /// 当前挂单按品种和价格排序的快照
+    pub async fn pending_book(&self) -> PendingBook {
+        self.positions.pending_book().await
+    }
+
+    /// 本地缓存的订单
+    pub async fn cached_order(&self, ticket: Ticket) -> Option<Order> {
+        self.positions.get(ticket).await
+    }
+
+    /// 订单生命周期状态
+    pub async fn order_state(&self, ticket: Ticket) -> Option<OrderState> {
+        self.lifecycle.lock().await.state(ticket)
+    }
+
+    /// 已设置的品种交易规格
+    pub async fn symbol_info(&self, symbol: &str) -> Option<SymbolInfo> {
+        self.symbols.read().await.get(symbol).cloned()
+    }
+
+    /// 持仓的保本价 (bid)，`spread` 为当前 ask - bid
+    pub async fn breakeven(&self, ticket: Ticket, spread: f64) -> Option<Breakeven> {
+        let order = self.positions.get(ticket).await?;
+        let spec = self.symbol_info_or_default(&order.symbol, order.digits).await;
+        position_breakeven(&order, &spec, spread)
+    }
+
+    /// 品种净持仓的保本价 (bid)，多空手数相等时为 None
+    pub async fn net_breakeven(&self, symbol: &str, spread: f64) -> Option<Breakeven> {
+        let orders = self.positions.positions_for_symbol(symbol).await;
+        let spec = self.symbol_info_or_default(symbol, orders.first()?.digits).await;
+        net_breakeven(&orders, &spec, spread)
+    }
+
+    async fn symbol_info_or_default(&self, symbol: &str, digits: i32) -> SymbolInfo {
+        self.symbol_info(symbol)
+            .await
+            .unwrap_or_else(|| SymbolInfo::new(symbol, digits))
+    }
+
+    /// 最近发出的事件 (按时间顺序)
+    pub fn recent_events(&self) -> Vec<TimedEvent> {
+        self.recent_events
+            .lock()
+            .map(|r| r.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 订阅事件广播
+    pub fn subscribe(&self) -> EventSubscription {
+        EventSubscription::new(self.broadcast.subscribe())
+    }
+
+    /// 订阅事件广播，并先回放最近的 `count` 个事件 (不重复也不遗漏)
+    pub fn subscribe_with_replay(&self, count: usize) -> EventSubscription {
+        let Ok(recent) = self.recent_events.lock() else {
+            return self.subscribe();
+        };
+        // 持有最近事件锁时订阅，期间不会有新事件广播
+        let rx = self.broadcast.subscribe();
+        let backlog = recent.iter().skip(recent.len().saturating_sub(count)).cloned().collect();
+        EventSubscription::with_backlog(rx, backlog)
+    }
+
+    /// 订阅事件广播，并先回放序号大于 `seq` 的事件 (不重复也不遗漏)
+    ///
+    /// 已被最近事件缓冲区覆盖的事件无法回放，计入 `EventSubscription::lagged()`
+    pub fn subscribe_from(&self, seq: u64) -> EventSubscription {
+        let Ok(recent) = self.recent_events.lock() else {
+            return self.subscribe();
+        };
+        EventSubscription::replay_from(self.broadcast.subscribe(), &recent, seq)
+    }
+
+    /// 订阅事件广播，并先收到当前状态快照: `AccountInfo` (已知时) 和 `PositionsSnapshot`
+    pub async fn subscribe_with_snapshot(&self) -> EventSubscription {
+        let rx = self.broadcast.subscribe();
+        let mut backlog = VecDeque::new();
+        if let Some(account) = self.account_info().await {
+            let event = Mt4Event::AccountInfo(account);
+            backlog.push_back(TimedEvent { event, time: EventTime::now(None), seq: 0 });
+        }
+        let event = Mt4Event::PositionsSnapshot(self.positions.all().await);
+        backlog.push_back(TimedEvent { event, time: EventTime::now(None), seq: 0 });
+        EventSubscription::with_backlog(rx, backlog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{Mt4Client, Mt4Event};
+    use crate::types::SymbolInfo;
+
+    #[tokio::test]
+    async fn test_mirror_shares_session_state() {
+        let client = Mt4Client::new();
+        let mirror = client.mirror();
+        assert!(!mirror.is_authenticated());
+
+        // 镜像创建之后的变化同样可见
+        client.set_symbol_info(SymbolInfo::new("XAUUSD", 2)).await;
+        assert_eq!(mirror.symbol_info("XAUUSD").await.unwrap().digits, 2);
+
+        let mut subscription = mirror.subscribe_with_snapshot().await;
+        assert!(matches!(subscription.recv().await, Some(Mt4Event::PositionsSnapshot(orders)) if orders.is_empty()));
+        assert!(mirror.account_info().await.is_none());
+    }
+}
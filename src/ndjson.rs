@@ -0,0 +1,312 @@
+//! NDJSON 事件日志
+//!
+//! `NdjsonSink` 把每个 `Mt4Event` 按 `mt4.event.v1` 格式 (见 `schema` 模块) 写成一行 JSON，
+//! 输出到按时间轮换的文件或任意 `AsyncWrite`，便于用 `jq`、日志采集器等工具处理:
+//!
+//! ```text
+//! events_20240102.ndjson
+//! events_20240102.1.ndjson   (超过 with_max_size 后的续写文件)
+//! events_20240103.ndjson
+//! ```
+//!
+//! ```no_run
+//! use mt4_client::{Mt4Client, NdjsonSink, TickRotation};
+//!
+//! # async fn example() -> mt4_client::Result<()> {
+//! let handle = Mt4Client::new().spawn();
+//! let sink = NdjsonSink::new("logs")?
+//!     .with_rotation(TickRotation::Hourly)
+//!     .with_max_size(64 * 1024 * 1024);
+//! tokio::spawn(sink.run(handle.subscribe()));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! - 轮换周期与报价记录相同 (`TickRotation`)，按事件的本地 UTC 接收时间划分
+//! - 文件已存在时追加；`AsyncWrite` 输出不轮换
+//! - 输出带缓冲，丢弃前调用 `finish()` (`run()` 在事件流结束时自动调用)
+
+use crate::error::{Mt4Error, Result};
+use crate::events::{EventSubscription, TimedEvent};
+use crate::recorder::TickRotation;
+use crate::schema::JsonSchemaAdapter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+
+/// 默认刷新间隔
+pub const DEFAULT_NDJSON_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// NDJSON 事件日志
+pub struct NdjsonSink {
+    output: Output,
+    flush_interval: Duration,
+    last_flush: Instant,
+    lines: u64,
+}
+
+impl std::fmt::Debug for NdjsonSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NdjsonSink")
+            .field("current", &self.current_path())
+            .field("lines", &self.lines)
+            .finish()
+    }
+}
+
+/// 输出目标
+enum Output {
+    Writer(BufWriter<Box<dyn AsyncWrite + Send + Unpin>>),
+    Files(Box<FileOutput>),
+}
+
+/// 轮换文件
+struct FileOutput {
+    dir: PathBuf,
+    prefix: String,
+    rotation: TickRotation,
+    max_size: Option<u64>,
+    current: Option<OpenFile>,
+}
+
+/// 当前写入的文件
+struct OpenFile {
+    period: i64,
+    seq: u32,
+    path: PathBuf,
+    writer: BufWriter<tokio::fs::File>,
+    size: u64,
+}
+
+impl NdjsonSink {
+    /// 在目录中写入事件日志 (目录不存在时创建)，默认按天轮换，前缀 `events`
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| Mt4Error::Io(format!("创建事件日志目录 {} 失败: {}", dir.display(), e)))?;
+        Ok(Self::with_output(Output::Files(Box::new(FileOutput {
+            dir,
+            prefix: "events".to_string(),
+            rotation: TickRotation::Daily,
+            max_size: None,
+            current: None,
+        }))))
+    }
+
+    /// 写入任意 `AsyncWrite` (如 stdout、管道、socket)
+    pub fn from_writer(writer: impl AsyncWrite + Send + Unpin + 'static) -> Self {
+        let writer: Box<dyn AsyncWrite + Send + Unpin> = Box::new(writer);
+        Self::with_output(Output::Writer(BufWriter::new(writer)))
+    }
+
+    fn with_output(output: Output) -> Self {
+        Self {
+            output,
+            flush_interval: DEFAULT_NDJSON_FLUSH_INTERVAL,
+            last_flush: Instant::now(),
+            lines: 0,
+        }
+    }
+
+    /// 设置文件名前缀
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        if let Output::Files(files) = &mut self.output {
+            files.prefix = prefix.into();
+        }
+        self
+    }
+
+    /// 设置轮换周期
+    pub fn with_rotation(mut self, rotation: TickRotation) -> Self {
+        if let Output::Files(files) = &mut self.output {
+            files.rotation = rotation;
+        }
+        self
+    }
+
+    /// 设置单个文件的最大字节数，超过后在同一周期内续写新文件
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        if let Output::Files(files) = &mut self.output {
+            files.max_size = Some(max_size);
+        }
+        self
+    }
+
+    /// 设置刷新间隔 (写入事件时距上次刷新超过该间隔即写入磁盘)
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// 当前写入的文件
+    pub fn current_path(&self) -> Option<&Path> {
+        match &self.output {
+            Output::Files(files) => files.current.as_ref().map(|file| file.path.as_path()),
+            Output::Writer(_) => None,
+        }
+    }
+
+    /// 已写入的事件数
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    /// 写入一个事件
+    pub async fn write(&mut self, event: &TimedEvent) -> Result<()> {
+        let mut line = JsonSchemaAdapter.to_value(event).to_string();
+        line.push('\n');
+        match &mut self.output {
+            Output::Writer(writer) => writer.write_all(line.as_bytes()).await.map_err(stream_error)?,
+            Output::Files(files) => {
+                let secs = event.time.utc.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+                files.write(secs, line.as_bytes()).await?;
+            }
+        }
+        self.lines += 1;
+        if self.last_flush.elapsed() >= self.flush_interval {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// 把缓冲的事件写入输出
+    pub async fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
+        match &mut self.output {
+            Output::Writer(writer) => writer.flush().await.map_err(stream_error),
+            Output::Files(files) => match &mut files.current {
+                Some(file) => file.writer.flush().await.map_err(|e| io_error("刷新", &file.path, e)),
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// 刷新并关闭当前文件 (之后的事件写入新文件)
+    pub async fn finish(&mut self) -> Result<()> {
+        match &mut self.output {
+            Output::Writer(writer) => writer.flush().await.map_err(stream_error),
+            Output::Files(files) => files.close().await,
+        }
+    }
+
+    /// 写入订阅的所有事件，事件流结束 (客户端被丢弃) 后刷新并返回
+    ///
+    /// 没有新事件时也按刷新间隔写入输出
+    pub async fn run(mut self, mut events: EventSubscription) -> Result<()> {
+        let mut ticker = tokio::time::interval(self.flush_interval);
+        loop {
+            tokio::select! {
+                event = events.recv_timed() => match event {
+                    Some(event) => self.write(&event).await?,
+                    None => break,
+                },
+                _ = ticker.tick() => self.flush().await?,
+            }
+        }
+        self.finish().await
+    }
+}
+
+impl FileOutput {
+    async fn write(&mut self, secs: i64, line: &[u8]) -> Result<()> {
+        let (period, suffix) = self.rotation.period(secs);
+        let len = line.len() as u64;
+        let seq = match &self.current {
+            Some(file) if file.period != period => Some(0),
+            Some(file) if self.max_size.is_some_and(|max| file.size > 0 && file.size + len > max) => Some(file.seq + 1),
+            Some(_) => None,
+            None => Some(0),
+        };
+        if let Some(seq) = seq {
+            self.close().await?;
+            self.open(period, &suffix, seq).await?;
+        }
+        if let Some(file) = &mut self.current {
+            file.writer.write_all(line).await.map_err(|e| io_error("写入", &file.path, e))?;
+            file.size += len;
+        }
+        Ok(())
+    }
+
+    /// 打开周期内第一个未写满的文件 (从 `seq` 开始)
+    async fn open(&mut self, period: i64, suffix: &str, mut seq: u32) -> Result<()> {
+        loop {
+            let path = match seq {
+                0 => self.dir.join(format!("{}_{}.ndjson", self.prefix, suffix)),
+                n => self.dir.join(format!("{}_{}.{}.ndjson", self.prefix, suffix, n)),
+            };
+            let size = tokio::fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0);
+            if self.max_size.is_some_and(|max| size >= max) {
+                seq += 1;
+                continue;
+            }
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await
+                .map_err(|e| io_error("打开", &path, e))?;
+            tracing::info!("Writing events to {}", path.display());
+            self.current = Some(OpenFile { period, seq, path, writer: BufWriter::new(file), size });
+            return Ok(());
+        }
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        match self.current.take() {
+            Some(mut file) => file.writer.shutdown().await.map_err(|e| io_error("关闭", &file.path, e)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn io_error(action: &str, path: &Path, e: impl std::fmt::Display) -> Mt4Error {
+    Mt4Error::Io(format!("{}事件日志 {} 失败: {}", action, path.display(), e))
+}
+
+fn stream_error(e: std::io::Error) -> Mt4Error {
+    Mt4Error::Io(format!("写入事件流失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Mt4Event;
+    use crate::clock::EventTime;
+
+    fn event(event: Mt4Event, secs: u64) -> TimedEvent {
+        let mut time = EventTime::now(None);
+        time.utc = UNIX_EPOCH + Duration::from_secs(secs);
+        TimedEvent { event, time, seq: 0 }
+    }
+
+    #[tokio::test]
+    async fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("mt4_ndjson_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        // 2024-01-01 23:59:59 和 2024-01-02 00:00:01 (UTC)
+        let (day1, day2) = (1_704_153_599, 1_704_153_601);
+
+        let mut sink = NdjsonSink::new(&dir).unwrap().with_max_size(100);
+        sink.write(&event(Mt4Event::Connected, day1)).await.unwrap();
+        sink.write(&event(Mt4Event::Error("x".repeat(80)), day1)).await.unwrap();
+        sink.write(&event(Mt4Event::Authenticated, day2)).await.unwrap();
+        sink.finish().await.unwrap();
+        assert_eq!(sink.lines(), 3);
+
+        let first = std::fs::read_to_string(dir.join("events_20240101.ndjson")).unwrap();
+        let line: serde_json::Value = serde_json::from_str(first.trim_end()).unwrap();
+        assert_eq!(line["type"], "Connected");
+        let overflow = std::fs::read_to_string(dir.join("events_20240101.1.ndjson")).unwrap();
+        assert_eq!(overflow.lines().count(), 1);
+        let second = std::fs::read_to_string(dir.join("events_20240102.ndjson")).unwrap();
+        assert!(second.contains("\"Authenticated\""));
+
+        // 已写满的文件跳过
+        let mut sink = NdjsonSink::new(&dir).unwrap().with_max_size(100);
+        sink.write(&event(Mt4Event::Pong, day1)).await.unwrap();
+        assert_eq!(sink.current_path(), Some(dir.join("events_20240101.2.ndjson").as_path()));
+        sink.finish().await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
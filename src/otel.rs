@@ -0,0 +1,225 @@
+//! OpenTelemetry 集成
+//!
+//! 开启 `otel` 特性后，`init()` 创建通过 OTLP/HTTP 导出的追踪和指标管道:
+//!
+//! - 追踪: 客户端的 `mt4.connect` / `mt4.auth` / `mt4.trade` / `mt4.trade.dispatch` 等 `tracing` span，
+//!   经 `OtelGuard::layer()` 返回的层转换为 OpenTelemetry span (需要加入应用的 tracing 订阅器)
+//! - 指标: 与 `telemetry` 模块相同的指标 (`mt4_connected`、`mt4_trade_latency_seconds` 等) 定期导出
+//!
+//! ```no_run
+//! use mt4_client::otel::{self, OtelConfig};
+//! use tracing_subscriber::layer::SubscriberExt;
+//!
+//! # fn example() -> mt4_client::Result<()> {
+//! let guard = otel::init(&OtelConfig::new("http://localhost:4318").with_service_name("grid-bot"))?;
+//! let subscriber = tracing_subscriber::registry().with(guard.layer());
+//! tracing::subscriber::set_global_default(subscriber).expect("subscriber");
+//! // ... 运行客户端，guard 被丢弃时导出剩余数据并关闭管道
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! 导出器使用阻塞 HTTP 客户端，在独立线程中创建和运行，可以在 tokio 运行时内调用 `init()`。
+
+use crate::error::{Mt4Error, Result};
+use opentelemetry::metrics::{Counter, Gauge, Histogram, Meter, MeterProvider as _};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// 仪表名称
+const INSTRUMENTATION_NAME: &str = "mt4_client";
+
+/// OTLP 导出配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtelConfig {
+    /// 收集器地址 (如 `http://localhost:4318`)，追踪和指标分别发送到 `/v1/traces` 和 `/v1/metrics`
+    pub endpoint: String,
+    /// `service.name` 资源属性
+    pub service_name: String,
+    /// 指标导出间隔
+    pub metric_interval: Duration,
+}
+
+impl OtelConfig {
+    /// 以收集器地址创建 (服务名 `mt4_client`，指标每 30 秒导出一次)
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            service_name: INSTRUMENTATION_NAME.to_string(),
+            metric_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// 设置服务名
+    pub fn with_service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_name = name.into();
+        self
+    }
+
+    /// 设置指标导出间隔
+    pub fn with_metric_interval(mut self, interval: Duration) -> Self {
+        self.metric_interval = interval;
+        self
+    }
+
+    fn signal_endpoint(&self, signal: &str) -> String {
+        format!("{}/v1/{}", self.endpoint.trim_end_matches('/'), signal)
+    }
+}
+
+/// 导出管道，丢弃时导出剩余数据并关闭
+#[derive(Debug)]
+pub struct OtelGuard {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl OtelGuard {
+    /// 客户端使用的 tracer
+    pub fn tracer(&self) -> SdkTracer {
+        self.tracer_provider.tracer(INSTRUMENTATION_NAME)
+    }
+
+    /// 把 `tracing` span 导出为 OpenTelemetry span 的订阅器层
+    pub fn layer<S>(&self) -> OpenTelemetryLayer<S, SdkTracer>
+    where
+        S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.tracer())
+    }
+}
+
+impl Drop for OtelGuard {
+    fn drop(&mut self) {
+        if let Ok(mut instruments) = INSTRUMENTS.write() {
+            *instruments = None;
+        }
+        if let Err(e) = self.tracer_provider.shutdown() {
+            tracing::warn!("OpenTelemetry tracer shutdown failed: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            tracing::warn!("OpenTelemetry meter shutdown failed: {}", e);
+        }
+    }
+}
+
+/// 创建导出管道，之后客户端的指标写入该管道 (重复调用时替换)
+pub fn init(config: &OtelConfig) -> Result<OtelGuard> {
+    let config = config.clone();
+    // 阻塞 HTTP 客户端不能在异步运行时线程中创建
+    let guard = std::thread::spawn(move || build(&config))
+        .join()
+        .map_err(|_| Mt4Error::InvalidParams("创建 OTLP 导出器的线程异常退出".to_string()))??;
+    if let Ok(mut instruments) = INSTRUMENTS.write() {
+        *instruments = Some(Instruments::new(&guard.meter_provider.meter(INSTRUMENTATION_NAME)));
+    }
+    Ok(guard)
+}
+
+fn build(config: &OtelConfig) -> Result<OtelGuard> {
+    let exporter_error = |e: opentelemetry_otlp::ExporterBuildError| {
+        Mt4Error::InvalidParams(format!("创建 OTLP 导出器失败: {}", e))
+    };
+    let resource = Resource::builder().with_service_name(config.service_name.clone()).build();
+
+    let span_exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(config.signal_endpoint("traces"))
+        .build()
+        .map_err(exporter_error)?;
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(span_exporter)
+        .with_resource(resource.clone())
+        .build();
+
+    let metric_exporter = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(config.signal_endpoint("metrics"))
+        .build()
+        .map_err(exporter_error)?;
+    let reader = PeriodicReader::builder(metric_exporter).with_interval(config.metric_interval).build();
+    let meter_provider = SdkMeterProvider::builder().with_reader(reader).with_resource(resource).build();
+
+    Ok(OtelGuard { tracer_provider, meter_provider })
+}
+
+/// 客户端指标 (与 `telemetry` 模块的 metrics 指标同名)
+pub(crate) struct Instruments {
+    pub(crate) connected: Gauge<i64>,
+    pub(crate) frames_received: Counter<u64>,
+    pub(crate) decrypt_errors: Counter<u64>,
+    pub(crate) events: Counter<u64>,
+    pub(crate) trade_requests: Counter<u64>,
+    pub(crate) trade_responses: Counter<u64>,
+    pub(crate) trade_timeouts: Counter<u64>,
+    pub(crate) trade_latency: Histogram<f64>,
+}
+
+impl Instruments {
+    fn new(meter: &Meter) -> Self {
+        Self {
+            connected: meter.i64_gauge("mt4_connected").build(),
+            frames_received: meter.u64_counter("mt4_frames_received_total").build(),
+            decrypt_errors: meter.u64_counter("mt4_decrypt_errors_total").build(),
+            events: meter.u64_counter("mt4_events_total").build(),
+            trade_requests: meter.u64_counter("mt4_trade_requests_total").build(),
+            trade_responses: meter.u64_counter("mt4_trade_responses_total").build(),
+            trade_timeouts: meter.u64_counter("mt4_trade_timeouts_total").build(),
+            trade_latency: meter.f64_histogram("mt4_trade_latency_seconds").with_unit("s").build(),
+        }
+    }
+}
+
+static INSTRUMENTS: RwLock<Option<Instruments>> = RwLock::new(None);
+
+/// 在已初始化的管道上记录指标 (未初始化时为空操作)
+pub(crate) fn record(f: impl FnOnce(&Instruments)) {
+    if let Ok(instruments) = INSTRUMENTS.read() {
+        if let Some(instruments) = instruments.as_ref() {
+            f(instruments);
+        }
+    }
+}
+
+/// 指标标签
+pub(crate) fn label(key: &'static str, value: impl Into<opentelemetry::Value>) -> [KeyValue; 1] {
+    [KeyValue::new(key, value)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_pipeline_lifecycle() {
+        let config = OtelConfig::new("http://127.0.0.1:9/").with_metric_interval(Duration::from_secs(3600));
+        assert_eq!(config.signal_endpoint("traces"), "http://127.0.0.1:9/v1/traces");
+
+        let guard = init(&config).unwrap();
+        let mut recorded = false;
+        record(|_| recorded = true);
+        assert!(recorded);
+        crate::telemetry::trade_request(66);
+        crate::telemetry::trade_response(true, Some(Duration::from_millis(80)));
+
+        let subscriber = tracing_subscriber::registry().with(guard.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("mt4.trade", trade_type = 66).entered();
+        });
+
+        // 收集器不可达时关闭只记录警告；关闭后指标记录为空操作
+        drop(guard);
+        let mut recorded = false;
+        record(|_| recorded = true);
+        assert!(!recorded);
+    }
+}
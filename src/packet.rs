@@ -0,0 +1,270 @@
+//! 数据包编解码
+//!
+//! WebSocket 连接和浏览器 (`wasm` 模块) 共用这里的编解码 (本地终端桥接使用 JSON 消息，见 `bridge` 模块):
+//!
+//! ```text
+//! [u32 密文长度][u32 1][AES-256-CBC 密文]
+//! 密文解密后: [u16 数据包 ID][u16 命令][数据]           (客户端 -> 服务器)
+//!             [2 字节][u16 命令][u8 错误码][数据]         (服务器 -> 客户端)
+//! ```
+//!
+//! 网页端在前两个字节填入随机数，服务器不校验。客户端改为填入递增的数据包 ID
+//! (取自 `RequestTracker` 的 request_id 计数器)，日志中的发送记录可以与请求对应
+//!
+//! 一个 WebSocket 二进制帧可能包含多个数据包，一个数据包也可能跨越多个帧，
+//! 接收端用 `PacketFramer` 按长度字段切分和重组
+
+use crate::crypto::CryptoProvider;
+use crate::error::{Mt4Error, Result};
+use crate::protocol::AUTH_DATA_SIZE;
+use crate::types::{Symbol, SYMBOL_MAX_LEN};
+use byteorder::{LittleEndian, WriteBytesExt};
+use bytes::{Bytes, BytesMut};
+use std::io::Cursor;
+use zeroize::Zeroizing;
+
+/// 数据包头长度
+pub const PACKET_HEADER_SIZE: usize = 8;
+
+/// 单个数据包密文的最大长度，超过时视为数据流错位
+pub const MAX_PACKET_SIZE: usize = 16 * 1024 * 1024;
+
+/// 构建数据包 (认证 token 使用预设认证密钥加密，其他命令使用会话密钥)
+///
+/// `packet_id` 以小端写入负载的前两个字节
+pub fn build_packet(
+    packet_id: u16,
+    command: u16,
+    data: &[u8],
+    crypto: &dyn CryptoProvider,
+    use_auth_key: bool,
+) -> Result<Vec<u8>> {
+    // 4字节头 + 数据
+    let mut payload = vec![0u8; 4 + data.len()];
+    payload[..2].copy_from_slice(&packet_id.to_le_bytes());
+    payload[2] = (command & 0xFF) as u8;
+    payload[3] = (command >> 8) as u8;
+    payload[4..].copy_from_slice(data);
+
+    // 加密
+    let encrypted = crypto.encrypt(&payload, use_auth_key)?;
+
+    // 8字节头 + 加密数据
+    let mut packet = vec![0u8; PACKET_HEADER_SIZE + encrypted.len()];
+    let mut cursor = Cursor::new(&mut packet[..]);
+    cursor.write_u32::<LittleEndian>(encrypted.len() as u32).unwrap();
+    cursor.write_u32::<LittleEndian>(1).unwrap();
+    packet[PACKET_HEADER_SIZE..].copy_from_slice(&encrypted);
+
+    Ok(packet)
+}
+
+/// 待发送的明文命令帧
+///
+/// 客户端各处发出的请求经同一个通道交给连接的写入任务，由写入任务按传输方式编码:
+/// WebSocket 连接加密为数据包 (`encode`)，终端桥接直接转为 JSON 消息。
+/// 数据可能包含密码，释放时清零
+#[derive(Debug)]
+pub(crate) struct OutboundFrame {
+    /// 数据包 ID
+    pub packet_id: u16,
+    /// 命令
+    pub command: u16,
+    /// 命令数据
+    pub data: Zeroizing<Vec<u8>>,
+    /// 使用预设认证密钥加密 (仅认证 token)
+    pub use_auth_key: bool,
+}
+
+impl OutboundFrame {
+    /// 使用会话密钥加密的命令帧
+    pub(crate) fn new(packet_id: u16, command: u16, data: impl Into<Vec<u8>>) -> Self {
+        Self { packet_id, command, data: Zeroizing::new(data.into()), use_auth_key: false }
+    }
+
+    /// 加密为 Web 协议数据包
+    pub(crate) fn encode(&self, crypto: &dyn CryptoProvider) -> Result<Vec<u8>> {
+        build_packet(self.packet_id, self.command, &self.data, crypto, self.use_auth_key)
+    }
+}
+
+/// 解密服务器发来的数据包，返回 (命令, 错误码, 数据)
+///
+/// 数据包或解密后的负载过短时返回 `Ok(None)`，解密失败时返回错误。
+/// 数据以切片共享解密缓冲区，之后的处理不再复制
+pub fn decode_packet(packet: &[u8], crypto: &dyn CryptoProvider) -> Result<Option<(u16, u8, Bytes)>> {
+    if packet.len() < PACKET_HEADER_SIZE {
+        return Ok(None);
+    }
+    let decrypted = crypto.decrypt(&packet[PACKET_HEADER_SIZE..])?;
+    if decrypted.len() < 5 {
+        return Ok(None);
+    }
+    let decrypted = Bytes::from(decrypted);
+    let command = u16::from_le_bytes([decrypted[2], decrypted[3]]);
+    Ok(Some((command, decrypted[4], decrypted.slice(5..))))
+}
+
+/// 数据包分帧器
+///
+/// 缓存收到的字节，按包头中的密文长度切出完整的数据包
+#[derive(Debug, Default)]
+pub struct PacketFramer {
+    buffer: BytesMut,
+}
+
+impl PacketFramer {
+    /// 创建分帧器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加收到的字节
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// 取出下一个完整的数据包 (包含 8 字节头)，数据不足时返回 `Ok(None)`
+    ///
+    /// 数据包与缓存共享内存，不复制；长度字段超过 `MAX_PACKET_SIZE` 时丢弃缓存的数据并返回错误
+    pub fn next_packet(&mut self) -> Result<Option<Bytes>> {
+        if self.buffer.len() < PACKET_HEADER_SIZE {
+            return Ok(None);
+        }
+        let len = u32::from_le_bytes([self.buffer[0], self.buffer[1], self.buffer[2], self.buffer[3]]) as usize;
+        if len > MAX_PACKET_SIZE {
+            let buffered = self.buffer.len();
+            self.buffer.clear();
+            return Err(Mt4Error::Protocol(format!("数据包长度 {} 超过上限，丢弃 {} 字节", len, buffered)));
+        }
+        if self.buffer.len() < PACKET_HEADER_SIZE + len {
+            return Ok(None);
+        }
+        Ok(Some(self.buffer.split_to(PACKET_HEADER_SIZE + len).freeze()))
+    }
+
+    /// 缓存中尚未组成完整数据包的字节数
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+/// 编码认证 token (64字节 ASCII，超长截断)
+pub fn encode_token(token: &str) -> Vec<u8> {
+    let mut buffer = vec![0u8; AUTH_DATA_SIZE];
+    let bytes = token.as_bytes();
+    let len = bytes.len().min(AUTH_DATA_SIZE);
+    buffer[..len].copy_from_slice(&bytes[..len]);
+    buffer
+}
+
+/// 编码密码 (64字节 UTF-16 LE)
+pub fn encode_password(password: &str) -> Vec<u8> {
+    let mut buffer = vec![0u8; AUTH_DATA_SIZE];
+    for (i, c) in password.chars().take(32).enumerate() {
+        let code = c as u16;
+        buffer[i * 2] = (code & 0xFF) as u8;
+        buffer[i * 2 + 1] = (code >> 8) as u8;
+    }
+    buffer
+}
+
+/// 编码市场报价变更 (Command 26)
+///
+/// 布局按 Web Terminal 显示/隐藏品种时发出的数据推断: `[u8 1 显示 / 0 隐藏][12 字节品种名 × N]`
+pub fn encode_market_watch(show: bool, symbols: &[Symbol]) -> Vec<u8> {
+    let mut buffer = vec![0u8; 1 + symbols.len() * SYMBOL_MAX_LEN];
+    buffer[0] = show as u8;
+    for (i, symbol) in symbols.iter().enumerate() {
+        let offset = 1 + i * SYMBOL_MAX_LEN;
+        buffer[offset..offset + symbol.len()].copy_from_slice(symbol.as_bytes());
+    }
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Mt4Crypto;
+    use proptest::prelude::*;
+
+    fn session_crypto() -> Mt4Crypto {
+        let mut crypto = Mt4Crypto::new().unwrap();
+        crypto.set_session_key("1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef").unwrap();
+        crypto
+    }
+
+    #[test]
+    fn test_framer_rejects_oversized() {
+        let mut framer = PacketFramer::new();
+        framer.extend(&[0xFF, 0xFF, 0xFF, 0xFF, 1, 0, 0, 0, 9]);
+        assert!(framer.next_packet().is_err());
+        assert_eq!(framer.buffered(), 0);
+    }
+
+    #[test]
+    fn test_encode_market_watch() {
+        let symbols = [Symbol::new("EURUSD").unwrap(), Symbol::new("XAUUSD.pro").unwrap()];
+        let data = encode_market_watch(true, &symbols);
+        assert_eq!(data.len(), 1 + 2 * SYMBOL_MAX_LEN);
+        assert_eq!(data[0], 1);
+        assert_eq!(&data[1..13], b"EURUSD\0\0\0\0\0\0");
+        assert_eq!(Symbol::from_wire(&data[13..25]).as_str(), "XAUUSD.pro");
+        assert_eq!(encode_market_watch(false, &[]), vec![0]);
+    }
+
+    proptest! {
+        /// 任意字节 (截断、长度字段不符、非块对齐的密文) 不会 panic
+        #[test]
+        fn prop_decode_never_panics(packet in proptest::collection::vec(any::<u8>(), 0..512)) {
+            let _ = decode_packet(&packet, &session_crypto());
+        }
+
+        /// 编码后再解码得到原命令，数据首字节按服务器格式作为错误码
+        #[test]
+        fn prop_packet_round_trip(
+            packet_id in any::<u16>(),
+            command in any::<u16>(),
+            data in proptest::collection::vec(any::<u8>(), 1..512),
+        ) {
+            let crypto = session_crypto();
+            let packet = build_packet(packet_id, command, &data, &crypto, false).unwrap();
+            let payload = crypto.decrypt(&packet[PACKET_HEADER_SIZE..]).unwrap();
+            prop_assert_eq!(u16::from_le_bytes([payload[0], payload[1]]), packet_id);
+            prop_assert_eq!(
+                u32::from_le_bytes(packet[..4].try_into().unwrap()) as usize,
+                packet.len() - PACKET_HEADER_SIZE
+            );
+            let (decoded, error_code, rest) = decode_packet(&packet, &crypto).unwrap().unwrap();
+            prop_assert_eq!(decoded, command);
+            prop_assert_eq!(error_code, data[0]);
+            prop_assert_eq!(&rest[..], &data[1..]);
+        }
+
+        /// 多个数据包拼接后按任意位置切成多个帧，分帧器还原出原数据包
+        #[test]
+        fn prop_framer_reassembles(
+            lens in proptest::collection::vec(0usize..64, 1..8),
+            cuts in proptest::collection::vec(any::<prop::sample::Index>(), 0..8),
+        ) {
+            let crypto = session_crypto();
+            let packets: Vec<Vec<u8>> =
+                lens.iter().map(|&len| build_packet(0, len as u16, &vec![len as u8; len], &crypto, false).unwrap()).collect();
+            let stream = packets.concat();
+            let mut cuts: Vec<usize> = cuts.iter().map(|cut| cut.index(stream.len() + 1)).collect();
+            cuts.extend([0, stream.len()]);
+            cuts.sort_unstable();
+
+            let mut framer = PacketFramer::new();
+            let mut framed = Vec::new();
+            for range in cuts.windows(2) {
+                framer.extend(&stream[range[0]..range[1]]);
+                while let Some(packet) = framer.next_packet().unwrap() {
+                    framed.push(packet);
+                }
+            }
+            prop_assert_eq!(framed, packets);
+            prop_assert_eq!(framer.buffered(), 0);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! 按命令统计的入站流量
+//!
+//! `Mt4Client::stats()` 返回客户端创建以来按命令号累计的消息数、字节数和解析失败次数，以及解密失败次数。
+//! 不同经纪商会发送文档以外的命令，统计结果可以确认某个服务器实际发送了哪些命令:
+//!
+//! ```no_run
+//! # fn example(client: &mt4_client::Mt4Client) {
+//! for (command, stats) in &client.stats().commands {
+//!     println!("cmd {:>3}: {} msg, {} bytes", command, stats.messages, stats.bytes);
+//! }
+//! # }
+//! ```
+//!
+//! 统计包含重连、回放和终端桥接收到的帧；字节数为解密后的消息数据长度，不含包头。
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 单个命令的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CommandStats {
+    /// 收到的消息数
+    pub messages: u64,
+    /// 消息数据总字节数
+    pub bytes: u64,
+    /// 数据无法解析的次数
+    pub parse_failures: u64,
+}
+
+/// 入站流量统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PacketStats {
+    /// 按命令号统计
+    pub commands: BTreeMap<u16, CommandStats>,
+    /// 解密或分包失败的次数 (无法得知命令号)
+    pub decrypt_failures: u64,
+}
+
+impl PacketStats {
+    /// 创建空统计
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一条解密后的消息
+    pub fn record_message(&mut self, command: u16, bytes: usize) {
+        let stats = self.commands.entry(command).or_default();
+        stats.messages += 1;
+        stats.bytes += bytes as u64;
+    }
+
+    /// 记录一次解析失败
+    pub fn record_parse_failure(&mut self, command: u16) {
+        self.commands.entry(command).or_default().parse_failures += 1;
+    }
+
+    /// 记录一次解密失败
+    pub fn record_decrypt_failure(&mut self) {
+        self.decrypt_failures += 1;
+    }
+
+    /// 某个命令的统计 (未收到过时为零)
+    pub fn command(&self, command: u16) -> CommandStats {
+        self.commands.get(&command).copied().unwrap_or_default()
+    }
+
+    /// 消息总数
+    pub fn total_messages(&self) -> u64 {
+        self.commands.values().map(|s| s.messages).sum()
+    }
+
+    /// 消息数据总字节数
+    pub fn total_bytes(&self) -> u64 {
+        self.commands.values().map(|s| s.bytes).sum()
+    }
+
+    /// 解析失败总次数
+    pub fn parse_failures(&self) -> u64 {
+        self.commands.values().map(|s| s.parse_failures).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_stats() {
+        let mut stats = PacketStats::new();
+        stats.record_message(8, 32);
+        stats.record_message(8, 64);
+        stats.record_message(10, 100);
+        stats.record_parse_failure(10);
+        stats.record_decrypt_failure();
+
+        assert_eq!(stats.command(8), CommandStats { messages: 2, bytes: 96, parse_failures: 0 });
+        assert_eq!(stats.command(99), CommandStats::default());
+        assert_eq!((stats.total_messages(), stats.total_bytes(), stats.parse_failures()), (3, 196, 1));
+        assert_eq!(stats.commands.keys().copied().collect::<Vec<_>>(), [8, 10]);
+        assert_eq!(stats.decrypt_failures, 1);
+    }
+}
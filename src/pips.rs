@@ -0,0 +1,139 @@
+//! 点 (pip) 与点值换算
+//!
+//! 各处统一按品种规格换算止损/止盈距离和盈亏估算:
+//!
+//! - `pips_to_price` / `price_to_pips`: pip 数与价格差互换 (5/3 位报价 1 pip = 10 点，见 `pip_size`)
+//! - `point_value_in_account_currency`: 价格变动 1 点时 1 手的盈亏，换算为账户货币
+//!
+//! 品种名前 6 个字符为大写字母时视为货币对 (`EURUSD`、`XAUUSD`、`GBPJPY.m` 等)，按报价货币
+//! 与账户货币之间的实时汇率换算；其他品种 (指数、差价合约) 按 `SymbolInfo::tick_value` 的约定视为
+//! 以账户货币计价。汇率取自 `CrossRates` (客户端收到报价时自动更新，见 `Mt4Client::point_value()`)，
+//! 没有直接的货币对时经 USD 交叉换算。
+//!
+//! ```
+//! use mt4_client::{point_value_in_account_currency, pips_to_price, CrossRates, Quote, SymbolInfo};
+//!
+//! let spec = SymbolInfo::new("USDJPY", 3);
+//! assert!((pips_to_price(&spec, 20.0) - 0.2).abs() < 1e-9);
+//!
+//! // 账户货币 USD: 1 点 (0.001 JPY) × 100000 = 100 JPY = 100 / 150 USD
+//! let mut rates = CrossRates::new();
+//! rates.update(&Quote { symbol: "USDJPY".to_string(), bid: 149.99, ask: 150.01, time: 0 });
+//! let value = point_value_in_account_currency(&spec, "USD", &rates).unwrap();
+//! assert!((value - 100.0 / 150.0).abs() < 1e-9);
+//! ```
+
+use crate::book::pip_size;
+use crate::types::{Quote, SymbolInfo};
+use std::collections::HashMap;
+
+/// pip 数换算为价格差
+pub fn pips_to_price(spec: &SymbolInfo, pips: f64) -> f64 {
+    pips * pip_size(spec.digits)
+}
+
+/// 价格差换算为 pip 数
+pub fn price_to_pips(spec: &SymbolInfo, price: f64) -> f64 {
+    price / pip_size(spec.digits)
+}
+
+/// 价格变动 1 点时 1 手的盈亏 (账户货币)，缺少换算汇率时返回 None
+pub fn point_value_in_account_currency(spec: &SymbolInfo, account_currency: &str, rates: &CrossRates) -> Option<f64> {
+    match currency_pair(&spec.symbol) {
+        Some((_, quote)) => rates.convert(spec.tick_value(), quote, account_currency),
+        None => Some(spec.tick_value()),
+    }
+}
+
+/// 品种名对应的 (基础货币, 报价货币)，不是货币对时返回 None
+pub fn currency_pair(symbol: &str) -> Option<(&str, &str)> {
+    let pair = symbol.get(..6)?;
+    pair.bytes().all(|b| b.is_ascii_uppercase()).then(|| (&pair[..3], &pair[3..]))
+}
+
+/// 实时汇率表 (按品种保存最近一次报价)
+#[derive(Debug, Clone, Default)]
+pub struct CrossRates {
+    /// 品种 -> (bid, ask)
+    prices: HashMap<String, (f64, f64)>,
+}
+
+impl CrossRates {
+    /// 创建空的汇率表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一条报价
+    pub fn update(&mut self, quote: &Quote) {
+        self.prices.insert(quote.symbol.clone(), (quote.bid, quote.ask));
+    }
+
+    /// 品种最近一次报价的 (bid, ask)
+    pub fn price(&self, symbol: &str) -> Option<(f64, f64)> {
+        self.prices.get(symbol).copied()
+    }
+
+    /// 品种最近一次报价的中间价
+    pub fn mid(&self, symbol: &str) -> Option<f64> {
+        self.prices.get(symbol).map(|(bid, ask)| (bid + ask) / 2.0)
+    }
+
+    /// 1 单位 `base` 以 `quote` 计的价格 (中间价)，直接或反向货币对都没有报价时返回 None
+    ///
+    /// 优先使用名称完全相同的品种，其次使用带后缀的品种 (如 `EURUSD.m`)
+    pub fn rate(&self, base: &str, quote: &str) -> Option<f64> {
+        if base == quote {
+            return Some(1.0);
+        }
+        let find = |base: &str, quote: &str| {
+            self.mid(&format!("{}{}", base, quote)).or_else(|| {
+                let mut symbols: Vec<&String> =
+                    self.prices.keys().filter(|s| currency_pair(s) == Some((base, quote))).collect();
+                symbols.sort();
+                symbols.first().and_then(|s| self.mid(s))
+            })
+        };
+        let rate = find(base, quote).or_else(|| find(quote, base).map(|inverse| 1.0 / inverse))?;
+        (rate.is_finite() && rate > 0.0).then_some(rate)
+    }
+
+    /// 金额从 `from` 货币换算为 `to` 货币 (没有直接汇率时经 USD 交叉换算)
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Option<f64> {
+        let rate = self.rate(from, to).or_else(|| Some(self.rate(from, "USD")? * self.rate("USD", to)?))?;
+        Some(amount * rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(symbol: &str, mid: f64) -> Quote {
+        Quote { symbol: symbol.to_string(), bid: mid, ask: mid, time: 0 }
+    }
+
+    #[test]
+    fn test_point_value_conversion() {
+        let mut rates = CrossRates::new();
+        rates.update(&quote("EURUSD.m", 1.25));
+        rates.update(&quote("USDJPY", 160.0));
+
+        let eurusd = SymbolInfo::new("EURUSD", 5);
+        assert!((price_to_pips(&eurusd, 0.0025) - 25.0).abs() < 1e-9);
+        // 报价货币即账户货币: 1 点 × 100000 = 1 USD
+        assert!((point_value_in_account_currency(&eurusd, "USD", &rates).unwrap() - 1.0).abs() < 1e-9);
+        // 账户货币 EUR: 经带后缀的 EURUSD.m 反向换算
+        assert!((point_value_in_account_currency(&eurusd, "EUR", &rates).unwrap() - 0.8).abs() < 1e-9);
+
+        // 账户货币 EUR、报价货币 JPY: 没有 EURJPY 时经 USD 交叉换算
+        let gbpjpy = SymbolInfo::new("GBPJPY", 3);
+        let value = point_value_in_account_currency(&gbpjpy, "EUR", &rates).unwrap();
+        assert!((value - 100.0 / 160.0 / 1.25).abs() < 1e-9);
+        assert!(point_value_in_account_currency(&gbpjpy, "CHF", &rates).is_none());
+
+        // 非货币对按账户货币计价
+        let index = SymbolInfo { contract_size: 1.0, ..SymbolInfo::new("US30", 1) };
+        assert!((point_value_in_account_currency(&index, "CHF", &rates).unwrap() - 0.1).abs() < 1e-12);
+    }
+}
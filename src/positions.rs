@@ -0,0 +1,275 @@
+//! 持仓与挂单本地状态
+//!
+//! `PositionManager` 由读取任务维护:
+//! - Command 4 持仓快照: 整体替换 (快照中没有的订单视为已不存在)
+//! - Command 10 订单更新: 新建/修改写入，平仓/删除移除
+//! - Command 12 交易响应: 响应中携带的订单同样写入或移除
+//!
+//! 因此 `positions()` / `pending_orders()` 随时反映服务器端的当前状态。漏收推送时缓存可能过期，
+//! `reconcile()` 用服务器重新发送的快照修正缓存并返回不一致之处 (见 `reconcile` 模块)。
+//!
+//! 订单中的 `profit` 只在服务器推送订单时更新。客户端每收到一条报价就用 `revalue()` 按
+//! 最新价格重新估值该品种的持仓 (多单按 bid、空单按 ask 平仓计算)，`unrealized_pnl()` /
+//! `total_unrealized_pnl()` 返回估值结果；尚未估值的持仓 (或订单刚被服务器更新) 使用 `profit`。
+//! 客户端只估值已设置品种规格的品种，其余品种始终使用服务器推送的 `profit`。
+
+use crate::book::PendingBook;
+use crate::reconcile::{diff_orders, StateDivergence};
+use crate::types::{Order, OrderUpdate, Quote, Ticket, TradeResponse};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::RwLock;
+
+/// 持仓与挂单管理器
+#[derive(Debug, Default)]
+pub struct PositionManager {
+    /// ticket -> Order
+    orders: RwLock<HashMap<Ticket, Order>>,
+    /// ticket -> 按最新报价估值的盈亏 (账户货币，不含库存费和佣金)
+    live_profit: RwLock<HashMap<Ticket, f64>>,
+    /// 是否已应用过持仓快照
+    synced: AtomicBool,
+}
+
+impl PositionManager {
+    /// 创建空的管理器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 应用持仓快照 (Command 4)
+    pub async fn apply_snapshot(&self, orders: &[Order]) {
+        let mut cache = self.orders.write().await;
+        cache.clear();
+        self.live_profit.write().await.clear();
+        for order in orders.iter().filter(|o| !is_order_closed(o)) {
+            cache.insert(order.ticket, order.clone());
+        }
+        self.synced.store(true, Ordering::SeqCst);
+    }
+
+    /// 用持仓快照 (Command 4) 修正缓存，返回修正前缓存与快照的不一致之处
+    ///
+    /// 尚未应用过快照时 (缓存还没有初始化) 只应用快照，返回空列表
+    pub async fn reconcile(&self, orders: &[Order]) -> Vec<StateDivergence> {
+        let open: Vec<Order> = orders.iter().filter(|o| !is_order_closed(o)).cloned().collect();
+        let mut cache = self.orders.write().await;
+        let divergences =
+            if self.synced.swap(true, Ordering::SeqCst) { diff_orders(cache.values(), &open) } else { Vec::new() };
+        self.live_profit.write().await.clear();
+        *cache = open.into_iter().map(|o| (o.ticket, o)).collect();
+        divergences
+    }
+
+    /// 应用订单更新 (Command 10)
+    pub async fn apply_updates(&self, updates: &[OrderUpdate]) {
+        let mut cache = self.orders.write().await;
+        let mut live = self.live_profit.write().await;
+        for update in updates {
+            live.remove(&update.order.ticket);
+            if update.is_close_notification() || is_order_closed(&update.order) {
+                cache.remove(&update.order.ticket);
+            } else {
+                cache.insert(update.order.ticket, update.order.clone());
+            }
+        }
+    }
+
+    /// 应用交易响应中携带的订单 (Command 12)
+    pub async fn apply_trade_response(&self, response: &TradeResponse) {
+        if response.status >= 2 || response.orders.is_empty() {
+            return;
+        }
+        let mut cache = self.orders.write().await;
+        let mut live = self.live_profit.write().await;
+        for order in &response.orders {
+            live.remove(&order.ticket);
+            if is_order_closed(order) {
+                cache.remove(&order.ticket);
+            } else {
+                cache.insert(order.ticket, order.clone());
+            }
+        }
+    }
+
+    /// 当前持仓 (市价单)，按 ticket 排序
+    pub async fn positions(&self) -> Vec<Order> {
+        self.filtered(|o| !o.is_pending()).await
+    }
+
+    /// 当前挂单，按 ticket 排序
+    pub async fn pending_orders(&self) -> Vec<Order> {
+        self.filtered(|o| o.is_pending()).await
+    }
+
+    /// 所有持仓和挂单，按 ticket 排序
+    pub async fn all(&self) -> Vec<Order> {
+        self.filtered(|_| true).await
+    }
+
+    /// 指定品种的持仓 (市价单)
+    pub async fn positions_for_symbol(&self, symbol: &str) -> Vec<Order> {
+        self.filtered(|o| !o.is_pending() && o.symbol == symbol).await
+    }
+
+    /// 当前挂单按品种和价格排序的快照
+    pub async fn pending_book(&self) -> PendingBook {
+        PendingBook::from_orders(self.orders.read().await.values())
+    }
+
+    /// 获取指定订单
+    pub async fn get(&self, ticket: Ticket) -> Option<Order> {
+        self.orders.read().await.get(&ticket).cloned()
+    }
+
+    /// 持仓和挂单总数
+    pub async fn len(&self) -> usize {
+        self.orders.read().await.len()
+    }
+
+    /// 是否没有任何持仓和挂单
+    pub async fn is_empty(&self) -> bool {
+        self.orders.read().await.is_empty()
+    }
+
+    /// 清空
+    pub async fn clear(&self) {
+        self.orders.write().await.clear();
+        self.live_profit.write().await.clear();
+        self.synced.store(false, Ordering::SeqCst);
+    }
+
+    /// 按报价重新估值该品种的持仓，返回估值的持仓数
+    ///
+    /// `contract_size` 为 1 手的合约数量，`to_account` 为报价货币换算为账户货币的汇率
+    pub async fn revalue(&self, quote: &Quote, contract_size: f64, to_account: f64) -> usize {
+        let orders = self.orders.read().await;
+        let mut live = self.live_profit.write().await;
+        let mut revalued = 0;
+        for order in orders.values().filter(|o| !o.is_pending() && o.symbol == quote.symbol) {
+            let profit = price_profit(order, quote.bid, quote.ask) * order.volume * contract_size * to_account;
+            live.insert(order.ticket, (profit * 100.0).round() / 100.0);
+            revalued += 1;
+        }
+        revalued
+    }
+
+    /// 持仓的浮动盈亏 (含库存费和佣金)，不是持仓时返回 None
+    pub async fn unrealized_pnl(&self, ticket: Ticket) -> Option<f64> {
+        let orders = self.orders.read().await;
+        let order = orders.get(&ticket).filter(|o| !o.is_pending())?;
+        let live = self.live_profit.read().await;
+        Some(live.get(&ticket).copied().unwrap_or(order.profit) + order.swap + order.commission)
+    }
+
+    /// 所有持仓的浮动盈亏合计 (含库存费和佣金)
+    pub async fn total_unrealized_pnl(&self) -> f64 {
+        let orders = self.orders.read().await;
+        let live = self.live_profit.read().await;
+        orders
+            .values()
+            .filter(|o| !o.is_pending())
+            .map(|o| live.get(&o.ticket).copied().unwrap_or(o.profit) + o.swap + o.commission)
+            .sum()
+    }
+
+    async fn filtered(&self, predicate: impl Fn(&Order) -> bool) -> Vec<Order> {
+        let mut orders: Vec<Order> = self.orders.read().await.values().filter(|o| predicate(o)).cloned().collect();
+        orders.sort_by_key(|o| o.ticket);
+        orders
+    }
+}
+
+/// 按 bid/ask 平仓时每单位的价格盈亏 (多单按 bid，空单按 ask)
+fn price_profit(order: &Order, bid: f64, ask: f64) -> f64 {
+    if order.order_type.is_buy() {
+        bid - order.open_price
+    } else {
+        order.open_price - ask
+    }
+}
+
+/// 判断订单是否已平仓
+///
+/// 判断逻辑:
+/// 1. close_time > 0 表示已平仓 (最可靠)
+/// 2. close_price > 0 且 != open_price 表示已平仓 (备用)
+pub(crate) fn is_order_closed(order: &Order) -> bool {
+    // 方法1: 有明确的平仓时间
+    if order.close_time > 0 {
+        return true;
+    }
+
+    // 方法2: close_price 有意义且不等于开仓价 (允许浮点误差)
+    if order.close_price > 0.0 && (order.close_price - order.open_price).abs() > 0.00001 {
+        return true;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+
+    fn order(ticket: i32, order_type: OrderType, close_time: i64) -> Order {
+        Order { close_time, ..Order::for_test(ticket, "EURUSD", order_type, 0.1, 1.08) }
+    }
+
+    fn update(notify_type: i32, order: Order) -> OrderUpdate {
+        OrderUpdate { notify_id: 1, notify_type, df: 0.0, xh: 0.0, raw_size: 185, order, related_order: None }
+    }
+
+    #[tokio::test]
+    async fn test_position_manager_sync() {
+        let manager = PositionManager::new();
+        manager
+            .apply_snapshot(&[order(1, OrderType::Buy, 0), order(2, OrderType::SellLimit, 0), order(3, OrderType::Sell, 1)])
+            .await;
+        assert_eq!(manager.positions().await.len(), 1);
+        assert_eq!(manager.pending_orders().await[0].ticket, Ticket(2));
+
+        // 挂单成交为持仓，持仓 1 平仓
+        manager
+            .apply_updates(&[update(1, order(2, OrderType::SellLimit, 0)), update(0, order(4, OrderType::Sell, 0))])
+            .await;
+        manager.apply_updates(&[update(1, order(1, OrderType::Buy, 1_700_000_100))]).await;
+        let tickets: Vec<i32> = manager.all().await.iter().map(|o| o.ticket.get()).collect();
+        assert_eq!(tickets, vec![4]);
+
+        // 交易响应中携带的新订单
+        let response = TradeResponse {
+            request_id: 1,
+            status: 0,
+            price1: 1.08,
+            price2: 1.08,
+            orders: vec![order(5, OrderType::BuyStop, 0)],
+        };
+        manager.apply_trade_response(&response).await;
+        assert_eq!(manager.pending_orders().await.len(), 1);
+        assert_eq!(manager.pending_book().await.nearest_above("EURUSD", 1.0).map(|o| o.ticket), Some(Ticket(5)));
+        assert_eq!(manager.positions_for_symbol("EURUSD").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_revalue_unrealized_pnl() {
+        let manager = PositionManager::new();
+        let mut buy = order(1, OrderType::Buy, 0);
+        (buy.profit, buy.swap) = (-5.0, -1.0);
+        manager.apply_snapshot(&[buy.clone(), order(2, OrderType::Sell, 0), order(3, OrderType::BuyLimit, 0)]).await;
+        // 未估值时使用服务器的 profit
+        assert_eq!(manager.unrealized_pnl(Ticket(1)).await, Some(-6.0));
+        assert_eq!(manager.unrealized_pnl(Ticket(3)).await, None);
+
+        // 0.1 手: 多单 (1.0810 - 1.08) × 10000 = 10，空单 (1.08 - 1.0812) × 10000 = -12
+        let quote = Quote { symbol: "EURUSD".to_string(), bid: 1.0810, ask: 1.0812, time: 0 };
+        assert_eq!(manager.revalue(&quote, 100_000.0, 1.0).await, 2);
+        assert_eq!(manager.unrealized_pnl(Ticket(1)).await, Some(9.0));
+        assert_eq!(manager.total_unrealized_pnl().await, -3.0);
+
+        // 服务器更新订单后回到 profit，直到下一条报价
+        manager.apply_updates(&[update(2, buy)]).await;
+        assert_eq!(manager.unrealized_pnl(Ticket(1)).await, Some(-6.0));
+    }
+}
@@ -0,0 +1,232 @@
+//! 经纪商预设
+//!
+//! 经纪商的默认交易参数 (滑点、对冲/FIFO 模式、最小止损距离、每周交易时段)，按交易服务器名称模式匹配。
+//! 预设需要显式开启: 通过 `Mt4ClientBuilder::broker_preset()` 指定，或用 `auto_broker_preset()`
+//! 让客户端在每次连接时按服务器名称从内置预设中选择。通过 `default_slippage()` 显式设置的滑点始终优先。
+//!
+//! 内置预设只收录有公开依据的差异: 美国经纪商遵循 NFA Compliance Rule 2-43(b) 的 FIFO 规则。
+//! 滑点使用 `DEFAULT_SLIPPAGE`，不限制止损距离 (最小止损距离随账户类型和品种变化，应以经纪商的
+//! 品种规格为准)；可以用 `PresetRegistry::register()` 追加或覆盖:
+//!
+//! ```
+//! use mt4_client::{AccountMode, PresetRegistry};
+//!
+//! let registry = PresetRegistry::builtin();
+//! let preset = registry.find("ICMarketsSC-Demo03").unwrap();
+//! assert_eq!(preset.account_mode, AccountMode::Hedging);
+//! ```
+
+use crate::config::DEFAULT_SLIPPAGE;
+use serde::{Deserialize, Serialize};
+
+/// 每周分钟数
+const MINUTES_PER_WEEK: u32 = 7 * 24 * 60;
+
+/// 账户持仓模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountMode {
+    /// 允许同一品种同时持有多空仓位
+    Hedging,
+    /// 先进先出: 不允许反向对冲，平仓须先平最早的仓位 (美国 NFA 规则)
+    Fifo,
+}
+
+/// 每周交易时段 (服务器时间)
+///
+/// 以周一 00:00 起算的分钟数表示，`close` 小于 `open` 表示跨越周末
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeeklySession {
+    /// 开市时间
+    pub open: u32,
+    /// 收市时间
+    pub close: u32,
+}
+
+impl WeeklySession {
+    /// 以 (星期, 时, 分) 创建，星期 0 为周一
+    pub fn new(open: (u32, u32, u32), close: (u32, u32, u32)) -> Self {
+        let minutes = |(day, hour, minute): (u32, u32, u32)| (day * 24 * 60 + hour * 60 + minute) % MINUTES_PER_WEEK;
+        Self { open: minutes(open), close: minutes(close) }
+    }
+
+    /// 外汇常见时段: 周一 00:05 至周五 23:55
+    pub fn forex() -> Self {
+        Self::new((0, 0, 5), (4, 23, 55))
+    }
+
+    /// 服务器时间 (Unix 时间戳形式，秒) 是否处于交易时段
+    pub fn contains(&self, server_time: i64) -> bool {
+        // 1970-01-01 为周四，偏移 3 天后从周一起算
+        let minute = ((server_time.div_euclid(60) + 3 * 24 * 60).rem_euclid(MINUTES_PER_WEEK as i64)) as u32;
+        if self.open <= self.close {
+            (self.open..self.close).contains(&minute)
+        } else {
+            minute >= self.open || minute < self.close
+        }
+    }
+}
+
+/// 经纪商预设
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BrokerPreset {
+    /// 经纪商名称
+    pub name: String,
+    /// 交易服务器名称模式 (不区分大小写，`*` 匹配任意字符)
+    pub server_patterns: Vec<String>,
+    /// 便捷方法使用的默认滑点 (点)
+    pub default_slippage: i32,
+    /// 持仓模式
+    pub account_mode: AccountMode,
+    /// 止损/止盈与当前价格的最小距离 (点，0 表示不限制)
+    pub stops_level: i32,
+    /// 每周交易时段 (服务器时间)
+    pub session: Option<WeeklySession>,
+}
+
+impl BrokerPreset {
+    /// 以名称和服务器名称模式创建 (滑点 `DEFAULT_SLIPPAGE`，对冲模式，不限制止损距离，外汇时段)
+    pub fn new(name: &str, patterns: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            server_patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            default_slippage: DEFAULT_SLIPPAGE,
+            account_mode: AccountMode::Hedging,
+            stops_level: 0,
+            session: Some(WeeklySession::forex()),
+        }
+    }
+
+    /// 设置默认滑点
+    pub fn with_slippage(mut self, slippage: i32) -> Self {
+        self.default_slippage = slippage;
+        self
+    }
+
+    /// 设置持仓模式
+    pub fn with_account_mode(mut self, mode: AccountMode) -> Self {
+        self.account_mode = mode;
+        self
+    }
+
+    /// 设置最小止损距离
+    pub fn with_stops_level(mut self, points: i32) -> Self {
+        self.stops_level = points;
+        self
+    }
+
+    /// 设置交易时段 (None 表示全天候)
+    pub fn with_session(mut self, session: Option<WeeklySession>) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// 服务器名称是否匹配
+    pub fn matches(&self, server: &str) -> bool {
+        let server = server.to_ascii_lowercase();
+        self.server_patterns.iter().any(|p| wildcard_match(&p.to_ascii_lowercase(), &server))
+    }
+}
+
+/// 经纪商预设登记表 (按登记顺序匹配，后登记的优先)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PresetRegistry {
+    presets: Vec<BrokerPreset>,
+}
+
+impl PresetRegistry {
+    /// 创建空登记表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 内置预设
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(BrokerPreset::new("IC Markets", &["ICMarkets*"]));
+        registry.register(BrokerPreset::new("Pepperstone", &["Pepperstone*"]));
+        registry.register(BrokerPreset::new("XM", &["XM*", "XMGlobal*", "XMTrading*"]));
+        registry.register(BrokerPreset::new("Exness", &["Exness*"]));
+        registry.register(BrokerPreset::new("FxPro", &["FxPro*"]));
+        registry.register(BrokerPreset::new("Admirals", &["Admiral*"]));
+        // NFA Compliance Rule 2-43(b): 美国客户账户不允许对冲，平仓按先进先出
+        registry.register(
+            BrokerPreset::new("FOREX.com US", &["FOREX.com*US*", "GAIN*US*"]).with_account_mode(AccountMode::Fifo),
+        );
+        registry
+    }
+
+    /// 登记预设
+    pub fn register(&mut self, preset: BrokerPreset) {
+        self.presets.push(preset);
+    }
+
+    /// 所有预设
+    pub fn presets(&self) -> &[BrokerPreset] {
+        &self.presets
+    }
+
+    /// 按服务器名称查找预设
+    pub fn find(&self, server: &str) -> Option<&BrokerPreset> {
+        self.presets.iter().rev().find(|p| p.matches(server))
+    }
+}
+
+/// `*` 通配符匹配
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // 没有通配符
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_preset() {
+        let mut registry = PresetRegistry::builtin();
+        assert_eq!(registry.find("ICMarketsSC-Demo03").unwrap().name, "IC Markets");
+        assert_eq!(registry.find("pepperstone-edge05").unwrap().name, "Pepperstone");
+        assert_eq!(registry.find("FOREX.com-US-Live 5").unwrap().account_mode, AccountMode::Fifo);
+        assert!(registry.find("Unknown-Demo").is_none());
+
+        // 后登记的预设优先
+        registry.register(BrokerPreset::new("IC Markets Raw", &["ICMarketsSC-Live*"]).with_slippage(10));
+        assert_eq!(registry.find("ICMarketsSC-Live07").unwrap().default_slippage, 10);
+        assert_eq!(registry.find("ICMarketsSC-Demo03").unwrap().default_slippage, DEFAULT_SLIPPAGE);
+
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("a*b*c", "axxcyyb"));
+        assert!(!wildcard_match("abc", "abcd"));
+    }
+
+    #[test]
+    fn test_weekly_session() {
+        let session = WeeklySession::forex();
+        // 2024-01-01 为周一
+        let monday = 1_704_067_200;
+        assert!(!session.contains(monday));
+        assert!(session.contains(monday + 10 * 60));
+        assert!(session.contains(monday + 4 * 86_400 + 23 * 3600));
+        assert!(!session.contains(monday + 5 * 86_400));
+
+        // 跨越周末的时段
+        let weekend = WeeklySession::new((4, 22, 0), (0, 2, 0));
+        assert!(weekend.contains(monday + 5 * 86_400));
+        assert!(!weekend.contains(monday + 3 * 3600));
+    }
+}
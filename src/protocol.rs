@@ -36,7 +36,7 @@ pub enum Command {
     TradeRequest = 12,
     /// 平仓请求
     CloseOrder = 13,
-    /// 连接状态
+    /// 连接状态 (数据布局未确认，解析需要 `experimental-status` 特性，否则作为 `RawMessage` 发出)
     ConnectionStatus = 15,
     /// 修改订单
     ModifyOrder = 16,
@@ -52,6 +52,12 @@ pub enum Command {
     Ping = 51,
 }
 
+impl From<Command> for u16 {
+    fn from(command: Command) -> Self {
+        command as u16
+    }
+}
+
 impl Command {
     /// 从 u16 创建命令
     pub fn from_u16(value: u16) -> Option<Self> {
@@ -106,6 +112,11 @@ impl OrderType {
         }
     }
 
+    /// 是否为买方向 (Buy / BuyLimit / BuyStop)
+    pub fn is_buy(&self) -> bool {
+        matches!(self, OrderType::Buy | OrderType::BuyLimit | OrderType::BuyStop)
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             OrderType::Buy => "BUY",
@@ -118,6 +129,49 @@ impl OrderType {
     }
 }
 
+/// K线周期 (Command 11 中以分钟数表示)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[repr(i32)]
+pub enum Timeframe {
+    M1 = 1,
+    M5 = 5,
+    M15 = 15,
+    M30 = 30,
+    H1 = 60,
+    H4 = 240,
+    D1 = 1440,
+    W1 = 10080,
+    MN1 = 43200,
+}
+
+impl Timeframe {
+    /// 周期分钟数
+    pub fn minutes(&self) -> i32 {
+        *self as i32
+    }
+
+    /// 周期秒数
+    pub fn seconds(&self) -> i64 {
+        self.minutes() as i64 * 60
+    }
+
+    /// 从分钟数创建
+    pub fn from_minutes(minutes: i32) -> Option<Self> {
+        match minutes {
+            1 => Some(Timeframe::M1),
+            5 => Some(Timeframe::M5),
+            15 => Some(Timeframe::M15),
+            30 => Some(Timeframe::M30),
+            60 => Some(Timeframe::H1),
+            240 => Some(Timeframe::H4),
+            1440 => Some(Timeframe::D1),
+            10080 => Some(Timeframe::W1),
+            43200 => Some(Timeframe::MN1),
+            _ => None,
+        }
+    }
+}
+
 /// 交易请求类型 (type)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -161,5 +215,14 @@ pub const ORDER_DATA_SIZE: usize = 161;
 /// 订单更新通知大小 (185字节)
 pub const ORDER_UPDATE_SIZE: usize = 185;
 
+/// K线请求大小 (24字节)
+pub const CHART_REQUEST_SIZE: usize = 24;
+
+/// 单根K线数据大小 (44字节)
+pub const CANDLE_SIZE: usize = 44;
+
+/// 报价快照记录大小 (32字节)
+pub const QUOTE_SIZE: usize = 32;
+
 /// Token/Password 大小 (64字节)
 pub const AUTH_DATA_SIZE: usize = 64;
@@ -0,0 +1,177 @@
+//! 经纪商差异登记表
+//!
+//! 不同经纪商/服务器版本的数据包布局并不完全一致。`QuirkRegistry` 以交易服务器名称为键，
+//! 记录各服务器实测得到的差异，解析时优先使用登记的参数，未登记的服务器仍走默认解析。
+
+use crate::error::{Mt4Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 余额类字段匹配容差 (账户货币，小于 1 分)
+const AMOUNT_TOLERANCE: f64 = 0.005;
+
+/// 账户信息已知值 (用于校准字段偏移)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountCalibration {
+    /// 账号
+    pub login: i32,
+    /// 余额
+    pub balance: f64,
+    /// 净值 (可选，无持仓时通常等于余额)
+    pub equity: Option<f64>,
+}
+
+impl AccountCalibration {
+    /// 以账号和余额创建
+    pub fn new(login: i32, balance: f64) -> Self {
+        Self { login, balance, equity: None }
+    }
+
+    /// 设置净值
+    pub fn with_equity(mut self, equity: f64) -> Self {
+        self.equity = Some(equity);
+        self
+    }
+}
+
+/// 账户信息块 (Command 3) 的字段偏移
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountLayout {
+    /// login (i32) 偏移
+    pub login: Option<usize>,
+    /// balance (f64) 偏移
+    pub balance: Option<usize>,
+    /// equity (f64) 偏移
+    pub equity: Option<usize>,
+}
+
+impl AccountLayout {
+    /// 根据已知账户值在账户信息块中搜索字段偏移
+    ///
+    /// login 与 balance 必须都能找到；equity 搜索时跳过 balance 所在位置，
+    /// 找不到时保持默认解析
+    pub fn discover(data: &[u8], known: &AccountCalibration) -> Result<Self> {
+        let login = find_i32(data, known.login, None).ok_or_else(|| {
+            Mt4Error::Protocol(format!("账户数据中未找到 login {}", known.login))
+        })?;
+        let balance = find_f64(data, known.balance, None).ok_or_else(|| {
+            Mt4Error::Protocol(format!("账户数据中未找到 balance {}", known.balance))
+        })?;
+        let equity = known.equity.and_then(|equity| find_f64(data, equity, Some(balance)));
+        Ok(Self {
+            login: Some(login),
+            balance: Some(balance),
+            equity,
+        })
+    }
+}
+
+/// 单个服务器的差异记录
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BrokerQuirks {
+    /// 校准得到的账户信息字段偏移
+    pub account_layout: Option<AccountLayout>,
+}
+
+/// 经纪商差异登记表: 服务器名称 -> 差异记录
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuirkRegistry {
+    servers: HashMap<String, BrokerQuirks>,
+}
+
+impl QuirkRegistry {
+    /// 创建空登记表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从 JSON 文件加载 (不存在时返回空登记表)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            Mt4Error::Io(format!("读取差异登记表 {} 失败: {}", path.display(), e))
+        })?;
+        serde_json::from_str(&text).map_err(|e| {
+            Mt4Error::Serialization(format!("解析差异登记表 {} 失败: {}", path.display(), e))
+        })
+    }
+
+    /// 保存为 JSON 文件
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Mt4Error::Serialization(format!("序列化差异登记表失败: {}", e)))?;
+        std::fs::write(path, json).map_err(|e| {
+            Mt4Error::Io(format!("写入差异登记表 {} 失败: {}", path.display(), e))
+        })
+    }
+
+    /// 获取服务器的差异记录
+    pub fn get(&self, server: &str) -> Option<&BrokerQuirks> {
+        self.servers.get(server)
+    }
+
+    /// 获取 (不存在时创建) 服务器的差异记录
+    pub fn entry(&mut self, server: &str) -> &mut BrokerQuirks {
+        self.servers.entry(server.to_string()).or_default()
+    }
+
+    /// 服务器的账户信息字段偏移
+    pub fn account_layout(&self, server: &str) -> Option<AccountLayout> {
+        self.get(server)?.account_layout
+    }
+
+    /// 记录服务器的账户信息字段偏移
+    pub fn set_account_layout(&mut self, server: &str, layout: AccountLayout) {
+        self.entry(server).account_layout = Some(layout);
+    }
+}
+
+/// 搜索 i32 值的偏移
+fn find_i32(data: &[u8], value: i32, skip: Option<usize>) -> Option<usize> {
+    let target = value.to_le_bytes();
+    (0..data.len().saturating_sub(3))
+        .filter(|&i| Some(i) != skip)
+        .find(|&i| data[i..i + 4] == target)
+}
+
+/// 搜索 f64 值的偏移 (允许 AMOUNT_TOLERANCE 误差)
+fn find_f64(data: &[u8], value: f64, skip: Option<usize>) -> Option<usize> {
+    (0..data.len().saturating_sub(7))
+        .filter(|&i| Some(i) != skip)
+        .find(|&i| {
+            let v = f64::from_le_bytes(data[i..i + 8].try_into().unwrap());
+            v.is_finite() && (v - value).abs() < AMOUNT_TOLERANCE
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_account_layout() {
+        let mut data = vec![0u8; 254];
+        data[200..204].copy_from_slice(&12345678i32.to_le_bytes());
+        data[100..108].copy_from_slice(&10_000.0f64.to_le_bytes());
+        data[108..116].copy_from_slice(&10_000.0f64.to_le_bytes());
+
+        let known = AccountCalibration::new(12345678, 10_000.0).with_equity(10_000.0);
+        let layout = AccountLayout::discover(&data, &known).unwrap();
+        assert_eq!(layout.login, Some(200));
+        assert_eq!(layout.balance, Some(100));
+        // 余额与净值相等时，净值取余额之外的位置
+        assert_eq!(layout.equity, Some(108));
+
+        assert!(AccountLayout::discover(&data, &AccountCalibration::new(87654321, 10_000.0)).is_err());
+
+        let mut registry = QuirkRegistry::new();
+        registry.set_account_layout("Broker-Demo", layout);
+        assert_eq!(registry.account_layout("Broker-Demo"), Some(layout));
+        assert!(registry.account_layout("Other-Live").is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! 与服务器定期对账
+//!
+//! 本地持仓缓存依赖订单更新推送维护，漏收通知 (见 `sequence` 模块) 或断线期间的变化会使缓存与服务器
+//! 不一致。设置 `Mt4ClientBuilder::reconcile_interval()` 后，客户端每隔该时长重新请求账户信息和
+//! 订单列表 (重连登录时服务器同样会重新发送)，收到订单列表时与本地缓存比较，以服务器数据为准修正缓存，
+//! 并为每处不一致发出 `Mt4Event::StateDivergence`:
+//!
+//! ```no_run
+//! use mt4_client::{LoginCredentials, Mt4Client, Mt4Event, StateDivergence};
+//! use std::time::Duration;
+//!
+//! # async fn example(credentials: LoginCredentials) -> mt4_client::Result<()> {
+//! let mut client = Mt4Client::builder().reconcile_interval(Duration::from_secs(300)).build();
+//! client.connect(&credentials).await?;
+//! let mut events = client.subscribe();
+//! while let Some(event) = events.recv().await {
+//!     if let Mt4Event::StateDivergence(StateDivergence::Stale(order)) = event {
+//!         println!("#{} 已不存在于服务器", order.ticket);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! 比较订单类型、品种、手数、开仓价和止损止盈；盈亏、库存费随行情变化，不视为不一致。
+
+use crate::types::{Order, Ticket};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 本地缓存与服务器的一处不一致 (已按服务器数据修正)
+#[derive(Debug, Clone, Serialize)]
+pub enum StateDivergence {
+    /// 服务器有而本地缓存没有的订单 (已加入缓存)
+    Missing(Order),
+    /// 本地缓存有而服务器没有的订单 (已从缓存移除)
+    Stale(Order),
+    /// 字段不一致的订单 (已替换为服务器数据)
+    Changed {
+        /// 本地缓存中的订单
+        local: Order,
+        /// 服务器返回的订单
+        server: Order,
+    },
+}
+
+impl StateDivergence {
+    /// 不一致的订单号
+    pub fn ticket(&self) -> Ticket {
+        self.order().ticket
+    }
+
+    /// 不一致的订单 (`Changed` 为服务器返回的订单)
+    pub fn order(&self) -> &Order {
+        match self {
+            StateDivergence::Missing(order) | StateDivergence::Stale(order) => order,
+            StateDivergence::Changed { server, .. } => server,
+        }
+    }
+}
+
+/// 比较本地订单与服务器订单，按订单号排序返回不一致之处
+pub fn diff_orders<'a>(local: impl IntoIterator<Item = &'a Order>, server: &[Order]) -> Vec<StateDivergence> {
+    let mut local: BTreeMap<Ticket, &Order> = local.into_iter().map(|o| (o.ticket, o)).collect();
+    let mut divergences = Vec::new();
+    for order in server {
+        match local.remove(&order.ticket) {
+            None => divergences.push(StateDivergence::Missing(order.clone())),
+            Some(cached) if !same_order(cached, order) => {
+                divergences.push(StateDivergence::Changed { local: cached.clone(), server: order.clone() })
+            }
+            Some(_) => {}
+        }
+    }
+    divergences.extend(local.into_values().map(|o| StateDivergence::Stale(o.clone())));
+    divergences.sort_by_key(StateDivergence::ticket);
+    divergences
+}
+
+fn same_order(a: &Order, b: &Order) -> bool {
+    const EPSILON: f64 = 1e-9;
+    a.order_type == b.order_type
+        && a.symbol == b.symbol
+        && (a.volume - b.volume).abs() < EPSILON
+        && (a.open_price - b.open_price).abs() < EPSILON
+        && (a.sl - b.sl).abs() < EPSILON
+        && (a.tp - b.tp).abs() < EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+
+    fn order(ticket: i32, volume: f64, sl: f64) -> Order {
+        Order { sl, profit: ticket as f64, ..Order::for_test(ticket, "EURUSD", OrderType::Buy, volume, 1.08) }
+    }
+
+    #[test]
+    fn test_diff_orders() {
+        let local = [order(1, 0.1, 0.0), order(2, 0.1, 0.0), order(3, 0.2, 1.05)];
+        // 盈亏不同不算不一致
+        let mut same = order(1, 0.1, 0.0);
+        same.profit = 99.0;
+        let server = [order(4, 0.3, 0.0), same, order(3, 0.2, 1.06)];
+
+        let divergences = diff_orders(&local, &server);
+        let tickets: Vec<i32> = divergences.iter().map(|d| d.ticket().0).collect();
+        assert_eq!(tickets, vec![2, 3, 4]);
+        assert!(matches!(&divergences[0], StateDivergence::Stale(o) if o.ticket == Ticket(2)));
+        assert!(matches!(
+            &divergences[1],
+            StateDivergence::Changed { local, server } if local.sl == 1.05 && server.sl == 1.06
+        ));
+        assert!(matches!(&divergences[2], StateDivergence::Missing(o) if o.volume == 0.3));
+        assert!(diff_orders(&server, &server).is_empty());
+    }
+
+    #[test]
+    fn test_diff_orders_empty_side() {
+        let orders = [order(1, 0.1, 0.0), order(2, 0.0, 0.0)];
+        assert!(diff_orders(&[], &[]).is_empty());
+        // 服务器没有任何订单时本地全部过期；本地为空时服务器的订单全部缺失 (手数为 0 也一样)
+        let stale = diff_orders(&orders, &[]);
+        assert!(stale.len() == 2 && stale.iter().all(|d| matches!(d, StateDivergence::Stale(_))));
+        let missing: Vec<Ticket> = diff_orders(&[], &orders).iter().map(|d| d.ticket()).collect();
+        assert_eq!(missing, vec![Ticket(1), Ticket(2)]);
+    }
+}
@@ -0,0 +1,450 @@
+//! 报价记录
+//!
+//! `TickRecorder` 把报价写入按时间轮换的 CSV 或 Parquet 文件 (列: symbol, bid, ask, timestamp, spread)，
+//! 用于直接从客户端积累历史数据。文件名为 `<前缀>_<UTC 日期>[_<小时>].<扩展名>`，轮换按报价时间划分:
+//!
+//! ```text
+//! ticks_20240102.csv
+//! ticks_20240103.csv
+//! ```
+//!
+//! 记录器实现了 `Strategy`，可以交给 `StrategyRunner` 订阅报价，定时器触发时刷新:
+//!
+//! ```no_run
+//! use mt4_client::{Mt4Client, StrategyRunner, TickFormat, TickRecorder};
+//! use std::time::Duration;
+//!
+//! # async fn example(client: Mt4Client) -> mt4_client::Result<()> {
+//! let recorder = TickRecorder::new("ticks", TickFormat::Csv)?;
+//! let mut runner = StrategyRunner::new(client, recorder)
+//!     .with_quotes(["EURUSD", "GBPUSD"], Duration::from_millis(500))?
+//!     .with_timer(Duration::from_secs(5));
+//! runner.run().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! - CSV 文件已存在时追加 (不重复写表头)；Parquet 文件不能追加，已存在时在文件名后加序号
+//! - Parquet 输出需要开启 `parquet` 特性，每次刷新写入一个行组，文件在轮换或 `finish()` 时写入文件尾
+
+use crate::clock::civil_date;
+use crate::error::{Mt4Error, Result};
+use crate::strategy::{Strategy, StrategyContext};
+use crate::types::Quote;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 默认刷新间隔
+pub const DEFAULT_TICK_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// CSV 表头
+const CSV_HEADER: &str = "symbol,bid,ask,timestamp,spread";
+
+/// 输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickFormat {
+    /// 逗号分隔文本
+    Csv,
+    /// Apache Parquet (需要 `parquet` 特性)
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl TickFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            TickFormat::Csv => "csv",
+            #[cfg(feature = "parquet")]
+            TickFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// 文件轮换周期 (按报价时间，UTC)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickRotation {
+    /// 不轮换，始终写入同一个文件
+    Never,
+    /// 每小时一个文件
+    Hourly,
+    /// 每天一个文件
+    Daily,
+}
+
+impl TickRotation {
+    /// 报价时间所属的周期 (周期序号, 文件名后缀)
+    pub(crate) fn period(self, time: i64) -> (i64, String) {
+        let (year, month, day) = civil_date(time.div_euclid(86_400));
+        match self {
+            TickRotation::Never => (0, "all".to_string()),
+            TickRotation::Daily => (time.div_euclid(86_400), format!("{:04}{:02}{:02}", year, month, day)),
+            TickRotation::Hourly => (
+                time.div_euclid(3_600),
+                format!("{:04}{:02}{:02}_{:02}", year, month, day, time.rem_euclid(86_400) / 3_600),
+            ),
+        }
+    }
+}
+
+/// 报价记录器
+pub struct TickRecorder {
+    dir: PathBuf,
+    prefix: String,
+    format: TickFormat,
+    rotation: TickRotation,
+    flush_interval: Duration,
+    current: Option<(i64, PathBuf, TickSink)>,
+    last_flush: Instant,
+    records: u64,
+}
+
+impl std::fmt::Debug for TickRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TickRecorder")
+            .field("dir", &self.dir)
+            .field("format", &self.format)
+            .field("rotation", &self.rotation)
+            .field("current", &self.current_path())
+            .field("records", &self.records)
+            .finish()
+    }
+}
+
+impl TickRecorder {
+    /// 在目录中记录报价 (目录不存在时创建)，默认按天轮换，前缀 `ticks`
+    pub fn new(dir: impl AsRef<Path>, format: TickFormat) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| Mt4Error::Io(format!("创建报价记录目录 {} 失败: {}", dir.display(), e)))?;
+        Ok(Self {
+            dir,
+            prefix: "ticks".to_string(),
+            format,
+            rotation: TickRotation::Daily,
+            flush_interval: DEFAULT_TICK_FLUSH_INTERVAL,
+            current: None,
+            last_flush: Instant::now(),
+            records: 0,
+        })
+    }
+
+    /// 设置文件名前缀
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// 设置轮换周期
+    pub fn with_rotation(mut self, rotation: TickRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// 设置刷新间隔 (记录报价时距上次刷新超过该间隔即写入磁盘)
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// 当前写入的文件
+    pub fn current_path(&self) -> Option<&Path> {
+        self.current.as_ref().map(|(_, path, _)| path.as_path())
+    }
+
+    /// 已记录的报价数
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// 记录一条报价
+    pub fn record(&mut self, quote: &Quote) -> Result<()> {
+        let (period, suffix) = self.rotation.period(quote.time);
+        if self.current.as_ref().is_none_or(|(p, _, _)| *p != period) {
+            self.finish()?;
+            let path = self.dir.join(format!("{}_{}.{}", self.prefix, suffix, self.format.extension()));
+            let (path, sink) = TickSink::open(self.format, path)?;
+            tracing::info!("Recording ticks to {}", path.display());
+            self.current = Some((period, path, sink));
+        }
+        if let Some((_, path, sink)) = &mut self.current {
+            sink.write(quote).map_err(|e| io_error("写入", path, e))?;
+        }
+        self.records += 1;
+        if self.last_flush.elapsed() >= self.flush_interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// 把缓冲的报价写入磁盘
+    pub fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
+        match &mut self.current {
+            Some((_, path, sink)) => sink.flush().map_err(|e| io_error("刷新", path, e)),
+            None => Ok(()),
+        }
+    }
+
+    /// 刷新并关闭当前文件 (之后的报价写入新文件)
+    pub fn finish(&mut self) -> Result<()> {
+        match self.current.take() {
+            Some((_, path, sink)) => sink.close().map_err(|e| io_error("关闭", &path, e)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TickRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            tracing::warn!("Failed to finish tick file: {}", e);
+        }
+    }
+}
+
+impl Strategy for TickRecorder {
+    async fn on_quote(&mut self, _ctx: &mut StrategyContext<'_>, quote: &Quote) -> Result<()> {
+        self.record(quote)
+    }
+
+    async fn on_timer(&mut self, _ctx: &mut StrategyContext<'_>) -> Result<()> {
+        self.flush()
+    }
+
+    async fn on_stop(&mut self, _ctx: &mut StrategyContext<'_>) -> Result<()> {
+        self.finish()
+    }
+}
+
+fn io_error(action: &str, path: &Path, e: impl std::fmt::Display) -> Mt4Error {
+    Mt4Error::Io(format!("{}报价文件 {} 失败: {}", action, path.display(), e))
+}
+
+/// 打开的输出文件
+enum TickSink {
+    Csv(BufWriter<File>),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet_sink::ParquetSink),
+}
+
+impl TickSink {
+    fn open(format: TickFormat, path: PathBuf) -> Result<(PathBuf, Self)> {
+        match format {
+            TickFormat::Csv => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|e| io_error("打开", &path, e))?;
+                let empty = file.metadata().map(|m| m.len() == 0).unwrap_or(true);
+                let mut writer = BufWriter::new(file);
+                if empty {
+                    writeln!(writer, "{}", CSV_HEADER).map_err(|e| io_error("写入", &path, e))?;
+                }
+                Ok((path, TickSink::Csv(writer)))
+            }
+            #[cfg(feature = "parquet")]
+            TickFormat::Parquet => {
+                let path = unused_path(path);
+                let file = File::create(&path).map_err(|e| io_error("创建", &path, e))?;
+                let sink = parquet_sink::ParquetSink::new(file).map_err(|e| io_error("创建", &path, e))?;
+                Ok((path, TickSink::Parquet(sink)))
+            }
+        }
+    }
+
+    fn write(&mut self, quote: &Quote) -> std::result::Result<(), String> {
+        match self {
+            TickSink::Csv(writer) => writeln!(
+                writer,
+                "{},{},{},{},{}",
+                quote.symbol,
+                quote.bid,
+                quote.ask,
+                quote.time,
+                spread(quote)
+            )
+            .map_err(|e| e.to_string()),
+            #[cfg(feature = "parquet")]
+            TickSink::Parquet(sink) => {
+                sink.push(quote);
+                Ok(())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> std::result::Result<(), String> {
+        match self {
+            TickSink::Csv(writer) => writer.flush().map_err(|e| e.to_string()),
+            #[cfg(feature = "parquet")]
+            TickSink::Parquet(sink) => sink.write_row_group().map_err(|e| e.to_string()),
+        }
+    }
+
+    fn close(mut self) -> std::result::Result<(), String> {
+        self.flush()?;
+        match self {
+            TickSink::Csv(_) => Ok(()),
+            #[cfg(feature = "parquet")]
+            TickSink::Parquet(sink) => sink.close().map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// 点差 (去掉浮点误差)
+fn spread(quote: &Quote) -> f64 {
+    ((quote.ask - quote.bid) * 1e8).round() / 1e8
+}
+
+/// 文件已存在时在文件名后加序号
+#[cfg(feature = "parquet")]
+fn unused_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+    (1..)
+        .map(|n| path.with_file_name(format!("{}_{}.{}", stem, n, extension)))
+        .find(|p| !p.exists())
+        .unwrap_or(path)
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_sink {
+    use super::spread;
+    use crate::types::Quote;
+    use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+    use parquet::errors::Result;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::fs::File;
+    use std::sync::Arc;
+
+    const SCHEMA: &str = "
+        message tick {
+            REQUIRED BYTE_ARRAY symbol (UTF8);
+            REQUIRED DOUBLE bid;
+            REQUIRED DOUBLE ask;
+            REQUIRED INT64 timestamp;
+            REQUIRED DOUBLE spread;
+        }
+    ";
+
+    /// 缓冲报价，刷新时写入一个行组
+    pub(super) struct ParquetSink {
+        writer: SerializedFileWriter<File>,
+        pending: Vec<Quote>,
+    }
+
+    impl ParquetSink {
+        pub(super) fn new(file: File) -> Result<Self> {
+            let schema = Arc::new(parse_message_type(SCHEMA)?);
+            let writer = SerializedFileWriter::new(file, schema, Arc::new(WriterProperties::builder().build()))?;
+            Ok(Self { writer, pending: Vec::new() })
+        }
+
+        pub(super) fn push(&mut self, quote: &Quote) {
+            self.pending.push(quote.clone());
+        }
+
+        pub(super) fn write_row_group(&mut self) -> Result<()> {
+            if self.pending.is_empty() {
+                return Ok(());
+            }
+            let quotes = std::mem::take(&mut self.pending);
+            let symbols: Vec<ByteArray> = quotes.iter().map(|q| ByteArray::from(q.symbol.as_str())).collect();
+            let bids: Vec<f64> = quotes.iter().map(|q| q.bid).collect();
+            let asks: Vec<f64> = quotes.iter().map(|q| q.ask).collect();
+            let times: Vec<i64> = quotes.iter().map(|q| q.time).collect();
+            let spreads: Vec<f64> = quotes.iter().map(spread).collect();
+
+            let mut row_group = self.writer.next_row_group()?;
+            let mut index = 0;
+            while let Some(mut column) = row_group.next_column()? {
+                match index {
+                    0 => column.typed::<ByteArrayType>().write_batch(&symbols, None, None)?,
+                    1 => column.typed::<DoubleType>().write_batch(&bids, None, None)?,
+                    2 => column.typed::<DoubleType>().write_batch(&asks, None, None)?,
+                    3 => column.typed::<Int64Type>().write_batch(&times, None, None)?,
+                    _ => column.typed::<DoubleType>().write_batch(&spreads, None, None)?,
+                };
+                column.close()?;
+                index += 1;
+            }
+            row_group.close()?;
+            Ok(())
+        }
+
+        pub(super) fn close(self) -> Result<()> {
+            self.writer.close()?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(symbol: &str, bid: f64, time: i64) -> Quote {
+        Quote { symbol: symbol.to_string(), bid, ask: bid + 0.0002, time }
+    }
+
+    #[test]
+    fn test_csv_rotation() {
+        let dir = std::env::temp_dir().join(format!("mt4_ticks_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        // 2024-01-01 23:59:59 和 2024-01-02 00:00:01 (UTC)
+        let (day1, day2) = (1_704_153_599, 1_704_153_601);
+
+        let mut recorder = TickRecorder::new(&dir, TickFormat::Csv).unwrap();
+        recorder.record(&quote("EURUSD", 1.1, day1)).unwrap();
+        recorder.record(&quote("GBPUSD", 1.27, day1)).unwrap();
+        recorder.record(&quote("EURUSD", 1.1001, day2)).unwrap();
+        drop(recorder);
+
+        let first = std::fs::read_to_string(dir.join("ticks_20240101.csv")).unwrap();
+        assert_eq!(
+            first.lines().collect::<Vec<_>>(),
+            [CSV_HEADER, "EURUSD,1.1,1.1002,1704153599,0.0002", "GBPUSD,1.27,1.2702,1704153599,0.0002"]
+        );
+
+        // 已存在的文件追加，不重复写表头
+        let mut recorder = TickRecorder::new(&dir, TickFormat::Csv).unwrap();
+        recorder.record(&quote("EURUSD", 1.1002, day2)).unwrap();
+        recorder.finish().unwrap();
+        let second = std::fs::read_to_string(dir.join("ticks_20240102.csv")).unwrap();
+        assert_eq!(second.lines().count(), 3);
+        assert_eq!(recorder.records(), 1);
+
+        assert_eq!(TickRotation::Hourly.period(day2).1, "20240102_00");
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_row_groups() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let dir = std::env::temp_dir().join(format!("mt4_ticks_parquet_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut recorder = TickRecorder::new(&dir, TickFormat::Parquet).unwrap().with_rotation(TickRotation::Never);
+        for i in 0..3 {
+            recorder.record(&quote("EURUSD", 1.1, 1_704_153_600 + i)).unwrap();
+        }
+        recorder.flush().unwrap();
+        recorder.record(&quote("EURUSD", 1.1, 1_704_153_700)).unwrap();
+        let path = recorder.current_path().unwrap().to_path_buf();
+        recorder.finish().unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 4);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
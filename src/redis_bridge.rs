@@ -0,0 +1,289 @@
+//! Redis 事件桥接 (需要开启 `redis` feature)
+//!
+//! 多进程交易系统常用 Redis 在进程间传递事件和指令。`RedisBridge` 订阅客户端句柄的事件广播，
+//! 把每个事件序列化为 `mt4.event.v1` JSON (见 `schema` 模块):
+//!
+//! - 发布到频道 (`PUBLISH <channel> <json>`)
+//! - 追加到流 (`XADD <stream> * type <事件类型> event <json>`)，可按近似长度裁剪
+//!
+//! 并可从指令流读取交易请求，经句柄依次执行:
+//!
+//! - 指令条目的 `request` 字段为 `TradeRequest` JSON，可选的 `id` 字段用于关联结果
+//! - 执行结果追加到 `<指令流>:results`，字段为 `id`、`entry` (指令条目 ID)、`ok` (1/0) 和
+//!   `response` (`TradeResponse` JSON) 或 `error`
+//! - 只执行桥接启动后追加的指令，启动前积压的指令不会被执行
+//!
+//! ```no_run
+//! use mt4_client::redis_bridge::{RedisBridge, RedisBridgeConfig};
+//! use mt4_client::Mt4Client;
+//!
+//! # async fn example() -> mt4_client::Result<()> {
+//! let handle = Mt4Client::new().spawn();
+//! let config = RedisBridgeConfig::new("redis://127.0.0.1/")
+//!     .with_channel("mt4:events")
+//!     .with_stream("mt4:events:log")
+//!     .with_stream_maxlen(100_000)
+//!     .with_command_stream("mt4:orders");
+//! let bridge = RedisBridge::start(&config, handle).await?;
+//! # let _ = bridge;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Mt4Error, Result};
+use crate::events::EventSubscription;
+use crate::handle::Mt4Handle;
+use crate::schema::JsonSchemaAdapter;
+use crate::types::{TradeRequest, TradeResponse};
+use redis::aio::MultiplexedConnection;
+use redis::streams::{StreamId, StreamMaxlen, StreamRangeReply, StreamReadOptions, StreamReadReply};
+use redis::{AsyncCommands, RedisResult};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// 读取指令流时的阻塞时间 (毫秒)
+const COMMAND_BLOCK_MS: usize = 2000;
+
+/// 每次读取的指令条数
+const COMMAND_BATCH: usize = 16;
+
+/// Redis 命令失败后的重试间隔
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Redis 桥接配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedisBridgeConfig {
+    /// Redis 地址 (如 `redis://127.0.0.1/`)
+    pub url: String,
+    /// 事件发布频道
+    pub channel: Option<String>,
+    /// 事件流
+    pub stream: Option<String>,
+    /// 事件流的近似最大长度 (`MAXLEN ~`)
+    pub stream_maxlen: Option<usize>,
+    /// 交易指令流
+    pub command_stream: Option<String>,
+}
+
+impl RedisBridgeConfig {
+    /// 以 Redis 地址创建 (不发布事件也不读取指令)
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            channel: None,
+            stream: None,
+            stream_maxlen: None,
+            command_stream: None,
+        }
+    }
+
+    /// 把事件发布到频道
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = Some(channel.into());
+        self
+    }
+
+    /// 把事件追加到流
+    pub fn with_stream(mut self, stream: impl Into<String>) -> Self {
+        self.stream = Some(stream.into());
+        self
+    }
+
+    /// 设置事件流的近似最大长度
+    pub fn with_stream_maxlen(mut self, maxlen: usize) -> Self {
+        self.stream_maxlen = Some(maxlen);
+        self
+    }
+
+    /// 从流读取交易指令
+    pub fn with_command_stream(mut self, stream: impl Into<String>) -> Self {
+        self.command_stream = Some(stream.into());
+        self
+    }
+
+    /// 指令执行结果流
+    pub fn result_stream(&self) -> Option<String> {
+        self.command_stream.as_ref().map(|stream| format!("{}:results", stream))
+    }
+}
+
+/// Redis 事件桥接，丢弃时停止
+pub struct RedisBridge {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl RedisBridge {
+    /// 连接 Redis 并启动事件发布和指令读取任务
+    pub async fn start(config: &RedisBridgeConfig, handle: Mt4Handle) -> Result<Self> {
+        let client = redis::Client::open(config.url.as_str())
+            .map_err(|e| Mt4Error::InvalidParams(format!("Redis 地址 {} 无效: {}", config.url, e)))?;
+        let connect = || async {
+            client
+                .get_multiplexed_async_connection()
+                .await
+                .map_err(|e| Mt4Error::Connection(format!("连接 Redis 失败: {}", e)))
+        };
+
+        let mut tasks = Vec::new();
+        if config.channel.is_some() || config.stream.is_some() {
+            let conn = connect().await?;
+            tasks.push(tokio::spawn(publish_events(conn, handle.subscribe(), config.clone())));
+        }
+        if let (Some(stream), Some(results)) = (config.command_stream.clone(), config.result_stream()) {
+            // XREAD BLOCK 会占住连接，指令读取使用单独的连接
+            let mut conn = connect().await?;
+            let last_id = latest_entry_id(&mut conn, &stream)
+                .await
+                .map_err(|e| Mt4Error::Connection(format!("读取指令流 {} 失败: {}", stream, e)))?;
+            tasks.push(tokio::spawn(consume_commands(conn, handle, stream, results, last_id)));
+        }
+        tracing::info!("Redis bridge started: {}", config.url);
+
+        Ok(Self { tasks })
+    }
+
+    /// 停止桥接
+    pub fn shutdown(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for RedisBridge {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// 发布事件，直到客户端被丢弃
+async fn publish_events(mut conn: MultiplexedConnection, mut events: EventSubscription, config: RedisBridgeConfig) {
+    let adapter = JsonSchemaAdapter;
+    while let Some(event) = events.recv_timed().await {
+        let payload = adapter.to_value(&event).to_string();
+        if let Some(channel) = &config.channel {
+            let published: RedisResult<()> = conn.publish(channel, &payload).await;
+            if let Err(e) = published {
+                tracing::warn!("Redis publish to {} failed: {}", channel, e);
+            }
+        }
+        if let Some(stream) = &config.stream {
+            let fields = [("type", event.event.kind()), ("event", payload.as_str())];
+            let added: RedisResult<String> = match config.stream_maxlen {
+                Some(maxlen) => conn.xadd_maxlen(stream, StreamMaxlen::Approx(maxlen), "*", &fields).await,
+                None => conn.xadd(stream, "*", &fields).await,
+            };
+            if let Err(e) = added {
+                tracing::warn!("Redis XADD to {} failed: {}", stream, e);
+            }
+        }
+    }
+}
+
+/// 指令流当前最后一条的 ID (空流为 `0-0`)
+async fn latest_entry_id(conn: &mut MultiplexedConnection, stream: &str) -> RedisResult<String> {
+    let reply: StreamRangeReply = conn.xrevrange_count(stream, "+", "-", 1).await?;
+    Ok(reply.ids.into_iter().next().map(|entry| entry.id).unwrap_or_else(|| "0-0".to_string()))
+}
+
+/// 依次执行指令流中的交易请求
+async fn consume_commands(
+    mut conn: MultiplexedConnection,
+    handle: Mt4Handle,
+    stream: String,
+    results: String,
+    mut last_id: String,
+) {
+    let options = StreamReadOptions::default().block(COMMAND_BLOCK_MS).count(COMMAND_BATCH);
+    loop {
+        let reply: RedisResult<Option<StreamReadReply>> = conn.xread_options(&[&stream], &[&last_id], &options).await;
+        let entries = match reply {
+            Ok(reply) => reply.into_iter().flat_map(|r| r.keys).flat_map(|key| key.ids),
+            Err(e) => {
+                tracing::warn!("Redis XREAD from {} failed: {}", stream, e);
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+        for entry in entries {
+            last_id = entry.id.clone();
+            let outcome = match parse_command(&entry) {
+                Ok(request) => handle.send_trade_and_wait(request).await,
+                Err(e) => Err(e),
+            };
+            let fields = result_fields(&entry, &outcome);
+            let added: RedisResult<String> = conn.xadd(&results, "*", &fields).await;
+            if let Err(e) = added {
+                tracing::warn!("Redis XADD to {} failed: {}", results, e);
+            }
+        }
+    }
+}
+
+/// 解析指令条目的 `request` 字段
+fn parse_command(entry: &StreamId) -> Result<TradeRequest> {
+    let request: String = entry
+        .get("request")
+        .ok_or_else(|| Mt4Error::InvalidParams(format!("指令 {} 缺少 request 字段", entry.id)))?;
+    serde_json::from_str(&request).map_err(|e| Mt4Error::InvalidParams(format!("指令 {} 格式错误: {}", entry.id, e)))
+}
+
+/// 执行结果条目的字段
+fn result_fields(entry: &StreamId, outcome: &Result<TradeResponse>) -> Vec<(&'static str, String)> {
+    let mut fields = vec![
+        ("id", entry.get::<String>("id").unwrap_or_default()),
+        ("entry", entry.id.clone()),
+    ];
+    match outcome {
+        Ok(response) => {
+            fields.push(("ok", "1".to_string()));
+            fields.push(("response", serde_json::to_string(response).unwrap_or_default()));
+        }
+        Err(e) => {
+            fields.push(("ok", "0".to_string()));
+            fields.push(("error", e.to_string()));
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Symbol;
+    use redis::Value;
+
+    fn entry(fields: &[(&str, &str)]) -> StreamId {
+        StreamId {
+            id: "1700000000000-0".to_string(),
+            map: fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), Value::BulkString(v.as_bytes().to_vec())))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_command_entries() {
+        let config = RedisBridgeConfig::new("redis://127.0.0.1/").with_command_stream("mt4:orders");
+        assert_eq!(config.result_stream().as_deref(), Some("mt4:orders:results"));
+
+        let request = TradeRequest::buy(&Symbol::new("EURUSD").unwrap(), 0.1, 0.0, 0.0);
+        let json = serde_json::to_string(&request).unwrap();
+        let command = entry(&[("id", "grid-7"), ("request", &json)]);
+        assert_eq!(parse_command(&command).unwrap(), request);
+
+        let response = TradeResponse { request_id: 1001, status: 0, price1: 1.1, price2: 1.1002, orders: Vec::new() };
+        let fields = result_fields(&command, &Ok(response));
+        assert_eq!(fields[0], ("id", "grid-7".to_string()));
+        assert_eq!(fields[2], ("ok", "1".to_string()));
+        assert!(fields[3].1.contains("\"request_id\":1001"));
+
+        let broken = entry(&[("request", "{}")]);
+        let outcome = parse_command(&broken).map(|_| unreachable!());
+        let fields = result_fields(&broken, &outcome);
+        assert_eq!(fields[0], ("id", String::new()));
+        assert_eq!(fields[2], ("ok", "0".to_string()));
+        assert!(parse_command(&entry(&[])).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+//! 重新报价自动重试
+//!
+//! 行情快速变化时，市价单常因 135 (Price changed) 或 138 (Requote) 被拒绝。配置 `RequotePolicy` 后，
+//! `send_trade_and_wait` 对市价开仓请求在这两种错误时重新获取报价并放宽滑点重新提交，
+//! 每次重试前发出 `Mt4Event::Requote`，调用方可以观察重试过程:
+//!
+//! ```no_run
+//! use mt4_client::{Mt4Client, RequotePolicy};
+//!
+//! let client = Mt4Client::builder().requote_policy(RequotePolicy::new(3)).build();
+//! ```
+
+use std::time::Duration;
+
+/// 价格已变化
+pub const PRICE_CHANGED: u8 = 135;
+
+/// 重新报价
+pub const REQUOTE: u8 = 138;
+
+/// 错误码是否为重新报价 (135 / 138)
+pub fn is_requote_code(code: u8) -> bool {
+    matches!(code, PRICE_CHANGED | REQUOTE)
+}
+
+/// 重新报价重试策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequotePolicy {
+    /// 最多重试次数 (不含首次提交)
+    pub max_retries: u32,
+    /// 每次重试增加的滑点 (点)
+    pub slippage_step: i32,
+    /// 滑点上限 (点)
+    pub max_slippage: i32,
+    /// 重试前的等待时间
+    pub retry_delay: Duration,
+}
+
+impl Default for RequotePolicy {
+    fn default() -> Self {
+        Self::new(2)
+    }
+}
+
+impl RequotePolicy {
+    /// 最多重试 `max_retries` 次，每次滑点增加 10 点，上限 100 点，间隔 200ms
+    pub fn new(max_retries: u32) -> Self {
+        Self { max_retries, slippage_step: 10, max_slippage: 100, retry_delay: Duration::from_millis(200) }
+    }
+
+    /// 设置每次重试增加的滑点和滑点上限
+    pub fn with_slippage(mut self, step: i32, max: i32) -> Self {
+        self.slippage_step = step;
+        self.max_slippage = max;
+        self
+    }
+
+    /// 设置重试前的等待时间
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// 第 `attempt` 次重试 (从 1 开始) 使用的滑点
+    pub fn slippage_for(&self, base: i32, attempt: u32) -> i32 {
+        let widened = base.saturating_add(self.slippage_step.saturating_mul(attempt as i32));
+        widened.min(self.max_slippage.max(base))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_widening_slippage() {
+        let policy = RequotePolicy::new(3).with_slippage(15, 60);
+        assert_eq!(policy.slippage_for(30, 1), 45);
+        assert_eq!(policy.slippage_for(30, 2), 60);
+        assert_eq!(policy.slippage_for(30, 3), 60);
+        // 初始滑点已超过上限时保持不变
+        assert_eq!(policy.slippage_for(80, 1), 80);
+
+        assert!(is_requote_code(135) && is_requote_code(138));
+        assert!(!is_requote_code(136));
+    }
+}
@@ -0,0 +1,248 @@
+//! 风险控制
+//!
+//! `RiskManager` 在开仓请求发送前按 `RiskLimits` 检查:
+//!
+//! - 持仓和挂单的总手数上限 (开启 `downsize` 时把超出的请求缩小到剩余额度)
+//! - 单个品种的持仓和挂单数量上限
+//! - 当日最大亏损 (净值相对当日起始净值的回撤，起始净值取 UTC 日切换后收到的第一条账户信息)
+//! - 最大滑点 (开启 `downsize` 时把滑点降到上限)
+//!
+//! 平仓、删除挂单、修改和报价请求降低风险或不产生新敞口，不受限制。违反规则的请求不会发送，
+//! 客户端返回 `Mt4Error::RiskRejected` 并发出 `Mt4Event::RiskRejected`。
+
+pub use crate::error::RiskRule;
+
+use crate::error::{Mt4Error, Result};
+use crate::types::{AccountInfo, Order, SymbolInfo, TradeRequest};
+use serde::{Deserialize, Serialize};
+
+/// 风险限制 (None 表示不限)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RiskLimits {
+    /// 持仓和挂单的总手数上限
+    pub max_open_lots: Option<f64>,
+    /// 单个品种的持仓和挂单数量上限
+    pub max_positions_per_symbol: Option<usize>,
+    /// 当日最大亏损 (账户货币)
+    pub max_daily_loss: Option<f64>,
+    /// 最大滑点 (点)
+    pub max_slippage: Option<i32>,
+    /// 超出手数或滑点上限时缩小请求而不是拒绝
+    pub downsize: bool,
+}
+
+impl RiskLimits {
+    /// 不限制任何规则
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置总手数上限
+    pub fn with_max_open_lots(mut self, lots: f64) -> Self {
+        self.max_open_lots = Some(lots);
+        self
+    }
+
+    /// 设置单个品种的订单数量上限
+    pub fn with_max_positions_per_symbol(mut self, count: usize) -> Self {
+        self.max_positions_per_symbol = Some(count);
+        self
+    }
+
+    /// 设置当日最大亏损
+    pub fn with_max_daily_loss(mut self, loss: f64) -> Self {
+        self.max_daily_loss = Some(loss);
+        self
+    }
+
+    /// 设置最大滑点
+    pub fn with_max_slippage(mut self, points: i32) -> Self {
+        self.max_slippage = Some(points);
+        self
+    }
+
+    /// 超出上限时缩小请求
+    pub fn with_downsize(mut self, downsize: bool) -> Self {
+        self.downsize = downsize;
+        self
+    }
+}
+
+/// 风险管理器
+#[derive(Debug, Default)]
+pub struct RiskManager {
+    limits: RiskLimits,
+    /// (UTC 日序号, 当日起始净值)
+    day_start: Option<(i64, f64)>,
+}
+
+impl RiskManager {
+    /// 以限制创建
+    pub fn new(limits: RiskLimits) -> Self {
+        Self { limits, day_start: None }
+    }
+
+    /// 当前限制
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    /// 替换限制 (当日起始净值保留)
+    pub fn set_limits(&mut self, limits: RiskLimits) {
+        self.limits = limits;
+    }
+
+    /// 记录账户信息更新，UTC 日切换后的第一条作为当日起始净值
+    ///
+    /// 客户端在每次收到账户信息 (登录后及每次对账) 时调用；连接晚于日切换时以连接后的第一条为准
+    pub fn on_account(&mut self, account: &AccountInfo, now: i64) {
+        let day = now.div_euclid(86_400);
+        if self.day_start.is_none_or(|(d, _)| d != day) {
+            self.day_start = Some((day, account.equity));
+        }
+    }
+
+    /// 当日亏损 (净值相对当日起始净值的回撤，盈利或尚未收到当日的账户信息时为 0)
+    pub fn daily_loss(&self, account: &AccountInfo, now: i64) -> f64 {
+        match self.day_start {
+            Some((day, start)) if day == now.div_euclid(86_400) => (start - account.equity).max(0.0),
+            _ => 0.0,
+        }
+    }
+
+    /// 检查请求，可能缩小手数或滑点
+    ///
+    /// - `orders`: 当前持仓和挂单
+    /// - `account`: 最新账户信息 (未知时不检查当日亏损)
+    /// - `now`: 当前 UTC 时间戳 (秒)，用于划分交易日
+    ///
+    /// 违反规则时返回 `Mt4Error::RiskRejected`
+    pub fn check(
+        &mut self,
+        request: &mut TradeRequest,
+        spec: &SymbolInfo,
+        orders: &[Order],
+        account: Option<&AccountInfo>,
+        now: i64,
+    ) -> Result<()> {
+        if !request.is_open_request() {
+            return Ok(());
+        }
+        let limits = &self.limits;
+
+        if let (Some(max_loss), Some(account)) = (limits.max_daily_loss, account) {
+            let loss = self.daily_loss(account, now);
+            if loss >= max_loss {
+                return Err(Self::reject(
+                    RiskRule::MaxDailyLoss,
+                    format!("当日亏损 {:.2} 已达到上限 {:.2}", loss, max_loss),
+                ));
+            }
+        }
+
+        if let Some(max_count) = limits.max_positions_per_symbol {
+            let count = orders.iter().filter(|o| o.symbol == request.symbol).count();
+            if count >= max_count {
+                return Err(Self::reject(
+                    RiskRule::MaxPositionsPerSymbol,
+                    format!("{} 已有 {} 个订单，达到上限 {}", request.symbol, count, max_count),
+                ));
+            }
+        }
+
+        if let Some(max_lots) = limits.max_open_lots {
+            let open: f64 = orders.iter().map(|o| o.volume).sum();
+            let available = max_lots - open;
+            if request.volume > available + 1e-9 {
+                // 向下取整到手数步长，不超过剩余额度
+                let step = if spec.lot_step > 0.0 { spec.lot_step } else { 0.01 };
+                let downsized = ((available / step + 1e-9).floor() * step * 1e8).round() / 1e8;
+                if !limits.downsize || downsized < spec.lot_min {
+                    return Err(Self::reject(
+                        RiskRule::MaxOpenLots,
+                        format!("已开 {:.2} 手，再开 {} 手将超过上限 {:.2} 手", open, request.volume, max_lots),
+                    ));
+                }
+                tracing::info!("📉 [风险控制] {} 手缩小为 {} 手 (总手数上限 {})", request.volume, downsized, max_lots);
+                request.volume = downsized;
+            }
+        }
+
+        if let Some(max_slippage) = limits.max_slippage {
+            if request.slippage > max_slippage {
+                if !limits.downsize {
+                    return Err(Self::reject(
+                        RiskRule::MaxSlippage,
+                        format!("滑点 {} 超过上限 {}", request.slippage, max_slippage),
+                    ));
+                }
+                request.slippage = max_slippage;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn reject(rule: RiskRule, reason: String) -> Mt4Error {
+        Mt4Error::RiskRejected { rule, reason }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+    use crate::types::{Symbol, Ticket};
+
+    fn order(ticket: i32, symbol: &str, volume: f64) -> Order {
+        Order::for_test(ticket, symbol, OrderType::Buy, volume, 1.08)
+    }
+
+    fn account(equity: f64) -> AccountInfo {
+        AccountInfo { balance: 10_000.0, equity, ..AccountInfo::default() }
+    }
+
+    #[test]
+    fn test_risk_rules() {
+        let spec = SymbolInfo::new("EURUSD", 5);
+        let symbol = Symbol::new("EURUSD").unwrap();
+        let orders = [order(1, "EURUSD", 0.5), order(2, "GBPUSD", 0.3)];
+        let rule = |result: Result<()>| match result {
+            Err(Mt4Error::RiskRejected { rule, .. }) => rule,
+            other => panic!("unexpected {:?}", other),
+        };
+        let now = 1_700_000_000;
+
+        let limits = RiskLimits::new().with_max_open_lots(1.0).with_max_positions_per_symbol(2).with_max_slippage(30);
+        let mut risk = RiskManager::new(limits.clone());
+        let mut request = TradeRequest::buy(&symbol, 0.5, 0.0, 0.0);
+        assert_eq!(rule(risk.check(&mut request, &spec, &orders, None, now)), RiskRule::MaxOpenLots);
+        request.volume = 0.2;
+        assert_eq!(rule(risk.check(&mut request, &spec, &orders, None, now)), RiskRule::MaxSlippage);
+
+        // 缩小模式: 手数降到剩余额度，滑点降到上限
+        risk.set_limits(limits.with_downsize(true));
+        let mut request = TradeRequest::buy(&symbol, 0.5, 0.0, 0.0);
+        risk.check(&mut request, &spec, &orders, None, now).unwrap();
+        assert_eq!((request.volume, request.slippage), (0.2, 30));
+
+        let orders = [order(1, "EURUSD", 0.1), order(3, "EURUSD", 0.1)];
+        let mut request = TradeRequest::buy(&symbol, 0.1, 0.0, 0.0);
+        assert_eq!(rule(risk.check(&mut request, &spec, &orders, None, now)), RiskRule::MaxPositionsPerSymbol);
+        // 平仓不受限制
+        let mut close = TradeRequest::close(Ticket(1), &symbol, 0.1);
+        assert!(risk.check(&mut close, &spec, &orders, None, now).is_ok());
+
+        // 当日亏损以日切换后第一条账户信息的净值为基准: 首次下单前已有的亏损同样计入
+        let mut risk = RiskManager::new(RiskLimits::new().with_max_daily_loss(500.0));
+        risk.on_account(&account(10_000.0), now);
+        risk.on_account(&account(9_400.0), now + 60);
+        assert_eq!(risk.daily_loss(&account(9_400.0), now + 60), 600.0);
+        assert_eq!(rule(risk.check(&mut request, &spec, &[], Some(&account(9_400.0)), now)), RiskRule::MaxDailyLoss);
+        // 跨日后收到新的账户信息前不限制，之后以新的净值为基准
+        assert!(risk.check(&mut request, &spec, &[], Some(&account(9_000.0)), now + 86_400).is_ok());
+        risk.on_account(&account(9_000.0), now + 86_400);
+        assert!(risk.check(&mut request, &spec, &[], Some(&account(8_600.0)), now + 86_400).is_ok());
+        assert_eq!(risk.daily_loss(&account(8_400.0), now + 86_400), 600.0);
+    }
+}
@@ -0,0 +1,606 @@
+//! 事件流外部格式适配
+//!
+//! 下游 OMS 通常不直接消费 `Mt4Event`。`EventAdapter` 将事件转换为外部格式，
+//! 内置两种实现:
+//!
+//! - `JsonSchemaAdapter`: 固定结构的 JSON (`mt4.event.v1`)
+//! - `FixAdapter`: 类 FIX 4.4 ExecutionReport (35=8) 结构
+//!
+//! ## JSON 结构 (`mt4.event.v1`)
+//!
+//! ```text
+//! {
+//!   "schema": "mt4.event.v1",
+//!   "type": "OrderUpdate",          // Mt4Event::kind()
+//!   "received_at": 1700000000.123,  // 本地 UTC 接收时间 (秒)
+//!   "server_time": 1700007200,      // 服务器时间 (秒)，无则为 null
+//!   "seq": 42,                      // 事件序号 (TimedEvent::seq)
+//!   "data": { ... }                 // 按 type 不同，见下表
+//! }
+//! ```
+//!
+//! | type | data |
+//! |------|------|
+//! | Connected / Authenticated / Disconnected / Pong | `{}` |
+//! | AuthFailed | `{"code"}` |
+//! | AccountInfo | `AccountInfo` 全部字段 |
+//! | OrderUpdate | `{"notify_id", "notify_type", "action", "order"}`，action 为 opened/closed/modified/account |
+//! | OrderUpdates | `{"updates": [OrderUpdate data...]}` |
+//! | PositionsSnapshot / HistoryOrders | `{"orders": [Order...]}` |
+//! | Quote | `{"symbol", "bid", "ask", "time"}` |
+//! | TradeSuccess | `{"request_id", "status"}` |
+//! | TradeFailed | `{"code", "message"}` |
+//! | TradeTimeout | `{"request_id", "request", "elapsed_secs"}` |
+//! | IntentExecuted | `{"intent_id", "request_id"}` |
+//! | IntentExpired | `{"intent_id", "reason"}` |
+//! | IntentFailed | `{"intent_id", "message"}` |
+//! | OrderStateChanged | `{"ticket", "from", "to", "valid", "remaining_ticket", "order"}` |
+//! | ConnectionStatus (`experimental-status`) | `{"connected", "maintenance", "code", "error_code"}` |
+//! | SpreadAlert | `{"symbol", "spread", "threshold", "active", "time"}` |
+//! | CandleClosed | `{"symbol", "period_secs", "candle"}` |
+//! | BackfillProgress | `{"symbol", "chunks_done", "chunks_total", "fetched", "covered_to", "retries", "error"}` |
+//! | MarginWarning / MarginCritical | `{"margin_level", "equity", "margin", "threshold"}` |
+//! | TradeStats | `{"overall", "by_symbol"}` |
+//! | MissedUpdates | `{"from", "to"}` |
+//! | StateDivergence | `{"Missing": Order}` / `{"Stale": Order}` / `{"Changed": {"local", "server"}}` |
+//! | BalanceOperation | `{"ticket", "kind", "amount", "comment", "time", "balance", "credit", "notify_id"}` |
+//! | Error | `{"message"}` |
+//! | RawMessage | `{"command", "error_code", "data"}`，data 为十六进制字符串 |
+//! | Custom | `{"command", "name", "error_code", "value"}`，value 为解码结果的 `Debug` 输出 |
+
+use crate::client::Mt4Event;
+use crate::clock::{civil_date, DriftEstimator};
+use crate::events::TimedEvent;
+use crate::protocol::OrderType;
+use crate::types::{Order, OrderUpdate, TradeRequest};
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// JSON 结构版本标识
+pub const EVENT_SCHEMA_VERSION: &str = "mt4.event.v1";
+
+/// 事件格式适配器
+pub trait EventAdapter {
+    /// 输出类型
+    type Output;
+
+    /// 转换事件，一个事件可能对应零个或多个输出
+    fn adapt(&self, event: &TimedEvent) -> Vec<Self::Output>;
+}
+
+/// JSON 适配器 (`mt4.event.v1`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonSchemaAdapter;
+
+impl JsonSchemaAdapter {
+    /// 转换为 JSON 值
+    pub fn to_value(&self, event: &TimedEvent) -> Value {
+        json!({
+            "schema": EVENT_SCHEMA_VERSION,
+            "type": event.event.kind(),
+            "received_at": event.time.utc_secs(),
+            "server_time": event.time.server_time,
+            "seq": event.seq,
+            "data": Self::event_data(&event.event),
+        })
+    }
+
+    fn event_data(event: &Mt4Event) -> Value {
+        match event {
+            Mt4Event::Connected | Mt4Event::Authenticated | Mt4Event::Disconnected | Mt4Event::Pong => json!({}),
+            Mt4Event::AuthFailed(code) => json!({ "code": code }),
+            Mt4Event::AccountInfo(info) => json!(info),
+            Mt4Event::OrderUpdate(update) => Self::order_update_data(update),
+            Mt4Event::OrderUpdates(updates) => json!({
+                "updates": updates.iter().map(Self::order_update_data).collect::<Vec<_>>(),
+            }),
+            Mt4Event::PositionsSnapshot(orders) | Mt4Event::HistoryOrders(orders) => json!({ "orders": orders }),
+            Mt4Event::Quote(quote) => json!(quote),
+            Mt4Event::TradeSuccess { request_id, status } => json!({ "request_id": request_id, "status": status }),
+            Mt4Event::TradeFailed { code, message } => json!({ "code": code, "message": message }),
+            Mt4Event::TradeTimeout { request_id, request, elapsed_secs } => json!({
+                "request_id": request_id,
+                "request": request,
+                "elapsed_secs": elapsed_secs,
+            }),
+            Mt4Event::Requote { symbol, code, attempt, slippage, price } => json!({
+                "symbol": symbol,
+                "code": code,
+                "attempt": attempt,
+                "slippage": slippage,
+                "price": price,
+            }),
+            Mt4Event::RiskRejected { rule, reason, request } => json!({
+                "rule": rule,
+                "reason": reason,
+                "request": request,
+            }),
+            Mt4Event::IntentExecuted { intent_id, request_id } => {
+                json!({ "intent_id": intent_id, "request_id": request_id })
+            }
+            Mt4Event::IntentExpired { intent_id, reason } => json!({ "intent_id": intent_id, "reason": reason }),
+            Mt4Event::IntentFailed { intent_id, message } => json!({ "intent_id": intent_id, "message": message }),
+            Mt4Event::OrderStateChanged(transition) => json!(transition),
+            Mt4Event::Funding(operation) => json!(operation),
+            Mt4Event::BalanceOperation(operation) => json!(operation),
+            Mt4Event::MissedUpdates(missed) => json!(missed),
+            Mt4Event::StateDivergence(divergence) => json!(divergence),
+            Mt4Event::SpreadAlert(alert) => json!(alert),
+            Mt4Event::CandleClosed(bar) => json!(bar),
+            Mt4Event::BackfillProgress(progress) => json!(progress),
+            Mt4Event::MarginWarning(alert) | Mt4Event::MarginCritical(alert) => json!(alert),
+            Mt4Event::TradeStats(report) => json!(report),
+            #[cfg(feature = "experimental-status")]
+            Mt4Event::ConnectionStatus(status) => json!(status),
+            Mt4Event::Error(message) => json!({ "message": message }),
+            Mt4Event::RawMessage { command, error_code, data } => json!({
+                "command": command,
+                "error_code": error_code,
+                "data": hex::encode(data),
+            }),
+            Mt4Event::Custom(custom) => json!({
+                "command": custom.command,
+                "name": custom.name,
+                "error_code": custom.error_code,
+                "value": format!("{:?}", custom.value),
+            }),
+        }
+    }
+
+    fn order_update_data(update: &OrderUpdate) -> Value {
+        let action = match update.notify_type {
+            0 => "opened",
+            1 => "closed",
+            2 => "modified",
+            _ => "account",
+        };
+        json!({
+            "notify_id": update.notify_id,
+            "notify_type": update.notify_type,
+            "action": action,
+            "order": update.order,
+        })
+    }
+}
+
+impl EventAdapter for JsonSchemaAdapter {
+    type Output = Value;
+
+    fn adapt(&self, event: &TimedEvent) -> Vec<Value> {
+        vec![self.to_value(event)]
+    }
+}
+
+/// ExecType (150)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecType {
+    New,
+    Canceled,
+    Replaced,
+    Rejected,
+    Trade,
+}
+
+impl ExecType {
+    /// FIX 字段值
+    pub fn code(&self) -> char {
+        match self {
+            ExecType::New => '0',
+            ExecType::Canceled => '4',
+            ExecType::Replaced => '5',
+            ExecType::Rejected => '8',
+            ExecType::Trade => 'F',
+        }
+    }
+}
+
+/// OrdStatus (39)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrdStatus {
+    New,
+    Filled,
+    Canceled,
+    Rejected,
+}
+
+impl OrdStatus {
+    /// FIX 字段值
+    pub fn code(&self) -> char {
+        match self {
+            OrdStatus::New => '0',
+            OrdStatus::Filled => '2',
+            OrdStatus::Canceled => '4',
+            OrdStatus::Rejected => '8',
+        }
+    }
+}
+
+/// Side (54)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// FIX 字段值
+    pub fn code(&self) -> char {
+        match self {
+            Side::Buy => '1',
+            Side::Sell => '2',
+        }
+    }
+
+    fn of(order_type: OrderType) -> Self {
+        if order_type.is_buy() {
+            Side::Buy
+        } else {
+            Side::Sell
+        }
+    }
+
+    fn opposite(&self) -> Self {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+}
+
+/// 类 FIX 4.4 ExecutionReport (35=8)
+///
+/// 只包含执行报告的业务字段，不含会话层头尾 (BeginString/MsgSeqNum/CheckSum 等)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionReport {
+    /// Account (1)
+    pub account: Option<String>,
+    /// ClOrdID (11): 交易请求 request_id
+    pub cl_ord_id: Option<String>,
+    /// OrderID (37): MT4 ticket，未生成订单时为 "NONE"
+    pub order_id: String,
+    /// ExecID (17)
+    pub exec_id: String,
+    /// ExecType (150)
+    pub exec_type: ExecType,
+    /// OrdStatus (39)
+    pub ord_status: OrdStatus,
+    /// Symbol (55)
+    pub symbol: String,
+    /// Side (54)
+    pub side: Side,
+    /// OrdType (40): '1' 市价，'2' 限价，'3' 止损
+    pub ord_type: char,
+    /// OrderQty (38): 手数
+    pub order_qty: f64,
+    /// Price (44): 挂单价格
+    pub price: Option<f64>,
+    /// LastQty (32)
+    pub last_qty: f64,
+    /// LastPx (31)
+    pub last_px: f64,
+    /// LeavesQty (151)
+    pub leaves_qty: f64,
+    /// CumQty (14)
+    pub cum_qty: f64,
+    /// AvgPx (6)
+    pub avg_px: f64,
+    /// TransactTime (60): UTC 时间，输出为 `YYYYMMDD-HH:MM:SS.sss`
+    pub transact_time: Option<SystemTime>,
+    /// Text (58)
+    pub text: Option<String>,
+}
+
+impl ExecutionReport {
+    /// 按 FIX tag 顺序输出字段 (含 35=8)
+    pub fn to_fix_fields(&self) -> Vec<(u32, String)> {
+        let mut fields = vec![(35, "8".to_string())];
+        if let Some(account) = &self.account {
+            fields.push((1, account.clone()));
+        }
+        if let Some(cl_ord_id) = &self.cl_ord_id {
+            fields.push((11, cl_ord_id.clone()));
+        }
+        fields.push((37, self.order_id.clone()));
+        fields.push((17, self.exec_id.clone()));
+        fields.push((150, self.exec_type.code().to_string()));
+        fields.push((39, self.ord_status.code().to_string()));
+        fields.push((55, self.symbol.clone()));
+        fields.push((54, self.side.code().to_string()));
+        fields.push((40, self.ord_type.to_string()));
+        fields.push((38, self.order_qty.to_string()));
+        if let Some(price) = self.price {
+            fields.push((44, price.to_string()));
+        }
+        fields.push((32, self.last_qty.to_string()));
+        fields.push((31, self.last_px.to_string()));
+        fields.push((151, self.leaves_qty.to_string()));
+        fields.push((14, self.cum_qty.to_string()));
+        fields.push((6, self.avg_px.to_string()));
+        if let Some(time) = self.transact_time {
+            fields.push((60, utc_timestamp(time)));
+        }
+        if let Some(text) = &self.text {
+            fields.push((58, text.clone()));
+        }
+        fields
+    }
+
+    /// 以 tag=value 形式输出，字段间以 `separator` 分隔 (标准 FIX 为 '\x01')
+    pub fn to_fix_string(&self, separator: char) -> String {
+        self.to_fix_fields()
+            .iter()
+            .map(|(tag, value)| format!("{}={}{}", tag, value, separator))
+            .collect()
+    }
+}
+
+/// FIX ExecutionReport 适配器
+///
+/// 映射规则:
+/// - 订单新建 (notify_type 0): 挂单为 New，市价单为成交 (Trade/Filled)
+/// - 订单关闭 (notify_type 1): 挂单为 Canceled，持仓为反方向成交，LastPx 为平仓价
+/// - 订单修改 (notify_type 2): Replaced
+/// - 交易失败/超时: Rejected
+/// - 其他事件不输出
+///
+/// 订单时间为经纪商服务器时间，TransactTime (60) 通过 `with_clock()` 设置的时钟偏移换算为 UTC；
+/// 未设置或尚无偏移样本时，`adapt()` 使用事件的本地 UTC 接收时间。
+#[derive(Debug, Clone, Default)]
+pub struct FixAdapter {
+    /// 填入 Account (1) 字段的账号
+    pub account: Option<String>,
+    /// 服务器时间换算 UTC 用的时钟偏移
+    pub clock: Option<DriftEstimator>,
+}
+
+impl FixAdapter {
+    /// 创建适配器，设置 Account 字段
+    pub fn new(account: Option<String>) -> Self {
+        Self { account, clock: None }
+    }
+
+    /// 设置时钟偏移 (通常为 `Mt4Client::drift_estimator()`)
+    pub fn with_clock(mut self, clock: DriftEstimator) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// 服务器时间换算为 UTC (没有偏移时为 None)
+    fn server_to_utc(&self, server_time: i64) -> Option<SystemTime> {
+        self.clock.as_ref()?.server_to_utc(server_time)
+    }
+
+    /// 订单更新转换为执行报告 (账户更新返回 None)
+    pub fn from_order_update(&self, update: &OrderUpdate) -> Option<ExecutionReport> {
+        let order = &update.order;
+        let mut report = self.order_report(order, format!("{}-{}", order.ticket, update.notify_id));
+        match (update.notify_type, order.is_pending()) {
+            (0, true) => {}
+            (0, false) => {
+                report.exec_type = ExecType::Trade;
+                report.ord_status = OrdStatus::Filled;
+                Self::fill(&mut report, order.volume, order.open_price);
+                report.transact_time = self.server_to_utc(order.open_time);
+            }
+            (1, true) => {
+                report.exec_type = ExecType::Canceled;
+                report.ord_status = OrdStatus::Canceled;
+                report.leaves_qty = 0.0;
+                report.transact_time = self.server_to_utc(order.close_time);
+            }
+            (1, false) => {
+                report.exec_type = ExecType::Trade;
+                report.ord_status = OrdStatus::Filled;
+                report.side = report.side.opposite();
+                report.ord_type = '1';
+                report.price = None;
+                Self::fill(&mut report, order.volume, order.close_price);
+                report.transact_time = self.server_to_utc(order.close_time);
+                report.text = Some(format!("close #{}", order.ticket));
+            }
+            (2, pending) => {
+                report.exec_type = ExecType::Replaced;
+                if !pending {
+                    report.ord_status = OrdStatus::Filled;
+                    Self::fill(&mut report, order.volume, order.open_price);
+                    report.last_qty = 0.0;
+                    report.last_px = 0.0;
+                }
+            }
+            _ => return None,
+        }
+        Some(report)
+    }
+
+    fn order_report(&self, order: &Order, exec_id: String) -> ExecutionReport {
+        let pending = order.is_pending();
+        ExecutionReport {
+            account: self.account.clone(),
+            cl_ord_id: None,
+            order_id: order.ticket.to_string(),
+            exec_id,
+            exec_type: ExecType::New,
+            ord_status: OrdStatus::New,
+            symbol: order.symbol.to_string(),
+            side: Side::of(order.order_type),
+            ord_type: Self::ord_type(order.order_type),
+            order_qty: order.volume,
+            price: pending.then_some(order.open_price),
+            last_qty: 0.0,
+            last_px: 0.0,
+            leaves_qty: order.volume,
+            cum_qty: 0.0,
+            avg_px: 0.0,
+            transact_time: None,
+            text: None,
+        }
+    }
+
+    fn rejected(&self, request: Option<(i32, &TradeRequest)>, exec_id: String, text: String) -> ExecutionReport {
+        ExecutionReport {
+            account: self.account.clone(),
+            cl_ord_id: request.map(|(id, _)| id.to_string()),
+            order_id: request
+                .filter(|(_, r)| r.ticket.get() > 0)
+                .map(|(_, r)| r.ticket.to_string())
+                .unwrap_or_else(|| "NONE".to_string()),
+            exec_id,
+            exec_type: ExecType::Rejected,
+            ord_status: OrdStatus::Rejected,
+            symbol: request.map(|(_, r)| r.symbol.to_string()).unwrap_or_default(),
+            side: request.map(|(_, r)| Side::of(r.order_type)).unwrap_or(Side::Buy),
+            ord_type: request.map(|(_, r)| Self::ord_type(r.order_type)).unwrap_or('1'),
+            order_qty: request.map(|(_, r)| r.volume).unwrap_or(0.0),
+            price: None,
+            last_qty: 0.0,
+            last_px: 0.0,
+            leaves_qty: 0.0,
+            cum_qty: 0.0,
+            avg_px: 0.0,
+            transact_time: None,
+            text: Some(text),
+        }
+    }
+
+    fn fill(report: &mut ExecutionReport, volume: f64, price: f64) {
+        report.last_qty = volume;
+        report.last_px = price;
+        report.cum_qty = volume;
+        report.leaves_qty = 0.0;
+        report.avg_px = price;
+    }
+
+    fn ord_type(order_type: OrderType) -> char {
+        match order_type {
+            OrderType::Buy | OrderType::Sell => '1',
+            OrderType::BuyLimit | OrderType::SellLimit => '2',
+            OrderType::BuyStop | OrderType::SellStop => '3',
+        }
+    }
+}
+
+impl EventAdapter for FixAdapter {
+    type Output = ExecutionReport;
+
+    fn adapt(&self, event: &TimedEvent) -> Vec<ExecutionReport> {
+        let exec_id = format!("{:.6}", event.time.utc_secs());
+        let mut reports = match &event.event {
+            Mt4Event::OrderUpdate(update) => self.from_order_update(update).into_iter().collect(),
+            Mt4Event::OrderUpdates(updates) => updates.iter().filter_map(|u| self.from_order_update(u)).collect(),
+            Mt4Event::TradeFailed { code, message } => {
+                vec![self.rejected(None, exec_id, format!("{}: {}", code, message))]
+            }
+            Mt4Event::TradeTimeout { request_id, request, elapsed_secs } => vec![self.rejected(
+                Some((*request_id, request)),
+                exec_id,
+                format!("Trade timeout after {:.0}s", elapsed_secs),
+            )],
+            _ => Vec::new(),
+        };
+        for report in &mut reports {
+            report.transact_time.get_or_insert(event.time.utc);
+        }
+        reports
+    }
+}
+
+/// FIX UTCTimestamp 格式 (`YYYYMMDD-HH:MM:SS.sss`)
+fn utc_timestamp(time: SystemTime) -> String {
+    let millis = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64);
+    let secs = millis.div_euclid(1000);
+    let (year, month, day) = civil_date(secs.div_euclid(86_400));
+    let seconds_of_day = secs.rem_euclid(86_400);
+    format!(
+        "{:04}{:02}{:02}-{:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3_600,
+        seconds_of_day % 3_600 / 60,
+        seconds_of_day % 60,
+        millis.rem_euclid(1000)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::EventTime;
+    use std::time::Duration;
+
+    fn order(ticket: i32, order_type: OrderType, close_time: i64) -> Order {
+        Order {
+            close_time,
+            close_price: if close_time > 0 { 1.09 } else { 0.0 },
+            ..Order::for_test(ticket, "EURUSD", order_type, 0.1, 1.08)
+        }
+    }
+
+    fn update(notify_type: i32, order: Order) -> OrderUpdate {
+        OrderUpdate { notify_id: 7, notify_type, df: 0.0, xh: 0.0, raw_size: 185, order, related_order: None }
+    }
+
+    fn timed(event: Mt4Event) -> TimedEvent {
+        TimedEvent { time: EventTime::now(event.server_time()), event, seq: 0 }
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let value = JsonSchemaAdapter.to_value(&timed(Mt4Event::OrderUpdate(update(0, order(1, OrderType::Buy, 0)))));
+        assert_eq!(value["schema"], EVENT_SCHEMA_VERSION);
+        assert_eq!(value["type"], "OrderUpdate");
+        assert_eq!(value["server_time"], 1_700_000_000);
+        assert_eq!(value["data"]["action"], "opened");
+        assert_eq!(value["data"]["order"]["ticket"], 1);
+
+        let value = JsonSchemaAdapter.to_value(&timed(Mt4Event::RawMessage { command: 99, error_code: 0, data: vec![0xab].into() }));
+        assert_eq!(value["data"]["data"], "ab");
+    }
+
+    #[test]
+    fn test_fix_execution_reports() {
+        let adapter = FixAdapter::new(Some("31313724".to_string()));
+
+        // 市价开仓: 成交
+        let open = adapter.adapt(&timed(Mt4Event::OrderUpdate(update(0, order(1, OrderType::Buy, 0)))));
+        assert_eq!(open.len(), 1);
+        assert_eq!((open[0].exec_type, open[0].ord_status), (ExecType::Trade, OrdStatus::Filled));
+        assert_eq!(open[0].last_px, 1.08);
+        assert!(open[0].to_fix_string('|').starts_with("35=8|1=31313724|37=1|17=1-7|150=F|39=2|55=EURUSD|54=1|"));
+
+        // 平仓: 反方向成交
+        let close = adapter.from_order_update(&update(1, order(1, OrderType::Buy, 1_700_000_600))).unwrap();
+        assert_eq!(close.side, Side::Sell);
+        assert_eq!(close.last_px, 1.09);
+
+        // 挂单删除: Canceled
+        let cancel = adapter.from_order_update(&update(1, order(2, OrderType::BuyLimit, 1_700_000_600))).unwrap();
+        assert_eq!((cancel.exec_type, cancel.ord_status), (ExecType::Canceled, OrdStatus::Canceled));
+
+        // 交易失败: Rejected
+        let rejected = adapter.adapt(&timed(Mt4Event::TradeFailed { code: 134, message: "Not enough money".to_string() }));
+        assert_eq!(rejected[0].ord_status, OrdStatus::Rejected);
+        assert_eq!(rejected[0].order_id, "NONE");
+
+        assert!(adapter.adapt(&timed(Mt4Event::Pong)).is_empty());
+    }
+
+    #[test]
+    fn test_fix_transact_time() {
+        // 服务器为 GMT+2: 服务器时间 1_700_007_800 对应 UTC 2023-11-14 22:23:20
+        let mut clock = DriftEstimator::default();
+        clock.add_sample(1_700_000_000 + 7200, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let adapter = FixAdapter::new(None).with_clock(clock);
+        let close = adapter.from_order_update(&update(1, order(1, OrderType::Buy, 1_700_007_800))).unwrap();
+        assert!(close.to_fix_string('|').contains("|60=20231114-22:23:20.000|"));
+
+        assert_eq!(utc_timestamp(UNIX_EPOCH + Duration::from_millis(951_782_400_007)), "20000229-00:00:00.007");
+
+        // 没有时钟偏移时使用事件接收时间
+        let adapter = FixAdapter::new(None);
+        let closed = update(1, order(1, OrderType::Buy, 1_700_007_800));
+        assert_eq!(adapter.from_order_update(&closed).unwrap().transact_time, None);
+        let event = timed(Mt4Event::OrderUpdate(closed));
+        assert_eq!(adapter.adapt(&event)[0].transact_time, Some(event.time.utc));
+    }
+}
@@ -0,0 +1,294 @@
+//! 启动自检
+//!
+//! `Mt4Client::self_test()` 在不连接服务器的情况下验证客户端依赖的加密和协议假设:
+//!
+//! - `auth_key`: 预设认证密钥的解码结果
+//! - `aes_known_answer`: 认证密钥下 AES-256-CBC 的已知答案
+//! - `aes_round_trip`: 认证密钥和会话密钥下各种长度的加密/解密往返，以及数据包封装
+//! - `trade_request_codec`: `TradeRequest` 序列化与解析互逆
+//! - `parser_fixtures`: 订单、订单更新、交易响应、账户信息和K线解析器的固定样本
+//!
+//! 部署时运行一次，可以在构建损坏 (依赖升级、交叉编译、字节序等问题) 影响真实账户之前发现它:
+//!
+//! ```
+//! let report = mt4_client::Mt4Client::self_test();
+//! assert!(report.passed(), "{}", report);
+//! ```
+
+use crate::crypto::Mt4Crypto;
+use crate::packet::build_packet;
+use crate::protocol::{Command, OrderType, AUTH_KEY_HEX};
+use crate::types::{
+    AccountInfo, Candle, Order, OrderUpdate, Symbol, Ticket, TradeRequest, TradeResponse, ACCOUNT_INFO_SIZE,
+};
+use std::fmt;
+
+/// `b"MT4 self-test"` 用认证密钥、零 IV 加密后的密文
+const AES_KNOWN_ANSWER: &str = "ff6e3864d986e8e01f0c4b8696f809b5";
+
+/// 单项检查结果
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SelfTestCheck {
+    /// 检查名称
+    pub name: &'static str,
+    /// 是否通过
+    pub passed: bool,
+    /// 失败原因 (通过时为空)
+    pub detail: String,
+}
+
+/// 自检报告
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct SelfTestReport {
+    /// 各项检查结果 (按执行顺序)
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    /// 是否全部通过
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    /// 未通过的检查
+    pub fn failures(&self) -> Vec<&SelfTestCheck> {
+        self.checks.iter().filter(|c| !c.passed).collect()
+    }
+
+    fn record(&mut self, name: &'static str, result: std::result::Result<(), String>) {
+        let (passed, detail) = match result {
+            Ok(()) => (true, String::new()),
+            Err(detail) => (false, detail),
+        };
+        if !passed {
+            tracing::error!("Self-test {} failed: {}", name, detail);
+        }
+        self.checks.push(SelfTestCheck { name, passed, detail });
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            if check.passed {
+                writeln!(f, "[ OK ] {}", check.name)?;
+            } else {
+                writeln!(f, "[FAIL] {}: {}", check.name, check.detail)?;
+            }
+        }
+        write!(f, "{}/{} passed", self.checks.iter().filter(|c| c.passed).count(), self.checks.len())
+    }
+}
+
+/// 运行全部检查
+pub(crate) fn run() -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+    report.record("auth_key", check_auth_key());
+    report.record("aes_known_answer", check_aes_known_answer());
+    report.record("aes_round_trip", check_aes_round_trip());
+    report.record("trade_request_codec", check_trade_request_codec());
+    report.record("parser_fixtures", check_parser_fixtures());
+    report
+}
+
+fn ensure(condition: bool, detail: impl FnOnce() -> String) -> std::result::Result<(), String> {
+    if condition {
+        Ok(())
+    } else {
+        Err(detail())
+    }
+}
+
+fn check_auth_key() -> std::result::Result<(), String> {
+    let crypto = Mt4Crypto::new().map_err(|e| e.to_string())?;
+    let key = crypto.auth_key_hex();
+    ensure(key == AUTH_KEY_HEX, || format!("认证密钥解码为 {}", key))?;
+    ensure(crypto.session_key_hex().is_none(), || "新建加密器已有会话密钥".to_string())
+}
+
+fn check_aes_known_answer() -> std::result::Result<(), String> {
+    let crypto = Mt4Crypto::new().map_err(|e| e.to_string())?;
+    let encrypted = hex::encode(crypto.encrypt(b"MT4 self-test", true).map_err(|e| e.to_string())?);
+    ensure(encrypted == AES_KNOWN_ANSWER, || format!("密文为 {}", encrypted))
+}
+
+fn check_aes_round_trip() -> std::result::Result<(), String> {
+    let mut crypto = Mt4Crypto::new().map_err(|e| e.to_string())?;
+    let mut session = crypto.clone();
+    session
+        .set_session_key("00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff")
+        .map_err(|e| e.to_string())?;
+
+    for len in [0, 1, 15, 16, 17, 95, 4096] {
+        let data: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+        for (label, crypto) in [("认证密钥", &crypto), ("会话密钥", &session)] {
+            let encrypted = crypto.encrypt(&data, false).map_err(|e| e.to_string())?;
+            ensure(encrypted.len() % 16 == 0 && encrypted.len() > data.len(), || {
+                format!("{} {} 字节的密文长度为 {}", label, len, encrypted.len())
+            })?;
+            let decrypted = crypto.decrypt(&encrypted).map_err(|e| e.to_string())?;
+            ensure(decrypted == data, || format!("{} {} 字节往返后内容不一致", label, len))?;
+        }
+    }
+
+    // 数据包: 8字节头 (密文长度, 1) + 密文 [u16 数据包 ID][u16 命令][数据]
+    crypto.set_session_key(&session.session_key_hex().unwrap_or_default()).map_err(|e| e.to_string())?;
+    let packet = build_packet(1, Command::Ping as u16, b"ping", &crypto, false).map_err(|e| e.to_string())?;
+    let length = u32::from_le_bytes([packet[0], packet[1], packet[2], packet[3]]) as usize;
+    ensure(length == packet.len() - 8, || format!("包头长度 {} 与密文长度 {} 不符", length, packet.len() - 8))?;
+    let payload = session.decrypt(&packet[8..]).map_err(|e| e.to_string())?;
+    let command = payload.get(2..4).map(|c| u16::from_le_bytes([c[0], c[1]]));
+    ensure(command == Some(Command::Ping as u16) && payload.get(4..) == Some(&b"ping"[..]), || {
+        format!("数据包解析为 {}", hex::encode(&payload))
+    })
+}
+
+fn check_trade_request_codec() -> std::result::Result<(), String> {
+    let symbol = |name: &str| Symbol::new(name).map_err(|e| e.to_string());
+    let eurusd = symbol("EURUSD")?;
+    let mut pending = TradeRequest::buy_limit(&symbol("XAUUSD")?, 1.5, 1925.35, 1910.0, 1950.0);
+    pending.comment = "self-test".to_string();
+    pending.expiration = 1_700_086_400;
+    let requests = [
+        TradeRequest::buy(&eurusd, 0.1, 1.075, 1.09),
+        TradeRequest::sell(&symbol("GBPUSD.m")?, 2.0, 0.0, 0.0),
+        pending,
+        TradeRequest::close(Ticket(123_456_789), &symbol("USDJPY")?, 0.5),
+        TradeRequest::modify(Ticket(42), &eurusd, OrderType::SellStop, 1.05, 1.06, 1.03),
+        TradeRequest::cancel(Ticket(43), &eurusd),
+        TradeRequest::quote(&symbol("BTCUSD")?, 0.01),
+    ];
+    for (i, mut request) in requests.into_iter().enumerate() {
+        request.request_id = 1000 + i as i32;
+        let bytes = request.to_bytes();
+        ensure(bytes.len() == 95, || format!("#{} 序列化为 {} 字节", i, bytes.len()))?;
+        let decoded = TradeRequest::from_bytes(&bytes);
+        ensure(decoded.as_ref() == Some(&request), || format!("#{} 解析为 {:?}", i, decoded))?;
+    }
+    Ok(())
+}
+
+fn check_parser_fixtures() -> std::result::Result<(), String> {
+    let order = Order {
+        ticket: Ticket(987_654),
+        symbol: Symbol::new("EURUSD").map_err(|e| e.to_string())?,
+        digits: 5,
+        order_type: OrderType::Sell,
+        volume: 0.25,
+        open_time: 1_700_000_000,
+        open_price: 1.08321,
+        sl: 1.09,
+        tp: 1.07,
+        close_time: 1_700_003_600,
+        close_price: 1.08111,
+        commission: -1.75,
+        swap: -0.42,
+        profit: 52.5,
+        comment: "fixture".to_string(),
+    };
+    let same = |a: &Order, b: &Order| serde_json::to_value(a).ok() == serde_json::to_value(b).ok();
+
+    let parsed = Order::from_bytes(&order_fixture(&order), 0);
+    ensure(parsed.as_ref().is_some_and(|p| same(p, &order)), || format!("订单解析为 {:?}", parsed))?;
+
+    // 订单更新: [notify_id][notify_type][df][xh][订单]
+    let mut data = Vec::new();
+    data.extend_from_slice(&7i32.to_le_bytes());
+    data.extend_from_slice(&1i32.to_le_bytes());
+    data.extend_from_slice(&10_052.5f64.to_le_bytes());
+    data.extend_from_slice(&100.0f64.to_le_bytes());
+    data.extend_from_slice(&order_fixture(&order));
+    let updates = OrderUpdate::parse_all(&data.repeat(2));
+    ensure(
+        updates.len() == 2
+            && updates.iter().all(|u| u.notify_id == 7 && u.is_close_notification() && u.df == 10_052.5 && u.xh == 100.0 && same(&u.order, &order)),
+        || format!("订单更新解析为 {:?}", updates),
+    )?;
+
+    // 交易响应: [request_id][status][price1][price2][订单 × N]
+    let mut data = Vec::new();
+    data.extend_from_slice(&1001i32.to_le_bytes());
+    data.extend_from_slice(&0i32.to_le_bytes());
+    data.extend_from_slice(&1.08111f64.to_le_bytes());
+    data.extend_from_slice(&1.08125f64.to_le_bytes());
+    data.extend_from_slice(&order_fixture(&order));
+    let response = TradeResponse::from_bytes(&data);
+    ensure(
+        response.as_ref().is_some_and(|r| {
+            r.request_id == 1001 && r.status == 0 && r.price1 == 1.08111 && r.price2 == 1.08125 && r.orders.len() == 1
+        }),
+        || format!("交易响应解析为 {:?}", response),
+    )?;
+
+    let account = AccountInfo::from_bytes(&account_fixture());
+    ensure(
+        account.as_ref().is_some_and(|a| {
+            a.login == 31_313_724
+                && a.balance == 10_000.0
+                && a.equity == 10_052.5
+                && a.leverage == 500
+                && a.currency == "USD"
+                && a.server == "Broker-Demo"
+                && a.name == "Self Test"
+        }),
+        || format!("账户信息解析为 {:?}", account),
+    )?;
+
+    let candles = [
+        Candle { time: 1_700_000_000, open: 1.0831, high: 1.0845, low: 1.0822, close: 1.0840, volume: 1234.0 },
+        Candle { time: 1_700_000_060, open: 1.0840, high: 1.0841, low: 1.0830, close: 1.0833, volume: 87.0 },
+    ];
+    let data: Vec<u8> = candles.iter().flat_map(Candle::to_bytes).collect();
+    let parsed = Candle::parse_all(&data);
+    ensure(parsed == candles, || format!("K线解析为 {:?}", parsed))
+}
+
+/// 按 `Order::from_bytes` 的布局生成 161 字节订单数据
+fn order_fixture(order: &Order) -> Vec<u8> {
+    let mut data = vec![0u8; 161];
+    let mut put = |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    put(0, &order.ticket.get().to_le_bytes());
+    put(4, order.symbol.as_bytes());
+    put(16, &order.digits.to_le_bytes());
+    put(20, &(order.order_type as i32).to_le_bytes());
+    put(24, &((order.volume * 100.0).round() as i32).to_le_bytes());
+    put(28, &(order.open_time as i32).to_le_bytes());
+    put(36, &order.open_price.to_le_bytes());
+    put(44, &order.sl.to_le_bytes());
+    put(52, &order.tp.to_le_bytes());
+    put(60, &(order.close_time as i32).to_le_bytes());
+    put(93, &order.close_price.to_le_bytes());
+    put(101, &order.profit.to_le_bytes());
+    put(109, &order.swap.to_le_bytes());
+    put(121, order.comment.as_bytes());
+    put(153, &order.commission.to_le_bytes());
+    data
+}
+
+/// 按 `AccountInfo::from_bytes` 的布局生成账户信息块
+fn account_fixture() -> Vec<u8> {
+    let mut data = vec![0u8; ACCOUNT_INFO_SIZE];
+    let mut put = |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    let utf16 = |s: &str| s.encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+    put(1, &10_000.0f64.to_le_bytes());
+    put(9, &10_052.5f64.to_le_bytes());
+    put(17, &utf16("USD"));
+    put(49, &500i32.to_le_bytes());
+    put(53, &31_313_724i32.to_le_bytes());
+    put(58, &utf16("Broker-Demo"));
+    put(190, b"Self Test");
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        let report = run();
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checks.len(), 5);
+        assert!(report.to_string().ends_with("5/5 passed"));
+    }
+}
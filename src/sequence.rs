@@ -0,0 +1,82 @@
+//! 订单更新通知序号检测
+//!
+//! 同一会话中订单更新 (Command 10) 的 `notify_id` 单调递增。`NotifySequence` 记录最近一次的序号，
+//! 出现跳号 (断线、丢包或服务器端队列溢出导致漏收通知) 时返回缺失的区间，客户端据此发出
+//! `Mt4Event::MissedUpdates`，提示本地持仓状态可能已过期，应重新请求订单列表。
+//!
+//! 余额/信用操作 (见 `funding` 模块) 与订单更新共用序号，同样需要计入。
+//! 每个连接使用新的检测器，重连后的第一个通知只作为基准。
+
+use serde::Serialize;
+
+/// 缺失的通知序号区间 (含两端)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MissedUpdates {
+    /// 第一个缺失的序号
+    pub from: i32,
+    /// 最后一个缺失的序号
+    pub to: i32,
+}
+
+impl MissedUpdates {
+    /// 缺失的通知数量
+    pub fn count(&self) -> u32 {
+        self.to.abs_diff(self.from) + 1
+    }
+}
+
+/// 通知序号检测器
+#[derive(Debug, Clone, Default)]
+pub struct NotifySequence {
+    last: Option<i32>,
+}
+
+impl NotifySequence {
+    /// 创建检测器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 最近一次的通知序号
+    pub fn last(&self) -> Option<i32> {
+        self.last
+    }
+
+    /// 记录一个通知序号，与上一个之间有缺失时返回缺失区间 (重复或更早的序号忽略)
+    pub fn observe(&mut self, notify_id: i32) -> Option<MissedUpdates> {
+        let last = match self.last {
+            Some(last) if notify_id <= last => return None,
+            Some(last) => last,
+            None => {
+                self.last = Some(notify_id);
+                return None;
+            }
+        };
+        self.last = Some(notify_id);
+        (notify_id > last + 1).then(|| MissedUpdates { from: last + 1, to: notify_id - 1 })
+    }
+
+    /// 记录一批通知序号 (按序号排序后处理)
+    pub fn observe_all(&mut self, notify_ids: impl IntoIterator<Item = i32>) -> Vec<MissedUpdates> {
+        let mut notify_ids: Vec<i32> = notify_ids.into_iter().collect();
+        notify_ids.sort_unstable();
+        notify_ids.into_iter().filter_map(|id| self.observe(id)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_gaps() {
+        let mut sequence = NotifySequence::new();
+        assert!(sequence.observe_all([11, 10]).is_empty());
+        assert!(sequence.observe(11).is_none());
+
+        let missed = sequence.observe_all([16, 12]);
+        assert_eq!(missed, vec![MissedUpdates { from: 13, to: 15 }]);
+        assert_eq!((missed[0].count(), sequence.last()), (3, Some(16)));
+        assert_eq!(sequence.observe(18), Some(MissedUpdates { from: 17, to: 17 }));
+    }
+}
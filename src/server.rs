@@ -0,0 +1,493 @@
+//! 内置 REST 服务 (需要开启 `server` feature)
+//!
+//! 通过 HTTP 暴露一个已连接的客户端，非 Rust 服务可以共用同一条维护中的连接下单。
+//! 与状态页相同，不依赖额外的 HTTP 框架，直接基于 tokio TcpListener:
+//!
+//! | 方法 | 路径 | 说明 |
+//! |------|------|------|
+//! | `POST` | `/orders` | 开仓或挂单，返回 `TradeResponse` |
+//! | `DELETE` | `/orders/{ticket}` | 持仓全部平仓，挂单取消，返回 `TradeResponse` |
+//! | `GET` | `/positions` | 本地缓存的持仓和挂单 (`[Order...]`) |
+//! | `GET` | `/account` | 账户信息 (未收到时为 `null`) |
+//! | `GET` | `/events` | Server-Sent Events 事件流，`data` 为 `mt4.event.v1` JSON (见 `schema` 模块) |
+//!
+//! 事件流的 `id` 为事件序号。断线重连时携带 `Last-Event-ID` 请求头 (浏览器 `EventSource` 自动携带)
+//! 或 `?since=<序号>` 参数，先回放之后的最近事件再继续推送实时事件。
+//!
+//! `POST /orders` 的请求体:
+//!
+//! ```text
+//! {
+//!   "symbol": "EURUSD",
+//!   "order_type": "Buy",     // Buy / Sell 为市价单，BuyLimit / SellLimit / BuyStop / SellStop 为挂单
+//!   "volume": 0.1,
+//!   "price": 0.0,            // 以下字段可省略
+//!   "sl": 0.0,
+//!   "tp": 0.0,
+//!   "slippage": 50,
+//!   "comment": "grid-bot",
+//!   "expiration": 0
+//! }
+//! ```
+//!
+//! 失败时返回 `{"error", "kind", "code"}`，`kind` 为 `ErrorKind`，`code` 为交易错误码 (本地错误为 null)。
+//! 请求体无法解析时返回 400，参数无效 422，频率限制 429，未连接 503，交易超时 504，
+//! 本地文件读写或序列化失败 500，其他被拒绝的交易 409。
+//!
+//! ```no_run
+//! use mt4_client::server::RestServer;
+//! use mt4_client::{LoginCredentials, Mt4Client};
+//!
+//! # async fn example(credentials: LoginCredentials) -> mt4_client::Result<()> {
+//! let handle = Mt4Client::new().spawn();
+//! handle.connect(&credentials).await?;
+//! let server = RestServer::bind_with_token("127.0.0.1:8080".parse().unwrap(), handle, "secret").await?;
+//! # let _ = server;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! 服务可以下单，应只监听本机地址或通过 `bind_with_token` 要求访问令牌 (`Authorization: Bearer <token>`)。
+
+use crate::error::{ErrorKind, Mt4Error, Result};
+use crate::handle::Mt4Handle;
+use crate::protocol::OrderType;
+use crate::schema::JsonSchemaAdapter;
+use crate::types::{Symbol, Ticket, TradeRequest};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// 请求头最大长度
+const MAX_HEADER_SIZE: usize = 8192;
+
+/// 请求体最大长度
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// 默认滑点 (点)
+const DEFAULT_SLIPPAGE: i32 = 50;
+
+/// `POST /orders` 请求体
+#[derive(Debug, Clone, Deserialize)]
+struct OrderBody {
+    symbol: String,
+    order_type: OrderType,
+    volume: f64,
+    #[serde(default)]
+    price: f64,
+    #[serde(default)]
+    sl: f64,
+    #[serde(default)]
+    tp: f64,
+    #[serde(default)]
+    slippage: Option<i32>,
+    #[serde(default)]
+    comment: String,
+    #[serde(default)]
+    expiration: i32,
+}
+
+impl OrderBody {
+    /// 转换为交易请求，市价单类型为 66，挂单为 67
+    fn to_request(&self) -> Result<TradeRequest> {
+        let trade_type = match self.order_type {
+            OrderType::Buy | OrderType::Sell => 66,
+            _ => 67,
+        };
+        Ok(TradeRequest {
+            trade_type,
+            order_type: self.order_type,
+            ticket: Ticket(0),
+            symbol: Symbol::new(&self.symbol)?,
+            volume: self.volume,
+            price: self.price,
+            sl: self.sl,
+            tp: self.tp,
+            slippage: self.slippage.unwrap_or(DEFAULT_SLIPPAGE),
+            comment: self.comment.clone(),
+            expiration: self.expiration,
+            request_id: 0,
+        })
+    }
+}
+
+/// 解析后的 HTTP 请求
+struct HttpRequest {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    last_event_id: Option<String>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// 事件流续订的起始序号: `Last-Event-ID` 请求头优先，其次为 `since` 查询参数
+    fn resume_from(&self) -> Option<u64> {
+        let query = self.path.split_once('?').map_or("", |(_, query)| query);
+        let since = query.split('&').find_map(|pair| pair.strip_prefix("since="));
+        self.last_event_id.as_deref().or(since).and_then(|seq| seq.parse().ok())
+    }
+}
+
+/// 普通 JSON 响应
+struct HttpResponse {
+    status: &'static str,
+    body: Value,
+}
+
+impl HttpResponse {
+    fn ok(body: Value) -> Self {
+        Self { status: "200 OK", body }
+    }
+
+    fn error(status: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into(), "kind": null, "code": null }),
+        }
+    }
+
+    /// 按错误类别映射状态码
+    fn from_error(error: &Mt4Error) -> Self {
+        let kind = error.kind();
+        let status = match kind {
+            ErrorKind::InvalidRequest => "422 Unprocessable Entity",
+            ErrorKind::RateLimited => "429 Too Many Requests",
+            ErrorKind::Connection | ErrorKind::Busy => "503 Service Unavailable",
+            ErrorKind::Timeout => "504 Gateway Timeout",
+            ErrorKind::Auth | ErrorKind::Protocol | ErrorKind::Other => "502 Bad Gateway",
+            ErrorKind::Io | ErrorKind::Serialization => "500 Internal Server Error",
+            _ => "409 Conflict",
+        };
+        let code = match error {
+            Mt4Error::Trade { code, .. } => Some(*code),
+            _ => None,
+        };
+        Self {
+            status,
+            body: json!({ "error": error.to_string(), "kind": format!("{:?}", kind), "code": code }),
+        }
+    }
+
+    fn from_result<T: serde::Serialize>(result: Result<T>) -> Self {
+        match result {
+            Ok(value) => Self::ok(json!(value)),
+            Err(e) => Self::from_error(&e),
+        }
+    }
+}
+
+/// REST 服务
+pub struct RestServer {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl RestServer {
+    /// 绑定地址并开始提供服务 (不校验访问令牌)
+    pub async fn bind(addr: SocketAddr, handle: Mt4Handle) -> Result<Self> {
+        Self::start(addr, handle, None).await
+    }
+
+    /// 绑定地址并开始提供服务，请求须带 `Authorization: Bearer <token>`
+    pub async fn bind_with_token(addr: SocketAddr, handle: Mt4Handle, token: impl Into<String>) -> Result<Self> {
+        Self::start(addr, handle, Some(token.into())).await
+    }
+
+    async fn start(addr: SocketAddr, handle: Mt4Handle, token: Option<String>) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| Mt4Error::Connection(format!("REST 服务绑定 {} 失败: {}", addr, e)))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| Mt4Error::Connection(e.to_string()))?;
+        tracing::info!("REST server listening on http://{}", local_addr);
+
+        let token = Arc::new(token);
+        let task = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let handle = handle.clone();
+                        let token = token.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = Self::handle(stream, handle, token).await {
+                                tracing::debug!("REST connection error: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        tracing::warn!("REST accept error: {}", e);
+                    }
+                }
+            }
+        });
+
+        Ok(Self { local_addr, task })
+    }
+
+    /// 实际监听的地址 (绑定端口 0 时可用于获取分配的端口)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// 停止服务 (已建立的事件流连接随客户端断开)
+    pub fn shutdown(&self) {
+        self.task.abort();
+    }
+
+    /// 处理单个 HTTP 连接
+    async fn handle(
+        mut stream: TcpStream,
+        handle: Mt4Handle,
+        token: Arc<Option<String>>,
+    ) -> std::io::Result<()> {
+        let request = match read_request(&mut stream).await? {
+            Some(request) => request,
+            None => {
+                let response = HttpResponse::error("400 Bad Request", "malformed request");
+                return write_response(&mut stream, response).await;
+            }
+        };
+
+        if let Some(expected) = token.as_ref() {
+            let provided = request.authorization.as_deref().and_then(|a| a.strip_prefix("Bearer "));
+            if provided != Some(expected.as_str()) {
+                let response = HttpResponse::error("401 Unauthorized", "missing or invalid token");
+                return write_response(&mut stream, response).await;
+            }
+        }
+
+        if request.method == "GET" && request.path.split('?').next() == Some("/events") {
+            return stream_events(stream, handle, request.resume_from()).await;
+        }
+
+        let response = route(&request, &handle).await;
+        write_response(&mut stream, response).await
+    }
+}
+
+impl Drop for RestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 分发普通请求
+async fn route(request: &HttpRequest, handle: &Mt4Handle) -> HttpResponse {
+    let path = request.path.split('?').next().unwrap_or("");
+    match (request.method.as_str(), path) {
+        ("GET", "/positions") => HttpResponse::from_result(handle.positions().await),
+        ("GET", "/account") => HttpResponse::from_result(handle.account_info().await),
+        ("POST", "/orders") => {
+            let body: OrderBody = match serde_json::from_slice(&request.body) {
+                Ok(body) => body,
+                Err(e) => return HttpResponse::error("400 Bad Request", format!("invalid order body: {}", e)),
+            };
+            match body.to_request() {
+                Ok(trade) => HttpResponse::from_result(handle.send_trade_and_wait(trade).await),
+                Err(e) => HttpResponse::from_error(&e),
+            }
+        }
+        ("DELETE", path) if path.starts_with("/orders/") => {
+            let ticket = match path["/orders/".len()..].parse::<i32>() {
+                Ok(ticket) => Ticket(ticket),
+                Err(_) => return HttpResponse::error("400 Bad Request", "invalid ticket"),
+            };
+            let order = match handle.call(move |client| Box::pin(client.cached_order(ticket))).await {
+                Ok(Some(order)) => order,
+                Ok(None) => return HttpResponse::error("404 Not Found", format!("order #{} not found", ticket)),
+                Err(e) => return HttpResponse::from_error(&e),
+            };
+            let trade = if order.is_pending() {
+                TradeRequest::cancel(ticket, &order.symbol)
+            } else {
+                TradeRequest::close(ticket, &order.symbol, order.volume)
+            };
+            HttpResponse::from_result(handle.send_trade_and_wait(trade).await)
+        }
+        ("GET", _) | ("DELETE", _) | ("POST", _) => HttpResponse::error("404 Not Found", "not found"),
+        _ => HttpResponse::error("405 Method Not Allowed", "method not allowed"),
+    }
+}
+
+/// 读取请求头和 Content-Length 指定的请求体，格式错误或超长时返回 None
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<HttpRequest>> {
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    let header_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 || buffer.len() + n > MAX_HEADER_SIZE + MAX_BODY_SIZE {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if buffer.len() > MAX_HEADER_SIZE && !buffer[..MAX_HEADER_SIZE].windows(4).any(|w| w == b"\r\n\r\n") {
+            return Ok(None);
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or("").split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let path = parts.next().unwrap_or("/").to_string();
+
+    let mut content_length = 0usize;
+    let mut authorization = None;
+    let mut last_event_id = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            match value.parse() {
+                Ok(length) if length <= MAX_BODY_SIZE => content_length = length,
+                _ => return Ok(None),
+            }
+        } else if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("last-event-id") {
+            last_event_id = Some(value.to_string());
+        }
+    }
+
+    let mut body = buffer.split_off(header_end);
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    Ok(Some(HttpRequest { method, path, authorization, last_event_id, body }))
+}
+
+/// 写入 JSON 响应并关闭连接
+async fn write_response(stream: &mut TcpStream, response: HttpResponse) -> std::io::Result<()> {
+    let body = response.body.to_string();
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// 以 Server-Sent Events 推送事件，直到对端断开或客户端被丢弃
+///
+/// 只持有事件订阅，不持有句柄，事件流连接不会让后台任务保持运行。
+/// `resume_from` 为上次收到的事件序号时先回放之后的最近事件
+async fn stream_events(mut stream: TcpStream, handle: Mt4Handle, resume_from: Option<u64>) -> std::io::Result<()> {
+    let mut events = match resume_from {
+        Some(seq) => handle.subscribe_from(seq).await.unwrap_or_else(|_| handle.subscribe()),
+        None => handle.subscribe(),
+    };
+    drop(handle);
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+    stream.flush().await?;
+
+    let adapter = JsonSchemaAdapter;
+    while let Some(event) = events.recv_timed().await {
+        // 序号为 0 的事件不是客户端分发的，不设置 id，以免覆盖浏览器记录的续订位置
+        let id = if event.seq > 0 { format!("id: {}\n", event.seq) } else { String::new() };
+        let frame = format!("event: {}\n{}data: {}\n\n", event.event.kind(), id, adapter.to_value(&event));
+        stream.write_all(frame.as_bytes()).await?;
+        stream.flush().await?;
+    }
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{Mt4Client, Mt4Event};
+    use crate::clock::EventTime;
+    use crate::events::TimedEvent;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    async fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_rest_server() {
+        let client = Mt4Client::new();
+        let broadcast = client.broadcast_sender();
+        let server = RestServer::bind_with_token("127.0.0.1:0".parse().unwrap(), client.spawn(), "secret")
+            .await
+            .unwrap();
+        let addr = server.local_addr();
+
+        let positions = request(addr, "GET", "/positions", "").await;
+        assert!(positions.starts_with("HTTP/1.1 200 OK"));
+        assert!(positions.ends_with("[]"));
+        assert!(request(addr, "GET", "/account", "").await.ends_with("null"));
+        assert!(request(addr, "GET", "/nope", "").await.starts_with("HTTP/1.1 404"));
+        assert!(request(addr, "DELETE", "/orders/42", "").await.starts_with("HTTP/1.1 404"));
+
+        assert!(request(addr, "POST", "/orders", "{\"symbol\":").await.starts_with("HTTP/1.1 400"));
+        let invalid = request(addr, "POST", "/orders", r#"{"symbol":"","order_type":"Buy","volume":0.1}"#).await;
+        assert!(invalid.starts_with("HTTP/1.1 422"));
+        assert!(invalid.contains("\"kind\":\"InvalidRequest\""));
+        let offline = request(addr, "POST", "/orders", r#"{"symbol":"EURUSD","order_type":"Buy","volume":0.1}"#).await;
+        assert!(offline.starts_with("HTTP/1.1 503"));
+
+        // 缺少令牌
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /positions HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 401"));
+
+        // 事件流
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /events HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n")
+            .await
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("HTTP/1.1 200 OK"));
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+        }
+        broadcast
+            .send(TimedEvent { event: Mt4Event::Pong, time: EventTime::now(None), seq: 0 })
+            .unwrap();
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "event: Pong\n");
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("data: {") && line.contains("\"type\":\"Pong\""));
+    }
+}
@@ -0,0 +1,139 @@
+//! 会话录制与回放
+//!
+//! 录制文件为 JSON Lines，每行一个解密后的入站帧:
+//!
+//! ```text
+//! {"elapsed_ms":1532,"command":10,"error_code":0,"data":"01000000..."}
+//! ```
+//!
+//! `data` 为帧数据 (去掉随机字节、命令和错误码之后的部分) 的十六进制字符串。
+//! 录制通过 `Mt4Client::record_session()` 开启；`Mt4Client::replay_session()`
+//! 将文件中的帧按顺序交给与实时连接相同的解析流程，用于复现解析问题。
+
+use crate::error::{Mt4Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// 录制的入站帧
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// 距录制开始的时间 (毫秒)
+    pub elapsed_ms: u64,
+    /// 命令
+    pub command: u16,
+    /// 错误码
+    pub error_code: u8,
+    /// 帧数据
+    #[serde(with = "hex_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// 会话录制器
+#[derive(Debug)]
+pub struct SessionRecorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    started: Instant,
+    frames: usize,
+}
+
+impl SessionRecorder {
+    /// 创建录制文件 (已存在时覆盖)
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::create(&path).map_err(|e| {
+            Mt4Error::Io(format!("创建录制文件 {} 失败: {}", path.display(), e))
+        })?;
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+            started: Instant::now(),
+            frames: 0,
+        })
+    }
+
+    /// 记录一个入站帧 (每帧写入后立即刷新，进程崩溃时不丢失已录制的帧)
+    pub fn record(&mut self, command: u16, error_code: u8, data: &[u8]) -> Result<()> {
+        let frame = RecordedFrame {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            command,
+            error_code,
+            data: data.to_vec(),
+        };
+        let line = serde_json::to_string(&frame)
+            .map_err(|e| Mt4Error::Serialization(format!("序列化录制帧失败: {}", e)))?;
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| Mt4Error::Io(format!("写入录制文件 {} 失败: {}", self.path.display(), e)))?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// 已录制的帧数
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// 录制文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// 读取录制文件中的所有帧 (忽略空行)
+pub fn read_session(path: impl AsRef<Path>) -> Result<Vec<RecordedFrame>> {
+    let path = path.as_ref();
+    let file = File::open(path)
+        .map_err(|e| Mt4Error::Io(format!("打开录制文件 {} 失败: {}", path.display(), e)))?;
+    let mut frames = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| Mt4Error::Io(format!("读取录制文件失败: {}", e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let frame = serde_json::from_str(&line)
+            .map_err(|e| Mt4Error::Serialization(format!("录制文件第 {} 行格式错误: {}", i + 1, e)))?;
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// 以十六进制字符串序列化字节
+pub(crate) mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        hex::decode(text).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_read() {
+        let path = std::env::temp_dir().join(format!("mt4_session_{}.jsonl", std::process::id()));
+        let mut recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(51, 0, &[]).unwrap();
+        recorder.record(10, 2, &[0xde, 0xad]).unwrap();
+        assert_eq!(recorder.frames(), 2);
+        drop(recorder);
+
+        let frames = read_session(&path).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!((frames[1].command, frames[1].error_code), (10, 2));
+        assert_eq!(frames[1].data, vec![0xde, 0xad]);
+        assert!(std::fs::read_to_string(&path).unwrap().contains("\"data\":\"dead\""));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
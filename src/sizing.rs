@@ -0,0 +1,74 @@
+//! 按风险计算手数
+//!
+//! 给定账户净值、单笔风险百分比和止损距离，按品种的合约规格计算手数:
+//!
+//! ```text
+//! 手数 = 净值 × 风险% ÷ (止损距离 ÷ 点值 × 每点价值)
+//! ```
+//!
+//! 结果向下取整到手数步长 (实际风险不超过设定值)，并限制在最大手数以内。
+//!
+//! ```
+//! use mt4_client::{position_size, SymbolInfo};
+//!
+//! // 净值 10000，风险 1%，止损 50 pips: 100 / (0.0050 × 100000) = 0.2 手
+//! let lots = position_size(10_000.0, 1.0, 0.0050, &SymbolInfo::new("EURUSD", 5)).unwrap();
+//! assert_eq!(lots, 0.2);
+//! ```
+
+use crate::error::{Mt4Error, Result};
+use crate::types::SymbolInfo;
+
+/// 计算风险为净值 `risk_percent`% 、止损距离为 `sl_distance` (价格差) 时的手数
+///
+/// 计算结果低于最小手数时返回 `Mt4Error::InvalidParams` (按最小手数开仓会超出风险)
+pub fn position_size(equity: f64, risk_percent: f64, sl_distance: f64, spec: &SymbolInfo) -> Result<f64> {
+    if !(equity.is_finite() && equity > 0.0) {
+        return Err(Mt4Error::InvalidParams(format!("无效的净值: {}", equity)));
+    }
+    if !(risk_percent.is_finite() && risk_percent > 0.0 && risk_percent <= 100.0) {
+        return Err(Mt4Error::InvalidParams(format!("无效的风险百分比: {}", risk_percent)));
+    }
+    let sl_distance = sl_distance.abs();
+    if !(sl_distance.is_finite() && sl_distance > 0.0) {
+        return Err(Mt4Error::InvalidParams("止损距离必须大于 0".to_string()));
+    }
+    let loss_per_lot = sl_distance / spec.point() * spec.tick_value();
+    if !(loss_per_lot.is_finite() && loss_per_lot > 0.0) {
+        return Err(Mt4Error::InvalidParams(format!("{} 的合约规格无效", spec.symbol)));
+    }
+
+    let raw = equity * risk_percent / 100.0 / loss_per_lot;
+    let step = if spec.lot_step > 0.0 { spec.lot_step } else { 0.01 };
+    let lots = (((raw / step + 1e-9).floor() * step).min(spec.lot_max) * 1e8).round() / 1e8;
+    if lots < spec.lot_min {
+        return Err(Mt4Error::InvalidParams(format!(
+            "按 {}% 风险计算的手数 {:.4} 低于 {} 的最小手数 {}",
+            risk_percent, raw, spec.symbol, spec.lot_min
+        )));
+    }
+    Ok(lots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_size() {
+        let eurusd = SymbolInfo::new("EURUSD", 5);
+        // 向下取整: 100 / (0.0030 × 100000) = 0.333 → 0.33
+        assert_eq!(position_size(10_000.0, 1.0, 0.0030, &eurusd).unwrap(), 0.33);
+        // 止损距离按绝对值计算
+        assert_eq!(position_size(10_000.0, 1.0, -0.0050, &eurusd).unwrap(), 0.2);
+
+        // 黄金: 合约 100 盎司，止损 5 美元，风险 200 → 0.4 手
+        let gold = SymbolInfo { contract_size: 100.0, ..SymbolInfo::new("XAUUSD", 2) };
+        assert_eq!(position_size(20_000.0, 1.0, 5.0, &gold).unwrap(), 0.4);
+
+        // 限制在最大手数，低于最小手数时拒绝
+        assert_eq!(position_size(1e9, 1.0, 0.0010, &eurusd).unwrap(), 100.0);
+        assert!(position_size(100.0, 0.5, 0.0100, &eurusd).is_err());
+        assert!(position_size(10_000.0, 1.0, 0.0, &eurusd).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! 客户端状态快照
+//!
+//! `Mt4Client::snapshot()` 在某一时刻收集账户信息、持仓、挂单和订阅状态 (市场报价列表、K线合成周期)，
+//! 用于机器人检查点和调试。快照可序列化为 JSON，也可以从 JSON 读回比较:
+//!
+//! ```no_run
+//! # async fn example(client: &mt4_client::Mt4Client) -> mt4_client::Result<()> {
+//! let snapshot = client.snapshot().await;
+//! std::fs::write("checkpoint.json", snapshot.to_json()?).unwrap();
+//! println!("{} position(s), {} pending", snapshot.positions.len(), snapshot.pending_orders.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Mt4Error, Result};
+use crate::types::{AccountInfo, Order, Symbol};
+#[cfg(feature = "experimental-status")]
+use crate::types::ConnectionStatus;
+use serde::{Deserialize, Serialize};
+
+/// 客户端状态快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientSnapshot {
+    /// 生成时间 (Unix 时间戳，秒)
+    pub taken_at: f64,
+    /// 对应的服务器时间 (尚未估计时钟偏移时为 None)
+    pub server_time: Option<i64>,
+    /// 交易服务器
+    pub server: Option<String>,
+    /// 是否已连接
+    pub connected: bool,
+    /// 是否已认证
+    pub authenticated: bool,
+    /// 最近一次收到的交易服务器连接状态 (`experimental-status` 特性)
+    #[cfg(feature = "experimental-status")]
+    pub connection_status: Option<ConnectionStatus>,
+    /// 账户信息
+    pub account: Option<AccountInfo>,
+    /// 保证金比例 (%)
+    pub margin_level: Option<f64>,
+    /// 按实时报价估算的持仓浮动盈亏合计
+    pub unrealized_pnl: f64,
+    /// 持仓
+    pub positions: Vec<Order>,
+    /// 挂单
+    pub pending_orders: Vec<Order>,
+    /// 市场报价列表中的品种
+    pub market_watch: Vec<Symbol>,
+    /// 正在合成K线的周期 (秒)
+    pub candle_periods: Vec<u64>,
+}
+
+impl ClientSnapshot {
+    /// 序列化为 JSON (带缩进)
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Mt4Error::Serialization(format!("序列化快照失败: {}", e)))
+    }
+
+    /// 从 JSON 读取快照
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Mt4Error::Serialization(format!("解析快照失败: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+    use crate::types::Ticket;
+
+    #[test]
+    fn test_snapshot_json_roundtrip() {
+        let order = Order::for_test(42, "EURUSD", OrderType::Buy, 0.5, 1.1);
+        let snapshot = ClientSnapshot {
+            taken_at: 1_700_000_000.5,
+            server_time: Some(1_700_007_200),
+            server: Some("Demo-Server".to_string()),
+            connected: true,
+            authenticated: true,
+            #[cfg(feature = "experimental-status")]
+            connection_status: None,
+            account: Some(AccountInfo::default()),
+            margin_level: Some(250.0),
+            unrealized_pnl: -12.5,
+            positions: vec![order],
+            pending_orders: Vec::new(),
+            market_watch: vec![Symbol::new("EURUSD").unwrap()],
+            candle_periods: vec![60, 300],
+        };
+        let json = snapshot.to_json().unwrap();
+        let restored = ClientSnapshot::from_json(&json).unwrap();
+        assert_eq!(restored.to_json().unwrap(), json);
+        assert_eq!((restored.positions[0].ticket, restored.candle_periods), (Ticket(42), vec![60, 300]));
+        assert!(ClientSnapshot::from_json("{}").is_err());
+    }
+}
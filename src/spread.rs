@@ -0,0 +1,130 @@
+//! 点差监控
+//!
+//! `SpreadMonitor` 记录每个品种最近一次报价的点差，点差超过设置的上限时发出
+//! `SpreadAlert { active: true }`，回落到上限以内时发出 `active: false`，常用于在新闻行情中
+//! 暂停交易。客户端收到报价 (`Mt4Event::Quote`) 时自动更新，告警作为 `Mt4Event::SpreadAlert` 发出:
+//!
+//! ```no_run
+//! # async fn example(client: &mt4_client::Mt4Client) {
+//! // EURUSD 点差超过 3 个 pip (0.0003) 时告警，其他品种使用默认上限
+//! client.set_spread_threshold("EURUSD", Some(0.0003));
+//! client.set_default_spread_threshold(Some(0.5));
+//! if client.spread_too_wide("EURUSD") {
+//!     println!("EURUSD spread {:?}", client.spread("EURUSD"));
+//! }
+//! # }
+//! ```
+//!
+//! 点差和上限都以价格为单位 (ask - bid)，不同小数位的品种需要分别设置。
+
+use crate::types::Quote;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 点差告警
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpreadAlert {
+    /// 品种
+    pub symbol: String,
+    /// 当前点差 (ask - bid)
+    pub spread: f64,
+    /// 生效的上限 (回落时上限已被移除则为 None)
+    pub threshold: Option<f64>,
+    /// true: 点差超过上限；false: 点差已回落
+    pub active: bool,
+    /// 报价时间 (服务器时间，秒)
+    pub time: i64,
+}
+
+/// 点差监控器
+#[derive(Debug, Clone, Default)]
+pub struct SpreadMonitor {
+    thresholds: HashMap<String, f64>,
+    default_threshold: Option<f64>,
+    /// 品种 -> (最近点差, 是否超过上限)
+    spreads: HashMap<String, (f64, bool)>,
+}
+
+impl SpreadMonitor {
+    /// 创建监控器 (未设置上限时只记录点差)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置品种的点差上限 (None 表示使用默认上限)
+    pub fn set_threshold(&mut self, symbol: &str, threshold: Option<f64>) {
+        match threshold {
+            Some(threshold) => self.thresholds.insert(symbol.to_string(), threshold),
+            None => self.thresholds.remove(symbol),
+        };
+    }
+
+    /// 设置未单独配置的品种使用的默认上限 (None 表示不告警)
+    pub fn set_default_threshold(&mut self, threshold: Option<f64>) {
+        self.default_threshold = threshold;
+    }
+
+    /// 品种生效的点差上限
+    pub fn threshold(&self, symbol: &str) -> Option<f64> {
+        self.thresholds.get(symbol).copied().or(self.default_threshold)
+    }
+
+    /// 品种最近一次报价的点差
+    pub fn spread(&self, symbol: &str) -> Option<f64> {
+        self.spreads.get(symbol).map(|(spread, _)| *spread)
+    }
+
+    /// 品种点差是否超过上限 (没有报价时为 false)
+    pub fn is_too_wide(&self, symbol: &str) -> bool {
+        self.spreads.get(symbol).is_some_and(|(_, wide)| *wide)
+    }
+
+    /// 处理一条报价，点差越过上限 (或回落) 时返回告警
+    pub fn on_quote(&mut self, quote: &Quote) -> Option<SpreadAlert> {
+        let spread = quote.ask - quote.bid;
+        let threshold = self.threshold(&quote.symbol);
+        let wide = threshold.is_some_and(|threshold| spread > threshold);
+        let was_wide = self.spreads.insert(quote.symbol.clone(), (spread, wide)).is_some_and(|(_, wide)| wide);
+        if wide == was_wide {
+            return None;
+        }
+        Some(SpreadAlert {
+            symbol: quote.symbol.clone(),
+            spread,
+            threshold,
+            active: wide,
+            time: quote.time,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(symbol: &str, bid: f64, ask: f64) -> Quote {
+        Quote { symbol: symbol.to_string(), bid, ask, time: 100 }
+    }
+
+    #[test]
+    fn test_alerts_on_crossing() {
+        let mut monitor = SpreadMonitor::new();
+        monitor.set_threshold("EURUSD", Some(0.0003));
+        assert!(monitor.on_quote(&quote("EURUSD", 1.1000, 1.1001)).is_none());
+
+        let alert = monitor.on_quote(&quote("EURUSD", 1.1000, 1.1010)).unwrap();
+        assert!(alert.active && alert.threshold == Some(0.0003));
+        assert!(monitor.is_too_wide("EURUSD"));
+        // 持续超过上限不重复告警
+        assert!(monitor.on_quote(&quote("EURUSD", 1.1000, 1.1008)).is_none());
+
+        let alert = monitor.on_quote(&quote("EURUSD", 1.1000, 1.1002)).unwrap();
+        assert!(!alert.active && !monitor.is_too_wide("EURUSD"));
+        assert!((monitor.spread("EURUSD").unwrap() - 0.0002).abs() < 1e-9);
+
+        // 没有上限的品种只记录点差
+        assert!(monitor.on_quote(&quote("XAUUSD", 2000.0, 2005.0)).is_none());
+        monitor.set_default_threshold(Some(1.0));
+        assert!(monitor.on_quote(&quote("XAUUSD", 2000.0, 2005.0)).unwrap().active);
+    }
+}
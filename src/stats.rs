@@ -0,0 +1,185 @@
+//! 已平仓交易统计
+//!
+//! `TradeStatistics` 汇总平仓通知 (Command 10) 中的已平仓订单，按品种和整体统计胜率、盈亏比 (profit factor)、
+//! 平均盈利/亏损、期望值和最长连续亏损次数。客户端自动统计，可随时通过 `Mt4Client::trade_stats()` 查询；
+//! 调用 `Mt4Client::set_trade_stats_report_every()` 后每平仓 N 笔发出一次 `Mt4Event::TradeStats`。
+//!
+//! - 盈亏按 profit + swap + commission 计算，大于 0 为盈利，小于 0 为亏损，等于 0 只计入交易笔数
+//! - 同一 ticket 只统计一次 (重复推送的平仓通知忽略)；删除的挂单不计入
+
+use crate::types::{Order, OrderUpdate, Ticket};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// 一组已平仓交易的统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TradeStats {
+    /// 交易笔数
+    pub trades: u32,
+    /// 盈利笔数
+    pub wins: u32,
+    /// 亏损笔数
+    pub losses: u32,
+    /// 盈利合计
+    pub gross_profit: f64,
+    /// 亏损合计 (正数)
+    pub gross_loss: f64,
+    /// 净盈亏
+    pub net_profit: f64,
+    /// 最长连续亏损笔数
+    pub longest_losing_streak: u32,
+    /// 当前连续亏损笔数
+    pub current_losing_streak: u32,
+}
+
+impl TradeStats {
+    /// 记录一笔交易的盈亏
+    pub fn record(&mut self, pnl: f64) {
+        self.trades += 1;
+        self.net_profit += pnl;
+        if pnl > 0.0 {
+            self.wins += 1;
+            self.gross_profit += pnl;
+            self.current_losing_streak = 0;
+        } else if pnl < 0.0 {
+            self.losses += 1;
+            self.gross_loss -= pnl;
+            self.current_losing_streak += 1;
+            self.longest_losing_streak = self.longest_losing_streak.max(self.current_losing_streak);
+        } else {
+            self.current_losing_streak = 0;
+        }
+    }
+
+    /// 胜率 (0.0 - 1.0)
+    pub fn win_rate(&self) -> Option<f64> {
+        (self.trades > 0).then(|| self.wins as f64 / self.trades as f64)
+    }
+
+    /// 盈亏比: 盈利合计 ÷ 亏损合计 (没有亏损时为 None)
+    pub fn profit_factor(&self) -> Option<f64> {
+        (self.gross_loss > 0.0).then(|| self.gross_profit / self.gross_loss)
+    }
+
+    /// 平均盈利
+    pub fn average_win(&self) -> Option<f64> {
+        (self.wins > 0).then(|| self.gross_profit / self.wins as f64)
+    }
+
+    /// 平均亏损 (正数)
+    pub fn average_loss(&self) -> Option<f64> {
+        (self.losses > 0).then(|| self.gross_loss / self.losses as f64)
+    }
+
+    /// 期望值: 每笔交易的平均净盈亏
+    pub fn expectancy(&self) -> Option<f64> {
+        (self.trades > 0).then(|| self.net_profit / self.trades as f64)
+    }
+}
+
+/// 统计快照
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TradeStatsReport {
+    /// 全部交易
+    pub overall: TradeStats,
+    /// 品种 -> 该品种的交易
+    pub by_symbol: BTreeMap<String, TradeStats>,
+}
+
+/// 已平仓交易统计器
+#[derive(Debug, Clone, Default)]
+pub struct TradeStatistics {
+    report: TradeStatsReport,
+    /// 已统计的 ticket
+    seen: HashSet<Ticket>,
+    /// 每平仓 N 笔生成一次快照 (None 表示不生成)
+    report_every: Option<u32>,
+    /// 上次生成快照后新统计的笔数
+    since_report: u32,
+}
+
+impl TradeStatistics {
+    /// 创建空的统计器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置每平仓多少笔生成一次快照 (None 或 0 表示不生成)
+    pub fn set_report_every(&mut self, trades: Option<u32>) {
+        self.report_every = trades.filter(|n| *n > 0);
+        self.since_report = 0;
+    }
+
+    /// 统计一笔已平仓订单，返回是否为新的交易 (挂单或已统计的 ticket 返回 false)
+    pub fn record_order(&mut self, order: &Order) -> bool {
+        if order.is_pending() || !self.seen.insert(order.ticket) {
+            return false;
+        }
+        let pnl = order.profit + order.swap + order.commission;
+        self.report.overall.record(pnl);
+        self.report.by_symbol.entry(order.symbol.to_string()).or_default().record(pnl);
+        true
+    }
+
+    /// 统计订单更新中的平仓通知，达到快照间隔时返回快照
+    pub fn on_updates(&mut self, updates: &[OrderUpdate]) -> Option<TradeStatsReport> {
+        let recorded = updates
+            .iter()
+            .filter(|u| u.is_close_notification() && self.record_order(&u.order))
+            .count() as u32;
+        let every = self.report_every?;
+        self.since_report += recorded;
+        if self.since_report < every {
+            return None;
+        }
+        self.since_report = 0;
+        Some(self.report.clone())
+    }
+
+    /// 当前统计快照
+    pub fn report(&self) -> &TradeStatsReport {
+        &self.report
+    }
+
+    /// 清空统计 (保留快照间隔)
+    pub fn reset(&mut self) {
+        self.report = TradeStatsReport::default();
+        self.seen.clear();
+        self.since_report = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+
+    fn closed(ticket: i32, symbol: &str, profit: f64) -> OrderUpdate {
+        let order = Order { profit, close_time: 1, ..Order::for_test(ticket, symbol, OrderType::Buy, 0.1, 1.08) };
+        OrderUpdate { notify_id: ticket, notify_type: 1, df: 0.0, xh: 0.0, raw_size: 185, order, related_order: None }
+    }
+
+    #[test]
+    fn test_trade_statistics() {
+        let mut stats = TradeStatistics::new();
+        stats.set_report_every(Some(4));
+        let updates = [closed(1, "EURUSD", 30.0), closed(2, "EURUSD", -10.0), closed(3, "XAUUSD", -20.0)];
+        assert!(stats.on_updates(&updates).is_none());
+        // 重复的平仓通知和删除的挂单不计入
+        let mut pending = closed(5, "EURUSD", 0.0);
+        pending.order.order_type = OrderType::BuyLimit;
+        assert!(stats.on_updates(&[closed(2, "EURUSD", -10.0), pending]).is_none());
+
+        let report = stats.on_updates(&[closed(4, "EURUSD", 20.0)]).unwrap();
+        let overall = &report.overall;
+        assert_eq!((overall.trades, overall.wins, overall.losses, overall.longest_losing_streak), (4, 2, 2, 2));
+        assert_eq!(overall.win_rate(), Some(0.5));
+        assert_eq!(overall.profit_factor(), Some(50.0 / 30.0));
+        assert_eq!((overall.average_win(), overall.average_loss()), (Some(25.0), Some(15.0)));
+        assert_eq!(overall.expectancy(), Some(5.0));
+
+        let eurusd = &report.by_symbol["EURUSD"];
+        assert_eq!((eurusd.trades, eurusd.net_profit, eurusd.longest_losing_streak), (3, 40.0, 1));
+        assert_eq!(report.by_symbol["XAUUSD"].profit_factor(), Some(0.0));
+    }
+}
@@ -0,0 +1,408 @@
+//! 内置状态页 (需要开启 `status-page` feature)
+//!
+//! 不依赖额外的 HTTP 框架，直接基于 tokio TcpListener 提供两个只读页面:
+//! - `GET /`            HTML 状态页 (每 5 秒自动刷新)
+//! - `GET /status.json` JSON 状态快照
+//!
+//! 页面内容来自 `StatusProvider`，`Mt4Client::status_provider()` 提供单个客户端的实现，
+//! 管理多个账户的上层代码可以自行实现该 trait 返回多个 `AccountStatus`。
+
+use crate::client::{Mt4Event, RequestTracker};
+use crate::error::{Mt4Error, Result};
+use crate::events::TimedEvent;
+use crate::positions::PositionManager;
+use crate::types::{AccountInfo, Order};
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+/// 请求头最大长度
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// 状态页展示的最近事件数量
+const STATUS_RECENT_EVENTS: usize = 20;
+
+/// 单个账户的状态
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountStatus {
+    /// 账号
+    pub login: String,
+    /// 交易服务器
+    pub server: String,
+    /// 是否已认证
+    pub authenticated: bool,
+    /// 待确认的交易请求数量
+    pub pending_requests: usize,
+    /// 距最近一次事件的秒数
+    pub last_event_age_secs: Option<f64>,
+    /// 账户信息
+    pub account: Option<AccountInfo>,
+    /// 当前持仓和挂单
+    pub positions: Vec<Order>,
+    /// 最近事件 (新事件在前)
+    pub recent_events: Vec<EventSummary>,
+}
+
+/// 事件摘要
+#[derive(Debug, Clone, Serialize)]
+pub struct EventSummary {
+    /// 本地 UTC 接收时间 (Unix 时间戳，秒)
+    pub utc_secs: f64,
+    /// 服务器时间
+    pub server_time: Option<i64>,
+    /// 事件类型
+    pub kind: String,
+    /// 事件内容 (截断)
+    pub detail: String,
+}
+
+impl EventSummary {
+    /// 从带时间戳的事件生成摘要
+    pub fn from_event(event: &TimedEvent) -> Self {
+        let mut detail = match &event.event {
+            Mt4Event::OrderUpdates(updates) => format!("{} order update(s)", updates.len()),
+            Mt4Event::PositionsSnapshot(orders) => format!("{} position(s)", orders.len()),
+            Mt4Event::HistoryOrders(orders) => format!("{} history order(s)", orders.len()),
+            other => format!("{:?}", other),
+        };
+        if detail.len() > 200 {
+            let mut end = 200;
+            while !detail.is_char_boundary(end) {
+                end -= 1;
+            }
+            detail.truncate(end);
+            detail.push('…');
+        }
+        Self {
+            utc_secs: event.time.utc_secs(),
+            server_time: event.time.server_time,
+            kind: event.event.kind().to_string(),
+            detail,
+        }
+    }
+}
+
+/// 状态快照
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusSnapshot {
+    /// 生成时间 (Unix 时间戳，秒)
+    pub generated_at: f64,
+    /// 各账户状态
+    pub accounts: Vec<AccountStatus>,
+}
+
+/// 状态数据来源
+pub trait StatusProvider: Send + Sync + 'static {
+    /// 生成当前状态快照
+    fn snapshot(&self) -> BoxFuture<'_, StatusSnapshot>;
+}
+
+/// 单个客户端的状态数据来源 (通过 `Mt4Client::status_provider()` 获取)
+#[derive(Clone)]
+pub struct ClientStatus {
+    pub(crate) login: String,
+    pub(crate) server: String,
+    pub(crate) authenticated: Arc<AtomicBool>,
+    pub(crate) request_tracker: Arc<RequestTracker>,
+    pub(crate) positions: Arc<PositionManager>,
+    pub(crate) account: Arc<RwLock<Option<AccountInfo>>>,
+    pub(crate) recent_events: Arc<std::sync::Mutex<VecDeque<TimedEvent>>>,
+}
+
+impl ClientStatus {
+    /// 当前账户状态
+    pub async fn account_status(&self) -> AccountStatus {
+        let (recent_events, last_event_age_secs) = match self.recent_events.lock() {
+            Ok(recent) => (
+                recent
+                    .iter()
+                    .rev()
+                    .take(STATUS_RECENT_EVENTS)
+                    .map(EventSummary::from_event)
+                    .collect(),
+                recent.back().map(|e| e.time.monotonic.elapsed().as_secs_f64()),
+            ),
+            Err(_) => (Vec::new(), None),
+        };
+
+        let positions = self.positions.all().await;
+
+        AccountStatus {
+            login: self.login.clone(),
+            server: self.server.clone(),
+            authenticated: self.authenticated.load(Ordering::SeqCst),
+            pending_requests: self.request_tracker.pending_count().await,
+            last_event_age_secs,
+            account: self.account.read().await.clone(),
+            positions,
+            recent_events,
+        }
+    }
+}
+
+impl StatusProvider for ClientStatus {
+    fn snapshot(&self) -> BoxFuture<'_, StatusSnapshot> {
+        Box::pin(async move {
+            StatusSnapshot {
+                generated_at: unix_secs(),
+                accounts: vec![self.account_status().await],
+            }
+        })
+    }
+}
+
+/// 状态页 HTTP 服务
+pub struct StatusServer {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl StatusServer {
+    /// 绑定地址并开始提供状态页
+    pub async fn bind(addr: SocketAddr, provider: Arc<dyn StatusProvider>) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| Mt4Error::Connection(format!("状态页绑定 {} 失败: {}", addr, e)))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| Mt4Error::Connection(e.to_string()))?;
+        tracing::info!("Status page listening on http://{}", local_addr);
+
+        let task = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let provider = provider.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = Self::handle(stream, provider).await {
+                                tracing::debug!("Status page connection error: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        tracing::warn!("Status page accept error: {}", e);
+                    }
+                }
+            }
+        });
+
+        Ok(Self { local_addr, task })
+    }
+
+    /// 实际监听的地址 (绑定端口 0 时可用于获取分配的端口)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// 停止服务
+    pub fn shutdown(&self) {
+        self.task.abort();
+    }
+
+    /// 处理单个 HTTP 连接
+    async fn handle(mut stream: TcpStream, provider: Arc<dyn StatusProvider>) -> std::io::Result<()> {
+        let mut buffer = Vec::with_capacity(1024);
+        let mut chunk = [0u8; 1024];
+        while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 || buffer.len() + n > MAX_REQUEST_SIZE {
+                break;
+            }
+            buffer.extend_from_slice(&chunk[..n]);
+        }
+
+        let request = String::from_utf8_lossy(&buffer);
+        let mut parts = request.lines().next().unwrap_or("").split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let path = parts.next().unwrap_or("/");
+
+        let (status, content_type, body) = match (method, path) {
+            ("GET", "/status.json") => {
+                let snapshot = provider.snapshot().await;
+                let body = serde_json::to_string_pretty(&snapshot).unwrap_or_else(|_| "{}".to_string());
+                ("200 OK", "application/json", body)
+            }
+            ("GET", "/") => {
+                let snapshot = provider.snapshot().await;
+                ("200 OK", "text/html; charset=utf-8", render_html(&snapshot))
+            }
+            ("GET", _) => ("404 Not Found", "text/plain", "not found".to_string()),
+            _ => ("405 Method Not Allowed", "text/plain", "method not allowed".to_string()),
+        };
+
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+            status,
+            content_type,
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.write_all(body.as_bytes()).await?;
+        stream.shutdown().await
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 渲染 HTML 状态页
+fn render_html(snapshot: &StatusSnapshot) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><meta http-equiv=\"refresh\" content=\"5\">\
+         <title>MT4 Client Status</title><style>body{font-family:monospace;margin:1em}\
+         table{border-collapse:collapse;margin-bottom:1em}td,th{border:1px solid #ccc;padding:2px 6px}\
+         .ok{color:green}.down{color:red}</style></head><body><h1>MT4 Client Status</h1>",
+    );
+
+    for account in &snapshot.accounts {
+        let (class, state) = if account.authenticated {
+            ("ok", "authenticated")
+        } else {
+            ("down", "disconnected")
+        };
+        html.push_str(&format!(
+            "<h2>{} @ {} <span class=\"{}\">{}</span></h2><p>pending requests: {} | last event: {}</p>",
+            escape(&account.login),
+            escape(&account.server),
+            class,
+            state,
+            account.pending_requests,
+            account
+                .last_event_age_secs
+                .map(|s| format!("{:.1}s ago", s))
+                .unwrap_or_else(|| "-".to_string()),
+        ));
+
+        if let Some(info) = &account.account {
+            html.push_str(&format!(
+                "<table><tr><th>balance</th><th>equity</th><th>margin</th><th>free margin</th><th>leverage</th><th>currency</th></tr>\
+                 <tr><td>{:.2}</td><td>{:.2}</td><td>{}</td><td>{}</td><td>1:{}</td><td>{}</td></tr></table>",
+                info.balance,
+                info.equity,
+                optional_amount(info.margin),
+                optional_amount(info.free_margin),
+                info.leverage,
+                escape(&info.currency),
+            ));
+        }
+
+        html.push_str(
+            "<table><tr><th>ticket</th><th>symbol</th><th>type</th><th>volume</th><th>open</th>\
+             <th>sl</th><th>tp</th><th>profit</th><th>comment</th></tr>",
+        );
+        for order in &account.positions {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td><td>{:.5}</td><td>{:.5}</td><td>{:.5}</td><td>{:.2}</td><td>{}</td></tr>",
+                order.ticket,
+                escape(&order.symbol),
+                order.order_type.name(),
+                order.volume,
+                order.open_price,
+                order.sl,
+                order.tp,
+                order.profit,
+                escape(&order.comment),
+            ));
+        }
+        html.push_str("</table><table><tr><th>utc</th><th>event</th><th>detail</th></tr>");
+        for event in &account.recent_events {
+            html.push_str(&format!(
+                "<tr><td>{:.3}</td><td>{}</td><td>{}</td></tr>",
+                event.utc_secs,
+                escape(&event.kind),
+                escape(&event.detail),
+            ));
+        }
+        html.push_str("</table>");
+    }
+
+    html.push_str("</body></html>");
+    html
+}
+
+/// HTML 转义
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 金额 (未知时为 "-")
+fn optional_amount(value: Option<f64>) -> String {
+    value.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "-".to_string())
+}
+
+/// 当前 Unix 时间戳 (秒，带小数)
+fn unix_secs() -> f64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedStatus;
+
+    impl StatusProvider for FixedStatus {
+        fn snapshot(&self) -> BoxFuture<'_, StatusSnapshot> {
+            Box::pin(async {
+                StatusSnapshot {
+                    generated_at: 0.0,
+                    accounts: vec![AccountStatus {
+                        login: "31313724".to_string(),
+                        server: "Demo<01>".to_string(),
+                        authenticated: true,
+                        pending_requests: 0,
+                        last_event_age_secs: None,
+                        account: None,
+                        positions: Vec::new(),
+                        recent_events: Vec::new(),
+                    }],
+                }
+            })
+        }
+    }
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_status_server() {
+        let server = StatusServer::bind("127.0.0.1:0".parse().unwrap(), Arc::new(FixedStatus))
+            .await
+            .unwrap();
+
+        let json = get(server.local_addr(), "/status.json").await;
+        assert!(json.starts_with("HTTP/1.1 200 OK"));
+        assert!(json.contains("\"login\": \"31313724\""));
+
+        let html = get(server.local_addr(), "/").await;
+        assert!(html.contains("Demo&lt;01&gt;"));
+
+        let missing = get(server.local_addr(), "/nope").await;
+        assert!(missing.starts_with("HTTP/1.1 404"));
+    }
+}
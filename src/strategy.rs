@@ -0,0 +1,380 @@
+//! 策略接口与事件循环
+//!
+//! 实现 `Strategy` 的回调，由 `StrategyRunner` 接管客户端并驱动事件循环，
+//! 不必在每个机器人里重复 `select!` / `match` 样板代码:
+//!
+//! ```no_run
+//! use mt4_client::{LoginCredentials, Mt4Client, OrderUpdate, Quote, Result, Strategy, StrategyContext, StrategyRunner};
+//! use std::time::Duration;
+//!
+//! struct Breakout {
+//!     high: f64,
+//! }
+//!
+//! impl Strategy for Breakout {
+//!     async fn on_quote(&mut self, ctx: &mut StrategyContext<'_>, quote: &Quote) -> Result<()> {
+//!         if quote.ask > self.high && ctx.positions().await.is_empty() {
+//!             ctx.buy(&quote.symbol, 0.01, None, None).await?;
+//!         }
+//!         Ok(())
+//!     }
+//!
+//!     async fn on_order_update(&mut self, ctx: &mut StrategyContext<'_>, update: &OrderUpdate) -> Result<()> {
+//!         println!("#{} notify_type={}", update.order.ticket, update.notify_type);
+//!         if update.notify_type == 1 {
+//!             ctx.stop();
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # async fn example(credentials: LoginCredentials) -> Result<()> {
+//! let mut client = Mt4Client::new();
+//! client.connect(&credentials).await?;
+//!
+//! let mut runner = StrategyRunner::new(client, Breakout { high: 1.1 })
+//!     .with_quotes(["EURUSD"], Duration::from_secs(1))?
+//!     .with_timer(Duration::from_secs(60));
+//! runner.run().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! - 回调依次执行，回调期间到达的事件在事件通道中排队
+//! - 回调返回的错误只记录日志，不会结束循环；需要结束时调用 `StrategyContext::stop()`
+//! - `Mt4Event::Quote` 事件 (如 `request_quotes()` 的报价快照) 也会调用 `on_quote`
+//! - 协议没有实时报价推送时，`with_quotes()` 按间隔发送报价请求 (type=0) 轮询报价
+
+use crate::candles::CandleClosed;
+use crate::client::{Mt4Client, Mt4Event};
+use crate::error::Result;
+use crate::intents::unix_now;
+use crate::types::{AccountInfo, Order, OrderUpdate, Quote, Symbol, Ticket, TradeRequest, TradeResponse};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// 交易策略
+///
+/// 所有回调都有空的默认实现，按需实现即可 (可以写成 `async fn`)
+pub trait Strategy: Send {
+    /// 事件循环开始前调用一次
+    fn on_start(&mut self, ctx: &mut StrategyContext<'_>) -> impl Future<Output = Result<()>> + Send {
+        let _ = ctx;
+        async { Ok(()) }
+    }
+
+    /// 收到报价
+    fn on_quote(&mut self, ctx: &mut StrategyContext<'_>, quote: &Quote) -> impl Future<Output = Result<()>> + Send {
+        let _ = (ctx, quote);
+        async { Ok(()) }
+    }
+
+    /// 由报价合成的K线收盘 (见 `Mt4Client::aggregate_candles()`)
+    fn on_candle(&mut self, ctx: &mut StrategyContext<'_>, bar: &CandleClosed) -> impl Future<Output = Result<()>> + Send {
+        let _ = (ctx, bar);
+        async { Ok(()) }
+    }
+
+    /// 收到订单更新 (批量推送中的每个更新各调用一次)
+    fn on_order_update(
+        &mut self,
+        ctx: &mut StrategyContext<'_>,
+        update: &OrderUpdate,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let _ = (ctx, update);
+        async { Ok(()) }
+    }
+
+    /// 定时器触发 (见 `StrategyRunner::with_timer()`)
+    fn on_timer(&mut self, ctx: &mut StrategyContext<'_>) -> impl Future<Output = Result<()>> + Send {
+        let _ = ctx;
+        async { Ok(()) }
+    }
+
+    /// 收到任意事件 (在 `on_order_update` 之前调用)
+    fn on_event(&mut self, ctx: &mut StrategyContext<'_>, event: &Mt4Event) -> impl Future<Output = Result<()>> + Send {
+        let _ = (ctx, event);
+        async { Ok(()) }
+    }
+
+    /// 事件循环结束后调用一次
+    fn on_stop(&mut self, ctx: &mut StrategyContext<'_>) -> impl Future<Output = Result<()>> + Send {
+        let _ = ctx;
+        async { Ok(()) }
+    }
+}
+
+/// 传给策略回调的上下文，提供交易方法
+pub struct StrategyContext<'a> {
+    client: &'a Mt4Client,
+    stopped: bool,
+}
+
+impl<'a> StrategyContext<'a> {
+    /// 底层客户端 (上下文没有封装的操作)
+    pub fn client(&self) -> &'a Mt4Client {
+        self.client
+    }
+
+    /// 在当前回调结束后停止事件循环
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// 是否已请求停止
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// 市价买入
+    pub async fn buy(&self, symbol: &str, volume: f64, sl: Option<f64>, tp: Option<f64>) -> Result<()> {
+        self.client.buy(symbol, volume, sl, tp).await
+    }
+
+    /// 市价卖出
+    pub async fn sell(&self, symbol: &str, volume: f64, sl: Option<f64>, tp: Option<f64>) -> Result<()> {
+        self.client.sell(symbol, volume, sl, tp).await
+    }
+
+    /// 平仓
+    pub async fn close_order(&self, ticket: Ticket, symbol: &str, volume: f64) -> Result<()> {
+        self.client.close_order(ticket, symbol, volume).await
+    }
+
+    /// 修改订单止损止盈
+    pub async fn modify_order(&self, ticket: Ticket, sl: f64, tp: f64) -> Result<TradeResponse> {
+        self.client.modify_order(ticket, sl, tp).await
+    }
+
+    /// 发送交易请求并等待服务器响应
+    pub async fn send_trade_and_wait(&self, request: TradeRequest) -> Result<TradeResponse> {
+        self.client.send_trade_and_wait(request).await
+    }
+
+    /// 本地缓存的持仓
+    pub async fn positions(&self) -> Vec<Order> {
+        self.client.positions().await
+    }
+
+    /// 最近一次收到的账户信息
+    pub async fn account_info(&self) -> Option<AccountInfo> {
+        self.client.account_info().await
+    }
+}
+
+/// 策略运行器
+///
+/// 持有客户端和策略，`run()` 把事件、报价和定时器分派给策略回调
+pub struct StrategyRunner<S> {
+    client: Mt4Client,
+    strategy: S,
+    timer: Option<Duration>,
+    quote_symbols: Vec<Symbol>,
+    quote_interval: Duration,
+}
+
+impl<S: Strategy> StrategyRunner<S> {
+    /// 接管 (通常已连接的) 客户端
+    pub fn new(client: Mt4Client, strategy: S) -> Self {
+        Self { client, strategy, timer: None, quote_symbols: Vec::new(), quote_interval: Duration::from_secs(1) }
+    }
+
+    /// 每隔 `interval` 调用一次 `on_timer`
+    pub fn with_timer(mut self, interval: Duration) -> Self {
+        self.timer = Some(interval);
+        self
+    }
+
+    /// 每隔 `interval` 请求一次品种报价并调用 `on_quote`
+    pub fn with_quotes<I, T>(mut self, symbols: I, interval: Duration) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        for symbol in symbols {
+            self.quote_symbols.push(Symbol::new(symbol.as_ref())?);
+        }
+        self.quote_interval = interval;
+        Ok(self)
+    }
+
+    /// 客户端
+    pub fn client(&self) -> &Mt4Client {
+        &self.client
+    }
+
+    /// 策略
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// 取回客户端和策略
+    pub fn into_inner(self) -> (Mt4Client, S) {
+        (self.client, self.strategy)
+    }
+
+    /// 运行事件循环，直到策略调用 `stop()`、连接断开或事件通道关闭
+    pub async fn run(&mut self) -> Result<()> {
+        let mut stopped = self.dispatch(Dispatch::Start).await;
+        let mut timer = self.timer.map(interval);
+        let mut quotes = (!self.quote_symbols.is_empty()).then(|| interval(self.quote_interval));
+
+        while !stopped {
+            tokio::select! {
+                event = self.client.next_event() => match event {
+                    Some(event) => {
+                        let disconnected = matches!(event, Mt4Event::Disconnected);
+                        stopped = self.dispatch(Dispatch::Event(&event)).await || disconnected;
+                    }
+                    None => break,
+                },
+                _ = tick(&mut timer) => stopped = self.dispatch(Dispatch::Timer).await,
+                _ = tick(&mut quotes) => {
+                    for symbol in self.quote_symbols.clone() {
+                        let quote = match self.client.request_price(&symbol).await {
+                            Ok((bid, ask)) => Quote { symbol: symbol.to_string(), bid, ask, time: unix_now() },
+                            Err(e) => {
+                                tracing::warn!("Strategy quote request for {} failed: {}", symbol, e);
+                                continue;
+                            }
+                        };
+                        if self.dispatch(Dispatch::Quote(&quote)).await {
+                            stopped = true;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.dispatch(Dispatch::Stop).await;
+        Ok(())
+    }
+
+    /// 调用对应的回调，返回策略是否请求停止
+    async fn dispatch(&mut self, dispatch: Dispatch<'_>) -> bool {
+        let mut ctx = StrategyContext { client: &self.client, stopped: false };
+        let strategy = &mut self.strategy;
+        let results = match dispatch {
+            Dispatch::Start => vec![strategy.on_start(&mut ctx).await],
+            Dispatch::Stop => vec![strategy.on_stop(&mut ctx).await],
+            Dispatch::Timer => vec![strategy.on_timer(&mut ctx).await],
+            Dispatch::Quote(quote) => vec![strategy.on_quote(&mut ctx, quote).await],
+            Dispatch::Event(event) => {
+                let mut results = vec![strategy.on_event(&mut ctx, event).await];
+                match event {
+                    Mt4Event::Quote(quote) => results.push(strategy.on_quote(&mut ctx, quote).await),
+                    Mt4Event::CandleClosed(bar) => results.push(strategy.on_candle(&mut ctx, bar).await),
+                    _ => {}
+                }
+                let updates = match event {
+                    Mt4Event::OrderUpdate(update) => std::slice::from_ref(update),
+                    Mt4Event::OrderUpdates(updates) => updates.as_slice(),
+                    _ => &[],
+                };
+                for update in updates {
+                    results.push(strategy.on_order_update(&mut ctx, update).await);
+                }
+                results
+            }
+        };
+        for e in results.into_iter().filter_map(|r| r.err()) {
+            tracing::warn!("Strategy callback failed: {}", e);
+        }
+        ctx.stopped
+    }
+}
+
+enum Dispatch<'a> {
+    Start,
+    Stop,
+    Timer,
+    Quote(&'a Quote),
+    Event(&'a Mt4Event),
+}
+
+/// 首次在一个周期后触发的定时器
+fn interval(period: Duration) -> Interval {
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+/// 等待下一次触发，未设置时永不触发
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<String>,
+    }
+
+    impl Strategy for Recorder {
+        async fn on_start(&mut self, _ctx: &mut StrategyContext<'_>) -> Result<()> {
+            self.calls.push("start".to_string());
+            Ok(())
+        }
+
+        async fn on_order_update(&mut self, ctx: &mut StrategyContext<'_>, update: &OrderUpdate) -> Result<()> {
+            self.calls.push(format!("update {}", update.order.ticket));
+            // 未连接时交易失败，错误不会结束循环
+            ctx.buy("EURUSD", 0.01, None, None).await
+        }
+
+        async fn on_timer(&mut self, ctx: &mut StrategyContext<'_>) -> Result<()> {
+            self.calls.push("timer".to_string());
+            ctx.stop();
+            Ok(())
+        }
+
+        async fn on_stop(&mut self, _ctx: &mut StrategyContext<'_>) -> Result<()> {
+            self.calls.push("stop".to_string());
+            Ok(())
+        }
+    }
+
+    fn update(ticket: i32) -> OrderUpdate {
+        OrderUpdate {
+            notify_id: ticket,
+            notify_type: 0,
+            df: 0.0,
+            xh: 0.0,
+            raw_size: 185,
+            order: Order::for_test(ticket, "EURUSD", OrderType::Buy, 0.1, 1.08),
+            related_order: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runner_dispatches_callbacks() {
+        let mut client = Mt4Client::new();
+        let events = client.open_event_channel();
+        events.send(Mt4Event::OrderUpdate(update(1))).await.unwrap();
+        events.send(Mt4Event::OrderUpdates(vec![update(2), update(3)])).await.unwrap();
+
+        // 事件先于定时器处理，定时器回调请求停止
+        let mut runner = StrategyRunner::new(client, Recorder::default()).with_timer(Duration::from_millis(50));
+        runner.run().await.unwrap();
+        assert_eq!(runner.strategy().calls, ["start", "update 1", "update 2", "update 3", "timer", "stop"]);
+
+        // 连接断开时循环结束
+        let (mut client, _) = runner.into_inner();
+        let events = client.open_event_channel();
+        events.send(Mt4Event::Disconnected).await.unwrap();
+        let mut runner = StrategyRunner::new(client, Recorder::default());
+        runner.run().await.unwrap();
+        assert_eq!(runner.strategy().calls, ["start", "stop"]);
+    }
+}
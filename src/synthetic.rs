@@ -0,0 +1,272 @@
+//! 合成报价
+//!
+//! `QuoteGenerator` 按时间表产生 `Quote`，用于在没有行情连接时测试策略、风控和回放流程:
+//!
+//! - 随机游走: 从起始价格开始，每个间隔随机移动不超过 `volatility`，按小数位取整，点差固定
+//! - 文件回放: 读取 `TickRecorder` 写出的 CSV，按原始时间间隔 (可加速) 重新产生
+//! - 固定脚本: 按给定顺序逐个产生，用于构造确定的价格路径
+//!
+//! ```no_run
+//! use mt4_client::QuoteGenerator;
+//! use std::time::Duration;
+//!
+//! # async fn example() {
+//! let mut quotes = QuoteGenerator::random_walk("EURUSD", 1.08, 0.00012)
+//!     .with_volatility(0.0002)
+//!     .with_seed(7)
+//!     .with_interval(Duration::from_millis(250))
+//!     .spawn(64);
+//! while let Some(quote) = quotes.recv().await {
+//!     println!("{} {} {}", quote.symbol, quote.bid, quote.ask);
+//! }
+//! # }
+//! ```
+
+use crate::error::{Mt4Error, Result};
+use crate::types::Quote;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+/// 默认报价间隔
+pub const DEFAULT_QUOTE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 报价来源
+#[derive(Debug)]
+enum Source {
+    /// 随机游走
+    RandomWalk {
+        symbol: String,
+        bid: f64,
+        spread: f64,
+        volatility: f64,
+        digits: u32,
+        rng: Box<StdRng>,
+    },
+    /// 回放的报价，按报价时间间隔产生
+    Replay { quotes: VecDeque<Quote>, last_time: Option<i64> },
+    /// 脚本报价，按固定间隔产生
+    Script(VecDeque<Quote>),
+}
+
+/// 合成报价生成器
+#[derive(Debug)]
+pub struct QuoteGenerator {
+    source: Source,
+    interval: Duration,
+    speed: f64,
+    /// 随机游走的起始报价时间 (秒)
+    start_time: i64,
+    /// 已产生的随机游走报价数
+    ticks: u64,
+}
+
+impl QuoteGenerator {
+    fn with_source(source: Source) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+        Self { source, interval: DEFAULT_QUOTE_INTERVAL, speed: 1.0, start_time: now, ticks: 0 }
+    }
+
+    /// 随机游走 (默认 5 位小数，每步最多移动 0.0001)
+    pub fn random_walk(symbol: impl Into<String>, start_bid: f64, spread: f64) -> Self {
+        Self::with_source(Source::RandomWalk {
+            symbol: symbol.into(),
+            bid: start_bid,
+            spread,
+            volatility: 0.0001,
+            digits: 5,
+            rng: Box::new(StdRng::from_entropy()),
+        })
+    }
+
+    /// 回放 `TickRecorder` 写出的 CSV 文件 (列: symbol, bid, ask, timestamp, spread)
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Mt4Error::Io(format!("读取报价文件 {} 失败: {}", path.display(), e)))?;
+        let mut quotes = VecDeque::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with("symbol,") {
+                continue;
+            }
+            let quote = parse_csv_line(line).ok_or_else(|| {
+                Mt4Error::Serialization(format!("报价文件 {} 第 {} 行格式错误", path.display(), i + 1))
+            })?;
+            quotes.push_back(quote);
+        }
+        Ok(Self::with_source(Source::Replay { quotes, last_time: None }))
+    }
+
+    /// 按顺序产生给定的报价
+    pub fn script(quotes: impl IntoIterator<Item = Quote>) -> Self {
+        Self::with_source(Source::Script(quotes.into_iter().collect()))
+    }
+
+    /// 设置随机游走每步的最大移动幅度
+    pub fn with_volatility(mut self, volatility: f64) -> Self {
+        if let Source::RandomWalk { volatility: v, .. } = &mut self.source {
+            *v = volatility.abs();
+        }
+        self
+    }
+
+    /// 设置随机游走价格的小数位数
+    pub fn with_digits(mut self, digits: u32) -> Self {
+        if let Source::RandomWalk { digits: d, .. } = &mut self.source {
+            *d = digits;
+        }
+        self
+    }
+
+    /// 设置随机种子 (相同种子产生相同的价格路径)
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        if let Source::RandomWalk { rng, .. } = &mut self.source {
+            **rng = StdRng::seed_from_u64(seed);
+        }
+        self
+    }
+
+    /// 设置随机游走和脚本的报价间隔
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 设置回放速度倍数 (2.0 表示以两倍速回放，不大于 0 时不等待)
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// 设置随机游走的起始报价时间 (服务器时间，秒，默认当前时间)
+    pub fn with_start_time(mut self, time: i64) -> Self {
+        self.start_time = time;
+        self
+    }
+
+    /// 下一个报价，回放或脚本结束后返回 None
+    pub fn next_quote(&mut self) -> Option<Quote> {
+        self.next_tick().map(|(_, quote)| quote)
+    }
+
+    /// 下一个报价及产生前需要等待的时间
+    fn next_tick(&mut self) -> Option<(Duration, Quote)> {
+        match &mut self.source {
+            Source::RandomWalk { symbol, bid, spread, volatility, digits, rng } => {
+                let scale = 10f64.powi(*digits as i32);
+                let round = |price: f64| (price * scale).round() / scale;
+                if self.ticks > 0 {
+                    let step = rng.gen_range(-1.0..=1.0) * *volatility;
+                    *bid = round((*bid + step).max(1.0 / scale));
+                }
+                let elapsed = self.interval.as_secs_f64() * self.ticks as f64;
+                let delay = if self.ticks == 0 { Duration::ZERO } else { self.interval };
+                self.ticks += 1;
+                let quote = Quote {
+                    symbol: symbol.clone(),
+                    bid: round(*bid),
+                    ask: round(*bid + *spread),
+                    time: self.start_time + elapsed as i64,
+                };
+                Some((delay, quote))
+            }
+            Source::Replay { quotes, last_time } => {
+                let quote = quotes.pop_front()?;
+                let gap = last_time.map_or(0, |last| (quote.time - last).max(0));
+                *last_time = Some(quote.time);
+                let delay = if self.speed > 0.0 {
+                    Duration::from_secs_f64(gap as f64 / self.speed)
+                } else {
+                    Duration::ZERO
+                };
+                Some((delay, quote))
+            }
+            Source::Script(quotes) => {
+                let quote = quotes.pop_front()?;
+                let delay = if self.ticks == 0 { Duration::ZERO } else { self.interval };
+                self.ticks += 1;
+                Some((delay, quote))
+            }
+        }
+    }
+
+    /// 按时间表把报价发送到通道，来源结束或接收端关闭后返回
+    pub async fn run(mut self, tx: mpsc::Sender<Quote>) {
+        while let Some((delay, quote)) = self.next_tick() {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            if tx.send(quote).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// 在后台任务中运行，返回报价接收端
+    pub fn spawn(self, capacity: usize) -> mpsc::Receiver<Quote> {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        tokio::spawn(self.run(tx));
+        rx
+    }
+}
+
+/// 解析 `symbol,bid,ask,timestamp[,spread]`
+fn parse_csv_line(line: &str) -> Option<Quote> {
+    let mut fields = line.split(',').map(str::trim);
+    let symbol = fields.next().filter(|s| !s.is_empty())?.to_string();
+    let bid = fields.next()?.parse().ok()?;
+    let ask = fields.next()?.parse().ok()?;
+    let time = fields.next()?.parse().ok()?;
+    Some(Quote { symbol, bid, ask, time })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(bid: f64, time: i64) -> Quote {
+        Quote { symbol: "EURUSD".to_string(), bid, ask: bid + 0.0001, time }
+    }
+
+    #[tokio::test]
+    async fn test_generators() {
+        // 相同种子产生相同路径，价格按小数位取整，点差不变
+        let walk = |seed| {
+            let mut generator = QuoteGenerator::random_walk("XAUUSD", 2300.0, 0.3)
+                .with_digits(2)
+                .with_volatility(0.5)
+                .with_seed(seed)
+                .with_start_time(1_700_000_000)
+                .with_interval(Duration::from_millis(500));
+            (0..20).map(|_| generator.next_quote().unwrap()).collect::<Vec<_>>()
+        };
+        let (first, second) = (walk(1), walk(1));
+        assert_eq!(first[0].bid, 2300.0);
+        assert!(first.iter().zip(&second).all(|(a, b)| a.bid == b.bid));
+        assert!(first.windows(2).all(|w| (w[1].bid - w[0].bid).abs() <= 0.5 + 1e-9));
+        let on_grid = |price: f64| (price * 100.0 - (price * 100.0).round()).abs() < 1e-6;
+        assert!(first.iter().all(|q| ((q.ask - q.bid) - 0.3).abs() < 1e-9 && on_grid(q.bid)));
+        assert_eq!(first[19].time, 1_700_000_009);
+
+        // 回放按报价时间间隔等待，可加速
+        let path = std::env::temp_dir().join(format!("mt4_synthetic_{}.csv", std::process::id()));
+        std::fs::write(&path, "symbol,bid,ask,timestamp,spread\nEURUSD,1.1,1.1002,100,0.0002\nEURUSD,1.1001,1.1003,104,0.0002\n")
+            .unwrap();
+        let mut replay = QuoteGenerator::replay(&path).unwrap().with_speed(2.0);
+        assert_eq!(replay.next_tick().map(|(d, q)| (d, q.bid)), Some((Duration::ZERO, 1.1)));
+        assert_eq!(replay.next_tick().map(|(d, q)| (d, q.time)), Some((Duration::from_secs(2), 104)));
+        assert!(replay.next_quote().is_none());
+        std::fs::write(&path, "EURUSD,abc,1.1,100\n").unwrap();
+        assert!(QuoteGenerator::replay(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+
+        // 脚本按顺序发送，结束后关闭通道
+        let mut rx = QuoteGenerator::script([quote(1.1, 1), quote(1.2, 2)]).with_interval(Duration::from_millis(1)).spawn(4);
+        assert_eq!(rx.recv().await.map(|q| q.bid), Some(1.1));
+        assert_eq!(rx.recv().await.map(|q| q.bid), Some(1.2));
+        assert!(rx.recv().await.is_none());
+    }
+}
@@ -0,0 +1,113 @@
+//! 原始帧监听
+//!
+//! `RawMessage` 事件只包含客户端没有解析的命令。逆向分析协议时需要观察所有命令的原始数据，
+//! `Mt4Client::raw_messages()` 返回的接收端收到每个解密后的入站帧 (包括已解析的命令，如 10 / 12):
+//!
+//! ```no_run
+//! # async fn example(client: &mt4_client::Mt4Client) {
+//! let mut frames = client.raw_messages();
+//! while let Some(frame) = frames.recv().await {
+//!     if frame.command == 10 {
+//!         println!("cmd={} err={} {:02x?}", frame.command, frame.error_code, &frame.data[..]);
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! - 帧在读取任务处理之前分发，与事件流互不影响
+//! - 数据与解密后的帧共享缓冲区，不复制；没有接收端时不分发
+//! - 接收端落后超过 `RAW_TAP_CAPACITY` 帧时跳过被覆盖的帧并累计到 `lagged()`
+
+use bytes::Bytes;
+use futures_util::Stream;
+use tokio::sync::broadcast;
+
+/// 原始帧通道容量 (每个接收端最多落后的帧数)
+pub const RAW_TAP_CAPACITY: usize = 1024;
+
+/// 解密后的入站帧
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFrame {
+    /// 命令号
+    pub command: u16,
+    /// 错误码
+    pub error_code: u8,
+    /// 消息数据
+    pub data: Bytes,
+}
+
+/// 原始帧广播通道
+#[derive(Debug)]
+pub(crate) struct RawTap {
+    tx: broadcast::Sender<RawFrame>,
+}
+
+impl Default for RawTap {
+    fn default() -> Self {
+        Self { tx: broadcast::channel(RAW_TAP_CAPACITY).0 }
+    }
+}
+
+impl RawTap {
+    pub(crate) fn subscribe(&self) -> RawMessageReceiver {
+        RawMessageReceiver { rx: self.tx.subscribe(), lagged: 0 }
+    }
+
+    /// 分发一帧 (没有接收端时直接返回)
+    pub(crate) fn publish(&self, command: u16, error_code: u8, data: &Bytes) {
+        if self.tx.receiver_count() > 0 {
+            let _ = self.tx.send(RawFrame { command, error_code, data: data.clone() });
+        }
+    }
+}
+
+/// 原始帧接收端 (由 `Mt4Client::raw_messages()` 创建)
+#[derive(Debug)]
+pub struct RawMessageReceiver {
+    rx: broadcast::Receiver<RawFrame>,
+    lagged: u64,
+}
+
+impl RawMessageReceiver {
+    /// 接收下一帧 (客户端被丢弃后返回 None)
+    pub async fn recv(&mut self) -> Option<RawFrame> {
+        loop {
+            match self.rx.recv().await {
+                Ok(frame) => return Some(frame),
+                Err(broadcast::error::RecvError::Lagged(n)) => self.lagged += n,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// 因落后而丢弃的帧总数
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+
+    /// 转换为帧流
+    pub fn into_stream(self) -> impl Stream<Item = RawFrame> + Send + 'static {
+        futures_util::stream::unfold(self, |mut rx| async move { rx.recv().await.map(|frame| (frame, rx)) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_raw_tap() {
+        let tap = RawTap::default();
+        // 没有接收端时不分发
+        tap.publish(3, 0, &Bytes::from_static(&[1]));
+        let mut frames = tap.subscribe();
+        tap.publish(10, 0, &Bytes::from_static(&[0xab, 0xcd]));
+        tap.publish(12, 1, &Bytes::new());
+
+        let frame = frames.recv().await.unwrap();
+        assert_eq!((frame.command, frame.error_code, &frame.data[..]), (10, 0, &[0xab, 0xcd][..]));
+        assert_eq!(frames.recv().await.map(|f| (f.command, f.error_code)), Some((12, 1)));
+        drop(tap);
+        assert!(frames.recv().await.is_none());
+    }
+}
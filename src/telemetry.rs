@@ -0,0 +1,168 @@
+//! 指标输出
+//!
+//! 启用 `metrics` 特性后，客户端通过 [`metrics`](https://docs.rs/metrics) 门面输出下列指标，
+//! 由应用自行安装导出器 (Prometheus、StatsD 等)；启用 `otel` 特性并调用 `otel::init()` 后同名指标经 OTLP 导出
+//! (见 `otel` 模块)。两者都未启用时以下函数均为空操作。
+//!
+//! | 指标 | 类型 | 标签 |
+//! |------|------|------|
+//! | `mt4_connected` | gauge | |
+//! | `mt4_frames_received_total` | counter | `command` |
+//! | `mt4_decrypt_errors_total` | counter | |
+//! | `mt4_events_total` | counter | `kind` |
+//! | `mt4_trade_requests_total` | counter | `trade_type` |
+//! | `mt4_trade_responses_total` | counter | `result` (`ok` / `error`) |
+//! | `mt4_trade_timeouts_total` | counter | |
+//! | `mt4_trade_latency_seconds` | histogram | |
+
+use std::time::Duration;
+
+#[cfg(feature = "otel")]
+use crate::otel::{label, record};
+
+/// 连接状态变化
+pub(crate) fn connection_state(connected: bool) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!("mt4_connected").set(if connected { 1.0 } else { 0.0 });
+    #[cfg(feature = "otel")]
+    record(|m| m.connected.record(i64::from(connected), &[]));
+    #[cfg(not(any(feature = "metrics", feature = "otel")))]
+    let _ = connected;
+}
+
+/// 收到解密后的入站帧
+pub(crate) fn frame_received(command: u16) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("mt4_frames_received_total", "command" => command.to_string()).increment(1);
+    #[cfg(feature = "otel")]
+    record(|m| m.frames_received.add(1, &label("command", i64::from(command))));
+    #[cfg(not(any(feature = "metrics", feature = "otel")))]
+    let _ = command;
+}
+
+/// 入站帧解密失败
+pub(crate) fn decrypt_error() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("mt4_decrypt_errors_total").increment(1);
+    #[cfg(feature = "otel")]
+    record(|m| m.decrypt_errors.add(1, &[]));
+}
+
+/// 发出事件
+pub(crate) fn event(kind: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("mt4_events_total", "kind" => kind).increment(1);
+    #[cfg(feature = "otel")]
+    record(|m| m.events.add(1, &label("kind", kind)));
+    #[cfg(not(any(feature = "metrics", feature = "otel")))]
+    let _ = kind;
+}
+
+/// 发送交易请求
+pub(crate) fn trade_request(trade_type: u8) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("mt4_trade_requests_total", "trade_type" => trade_type.to_string()).increment(1);
+    #[cfg(feature = "otel")]
+    record(|m| m.trade_requests.add(1, &label("trade_type", i64::from(trade_type))));
+    #[cfg(not(any(feature = "metrics", feature = "otel")))]
+    let _ = trade_type;
+}
+
+/// 收到交易响应 (`latency` 为请求发出到响应的耗时，未匹配到请求时为 None)
+pub(crate) fn trade_response(ok: bool, latency: Option<Duration>) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!("mt4_trade_responses_total", "result" => if ok { "ok" } else { "error" }).increment(1);
+        if let Some(latency) = latency {
+            metrics::histogram!("mt4_trade_latency_seconds").record(latency.as_secs_f64());
+        }
+    }
+    #[cfg(feature = "otel")]
+    record(|m| {
+        m.trade_responses.add(1, &label("result", if ok { "ok" } else { "error" }));
+        if let Some(latency) = latency {
+            m.trade_latency.record(latency.as_secs_f64(), &[]);
+        }
+    });
+    #[cfg(not(any(feature = "metrics", feature = "otel")))]
+    let _ = (ok, latency);
+}
+
+/// 交易请求超时未响应
+pub(crate) fn trade_timeout() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("mt4_trade_timeouts_total").increment(1);
+    #[cfg(feature = "otel")]
+    record(|m| m.trade_timeouts.add(1, &[]));
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use metrics::{Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// 记录计数器增量的测试记录器
+    #[derive(Default)]
+    struct CountingRecorder {
+        counters: Mutex<Vec<(String, Arc<AtomicU64>)>>,
+    }
+
+    struct AtomicCounter(Arc<AtomicU64>);
+
+    impl CounterFn for AtomicCounter {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::SeqCst);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0.store(value, Ordering::SeqCst);
+        }
+    }
+
+    impl CountingRecorder {
+        fn value(&self, key: &str) -> u64 {
+            let counters = self.counters.lock().unwrap();
+            counters.iter().filter(|(k, _)| k == key).map(|(_, v)| v.load(Ordering::SeqCst)).sum()
+        }
+    }
+
+    impl Recorder for CountingRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            let labels: Vec<String> = key.labels().map(|l| format!("{}={}", l.key(), l.value())).collect();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            let value = Arc::new(AtomicU64::new(0));
+            self.counters.lock().unwrap().push((name, value.clone()));
+            Counter::from_arc(Arc::new(AtomicCounter(value)))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_counters() {
+        let recorder = CountingRecorder::default();
+        metrics::with_local_recorder(&recorder, || {
+            frame_received(10);
+            frame_received(10);
+            event("Pong");
+            trade_request(66);
+            trade_response(false, Some(Duration::from_millis(120)));
+        });
+        assert_eq!(recorder.value("mt4_frames_received_total{command=10}"), 2);
+        assert_eq!(recorder.value("mt4_events_total{kind=Pong}"), 1);
+        assert_eq!(recorder.value("mt4_trade_requests_total{trade_type=66}"), 1);
+        assert_eq!(recorder.value("mt4_trade_responses_total{result=error}"), 1);
+    }
+}
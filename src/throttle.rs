@@ -0,0 +1,179 @@
+//! 按策略限制交易频率
+//!
+//! 多个策略共用一个账户时，每个策略可以有独立的交易预算 (例如策略 A 每小时最多 10 笔，
+//! 策略 B 不限)，避免单个失控的策略耗尽整个账户的请求额度。
+//!
+//! 策略由交易请求的注释区分: 注释中第一个 ':' 之前的部分为策略标签 (如 "trend:entry" → "trend")，
+//! 没有 ':' 时整个注释即为标签，空注释使用默认预算。
+//!
+//! 平仓 (type=70)、删除挂单 (type=72) 和报价请求 (type=0) 属于降低风险或不产生交易的操作，不受限制。
+
+use crate::error::{Mt4Error, Result};
+use crate::types::TradeRequest;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// 交易预算: 时间窗口内最多的交易次数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateBudget {
+    /// 窗口内最多交易次数
+    pub max_trades: u32,
+    /// 滑动窗口长度
+    pub window: Duration,
+}
+
+impl RateBudget {
+    /// 创建预算
+    pub fn new(max_trades: u32, window: Duration) -> Self {
+        Self { max_trades, window }
+    }
+
+    /// 每小时最多 `max_trades` 次
+    pub fn per_hour(max_trades: u32) -> Self {
+        Self::new(max_trades, Duration::from_secs(3600))
+    }
+
+    /// 每分钟最多 `max_trades` 次
+    pub fn per_minute(max_trades: u32) -> Self {
+        Self::new(max_trades, Duration::from_secs(60))
+    }
+}
+
+/// 按策略的交易频率限制器
+#[derive(Debug, Default)]
+pub struct TradeThrottle {
+    /// 未单独设置的策略使用的预算 (None 表示不限)
+    default_budget: Option<RateBudget>,
+    /// 策略标签 -> 预算 (None 表示不限)
+    budgets: HashMap<String, Option<RateBudget>>,
+    /// 策略标签 -> 窗口内的交易时间
+    history: HashMap<String, VecDeque<Instant>>,
+}
+
+impl TradeThrottle {
+    /// 创建不限制任何策略的限制器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置默认预算 (None 表示不限)
+    pub fn set_default_budget(&mut self, budget: Option<RateBudget>) {
+        self.default_budget = budget;
+    }
+
+    /// 设置策略预算 (None 表示该策略不限，即使设置了默认预算)
+    pub fn set_budget(&mut self, strategy: &str, budget: Option<RateBudget>) {
+        self.budgets.insert(strategy.to_string(), budget);
+    }
+
+    /// 移除策略的单独设置，恢复使用默认预算
+    pub fn remove_budget(&mut self, strategy: &str) {
+        self.budgets.remove(strategy);
+    }
+
+    /// 请求所属的策略标签
+    pub fn strategy_tag(comment: &str) -> &str {
+        comment.split(':').next().unwrap_or("").trim()
+    }
+
+    /// 策略当前窗口内剩余的交易次数 (不限时为 None)
+    pub fn remaining(&mut self, strategy: &str, now: Instant) -> Option<u32> {
+        let budget = self.budget_for(strategy)?;
+        let used = self.prune(strategy, budget, now);
+        Some(budget.max_trades.saturating_sub(used as u32))
+    }
+
+    /// 检查请求是否在预算内，是则记入窗口
+    ///
+    /// 超出预算时返回 `Mt4Error::RateLimited`，不记录
+    pub fn check(&mut self, request: &TradeRequest, now: Instant) -> Result<()> {
+        if !Self::is_throttled(request) {
+            return Ok(());
+        }
+        let strategy = Self::strategy_tag(&request.comment).to_string();
+        let budget = match self.budget_for(&strategy) {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+        if self.prune(&strategy, budget, now) >= budget.max_trades as usize {
+            return Err(Mt4Error::RateLimited(format!(
+                "策略 \"{}\" 已达到 {} 秒内 {} 笔的交易上限",
+                strategy,
+                budget.window.as_secs(),
+                budget.max_trades
+            )));
+        }
+        self.history.entry(strategy).or_default().push_back(now);
+        Ok(())
+    }
+
+    fn budget_for(&self, strategy: &str) -> Option<RateBudget> {
+        match self.budgets.get(strategy) {
+            Some(budget) => *budget,
+            None => self.default_budget,
+        }
+    }
+
+    /// 移除窗口外的记录，返回窗口内的交易次数
+    fn prune(&mut self, strategy: &str, budget: RateBudget, now: Instant) -> usize {
+        let Some(times) = self.history.get_mut(strategy) else {
+            return 0;
+        };
+        while times.front().is_some_and(|t| now.duration_since(*t) >= budget.window) {
+            times.pop_front();
+        }
+        times.len()
+    }
+
+    fn is_throttled(request: &TradeRequest) -> bool {
+        !matches!(request.trade_type, 0 | 70 | 72)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Symbol, Ticket};
+
+    fn request(comment: &str) -> TradeRequest {
+        let mut request = TradeRequest::buy(&Symbol::new("EURUSD").unwrap(), 0.01, 0.0, 0.0);
+        request.comment = comment.to_string();
+        request
+    }
+
+    #[test]
+    fn test_per_strategy_budgets() {
+        let mut throttle = TradeThrottle::new();
+        throttle.set_budget("a", Some(RateBudget::per_hour(2)));
+        let now = Instant::now();
+
+        assert!(throttle.check(&request("a:entry"), now).is_ok());
+        assert!(throttle.check(&request("a:scale"), now).is_ok());
+        assert!(matches!(throttle.check(&request("a"), now), Err(Mt4Error::RateLimited(_))));
+        assert_eq!(throttle.remaining("a", now), Some(0));
+
+        // 其他策略和平仓不受影响
+        for _ in 0..10 {
+            assert!(throttle.check(&request("b:entry"), now).is_ok());
+        }
+        assert!(throttle.check(&TradeRequest::close(Ticket(1), &Symbol::new("EURUSD").unwrap(), 0.01), now).is_ok());
+
+        // 窗口滑过后恢复
+        let later = now + Duration::from_secs(3600);
+        assert!(throttle.check(&request("a"), later).is_ok());
+    }
+
+    #[test]
+    fn test_default_budget() {
+        let mut throttle = TradeThrottle::new();
+        throttle.set_default_budget(Some(RateBudget::per_minute(1)));
+        throttle.set_budget("unlimited", None);
+        let now = Instant::now();
+
+        assert!(throttle.check(&request(""), now).is_ok());
+        assert!(throttle.check(&request(""), now).is_err());
+        assert!(throttle.check(&request("unlimited"), now).is_ok());
+        assert!(throttle.check(&request("unlimited"), now).is_ok());
+        assert_eq!(throttle.remaining("unlimited", now), None);
+    }
+}
@@ -0,0 +1,290 @@
+//! TLS 配置
+//!
+//! TLS 后端由 crate 特性选择:
+//!
+//! - `rustls` (默认): 纯 Rust 实现，内置 webpki 根证书
+//! - `native-tls`: 系统 TLS 库 (OpenSSL / SChannel / Security.framework)
+//!
+//! 加固部署可以通过 `TlsConfig` 为信号服务器 (WebSocket) 连接追加或替换根证书，
+//! 并固定服务器证书的 SHA-256 指纹。证书链验证照常进行，固定指纹是额外的检查:
+//! 服务器证书或任一中间证书的指纹匹配即通过。自定义 TLS 配置需要 `rustls` 特性。
+//!
+//! 两个特性都关闭时 (`--no-default-features`) 只能连接 ws:// 地址，wss:// 地址返回 `Mt4Error::Connection`。
+//!
+//! ```no_run
+//! use mt4_client::{Mt4Client, TlsConfig};
+//!
+//! # fn main() -> mt4_client::Result<()> {
+//! let tls = TlsConfig::new()
+//!     .with_root_pem_file("/etc/mt4/ca.pem")?
+//!     .with_pinned_sha256("3f:a1:...")?;
+//! let client = Mt4Client::builder().tls(tls).build();
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Mt4Error, Result};
+use std::path::Path;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// 信号服务器 WebSocket 连接
+pub(crate) type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// 信号服务器连接的 TLS 配置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// 额外信任的根证书 (DER)
+    pub root_certs: Vec<Vec<u8>>,
+    /// 只信任 `root_certs`，不使用内置根证书
+    pub exclusive_roots: bool,
+    /// 固定的证书 SHA-256 指纹 (为空表示不固定)
+    pub pinned_sha256: Vec<[u8; 32]>,
+}
+
+impl TlsConfig {
+    /// 创建默认配置 (内置根证书，不固定指纹)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 是否为默认配置 (无需自定义 TLS 连接器)
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// 追加 DER 格式的根证书
+    pub fn with_root_der(mut self, der: impl Into<Vec<u8>>) -> Self {
+        self.root_certs.push(der.into());
+        self
+    }
+
+    /// 追加 PEM 文本中的所有根证书
+    pub fn with_root_pem(mut self, pem: &str) -> Result<Self> {
+        let certs = parse_pem_certs(pem)?;
+        if certs.is_empty() {
+            return Err(Mt4Error::InvalidParams("PEM 中没有证书".to_string()));
+        }
+        self.root_certs.extend(certs);
+        Ok(self)
+    }
+
+    /// 追加 PEM 文件中的所有根证书
+    pub fn with_root_pem_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let pem = std::fs::read_to_string(path)
+            .map_err(|e| Mt4Error::Io(format!("读取证书 {} 失败: {}", path.display(), e)))?;
+        self.with_root_pem(&pem)
+    }
+
+    /// 只信任自定义根证书
+    pub fn exclusive_roots(mut self) -> Self {
+        self.exclusive_roots = true;
+        self
+    }
+
+    /// 固定证书指纹 (64 位十六进制，可带 ':' 分隔)
+    pub fn with_pinned_sha256(mut self, fingerprint: &str) -> Result<Self> {
+        let digits: String = fingerprint.chars().filter(|c| *c != ':' && !c.is_whitespace()).collect();
+        let pin = hex::decode(&digits)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| Mt4Error::InvalidParams(format!("证书指纹 {} 无效", fingerprint)))?;
+        self.pinned_sha256.push(pin);
+        Ok(self)
+    }
+
+    /// 建立 WebSocket 连接 (`tunnel` 为代理隧道，None 时直接连接)
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub(crate) async fn connect(&self, url: &str, tunnel: Option<TcpStream>) -> Result<WsStream> {
+        let connector = self.connector()?;
+        let (stream, _) = match tunnel {
+            Some(stream) => tokio_tungstenite::client_async_tls_with_config(url, stream, None, connector).await?,
+            None => tokio_tungstenite::connect_async_tls_with_config(url, None, false, connector).await?,
+        };
+        Ok(stream)
+    }
+
+    /// 建立 WebSocket 连接 (未启用 TLS 特性: 只支持 ws:// 地址)
+    #[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+    pub(crate) async fn connect(&self, url: &str, tunnel: Option<TcpStream>) -> Result<WsStream> {
+        if !url.starts_with("ws://") {
+            return Err(Mt4Error::Connection(format!("{} 需要 TLS，请启用 rustls 或 native-tls 特性", url)));
+        }
+        if !self.is_default() {
+            return Err(Mt4Error::InvalidParams("自定义根证书和证书固定需要启用 rustls 特性".to_string()));
+        }
+        let (stream, _) = match tunnel {
+            Some(stream) => tokio_tungstenite::client_async_with_config(url, MaybeTlsStream::Plain(stream), None).await?,
+            None => tokio_tungstenite::connect_async_with_config(url, None, false).await?,
+        };
+        Ok(stream)
+    }
+
+    /// tokio-tungstenite 连接器 (默认配置为 None，使用后端自带的 TLS 设置)
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub(crate) fn connector(&self) -> Result<Option<tokio_tungstenite::Connector>> {
+        if self.is_default() {
+            return Ok(None);
+        }
+        #[cfg(feature = "rustls")]
+        {
+            Ok(Some(tokio_tungstenite::Connector::Rustls(self.rustls_config()?)))
+        }
+        #[cfg(not(feature = "rustls"))]
+        {
+            Err(Mt4Error::InvalidParams("自定义根证书和证书固定需要启用 rustls 特性".to_string()))
+        }
+    }
+
+    /// 构建 rustls 客户端配置
+    #[cfg(feature = "rustls")]
+    fn rustls_config(&self) -> Result<std::sync::Arc<rustls::ClientConfig>> {
+        use rustls::client::WebPkiServerVerifier;
+        use rustls::pki_types::CertificateDer;
+        use std::sync::Arc;
+
+        let tls_err = |e: rustls::Error| Mt4Error::InvalidParams(format!("TLS 配置无效: {}", e));
+        let mut roots = rustls::RootCertStore::empty();
+        if !self.exclusive_roots {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+        for der in &self.root_certs {
+            roots.add(CertificateDer::from(der.clone())).map_err(tls_err)?;
+        }
+        if roots.is_empty() {
+            return Err(Mt4Error::InvalidParams("没有可信任的根证书".to_string()));
+        }
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_err)?;
+        let config = if self.pinned_sha256.is_empty() {
+            builder.with_root_certificates(roots)
+        } else {
+            let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(|e| Mt4Error::InvalidParams(format!("TLS 配置无效: {}", e)))?;
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PinnedVerifier { inner, pins: self.pinned_sha256.clone() }))
+        };
+        Ok(Arc::new(config.with_no_client_auth()))
+    }
+}
+
+/// 解析 PEM 文本中的 CERTIFICATE 块
+fn parse_pem_certs(pem: &str) -> Result<Vec<Vec<u8>>> {
+    #[cfg(feature = "rustls")]
+    {
+        use rustls::pki_types::pem::PemObject;
+        use rustls::pki_types::CertificateDer;
+
+        CertificateDer::pem_slice_iter(pem.as_bytes())
+            .map(|cert| {
+                cert.map(|c| c.to_vec())
+                    .map_err(|e| Mt4Error::InvalidParams(format!("PEM 证书无效: {:?}", e)))
+            })
+            .collect()
+    }
+    #[cfg(not(feature = "rustls"))]
+    {
+        let _ = pem;
+        Err(Mt4Error::InvalidParams("自定义根证书需要启用 rustls 特性".to_string()))
+    }
+}
+
+/// 证书 SHA-256 指纹
+#[cfg(feature = "rustls")]
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let digest = ring::digest::digest(&ring::digest::SHA256, data);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(digest.as_ref());
+    out
+}
+
+/// 在标准证书链验证之上检查固定指纹
+#[cfg(feature = "rustls")]
+#[derive(Debug)]
+struct PinnedVerifier {
+    inner: std::sync::Arc<rustls::client::WebPkiServerVerifier>,
+    pins: Vec<[u8; 32]>,
+}
+
+#[cfg(feature = "rustls")]
+impl rustls::client::danger::ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::pki_types::CertificateDer<'_>,
+        intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        server_name: &rustls::pki_types::ServerName<'_>,
+        ocsp_response: &[u8],
+        now: rustls::pki_types::UnixTime,
+    ) -> std::result::Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+        if std::iter::once(end_entity).chain(intermediates).any(|cert| self.pins.contains(&sha256(cert))) {
+            Ok(verified)
+        } else {
+            tracing::error!("Server certificate for {:?} does not match any pinned fingerprint", server_name);
+            Err(rustls::Error::General("服务器证书指纹不匹配".to_string()))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn test_tls_config() {
+        assert!(TlsConfig::new().connector().unwrap().is_none());
+
+        let pin = "AB:".repeat(31) + "AB";
+        let tls = TlsConfig::new().with_pinned_sha256(&pin).unwrap();
+        assert_eq!(tls.pinned_sha256, vec![[0xab; 32]]);
+        assert!(matches!(tls.connector().unwrap(), Some(tokio_tungstenite::Connector::Rustls(_))));
+        assert!(TlsConfig::new().with_pinned_sha256("abcd").is_err());
+
+        // 只信任自定义根证书但未提供
+        assert!(TlsConfig::new().exclusive_roots().connector().is_err());
+        assert!(TlsConfig::new().with_root_pem("not a certificate").is_err());
+        assert_eq!(
+            hex::encode(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    /// 未启用 TLS 特性时拒绝 wss:// 和自定义 TLS 配置，不会以明文连接 TLS 端口
+    #[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+    #[tokio::test]
+    async fn test_connect_without_tls() {
+        let result = TlsConfig::new().connect("wss://127.0.0.1:1/", None).await;
+        assert!(matches!(result, Err(Mt4Error::Connection(_))));
+        let tls = TlsConfig::new().with_root_der(vec![0u8]);
+        assert!(matches!(tls.connect("ws://127.0.0.1:1/", None).await, Err(Mt4Error::InvalidParams(_))));
+    }
+}
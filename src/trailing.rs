@@ -0,0 +1,208 @@
+//! 移动止损模拟
+//!
+//! MT4 Web 协议没有服务器端移动止损。`TrailingEngine` 记录需要跟踪的持仓和移动参数，
+//! 根据最新报价计算新的止损。`Mt4Client::trail_stop()` 登记的持仓由读取任务在每次收到报价时计算并发送修改请求；
+//! `Mt4Client::apply_trailing()` 按调用方提供的报价立即修改并等待结果。
+//!
+//! 引擎只保存 ticket 和参数，每次计算都读取持仓缓存中的最新止损，因此重连后持仓快照到达即自动恢复跟踪；
+//! 部分平仓后跟踪转到剩余手数的新 ticket。
+//!
+//! ```
+//! use mt4_client::{Order, OrderType, Quote, Symbol, Ticket, TrailingEngine, TrailingStop};
+//! # let position = Order {
+//! #     ticket: Ticket(1), symbol: Symbol::new("EURUSD").unwrap(), digits: 5, order_type: OrderType::Buy,
+//! #     volume: 0.1, open_time: 0, open_price: 1.08, sl: 0.0, tp: 0.0, close_time: 0, close_price: 0.0,
+//! #     commission: 0.0, swap: 0.0, profit: 0.0, comment: String::new(),
+//! # };
+//!
+//! let mut engine = TrailingEngine::new();
+//! // 距离 200 点 (20 pips)，每次至少移动 50 点
+//! engine.track(Ticket(1), TrailingStop::new(200).with_step(50));
+//!
+//! let quote = Quote { symbol: "EURUSD".to_string(), bid: 1.0850, ask: 1.0852, time: 0 };
+//! let updates = engine.on_quote(&quote, &[position]);
+//! assert!((updates[0].sl - 1.0830).abs() < 1e-9);
+//! ```
+
+use crate::types::{Order, Quote, Ticket};
+use std::collections::HashMap;
+
+/// 移动止损参数 (以点为单位，1 点 = 10^-digits)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TrailingStop {
+    /// 止损与当前价格的距离
+    pub distance: i32,
+    /// 每次至少移动的距离，避免频繁修改
+    pub step: i32,
+    /// 盈利达到该点数后才开始移动 (0 表示立即)
+    pub activation: i32,
+}
+
+impl TrailingStop {
+    /// 以距离创建 (步长 1 点，立即生效)
+    pub fn new(distance: i32) -> Self {
+        Self { distance, step: 1, activation: 0 }
+    }
+
+    /// 设置最小移动步长
+    pub fn with_step(mut self, step: i32) -> Self {
+        self.step = step.max(1);
+        self
+    }
+
+    /// 设置开始移动所需的盈利点数
+    pub fn with_activation(mut self, activation: i32) -> Self {
+        self.activation = activation.max(0);
+        self
+    }
+}
+
+/// 需要发送的止损修改
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrailUpdate {
+    /// 持仓订单号
+    pub ticket: Ticket,
+    /// 新止损
+    pub sl: f64,
+    /// 保持不变的止盈
+    pub tp: f64,
+}
+
+/// 移动止损引擎
+#[derive(Debug, Clone, Default)]
+pub struct TrailingEngine {
+    stops: HashMap<Ticket, TrailingStop>,
+}
+
+impl TrailingEngine {
+    /// 创建空引擎
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始 (或更新) 跟踪持仓
+    pub fn track(&mut self, ticket: Ticket, stop: TrailingStop) {
+        self.stops.insert(ticket, stop);
+    }
+
+    /// 停止跟踪，返回是否曾在跟踪
+    pub fn untrack(&mut self, ticket: Ticket) -> bool {
+        self.stops.remove(&ticket).is_some()
+    }
+
+    /// 持仓的移动止损参数
+    pub fn get(&self, ticket: Ticket) -> Option<TrailingStop> {
+        self.stops.get(&ticket).copied()
+    }
+
+    /// 正在跟踪的订单号 (升序)
+    pub fn tracked(&self) -> Vec<Ticket> {
+        let mut tickets: Vec<Ticket> = self.stops.keys().copied().collect();
+        tickets.sort();
+        tickets
+    }
+
+    /// 是否没有跟踪任何持仓
+    pub fn is_empty(&self) -> bool {
+        self.stops.is_empty()
+    }
+
+    /// 部分平仓后把跟踪转到剩余手数的新 ticket
+    pub fn transfer(&mut self, from: Ticket, to: Ticket) {
+        if let Some(stop) = self.stops.remove(&from) {
+            self.stops.insert(to, stop);
+        }
+    }
+
+    /// 根据报价计算需要移动止损的持仓
+    ///
+    /// `positions` 为当前持仓 (通常来自持仓缓存)，不在其中的已跟踪 ticket 暂不处理
+    pub fn on_quote(&self, quote: &Quote, positions: &[Order]) -> Vec<TrailUpdate> {
+        positions
+            .iter()
+            .filter(|o| !o.is_pending() && o.symbol == quote.symbol.as_str())
+            .filter_map(|o| Self::trail(o, self.stops.get(&o.ticket)?, quote))
+            .collect()
+    }
+
+    fn trail(order: &Order, stop: &TrailingStop, quote: &Quote) -> Option<TrailUpdate> {
+        let point = 10f64.powi(-order.digits);
+        let round = |price: f64| (price / point).round() * point;
+        let buy = order.order_type.is_buy();
+
+        // 买单按 bid 平仓，卖单按 ask 平仓
+        let (price, profit) = if buy {
+            (quote.bid, quote.bid - order.open_price)
+        } else {
+            (quote.ask, order.open_price - quote.ask)
+        };
+        if price <= 0.0 || profit < stop.activation as f64 * point - point / 2.0 {
+            return None;
+        }
+
+        let sl = round(if buy { price - stop.distance as f64 * point } else { price + stop.distance as f64 * point });
+        let min_move = stop.step as f64 * point - point / 2.0;
+        let improved = order.sl <= 0.0 || if buy { sl - order.sl >= min_move } else { order.sl - sl >= min_move };
+        improved.then_some(TrailUpdate { ticket: order.ticket, sl, tp: order.tp })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::OrderType;
+
+    fn position(ticket: i32, order_type: OrderType, open_price: f64, sl: f64) -> Order {
+        Order { sl, ..Order::for_test(ticket, "EURUSD", order_type, 0.1, open_price) }
+    }
+
+    #[test]
+    fn test_trailing_updates() {
+        let mut engine = TrailingEngine::new();
+        let stop = TrailingStop::new(200).with_step(50).with_activation(100);
+        engine.track(Ticket(1), stop);
+        engine.track(Ticket(2), stop);
+        let quote = |bid: f64| Quote { symbol: "EURUSD".to_string(), bid, ask: bid + 0.0002, time: 0 };
+
+        // 盈利不足 10 点时不移动
+        let positions = [position(1, OrderType::Buy, 1.0800, 0.0), position(2, OrderType::Sell, 1.0900, 1.0950)];
+        assert!(engine.on_quote(&quote(1.0805), &positions[..1]).is_empty());
+
+        let updates = engine.on_quote(&quote(1.0830), &positions);
+        assert_eq!(updates.len(), 2);
+        assert!((updates[0].sl - 1.0810).abs() < 1e-9);
+        // 卖单按 ask 计算: 1.0832 + 0.0020
+        assert!((updates[1].sl - 1.0852).abs() < 1e-9);
+
+        // 改善不足步长时不修改，止损也不会后退
+        let positions = [position(1, OrderType::Buy, 1.0800, 1.0810)];
+        assert!(engine.on_quote(&quote(1.0834), &positions).is_empty());
+        assert!(engine.on_quote(&quote(1.0820), &positions).is_empty());
+        assert_eq!(engine.on_quote(&quote(1.0835), &positions).len(), 1);
+
+        engine.transfer(Ticket(1), Ticket(3));
+        assert_eq!(engine.tracked(), vec![Ticket(2), Ticket(3)]);
+    }
+
+    #[test]
+    fn test_trailing_sell_mirror() {
+        let mut engine = TrailingEngine::new();
+        engine.track(Ticket(1), TrailingStop::new(200).with_step(50).with_activation(100));
+        let quote = |ask: f64| Quote { symbol: "EURUSD".to_string(), bid: ask - 0.0002, ask, time: 0 };
+
+        // 卖单按 ask 计算盈利，不足 10 点时不移动
+        let positions = [position(1, OrderType::Sell, 1.0900, 0.0)];
+        assert!(engine.on_quote(&quote(1.0895), &positions).is_empty());
+        assert!((engine.on_quote(&quote(1.0880), &positions)[0].sl - 1.0900).abs() < 1e-9);
+
+        // 止损只向下移动，且每次至少移动步长
+        let positions = [position(1, OrderType::Sell, 1.0900, 1.0900)];
+        assert!(engine.on_quote(&quote(1.0876), &positions).is_empty());
+        assert!(engine.on_quote(&quote(1.0890), &positions).is_empty());
+        assert!((engine.on_quote(&quote(1.0875), &positions)[0].sl - 1.0895).abs() < 1e-9);
+
+        // 未跟踪的 ticket 和挂单不处理
+        assert!(engine.on_quote(&quote(1.0800), &[position(2, OrderType::Sell, 1.0900, 0.0)]).is_empty());
+        assert!(engine.on_quote(&quote(1.0800), &[position(1, OrderType::SellLimit, 1.0900, 0.0)]).is_empty());
+    }
+}
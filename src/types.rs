@@ -1,16 +1,166 @@
 //! 数据类型定义
 
-use crate::protocol::OrderType;
+use crate::error::{Mt4Error, Result};
+use crate::protocol::{OrderType, CANDLE_SIZE, QUOTE_SIZE};
+#[cfg(feature = "experimental-chart")]
+use crate::protocol::{Timeframe, CHART_REQUEST_SIZE};
+use crate::quirks::AccountLayout;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::fmt;
 use std::io::Cursor;
 
+/// 品种名称在协议中的最大长度 (字节)
+pub const SYMBOL_MAX_LEN: usize = 12;
+
+/// 订单号
+///
+/// 与 request_id、notify_id 等其他 i32 区分，避免把其他数值当作订单号传入
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Ticket(pub i32);
+
+impl Ticket {
+    /// 原始订单号
+    pub fn get(self) -> i32 {
+        self.0
+    }
+
+    /// 是否指向已有订单 (新订单请求的订单号为 0)
+    pub fn is_set(self) -> bool {
+        self.0 != 0
+    }
+}
+
+impl From<i32> for Ticket {
+    fn from(ticket: i32) -> Self {
+        Self(ticket)
+    }
+}
+
+impl From<Ticket> for i32 {
+    fn from(ticket: Ticket) -> Self {
+        ticket.0
+    }
+}
+
+impl fmt::Display for Ticket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// 品种名称
+///
+/// 非空、只含 ASCII 可见字符、不超过 12 字节 (协议字段长度)，
+/// 超长的名称在构造时报错，而不是在编码时被静默截断
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Symbol(String);
+
+impl Symbol {
+    /// 校验并创建
+    pub fn new(symbol: &str) -> Result<Self> {
+        if symbol.is_empty() || symbol.len() > SYMBOL_MAX_LEN || !symbol.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(Mt4Error::InvalidParams(format!(
+                "品种名称 {:?} 无效 (须为 1-{} 个 ASCII 可见字符)",
+                symbol, SYMBOL_MAX_LEN
+            )));
+        }
+        Ok(Self(symbol.to_string()))
+    }
+
+    /// 从协议字段解码 (最多 12 字节，去掉尾部的 0，不做校验)
+    pub(crate) fn from_wire(bytes: &[u8]) -> Self {
+        Self(String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string())
+    }
+
+    /// 字符串形式
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::borrow::Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for Symbol {
+    type Err = Mt4Error;
+
+    fn from_str(symbol: &str) -> Result<Self> {
+        Self::new(symbol)
+    }
+}
+
+impl TryFrom<String> for Symbol {
+    type Error = Mt4Error;
+
+    fn try_from(symbol: String) -> Result<Self> {
+        Self::new(&symbol)
+    }
+}
+
+impl TryFrom<&str> for Symbol {
+    type Error = Mt4Error;
+
+    fn try_from(symbol: &str) -> Result<Self> {
+        Self::new(symbol)
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.0
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// 订单信息
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Order {
     /// 订单号
-    pub ticket: i32,
+    pub ticket: Ticket,
     /// 品种
-    pub symbol: String,
+    pub symbol: Symbol,
     /// 小数位数
     pub digits: i32,
     /// 订单类型
@@ -66,22 +216,20 @@ impl Order {
     /// - 121-152: comment (32 bytes)     - c.vc (xg)
     /// - 153-160: commission (f64)       - c.wo
     pub fn from_bytes(data: &[u8], offset: usize) -> Option<Self> {
-        if data.len() < offset + 161 {
+        if data.len().saturating_sub(offset) < 161 {
             return None;
         }
 
         let base = offset;
 
         // 0-3: ticket (i32)
-        let ticket = i32::from_le_bytes([
+        let ticket = Ticket(i32::from_le_bytes([
             data[base], data[base+1], data[base+2], data[base+3]
-        ]);
+        ]));
 
         // 4-15: symbol (12字节)
         let symbol_bytes = &data[base+4..base+16];
-        let symbol = String::from_utf8_lossy(symbol_bytes)
-            .trim_end_matches('\0')
-            .to_string();
+        let symbol = Symbol::from_wire(symbol_bytes);
 
         // 16-19: digits (i32)
         let digits = i32::from_le_bytes([
@@ -183,6 +331,13 @@ impl Order {
         })
     }
 
+    /// 解析 161 字节 Order 结构数组 (无头部，Command 4/5/9)
+    ///
+    /// 历史订单的平仓时间和平仓价格分别在 60-63 和 93-100，末尾不足 161 字节的部分忽略。
+    pub fn parse_list(data: &[u8]) -> Vec<Self> {
+        data.chunks_exact(161).filter_map(|record| Self::from_bytes(record, 0)).collect()
+    }
+
     /// 是否为持仓订单 (close_time == 0 表示未平仓)
     pub fn is_open(&self) -> bool {
         self.close_time == 0
@@ -198,19 +353,167 @@ impl Order {
                 | OrderType::SellStop
         )
     }
+
+    /// 部分平仓后剩余订单的原始 ticket
+    ///
+    /// MT4 部分平仓时服务器会为剩余手数开一张新单，注释为 "from #<原ticket>"
+    pub fn parent_ticket(&self) -> Option<Ticket> {
+        let rest = self.comment.trim().strip_prefix("from #")?;
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        digits.parse().ok().map(Ticket)
+    }
+
+    /// 部分平仓后剩余订单的新 ticket
+    ///
+    /// 被部分平仓的原订单关闭时注释为 "to #<新ticket>"
+    pub fn child_ticket(&self) -> Option<Ticket> {
+        let rest = self.comment.trim().strip_prefix("to #")?;
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        digits.parse().ok().map(Ticket)
+    }
+}
+
+#[cfg(test)]
+impl Order {
+    /// 测试用的未平仓订单 (digits 为 5，开仓时间 1_700_000_000，其余字段为 0 / 空)
+    pub(crate) fn for_test(ticket: i32, symbol: &str, order_type: OrderType, volume: f64, price: f64) -> Self {
+        Self {
+            ticket: Ticket(ticket),
+            symbol: Symbol::new(symbol).unwrap(),
+            digits: 5,
+            order_type,
+            volume,
+            open_time: 1_700_000_000,
+            open_price: price,
+            sl: 0.0,
+            tp: 0.0,
+            close_time: 0,
+            close_price: 0.0,
+            commission: 0.0,
+            swap: 0.0,
+            profit: 0.0,
+            comment: String::new(),
+        }
+    }
+}
+
+/// 品种交易规格
+///
+/// Command 3 中的品种信息 (28字节) 不包含手数规格，
+/// 因此由调用方通过 `Mt4Client::set_symbol_info()` 提供，未提供时使用 MT4 常见默认值
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SymbolInfo {
+    /// 品种
+    pub symbol: String,
+    /// 小数位数
+    pub digits: i32,
+    /// 最小手数
+    pub lot_min: f64,
+    /// 最大手数
+    pub lot_max: f64,
+    /// 手数步长
+    pub lot_step: f64,
+    /// 1 手的合约数量，即价格变动 1.0 时 1 手的盈亏 (账户货币)
+    ///
+    /// 默认按外汇 100000 计算；报价货币不是账户货币或为差价合约时需要按实际情况设置
+    #[serde(default = "default_contract_size")]
+    pub contract_size: f64,
+}
+
+fn default_contract_size() -> f64 {
+    100_000.0
+}
+
+impl SymbolInfo {
+    /// 使用默认手数规格创建 (0.01 / 100.0 / 0.01，合约 100000)
+    pub fn new(symbol: &str, digits: i32) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            digits,
+            lot_min: 0.01,
+            lot_max: 100.0,
+            lot_step: 0.01,
+            contract_size: default_contract_size(),
+        }
+    }
+
+    /// 手数是否为步长的整数倍 (允许浮点误差)
+    pub fn is_volume_on_step(&self, volume: f64) -> bool {
+        if self.lot_step <= 0.0 {
+            return true;
+        }
+        let steps = volume / self.lot_step;
+        (steps - steps.round()).abs() < 1e-6
+    }
+
+    /// 将手数取整到最近的步长整数倍，并限制在最小/最大手数之间
+    ///
+    /// ```
+    /// use mt4_client::SymbolInfo;
+    ///
+    /// let spec = SymbolInfo::new("EURUSD", 5);
+    /// assert_eq!(spec.normalize_volume(0.013), 0.01);
+    /// assert_eq!(spec.normalize_volume(0.001), 0.01);
+    /// assert_eq!(spec.normalize_volume(250.0), 100.0);
+    /// ```
+    pub fn normalize_volume(&self, volume: f64) -> f64 {
+        let volume = if self.lot_step > 0.0 { (volume / self.lot_step).round() * self.lot_step } else { volume };
+        // 去掉步长相乘引入的浮点误差 (0.07 而不是 0.07000000000000001)
+        (volume.clamp(self.lot_min, self.lot_max.max(self.lot_min)) * 1e8).round() / 1e8
+    }
+
+    /// 最小价格变动 (1 点 = 10^-digits)
+    pub fn point(&self) -> f64 {
+        10f64.powi(-self.digits)
+    }
+
+    /// 价格变动 1 点时 1 手的盈亏 (账户货币)
+    pub fn tick_value(&self) -> f64 {
+        self.contract_size * self.point()
+    }
+}
+
+/// 挂单有效期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TimeInForce {
+    /// 撤销前一直有效 (GTC)
+    GoodTillCancelled,
+    /// 在指定时间前有效 (GTD)，UTC Unix 时间戳 (秒)
+    GoodTillDate(i64),
+}
+
+impl TimeInForce {
+    /// 换算为协议中的过期时间 (经纪商服务器时间，GTC 为 0)
+    ///
+    /// `offset_secs` 为 "服务器时间 - UTC" 偏移，可由 `DriftEstimator::timezone_offset_secs()` 估计
+    pub fn to_server_time(&self, offset_secs: i64) -> i32 {
+        match self {
+            TimeInForce::GoodTillCancelled => 0,
+            TimeInForce::GoodTillDate(utc) => (utc + offset_secs).clamp(1, i32::MAX as i64) as i32,
+        }
+    }
+
+    /// 从协议中的过期时间还原
+    pub fn from_server_time(expiration: i32, offset_secs: i64) -> Self {
+        if expiration > 0 {
+            TimeInForce::GoodTillDate(expiration as i64 - offset_secs)
+        } else {
+            TimeInForce::GoodTillCancelled
+        }
+    }
 }
 
 /// 交易请求
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct TradeRequest {
     /// 请求类型
     pub trade_type: u8,
     /// 订单类型
     pub order_type: OrderType,
     /// 订单号 (新订单为0)
-    pub ticket: i32,
+    pub ticket: Ticket,
     /// 品种
-    pub symbol: String,
+    pub symbol: Symbol,
     /// 手数 (实际手数)
     pub volume: f64,
     /// 价格 (市价单可为0)
@@ -232,12 +535,12 @@ pub struct TradeRequest {
 
 impl TradeRequest {
     /// 创建市价买入请求
-    pub fn buy(symbol: &str, volume: f64, sl: f64, tp: f64) -> Self {
+    pub fn buy(symbol: &Symbol, volume: f64, sl: f64, tp: f64) -> Self {
         Self {
             trade_type: 66, // Market
             order_type: OrderType::Buy,
-            ticket: 0,
-            symbol: symbol.to_string(),
+            ticket: Ticket(0),
+            symbol: symbol.clone(),
             volume,
             price: 0.0,
             sl,
@@ -250,12 +553,12 @@ impl TradeRequest {
     }
 
     /// 创建市价卖出请求
-    pub fn sell(symbol: &str, volume: f64, sl: f64, tp: f64) -> Self {
+    pub fn sell(symbol: &Symbol, volume: f64, sl: f64, tp: f64) -> Self {
         Self {
             trade_type: 66, // Market
             order_type: OrderType::Sell,
-            ticket: 0,
-            symbol: symbol.to_string(),
+            ticket: Ticket(0),
+            symbol: symbol.clone(),
             volume,
             price: 0.0,
             sl,
@@ -268,12 +571,12 @@ impl TradeRequest {
     }
 
     /// 创建限价买入请求
-    pub fn buy_limit(symbol: &str, volume: f64, price: f64, sl: f64, tp: f64) -> Self {
+    pub fn buy_limit(symbol: &Symbol, volume: f64, price: f64, sl: f64, tp: f64) -> Self {
         Self {
             trade_type: 67, // Pending
             order_type: OrderType::BuyLimit,
-            ticket: 0,
-            symbol: symbol.to_string(),
+            ticket: Ticket(0),
+            symbol: symbol.clone(),
             volume,
             price,
             sl,
@@ -286,12 +589,12 @@ impl TradeRequest {
     }
 
     /// 创建限价卖出请求
-    pub fn sell_limit(symbol: &str, volume: f64, price: f64, sl: f64, tp: f64) -> Self {
+    pub fn sell_limit(symbol: &Symbol, volume: f64, price: f64, sl: f64, tp: f64) -> Self {
         Self {
             trade_type: 67, // Pending
             order_type: OrderType::SellLimit,
-            ticket: 0,
-            symbol: symbol.to_string(),
+            ticket: Ticket(0),
+            symbol: symbol.clone(),
             volume,
             price,
             sl,
@@ -304,12 +607,12 @@ impl TradeRequest {
     }
 
     /// 创建平仓请求
-    pub fn close(ticket: i32, symbol: &str, volume: f64) -> Self {
+    pub fn close(ticket: Ticket, symbol: &Symbol, volume: f64) -> Self {
         Self {
             trade_type: 70, // CloseMarket
             order_type: OrderType::Buy, // 会被忽略
             ticket,
-            symbol: symbol.to_string(),
+            symbol: symbol.clone(),
             volume,
             price: 0.0,
             sl: 0.0,
@@ -321,13 +624,33 @@ impl TradeRequest {
         }
     }
 
+    /// 创建修改订单请求 (type=71)
+    ///
+    /// 修改持仓的止损/止盈，或挂单的价格/止损/止盈；`price` 对持仓传开仓价
+    pub fn modify(ticket: Ticket, symbol: &Symbol, order_type: OrderType, price: f64, sl: f64, tp: f64) -> Self {
+        Self {
+            trade_type: 71, // Modify
+            order_type,
+            ticket,
+            symbol: symbol.clone(),
+            volume: 0.0,
+            price,
+            sl,
+            tp,
+            slippage: 0,
+            comment: String::new(),
+            expiration: 0,
+            request_id: 0,
+        }
+    }
+
     /// 创建取消挂单请求
-    pub fn cancel(ticket: i32, symbol: &str) -> Self {
+    pub fn cancel(ticket: Ticket, symbol: &Symbol) -> Self {
         Self {
             trade_type: 72, // Delete
             order_type: OrderType::Buy, // 会被忽略
             ticket,
-            symbol: symbol.to_string(),
+            symbol: symbol.clone(),
             volume: 0.0,
             price: 0.0,
             sl: 0.0,
@@ -339,6 +662,58 @@ impl TradeRequest {
         }
     }
 
+    /// 创建报价请求 (type=0)
+    ///
+    /// 服务器在交易响应的 price1/price2 中返回当前 bid/ask，不会开仓
+    pub fn quote(symbol: &Symbol, volume: f64) -> Self {
+        Self {
+            trade_type: 0, // Quote
+            order_type: OrderType::Buy,
+            ticket: Ticket(0),
+            symbol: symbol.clone(),
+            volume,
+            price: 0.0,
+            sl: 0.0,
+            tp: 0.0,
+            slippage: 0,
+            comment: String::new(),
+            expiration: 0,
+            request_id: 0,
+        }
+    }
+
+    /// 设置挂单有效期，`offset_secs` 为 "服务器时间 - UTC" 偏移
+    ///
+    /// ```
+    /// use mt4_client::{Symbol, TimeInForce, TradeRequest};
+    ///
+    /// let symbol = Symbol::new("EURUSD").unwrap();
+    /// // UTC 2023-11-14 22:13:20 到期，经纪商为 GMT+2
+    /// let request = TradeRequest::buy_limit(&symbol, 0.1, 1.07, 0.0, 0.0)
+    ///     .with_time_in_force(TimeInForce::GoodTillDate(1_700_000_000), 7200);
+    /// assert_eq!(request.expiration, 1_700_007_200);
+    /// assert_eq!(request.time_in_force(7200), TimeInForce::GoodTillDate(1_700_000_000));
+    /// ```
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce, offset_secs: i64) -> Self {
+        self.expiration = time_in_force.to_server_time(offset_secs);
+        self
+    }
+
+    /// 挂单有效期
+    pub fn time_in_force(&self, offset_secs: i64) -> TimeInForce {
+        TimeInForce::from_server_time(self.expiration, offset_secs)
+    }
+
+    /// 是否为开仓请求 (市价单或挂单)
+    pub fn is_open_request(&self) -> bool {
+        matches!(self.trade_type, 64..=67)
+    }
+
+    /// 是否为市价开仓请求 (立即执行、请求执行或市价执行)
+    pub fn is_market_request(&self) -> bool {
+        matches!(self.trade_type, 64..=66)
+    }
+
     /// 序列化为字节数组 (95字节)
     ///
     /// 根据 JS mt4.en.js 第1104行 q.pG 函数:
@@ -368,7 +743,7 @@ impl TradeRequest {
             .unwrap();
 
         // ticket (4 bytes)
-        cursor.write_i32::<LittleEndian>(self.ticket).unwrap();
+        cursor.write_i32::<LittleEndian>(self.ticket.0).unwrap();
 
         // unknown (4 bytes)
         cursor.write_i32::<LittleEndian>(0).unwrap();
@@ -381,9 +756,9 @@ impl TradeRequest {
         // 跳过 symbol 后继续写入
         let mut cursor = Cursor::new(&mut buffer[23..]);
 
-        // volume (4 bytes) - 手数*100
+        // volume (4 bytes) - 手数*100 (四舍五入，0.29 不会因浮点误差截断为 28)
         cursor
-            .write_i32::<LittleEndian>((self.volume * 100.0) as i32)
+            .write_i32::<LittleEndian>((self.volume * 100.0).round() as i32)
             .unwrap();
 
         // price (8 bytes)
@@ -413,10 +788,54 @@ impl TradeRequest {
 
         buffer
     }
+
+    /// 从字节数组解析 (95字节，`to_bytes` 的逆操作)
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 95 {
+            return None;
+        }
+        let text = |range: std::ops::Range<usize>| {
+            String::from_utf8_lossy(&data[range]).trim_end_matches('\0').to_string()
+        };
+
+        let mut cursor = Cursor::new(data);
+        let trade_type = cursor.read_u8().ok()?;
+        let order_type = OrderType::from_i32(cursor.read_i16::<LittleEndian>().ok()? as i32)?;
+        let ticket = Ticket(cursor.read_i32::<LittleEndian>().ok()?);
+
+        let mut cursor = Cursor::new(&data[23..]);
+        let volume = cursor.read_i32::<LittleEndian>().ok()? as f64 / 100.0;
+        let price = cursor.read_f64::<LittleEndian>().ok()?;
+        let sl = cursor.read_f64::<LittleEndian>().ok()?;
+        let tp = cursor.read_f64::<LittleEndian>().ok()?;
+        let slippage = cursor.read_i32::<LittleEndian>().ok()?;
+
+        let mut cursor = Cursor::new(&data[87..]);
+        let expiration = cursor.read_i32::<LittleEndian>().ok()?;
+        let request_id = cursor.read_i32::<LittleEndian>().ok()?;
+
+        Some(Self {
+            trade_type,
+            order_type,
+            ticket,
+            symbol: Symbol::from_wire(&data[11..23]),
+            volume,
+            price,
+            sl,
+            tp,
+            slippage,
+            comment: text(55..87),
+            expiration,
+            request_id,
+        })
+    }
 }
 
+/// Command 3 中账户信息块的大小 (254字节，JS: q.Vp)
+pub const ACCOUNT_INFO_SIZE: usize = 254;
+
 /// 账户信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct AccountInfo {
     /// 账号
     pub login: i32,
@@ -424,10 +843,12 @@ pub struct AccountInfo {
     pub balance: f64,
     /// 净值
     pub equity: f64,
-    /// 已用保证金
-    pub margin: f64,
-    /// 可用保证金
-    pub free_margin: f64,
+    /// 已用保证金 (账户信息块中不包含，未知时为 None；按持仓估算见 `Mt4Client::used_margin`)
+    #[serde(default)]
+    pub margin: Option<f64>,
+    /// 可用保证金 (账户信息块中不包含，未知时为 None)
+    #[serde(default)]
+    pub free_margin: Option<f64>,
     /// 账户杠杆
     pub leverage: i32,
     /// 账户货币
@@ -436,128 +857,97 @@ pub struct AccountInfo {
     pub name: String,
     /// 服务器名称
     pub server: String,
-    /// 公司名称
+    /// 信用额度 (账户信息块中不包含，未知时为 None)
+    #[serde(default)]
+    pub credit: Option<f64>,
+    /// 公司名称 (来自 Token 响应)
     pub company: String,
 }
 
 impl AccountInfo {
     /// 从字节数据解析账户信息
     ///
-    /// 根据 MT4 Web Terminal JS 源码分析:
-    /// 数据包格式: [4字节记录数] + [账户数据...]
+    /// 账户信息块固定 254 字节 (JS: q.Vp=254)，之后才是品种和报价信息:
+    /// - 0:       1 byte    - flag
+    /// - 1-8:     8 bytes   - balance (f64)
+    /// - 9-16:    8 bytes   - equity (f64)
+    /// - 17-48:   32 bytes  - currency (UTF-16 LE, 16 chars)
+    /// - 49-52:   4 bytes   - leverage (i32)
+    /// - 53-56:   4 bytes   - login (i32，未经抓包确认)
+    /// - 57:      1 byte    - unknown
+    /// - 58-185:  128 bytes - server (UTF-16 LE, 64 chars)
+    /// - 186-189: 4 bytes   - unknown
+    /// - 190-253: 64 bytes  - name (UTF-8)
+    ///
+    /// currency / leverage / server 的偏移已由实际数据确认；login 的偏移只来自 JS 源码分析，
+    /// 客户端在未校准 login 偏移时 (`Mt4Client::calibrate_account_info`) 使用认证时的 login。
     ///
-    /// 账户数据结构 (从 offset 4 开始，即 base=4):
-    /// - base+0:      1 byte  - flag
-    /// - base+1:      8 bytes - balance (f64)
-    /// - base+9:      8 bytes - equity (f64)
-    /// - base+17:     32 bytes - currency (UTF-16 LE, 16 chars)
-    /// - base+49:     4 bytes - login (u32)
-    /// - base+53:     4 bytes - leverage (i32)
-    /// - base+57:     1 byte  - unknown
-    /// - base+58:     128 bytes - server (UTF-16 LE, 64 chars)
-    /// - base+186:    2 bytes - unknown
-    /// - base+188:    1 byte  - unknown
-    /// - base+189:    1 byte  - unknown
-    /// - base+190:    64 bytes - name (UTF-8)
+    /// 账户块中没有 margin / free_margin / credit / company 字段:
+    /// - margin / free_margin / credit 为 None，不以 0 代替
+    /// - company 来自 Token 响应，由客户端在收到 Command 3 时填入
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
-        if data.len() < 260 {
+        if data.len() < ACCOUNT_INFO_SIZE {
             return None;
         }
 
-        // 根据实际数据分析，消息头不是 4 字节
-        // 数据格式 (JS 中的偏移直接对应 msg_data):
-        // offset 0: flag (1 byte)
-        // offset 1-8: balance (f64) - 但实际数据可能不在这里
-        // offset 17: currency (UTF-16 LE) - "USD" 确认在这里
-        // offset 49: leverage = 500 确认在这里
-        // offset 57: unknown
-        // offset 58: server (UTF-16 LE) - "ICMarketsSC-Demo03" 确认在这里
-
         // flag at offset 0
         let _flag = data[0];
 
+        let balance = Self::read_f64(data, 1).unwrap_or(0.0);
+        let equity = Self::read_f64(data, 9).unwrap_or(0.0);
+
         // currency at offset 17 (32 bytes UTF-16 LE = 16 chars)
         let currency = Self::read_utf16_string(data, 17, 16).unwrap_or_default();
 
         // leverage at offset 49 (4 bytes i32)
         let leverage = i32::from_le_bytes([data[49], data[50], data[51], data[52]]);
 
+        // login at offset 53 (4 bytes i32)
+        let login = i32::from_le_bytes([data[53], data[54], data[55], data[56]]);
+
         // server at offset 58 (128 bytes UTF-16 LE = 64 chars)
         let server = Self::read_utf16_string(data, 58, 64).unwrap_or_default();
 
         // name at offset 190 (64 bytes UTF-8)
         let name = Self::read_ascii_string(data, 190, 64).unwrap_or_default();
 
-        // balance 和 equity 需要找到正确位置
-        // 根据 hex: 00 20 6e c3 40 00 00 00 在 offset 4-11
-        // 这可能是某种编码的数值，让我们尝试不同的解析方式
-
-        // 尝试从 offset 1 读取 balance (按 JS 代码)
-        let balance = Self::read_f64(data, 1).unwrap_or(0.0);
-        let equity = Self::read_f64(data, 9).unwrap_or(0.0);
-
-        // login 需要搜索
-        // MT4 账号通常是 7-8 位数字，范围 1,000,000 - 99,999,999
-        let login = Self::find_login_value(data).unwrap_or(0);
-
-        let margin = 0.0;
-        let free_margin = 0.0;
-        let company = String::new();
-
         Some(AccountInfo {
             login,
             balance,
             equity,
-            margin,
-            free_margin,
+            margin: None,
+            free_margin: None,
             leverage,
             currency,
             name,
             server,
-            company,
+            credit: None,
+            company: String::new(),
         })
     }
 
-    /// 在数据中搜索 MT4 账号值
-    /// MT4 账号通常是 7-8 位数字
-    fn find_login_value(data: &[u8]) -> Option<i32> {
-        // 首先检查可能的固定偏移位置
-        // 根据 JS 分析，login 可能在 offset 53 或其他位置
-        let possible_offsets = [53, 49, 254, 255, 256, 257];
-
-        for &offset in &possible_offsets {
-            if data.len() >= offset + 4 {
-                let val = i32::from_le_bytes([
-                    data[offset],
-                    data[offset + 1],
-                    data[offset + 2],
-                    data[offset + 3],
-                ]);
-                // 检查是否是有效的 MT4 账号 (7-8 位数字)
-                if val >= 1_000_000 && val <= 99_999_999 {
-                    return Some(val);
-                }
-            }
+    /// 按校准得到的字段偏移解析账户信息
+    ///
+    /// 布局中登记的字段从对应偏移读取，其余字段沿用 `from_bytes` 的默认解析
+    pub fn from_bytes_with_layout(data: &[u8], layout: &AccountLayout) -> Option<Self> {
+        let mut account = Self::from_bytes(data)?;
+        if let Some(offset) = layout.login {
+            let bytes = data.get(offset..offset.checked_add(4)?)?;
+            account.login = i32::from_le_bytes(bytes.try_into().ok()?);
         }
-
-        // 如果固定偏移没找到，扫描整个数据
-        for i in 0..data.len().saturating_sub(4) {
-            let val = i32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
-            // MT4 账号通常是 7-8 位数字
-            if val >= 1_000_000 && val <= 99_999_999 {
-                return Some(val);
-            }
+        if let Some(offset) = layout.balance {
+            account.balance = Self::read_f64(data, offset)?;
         }
-        None
+        if let Some(offset) = layout.equity {
+            account.equity = Self::read_f64(data, offset)?;
+        }
+        Some(account)
     }
 
     /// 读取 f64
     fn read_f64(data: &[u8], offset: usize) -> Option<f64> {
-        if data.len() < offset + 8 {
-            return None;
-        }
-        let mut cursor = Cursor::new(&data[offset..]);
-        cursor.read_f64::<LittleEndian>().ok()
+        let bytes = data.get(offset..offset.checked_add(8)?)?;
+        Some(f64::from_le_bytes(bytes.try_into().ok()?))
     }
 
     /// 读取 UTF-16 LE 字符串
@@ -603,8 +993,43 @@ impl AccountInfo {
     }
 }
 
+/// 交易服务器连接状态 (Command 15，需要 `experimental-status` 特性)
+///
+/// WebSocket 连接正常时，Web Terminal 服务器与经纪商交易服务器之间仍可能断开或进入维护，
+/// 此时交易请求会失败。数据布局 (按观察推断，未在所有经纪商上确认):
+///
+/// - 帧错误码非 0: 与交易服务器断开
+/// - 0-3: 状态 (i32): 0 已连接，1 已断开，2 维护中；其他值视为断开，原值保留在 `code` 中
+/// - 数据为空且错误码为 0: 已连接
+#[cfg(feature = "experimental-status")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConnectionStatus {
+    /// 是否已连接交易服务器
+    pub connected: bool,
+    /// 交易服务器是否在维护
+    pub maintenance: bool,
+    /// 状态原值
+    pub code: i32,
+    /// 帧错误码
+    pub error_code: u8,
+}
+
+#[cfg(feature = "experimental-status")]
+impl ConnectionStatus {
+    /// 解析 Command 15 数据
+    pub fn from_bytes(error_code: u8, data: &[u8]) -> Self {
+        let code = data.get(..4).map_or(0, |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+        Self { connected: error_code == 0 && code == 0, maintenance: code == 2, code, error_code }
+    }
+
+    /// 是否可以交易 (已连接且不在维护)
+    pub fn can_trade(&self) -> bool {
+        self.connected && !self.maintenance
+    }
+}
+
 /// 报价数据
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Quote {
     /// 品种
     pub symbol: String,
@@ -616,8 +1041,137 @@ pub struct Quote {
     pub time: i64,
 }
 
+impl Quote {
+    /// 从字节数据解析单条报价快照 (32字节)
+    ///
+    /// 数据布局 (按观察推断，未在所有经纪商上确认):
+    /// - 0-11:  symbol (12字节，'\0' 填充)
+    /// - 12-19: bid (f64)
+    /// - 20-27: ask (f64)
+    /// - 28-31: time (i32, 服务器时间，秒)
+    pub fn from_bytes(data: &[u8], offset: usize) -> Option<Self> {
+        if data.len().saturating_sub(offset) < QUOTE_SIZE {
+            return None;
+        }
+        let record = &data[offset..offset + QUOTE_SIZE];
+        let symbol = Symbol::from_wire(&record[..12]).to_string();
+        if symbol.is_empty() {
+            return None;
+        }
+        let mut cursor = Cursor::new(&record[12..]);
+        let bid = cursor.read_f64::<LittleEndian>().ok()?;
+        let ask = cursor.read_f64::<LittleEndian>().ok()?;
+        let time = cursor.read_i32::<LittleEndian>().ok()? as i64;
+        Some(Quote { symbol, bid, ask, time })
+    }
+
+    /// 解析 Command 8 响应中的所有报价 (32字节 × N，无头部，跳过空品种)
+    pub fn parse_all(data: &[u8]) -> Vec<Quote> {
+        (0..data.len() / QUOTE_SIZE)
+            .filter_map(|i| Self::from_bytes(data, i * QUOTE_SIZE))
+            .collect()
+    }
+}
+
+/// K线 (OHLCV)
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Candle {
+    /// 开盘时间 (服务器时间，Unix 时间戳，秒)
+    pub time: i64,
+    /// 开盘价
+    pub open: f64,
+    /// 最高价
+    pub high: f64,
+    /// 最低价
+    pub low: f64,
+    /// 收盘价
+    pub close: f64,
+    /// 成交量 (tick volume)
+    pub volume: f64,
+}
+
+impl Candle {
+    /// 从字节数据解析单根K线 (44字节)
+    ///
+    /// 采用 MT4 RateInfo 结构 (与 .hst 历史文件一致):
+    /// - 0-3:   time (i32, 秒)
+    /// - 4-11:  open (f64)
+    /// - 12-19: low (f64)
+    /// - 20-27: high (f64)
+    /// - 28-35: close (f64)
+    /// - 36-43: volume (f64)
+    pub fn from_bytes(data: &[u8], offset: usize) -> Option<Self> {
+        if data.len().saturating_sub(offset) < CANDLE_SIZE {
+            return None;
+        }
+        let mut cursor = Cursor::new(&data[offset..offset + CANDLE_SIZE]);
+        let time = cursor.read_i32::<LittleEndian>().ok()? as i64;
+        let open = cursor.read_f64::<LittleEndian>().ok()?;
+        let low = cursor.read_f64::<LittleEndian>().ok()?;
+        let high = cursor.read_f64::<LittleEndian>().ok()?;
+        let close = cursor.read_f64::<LittleEndian>().ok()?;
+        let volume = cursor.read_f64::<LittleEndian>().ok()?;
+        Some(Candle { time, open, high, low, close, volume })
+    }
+
+    /// 解析 Command 11 响应中的所有K线 (44字节 × N，无头部)
+    pub fn parse_all(data: &[u8]) -> Vec<Candle> {
+        (0..data.len() / CANDLE_SIZE)
+            .filter_map(|i| Self::from_bytes(data, i * CANDLE_SIZE))
+            .collect()
+    }
+
+    /// 序列化为字节数组 (44字节)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(CANDLE_SIZE);
+        buffer.write_i32::<LittleEndian>(self.time as i32).unwrap();
+        for value in [self.open, self.low, self.high, self.close, self.volume] {
+            buffer.write_f64::<LittleEndian>(value).unwrap();
+        }
+        buffer
+    }
+}
+
+/// K线历史请求 (Command 11)
+///
+/// 请求布局没有网页端脚本或抓包依据，需要开启 `experimental-chart` 特性
+#[cfg(feature = "experimental-chart")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartRequest {
+    /// 品种
+    pub symbol: String,
+    /// 周期
+    pub timeframe: Timeframe,
+    /// 开始时间 (服务器时间，秒)
+    pub from: i64,
+    /// 结束时间 (服务器时间，秒)
+    pub to: i64,
+}
+
+#[cfg(feature = "experimental-chart")]
+impl ChartRequest {
+    /// 序列化为字节数组 (24字节)
+    ///
+    /// - 0-11:  symbol (12 bytes ASCII)
+    /// - 12-15: period (i32, 分钟)
+    /// - 16-19: from (i32, 秒)
+    /// - 20-23: to (i32, 秒)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![0u8; CHART_REQUEST_SIZE];
+        let symbol_bytes = self.symbol.as_bytes();
+        let len = symbol_bytes.len().min(12);
+        buffer[..len].copy_from_slice(&symbol_bytes[..len]);
+
+        let mut cursor = Cursor::new(&mut buffer[12..]);
+        cursor.write_i32::<LittleEndian>(self.timeframe.minutes()).unwrap();
+        cursor.write_i32::<LittleEndian>(self.from as i32).unwrap();
+        cursor.write_i32::<LittleEndian>(self.to as i32).unwrap();
+        buffer
+    }
+}
+
 /// 交易响应 (Command 12)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TradeResponse {
     /// 请求ID
     pub request_id: i32,
@@ -681,6 +1235,23 @@ impl TradeResponse {
     }
 }
 
+/// 部分平仓结果
+#[derive(Debug, Clone)]
+pub struct PartialClose {
+    /// 原订单号
+    pub ticket: Ticket,
+    /// 已平仓手数
+    pub closed_volume: f64,
+    /// 剩余手数
+    pub remaining_volume: f64,
+    /// 剩余手数对应的新订单号 (未在等待时间内收到推送时为 None)
+    pub remaining_ticket: Option<Ticket>,
+    /// 剩余手数对应的新订单
+    pub remaining_order: Option<Order>,
+    /// 交易响应
+    pub response: TradeResponse,
+}
+
 /// 订单更新事件
 ///
 /// 数据包固定大小: 185 字节
@@ -723,7 +1294,7 @@ impl OrderUpdate {
     /// - offset: 从哪个位置开始解析
     pub fn from_bytes(data: &[u8], offset: usize) -> Option<Self> {
         // 确保有足够的数据（185 字节）
-        if offset + 185 > data.len() {
+        if data.len().saturating_sub(offset) < 185 {
             return None;
         }
 
@@ -776,6 +1347,18 @@ impl OrderUpdate {
         self.notify_type == 1
     }
 
+    /// 该通知对应的服务器时间 (经纪商时间，秒)
+    ///
+    /// 新订单取 open_time，平仓取 close_time；订单修改等通知不携带可靠时间
+    pub fn server_time(&self) -> Option<i64> {
+        let time = match self.notify_type {
+            0 => self.order.open_time,
+            1 => self.order.close_time,
+            _ => 0,
+        };
+        (time > 0).then_some(time)
+    }
+
     /// 是否为 Close By 操作 (对冲平仓)
     ///
     /// 注意：由于采用 JS 的简单分割方式，Close By 操作会被解析为两个独立的 OrderUpdate
@@ -794,3 +1377,264 @@ impl OrderUpdate {
         self.order.close_price
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// 写入 UTF-16 LE 字符串
+    fn put_utf16(data: &mut [u8], offset: usize, text: &str) {
+        for (i, c) in text.encode_utf16().enumerate() {
+            data[offset + i * 2..offset + i * 2 + 2].copy_from_slice(&c.to_le_bytes());
+        }
+    }
+
+    /// 按文档布局构造的账户信息块 (合成数据，只能验证解析与文档布局一致，不能证明布局本身正确)
+    fn account_fixture() -> Vec<u8> {
+        let mut data = vec![0u8; ACCOUNT_INFO_SIZE + 28];
+        data[0] = 1;
+        data[1..9].copy_from_slice(&10_234.56f64.to_le_bytes());
+        data[9..17].copy_from_slice(&10_198.12f64.to_le_bytes());
+        put_utf16(&mut data, 17, "USD");
+        data[49..53].copy_from_slice(&500i32.to_le_bytes());
+        data[53..57].copy_from_slice(&31313724i32.to_le_bytes());
+        put_utf16(&mut data, 58, "ICMarketsSC-Demo03");
+        data[190..200].copy_from_slice(b"John Smith");
+        // 紧随其后的品种信息不应影响账户字段
+        data[ACCOUNT_INFO_SIZE..ACCOUNT_INFO_SIZE + 6].copy_from_slice(b"EURUSD");
+        data
+    }
+
+    #[test]
+    fn test_account_info_fixture() {
+        let account = AccountInfo::from_bytes(&account_fixture()).unwrap();
+        assert_eq!(account.login, 31313724);
+        assert_eq!(account.balance, 10_234.56);
+        assert_eq!(account.equity, 10_198.12);
+        // 账户块中没有的字段不以 0 代替
+        assert_eq!((account.margin, account.free_margin, account.credit), (None, None, None));
+        assert_eq!(account.leverage, 500);
+        assert_eq!(account.currency, "USD");
+        assert_eq!(account.server, "ICMarketsSC-Demo03");
+        assert_eq!(account.name, "John Smith");
+        assert!(account.company.is_empty());
+    }
+
+    #[test]
+    fn test_account_info_minimal_block() {
+        // 只有 254 字节账户信息块，不带品种/报价信息
+        let mut data = vec![0u8; ACCOUNT_INFO_SIZE];
+        put_utf16(&mut data, 17, "USD");
+        data[49..53].copy_from_slice(&500i32.to_le_bytes());
+        put_utf16(&mut data, 58, "ICMarketsSC-Demo03");
+        data[190..194].copy_from_slice(b"Demo");
+
+        let account = AccountInfo::from_bytes(&data).expect("254-byte block should parse");
+        assert_eq!(account.currency, "USD");
+        assert_eq!(account.leverage, 500);
+        assert_eq!(account.server, "ICMarketsSC-Demo03");
+        assert_eq!(account.name, "Demo");
+
+        assert!(AccountInfo::from_bytes(&data[..ACCOUNT_INFO_SIZE - 1]).is_none());
+    }
+
+    #[test]
+    fn test_candle_round_trip() {
+        let candle = Candle { time: 1_700_000_040, open: 1.1, high: 1.3, low: 1.0, close: 1.2, volume: 42.0 };
+        let mut data = candle.to_bytes();
+        data.extend(Candle { time: 1_700_000_100, ..candle }.to_bytes());
+        data.push(0); // 不足一根的尾部数据被忽略
+
+        let candles = Candle::parse_all(&data);
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0], candle);
+        // RateInfo 顺序为 open, low, high, close
+        assert_eq!(f64::from_le_bytes(data[12..20].try_into().unwrap()), 1.0);
+    }
+
+    #[cfg(feature = "experimental-chart")]
+    #[test]
+    fn test_chart_request_bytes() {
+        let request = ChartRequest { symbol: "EURUSD".to_string(), timeframe: Timeframe::H1, from: 100, to: 200 };
+        let bytes = request.to_bytes();
+        assert_eq!(bytes.len(), CHART_REQUEST_SIZE);
+        assert_eq!(&bytes[..6], b"EURUSD");
+        assert_eq!(i32::from_le_bytes(bytes[12..16].try_into().unwrap()), 60);
+    }
+
+    #[test]
+    fn test_symbol_validation() {
+        assert_eq!(Symbol::new("EURUSD.m").unwrap(), "EURUSD.m");
+        assert!(Symbol::new("").is_err());
+        assert!(Symbol::new("ABCDEFGHIJKLM").is_err());
+        assert!(Symbol::new("EUR USD").is_err());
+
+        // 协议字段以 \0 填充
+        let symbol = Symbol::from_wire(b"XAUUSD\0\0\0\0\0\0");
+        assert_eq!(symbol.as_str(), "XAUUSD");
+        assert_eq!(serde_json::to_string(&symbol).unwrap(), "\"XAUUSD\"");
+        assert!(serde_json::from_str::<Symbol>("\"TOO_LONG_SYMBOL\"").is_err());
+        assert_eq!(Ticket(42).to_string(), "42");
+    }
+
+    #[test]
+    fn test_normalize_volume() {
+        let spec = SymbolInfo { lot_min: 0.1, lot_max: 50.0, lot_step: 0.1, ..SymbolInfo::new("XAUUSD", 2) };
+        assert_eq!(spec.normalize_volume(0.26), 0.3);
+        assert_eq!(spec.normalize_volume(0.04), 0.1);
+        assert_eq!(spec.normalize_volume(0.7000001), 0.7);
+        assert_eq!(spec.normalize_volume(75.0), 50.0);
+
+        // 取整后的手数按 *100 编码时不会被截断
+        let request = TradeRequest::buy(&Symbol::new("EURUSD").unwrap(), 0.29, 0.0, 0.0);
+        assert_eq!(i32::from_le_bytes(request.to_bytes()[23..27].try_into().unwrap()), 29);
+    }
+
+    #[cfg(feature = "experimental-status")]
+    #[test]
+    fn test_connection_status() {
+        assert!(ConnectionStatus::from_bytes(0, &[]).can_trade());
+        assert!(ConnectionStatus::from_bytes(0, &0i32.to_le_bytes()).can_trade());
+        let down = ConnectionStatus::from_bytes(0, &1i32.to_le_bytes());
+        assert!(!down.connected && !down.maintenance);
+        let maintenance = ConnectionStatus::from_bytes(0, &2i32.to_le_bytes());
+        assert!(maintenance.maintenance && !maintenance.can_trade());
+        assert!(!ConnectionStatus::from_bytes(3, &[]).connected);
+        assert_eq!(ConnectionStatus::from_bytes(0, &7i32.to_le_bytes()).code, 7);
+    }
+
+    #[test]
+    fn test_parse_history_orders() {
+        let record = |ticket: i32, close_time: i32, close_price: f64| {
+            let mut data = vec![0u8; 161];
+            data[0..4].copy_from_slice(&ticket.to_le_bytes());
+            data[4..10].copy_from_slice(b"EURUSD");
+            data[60..64].copy_from_slice(&close_time.to_le_bytes());
+            data[93..101].copy_from_slice(&close_price.to_le_bytes());
+            data
+        };
+        let mut data = [record(1, 1_704_153_600, 1.10521), record(2, 1_704_157_200, 1.10498)].concat();
+        data.extend_from_slice(&[0u8; 40]);
+
+        // 末尾不足一条记录的部分忽略
+        let orders = Order::parse_list(&data);
+        assert_eq!(orders.len(), 2);
+        assert_eq!((orders[0].ticket.get(), orders[0].close_time, orders[0].close_price), (1, 1_704_153_600, 1.10521));
+        assert_eq!((orders[1].ticket.get(), orders[1].close_time, orders[1].close_price), (2, 1_704_157_200, 1.10498));
+        assert!(Order::parse_list(&data[..160]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_quotes() {
+        let record = |symbol: &str, bid: f64, ask: f64, time: i32| {
+            let mut data = vec![0u8; QUOTE_SIZE];
+            data[..symbol.len()].copy_from_slice(symbol.as_bytes());
+            data[12..20].copy_from_slice(&bid.to_le_bytes());
+            data[20..28].copy_from_slice(&ask.to_le_bytes());
+            data[28..32].copy_from_slice(&time.to_le_bytes());
+            data
+        };
+        let data = [record("EURUSD", 1.0852, 1.0853, 1_704_153_600), record("", 0.0, 0.0, 0), record("XAUUSD", 2050.1, 2050.4, 1_704_153_601)]
+            .concat();
+        let quotes = Quote::parse_all(&data);
+        assert_eq!(quotes.len(), 2);
+        assert_eq!(quotes[0], Quote { symbol: "EURUSD".to_string(), bid: 1.0852, ask: 1.0853, time: 1_704_153_600 });
+        assert_eq!((quotes[1].symbol.as_str(), quotes[1].ask), ("XAUUSD", 2050.4));
+        assert!(Quote::parse_all(&data[..31]).is_empty());
+    }
+
+    /// 逐帧比较 `fixtures/<dir>` 中样本的解析结果与 .expected.json，返回比较的帧数
+    fn check_fixtures(dir: &str) -> usize {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(dir);
+        let mut checked = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let frames = crate::session::read_session(&path).unwrap();
+            let expected: Vec<serde_json::Value> =
+                serde_json::from_str(&std::fs::read_to_string(path.with_extension("expected.json")).unwrap()).unwrap();
+            assert_eq!(frames.len(), expected.len(), "{}", path.display());
+
+            for (frame, expected) in frames.iter().zip(expected) {
+                let parsed = match frame.command {
+                    3 => serde_json::to_value(AccountInfo::from_bytes(&frame.data)),
+                    4 => serde_json::to_value(
+                        (0..frame.data.len() / 161).filter_map(|i| Order::from_bytes(&frame.data, i * 161)).collect::<Vec<_>>(),
+                    ),
+                    10 => serde_json::to_value(
+                        OrderUpdate::parse_all(&frame.data)
+                            .iter()
+                            .map(|u| serde_json::json!({"notify_id": u.notify_id, "notify_type": u.notify_type, "order": u.order}))
+                            .collect::<Vec<_>>(),
+                    ),
+                    12 => serde_json::to_value(TradeResponse::from_bytes(&frame.data)),
+                    command => panic!("{}: 未支持的命令 {}", path.display(), command),
+                };
+                assert_eq!(parsed.unwrap(), expected, "{} @ {}ms", path.display(), frame.elapsed_ms);
+                checked += 1;
+            }
+        }
+        checked
+    }
+
+    /// 合成样本 (fixtures/synthetic/): 按当前布局生成，只用于发现解析器的意外改动
+    #[test]
+    fn test_synthetic_fixtures() {
+        let checked = check_fixtures("synthetic");
+        assert!(checked >= 7, "fixtures/synthetic/ 中只有 {} 帧", checked);
+    }
+
+    /// 真实抓包 (fixtures/captures/): 期望值来自 MT4 终端显示
+    #[test]
+    fn test_captured_fixtures() {
+        check_fixtures("captures");
+    }
+
+    proptest! {
+        /// 网络数据可能被篡改或截断: 任意输入和偏移都不会 panic
+        #[test]
+        fn prop_parsers_never_panic(
+            data in proptest::collection::vec(any::<u8>(), 0..1024),
+            offset in any::<usize>(),
+        ) {
+            let near = offset % (data.len() + 1);
+            for offset in [offset, near, usize::MAX] {
+                let _ = Order::from_bytes(&data, offset);
+                let _ = OrderUpdate::from_bytes(&data, offset);
+                let _ = Candle::from_bytes(&data, offset);
+                let layout = AccountLayout { login: Some(offset), balance: Some(offset), equity: Some(near) };
+                let _ = AccountInfo::from_bytes_with_layout(&data, &layout);
+            }
+            let _ = AccountInfo::from_bytes(&data);
+            let _ = TradeRequest::from_bytes(&data);
+            let _ = Candle::parse_all(&data);
+
+            prop_assert_eq!(OrderUpdate::parse_all(&data).len(), data.len() / 185);
+            if let Some(response) = TradeResponse::from_bytes(&data) {
+                prop_assert_eq!(response.orders.len(), data.len().saturating_sub(24) / 161);
+            }
+        }
+
+        /// 解析结果只取决于记录本身的字节，不读取记录之后的数据
+        #[test]
+        fn prop_parsers_stay_in_bounds(
+            record in proptest::collection::vec(any::<u8>(), 185..=185),
+            trailing in proptest::collection::vec(any::<u8>(), 0..200),
+        ) {
+            let json = |order: Option<Order>| serde_json::to_value(order).unwrap();
+            let mut extended = record.clone();
+            extended.extend_from_slice(&trailing);
+
+            prop_assert_eq!(json(Order::from_bytes(&record[..161], 0)), json(Order::from_bytes(&extended, 0)));
+            prop_assert!(Order::from_bytes(&record[..160], 0).is_none());
+            prop_assert_eq!(
+                json(OrderUpdate::from_bytes(&record, 0).map(|u| u.order)),
+                json(OrderUpdate::from_bytes(&extended, 0).map(|u| u.order))
+            );
+            prop_assert!(OrderUpdate::from_bytes(&record[..184], 0).is_none());
+        }
+    }
+}
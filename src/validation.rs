@@ -0,0 +1,292 @@
+//! 交易请求的本地校验
+//!
+//! 服务器对手数不符合规格、止损方向错误、挂单缺少价格等问题一律返回错误码 3 (Invalid parameters)，
+//! 无法看出具体原因。客户端在序列化 `TradeRequest` 之前按品种规格逐项检查，
+//! 不通过时返回带有具体原因的 `Mt4Error::InvalidParams`，请求不会发送。
+//!
+//! 持仓 (市价单) 的修改请求中 `price` 为开仓价，止损/止盈的方向应相对当前平仓价 (买单 bid，卖单 ask) 检查，
+//! 由 `validate_position_stops` 按最新报价单独校验；未设置品种规格时用 `validate_trade_request_without_spec`，
+//! 不检查手数规格和最小止损距离。
+//!
+//! 校验默认开启，可以通过 `Mt4ClientBuilder::disable_trade_validation()` 关闭:
+//!
+//! ```
+//! use mt4_client::{validate_trade_request, Symbol, SymbolInfo, TradeRequest};
+//!
+//! let spec = SymbolInfo::new("EURUSD", 5);
+//! let symbol = Symbol::new("EURUSD").unwrap();
+//! // 买单止损高于止盈
+//! let request = TradeRequest::buy(&symbol, 0.1, 1.0900, 1.0800);
+//! assert!(validate_trade_request(&request, &spec, 0).is_err());
+//! ```
+
+use crate::error::{Mt4Error, Result};
+use crate::protocol::{OrderType, TradeType};
+use crate::types::{SymbolInfo, TradeRequest};
+
+/// 注释的最大长度 (字节，协议字段 32 字节，最后一字节为结束符)
+pub const COMMENT_MAX_LEN: usize = 31;
+
+/// 挂单过期时间距当前服务器时间的最小间隔 (秒)，MT4 服务器通常拒绝 10 分钟内过期的挂单
+pub const MIN_EXPIRATION_SECS: i64 = 10 * 60;
+
+/// 校验交易请求
+///
+/// `stops_level` 为止损/止盈与价格的最小距离 (点，0 表示不限制)，仅对带价格的请求 (挂单、挂单修改) 检查
+pub fn validate_trade_request(request: &TradeRequest, spec: &SymbolInfo, stops_level: i32) -> Result<()> {
+    validate(request, Some(spec), stops_level)
+}
+
+/// 未设置品种规格时校验交易请求: 手数只检查是否为正数，不检查手数规格和最小止损距离
+pub fn validate_trade_request_without_spec(request: &TradeRequest) -> Result<()> {
+    validate(request, None, 0)
+}
+
+/// 校验持仓修改请求的止损/止盈: 须位于当前平仓价 (买单 `bid`，卖单 `ask`) 的正确一侧，
+/// 设置了品种规格时同时检查最小止损距离 `stops_level` (点)
+pub fn validate_position_stops(
+    request: &TradeRequest,
+    spec: Option<&SymbolInfo>,
+    stops_level: i32,
+    bid: f64,
+    ask: f64,
+) -> Result<()> {
+    let price = if request.order_type.is_buy() { bid } else { ask };
+    validate_stops_against(request, price, spec, stops_level)
+}
+
+/// 是否为持仓 (市价单) 的修改请求
+pub(crate) fn is_position_modify(request: &TradeRequest) -> bool {
+    request.trade_type == TradeType::Modify as u8 && matches!(request.order_type, OrderType::Buy | OrderType::Sell)
+}
+
+fn validate(request: &TradeRequest, spec: Option<&SymbolInfo>, stops_level: i32) -> Result<()> {
+    let trade_type = request.trade_type;
+    let is = |t: TradeType| trade_type == t as u8;
+
+    if is(TradeType::Quote) {
+        return Ok(());
+    }
+
+    let opens = is(TradeType::Instant) || is(TradeType::Request) || is(TradeType::Market) || is(TradeType::Pending);
+    let closes = is(TradeType::CloseInstant) || is(TradeType::CloseRequest) || is(TradeType::CloseMarket);
+
+    if (closes || is(TradeType::Modify) || is(TradeType::Delete)) && !request.ticket.is_set() {
+        return Err(Mt4Error::InvalidParams(format!("请求类型 {} 缺少订单号", trade_type)));
+    }
+
+    if opens || closes {
+        validate_volume(request.volume, spec)?;
+    }
+
+    if request.comment.len() > COMMENT_MAX_LEN {
+        return Err(Mt4Error::InvalidParams(format!(
+            "注释 \"{}\" 为 {} 字节，超过 {} 字节",
+            request.comment,
+            request.comment.len(),
+            COMMENT_MAX_LEN
+        )));
+    }
+
+    if is(TradeType::Pending) && !(request.price.is_finite() && request.price > 0.0) {
+        return Err(Mt4Error::InvalidParams(format!(
+            "{} 挂单缺少价格: {}",
+            request.order_type.name(),
+            request.price
+        )));
+    }
+
+    if opens || is(TradeType::Modify) {
+        validate_stops(request, spec, stops_level)?;
+    }
+
+    if request.expiration != 0 && !(is(TradeType::Pending) || is(TradeType::Modify)) {
+        return Err(Mt4Error::InvalidParams(format!(
+            "只有挂单可以设置过期时间 (请求类型 {})",
+            trade_type
+        )));
+    }
+
+    Ok(())
+}
+
+/// 校验挂单过期时间: 须晚于当前服务器时间 `server_now` 至少 `MIN_EXPIRATION_SECS`
+pub fn validate_expiration(request: &TradeRequest, server_now: i64) -> Result<()> {
+    if request.expiration == 0 {
+        return Ok(());
+    }
+    let earliest = server_now + MIN_EXPIRATION_SECS;
+    if (request.expiration as i64) < earliest {
+        return Err(Mt4Error::InvalidParams(format!(
+            "过期时间 {} 早于服务器允许的最早时间 {} (当前服务器时间 {} + {} 秒)",
+            request.expiration, earliest, server_now, MIN_EXPIRATION_SECS
+        )));
+    }
+    Ok(())
+}
+
+/// 手数须为正数；设置了品种规格时还须在最小/最大手数之间且为步长的整数倍
+fn validate_volume(volume: f64, spec: Option<&SymbolInfo>) -> Result<()> {
+    if !volume.is_finite() || volume <= 0.0 {
+        return Err(Mt4Error::InvalidParams(format!("无效的手数: {}", volume)));
+    }
+    let Some(spec) = spec else { return Ok(()) };
+    if volume < spec.lot_min - 1e-9 {
+        return Err(Mt4Error::InvalidParams(format!(
+            "手数 {} 低于 {} 的最小手数 {}",
+            volume, spec.symbol, spec.lot_min
+        )));
+    }
+    if volume > spec.lot_max + 1e-9 {
+        return Err(Mt4Error::InvalidParams(format!(
+            "手数 {} 超过 {} 的最大手数 {}",
+            volume, spec.symbol, spec.lot_max
+        )));
+    }
+    if !spec.is_volume_on_step(volume) {
+        return Err(Mt4Error::InvalidParams(format!(
+            "手数 {} 不是 {} 的手数步长 {} 的整数倍",
+            volume, spec.symbol, spec.lot_step
+        )));
+    }
+    Ok(())
+}
+
+/// 止损/止盈须位于价格的正确一侧；没有价格 (市价单) 或为持仓修改 (价格为开仓价) 时只检查两者的相对位置
+fn validate_stops(request: &TradeRequest, spec: Option<&SymbolInfo>, stops_level: i32) -> Result<()> {
+    if request.price > 0.0 && !is_position_modify(request) {
+        return validate_stops_against(request, request.price, spec, stops_level);
+    }
+    let (sl, tp) = validate_stop_values(request)?;
+    let buy = request.order_type.is_buy();
+    if sl > 0.0 && tp > 0.0 && (if buy { sl >= tp } else { sl <= tp }) {
+        return Err(Mt4Error::InvalidParams(format!(
+            "{} 的止损 {} 应{}于止盈 {}",
+            request.order_type.name(),
+            sl,
+            if buy { "低" } else { "高" },
+            tp
+        )));
+    }
+    Ok(())
+}
+
+/// 止损/止盈须为有限的非负数
+fn validate_stop_values(request: &TradeRequest) -> Result<(f64, f64)> {
+    let (sl, tp) = (request.sl, request.tp);
+    for (name, value) in [("止损", sl), ("止盈", tp)] {
+        if !value.is_finite() || value < 0.0 {
+            return Err(Mt4Error::InvalidParams(format!("无效的{}: {}", name, value)));
+        }
+    }
+    Ok((sl, tp))
+}
+
+/// 止损/止盈须位于 `price` 的正确一侧，设置了品种规格时检查最小止损距离
+fn validate_stops_against(request: &TradeRequest, price: f64, spec: Option<&SymbolInfo>, stops_level: i32) -> Result<()> {
+    let (sl, tp) = validate_stop_values(request)?;
+    let buy = request.order_type.is_buy();
+    let side = request.order_type.name();
+    // 买单止损在价格下方、止盈在上方，卖单相反
+    if sl > 0.0 && (if buy { sl >= price } else { sl <= price }) {
+        return Err(Mt4Error::InvalidParams(format!(
+            "{} 的止损 {} 应在价格 {} {}方",
+            side,
+            sl,
+            price,
+            if buy { "下" } else { "上" }
+        )));
+    }
+    if tp > 0.0 && (if buy { tp <= price } else { tp >= price }) {
+        return Err(Mt4Error::InvalidParams(format!(
+            "{} 的止盈 {} 应在价格 {} {}方",
+            side,
+            tp,
+            price,
+            if buy { "上" } else { "下" }
+        )));
+    }
+    if let Some(spec) = spec.filter(|_| stops_level > 0) {
+        let min_distance = stops_level as f64 * 10f64.powi(-spec.digits);
+        for (name, value) in [("止损", sl), ("止盈", tp)] {
+            if value > 0.0 && (value - price).abs() < min_distance - 1e-9 {
+                return Err(Mt4Error::InvalidParams(format!(
+                    "{} {} 距价格 {} 不足最小止损距离 {} 点",
+                    name, value, price, stops_level
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Symbol, Ticket, TimeInForce};
+
+    #[test]
+    fn test_validate_trade_request() {
+        let spec = SymbolInfo { lot_max: 50.0, ..SymbolInfo::new("EURUSD", 5) };
+        let symbol = Symbol::new("EURUSD").unwrap();
+        let reason = |request: &TradeRequest, stops_level| match validate_trade_request(request, &spec, stops_level) {
+            Err(Mt4Error::InvalidParams(reason)) => reason,
+            other => panic!("unexpected {:?}", other),
+        };
+
+        assert!(validate_trade_request(&TradeRequest::buy(&symbol, 0.1, 1.07, 1.09), &spec, 0).is_ok());
+        assert!(reason(&TradeRequest::buy(&symbol, 0.015, 0.0, 0.0), 0).contains("步长"));
+        assert!(reason(&TradeRequest::sell(&symbol, 60.0, 0.0, 0.0), 0).contains("最大手数"));
+        assert!(reason(&TradeRequest::sell(&symbol, 0.1, 1.07, 1.09), 0).contains("止损"));
+
+        // 挂单: 价格必填，止损止盈相对价格检查
+        assert!(reason(&TradeRequest::buy_limit(&symbol, 0.1, 0.0, 0.0, 0.0), 0).contains("缺少价格"));
+        let pending = TradeRequest::sell_limit(&symbol, 0.1, 1.0900, 1.0905, 1.0800);
+        assert!(validate_trade_request(&pending, &spec, 0).is_ok());
+        assert!(reason(&pending, 100).contains("最小止损距离"));
+        let modify = TradeRequest::modify(Ticket(7), &symbol, OrderType::BuyStop, 1.0900, 1.0950, 0.0);
+        assert!(reason(&modify, 0).contains("下方"));
+
+        let mut request = TradeRequest::buy(&symbol, 0.1, 0.0, 0.0);
+        request.comment = "x".repeat(COMMENT_MAX_LEN + 1);
+        assert!(reason(&request, 0).contains("注释"));
+        assert!(reason(&TradeRequest::close(Ticket(0), &symbol, 0.1), 0).contains("订单号"));
+
+        // 过期时间只用于挂单，且须晚于服务器时间 10 分钟
+        let mut request = TradeRequest::buy(&symbol, 0.1, 0.0, 0.0);
+        request.expiration = 1_700_003_600;
+        assert!(reason(&request, 0).contains("过期时间"));
+        let pending = TradeRequest::buy_limit(&symbol, 0.1, 1.07, 0.0, 0.0).with_time_in_force(TimeInForce::GoodTillDate(1_700_000_300), 0);
+        assert!(validate_trade_request(&pending, &spec, 0).is_ok());
+        assert!(validate_expiration(&pending, 1_700_000_000).is_err());
+        assert!(validate_expiration(&pending, 1_699_999_000).is_ok());
+    }
+
+    #[test]
+    fn test_position_modify_stops() {
+        let spec = SymbolInfo::new("EURUSD", 5);
+        let symbol = Symbol::new("EURUSD").unwrap();
+
+        // 买单开仓价 1.0800，止损移到开仓价上方: 不与开仓价比较，按当前 bid 检查
+        let buy = TradeRequest::modify(Ticket(7), &symbol, OrderType::Buy, 1.0800, 1.0850, 0.0);
+        assert!(validate_trade_request(&buy, &spec, 0).is_ok());
+        assert!(validate_position_stops(&buy, Some(&spec), 0, 1.0870, 1.0872).is_ok());
+        assert!(validate_position_stops(&buy, Some(&spec), 0, 1.0840, 1.0842).is_err());
+        // 最小止损距离只在已知品种规格时检查
+        assert!(validate_position_stops(&buy, Some(&spec), 300, 1.0870, 1.0872).is_err());
+        assert!(validate_position_stops(&buy, None, 300, 1.0870, 1.0872).is_ok());
+
+        // 卖单与之对称，按 ask 检查
+        let sell = TradeRequest::modify(Ticket(8), &symbol, OrderType::Sell, 1.0900, 1.0850, 0.0);
+        assert!(validate_trade_request(&sell, &spec, 0).is_ok());
+        assert!(validate_position_stops(&sell, Some(&spec), 0, 1.0828, 1.0830).is_ok());
+        assert!(validate_position_stops(&sell, Some(&spec), 0, 1.0858, 1.0860).is_err());
+        // 止损止盈的相对位置仍然检查
+        let crossed = TradeRequest::modify(Ticket(8), &symbol, OrderType::Sell, 1.0900, 1.0850, 1.0860);
+        assert!(validate_trade_request(&crossed, &spec, 0).is_err());
+
+        // 未设置品种规格时不检查手数规格
+        assert!(validate_trade_request_without_spec(&TradeRequest::buy(&symbol, 0.015, 0.0, 0.0)).is_ok());
+        assert!(validate_trade_request_without_spec(&TradeRequest::buy(&symbol, 0.0, 0.0, 0.0)).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! 浏览器传输 (wasm32 目标，需要开启 `wasm` feature)
+//!
+//! `Mt4Client` 依赖 tokio 的网络和任务调度，在浏览器中不可用。`WasmSession` 通过浏览器的
+//! WebSocket 直接连接 Web Terminal 的信号服务器，与原生客户端共用 `packet` 编解码和 `types` 中的解析器:
+//!
+//! ```no_run
+//! use mt4_client::wasm::WasmSession;
+//! use mt4_client::Mt4Api;
+//!
+//! # async fn example(login: &str, server: &str, password: &str) -> mt4_client::Result<()> {
+//! // Token 接口通常不允许跨域请求，由页面所在站点转发到 https://metatraderweb.app
+//! let token = Mt4Api::with_base_url("/mt4").get_token(login, server, 4).await?;
+//! let mut session = WasmSession::connect(&token, password).await?;
+//! while let Some(frame) = session.next_frame().await {
+//!     if let Some(account) = frame.account_info() {
+//!         web_sys::console::log_1(&format!("equity {:.2}", account.equity).into());
+//!     }
+//!     for update in frame.order_updates() {
+//!         web_sys::console::log_1(&format!("#{} {}", update.order.ticket, update.order.symbol).into());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! - 认证流程与原生客户端相同: 发送 token (Command 0)，收到确认后发送密码 (Command 1)；
+//!   收到账户信息 (Command 3) 后自动请求当前持仓 (Command 4)
+//! - 浏览器中没有 tokio 计时器，连接和认证不设超时，需要时由调用方配合页面的计时器取消
+//! - `throttle`、`clock` 等模块使用 `std::time::Instant`，在 wasm32-unknown-unknown 中调用会 panic
+//! - 编译: `cargo build --target wasm32-unknown-unknown --no-default-features --features wasm`
+
+use crate::api::TokenResponse;
+use crate::crypto::Mt4Crypto;
+use crate::error::{Mt4Error, Result};
+use crate::funding::{split_order_updates, BalanceOperation};
+use crate::packet::{build_packet, decode_packet, encode_password, encode_token};
+use crate::protocol::Command;
+use crate::types::{AccountInfo, OrderUpdate, TradeRequest, TradeResponse};
+use bytes::Bytes;
+use js_sys::{ArrayBuffer, Uint8Array};
+use secrecy::{ExposeSecret, SecretString};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use tokio::sync::mpsc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{BinaryType, CloseEvent, Event, MessageEvent, WebSocket};
+
+/// 服务器发来的一帧
+#[derive(Debug, Clone)]
+pub struct WasmFrame {
+    /// 命令
+    pub command: u16,
+    /// 错误码
+    pub error_code: u8,
+    /// 帧数据
+    pub data: Bytes,
+}
+
+impl WasmFrame {
+    /// 账户信息 (Command 3)
+    pub fn account_info(&self) -> Option<AccountInfo> {
+        (self.command == Command::AccountInfo as u16)
+            .then(|| AccountInfo::from_bytes(&self.data))
+            .flatten()
+    }
+
+    /// 订单更新 (Command 10，不含余额/信用操作)，其他命令返回空列表
+    pub fn order_updates(&self) -> Vec<OrderUpdate> {
+        if self.command == Command::OrderUpdate as u16 {
+            split_order_updates(&self.data).0
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// 余额/信用操作 (Command 10 中 cmd 为 6 / 7 的记录)，其他命令返回空列表
+    pub fn balance_operations(&self) -> Vec<BalanceOperation> {
+        if self.command == Command::OrderUpdate as u16 {
+            split_order_updates(&self.data).1
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// 交易响应 (Command 12)
+    pub fn trade_response(&self) -> Option<TradeResponse> {
+        (self.command == Command::TradeRequest as u16)
+            .then(|| TradeResponse::from_bytes(&self.data))
+            .flatten()
+    }
+}
+
+/// 浏览器回调发给会话的消息
+enum Incoming {
+    Opened,
+    Frame(WasmFrame),
+    Closed(String),
+}
+
+/// 回调共享的连接状态
+struct Shared {
+    socket: WebSocket,
+    crypto: Mt4Crypto,
+    /// 收到 token 确认后发送，发送后清空
+    password: RefCell<Option<SecretString>>,
+    /// 下一个数据包 ID
+    next_packet_id: Cell<u16>,
+}
+
+impl Shared {
+    fn send(&self, command: u16, data: &[u8], use_auth_key: bool) -> Result<()> {
+        let packet_id = self.next_packet_id.get();
+        self.next_packet_id.set(packet_id.wrapping_add(1));
+        let packet = build_packet(packet_id, command, data, &self.crypto, use_auth_key)?;
+        self.socket
+            .send_with_u8_array(&packet)
+            .map_err(|e| js_error("WebSocket 发送失败", e))
+    }
+
+    fn on_message(&self, event: MessageEvent) -> Option<WasmFrame> {
+        let buffer = event.data().dyn_into::<ArrayBuffer>().ok()?;
+        let packet = Uint8Array::new(&buffer).to_vec();
+        let (command, error_code, data) = match decode_packet(&packet, &self.crypto) {
+            Ok(frame) => frame?,
+            Err(e) => {
+                tracing::error!("Decrypt error: {}", e);
+                return None;
+            }
+        };
+
+        let reply = if command == Command::AuthToken as u16 {
+            // Token 确认，发送密码
+            self.password.borrow_mut().take().map(|password| (Command::AuthPassword, encode_password(password.expose_secret())))
+        } else if command == Command::AccountInfo as u16 {
+            // 与原生客户端相同，收到账户信息后请求当前持仓
+            Some((Command::CurrentPositions, Vec::new()))
+        } else {
+            None
+        };
+        if let Some((reply, data)) = reply {
+            if let Err(e) = self.send(reply as u16, &data, false) {
+                tracing::error!("Failed to send command {}: {}", reply as u16, e);
+            }
+        }
+
+        Some(WasmFrame { command, error_code, data })
+    }
+}
+
+/// WebSocket 回调 (会话存在期间保持注册)
+struct Handlers {
+    _on_open: Closure<dyn FnMut()>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+    _on_error: Closure<dyn FnMut(Event)>,
+}
+
+/// 浏览器中的 Web Terminal 会话
+pub struct WasmSession {
+    shared: Rc<Shared>,
+    rx: mpsc::UnboundedReceiver<Incoming>,
+    /// 认证期间收到的其他帧
+    backlog: VecDeque<WasmFrame>,
+    closed: bool,
+    _handlers: Handlers,
+}
+
+impl WasmSession {
+    /// 连接信号服务器并完成认证
+    ///
+    /// `token` 为 `Mt4Api::get_token` 的结果 (也可以由后端获取后交给页面)，认证失败时返回 `Mt4Error::AuthFailed`
+    pub async fn connect(token: &TokenResponse, password: &str) -> Result<Self> {
+        let mut crypto = Mt4Crypto::new()?;
+        crypto.set_session_key(token.key.expose_secret())?;
+        let socket = WebSocket::new(&token.ws_url()).map_err(|e| js_error("创建 WebSocket 失败", e))?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+        let shared = Rc::new(Shared {
+            socket,
+            crypto,
+            password: RefCell::new(Some(SecretString::from(password))),
+            next_packet_id: Cell::new(1000),
+        });
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let on_open = {
+            let tx = tx.clone();
+            Closure::<dyn FnMut()>::new(move || {
+                let _ = tx.send(Incoming::Opened);
+            })
+        };
+        let on_message = {
+            let tx = tx.clone();
+            let shared = shared.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                if let Some(frame) = shared.on_message(event) {
+                    let _ = tx.send(Incoming::Frame(frame));
+                }
+            })
+        };
+        let on_close = {
+            let tx = tx.clone();
+            Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+                let _ = tx.send(Incoming::Closed(format!("code {} {}", event.code(), event.reason())));
+            })
+        };
+        let on_error = Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+            let _ = tx.send(Incoming::Closed("WebSocket error".to_string()));
+        });
+        shared.socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        shared.socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        shared.socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        shared.socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        let mut session = Self {
+            shared,
+            rx,
+            backlog: VecDeque::new(),
+            closed: false,
+            _handlers: Handlers {
+                _on_open: on_open,
+                _on_message: on_message,
+                _on_close: on_close,
+                _on_error: on_error,
+            },
+        };
+
+        match session.rx.recv().await {
+            Some(Incoming::Opened) => {}
+            Some(Incoming::Closed(reason)) => {
+                return Err(Mt4Error::Connection(format!("WebSocket 连接失败: {}", reason)));
+            }
+            _ => return Err(Mt4Error::Connection("WebSocket 连接失败".to_string())),
+        }
+
+        // 发送 token，收到确认后 on_message 发送密码
+        session.shared.send(Command::AuthToken as u16, &encode_token(token.token.expose_secret()), true)?;
+        loop {
+            let Some(frame) = session.recv().await else {
+                return Err(Mt4Error::Connection("认证完成前连接已关闭".to_string()));
+            };
+            match frame.command {
+                c if c == Command::AuthPassword as u16 => {
+                    if frame.error_code != 0 {
+                        return Err(Mt4Error::AuthFailed(frame.error_code));
+                    }
+                    tracing::info!("Authentication successful!");
+                    return Ok(session);
+                }
+                c if c == Command::AuthToken as u16 => {}
+                _ => session.backlog.push_back(frame),
+            }
+        }
+    }
+
+    /// 接收下一帧，连接关闭后返回 None
+    pub async fn next_frame(&mut self) -> Option<WasmFrame> {
+        match self.backlog.pop_front() {
+            Some(frame) => Some(frame),
+            None => self.recv().await,
+        }
+    }
+
+    async fn recv(&mut self) -> Option<WasmFrame> {
+        while !self.closed {
+            match self.rx.recv().await? {
+                Incoming::Frame(frame) => return Some(frame),
+                Incoming::Closed(reason) => {
+                    tracing::info!("WebSocket closed: {}", reason);
+                    self.closed = true;
+                }
+                Incoming::Opened => {}
+            }
+        }
+        None
+    }
+
+    /// 连接是否打开
+    pub fn is_open(&self) -> bool {
+        !self.closed && self.shared.socket.ready_state() == WebSocket::OPEN
+    }
+
+    /// 发送命令
+    pub fn send_command(&self, command: impl Into<u16>, data: &[u8]) -> Result<()> {
+        if !self.is_open() {
+            return Err(Mt4Error::NotConnected);
+        }
+        self.shared.send(command.into(), data, false)
+    }
+
+    /// 发送交易请求 (Command 12)
+    ///
+    /// 不经过原生客户端的去重、风控和校验流程，`request_id` 由调用方设置，
+    /// 响应通过 `WasmFrame::trade_response()` 按 request_id 匹配
+    pub fn send_trade(&self, request: &TradeRequest) -> Result<()> {
+        self.send_command(Command::TradeRequest, &request.to_bytes())
+    }
+
+    /// 关闭连接
+    pub fn close(self) {
+        drop(self);
+    }
+}
+
+impl Drop for WasmSession {
+    fn drop(&mut self) {
+        // 回调随会话释放，先从 WebSocket 上移除
+        let socket = &self.shared.socket;
+        socket.set_onopen(None);
+        socket.set_onmessage(None);
+        socket.set_onclose(None);
+        socket.set_onerror(None);
+        let _ = socket.close();
+    }
+}
+
+/// 转换浏览器异常
+fn js_error(context: &str, error: JsValue) -> Mt4Error {
+    Mt4Error::Connection(format!("{}: {:?}", context, error))
+}